@@ -1 +1,228 @@
+//! Generates Rust source from paramdex data, for inclusion by build scripts.
 
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use paramdex::{enums::ProjectEnum, Paramdex};
+
+mod param_structs;
+pub use param_structs::{emit_param_structs, field_ident, struct_ident};
+
+/// Name of the escape variant holding values which are not listed in the enum.
+const UNKNOWN_VARIANT: &str = "Unknown";
+
+/// Identifiers which cannot be used as variant or type names even once PascalCased.
+const RESERVED: [&str; 2] = ["Self", UNKNOWN_VARIANT];
+
+/// Converts `name` into a PascalCase Rust identifier.
+///
+/// Every run of characters which are not ASCII alphanumeric separates two words. Words in all
+/// caps are lowercased past their first letter, so `SPELL_TYPE` and `Spell Type` both become
+/// `SpellType`. Names which end up empty are replaced by `fallback`, and names starting with a
+/// digit are prefixed with `V`.
+pub fn sanitize_ident(name: &str, fallback: &str) -> String {
+    let mut ident = String::with_capacity(name.len());
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+        let all_caps = !word.chars().any(|c| c.is_ascii_lowercase());
+        let mut chars = word.chars();
+        ident.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        match all_caps {
+            true => ident.extend(chars.map(|c| c.to_ascii_lowercase())),
+            false => ident.extend(chars),
+        }
+    }
+    if ident.is_empty() {
+        ident.push_str(fallback);
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, 'V');
+    }
+    ident
+}
+
+/// Hands out identifiers which are unique within a scope.
+///
+/// On a collision, the first identifier keeps the sanitized name and the following ones are
+/// suffixed with `_2`, `_3`, etc. in order of insertion, skipping suffixes already in use.
+#[derive(Debug, Default)]
+struct IdentScope {
+    taken: HashSet<String>,
+}
+
+impl IdentScope {
+    fn with_reserved(reserved: &[&str]) -> Self {
+        Self {
+            taken: reserved.iter().map(|&s| s.to_owned()).collect(),
+        }
+    }
+
+    fn claim(&mut self, ident: String) -> String {
+        let ident = match self.taken.contains(&ident) {
+            false => ident,
+            true => {
+                (2..).map(|i| format!("{ident}_{i}")).find(|s| !self.taken.contains(s)).unwrap()
+            }
+        };
+        self.taken.insert(ident.clone());
+        ident
+    }
+}
+
+/// Writes `text` as `#[doc]` attributes, one per line, at the given indentation.
+fn write_doc(out: &mut impl Write, indent: &str, text: &str) -> io::Result<()> {
+    for line in text.lines().map(str::trim_end) {
+        writeln!(out, "{indent}#[doc = {:?}]", format!(" {line}").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Writes the enum for `e`, which must have at least one option with an integer ID.
+fn emit_project_enum(out: &mut impl Write, ident: &str, e: &ProjectEnum) -> io::Result<()> {
+    let mut scope = IdentScope::with_reserved(&RESERVED);
+    let mut seen_values = HashSet::new();
+    // Options whose ID is not an integer cannot be represented. Duplicate values are unreachable,
+    // so only the first option with a given value is kept
+    let variants: Vec<_> = e
+        .options
+        .iter()
+        .filter_map(|o| Some((o.value?, o)))
+        .filter(|(v, _)| seen_values.insert(*v))
+        .map(|(v, o)| {
+            let fallback = format!("Value{}", v.unsigned_abs());
+            let fallback = if v < 0 { format!("Neg{fallback}") } else { fallback };
+            (v, scope.claim(sanitize_ident(&o.name, &fallback)), o)
+        })
+        .collect();
+
+    let summary = if e.description.is_empty() { &e.display_name } else { &e.description };
+    write_doc(out, "", summary)?;
+    write_doc(out, "", &format!("\nProject enum `{}`.", e.name))?;
+    // Sanitized names may still trip style lints, e.g. `A B C` becomes `ABC`
+    writeln!(
+        out,
+        "#[allow(non_camel_case_types, clippy::upper_case_acronyms, clippy::enum_variant_names)]"
+    )?;
+    writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]")?;
+    writeln!(out, "pub enum {ident} {{")?;
+    for (value, variant, option) in &variants {
+        let doc = match option.description.is_empty() {
+            true => format!("`{}` (`{value}`).", option.name),
+            false => format!("`{}` (`{value}`): {}", option.name, option.description),
+        };
+        write_doc(out, "    ", &doc)?;
+        writeln!(out, "    {variant},")?;
+    }
+    writeln!(out, "    /// A value which is not listed in the paramdex.")?;
+    writeln!(out, "    {UNKNOWN_VARIANT}(i64),")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl {ident} {{")?;
+    writeln!(
+        out,
+        "    /// Converts a raw value, falling back to [`Self::{UNKNOWN_VARIANT}`]."
+    )?;
+    writeln!(out, "    pub const fn from_raw(value: i64) -> Self {{")?;
+    writeln!(out, "        match value {{")?;
+    for (value, variant, _) in &variants {
+        writeln!(out, "            {value} => Self::{variant},")?;
+    }
+    writeln!(out, "            v => Self::{UNKNOWN_VARIANT}(v),")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}\n")?;
+    writeln!(out, "    pub const fn to_raw(self) -> i64 {{")?;
+    writeln!(out, "        match self {{")?;
+    for (value, variant, _) in &variants {
+        writeln!(out, "            Self::{variant} => {value},")?;
+    }
+    writeln!(out, "            Self::{UNKNOWN_VARIANT}(v) => v,")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}\n")?;
+    writeln!(
+        out,
+        "    /// Name of the option in the paramdex, if the value is listed."
+    )?;
+    writeln!(
+        out,
+        "    pub const fn name(self) -> ::core::option::Option<&'static str> {{"
+    )?;
+    writeln!(out, "        match self {{")?;
+    for (_, variant, option) in &variants {
+        writeln!(
+            out,
+            "            Self::{variant} => ::core::option::Option::Some({:?}),",
+            option.name
+        )?;
+    }
+    writeln!(
+        out,
+        "            Self::{UNKNOWN_VARIANT}(_) => ::core::option::Option::None,"
+    )?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl ::core::convert::TryFrom<i64> for {ident} {{")?;
+    writeln!(out, "    type Error = i64;\n")?;
+    writeln!(
+        out,
+        "    /// Converts a listed value, or returns it back if it is not listed."
+    )?;
+    writeln!(
+        out,
+        "    fn try_from(value: i64) -> ::core::result::Result<Self, i64> {{"
+    )?;
+    writeln!(out, "        match Self::from_raw(value) {{")?;
+    writeln!(
+        out,
+        "            Self::{UNKNOWN_VARIANT}(v) => ::core::result::Result::Err(v),"
+    )?;
+    writeln!(
+        out,
+        "            known => ::core::result::Result::Ok(known),"
+    )?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl ::core::convert::From<{ident}> for i64 {{")?;
+    writeln!(out, "    fn from(value: {ident}) -> i64 {{")?;
+    writeln!(out, "        value.to_raw()")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")
+}
+
+/// Writes a Rust enum for each project enum (from `Enums.json`) loaded in `paramdex`.
+///
+/// Enums are emitted in name order, so the output only depends on the paramdex contents. Each
+/// enum has one variant per option with an integer ID, named by [`sanitize_ident`] and suffixed
+/// with `_2`, `_3`, etc. if the name collides with a previous option. Enums without any such
+/// option are skipped. Values which are not
+/// listed are kept in an `Unknown(i64)` variant, so conversions from raw values are lossless:
+/// - `from_raw` and `to_raw` convert between the enum and the raw value;
+/// - [`TryFrom<i64>`] only succeeds for listed values, returning the value back otherwise;
+/// - `name` returns the option name from the paramdex.
+///
+/// Since the `Unknown` variant carries a payload, the enums are not `#[repr]` types and cannot be
+/// transmuted from field values. The output is meant to be `include!`d as the body of a module,
+/// and only refers to `core` items by absolute path so that enum names cannot shadow them.
+///
+/// # Errors
+/// If writing to `out` fails.
+pub fn emit_project_enums(paramdex: &Paramdex, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "// @generated by codegen::emit_project_enums. Do not edit.\n"
+    )?;
+    let mut scope = IdentScope::with_reserved(&RESERVED);
+    let enums = paramdex.project_enums().filter(|e| e.options.iter().any(|o| o.value.is_some()));
+    for (i, e) in enums.enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        let ident = scope.claim(sanitize_ident(&e.name, "Enum"));
+        emit_project_enum(out, &ident, e)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,357 @@
+//! `#[repr(C)]` structs with the layout of paramdefs.
+
+use std::io::{self, Write};
+
+use paramdex::{
+    paramdef::{DefBaseRustType, DefBaseType, DefField, DefTypeModifier, Paramdef},
+    Paramdex,
+};
+
+use crate::{sanitize_ident, write_doc, IdentScope};
+
+/// Rust keywords, which can't be used as field or method names.
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "union", "unsafe", "unsized", "use", "virtual", "where", "while",
+];
+
+/// Converts the camelCase name of a def field into a snake_case Rust identifier.
+///
+/// Keywords are suffixed with `_`, and names starting with a digit are prefixed with `f`.
+pub fn field_ident(name: &str) -> String {
+    let mut ident = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !ident.is_empty() && !ident.ends_with('_') {
+                ident.push('_');
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower {
+            ident.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        ident.push(c.to_ascii_lowercase());
+    }
+    let mut ident = ident.trim_end_matches('_').to_owned();
+    if ident.is_empty() {
+        ident.push_str("field");
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, 'f');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// Unsigned type of the same size as `ty`, used to store bitfields.
+fn storage_type(ty: DefBaseRustType) -> &'static str {
+    match ty.size_bytes() {
+        1 => "u8",
+        2 => "u16",
+        _ => "u32",
+    }
+}
+
+/// A bitfield of a [`Member::Bits`] unit.
+struct Bitfield<'a> {
+    field: &'a DefField,
+    /// Offset of the bitfield in its storage unit.
+    shift: usize,
+    width: usize,
+}
+
+/// A field of the generated struct.
+enum Member<'a> {
+    /// Padding bytes, from dummy8 fields or between fields.
+    Pad { len: usize },
+    /// A field which is not a bitfield.
+    Plain(&'a DefField),
+    /// An integer holding consecutive bitfields of the same type.
+    Bits {
+        ty: DefBaseRustType,
+        fields: Vec<Bitfield<'a>>,
+    },
+}
+
+impl Member<'_> {
+    fn size_bytes(&self) -> usize {
+        match self {
+            Self::Pad { len } => *len,
+            Self::Plain(field) => field.size_bytes(),
+            Self::Bits { ty, .. } => ty.size_bytes(),
+        }
+    }
+
+    fn alignment(&self) -> usize {
+        match self {
+            Self::Pad { .. } => 1,
+            Self::Plain(field) => field.alignment(),
+            Self::Bits { ty, .. } => ty.alignment(),
+        }
+    }
+}
+
+/// Splits the official fields of `def`, laid out for the current version, into struct members
+/// sorted by offset, with explicit padding between them and up to the size of the def.
+fn members(def: &Paramdef) -> Vec<(usize, Member<'_>)> {
+    let mut fields: Vec<_> = (def.fields.iter())
+        .filter(|f| f.unofficial.is_none() && f.size_bits() != 0)
+        .filter_map(|f| Some((f.bit_offset?, f)))
+        .collect();
+    fields.sort_by_key(|&(bit_offset, _)| bit_offset);
+
+    let mut members: Vec<(usize, Member)> = Vec::new();
+    let mut end = 0;
+    for (bit_offset, field) in fields {
+        let field_def = &field.field_def;
+        let DefTypeModifier::Bitfield(width) = field_def.modifier
+        else {
+            let offset = bit_offset / 8;
+            if offset > end {
+                members.push((end, Member::Pad { len: offset - end }));
+            }
+            let member = match field_def.base_type {
+                DefBaseType::Dummy8 => Member::Pad {
+                    len: field.size_bytes(),
+                },
+                _ => Member::Plain(field),
+            };
+            end = offset + member.size_bytes();
+            members.push((offset, member));
+            continue;
+        };
+
+        let ty = field_def.base_type.rust_type();
+        let unit_bits = 8 * ty.size_bytes();
+        let unit_offset = bit_offset / unit_bits * unit_bits / 8;
+        let bitfield = Bitfield {
+            field,
+            shift: bit_offset - unit_offset * 8,
+            width,
+        };
+        match members.last_mut() {
+            Some((
+                offset,
+                Member::Bits {
+                    ty: last_ty,
+                    fields,
+                },
+            )) if *offset == unit_offset && *last_ty == ty => fields.push(bitfield),
+            _ => {
+                if unit_offset > end {
+                    members.push((
+                        end,
+                        Member::Pad {
+                            len: unit_offset - end,
+                        },
+                    ));
+                }
+                end = unit_offset + ty.size_bytes();
+                members.push((
+                    unit_offset,
+                    Member::Bits {
+                        ty,
+                        fields: vec![bitfield],
+                    },
+                ));
+            }
+        }
+    }
+    let size = def.size_bytes.unwrap_or(end);
+    if size > end {
+        members.push((end, Member::Pad { len: size - end }));
+    }
+    members
+}
+
+/// Name of the struct generated for a param type, e.g. `SpEffectParam` for `SP_EFFECT_PARAM_ST`.
+pub fn struct_ident(param_type: &str) -> String {
+    sanitize_ident(
+        param_type.strip_suffix("_ST").unwrap_or(param_type),
+        "Param",
+    )
+}
+
+fn field_doc(field: &DefField) -> String {
+    match &field.display_name {
+        Some(name) if !name.is_empty() => format!("{name}\n\n`{}`", field.field_def),
+        _ => format!("`{}`", field.field_def),
+    }
+}
+
+/// Writes the struct of `def`, whose field offsets were computed.
+fn emit_param_struct(
+    out: &mut impl Write,
+    ident: &str,
+    def: &Paramdef,
+    version: u64,
+) -> io::Result<()> {
+    let members = members(def);
+    let size = def.size_bytes.unwrap_or(0);
+    let alignment = members.iter().map(|(_, m)| m.alignment()).max().unwrap_or(1);
+
+    let mut field_scope = IdentScope::default();
+    let mut method_scope = IdentScope::default();
+    // Bitfield accessors, with the name of the storage field
+    let mut accessors = Vec::new();
+
+    write_doc(
+        out,
+        "",
+        &format!("Row of `{}` at layout version {version}.", def.param_type),
+    )?;
+    writeln!(out, "#[derive(Debug, Clone, Copy)]")?;
+    // The computed size of some defs is not a multiple of their alignment
+    if size.is_multiple_of(alignment) {
+        writeln!(out, "#[repr(C)]")?;
+    }
+    else {
+        writeln!(out, "#[repr(C, packed)]")?;
+    }
+    writeln!(out, "pub struct {ident} {{")?;
+    for (offset, member) in &members {
+        match member {
+            Member::Pad { len } => {
+                let name = field_scope.claim(format!("_pad{offset}"));
+                writeln!(out, "    {name}: [u8; {len}],")?;
+            }
+            Member::Plain(field) => {
+                let field_def = &field.field_def;
+                let ty = field_def.base_type.rust_type();
+                write_doc(out, "    ", &field_doc(field))?;
+                let name = field_scope.claim(field_ident(&field_def.name));
+                match field_def.modifier {
+                    DefTypeModifier::Array(len) => writeln!(out, "    pub {name}: [{ty}; {len}],")?,
+                    _ => writeln!(out, "    pub {name}: {ty},")?,
+                }
+            }
+            Member::Bits { ty, fields } => {
+                let name = field_scope.claim(format!("_bits{offset}"));
+                writeln!(out, "    {name}: {},", storage_type(*ty))?;
+                let named =
+                    fields.iter().filter(|b| b.field.field_def.base_type != DefBaseType::Dummy8);
+                for bitfield in named {
+                    let getter = method_scope.claim(field_ident(&bitfield.field.field_def.name));
+                    let setter = method_scope.claim(format!("set_{getter}"));
+                    accessors.push((name.clone(), *ty, getter, setter, bitfield));
+                }
+            }
+        }
+    }
+    writeln!(out, "}}\n")?;
+    writeln!(
+        out,
+        "const _: () = assert!(::core::mem::size_of::<{ident}>() == {size});"
+    )?;
+    if accessors.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\nimpl {ident} {{")?;
+    for (i, (storage, ty, getter, setter, bitfield)) in accessors.into_iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        let unit_bits = 8 * ty.size_bytes();
+        let (shift, width) = (bitfield.shift, bitfield.width);
+        let mask = (u64::MAX >> (64 - width)) << shift;
+        let (getter_expr, value) = match ty {
+            DefBaseRustType::I8 | DefBaseRustType::I16 | DefBaseRustType::I32 => {
+                let left = unit_bits - shift - width;
+                let unit = match left {
+                    0 => format!("self.{storage}"),
+                    _ => format!("(self.{storage} << {left})"),
+                };
+                let getter_expr = match unit_bits - width {
+                    0 => format!("{unit} as {ty}"),
+                    right => format!("({unit} as {ty}) >> {right}"),
+                };
+                (getter_expr, format!("value as {}", storage_type(ty)))
+            }
+            _ => {
+                let getter_expr = match shift {
+                    0 => format!("self.{storage} & {mask:#x}"),
+                    _ => format!("(self.{storage} & {mask:#x}) >> {shift}"),
+                };
+                (getter_expr, "value".to_owned())
+            }
+        };
+        write_doc(out, "    ", &field_doc(bitfield.field))?;
+        writeln!(out, "    pub fn {getter}(&self) -> {ty} {{")?;
+        writeln!(out, "        {getter_expr}")?;
+        writeln!(out, "    }}\n")?;
+        writeln!(
+            out,
+            "    /// Sets [`Self::{getter}`], truncating `value` to {width} bits."
+        )?;
+        writeln!(out, "    pub fn {setter}(&mut self, value: {ty}) {{")?;
+        if width == unit_bits {
+            writeln!(out, "        self.{storage} = {value};")?;
+            writeln!(out, "    }}")?;
+            continue;
+        }
+        let value = match value.contains(' ') {
+            true => format!("({value})"),
+            false => value,
+        };
+        writeln!(
+            out,
+            "        self.{storage} = (self.{storage} & !{mask:#x}) | ({} & {mask:#x});",
+            match shift {
+                0 => value,
+                _ => format!("({value} << {shift})"),
+            }
+        )?;
+        writeln!(out, "    }}")?;
+    }
+    writeln!(out, "}}")
+}
+
+/// Writes a `#[repr(C)]` struct with the layout of each little endian def loaded in
+/// `paramdex`, at layout version `version`.
+///
+/// Structs are named by [`struct_ident`] and emitted in param type order, with their fields
+/// sorted by offset, so the output only depends on the paramdex contents and can be committed:
+/// - Fields are named by [`field_ident`] and typed after their [`DefBaseRustType`], or as arrays
+///   of it. Fields which are not in the def at `version` are left out.
+/// - Consecutive bitfields sharing an integer are stored in a private `_bitsN` field, `N` being
+///   its byte offset, with a getter and a `set_` setter per bitfield. Signed bitfields are sign
+///   extended.
+/// - dummy8 fields and gaps between fields are private `_padN` byte arrays.
+///
+/// The size of each struct is asserted at compile time to be the size of the def. If it is not
+/// a multiple of the alignment of the fields, the struct is packed to keep that size.
+///
+/// # Errors
+/// If writing to `out` fails.
+pub fn emit_param_structs(
+    paramdex: &Paramdex,
+    version: u64,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "// @generated by codegen::emit_param_structs. Do not edit.\n"
+    )?;
+    let mut defs: Vec<_> = paramdex.defs().filter(|d| !d.big_endian).cloned().collect();
+    defs.sort_by(|a, b| a.param_type.cmp(&b.param_type));
+
+    let mut scope = IdentScope::default();
+    for (i, def) in defs.iter_mut().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        def.compute_field_offsets(version);
+        let ident = scope.claim(struct_ident(&def.param_type));
+        emit_param_struct(out, &ident, def, version)?;
+    }
+    Ok(())
+}
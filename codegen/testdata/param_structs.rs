@@ -0,0 +1,124 @@
+// @generated by codegen::emit_param_structs. Do not edit.
+
+#[doc = " Row of `ARRAY_TEST_PARAM_ST` at layout version 10400."]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ArrayTestParam {
+    #[doc = " Weight"]
+    #[doc = ""]
+    #[doc = " `f32 weight`"]
+    pub weight: f32,
+    #[doc = " `s32 values[4]`"]
+    pub values: [i32; 4],
+    #[doc = " `u8 bytes[3]`"]
+    pub bytes: [u8; 3],
+    _pad23: [u8; 1],
+    #[doc = " `fixstr name[16]`"]
+    pub name: [i8; 16],
+    #[doc = " `fixstrW wideName[8]`"]
+    pub wide_name: [i16; 8],
+}
+
+const _: () = assert!(::core::mem::size_of::<ArrayTestParam>() == 56);
+
+#[doc = " Row of `BITFIELD_TEST_PARAM_ST` at layout version 10400."]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct BitfieldTestParam {
+    #[doc = " ID"]
+    #[doc = ""]
+    #[doc = " `s32 id`"]
+    pub id: i32,
+    _bits4: u8,
+    _pad5: [u8; 1],
+    #[doc = " `u16 shortVal`"]
+    pub short_val: u16,
+    #[doc = " `u8 byteVal`"]
+    pub byte_val: u8,
+    _pad9: [u8; 3],
+    _bits12: u32,
+    _bits16: u8,
+    _pad17: [u8; 3],
+}
+
+const _: () = assert!(::core::mem::size_of::<BitfieldTestParam>() == 20);
+
+impl BitfieldTestParam {
+    #[doc = " `u8 flagA:1`"]
+    pub fn flag_a(&self) -> u8 {
+        self._bits4 & 0x1
+    }
+
+    /// Sets [`Self::flag_a`], truncating `value` to 1 bits.
+    pub fn set_flag_a(&mut self, value: u8) {
+        self._bits4 = (self._bits4 & !0x1) | (value & 0x1);
+    }
+
+    #[doc = " `u8 flagB:3`"]
+    pub fn flag_b(&self) -> u8 {
+        (self._bits4 & 0xe) >> 1
+    }
+
+    /// Sets [`Self::flag_b`], truncating `value` to 3 bits.
+    pub fn set_flag_b(&mut self, value: u8) {
+        self._bits4 = (self._bits4 & !0xe) | ((value << 1) & 0xe);
+    }
+
+    #[doc = " `u8 flagC:4`"]
+    pub fn flag_c(&self) -> u8 {
+        (self._bits4 & 0xf0) >> 4
+    }
+
+    /// Sets [`Self::flag_c`], truncating `value` to 4 bits.
+    pub fn set_flag_c(&mut self, value: u8) {
+        self._bits4 = (self._bits4 & !0xf0) | ((value << 4) & 0xf0);
+    }
+
+    #[doc = " `u32 wideBits:12`"]
+    pub fn wide_bits(&self) -> u32 {
+        self._bits12 & 0xfff
+    }
+
+    /// Sets [`Self::wide_bits`], truncating `value` to 12 bits.
+    pub fn set_wide_bits(&mut self, value: u32) {
+        self._bits12 = (self._bits12 & !0xfff) | (value & 0xfff);
+    }
+
+    #[doc = " `u32 narrowBits:20`"]
+    pub fn narrow_bits(&self) -> u32 {
+        (self._bits12 & 0xfffff000) >> 12
+    }
+
+    /// Sets [`Self::narrow_bits`], truncating `value` to 20 bits.
+    pub fn set_narrow_bits(&mut self, value: u32) {
+        self._bits12 = (self._bits12 & !0xfffff000) | ((value << 12) & 0xfffff000);
+    }
+
+    #[doc = " `u8 lastBits:4`"]
+    pub fn last_bits(&self) -> u8 {
+        (self._bits16 & 0xf0) >> 4
+    }
+
+    /// Sets [`Self::last_bits`], truncating `value` to 4 bits.
+    pub fn set_last_bits(&mut self, value: u8) {
+        self._bits16 = (self._bits16 & !0xf0) | ((value << 4) & 0xf0);
+    }
+}
+
+#[doc = " Row of `ENUM_TEST_PARAM_ST` at layout version 10400."]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct EnumTestParam {
+    #[doc = " Icon ID"]
+    #[doc = ""]
+    #[doc = " `s32 iconId`"]
+    pub icon_id: i32,
+    #[doc = " `u8 spellType`"]
+    pub spell_type: u8,
+    #[doc = " `u8 isEnabled = 1`"]
+    pub is_enabled: u8,
+    #[doc = " `s16 mixed`"]
+    pub mixed: i16,
+}
+
+const _: () = assert!(::core::mem::size_of::<EnumTestParam>() == 8);
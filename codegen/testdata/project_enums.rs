@@ -0,0 +1,227 @@
+// @generated by codegen::emit_project_enums. Do not edit.
+
+#[doc = " Nasty Names"]
+#[doc = ""]
+#[doc = " Project enum `NASTY-names`."]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms, clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NastyNames {
+    #[doc = " `` (`-1`): Empty name."]
+    NegValue1,
+    #[doc = " `Hand (L)` (`0`)."]
+    HandL,
+    #[doc = " `Hand [L]` (`1`): Collides with \"Hand (L)\"."]
+    HandL_2,
+    #[doc = " `Hand_L_2` (`2`): Looks like a suffixed name."]
+    HandL2,
+    #[doc = " `2-Handed` (`3`): Leading digit."]
+    V2Handed,
+    #[doc = " `Self` (`4`): Keyword."]
+    Self_2,
+    #[doc = " `Unknown` (`5`): Escape variant name."]
+    Unknown_2,
+    #[doc = " `Ünïcödé ☆` (`6`)."]
+    NCD,
+    #[doc = " `Sorcery` (`7`): Same value as the next option, kept."]
+    Sorcery,
+    #[doc = " `HP_RESTORE` (`9`)."]
+    HpRestore,
+    #[doc = " `Min` (`-9223372036854775808`)."]
+    Min,
+    /// A value which is not listed in the paramdex.
+    Unknown(i64),
+}
+
+impl NastyNames {
+    /// Converts a raw value, falling back to [`Self::Unknown`].
+    pub const fn from_raw(value: i64) -> Self {
+        match value {
+            -1 => Self::NegValue1,
+            0 => Self::HandL,
+            1 => Self::HandL_2,
+            2 => Self::HandL2,
+            3 => Self::V2Handed,
+            4 => Self::Self_2,
+            5 => Self::Unknown_2,
+            6 => Self::NCD,
+            7 => Self::Sorcery,
+            9 => Self::HpRestore,
+            -9223372036854775808 => Self::Min,
+            v => Self::Unknown(v),
+        }
+    }
+
+    pub const fn to_raw(self) -> i64 {
+        match self {
+            Self::NegValue1 => -1,
+            Self::HandL => 0,
+            Self::HandL_2 => 1,
+            Self::HandL2 => 2,
+            Self::V2Handed => 3,
+            Self::Self_2 => 4,
+            Self::Unknown_2 => 5,
+            Self::NCD => 6,
+            Self::Sorcery => 7,
+            Self::HpRestore => 9,
+            Self::Min => -9223372036854775808,
+            Self::Unknown(v) => v,
+        }
+    }
+
+    /// Name of the option in the paramdex, if the value is listed.
+    pub const fn name(self) -> ::core::option::Option<&'static str> {
+        match self {
+            Self::NegValue1 => ::core::option::Option::Some(""),
+            Self::HandL => ::core::option::Option::Some("Hand (L)"),
+            Self::HandL_2 => ::core::option::Option::Some("Hand [L]"),
+            Self::HandL2 => ::core::option::Option::Some("Hand_L_2"),
+            Self::V2Handed => ::core::option::Option::Some("2-Handed"),
+            Self::Self_2 => ::core::option::Option::Some("Self"),
+            Self::Unknown_2 => ::core::option::Option::Some("Unknown"),
+            Self::NCD => ::core::option::Option::Some("Ünïcödé ☆"),
+            Self::Sorcery => ::core::option::Option::Some("Sorcery"),
+            Self::HpRestore => ::core::option::Option::Some("HP_RESTORE"),
+            Self::Min => ::core::option::Option::Some("Min"),
+            Self::Unknown(_) => ::core::option::Option::None,
+        }
+    }
+}
+
+impl ::core::convert::TryFrom<i64> for NastyNames {
+    type Error = i64;
+
+    /// Converts a listed value, or returns it back if it is not listed.
+    fn try_from(value: i64) -> ::core::result::Result<Self, i64> {
+        match Self::from_raw(value) {
+            Self::Unknown(v) => ::core::result::Result::Err(v),
+            known => ::core::result::Result::Ok(known),
+        }
+    }
+}
+
+impl ::core::convert::From<NastyNames> for i64 {
+    fn from(value: NastyNames) -> i64 {
+        value.to_raw()
+    }
+}
+
+#[doc = " Shadows a prelude type."]
+#[doc = ""]
+#[doc = " Project enum `Option`."]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms, clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Option {
+    #[doc = " `Some` (`1`)."]
+    Some,
+    /// A value which is not listed in the paramdex.
+    Unknown(i64),
+}
+
+impl Option {
+    /// Converts a raw value, falling back to [`Self::Unknown`].
+    pub const fn from_raw(value: i64) -> Self {
+        match value {
+            1 => Self::Some,
+            v => Self::Unknown(v),
+        }
+    }
+
+    pub const fn to_raw(self) -> i64 {
+        match self {
+            Self::Some => 1,
+            Self::Unknown(v) => v,
+        }
+    }
+
+    /// Name of the option in the paramdex, if the value is listed.
+    pub const fn name(self) -> ::core::option::Option<&'static str> {
+        match self {
+            Self::Some => ::core::option::Option::Some("Some"),
+            Self::Unknown(_) => ::core::option::Option::None,
+        }
+    }
+}
+
+impl ::core::convert::TryFrom<i64> for Option {
+    type Error = i64;
+
+    /// Converts a listed value, or returns it back if it is not listed.
+    fn try_from(value: i64) -> ::core::result::Result<Self, i64> {
+        match Self::from_raw(value) {
+            Self::Unknown(v) => ::core::result::Result::Err(v),
+            known => ::core::result::Result::Ok(known),
+        }
+    }
+}
+
+impl ::core::convert::From<Option> for i64 {
+    fn from(value: Option) -> i64 {
+        value.to_raw()
+    }
+}
+
+#[doc = " Type of spell."]
+#[doc = ""]
+#[doc = " Project enum `SPELL_TYPE`."]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms, clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpellType {
+    #[doc = " `None` (`0`)."]
+    None,
+    #[doc = " `Sorcery` (`1`): Intelligence scaling."]
+    Sorcery,
+    #[doc = " `Incantation` (`2`): Faith scaling."]
+    #[doc = " Also called miracles in DS3."]
+    Incantation,
+    /// A value which is not listed in the paramdex.
+    Unknown(i64),
+}
+
+impl SpellType {
+    /// Converts a raw value, falling back to [`Self::Unknown`].
+    pub const fn from_raw(value: i64) -> Self {
+        match value {
+            0 => Self::None,
+            1 => Self::Sorcery,
+            2 => Self::Incantation,
+            v => Self::Unknown(v),
+        }
+    }
+
+    pub const fn to_raw(self) -> i64 {
+        match self {
+            Self::None => 0,
+            Self::Sorcery => 1,
+            Self::Incantation => 2,
+            Self::Unknown(v) => v,
+        }
+    }
+
+    /// Name of the option in the paramdex, if the value is listed.
+    pub const fn name(self) -> ::core::option::Option<&'static str> {
+        match self {
+            Self::None => ::core::option::Option::Some("None"),
+            Self::Sorcery => ::core::option::Option::Some("Sorcery"),
+            Self::Incantation => ::core::option::Option::Some("Incantation"),
+            Self::Unknown(_) => ::core::option::Option::None,
+        }
+    }
+}
+
+impl ::core::convert::TryFrom<i64> for SpellType {
+    type Error = i64;
+
+    /// Converts a listed value, or returns it back if it is not listed.
+    fn try_from(value: i64) -> ::core::result::Result<Self, i64> {
+        match Self::from_raw(value) {
+            Self::Unknown(v) => ::core::result::Result::Err(v),
+            known => ::core::result::Result::Ok(known),
+        }
+    }
+}
+
+impl ::core::convert::From<SpellType> for i64 {
+    fn from(value: SpellType) -> i64 {
+        value.to_raw()
+    }
+}
@@ -0,0 +1,121 @@
+use codegen::{emit_param_structs, field_ident, struct_ident};
+use paramdex::Paramdex;
+
+const GOLDEN_PATH: &str = "testdata/param_structs.rs";
+
+/// The golden output, compiled to check that the generated structs are valid and have the size
+/// of their defs.
+#[allow(dead_code)]
+mod generated {
+    include!("../testdata/param_structs.rs");
+}
+
+use generated::{ArrayTestParam, BitfieldTestParam, EnumTestParam};
+
+fn emit_fixture() -> String {
+    let mut out = Vec::new();
+    emit_param_structs(&Paramdex::fixture(), 10400, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Set `UPDATE_GOLDEN=1` to regenerate the golden file after an intended change.
+#[test]
+fn golden() {
+    let emitted = emit_fixture();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(GOLDEN_PATH, &emitted).unwrap();
+    }
+    let golden = std::fs::read_to_string(GOLDEN_PATH).unwrap().replace("\r\n", "\n");
+    assert_eq!(
+        emitted, golden,
+        "generated structs differ from {GOLDEN_PATH}"
+    );
+}
+
+#[test]
+fn deterministic() {
+    assert_eq!(emit_fixture(), emit_fixture());
+}
+
+#[test]
+fn idents() {
+    assert_eq!(struct_ident("SP_EFFECT_PARAM_ST"), "SpEffectParam");
+    assert_eq!(struct_ident("CACL_PARAM"), "CaclParam");
+    assert_eq!(field_ident("iconId"), "icon_id");
+    assert_eq!(field_ident("isEnabled"), "is_enabled");
+    assert_eq!(field_ident("DS3_flag"), "ds3_flag");
+    assert_eq!(field_ident("type"), "type_");
+    assert_eq!(field_ident("0x10 [*]"), "f0x10");
+}
+
+#[test]
+fn layouts() {
+    assert_eq!(size_of::<BitfieldTestParam>(), 20);
+    assert_eq!(size_of::<ArrayTestParam>(), 56);
+    assert_eq!(size_of::<EnumTestParam>(), 8);
+
+    // Offsets of the fields of the row, as computed by `compute_field_offsets`
+    let row: ArrayTestParam = unsafe { std::mem::zeroed() };
+    let base = &row as *const _ as usize;
+    assert_eq!(&row.values as *const _ as usize - base, 4);
+    assert_eq!(&row.name as *const _ as usize - base, 24);
+    assert_eq!(&row.wide_name as *const _ as usize - base, 40);
+}
+
+/// Row of `BitfieldTestParam` at version 10400, as in the ppatch row field tests.
+#[rustfmt::skip]
+const BITFIELD_ROW: [u8; 20] = [
+    0xFB, 0xFF, 0xFF, 0xFF, // id = -5
+    0xCB, 0, // flagA = 1, flagB = 5, flagC = 0xC
+    0x34, 0x12, // shortVal
+    0x7F, 0, 0, 0, // byteVal
+    0xBC, 0x5A, 0x34, 0x12, // wideBits = 0xABC, narrowBits = 0x12345
+    0x90, // pad, lastBits = 9
+    0x01, 0x18, 0x03, // endPad
+];
+
+#[test]
+fn bitfield_accessors() {
+    let mut row: BitfieldTestParam = unsafe { std::mem::transmute(BITFIELD_ROW) };
+    assert_eq!(row.id, -5);
+    assert_eq!((row.flag_a(), row.flag_b(), row.flag_c()), (1, 5, 0xC));
+    assert_eq!(row.short_val, 0x1234);
+    assert_eq!(row.byte_val, 0x7F);
+    assert_eq!((row.wide_bits(), row.narrow_bits()), (0xABC, 0x12345));
+    assert_eq!(row.last_bits(), 9);
+
+    row.set_narrow_bits(0xFFF_FFFF);
+    row.set_flag_b(2);
+    row.set_last_bits(3);
+    assert_eq!((row.wide_bits(), row.narrow_bits()), (0xABC, 0xF_FFFF));
+    assert_eq!((row.flag_a(), row.flag_b(), row.flag_c()), (1, 2, 0xC));
+
+    let mut expected = BITFIELD_ROW;
+    expected[4] = 0xC5;
+    expected[13..16].copy_from_slice(&[0xFA, 0xFF, 0xFF]);
+    expected[16] = 0x30;
+    let bytes: [u8; 20] = unsafe { std::mem::transmute(row) };
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn signed_bitfields_and_packing() {
+    let mut paramdex = Paramdex::new("testdata");
+    paramdex.load_defs().unwrap();
+    let mut out = Vec::new();
+    emit_param_structs(&paramdex, u64::MAX, &mut out).unwrap();
+    let code = String::from_utf8(out).unwrap();
+
+    // Sign extended by shifting the bitfield to the top of the integer and back
+    assert!(code.contains("((self._bits0 << 5) as i8) >> 5"));
+    assert!(code.contains("(self._bits0 as i8) >> 3"));
+    assert!(code.contains("self._bits0 = (self._bits0 & !0xf8) | (((value as u8) << 3) & 0xf8);"));
+    // Bitfields as wide as their type are plain casts, and keywords are escaped
+    assert!(code.contains("pub fn type_(&self) -> i16 {\n        self._bits2 as i16\n"));
+    assert!(code.contains(
+        "pub fn set_type_(&mut self, value: i16) {\n        self._bits2 = value as u16;"
+    ));
+    // The def is 5 bytes long, which is not a multiple of the alignment of `type`
+    assert!(code.contains("#[repr(C, packed)]\npub struct SignedBitsParam {"));
+    assert!(code.contains("size_of::<SignedBitsParam>() == 5"));
+}
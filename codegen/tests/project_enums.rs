@@ -0,0 +1,84 @@
+use codegen::{emit_project_enums, sanitize_ident};
+use paramdex::Paramdex;
+
+const GOLDEN_PATH: &str = "testdata/project_enums.rs";
+
+/// The golden output, compiled to check that the generated code is valid and behaves.
+#[allow(dead_code)]
+mod generated {
+    include!("../testdata/project_enums.rs");
+}
+
+use generated::{NastyNames, SpellType};
+
+fn emit_fixture() -> String {
+    let mut paramdex = Paramdex::new("testdata");
+    paramdex.load_enums().unwrap();
+    let mut out = Vec::new();
+    emit_project_enums(&paramdex, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Set `UPDATE_GOLDEN=1` to regenerate the golden file after an intended change.
+#[test]
+fn golden() {
+    let emitted = emit_fixture();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(GOLDEN_PATH, &emitted).unwrap();
+    }
+    let golden = std::fs::read_to_string(GOLDEN_PATH).unwrap().replace("\r\n", "\n");
+    assert_eq!(emitted, golden, "generated enums differ from {GOLDEN_PATH}");
+}
+
+#[test]
+fn deterministic() {
+    assert_eq!(emit_fixture(), emit_fixture());
+}
+
+#[test]
+fn skips_enums_without_values() {
+    assert!(!emit_fixture().contains("Project enum `_`"));
+}
+
+#[test]
+fn sanitize() {
+    assert_eq!(sanitize_ident("SPELL_TYPE", "X"), "SpellType");
+    assert_eq!(sanitize_ident("Spell Type", "X"), "SpellType");
+    assert_eq!(sanitize_ident("camelCase name", "X"), "CamelCaseName");
+    assert_eq!(sanitize_ident("2-Handed", "X"), "V2Handed");
+    assert_eq!(sanitize_ident(" (*) ", "X"), "X");
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(SpellType::from_raw(1), SpellType::Sorcery);
+    assert_eq!(SpellType::try_from(2), Ok(SpellType::Incantation));
+    assert_eq!(SpellType::try_from(3), Err(3));
+    assert_eq!(SpellType::from_raw(3), SpellType::Unknown(3));
+    assert_eq!(i64::from(SpellType::Unknown(-5)), -5);
+    assert_eq!(SpellType::None.name(), Some("None"));
+    assert_eq!(SpellType::Unknown(0).name(), None);
+
+    for v in [-1, 0, 7, 9, 10, i64::MIN, i64::MAX] {
+        assert_eq!(NastyNames::from_raw(v).to_raw(), v);
+    }
+}
+
+#[test]
+fn nasty_names() {
+    assert_eq!(NastyNames::NegValue1.to_raw(), -1);
+    assert_eq!(NastyNames::HandL.to_raw(), 0);
+    assert_eq!(NastyNames::HandL_2.to_raw(), 1);
+    assert_eq!(NastyNames::HandL2.to_raw(), 2);
+    assert_eq!(NastyNames::V2Handed.to_raw(), 3);
+    assert_eq!(NastyNames::Self_2.to_raw(), 4);
+    assert_eq!(NastyNames::Unknown_2.to_raw(), 5);
+    assert_eq!(NastyNames::Sorcery.name(), Some("Sorcery"));
+    assert_eq!(NastyNames::from_raw(7), NastyNames::Sorcery);
+    assert_eq!(NastyNames::HpRestore.to_raw(), 9);
+    assert_eq!(NastyNames::Min.to_raw(), i64::MIN);
+    // Non-integer IDs are not listed
+    assert_eq!(NastyNames::try_from(0x10), Err(0x10));
+    // Generated types may shadow prelude types without breaking the generated code
+    assert_eq!(generated::Option::Some.to_raw(), 1);
+}
@@ -0,0 +1,93 @@
+//! Generation of the field blocks of a paramdef, used by the ppatch build script.
+
+use std::ops::Range;
+
+use num_traits::PrimInt;
+use paramdex::paramdef::Paramdef;
+
+use crate::FieldBlock;
+
+/// Width of a block of type `N`, in bits.
+const fn block_size_bits<N>() -> usize {
+    8 * std::mem::size_of::<N>()
+}
+
+/// Mask of the bits `start..end` of a block of type `N`, where `start < end <= bits of N`.
+fn bit_range_mask<N: PrimInt>(start: usize, end: usize) -> N {
+    N::max_value() >> (block_size_bits::<N>() - (end - start)) << start
+}
+
+/// Splits the bits in the range `bits` (relative to the start of the row) into blocks of type
+/// `N`, whose `field_start` is `field_start`.
+fn range_blocks<N: PrimInt>(field_start: u16, bits: Range<usize>) -> Vec<FieldBlock<N>> {
+    let block_bits = block_size_bits::<N>();
+    let mut blocks = Vec::new();
+    let mut start = bits.start;
+    while start < bits.end {
+        let offset = start / block_bits;
+        let block_start = offset * block_bits;
+        let end = bits.end.min(block_start + block_bits);
+        blocks.push(FieldBlock {
+            field_start,
+            offset: offset as u16,
+            mask: bit_range_mask(start - block_start, end - block_start),
+        });
+        start = end;
+    }
+    blocks
+}
+
+/// Removes the bits in the range `bits` (relative to the start of the row) from the masks of
+/// `blocks`, splitting blocks in two so that each mask stays contiguous.
+fn carve_bits<N: PrimInt>(blocks: Vec<FieldBlock<N>>, bits: &Range<usize>) -> Vec<FieldBlock<N>> {
+    let block_bits = block_size_bits::<N>();
+    let mut carved = Vec::with_capacity(blocks.len() + 1);
+    for fb in blocks {
+        let block_start = fb.offset as usize * block_bits;
+        let start = bits.start.clamp(block_start, block_start + block_bits) - block_start;
+        let end = bits.end.clamp(block_start, block_start + block_bits) - block_start;
+        if start >= end {
+            carved.push(fb);
+            continue;
+        }
+        let below = fb.mask & !(N::max_value() << start);
+        let above = fb.mask & !below & !(N::max_value() >> (block_bits - end));
+        carved.extend(
+            [below, above]
+                .into_iter()
+                .filter(|mask| !mask.is_zero())
+                .map(|mask| FieldBlock { mask, ..fb }),
+        );
+    }
+    carved
+}
+
+/// Builds the field blocks of a def whose field offsets were computed (see
+/// [`Paramdef::compute_field_offsets`]), in blocks of type `N`.
+///
+/// Each field gets one block per block of the row it overlaps, with the bits of the field
+/// within that block set in the mask. Fields without an offset, i.e. not in the layout, and
+/// fields without bits have no blocks. Padding bits claimed by unofficial fields are only
+/// patched through those fields, so they are carved out of the blocks of their host.
+///
+/// Only available with the `paramdex` feature.
+pub fn build_blocks<N: PrimInt>(def: &Paramdef) -> Vec<FieldBlock<N>> {
+    let mut blocks: Vec<FieldBlock<N>> = Vec::new();
+
+    for f in def.fields.iter() {
+        let Some(bit_offset) = f.bit_offset
+        else {
+            continue;
+        };
+        let field_start = blocks.len() as u16;
+        let field_blocks = def
+            .unofficial_fields_in(&f.field_def.name)
+            .map(|uf| uf.bit_offset.unwrap()..uf.bit_offset.unwrap() + uf.size_bits())
+            .fold(
+                range_blocks(field_start, bit_offset..bit_offset + f.size_bits()),
+                |fbs, bits| carve_bits(fbs, &bits),
+            );
+        blocks.extend(field_blocks);
+    }
+    blocks
+}
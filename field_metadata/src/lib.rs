@@ -1,6 +1,12 @@
+use std::{collections::HashMap, fmt, hash::Hasher};
+
 use num_traits::PrimInt;
-use rkyv::AlignedVec;
-use std::collections::HashMap;
+use rkyv::{collections::hash_map::ArchivedHashMap, string::ArchivedString, vec::ArchivedVec};
+
+#[cfg(feature = "paramdex")]
+mod build;
+#[cfg(feature = "paramdex")]
+pub use build::build_blocks;
 
 /// Represents a portion (or superset) of a paramdef field, stored in an integer of type `N`.
 #[repr(C)]
@@ -29,15 +35,368 @@ impl<S: rkyv::ser::Serializer, N: PrimInt> rkyv::Serialize<S> for FieldBlock<N>
         Ok(*self)
     }
 }
+// Any bit pattern is a valid `FieldBlock`, since all of its fields are integers
+impl<C: ?Sized, N: PrimInt> rkyv::CheckBytes<C> for FieldBlock<N> {
+    type Error = std::convert::Infallible;
+
+    unsafe fn check_bytes<'a>(value: *const Self, _: &mut C) -> Result<&'a Self, Self::Error> {
+        Ok(&*value)
+    }
+}
+
+/// Invariant of a [`FieldBlock`] array that is not upheld by one of its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldBlockViolation {
+    /// The entry's `field_start` is after its own index.
+    FieldStartAfterEntry,
+    /// The entry's `field_start` does not refer to the first block of a field, or the blocks of
+    /// the field are not contiguous in the array.
+    NonContiguousField,
+    /// The entry's offset is smaller than the offset of the previous block of the same field.
+    DecreasingOffset,
+    /// The entry's mask is zero.
+    EmptyMask,
+}
+
+impl fmt::Display for FieldBlockViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FieldStartAfterEntry => "field start is after the entry",
+            Self::NonContiguousField => "blocks of the field are not contiguous",
+            Self::DecreasingOffset => "offset is smaller than the previous block of the field",
+            Self::EmptyMask => "mask is empty",
+        })
+    }
+}
+
+/// Error returned when a [`FieldBlock`] array violates the invariants the patchers rely on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidFieldBlocks {
+    /// Index of the offending entry in the field block array.
+    pub index: usize,
+    pub violation: FieldBlockViolation,
+}
+
+impl fmt::Display for InvalidFieldBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid field block {}: {}", self.index, self.violation)
+    }
+}
+
+impl std::error::Error for InvalidFieldBlocks {}
+
+/// Checks that `blocks` upholds the invariants the patchers rely on:
+/// - `field_start` is the index of the first block of the field, which is at most the
+///   index of the entry itself;
+/// - the blocks of a field are contiguous, with non-decreasing offsets;
+/// - masks are non-zero.
+pub fn validate_field_blocks<N: PrimInt>(
+    blocks: &[FieldBlock<N>],
+) -> Result<(), InvalidFieldBlocks> {
+    for (index, fb) in blocks.iter().enumerate() {
+        let fail = |violation| Err(InvalidFieldBlocks { index, violation });
+        let field_start = fb.field_start as usize;
+
+        if field_start > index {
+            return fail(FieldBlockViolation::FieldStartAfterEntry);
+        }
+        let prev_in_field = index
+            .checked_sub(1)
+            .map(|i| &blocks[i])
+            .filter(|prev| prev.field_start == fb.field_start);
+        match prev_in_field {
+            Some(prev) if fb.offset < prev.offset => {
+                return fail(FieldBlockViolation::DecreasingOffset)
+            }
+            Some(_) => (),
+            // First block of a field
+            None if field_start != index => return fail(FieldBlockViolation::NonContiguousField),
+            None => (),
+        }
+        if fb.mask.is_zero() {
+            return fail(FieldBlockViolation::EmptyMask);
+        }
+    }
+    Ok(())
+}
+
+/// Error returned when the field blocks of a param type in a repo are invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFieldBlockRepo {
+    pub param_type: String,
+    pub error: InvalidFieldBlocks,
+}
+
+impl fmt::Display for InvalidFieldBlockRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid field blocks for {}", self.param_type)
+    }
+}
+
+impl std::error::Error for InvalidFieldBlockRepo {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Checks the field blocks of every param type of an archived repo with [`validate_field_blocks`].
+pub fn validate_fb_repo<N: PrimInt>(
+    repo: &ArchivedHashMap<ArchivedString, ArchivedVec<FieldBlock<N>>>,
+) -> Result<(), InvalidFieldBlockRepo> {
+    for (param_type, blocks) in repo.iter() {
+        validate_field_blocks(blocks.as_slice()).map_err(|error| InvalidFieldBlockRepo {
+            param_type: param_type.to_string(),
+            error,
+        })?;
+    }
+    Ok(())
+}
 
 pub type Block = u32;
 pub type FieldBlockRepo = HashMap<String, Vec<FieldBlock<Block>>>;
 pub type ArchivedFieldBlockRepo = <FieldBlockRepo as rkyv::Archive>::Archived;
+/// Repo of 64-bit field blocks, which halve the number of blocks of 8-byte aligned params.
+pub type FieldBlockRepo64 = HashMap<String, Vec<FieldBlock<u64>>>;
+pub type ArchivedFieldBlockRepo64 = <FieldBlockRepo64 as rkyv::Archive>::Archived;
+
+/// Lookup of field blocks by the param type stored in a param file, which may differ from the
+/// paramdef's in case and be padded with NUL bytes or whitespace.
+pub trait FbRepoExt<N: PrimInt> {
+    /// Returns the field blocks of the param type `raw`, ignoring everything from its first NUL
+    /// byte, surrounding whitespace and ASCII case.
+    ///
+    /// An exact match is preferred if several param types only differ in case.
+    fn get_normalized(&self, raw: &[u8]) -> Option<&ArchivedVec<FieldBlock<N>>>;
+}
+
+impl<N: PrimInt> FbRepoExt<N> for ArchivedHashMap<ArchivedString, ArchivedVec<FieldBlock<N>>> {
+    fn get_normalized(&self, raw: &[u8]) -> Option<&ArchivedVec<FieldBlock<N>>> {
+        let len = raw.iter().position(|&c| c == 0).unwrap_or(raw.len());
+        let param_type = raw[..len].trim_ascii();
+        if let Some(blocks) = std::str::from_utf8(param_type).ok().and_then(|s| self.get(s)) {
+            return Some(blocks);
+        }
+        self.iter()
+            .find(|(key, _)| key.as_bytes().eq_ignore_ascii_case(param_type))
+            .map(|(_, blocks)| blocks)
+    }
+}
+
+/// Version of the serialized [`FieldBlockRepo`] format produced by [`serialize_fb_repo`].
+///
+/// Bumped whenever the archived layout changes (e.g. a change to [`FieldBlock`] or [`Block`]),
+/// together with a semver-breaking release of this crate, so that repos serialized by another
+/// release are rejected by [`load_fb_repo`] instead of being misread.
+pub const FB_REPO_FORMAT_VERSION: u32 = 3;
+
+const FB_REPO_MAGIC: [u8; 4] = *b"FBRP";
+/// Maximum length of the game tag of a repo, in bytes.
+pub const FB_REPO_GAME_TAG_LEN: usize = 8;
+/// Size of the header preceding the archive:
+/// - magic (4 bytes);
+/// - format version (`u32`);
+/// - block size in bytes (`u32`);
+/// - game tag, padded with zeros (8 bytes);
+/// - FNV-1a hash of the archive (`u64`).
+///
+/// Kept a multiple of 16 so that the archive has the same alignment as the serialized bytes.
+const FB_REPO_HEADER_SIZE: usize = 32;
+
+/// Error returned by [`load_fb_repo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadFbRepoError {
+    /// The bytes do not start with the header written by [`serialize_fb_repo`].
+    NotARepo,
+    /// The repo was serialized with another [`FB_REPO_FORMAT_VERSION`].
+    UnsupportedVersion { version: u32 },
+    /// The repo holds field blocks of another size, in bytes.
+    BlockSizeMismatch { expected: u32, actual: u32 },
+}
+
+impl fmt::Display for LoadFbRepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotARepo => f.write_str("not a serialized field block repo"),
+            Self::UnsupportedVersion { version } => write!(
+                f,
+                "unsupported field block repo format version {version} \
+                (expected {FB_REPO_FORMAT_VERSION})"
+            ),
+            Self::BlockSizeMismatch { expected, actual } => write!(
+                f,
+                "field block repo has {actual} byte blocks (expected {expected})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadFbRepoError {}
+
+/// Error returned by [`load_fb_repo_checked`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FbRepoError {
+    /// The header of the repo is invalid.
+    Header(LoadFbRepoError),
+    /// The repo was generated for another game.
+    GameMismatch { expected: String, found: String },
+    /// The archive doesn't match the hash in the header, e.g. because it is truncated or
+    /// corrupted.
+    ChecksumMismatch,
+    /// The archive is not a valid [`FieldBlockRepo`], even though it matches its hash.
+    InvalidArchive(String),
+}
+
+impl fmt::Display for FbRepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(e) => e.fmt(f),
+            Self::GameMismatch { expected, found } => write!(
+                f,
+                "field block repo was generated for game {found:?}, expected {expected:?}"
+            ),
+            Self::ChecksumMismatch => f.write_str("field block repo is truncated or corrupted"),
+            Self::InvalidArchive(e) => write!(f, "invalid field block repo archive: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FbRepoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Header(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadFbRepoError> for FbRepoError {
+    fn from(value: LoadFbRepoError) -> Self {
+        Self::Header(value)
+    }
+}
+
+/// Fields of a repo header after the format version.
+struct FbRepoHeader<'a> {
+    game: &'a str,
+    hash: u64,
+    archive: &'a [u8],
+}
+
+fn archive_hash(archive: &[u8]) -> u64 {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(archive);
+    hasher.finish()
+}
+
+/// Checks the header of a repo of `N` blocks, and returns the rest of it.
+fn check_fb_repo_header<N: PrimInt>(bytes: &[u8]) -> Result<FbRepoHeader<'_>, LoadFbRepoError> {
+    let (header, archive) =
+        bytes.split_at_checked(FB_REPO_HEADER_SIZE).ok_or(LoadFbRepoError::NotARepo)?;
+    if header[..4] != FB_REPO_MAGIC {
+        return Err(LoadFbRepoError::NotARepo);
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if version != FB_REPO_FORMAT_VERSION {
+        return Err(LoadFbRepoError::UnsupportedVersion { version });
+    }
+    let block_size = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    let expected = std::mem::size_of::<N>() as u32;
+    if block_size != expected {
+        return Err(LoadFbRepoError::BlockSizeMismatch {
+            expected,
+            actual: block_size,
+        });
+    }
+    let game = &header[16..16 + FB_REPO_GAME_TAG_LEN];
+    let game_len = game.iter().position(|&b| b == 0).unwrap_or(game.len());
+    Ok(FbRepoHeader {
+        game: std::str::from_utf8(&game[..game_len]).map_err(|_| LoadFbRepoError::NotARepo)?,
+        hash: u64::from_le_bytes(header[24..32].try_into().unwrap()),
+        archive,
+    })
+}
+
+/// Loads a repo serialized by [`serialize_fb_repo`].
+///
+/// Only the header is checked, see [`load_fb_repo_checked`] to also check the archive.
+///
+/// # Errors
+/// - If the bytes do not start with a repo header, returns [`LoadFbRepoError::NotARepo`].
+/// - If the format version of the repo is not [`FB_REPO_FORMAT_VERSION`], returns
+///   [`LoadFbRepoError::UnsupportedVersion`].
+/// - If the repo holds blocks of another type than [`Block`], returns
+///   [`LoadFbRepoError::BlockSizeMismatch`].
+///
+/// # Safety
+/// If the header is valid, the rest of the bytes must be a valid archived [`FieldBlockRepo`].
+pub unsafe fn load_fb_repo(bytes: &[u8]) -> Result<&ArchivedFieldBlockRepo, LoadFbRepoError> {
+    let header = check_fb_repo_header::<Block>(bytes)?;
+    Ok(rkyv::archived_root::<FieldBlockRepo>(header.archive))
+}
+
+/// Same as [`load_fb_repo`], for a repo of 64-bit blocks.
+///
+/// # Safety
+/// If the header is valid, the rest of the bytes must be a valid archived [`FieldBlockRepo64`].
+pub unsafe fn load_fb_repo64(bytes: &[u8]) -> Result<&ArchivedFieldBlockRepo64, LoadFbRepoError> {
+    let header = check_fb_repo_header::<u64>(bytes)?;
+    Ok(rkyv::archived_root::<FieldBlockRepo64>(header.archive))
+}
+
+/// Loads a repo serialized by [`serialize_fb_repo_for_game`] for `game`, checking the whole
+/// repo rather than trusting the bytes.
+///
+/// The bytes must be aligned like the archive, i.e. to 16 bytes.
+///
+/// # Errors
+/// - If the header is invalid, returns [`FbRepoError::Header`] (see [`load_fb_repo`]).
+/// - If the repo was generated for another game, returns [`FbRepoError::GameMismatch`].
+/// - If the archive does not match the hash in the header, returns
+///   [`FbRepoError::ChecksumMismatch`].
+/// - If the archive is not a valid [`FieldBlockRepo`] (or is misaligned), returns
+///   [`FbRepoError::InvalidArchive`].
+pub fn load_fb_repo_checked<'a>(
+    bytes: &'a [u8],
+    game: &str,
+) -> Result<&'a ArchivedFieldBlockRepo, FbRepoError> {
+    let header = check_fb_repo_header::<Block>(bytes)?;
+    if header.game != game {
+        return Err(FbRepoError::GameMismatch {
+            expected: game.to_owned(),
+            found: header.game.to_owned(),
+        });
+    }
+    if archive_hash(header.archive) != header.hash {
+        return Err(FbRepoError::ChecksumMismatch);
+    }
+    rkyv::check_archived_root::<FieldBlockRepo>(header.archive)
+        .map_err(|e| FbRepoError::InvalidArchive(e.to_string()))
+}
 
-pub unsafe fn load_fb_repo(bytes: &[u8]) -> &ArchivedFieldBlockRepo {
-    rkyv::archived_root::<FieldBlockRepo>(bytes)
+/// Serializes a repo, prefixed by a header holding [`FB_REPO_FORMAT_VERSION`] and the size of
+/// its blocks. The game tag of the repo is empty.
+pub fn serialize_fb_repo<N: PrimInt>(repo: &HashMap<String, Vec<FieldBlock<N>>>) -> Box<[u8]> {
+    serialize_fb_repo_for_game(repo, "")
 }
 
-pub fn serialize_fb_repo(repo: &FieldBlockRepo) -> Box<[u8]> {
-    rkyv::to_bytes::<_, 4096>(repo).unwrap().into_boxed_slice()
+/// Same as [`serialize_fb_repo`], tagging the repo with the game it was generated for.
+///
+/// # Panics
+/// If `game` is longer than [`FB_REPO_GAME_TAG_LEN`] bytes or contains a NUL byte.
+pub fn serialize_fb_repo_for_game<N: PrimInt>(
+    repo: &HashMap<String, Vec<FieldBlock<N>>>,
+    game: &str,
+) -> Box<[u8]> {
+    assert!(game.len() <= FB_REPO_GAME_TAG_LEN && !game.contains('\0'));
+    let archive = rkyv::to_bytes::<_, 4096>(repo).unwrap();
+    let mut bytes = Vec::with_capacity(FB_REPO_HEADER_SIZE + archive.len());
+    bytes.extend_from_slice(&FB_REPO_MAGIC);
+    bytes.extend_from_slice(&FB_REPO_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(std::mem::size_of::<N>() as u32).to_le_bytes());
+    bytes.resize(16, 0);
+    bytes.extend_from_slice(game.as_bytes());
+    bytes.resize(16 + FB_REPO_GAME_TAG_LEN, 0);
+    bytes.extend_from_slice(&archive_hash(&archive).to_le_bytes());
+    bytes.extend_from_slice(&archive);
+    bytes.into_boxed_slice()
 }
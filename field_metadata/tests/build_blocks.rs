@@ -0,0 +1,123 @@
+//! Field blocks built by [`build_blocks`] for defs with packed bitfields, arrays and fields
+//! spanning several blocks, against hand-computed masks.
+
+use field_metadata::{build_blocks, validate_field_blocks, FieldBlock};
+use num_traits::PrimInt;
+use paramdex::{paramdef::Paramdef, unofficial::UnofficialFields};
+
+fn def(fields: &str, overlay: &str) -> Paramdef {
+    let xml = format!(
+        "<PARAMDEF XmlVersion=\"3\"><ParamType>TEST_PARAM_ST</ParamType>\
+        <DataVersion>1</DataVersion><BigEndian>False</BigEndian><Unicode>True</Unicode>\
+        <FormatVersion>203</FormatVersion><Fields>{fields}</Fields></PARAMDEF>"
+    );
+    let mut def: Paramdef = quick_xml::de::from_str(&xml).unwrap();
+    let overlay = UnofficialFields::from_toml(overlay).unwrap();
+    def.merge_unofficial_fields(overlay.get("TEST_PARAM_ST")).unwrap();
+    def.compute_field_offsets(u64::MAX);
+    def
+}
+
+/// Built field blocks of `def`, as `(field_start, offset, mask)` tuples.
+fn built<N: PrimInt>(def: &Paramdef) -> Vec<(u16, u16, N)> {
+    let built: Vec<FieldBlock<N>> = build_blocks(def);
+    validate_field_blocks(&built).unwrap();
+    built.iter().map(|fb| (fb.field_start, fb.offset, fb.mask)).collect()
+}
+
+/// Bit ranges of the fields:
+/// - `id`: 0..32
+/// - `a`, `b`, `c`: 32..33, 33..36 and 36..40, packed in the same byte
+/// - `d`: 40..42, in the next byte as it doesn't fit in the previous one
+/// - `e`: 48..56
+/// - `f`: 56..59
+/// - `arr`: 64..112
+/// - `pad`: 112..136
+/// - `tail`: 136..144
+const FIELDS: &str = r#"
+    <Field Def="s32 id" />
+    <Field Def="u8 a:1" />
+    <Field Def="u8 b:3" />
+    <Field Def="u8 c:4" />
+    <Field Def="u8 d:2" />
+    <Field Def="u8 e" />
+    <Field Def="u8 f:3" />
+    <Field Def="u16 arr[3]" />
+    <Field Def="dummy8 pad[3]" />
+    <Field Def="u8 tail" />
+"#;
+
+#[test]
+fn bitfields_and_arrays() {
+    let def = def(FIELDS, "");
+    assert_eq!(
+        built::<u32>(&def),
+        [
+            (0, 0, 0xFFFF_FFFF),
+            (1, 1, 0x0000_0001),
+            (2, 1, 0x0000_000E),
+            (3, 1, 0x0000_00F0),
+            (4, 1, 0x0000_0300),
+            (5, 1, 0x00FF_0000),
+            (6, 1, 0x0700_0000),
+            (7, 2, 0xFFFF_FFFF),
+            (7, 3, 0x0000_FFFF),
+            (9, 3, 0xFFFF_0000),
+            (9, 4, 0x0000_00FF),
+            (11, 4, 0x0000_FF00),
+        ]
+    );
+    assert_eq!(
+        built::<u64>(&def),
+        [
+            (0, 0, 0x0000_0000_FFFF_FFFF),
+            (1, 0, 0x0000_0001_0000_0000),
+            (2, 0, 0x0000_000E_0000_0000),
+            (3, 0, 0x0000_00F0_0000_0000),
+            (4, 0, 0x0000_0300_0000_0000),
+            (5, 0, 0x00FF_0000_0000_0000),
+            (6, 0, 0x0700_0000_0000_0000),
+            (7, 1, 0x0000_FFFF_FFFF_FFFF),
+            (8, 1, 0xFFFF_0000_0000_0000),
+            (8, 2, 0x0000_0000_0000_00FF),
+            (10, 2, 0x0000_0000_0000_FF00),
+        ]
+    );
+}
+
+#[test]
+fn unofficial_field_spanning_blocks() {
+    // A u16 at bit 8 of `pad`, i.e. bits 120..136, which span blocks 3 and 4
+    let overlay = r#"
+        [[TEST_PARAM_ST]]
+        host = "pad"
+        bit_offset = 8
+        def = "u16 spanning"
+    "#;
+    let def = def(FIELDS, overlay);
+    assert_eq!(
+        built::<u32>(&def)[8..],
+        [
+            (7, 3, 0x0000_FFFF),
+            // What remains of `pad` once the unofficial field is carved out
+            (9, 3, 0x00FF_0000),
+            (10, 3, 0xFF00_0000),
+            (10, 4, 0x0000_00FF),
+            (12, 4, 0x0000_FF00),
+        ]
+    );
+}
+
+#[test]
+fn fields_without_offsets_are_skipped() {
+    let def = def(
+        r#"
+        <Field Def="u8 removed" RemovedVersion="100" />
+        <Field Def="u8 a:4" />
+        <Field Def="u8 b:4" />
+        "#,
+        "",
+    );
+    assert_eq!(def.fields[0].bit_offset, None);
+    assert_eq!(built::<u32>(&def), [(0, 0, 0x0F), (1, 0, 0xF0)]);
+}
@@ -1,11 +1,97 @@
+use std::collections::HashMap;
+
 use serde_derive::Deserialize;
 
+use crate::meta::ParamMetaEnum;
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ProjectEnums {
     pub list: Vec<ProjectEnum>,
 }
 
+/// Legacy schema of `Enums.json`, used by older paramdex snapshots and DSMapStudio: a map of enum
+/// names to maps of stringified option values to option names.
+pub type LegacyProjectEnums = HashMap<String, HashMap<String, String>>;
+
+/// Converts enums of the [`LegacyProjectEnums`] schema, which have no display names nor
+/// descriptions.
+///
+/// Display names default to the enum names and descriptions are empty. Options are sorted by
+/// value, followed by the ones whose ID is not an integer, sorted by ID.
+pub(crate) fn from_legacy(legacy: LegacyProjectEnums) -> Vec<ProjectEnum> {
+    let mut enums: Vec<_> = (legacy.into_iter())
+        .map(|(name, options)| {
+            let mut options: Vec<_> = (options.into_iter())
+                .map(|(id, name)| EnumOption {
+                    id,
+                    name,
+                    description: String::new(),
+                    value: None,
+                })
+                .collect();
+            options.sort_by_cached_key(|o| o.id.trim().parse::<i64>().map_err(|_| o.id.clone()));
+            ProjectEnum {
+                display_name: name.clone(),
+                name,
+                description: String::new(),
+                options,
+            }
+        })
+        .collect();
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+    enums
+}
+
+/// Schema of `Defs/Enums.xml`, shipped by some paramdexes instead of `Enums.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct XmlProjectEnums {
+    #[serde(default, rename = "Enum")]
+    enums: Vec<XmlProjectEnum>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct XmlProjectEnum {
+    #[serde(rename = "@Name")]
+    name: String,
+    #[serde(rename = "@DisplayName")]
+    display_name: Option<String>,
+    #[serde(default, rename = "@Description")]
+    description: String,
+    #[serde(default, rename = "Option")]
+    options: Vec<XmlEnumOption>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct XmlEnumOption {
+    #[serde(rename = "@Value")]
+    value: String,
+    #[serde(rename = "@Name")]
+    name: String,
+    #[serde(default, rename = "@Description")]
+    description: String,
+}
+
+impl From<XmlProjectEnums> for Vec<ProjectEnum> {
+    fn from(xml: XmlProjectEnums) -> Self {
+        (xml.enums.into_iter())
+            .map(|e| ProjectEnum {
+                display_name: e.display_name.unwrap_or_else(|| e.name.clone()),
+                name: e.name,
+                description: e.description,
+                options: (e.options.into_iter())
+                    .map(|o| EnumOption {
+                        id: o.value,
+                        name: o.name,
+                        description: o.description,
+                        value: None,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ProjectEnum {
@@ -22,4 +108,75 @@ pub struct EnumOption {
     pub id: String,
     pub name: String,
     pub description: String,
+    /// `id` parsed as an integer. Cached by [`crate::Paramdex::load_enums`] and
+    /// [`crate::Paramdex::load_enums_xml`].
+    #[serde(skip)]
+    pub value: Option<i64>,
+}
+
+impl EnumOption {
+    fn parse_value(&mut self) {
+        self.value = self.id.trim().parse().ok();
+    }
+}
+
+impl ProjectEnum {
+    pub(crate) fn parse_values(&mut self) {
+        self.options.iter_mut().for_each(EnumOption::parse_value);
+    }
+}
+
+/// Where the options of an [`EnumHandle`] come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnumSource {
+    /// Enum defined inline in a param meta file, referenced by a field's `@Enum` attribute.
+    Meta,
+    /// Enum defined in `Enums.json` or `Defs/Enums.xml`, referenced by a field's `@ProjectEnum`
+    /// attribute.
+    Project,
+}
+
+/// Uniform view over a [`ParamMetaEnum`] or a [`ProjectEnum`].
+#[derive(Clone, Copy, Debug)]
+pub enum EnumHandle<'a> {
+    Meta(&'a ParamMetaEnum),
+    Project(&'a ProjectEnum),
+}
+
+impl<'a> EnumHandle<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Self::Meta(e) => &e.name,
+            Self::Project(e) => &e.name,
+        }
+    }
+
+    pub fn source(&self) -> EnumSource {
+        match self {
+            Self::Meta(_) => EnumSource::Meta,
+            Self::Project(_) => EnumSource::Project,
+        }
+    }
+
+    /// Iterates over the `(value, name)` pairs of the enum options.
+    ///
+    /// Project enum options whose ID is not an integer are skipped.
+    pub fn options(&self) -> impl Iterator<Item = (i64, &'a str)> + 'a {
+        let (meta, project) = match *self {
+            Self::Meta(e) => (Some(&e.options), None),
+            Self::Project(e) => (None, Some(&e.options)),
+        };
+        let meta = meta.into_iter().flatten().map(|o| (o.value, o.name.as_str()));
+        let project =
+            project.into_iter().flatten().filter_map(|o| Some((o.value?, o.name.as_str())));
+        meta.chain(project)
+    }
+
+    pub fn name_of(&self, value: i64) -> Option<&'a str> {
+        self.options().find(|(v, _)| *v == value).map(|(_, n)| n)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<i64> {
+        self.options().find(|(_, n)| *n == name).map(|(v, _)| v)
+    }
 }
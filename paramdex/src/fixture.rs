@@ -0,0 +1,44 @@
+//! Small hand-maintained paramdex for tests, embedded from the `testdata/` directory.
+
+use super::Paramdex;
+
+/// Name, def and meta of each fixture def.
+const DEFS: [(&str, &str, &str); 3] = [
+    (
+        "ArrayTestParam",
+        include_str!("../testdata/Defs/ArrayTestParam.xml"),
+        include_str!("../testdata/Meta/ArrayTestParam.xml"),
+    ),
+    (
+        "BitfieldTestParam",
+        include_str!("../testdata/Defs/BitfieldTestParam.xml"),
+        include_str!("../testdata/Meta/BitfieldTestParam.xml"),
+    ),
+    (
+        "EnumTestParam",
+        include_str!("../testdata/Defs/EnumTestParam.xml"),
+        include_str!("../testdata/Meta/EnumTestParam.xml"),
+    ),
+];
+
+const ENUMS: &str = include_str!("../testdata/Enums.json");
+
+impl Paramdex {
+    /// Returns a paramdex with the defs, metas and enums of the `testdata/` fixture, for tests
+    /// which need some valid paramdex without network or filesystem access.
+    ///
+    /// The fixture covers bitfields (including version-dependent ones), arrays, strings and both
+    /// meta and project enums. Field layouts are not computed, see
+    /// [`Paramdex::compute_def_layouts`].
+    ///
+    /// Only available in tests or with the `test-fixtures` feature.
+    pub fn fixture() -> Self {
+        let defs = DEFS.map(|(name, def, _)| (name, def));
+        let mut paramdex = Self::from_sources(defs).expect("invalid fixture def");
+        for (name, _, meta) in DEFS {
+            paramdex.add_meta_xml(name, meta).expect("invalid fixture meta");
+        }
+        paramdex.add_enums_json(ENUMS).expect("invalid fixture enums");
+        paramdex
+    }
+}
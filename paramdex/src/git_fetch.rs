@@ -18,6 +18,8 @@ pub enum ParamdexFetchError {
     },
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("Incomplete paramdex checkout for {game}: {} is missing or empty", path.display())]
+    IncompleteCheckout { game: String, path: PathBuf },
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -98,17 +100,42 @@ impl ParamdexGitFetch {
             .exec_command()?;
 
         Command::new("git").current_dir(path).arg("checkout").exec_command()?;
+        self.validate(path)?;
 
         Ok(std::fs::canonicalize(path.join(&self.paramdex_path))?)
     }
 
+    /// Checks that a paramdex previously fetched to `path` is complete, i.e. that the `Defs`
+    /// folder of every requested game exists and is not empty.
+    ///
+    /// Returns [`ParamdexFetchError::IncompleteCheckout`] naming the first missing folder otherwise.
+    pub fn validate(&self, path: impl AsRef<Path>) -> Result<(), ParamdexFetchError> {
+        let root = path.as_ref().join(&self.paramdex_path);
+        for game in &self.games {
+            let defs_path = root.join(game).join("Defs");
+            let is_empty = match std::fs::read_dir(&defs_path) {
+                Ok(mut entries) => entries.next().is_none(),
+                Err(e) if e.kind() == ErrorKind::NotFound => true,
+                Err(e) => return Err(e.into()),
+            };
+            if is_empty {
+                return Err(ParamdexFetchError::IncompleteCheckout {
+                    game: game.clone(),
+                    path: defs_path,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Attempt to fetch a paramdex repository from a remote Git repo, cloning it to the provided path.
     /// Uses sparse checkouts to fetch only the files at a specific path for the given games.
     ///
     /// This is different from [`ParamdexGitFetch::fetch`] in two ways:
     /// - `path` is created if some of the folders comprising it don't exist;
     /// - The fetch operation is cached based on the fields of this [`ParamdexGitFetch`] object.
-    ///     If the last fetch was made from the same source, it will not happen again.
+    ///     If the last fetch was made from the same source, it will not happen again unless
+    ///     [`ParamdexGitFetch::validate`] reports that the cached checkout is incomplete.
     ///
     /// Returns the root paramdex path.
     pub fn fetch_cached(&self, path: impl AsRef<Path>) -> Result<PathBuf, ParamdexFetchError> {
@@ -126,7 +153,8 @@ impl ParamdexGitFetch {
             Ok(contents) => Ok(serde_json::de::from_slice::<ParamdexGitFetch>(&contents)? != *self),
         }?;
 
-        if !should_fetch {
+        // A previous fetch may have been interrupted after writing the meta file
+        if !should_fetch && self.validate(path).is_ok() {
             return Ok(std::fs::canonicalize(path.join(&self.paramdex_path))?);
         }
 
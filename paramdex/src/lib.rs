@@ -3,22 +3,99 @@ use std::{
     ffi::OsStr,
     fs::read_to_string,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
-use enums::{ProjectEnum, ProjectEnums};
-use meta::ParamMeta;
-use paramdef::Paramdef;
+use enums::{EnumHandle, ProjectEnum, ProjectEnums};
+use meta::{ParamMeta, ParamMetaField, ResolvedFieldMeta};
+use paramdef::{DefField, LayoutMismatch, Paramdef};
+use version::RegulationVersion;
 
 pub mod enums;
+#[cfg(any(test, feature = "test-fixtures"))]
+mod fixture;
 pub mod git_fetch;
 pub mod meta;
 pub mod paramdef;
+pub mod renames;
+pub mod unofficial;
+pub mod version;
 
 pub struct DefWithMeta {
     pub def: Paramdef,
     pub meta: Option<ParamMeta>,
 }
 
+impl DefWithMeta {
+    /// Returns the enum of the field named `field_name`, as referenced by the meta.
+    ///
+    /// If the meta field has both an `@Enum` and a `@ProjectEnum` attribute, the `@Enum` wins,
+    /// since it is defined alongside the field and matches its value type. The project enum is
+    /// only used if there is no `@Enum` or it doesn't exist in the meta.
+    pub fn enum_for_field<'a>(
+        &'a self,
+        field_name: &str,
+        paramdex: &'a Paramdex,
+    ) -> Option<EnumHandle<'a>> {
+        let field = self.meta.as_ref()?.fields.get(field_name)?;
+        self.resolve_enum(field, |name| paramdex.project_enum(name))
+    }
+
+    /// Returns the meta of `field`, a field of the def, with its `@Enum` resolved.
+    ///
+    /// Meta fields are looked up by the name of the def field, falling back to the one whose
+    /// `@AltName` is the name or display name of the def field. Project enums are not resolved,
+    /// since they are owned by the paramdex, see [`Paramdex::field_meta`].
+    pub fn field_meta<'a>(&'a self, field: &'a DefField) -> Option<ResolvedFieldMeta<'a>> {
+        self.resolve_field_meta(field, |_| None)
+    }
+
+    fn resolve_field_meta<'a>(
+        &'a self,
+        field: &'a DefField,
+        project_enum: impl FnOnce(&str) -> Option<&'a ProjectEnum>,
+    ) -> Option<ResolvedFieldMeta<'a>> {
+        let meta = self.meta.as_ref()?;
+        let name = field.field_def.name.as_str();
+        let meta_field = meta.fields.get(name).or_else(|| {
+            let display_name = field.display_name.as_deref();
+            (meta.fields.values())
+                .find(|f| f.alt_name == name || Some(f.alt_name.as_str()) == display_name)
+        })?;
+
+        let display_name = Some(meta_field.alt_name.as_str())
+            .filter(|n| !n.is_empty())
+            .or(field.display_name.as_deref())
+            .unwrap_or(name);
+        Some(ResolvedFieldMeta {
+            name,
+            display_name,
+            wiki: meta_field.wiki.as_deref(),
+            is_bool: meta_field.is_bool,
+            r#enum: self.resolve_enum(meta_field, project_enum),
+            project_enum: meta_field.project_enum.as_deref(),
+            refs: &meta_field.refs,
+        })
+    }
+
+    /// Resolves the enum of a meta field, see [`DefWithMeta::enum_for_field`].
+    fn resolve_enum<'a>(
+        &'a self,
+        field: &'a ParamMetaField,
+        project_enum: impl FnOnce(&str) -> Option<&'a ProjectEnum>,
+    ) -> Option<EnumHandle<'a>> {
+        let meta = self.meta.as_ref()?;
+        let meta_enum = field
+            .r#enum
+            .as_deref()
+            .and_then(|name| meta.enums.iter().find(|e| e.name == name));
+        if let Some(e) = meta_enum {
+            return Some(EnumHandle::Meta(e));
+        }
+        field.project_enum.as_deref().and_then(project_enum).map(EnumHandle::Project)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParamdexLoadError {
     #[error("IO error: {0}")]
@@ -27,12 +104,67 @@ pub enum ParamdexLoadError {
     XmlError(#[from] quick_xml::DeError),
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+    /// `Enums.json` matches neither of the supported schemas. Holds the error of each attempt.
+    #[error(
+        "Enums.json matches no known schema (ProjectEnums list: {current}; legacy enum map: {legacy})"
+    )]
+    UnknownEnumsSchema {
+        current: serde_json::Error,
+        legacy: serde_json::Error,
+    },
+}
+
+/// A def whose layout depends on the paramdef version it was computed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionSensitiveDef<'a> {
+    pub param_type: &'a str,
+    /// Size of the def at the version it was checked against.
+    pub size_at_version: usize,
+    /// Size of the def when all fields are enabled.
+    pub size_at_max: usize,
+}
+
+/// Error returned by [`Paramdex::verify_layout`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum VerifyLayoutError {
+    #[error("no def for param type {0}")]
+    UnknownParamType(String),
+    #[error(transparent)]
+    Mismatch(#[from] LayoutMismatch),
+}
+
+/// Defs of a paramdex with their layouts computed at a version, see
+/// [`Paramdex::defs_for_version`].
+#[derive(Debug)]
+pub struct VersionedDefs {
+    version: RegulationVersion,
+    defs: Vec<Paramdef>,
+}
+
+impl VersionedDefs {
+    pub fn version(&self) -> RegulationVersion {
+        self.version
+    }
+
+    pub fn defs(&self) -> impl Iterator<Item = &Paramdef> {
+        self.defs.iter()
+    }
+
+    /// Returns the def of `param_type`.
+    pub fn get(&self, param_type: &str) -> Option<&Paramdef> {
+        self.defs.iter().find(|d| d.param_type == param_type)
+    }
 }
 
+/// Name of the file holding project enums in the `Defs/` directory of some paramdexes.
+const ENUMS_XML: &str = "Enums.xml";
+
 pub struct Paramdex {
     path: PathBuf,
     enums: HashMap<String, ProjectEnum>,
     ext_defs: HashMap<String, DefWithMeta>,
+    /// Defs computed by [`Paramdex::defs_for_version`], cleared whenever the defs change.
+    versioned_defs: Mutex<HashMap<RegulationVersion, Arc<VersionedDefs>>>,
 }
 
 impl Paramdex {
@@ -41,7 +173,26 @@ impl Paramdex {
             path: path.as_ref().to_owned(),
             enums: Default::default(),
             ext_defs: Default::default(),
+            versioned_defs: Default::default(),
+        }
+    }
+
+    /// Builds a paramdex from the XML of its defs, by name, without touching the filesystem,
+    /// e.g. for defs embedded with `include_str!`. Metas and enums can then be added with
+    /// [`Paramdex::add_meta_xml`] and [`Paramdex::add_enums_json`].
+    ///
+    /// The paramdex has no path, so the `load_*` methods should not be used on it.
+    ///
+    /// # Errors
+    /// If a def is not a valid paramdef, returns [`ParamdexLoadError::XmlError`].
+    pub fn from_sources<'a, S: Into<String>>(
+        defs: impl IntoIterator<Item = (S, &'a str)>,
+    ) -> Result<Self, ParamdexLoadError> {
+        let mut paramdex = Self::new("");
+        for (name, xml) in defs {
+            paramdex.add_def_xml(name, xml)?;
         }
+        Ok(paramdex)
     }
 
     pub fn load_defs(&mut self) -> Result<&mut Self, ParamdexLoadError> {
@@ -49,7 +200,10 @@ impl Paramdex {
         for entry in std::fs::read_dir(defs_path)? {
             let fpath = entry?.path();
 
-            if fpath.extension() != Some(OsStr::new("xml")) {
+            // Project enums may be stored alongside the defs
+            if fpath.extension() != Some(OsStr::new("xml"))
+                || fpath.file_name() == Some(OsStr::new(ENUMS_XML))
+            {
                 continue;
             };
             let def_name = match fpath.file_stem() {
@@ -57,14 +211,38 @@ impl Paramdex {
                 Some(n) => n.to_string_lossy().to_string(),
             };
             let def_contents = std::fs::read_to_string(fpath)?;
-            self.ext_defs.insert(
-                def_name,
-                DefWithMeta {
-                    def: quick_xml::de::from_str(&def_contents)?,
-                    meta: None,
-                },
-            );
+            self.add_def_xml(def_name, &def_contents)?;
+        }
+        Ok(self)
+    }
+
+    /// Writes the loaded defs as XML to the `Defs/` directory of `path`, under the name they were
+    /// loaded with. See [`Paramdef::to_xml`].
+    pub fn save_defs(&self, path: impl AsRef<Path>) -> Result<(), ParamdexLoadError> {
+        let defs_path = path.as_ref().join("Defs");
+        std::fs::create_dir_all(&defs_path)?;
+        for (def_name, pair) in &self.ext_defs {
+            std::fs::write(
+                defs_path.join(format!("{def_name}.xml")),
+                pair.def.to_xml()?,
+            )?;
         }
+        Ok(())
+    }
+
+    /// Adds the def `def_name` from its XML, replacing the def of the same name and its meta if
+    /// any.
+    ///
+    /// # Errors
+    /// If `xml` is not a valid paramdef, returns [`ParamdexLoadError::XmlError`].
+    pub fn add_def_xml(
+        &mut self,
+        def_name: impl Into<String>,
+        xml: &str,
+    ) -> Result<&mut Self, ParamdexLoadError> {
+        let def = quick_xml::de::from_str(xml)?;
+        self.clear_versioned_defs();
+        self.ext_defs.insert(def_name.into(), DefWithMeta { def, meta: None });
         Ok(self)
     }
 
@@ -80,21 +258,81 @@ impl Paramdex {
                 None => continue,
                 Some(n) => n.to_string_lossy(),
             };
-            if let Some(pair) = self.ext_defs.get_mut(def_name.as_ref()) {
-                let meta_contents = std::fs::read_to_string(fpath)?;
-                pair.meta = Some(quick_xml::de::from_str(&meta_contents)?);
+            if self.ext_defs.contains_key(def_name.as_ref()) {
+                let meta_contents = std::fs::read_to_string(&fpath)?;
+                self.add_meta_xml(&def_name, &meta_contents)?;
             }
         }
         Ok(self)
     }
 
+    /// Sets the meta of the def `def_name` from its XML. Like [`Paramdex::load_metas`], the meta
+    /// is ignored if there is no such def.
+    ///
+    /// # Errors
+    /// If `xml` is not a valid param meta, returns [`ParamdexLoadError::XmlError`].
+    pub fn add_meta_xml(
+        &mut self,
+        def_name: &str,
+        xml: &str,
+    ) -> Result<&mut Self, ParamdexLoadError> {
+        if let Some(pair) = self.ext_defs.get_mut(def_name) {
+            pair.meta = Some(quick_xml::de::from_str(xml)?);
+        }
+        Ok(self)
+    }
+
+    /// Loads the project enums from `Enums.json`, see [`Paramdex::add_enums_json`].
+    ///
+    /// # Errors
+    /// If the file can't be read, returns [`ParamdexLoadError::IoError`]. If it matches neither
+    /// schema, returns [`ParamdexLoadError::UnknownEnumsSchema`].
     pub fn load_enums(&mut self) -> Result<&mut Self, ParamdexLoadError> {
-        let enums_content = std::fs::read(self.path.join("Enums.json"))?;
-        let enums: ProjectEnums = serde_json::from_slice(&enums_content)?;
-        self.enums = enums.list.into_iter().map(|e| (e.name.clone(), e)).collect();
+        let enums_content = std::fs::read_to_string(self.path.join("Enums.json"))?;
+        self.add_enums_json(&enums_content)
+    }
+
+    /// Loads the project enums from `Defs/Enums.xml`, for paramdexes which don't ship an
+    /// `Enums.json`. Enums previously loaded are replaced.
+    pub fn load_enums_xml(&mut self) -> Result<&mut Self, ParamdexLoadError> {
+        let contents = std::fs::read_to_string(self.path.join("Defs").join(ENUMS_XML))?;
+        let enums: enums::XmlProjectEnums = quick_xml::de::from_str(&contents)?;
+        self.set_enum_list(enums.into());
+        Ok(self)
+    }
+
+    /// Sets the project enums from the contents of an `Enums.json`.
+    ///
+    /// The current schema (a `List` of enums with their `Options`) is tried first, then the
+    /// [`LegacyProjectEnums`](enums::LegacyProjectEnums) map. Enums previously loaded are
+    /// replaced.
+    ///
+    /// # Errors
+    /// If `json` matches neither schema, returns [`ParamdexLoadError::UnknownEnumsSchema`].
+    pub fn add_enums_json(&mut self, json: &str) -> Result<&mut Self, ParamdexLoadError> {
+        let list = match serde_json::from_str::<ProjectEnums>(json) {
+            Ok(enums) => enums.list,
+            Err(current) => match serde_json::from_str(json) {
+                Ok(legacy) => enums::from_legacy(legacy),
+                Err(legacy) => {
+                    return Err(ParamdexLoadError::UnknownEnumsSchema { current, legacy })
+                }
+            },
+        };
+        self.set_enum_list(list);
         Ok(self)
     }
 
+    fn set_enum_list(&mut self, list: Vec<ProjectEnum>) {
+        self.enums = list
+            .into_iter()
+            .map(|mut e| {
+                e.parse_values();
+                (e.name.clone(), e)
+            })
+            .collect();
+    }
+
     pub fn compute_def_layouts(&mut self, version: u64) -> &mut Self {
         for def in self.ext_defs.values_mut().map(|pair| &mut pair.def) {
             def.compute_field_offsets(version);
@@ -102,7 +340,86 @@ impl Paramdex {
         self
     }
 
+    /// Returns copies of the defs with their layouts computed at `version`.
+    ///
+    /// Unlike [`Paramdex::compute_def_layouts`], the defs of the paramdex are left untouched, so
+    /// the defs of several versions can be used at once. They are computed once per version, until
+    /// the defs change.
+    pub fn defs_for_version(&self, version: RegulationVersion) -> Arc<VersionedDefs> {
+        let mut cache = self.versioned_defs.lock().unwrap();
+        let versioned = cache.entry(version).or_insert_with(|| {
+            let mut defs: Vec<_> = self.defs().cloned().collect();
+            for def in &mut defs {
+                def.compute_field_offsets(version.packed());
+            }
+            Arc::new(VersionedDefs { version, defs })
+        });
+        versioned.clone()
+    }
+
+    fn clear_versioned_defs(&mut self) {
+        self.versioned_defs.get_mut().unwrap().clear();
+    }
+
+    /// Returns the defs whose size at `version` differs from their size when all fields are
+    /// enabled, i.e. the ones for which picking the right layout version matters.
+    pub fn version_sensitive_defs(&self, version: u64) -> Vec<VersionSensitiveDef<'_>> {
+        let mut sensitive: Vec<_> = self
+            .defs()
+            .filter_map(|def| {
+                let mut def_copy = def.clone();
+                let size_at_version = def_copy.compute_field_offsets(version).size_bytes?;
+                let size_at_max = def_copy.compute_field_offsets(u64::MAX).size_bytes?;
+                (size_at_version != size_at_max).then_some(VersionSensitiveDef {
+                    param_type: &def.param_type,
+                    size_at_version,
+                    size_at_max,
+                })
+            })
+            .collect();
+        sensitive.sort_by_key(|s| s.param_type);
+        sensitive
+    }
+
+    /// Checks that the computed layout of the def of `param_type` is `row_size` bytes long. See
+    /// [`Paramdef::verify_layout`].
+    pub fn verify_layout(
+        &self,
+        param_type: &str,
+        row_size: usize,
+    ) -> Result<(), VerifyLayoutError> {
+        let def = (self.defs().find(|d| d.param_type == param_type))
+            .ok_or_else(|| VerifyLayoutError::UnknownParamType(param_type.to_owned()))?;
+        Ok(def.verify_layout(row_size)?)
+    }
+
     pub fn defs(&self) -> impl Iterator<Item = &Paramdef> {
         self.ext_defs.values().map(|pair| &pair.def)
     }
+
+    /// Returns the def and meta loaded from the def file named `def_name` (without extension).
+    pub fn def_with_meta(&self, def_name: &str) -> Option<&DefWithMeta> {
+        self.ext_defs.get(def_name)
+    }
+
+    /// Same as [`DefWithMeta::field_meta`], also resolving project enums from the loaded
+    /// `Enums.json`.
+    pub fn field_meta<'a>(
+        &'a self,
+        def: &'a DefWithMeta,
+        field: &'a DefField,
+    ) -> Option<ResolvedFieldMeta<'a>> {
+        def.resolve_field_meta(field, |name| self.project_enum(name))
+    }
+
+    pub fn project_enum(&self, name: &str) -> Option<&ProjectEnum> {
+        self.enums.get(name)
+    }
+
+    /// Iterates over the loaded project enums, sorted by name.
+    pub fn project_enums(&self) -> impl Iterator<Item = &ProjectEnum> {
+        let mut enums: Vec<_> = self.enums.values().collect();
+        enums.sort_by(|a, b| a.name.cmp(&b.name));
+        enums.into_iter()
+    }
 }
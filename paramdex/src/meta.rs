@@ -1,7 +1,9 @@
-use super::paramdef::DefBaseType;
+use std::{collections::HashMap, fmt, marker::PhantomData, str::FromStr};
+
 use serde::de::{self, Visitor};
 use serde_derive::Deserialize;
-use std::{collections::HashMap, marker::PhantomData};
+
+use super::{enums::EnumHandle, paramdef::DefBaseType};
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename = "PARAMMETA", rename_all = "PascalCase")]
@@ -15,22 +17,41 @@ pub struct ParamMeta {
         deserialize_with = "deserialize_map::<ParamMetaField, _>"
     )]
     pub fields: HashMap<String, ParamMetaField>,
-    #[serde(rename = "Self", deserialize_with = "self_desc")]
-    pub self_desc: Option<String>,
+    #[serde(default, rename = "Self")]
+    pub self_info: ParamMetaSelf,
 }
 
-fn self_desc<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    #[derive(Deserialize)]
-    struct SelfInfo {
-        #[serde(rename = "@Wiki")]
-        pub wiki: Option<String>,
+impl ParamMeta {
+    /// Description of the param, from the `@Wiki` of the `Self` element.
+    pub fn self_desc(&self) -> Option<&str> {
+        self.self_info.wiki.as_deref()
     }
+}
 
-    let self_info: SelfInfo = de::Deserialize::deserialize(deserializer)?;
-    Ok(self_info.wiki)
+/// Attributes of the `Self` element of a meta, describing the param as a whole.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ParamMetaSelf {
+    #[serde(rename = "@Wiki")]
+    pub wiki: Option<String>,
+    /// The first row of the param is a placeholder, e.g. row 0 of `EquipParamWeapon`.
+    #[serde(default, rename = "@Row0Dummy", deserialize_with = "is_tag_present")]
+    pub row0_dummy: bool,
+    /// Row IDs are consecutive, so that rows are usually inserted after a similar one.
+    #[serde(
+        default,
+        rename = "@ConsecutiveIDs",
+        deserialize_with = "is_tag_present"
+    )]
+    pub consecutive_ids: bool,
+    /// Step between the IDs of related rows, e.g. the reinforcement levels of a weapon.
+    #[serde(rename = "@OffsetSize")]
+    pub offset_size: Option<u32>,
+    /// Row ID added to the ID of the related row by the game, e.g. for upgrades.
+    #[serde(rename = "@FixedOffset")]
+    pub fixed_offset: Option<i64>,
+    /// Comma separated field names, in the order they should be displayed in.
+    #[serde(rename = "@AlternativeOrder")]
+    pub alternative_order: Option<String>,
 }
 
 #[derive(Default, Clone, Debug, Deserialize)]
@@ -66,9 +87,10 @@ pub struct ParamEnumOption {
     pub name: String,
 }
 
+/// Meta of a def field. Attributes not listed here are ignored.
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct ParamMetaField {
-    #[serde(rename = "@AltName")]
+    #[serde(default, rename = "@AltName")]
     pub alt_name: String,
     #[serde(rename = "@Wiki")]
     pub wiki: Option<String>,
@@ -78,6 +100,142 @@ pub struct ParamMetaField {
     pub project_enum: Option<String>,
     #[serde(default, rename = "@IsBool", deserialize_with = "is_tag_present")]
     pub is_bool: bool,
+    /// Rows of other params the value of the field is the ID of, from `@Refs`.
+    #[serde(default, rename = "@Refs", deserialize_with = "ref_list")]
+    pub refs: Vec<ParamRef>,
+    /// Virtual reference: fields of other params which share values with this one, without
+    /// being row IDs, e.g. `AttackElementCorrect` IDs.
+    #[serde(rename = "@VRef")]
+    pub vref: Option<String>,
+    /// FMG entries the value of the field is the ID of, from `@FmgRef`.
+    #[serde(default, rename = "@FmgRef", deserialize_with = "ref_list")]
+    pub fmg_refs: Vec<ParamRef>,
+    /// Secondary param references, kept as written.
+    #[serde(rename = "@ParamRef2")]
+    pub param_ref2: Option<String>,
+    /// Editors display a separator before the field.
+    #[serde(default, rename = "@Separator", deserialize_with = "is_tag_present")]
+    pub separator: bool,
+    /// The value of the field is a particle (FFX) ID.
+    #[serde(
+        default,
+        rename = "@ParticleAlias",
+        deserialize_with = "is_tag_present"
+    )]
+    pub particle_alias: bool,
+    /// The value of the field is a sound ID.
+    #[serde(default, rename = "@SoundAlias", deserialize_with = "is_tag_present")]
+    pub sound_alias: bool,
+    /// The value of the field is an event flag.
+    #[serde(default, rename = "@FlagAlias", deserialize_with = "is_tag_present")]
+    pub flag_alias: bool,
+}
+
+/// Reference from a field to another param or FMG, e.g. `AtkParam_Pc(refType=0)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamRef {
+    /// Name of the referenced param or FMG.
+    pub name: String,
+    /// The reference only applies when this condition holds.
+    pub condition: Option<RefCondition>,
+}
+
+/// Condition of a [`ParamRef`]: the field `field` of the same row has the value `value`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefCondition {
+    pub field: String,
+    pub value: i64,
+}
+
+/// Error returned when parsing an invalid [`ParamRef`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseParamRefError {
+    pub reference: String,
+}
+
+impl fmt::Display for ParseParamRefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid reference {:?}", self.reference)
+    }
+}
+
+impl std::error::Error for ParseParamRefError {}
+
+impl FromStr for ParamRef {
+    type Err = ParseParamRefError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseParamRefError {
+            reference: s.to_owned(),
+        };
+        let s = s.trim();
+        let Some((name, condition)) = s.split_once('(')
+        else {
+            return (!s.is_empty()).then(|| ParamRef::unconditional(s)).ok_or_else(err);
+        };
+        let (field, value) =
+            condition.strip_suffix(')').and_then(|c| c.split_once('=')).ok_or_else(err)?;
+        let (name, field) = (name.trim(), field.trim());
+        if name.is_empty() || field.is_empty() {
+            return Err(err());
+        }
+        Ok(ParamRef {
+            name: name.to_owned(),
+            condition: Some(RefCondition {
+                field: field.to_owned(),
+                value: value.trim().parse().map_err(|_| err())?,
+            }),
+        })
+    }
+}
+
+impl ParamRef {
+    /// Returns an unconditional reference to `name`.
+    pub fn unconditional(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            condition: None,
+        }
+    }
+
+    /// Parses a comma separated list of references, as found in `@Refs` and `@FmgRef`.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, ParseParamRefError> {
+        s.split(',').filter(|r| !r.trim().is_empty()).map(str::parse).collect()
+    }
+}
+
+fn ref_list<'de, D>(deserializer: D) -> Result<Vec<ParamRef>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let list: String = de::Deserialize::deserialize(deserializer)?;
+    ParamRef::parse_list(&list).map_err(de::Error::custom)
+}
+
+/// Meta of a def field, resolved by [`DefWithMeta::field_meta`](crate::DefWithMeta::field_meta)
+/// or [`Paramdex::field_meta`](crate::Paramdex::field_meta).
+#[derive(Clone, Copy, Debug)]
+pub struct ResolvedFieldMeta<'a> {
+    /// Name of the field in the def.
+    pub name: &'a str,
+    /// `@AltName` of the meta field, or the display name of the def field if it has none.
+    pub display_name: &'a str,
+    pub wiki: Option<&'a str>,
+    pub is_bool: bool,
+    /// Enum of the field, from the meta `@Enum` or the `@ProjectEnum` if it was resolved.
+    pub r#enum: Option<EnumHandle<'a>>,
+    /// Name of the project enum referenced by the meta field, whether it was resolved or not.
+    pub project_enum: Option<&'a str>,
+    /// Rows of other params the value of the field is the ID of.
+    pub refs: &'a [ParamRef],
+}
+
+impl<'a> ResolvedFieldMeta<'a> {
+    /// Returns the `(value, name)` pairs of the options of the field enum, or an empty list if
+    /// the field has no enum.
+    pub fn enum_options(&self) -> Vec<(i64, &'a str)> {
+        self.r#enum.iter().flat_map(|e| e.options()).collect()
+    }
 }
 
 fn is_tag_present<'de, D>(_deserializer: D) -> Result<bool, D::Error>
@@ -1,16 +1,25 @@
-use std::{fmt::Display, u64};
+use std::{borrow::Cow, fmt::Display, u64};
 
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::de;
-use serde_derive::Deserialize;
+use serde::{
+    de,
+    ser::{self, Serializer},
+};
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Deserialize, Clone, Debug)]
+use crate::unofficial::UnofficialPlacement;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename = "PARAMDEF", rename_all = "PascalCase")]
 pub struct Paramdef {
+    #[serde(rename = "@XmlVersion", skip_serializing_if = "Option::is_none")]
+    pub xml_version: Option<u32>,
     pub param_type: String,
     pub data_version: u32,
+    #[serde(serialize_with = "serialize_pascal_bool")]
     pub big_endian: bool,
+    #[serde(serialize_with = "serialize_pascal_bool")]
     pub unicode: bool,
     pub format_version: u32,
     pub fields: DefFields,
@@ -22,17 +31,23 @@ pub struct Paramdef {
 impl Paramdef {
     pub fn compute_field_offsets(&mut self, version: u64) -> &mut Self {
         let mut bit_offset: usize = 0;
-        let mut last_field = None;
+        let mut last_field: Option<usize> = None;
         let mut align_bits = 8;
         for i in 0..self.fields.len() {
             let f = &self.fields[i];
-            bit_offset = if !f.enabled_for_version(version) {
+            // Unofficial fields are placed relative to their host below, and don't take space
+            if f.unofficial.is_some() {
+                continue;
+            }
+            // Fields absent at this version take no space, and leave the running offset and
+            // alignment untouched
+            if !f.enabled_for_version(version) {
                 self.fields[i].bit_offset = None;
                 continue;
-            } else if let Some(j) = last_field {
-                f.field_def.compute_bit_offset(bit_offset, &self.fields[j as usize].field_def)
-            } else {
-                0
+            }
+            bit_offset = match last_field {
+                Some(j) => f.field_def.compute_bit_offset(bit_offset, &self.fields[j].field_def),
+                None => 0,
             };
 
             align_bits = f.alignment().max(align_bits);
@@ -44,16 +59,147 @@ impl Paramdef {
         bit_offset = (bit_offset + last_fsize + align_bits - 1) & !(align_bits - 1);
 
         self.size_bytes = Some(bit_offset / 8);
+
+        for i in 0..self.fields.len() {
+            let Some(placement) = &self.fields[i].unofficial
+            else {
+                continue;
+            };
+            let host_offset = self.field_by_name(&placement.host).and_then(|h| h.bit_offset);
+            let bit_offset = host_offset.map(|o| o + placement.bit_offset);
+            self.fields[i].bit_offset = bit_offset;
+        }
         self
     }
+
+    /// Checks that the size of the def computed by [`Paramdef::compute_field_offsets`] is
+    /// `expected_row_size`, e.g. the row size of a param file of this type.
+    ///
+    /// On mismatch, the error lists the last fields of the layout, which is usually where a
+    /// missing or misplaced field shows up.
+    pub fn verify_layout(&self, expected_row_size: usize) -> Result<(), LayoutMismatch> {
+        if self.size_bytes == Some(expected_row_size) {
+            return Ok(());
+        }
+        let placed = self.fields.iter().filter(|f| f.unofficial.is_none());
+        let mut last_fields: Vec<_> = placed
+            .filter_map(|f| {
+                Some(PlacedField {
+                    name: f.field_def.name.clone(),
+                    bit_offset: f.bit_offset?,
+                    size_bits: f.size_bits(),
+                })
+            })
+            .rev()
+            .take(LayoutMismatch::LAST_FIELDS)
+            .collect();
+        last_fields.reverse();
+        Err(LayoutMismatch {
+            param_type: self.param_type.clone(),
+            computed: self.size_bytes,
+            expected: expected_row_size,
+            last_fields,
+        })
+    }
+
+    /// Returns the field named `name`.
+    pub fn field_by_name(&self, name: &str) -> Option<&DefField> {
+        self.fields.iter().find(|f| f.field_def.name == name)
+    }
+
+    /// Serializes the def to XML, in the format of the Paramdex `Defs/` files.
+    ///
+    /// Unofficial fields are not part of the def and are left out.
+    pub fn to_xml(&self) -> Result<String, quick_xml::DeError> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+        serializer.indent(' ', 2);
+        ser::Serialize::serialize(self, serializer)?;
+        xml.push('\n');
+        Ok(xml)
+    }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// A field of a def along with its computed offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedField {
+    pub name: String,
+    pub bit_offset: usize,
+    pub size_bits: usize,
+}
+
+impl Display for PlacedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (byte, bit) = (self.bit_offset / 8, self.bit_offset % 8);
+        write!(f, "{} at 0x{byte:x}", self.name)?;
+        if bit != 0 {
+            write!(f, ".{bit}")?;
+        }
+        write!(f, " ({} bits)", self.size_bits)
+    }
+}
+
+/// Error returned by [`Paramdef::verify_layout`] when the computed size of a def is not the
+/// expected row size.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "{param_type} is {} but rows are {expected} bytes; last fields: {}",
+    describe_size(.computed),
+    describe_fields(.last_fields)
+)]
+pub struct LayoutMismatch {
+    pub param_type: String,
+    /// Size of the def in bytes, or `None` if its field offsets were not computed.
+    pub computed: Option<usize>,
+    pub expected: usize,
+    /// Last [`LayoutMismatch::LAST_FIELDS`] fields of the layout, in order.
+    pub last_fields: Vec<PlacedField>,
+}
+
+impl LayoutMismatch {
+    /// Maximum number of fields listed in [`LayoutMismatch::last_fields`].
+    pub const LAST_FIELDS: usize = 4;
+}
+
+fn describe_size(size: &Option<usize>) -> String {
+    match size {
+        Some(size) => format!("{size} bytes"),
+        None => "not laid out".to_owned(),
+    }
+}
+
+fn describe_fields(fields: &[PlacedField]) -> String {
+    if fields.is_empty() {
+        return "none".to_owned();
+    }
+    let fields: Vec<_> = fields.iter().map(PlacedField::to_string).collect();
+    fields.join(", ")
+}
+
+/// Writes booleans as `True` and `False`, like the Paramdex defs.
+fn serialize_pascal_bool<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(if *value { "True" } else { "False" })
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct DefFields {
     field: Vec<DefField>,
 }
 
+impl ser::Serialize for DefFields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct OfficialFields<'a> {
+            field: Vec<&'a DefField>,
+        }
+
+        let field = self.field.iter().filter(|f| f.unofficial.is_none()).collect();
+        ser::Serialize::serialize(&OfficialFields { field }, serializer)
+    }
+}
+
 impl std::ops::Deref for DefFields {
     type Target = Vec<DefField>;
     fn deref(&self) -> &Self::Target {
@@ -67,27 +213,38 @@ impl std::ops::DerefMut for DefFields {
     }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct DefField {
     #[serde(rename = "@Def")]
     pub field_def: DefType,
+    #[serde(rename = "@FirstVersion", skip_serializing_if = "Option::is_none")]
+    pub first_version: Option<u64>,
+    #[serde(rename = "@RemovedVersion", skip_serializing_if = "Option::is_none")]
+    pub removed_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
-    #[serde(rename = "Enum")]
+    #[serde(rename = "Enum", skip_serializing_if = "Option::is_none")]
     pub enum_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub edit_flags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub maximum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub increment: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub sort_id: Option<i32>,
-    #[serde(rename = "@FirstVersion")]
-    pub first_version: Option<u64>,
-    #[serde(rename = "@RemovedVersion")]
-    pub removed_version: Option<u64>,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub bit_offset: Option<usize>,
+    /// Set if the field is not part of the def, but was merged from an
+    /// [`UnofficialFields`](crate::unofficial::UnofficialFields) overlay.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub unofficial: Option<UnofficialPlacement>,
 }
 
 impl DefField {
@@ -194,6 +351,21 @@ impl DefBaseType {
         self.rust_type().alignment()
     }
 
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            Self::Dummy8 => "dummy8",
+            Self::S8 => "s8",
+            Self::U8 => "u8",
+            Self::S16 => "s16",
+            Self::U16 => "u16",
+            Self::S32 => "s32",
+            Self::U32 => "u32",
+            Self::F32 => "f32",
+            Self::Fixstr => "fixstr",
+            Self::FixstrW => "fixstrW",
+        }
+    }
+
     pub fn from_str(s: &str) -> Option<DefBaseType> {
         match s {
             "dummy8" => Some(Self::Dummy8),
@@ -238,6 +410,8 @@ pub struct DefType {
     pub name: String,
     pub base_type: DefBaseType,
     pub modifier: DefTypeModifier,
+    /// Default value following the declaration, e.g. `1` in `u8 isEnabled:1 = 1`, as written.
+    pub default_value: Option<String>,
 }
 
 impl DefType {
@@ -248,7 +422,7 @@ impl DefType {
         }
     }
 
-    fn size_bits(&self) -> usize {
+    pub(crate) fn size_bits(&self) -> usize {
         match self.modifier {
             DefTypeModifier::None => 8 * self.base_type.size_bytes(),
             DefTypeModifier::Array(len) => 8 * len * self.base_type.size_bytes(),
@@ -284,6 +458,28 @@ impl DefType {
     }
 }
 
+impl Display for DefType {
+    /// Writes the declaration in the format of the `Def` attribute of paramdef fields.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.base_type.to_str(), self.name)?;
+        match self.modifier {
+            DefTypeModifier::None => {}
+            DefTypeModifier::Array(len) => write!(f, "[{len}]")?,
+            DefTypeModifier::Bitfield(width) => write!(f, ":{width}")?,
+        }
+        if let Some(default_value) = &self.default_value {
+            write!(f, " = {default_value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ser::Serialize for DefType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for DefType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -291,11 +487,13 @@ impl<'de> serde::Deserialize<'de> for DefType {
     {
         lazy_static! {
             static ref FIELD_PARSE: Regex = Regex::new(
-                r"^(?P<base_type>[\w\d_]+)\s+(?P<name>[\w\d_]+)\s*((\[(?P<array_size>[\w\d]+)\])|(:\s*(?P<bitfield_size>[\w\d]+)))?\s*(=.*)?$"
+                r"^(?P<base_type>[\w\d_]+)\s+(?P<name>[\w\d_]+)\s*((\[(?P<array_size>[\w\d]+)\])|(:\s*(?P<bitfield_size>[\w\d]+)))?\s*(=\s*(?P<default_value>.*?)\s*)?$"
             ).unwrap();
         }
 
-        let s: &str = de::Deserialize::deserialize(deserializer)?;
+        // Borrowed when possible (e.g. XML), but owned strings are accepted for formats like TOML
+        let s: Cow<'de, str> = de::Deserialize::deserialize(deserializer)?;
+        let s = s.as_ref();
         let captures = FIELD_PARSE.captures(s).ok_or(de::Error::invalid_value(
             de::Unexpected::Str(s),
             &"C struct field",
@@ -326,6 +524,7 @@ impl<'de> serde::Deserialize<'de> for DefType {
                     DefTypeModifier::None
                 }
             },
+            default_value: captures.name("default_value").map(|v| v.as_str().to_owned()),
         })
     }
 }
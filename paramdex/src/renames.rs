@@ -0,0 +1,167 @@
+//! Tracking of fields renamed between paramdex versions, so that names saved against an older
+//! paramdex still resolve.
+
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+use super::{
+    paramdef::{DefField, Paramdef},
+    Paramdex,
+};
+
+/// Map from old to new field names, per param type.
+///
+/// Rename maps are written in TOML, with one table per param type:
+/// ```toml
+/// [EQUIP_PARAM_WEAPON_ST]
+/// oldFieldName = "newFieldName"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct RenameMap {
+    renames: HashMap<String, HashMap<String, String>>,
+}
+
+impl RenameMap {
+    /// Parses a rename map from TOML.
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    /// Returns the rename map shipped with the crate for `game` (`"DS3"`, `"ER"` or `"AC6"`).
+    pub fn builtin(game: &str) -> Option<Self> {
+        let toml = match game {
+            "DS3" => include_str!("../renames/DS3.toml"),
+            "ER" => include_str!("../renames/ER.toml"),
+            "AC6" => include_str!("../renames/AC6.toml"),
+            _ => return None,
+        };
+        Some(Self::from_toml(toml).expect("invalid builtin rename map"))
+    }
+
+    /// Adds the renames of `other` to this map, e.g. user-provided ones on top of the builtin
+    /// map. Renames in `other` take precedence.
+    pub fn extend(&mut self, other: RenameMap) {
+        for (param_type, renames) in other.renames {
+            self.renames.entry(param_type).or_default().extend(renames);
+        }
+    }
+
+    /// Adds a single rename.
+    pub fn insert(&mut self, param_type: &str, old_name: &str, new_name: &str) {
+        self.renames
+            .entry(param_type.to_owned())
+            .or_default()
+            .insert(old_name.to_owned(), new_name.to_owned());
+    }
+
+    /// Returns the current name of the field of `param_type` once named `old_name`, following
+    /// successive renames.
+    ///
+    /// Returns `None` if the field was never renamed, or if the renames form a cycle.
+    pub fn resolve(&self, param_type: &str, old_name: &str) -> Option<&str> {
+        let renames = self.renames.get(param_type)?;
+        let mut name = renames.get(old_name)?;
+        for _ in 0..renames.len() {
+            match renames.get(name) {
+                Some(next) => name = next,
+                None => return Some(name),
+            }
+        }
+        None
+    }
+}
+
+/// Result of [`Paramdef::resolve_field`].
+#[derive(Debug, Clone, Copy)]
+pub struct FieldLookup<'a> {
+    pub field: &'a DefField,
+    /// True if the field was found under its new name through a [`RenameMap`].
+    pub via_rename: bool,
+}
+
+impl Paramdef {
+    /// Returns the field named `name`, falling back to its current name in `renames` if no field
+    /// has that name.
+    pub fn resolve_field(&self, name: &str, renames: &RenameMap) -> Option<FieldLookup<'_>> {
+        if let Some(field) = self.field_by_name(name) {
+            return Some(FieldLookup {
+                field,
+                via_rename: false,
+            });
+        }
+        let new_name = renames.resolve(&self.param_type, name)?;
+        self.field_by_name(new_name).map(|field| FieldLookup {
+            field,
+            via_rename: true,
+        })
+    }
+}
+
+/// Field which is likely to have been renamed between two paramdex versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameCandidate {
+    pub param_type: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// Proposes rename map entries for the fields of defs present in both paramdexes which have the
+/// same type and offset, but a different name.
+///
+/// Layouts are compared with all fields enabled. Fields only match if their old name is missing
+/// from the new def, their new name is missing from the old def, and the match is unambiguous.
+/// Candidates are sorted by param type and old name.
+pub fn detect_renames(old: &Paramdex, new: &Paramdex) -> Vec<RenameCandidate> {
+    let with_layout = |def: &Paramdef| {
+        let mut def = def.clone();
+        def.compute_field_offsets(u64::MAX);
+        def
+    };
+
+    let mut candidates = Vec::new();
+    for old_def in old.defs() {
+        let Some(new_def) = new.defs().find(|d| d.param_type == old_def.param_type)
+        else {
+            continue;
+        };
+        let (old_def, new_def) = (with_layout(old_def), with_layout(new_def));
+
+        let missing_from = |def: &Paramdef, other: &Paramdef| -> Vec<DefField> {
+            def.fields
+                .iter()
+                .filter(|f| {
+                    f.bit_offset.is_some() && other.field_by_name(&f.field_def.name).is_none()
+                })
+                .cloned()
+                .collect()
+        };
+        let removed = missing_from(&old_def, &new_def);
+        let added = missing_from(&new_def, &old_def);
+
+        let same_layout = |a: &DefField, b: &DefField| {
+            a.bit_offset == b.bit_offset
+                && a.field_def.base_type == b.field_def.base_type
+                && a.field_def.modifier == b.field_def.modifier
+        };
+        for old_field in &removed {
+            let mut matches = added.iter().filter(|f| same_layout(f, old_field));
+            let Some(new_field) = matches.next()
+            else {
+                continue;
+            };
+            let is_unique = matches.next().is_none()
+                && removed.iter().filter(|f| same_layout(f, new_field)).count() == 1;
+            if is_unique {
+                candidates.push(RenameCandidate {
+                    param_type: old_def.param_type.clone(),
+                    old_name: old_field.field_def.name.clone(),
+                    new_name: new_field.field_def.name.clone(),
+                });
+            }
+        }
+    }
+    candidates.sort_by(|a, b| (&a.param_type, &a.old_name).cmp(&(&b.param_type, &b.old_name)));
+    candidates
+}
@@ -0,0 +1,242 @@
+//! Community-discovered fields living in the padding of official paramdefs.
+//!
+//! Some `dummy8` padding is known to hold meaningful values which are not in the defs yet. An
+//! [`UnofficialFields`] overlay describes such fields as ranges of bits inside a named padding
+//! field. Once merged into a [`Paramdef`], they are regular [`DefField`]s (flagged with
+//! [`DefField::unofficial`]) which get laid out, looked up and turned into field blocks like
+//! the official ones.
+
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+use super::{
+    paramdef::{DefBaseType, DefField, DefType, Paramdef},
+    Paramdex,
+};
+
+/// Synthetic field carved out of a padding field, as written in an overlay.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnofficialField {
+    /// Name of the `dummy8` field containing this one.
+    pub host: String,
+    /// Offset of the field from the start of the host field, in bits.
+    pub bit_offset: usize,
+    /// Declaration of the field, in the same format as the `Def` attribute of paramdef fields,
+    /// e.g. `u8 isEnabled:1` or `s16 unkId`.
+    pub def: DefType,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Position of an unofficial field, relative to the padding field it was carved from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnofficialPlacement {
+    /// Name of the `dummy8` field containing this one.
+    pub host: String,
+    /// Offset of the field from the start of the host field, in bits.
+    pub bit_offset: usize,
+}
+
+/// Unofficial fields, per param type.
+///
+/// Overlays are written in TOML, with an array of tables per param type:
+/// ```toml
+/// [[EQUIP_PARAM_WEAPON_ST]]
+/// host = "pad2"
+/// bit_offset = 8
+/// def = "u8 unkFlag:1"
+/// display_name = "Unknown Flag"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct UnofficialFields {
+    fields: HashMap<String, Vec<UnofficialField>>,
+}
+
+impl UnofficialFields {
+    /// Parses an overlay from TOML.
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    /// Adds an unofficial field to `param_type`.
+    pub fn insert(&mut self, param_type: &str, field: UnofficialField) {
+        self.fields.entry(param_type.to_owned()).or_default().push(field);
+    }
+
+    /// Returns the unofficial fields of `param_type`.
+    pub fn get(&self, param_type: &str) -> &[UnofficialField] {
+        self.fields.get(param_type).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Why an unofficial field could not be merged into a def.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UnofficialFieldError {
+    #[error("{param_type}.{field}: the def has no field named {host}")]
+    UnknownHost {
+        param_type: String,
+        field: String,
+        host: String,
+    },
+    #[error("{param_type}.{field}: {host} is not a dummy8 padding field")]
+    OverlapsOfficialField {
+        param_type: String,
+        field: String,
+        host: String,
+    },
+    #[error("{param_type}.{field}: bits {start}..{end} are out of bounds of {host}")]
+    OutOfBounds {
+        param_type: String,
+        field: String,
+        host: String,
+        start: usize,
+        end: usize,
+    },
+    #[error("{param_type}.{field}: overlaps unofficial field {other}")]
+    Overlap {
+        param_type: String,
+        field: String,
+        other: String,
+    },
+    #[error("{param_type}.{field}: the def already has a field with this name")]
+    NameTaken { param_type: String, field: String },
+}
+
+impl Paramdef {
+    /// Merges `fields` into the def, right after their host padding field.
+    ///
+    /// If the field offsets were computed, those of the new fields are set as well. Either all
+    /// fields are merged or none are.
+    ///
+    /// # Errors
+    /// - [`UnofficialFieldError::UnknownHost`] if the host field doesn't exist;
+    /// - [`UnofficialFieldError::OverlapsOfficialField`] if the host field is not `dummy8`
+    ///   padding;
+    /// - [`UnofficialFieldError::OutOfBounds`] if the field doesn't fit in its host;
+    /// - [`UnofficialFieldError::Overlap`] if the field overlaps another unofficial field,
+    ///   either from `fields` or merged previously;
+    /// - [`UnofficialFieldError::NameTaken`] if a field with the same name exists.
+    pub fn merge_unofficial_fields(
+        &mut self,
+        fields: &[UnofficialField],
+    ) -> Result<&mut Self, UnofficialFieldError> {
+        let mut merged: Vec<DefField> = Vec::with_capacity(fields.len());
+        for uf in fields {
+            let param_type = self.param_type.clone();
+            let field = uf.def.name.clone();
+            let host = uf.host.clone();
+
+            let Some(host_field) = self.field_by_name(&uf.host).filter(|h| h.unofficial.is_none())
+            else {
+                return Err(UnofficialFieldError::UnknownHost {
+                    param_type,
+                    field,
+                    host,
+                });
+            };
+            if host_field.field_def.base_type != DefBaseType::Dummy8 {
+                return Err(UnofficialFieldError::OverlapsOfficialField {
+                    param_type,
+                    field,
+                    host,
+                });
+            }
+            let (start, end) = (uf.bit_offset, uf.bit_offset + uf.def.size_bits());
+            if end > host_field.size_bits() {
+                return Err(UnofficialFieldError::OutOfBounds {
+                    param_type,
+                    field,
+                    host,
+                    start,
+                    end,
+                });
+            }
+            if self.field_by_name(&field).is_some()
+                || merged.iter().any(|f| f.field_def.name == field)
+            {
+                return Err(UnofficialFieldError::NameTaken { param_type, field });
+            }
+            let overlapping = self.fields.iter().chain(&merged).find(|f| {
+                f.unofficial.as_ref().is_some_and(|p| {
+                    p.host == uf.host && p.bit_offset < end && start < p.bit_offset + f.size_bits()
+                })
+            });
+            if let Some(other) = overlapping {
+                let other = other.field_def.name.clone();
+                return Err(UnofficialFieldError::Overlap {
+                    param_type,
+                    field,
+                    other,
+                });
+            }
+
+            merged.push(DefField {
+                field_def: uf.def.clone(),
+                display_name: uf.display_name.clone(),
+                enum_name: None,
+                description: uf.description.clone(),
+                edit_flags: None,
+                minimum: None,
+                maximum: None,
+                increment: None,
+                sort_id: None,
+                first_version: host_field.first_version,
+                removed_version: host_field.removed_version,
+                bit_offset: host_field.bit_offset.map(|o| o + uf.bit_offset),
+                unofficial: Some(UnofficialPlacement {
+                    host: uf.host.clone(),
+                    bit_offset: uf.bit_offset,
+                }),
+            });
+        }
+
+        for f in merged {
+            let placement = f.unofficial.as_ref().unwrap();
+            let host_index =
+                self.fields.iter().position(|h| h.field_def.name == placement.host).unwrap();
+            // Keep the unofficial fields of a host after it, sorted by offset
+            let preceding = self.fields[host_index + 1..]
+                .iter()
+                .take_while(|g| {
+                    g.unofficial.as_ref().is_some_and(|p| {
+                        p.host == placement.host && p.bit_offset < placement.bit_offset
+                    })
+                })
+                .count();
+            self.fields.insert(host_index + 1 + preceding, f);
+        }
+        Ok(self)
+    }
+
+    /// Iterates over the unofficial fields carved out of the padding field named `host`.
+    pub fn unofficial_fields_in<'a>(&'a self, host: &'a str) -> impl Iterator<Item = &'a DefField> {
+        self.fields
+            .iter()
+            .filter(move |f| f.unofficial.as_ref().is_some_and(|p| p.host == host))
+    }
+}
+
+impl Paramdex {
+    /// Merges the fields of `overlay` into the loaded defs with
+    /// [`Paramdef::merge_unofficial_fields`]. Overlay entries for param types without a def are
+    /// ignored.
+    ///
+    /// # Errors
+    /// On the first def the overlay could not be merged into. Defs are merged in an unspecified
+    /// order, and the ones merged before the error are left modified.
+    pub fn merge_unofficial_fields(
+        &mut self,
+        overlay: &UnofficialFields,
+    ) -> Result<&mut Self, UnofficialFieldError> {
+        self.clear_versioned_defs();
+        for def in self.ext_defs.values_mut().map(|pair| &mut pair.def) {
+            let fields = overlay.get(&def.param_type);
+            if !fields.is_empty() {
+                def.merge_unofficial_fields(fields)?;
+            }
+        }
+        Ok(self)
+    }
+}
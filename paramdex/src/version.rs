@@ -0,0 +1,121 @@
+//! Regulation versions, which the `FirstVersion` and `RemovedVersion` of paramdef fields refer
+//! to.
+
+use std::{fmt, str::FromStr};
+
+/// A regulation version, e.g. 1.07.1.
+///
+/// Paramdef fields give versions packed as decimal numbers, with (from the most significant
+/// digits) the major version, two digits for the minor version, two for the patch and three for
+/// the revision, which is usually zero. For instance, 1.07.1 is `10701000` and 1.12 is
+/// `11200000`. Versions are ordered like their packed form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegulationVersion(u64);
+
+const MINOR_FACTOR: u64 = 100_000;
+const PATCH_FACTOR: u64 = 1_000;
+const MAJOR_FACTOR: u64 = 100 * MINOR_FACTOR;
+
+impl RegulationVersion {
+    /// Version at which all fields are enabled, i.e. the layout of the latest defs.
+    pub const MAX: Self = Self(u64::MAX);
+
+    /// # Panics
+    /// If `minor` or `patch` is above 99.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        assert!(
+            minor < 100 && patch < 100,
+            "minor version and patch must be below 100"
+        );
+        Self(
+            major as u64 * MAJOR_FACTOR + minor as u64 * MINOR_FACTOR + patch as u64 * PATCH_FACTOR,
+        )
+    }
+
+    /// Version packed like the `FirstVersion` and `RemovedVersion` of paramdef fields.
+    pub const fn from_packed(packed: u64) -> Self {
+        Self(packed)
+    }
+
+    /// Version stored in the paramdef data version of a param file header, which packs it in
+    /// five decimal digits: the major version, then two digits for the minor version and two for
+    /// the patch, e.g. `10701` for 1.07.1.
+    pub const fn from_paramdef_data_version(data_version: u16) -> Self {
+        let v = data_version as u32;
+        Self::new(v / 10_000, v / 100 % 100, v % 100)
+    }
+
+    /// Packed form of the version, e.g. to compute the layout of a
+    /// [`Paramdef`](crate::paramdef::Paramdef) at this version.
+    pub const fn packed(self) -> u64 {
+        self.0
+    }
+
+    pub const fn major(self) -> u64 {
+        self.0 / MAJOR_FACTOR
+    }
+
+    pub const fn minor(self) -> u32 {
+        (self.0 / MINOR_FACTOR % 100) as u32
+    }
+
+    pub const fn patch(self) -> u32 {
+        (self.0 / PATCH_FACTOR % 100) as u32
+    }
+
+    pub const fn revision(self) -> u32 {
+        (self.0 % PATCH_FACTOR) as u32
+    }
+}
+
+impl From<RegulationVersion> for u64 {
+    fn from(version: RegulationVersion) -> Self {
+        version.packed()
+    }
+}
+
+/// Formats the version like `1.07.1`, followed by the revision if it is not zero (`1.07.1.5`).
+impl fmt::Display for RegulationVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}.{}", self.major(), self.minor(), self.patch())?;
+        if self.revision() != 0 {
+            write!(f, ".{}", self.revision())?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing a [`RegulationVersion`] from a string.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid regulation version {0:?}, expected a version like 1.07.1")]
+pub struct ParseVersionError(pub String);
+
+/// Parses versions like `1.07.1`. The patch defaults to zero (`1.12`), and a revision may follow
+/// it (`1.07.1.5`).
+impl FromStr for RegulationVersion {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_owned());
+        let parts = s
+            .split('.')
+            .map(|p| p.bytes().all(|c| c.is_ascii_digit()).then(|| p.parse::<u64>().ok()))
+            .map(Option::flatten)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+        let (major, minor, patch, revision) = match parts[..] {
+            [major, minor] => (major, minor, 0, 0),
+            [major, minor, patch] => (major, minor, patch, 0),
+            [major, minor, patch, revision] => (major, minor, patch, revision),
+            _ => return Err(err()),
+        };
+        if minor >= 100 || patch >= 100 || revision >= PATCH_FACTOR {
+            return Err(err());
+        }
+        let packed = major
+            .checked_mul(MAJOR_FACTOR)
+            .and_then(|v| v.checked_add(minor * MINOR_FACTOR + patch * PATCH_FACTOR + revision))
+            .ok_or_else(err)?;
+        Ok(Self(packed))
+    }
+}
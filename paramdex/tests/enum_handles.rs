@@ -0,0 +1,95 @@
+//! Resolving the enum of a field from its meta with [`paramdex::DefWithMeta::enum_for_field`].
+
+use paramdex::{enums::EnumSource, Paramdex};
+
+const DEF: &str = r#"<PARAMDEF XmlVersion="3">
+  <ParamType>ENUM_HANDLE_TEST_PARAM_ST</ParamType>
+  <DataVersion>1</DataVersion>
+  <BigEndian>False</BigEndian>
+  <Unicode>True</Unicode>
+  <FormatVersion>203</FormatVersion>
+  <Fields>
+    <Field Def="s32 iconId" />
+    <Field Def="u8 spellType" />
+    <Field Def="u8 isEnabled" />
+    <Field Def="s16 mixed" />
+  </Fields>
+</PARAMDEF>"#;
+
+const META: &str = r#"<PARAMMETA XmlVersion="0">
+  <Enums>
+    <Enum Name="ICON_TYPE" type="s32">
+      <Option Value="-1" Name="None" />
+      <Option Value="1" Name="Shield" />
+    </Enum>
+  </Enums>
+  <Field>
+    <iconId Enum="ICON_TYPE" />
+    <spellType ProjectEnum="SPELL_TYPE" />
+    <isEnabled IsBool="" />
+    <mixed Enum="ICON_TYPE" ProjectEnum="SPELL_TYPE" />
+  </Field>
+</PARAMMETA>"#;
+
+const ENUMS: &str = r#"{ "List": [{
+  "DisplayName": "Spell Type",
+  "Name": "SPELL_TYPE",
+  "Description": "",
+  "Options": [
+    { "ID": "0", "Name": "None", "Description": "" },
+    { "ID": "not a number", "Name": "Skipped", "Description": "" },
+    { "ID": "2", "Name": "Incantation", "Description": "" }
+  ]
+}] }"#;
+
+fn paramdex() -> Paramdex {
+    let mut paramdex = Paramdex::from_sources([("EnumHandleTestParam", DEF)]).unwrap();
+    paramdex
+        .add_meta_xml("EnumHandleTestParam", META)
+        .unwrap()
+        .add_enums_json(ENUMS)
+        .unwrap();
+    paramdex
+}
+
+#[test]
+fn enum_sources() {
+    let paramdex = paramdex();
+    let def = paramdex.def_with_meta("EnumHandleTestParam").unwrap();
+
+    let icon = def.enum_for_field("iconId", &paramdex).unwrap();
+    assert_eq!(icon.source(), EnumSource::Meta);
+    assert_eq!(icon.name(), "ICON_TYPE");
+    assert_eq!(icon.name_of(-1), Some("None"));
+    assert_eq!(icon.value_of("Shield"), Some(1));
+
+    // Options whose ID is not an integer are skipped
+    let spell = def.enum_for_field("spellType", &paramdex).unwrap();
+    assert_eq!(spell.source(), EnumSource::Project);
+    assert_eq!(
+        spell.options().collect::<Vec<_>>(),
+        [(0, "None"), (2, "Incantation")]
+    );
+    assert_eq!(spell.value_of("Skipped"), None);
+
+    assert!(def.enum_for_field("isEnabled", &paramdex).is_none());
+    assert!(def.enum_for_field("missing", &paramdex).is_none());
+}
+
+#[test]
+fn meta_enum_beats_project_enum() {
+    let mut paramdex = paramdex();
+    let def = paramdex.def_with_meta("EnumHandleTestParam").unwrap();
+
+    let mixed = def.enum_for_field("mixed", &paramdex).unwrap();
+    assert_eq!(mixed.source(), EnumSource::Meta);
+    assert_eq!(mixed.name(), "ICON_TYPE");
+
+    // The project enum is used if the meta enum doesn't exist
+    let meta = META.replace(r#"<mixed Enum="ICON_TYPE""#, r#"<mixed Enum="MISSING""#);
+    paramdex.add_meta_xml("EnumHandleTestParam", &meta).unwrap();
+    let def = paramdex.def_with_meta("EnumHandleTestParam").unwrap();
+    let mixed = def.enum_for_field("mixed", &paramdex).unwrap();
+    assert_eq!(mixed.source(), EnumSource::Project);
+    assert_eq!(mixed.name(), "SPELL_TYPE");
+}
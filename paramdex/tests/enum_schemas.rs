@@ -0,0 +1,96 @@
+//! Loading project enums stored with the current, legacy and XML schemas.
+
+use paramdex::{enums::ProjectEnum, Paramdex, ParamdexLoadError};
+
+/// Returns the `(id, value, name, description)` of the options of an enum.
+fn options(e: &ProjectEnum) -> Vec<(&str, Option<i64>, &str, &str)> {
+    (e.options.iter())
+        .map(|o| {
+            (
+                o.id.as_str(),
+                o.value,
+                o.name.as_str(),
+                o.description.as_str(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn current_schema() {
+    let mut paramdex = Paramdex::new("testdata");
+    paramdex.load_enums().unwrap();
+
+    let spell_type = paramdex.project_enum("SPELL_TYPE").unwrap();
+    assert_eq!(spell_type.display_name, "Spell Type");
+    assert_eq!(spell_type.description, "Type of spell.");
+    assert_eq!(options(spell_type)[1], ("1", Some(1), "Sorcery", ""));
+}
+
+#[test]
+fn legacy_schema() {
+    let mut paramdex = Paramdex::new("testdata/enum_schemas/legacy");
+    paramdex.load_enums().unwrap();
+
+    let names: Vec<_> = paramdex.project_enums().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["ATTACK_ELEMENT", "SPELL_TYPE"]);
+
+    let spell_type = paramdex.project_enum("SPELL_TYPE").unwrap();
+    assert_eq!(spell_type.display_name, "SPELL_TYPE");
+    assert_eq!(spell_type.description, "");
+    assert_eq!(
+        options(spell_type),
+        [
+            ("0", Some(0), "None", ""),
+            ("1", Some(1), "Sorcery", ""),
+            ("2", Some(2), "Incantation", ""),
+        ]
+    );
+    // Options are sorted by value, then by ID if it is not an integer
+    let element = paramdex.project_enum("ATTACK_ELEMENT").unwrap();
+    assert_eq!(
+        options(element),
+        [
+            ("-1", Some(-1), "None", ""),
+            ("10", Some(10), "Magic", ""),
+            ("unused", None, "Unused", ""),
+        ]
+    );
+}
+
+#[test]
+fn xml_schema() {
+    let mut paramdex = Paramdex::new("testdata/enum_schemas/xml");
+    paramdex.load_enums_xml().unwrap();
+
+    let spell_type = paramdex.project_enum("SPELL_TYPE").unwrap();
+    assert_eq!(spell_type.display_name, "Spell Type");
+    assert_eq!(spell_type.description, "Type of spell.");
+    assert_eq!(
+        options(spell_type),
+        [
+            ("0", Some(0), "None", ""),
+            ("1", Some(1), "Sorcery", ""),
+            ("2", Some(2), "Incantation", "Faith based spell."),
+        ]
+    );
+    let element = paramdex.project_enum("ATTACK_ELEMENT").unwrap();
+    assert_eq!(element.display_name, "ATTACK_ELEMENT");
+    assert_eq!(options(element), [("-1", Some(-1), "None", "")]);
+
+    // The enums are not mistaken for a def
+    paramdex.load_defs().unwrap();
+    let param_types: Vec<_> = paramdex.defs().map(|d| d.param_type.as_str()).collect();
+    assert_eq!(param_types, ["ENUM_TEST_PARAM_ST"]);
+}
+
+#[test]
+fn unknown_schema() {
+    let mut paramdex = Paramdex::new("testdata/enum_schemas/invalid");
+    let err = paramdex.load_enums().err().unwrap();
+    assert!(matches!(err, ParamdexLoadError::UnknownEnumsSchema { .. }));
+
+    let message = err.to_string();
+    assert!(message.starts_with("Enums.json matches no known schema (ProjectEnums list: "));
+    assert!(message.contains("; legacy enum map: "));
+}
@@ -0,0 +1,100 @@
+//! Tests of paramdex loading, using the embedded fixture paramdex.
+
+use paramdex::{enums::EnumSource, paramdef::Paramdef, DefWithMeta, Paramdex};
+
+#[test]
+fn fixture_loads() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(u64::MAX);
+
+    let mut sizes: Vec<_> =
+        paramdex.defs().map(|d| (d.param_type.as_str(), d.size_bytes)).collect();
+    sizes.sort();
+    assert_eq!(
+        sizes,
+        [
+            ("ARRAY_TEST_PARAM_ST", Some(56)),
+            ("BITFIELD_TEST_PARAM_ST", Some(16)),
+            ("ENUM_TEST_PARAM_ST", Some(8)),
+        ]
+    );
+    let meta = paramdex.def_with_meta("BitfieldTestParam").unwrap().meta.as_ref().unwrap();
+    assert!(meta.fields["flagA"].is_bool);
+}
+
+#[test]
+fn field_meta() {
+    let paramdex = Paramdex::fixture();
+    let def = paramdex.def_with_meta("EnumTestParam").unwrap();
+    let field = |name| def.def.field_by_name(name).unwrap();
+
+    let icon = paramdex.field_meta(def, field("iconId")).unwrap();
+    assert_eq!(icon.display_name, "Icon");
+    assert_eq!(icon.wiki, Some("Icon shown in menus."));
+    assert_eq!(icon.r#enum.unwrap().source(), EnumSource::Meta);
+    assert_eq!(
+        icon.enum_options(),
+        [(-1, "None"), (0, "Sword"), (1, "Shield")]
+    );
+
+    // Project enums are only resolved by the paramdex
+    let spell = paramdex.field_meta(def, field("spellType")).unwrap();
+    assert_eq!(spell.r#enum.unwrap().source(), EnumSource::Project);
+    assert_eq!(spell.enum_options()[2], (2, "Incantation"));
+    let unresolved = def.field_meta(field("spellType")).unwrap();
+    assert!(unresolved.r#enum.is_none());
+    assert_eq!(unresolved.project_enum, Some("SPELL_TYPE"));
+
+    let enabled = def.field_meta(field("isEnabled")).unwrap();
+    assert!(enabled.is_bool);
+    assert!(enabled.enum_options().is_empty());
+    let mixed = paramdex.field_meta(def, field("mixed")).unwrap();
+    assert_eq!(mixed.r#enum.unwrap().name(), "ICON_TYPE");
+}
+
+#[test]
+fn field_meta_fallbacks() {
+    let paramdex = Paramdex::fixture();
+    let def: Paramdef = quick_xml::de::from_str(
+        r#"<PARAMDEF><ParamType>TEST_ST</ParamType><DataVersion>1</DataVersion>
+        <BigEndian>False</BigEndian><Unicode>True</Unicode><FormatVersion>203</FormatVersion>
+        <Fields>
+          <Field Def="u8 spellType"><DisplayName>Spell</DisplayName></Field>
+          <Field Def="u8 renamed"><DisplayName>Old Name</DisplayName></Field>
+          <Field Def="u8 noAltName"><DisplayName>No Alt Name</DisplayName></Field>
+          <Field Def="u8 missing" />
+        </Fields></PARAMDEF>"#,
+    )
+    .unwrap();
+    let meta = quick_xml::de::from_str(
+        r#"<PARAMMETA XmlVersion="0"><Self />
+        <Field>
+          <oldName AltName="Old Name" ProjectEnum="SPELL_TYPE" />
+          <noAltName AltName="" />
+        </Field></PARAMMETA>"#,
+    )
+    .unwrap();
+    let def = DefWithMeta {
+        def,
+        meta: Some(meta),
+    };
+    let field = |name| def.def.field_by_name(name).unwrap();
+
+    // Found through the `@AltName` matching the display name of the def field
+    let renamed = paramdex.field_meta(&def, field("renamed")).unwrap();
+    assert_eq!(renamed.name, "renamed");
+    assert_eq!(renamed.display_name, "Old Name");
+    assert_eq!(renamed.r#enum.unwrap().name(), "SPELL_TYPE");
+
+    // An empty `@AltName` falls back to the def display name
+    let no_alt_name = def.field_meta(field("noAltName")).unwrap();
+    assert_eq!(no_alt_name.display_name, "No Alt Name");
+
+    assert!(paramdex.field_meta(&def, field("missing")).is_none());
+    assert!(paramdex.field_meta(&def, field("spellType")).is_none());
+    let no_meta = DefWithMeta {
+        def: def.def.clone(),
+        meta: None,
+    };
+    assert!(no_meta.field_meta(field("renamed")).is_none());
+}
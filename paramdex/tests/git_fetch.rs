@@ -0,0 +1,100 @@
+//! Validation of paramdex checkouts by [`ParamdexGitFetch`], and re-fetching incomplete cached
+//! checkouts.
+
+use std::path::{Path, PathBuf};
+
+use paramdex::git_fetch::{ParamdexFetchError, ParamdexGitFetch};
+
+const META_FILE_NAME: &str = ".paramdex_fetch_meta.json";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("paramdex-{name}-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Fetch from a repo which doesn't exist, so that any attempt to fetch fails.
+fn unreachable_fetch(dir: &Path) -> ParamdexGitFetch {
+    let mut fetch = ParamdexGitFetch::new(dir.join("missing-repo.git").to_str().unwrap());
+    fetch.paramdex_path("Paramdex").games(["ER", "DS3"]);
+    fetch
+}
+
+/// Creates the checkout of a game, with a def in `Defs` if `with_defs` is set.
+fn create_game(checkout: &Path, game: &str, with_defs: bool) {
+    let game_dir = checkout.join("Paramdex").join(game);
+    std::fs::create_dir_all(game_dir.join("Defs")).unwrap();
+    std::fs::create_dir_all(game_dir.join("Meta")).unwrap();
+    std::fs::write(game_dir.join("Meta").join("TestParam.xml"), "<PARAMMETA/>").unwrap();
+    if with_defs {
+        std::fs::write(game_dir.join("Defs").join("TestParam.xml"), "<PARAMDEF/>").unwrap();
+    }
+}
+
+fn assert_incomplete(result: Result<(), ParamdexFetchError>, game: &str, path: &Path) {
+    match result {
+        Err(ParamdexFetchError::IncompleteCheckout { game: g, path: p }) => {
+            assert_eq!(g, game);
+            assert_eq!(p, path);
+        }
+        other => panic!("expected an incomplete checkout of {game}, got {other:?}"),
+    }
+}
+
+#[test]
+fn validate_checkouts() {
+    let dir = temp_dir("validate");
+    let checkout = dir.join("checkout");
+    let fetch = unreachable_fetch(&dir);
+
+    // Meta but no Defs folder
+    create_game(&checkout, "ER", true);
+    std::fs::create_dir_all(checkout.join("Paramdex/DS3/Meta")).unwrap();
+    let ds3_defs = checkout.join("Paramdex").join("DS3").join("Defs");
+    assert_incomplete(fetch.validate(&checkout), "DS3", &ds3_defs);
+
+    // Empty Defs folder
+    create_game(&checkout, "DS3", false);
+    assert_incomplete(fetch.validate(&checkout), "DS3", &ds3_defs);
+
+    create_game(&checkout, "DS3", true);
+    fetch.validate(&checkout).unwrap();
+
+    // Nothing checked out at all
+    let er_defs = dir.join("empty").join("Paramdex").join("ER").join("Defs");
+    assert_incomplete(fetch.validate(dir.join("empty")), "ER", &er_defs);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fetch_cached_refetches_incomplete_checkouts() {
+    let dir = temp_dir("fetch-cached");
+    let checkout = dir.join("checkout");
+    let fetch = unreachable_fetch(&dir);
+
+    // A complete checkout matching the cache meta is used as is
+    create_game(&checkout, "ER", true);
+    create_game(&checkout, "DS3", true);
+    std::fs::write(
+        checkout.join(META_FILE_NAME),
+        serde_json::to_vec(&fetch).unwrap(),
+    )
+    .unwrap();
+    let root = fetch.fetch_cached(&checkout).unwrap();
+    assert_eq!(
+        root,
+        std::fs::canonicalize(checkout.join("Paramdex")).unwrap()
+    );
+
+    // A checkout missing the defs of a game is fetched again, even if the meta matches
+    std::fs::remove_dir_all(checkout.join("Paramdex").join("DS3").join("Defs")).unwrap();
+    let result = fetch.fetch_cached(&checkout);
+    assert!(
+        matches!(result, Err(ParamdexFetchError::CommandFailed { .. })),
+        "expected a failed fetch, got {result:?}"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
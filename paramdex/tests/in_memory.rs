@@ -0,0 +1,79 @@
+//! Building a [`Paramdex`] from embedded XML and JSON, without touching the filesystem.
+
+use paramdex::{enums::EnumSource, Paramdex, ParamdexLoadError};
+
+const ARRAY_DEF: &str = include_str!("../testdata/Defs/ArrayTestParam.xml");
+const ENUM_DEF: &str = include_str!("../testdata/Defs/EnumTestParam.xml");
+const ENUM_META: &str = include_str!("../testdata/Meta/EnumTestParam.xml");
+const ENUMS: &str = include_str!("../testdata/Enums.json");
+
+#[test]
+fn embedded_defs() {
+    let mut paramdex =
+        Paramdex::from_sources([("ArrayTestParam", ARRAY_DEF), ("EnumTestParam", ENUM_DEF)])
+            .unwrap();
+    paramdex
+        .add_meta_xml("EnumTestParam", ENUM_META)
+        .unwrap()
+        .add_enums_json(ENUMS)
+        .unwrap()
+        .compute_def_layouts(u64::MAX);
+
+    let mut sizes: Vec<_> =
+        paramdex.defs().map(|d| (d.param_type.as_str(), d.size_bytes)).collect();
+    sizes.sort();
+    assert_eq!(
+        sizes,
+        [
+            ("ARRAY_TEST_PARAM_ST", Some(56)),
+            ("ENUM_TEST_PARAM_ST", Some(8))
+        ]
+    );
+
+    let with_meta = paramdex.def_with_meta("EnumTestParam").unwrap();
+    assert_eq!(
+        with_meta.def.field_by_name("mixed").unwrap().bit_offset,
+        Some(48)
+    );
+    let spell_type = with_meta.enum_for_field("spellType", &paramdex).unwrap();
+    assert_eq!(spell_type.name(), "SPELL_TYPE");
+    assert!(matches!(spell_type.source(), EnumSource::Project));
+    assert!(paramdex.def_with_meta("ArrayTestParam").unwrap().meta.is_none());
+
+    // Metas of missing defs are ignored, like when loading them from a directory
+    paramdex.add_meta_xml("MissingParam", ENUM_META).unwrap();
+    assert!(paramdex.def_with_meta("MissingParam").is_none());
+}
+
+#[test]
+fn defs_are_replaced() {
+    let mut paramdex = Paramdex::from_sources([("TestParam", ARRAY_DEF)]).unwrap();
+    paramdex
+        .add_def_xml("TestParam".to_owned(), ENUM_DEF)
+        .unwrap()
+        .compute_def_layouts(u64::MAX);
+    let def = &paramdex.def_with_meta("TestParam").unwrap().def;
+    assert_eq!(def.param_type, "ENUM_TEST_PARAM_ST");
+    assert_eq!(paramdex.defs().count(), 1);
+}
+
+#[test]
+fn invalid_sources() {
+    let truncated = &ENUM_DEF[..ENUM_DEF.len() / 2];
+    assert!(matches!(
+        Paramdex::from_sources([("EnumTestParam", truncated)]),
+        Err(ParamdexLoadError::XmlError(_))
+    ));
+
+    let mut paramdex = Paramdex::from_sources([("EnumTestParam", ENUM_DEF)]).unwrap();
+    assert!(matches!(
+        paramdex.add_meta_xml("EnumTestParam", "<PARAMMETA>"),
+        Err(ParamdexLoadError::XmlError(_))
+    ));
+    assert!(matches!(
+        paramdex.add_enums_json("[1, 2]"),
+        Err(ParamdexLoadError::UnknownEnumsSchema { .. })
+    ));
+    // A failed addition leaves the paramdex as it was
+    assert!(paramdex.def_with_meta("EnumTestParam").unwrap().meta.is_none());
+}
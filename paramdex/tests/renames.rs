@@ -0,0 +1,106 @@
+use paramdex::{
+    renames::{detect_renames, RenameCandidate, RenameMap},
+    Paramdex,
+};
+
+const RENAMES: &str = r#"
+[ENUM_TEST_PARAM_ST]
+iconTyp = "iconType"
+iconType = "iconId"
+spelType = "spellType"
+
+[ARRAY_TEST_PARAM_ST]
+a = "b"
+b = "a"
+"#;
+
+#[test]
+fn builtin_maps_parse() {
+    for game in ["DS3", "ER", "AC6"] {
+        assert!(RenameMap::builtin(game).is_some(), "{game}");
+    }
+    assert!(RenameMap::builtin("DS2").is_none());
+}
+
+#[test]
+fn resolve() {
+    let mut renames = RenameMap::from_toml(RENAMES).unwrap();
+    assert_eq!(
+        renames.resolve("ENUM_TEST_PARAM_ST", "iconTyp"),
+        Some("iconId")
+    );
+    assert_eq!(renames.resolve("ENUM_TEST_PARAM_ST", "iconId"), None);
+    assert_eq!(renames.resolve("ARRAY_TEST_PARAM_ST", "a"), None);
+    assert_eq!(renames.resolve("OTHER_PARAM_ST", "iconTyp"), None);
+
+    // User-provided renames take precedence over the builtin ones
+    let mut user = RenameMap::default();
+    user.insert("ENUM_TEST_PARAM_ST", "spelType", "mixed");
+    renames.extend(user);
+    assert_eq!(
+        renames.resolve("ENUM_TEST_PARAM_ST", "spelType"),
+        Some("mixed")
+    );
+}
+
+#[test]
+fn field_lookup_fallback() {
+    let paramdex = Paramdex::fixture();
+    let def = &paramdex.def_with_meta("EnumTestParam").unwrap().def;
+    let renames = RenameMap::from_toml(RENAMES).unwrap();
+
+    let direct = def.resolve_field("spellType", &renames).unwrap();
+    assert_eq!(direct.field.field_def.name, "spellType");
+    assert!(!direct.via_rename);
+
+    let renamed = def.resolve_field("iconTyp", &renames).unwrap();
+    assert_eq!(renamed.field.field_def.name, "iconId");
+    assert!(renamed.via_rename);
+
+    assert!(def.resolve_field("missing", &renames).is_none());
+    assert!(def.field_by_name("iconTyp").is_none());
+}
+
+#[test]
+fn detect() {
+    // Second version of the fixture, with renamed fields
+    let dir = std::env::temp_dir().join(format!("paramdex-renames-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("Defs")).unwrap();
+    let defs = [
+        (
+            "BitfieldTestParam.xml",
+            include_str!("../testdata/Defs/BitfieldTestParam.xml")
+                .replace("byteVal", "byteValue")
+                // Type changed as well, so not a rename
+                .replace("u16 shortVal", "s16 signedVal"),
+        ),
+        (
+            "EnumTestParam.xml",
+            include_str!("../testdata/Defs/EnumTestParam.xml")
+                // Names shuffled between fields at different offsets are not detected
+                .replace("u8 spellType", "u8 isEnabled2")
+                .replace("u8 isEnabled", "u8 magicType")
+                .replace("u8 magicType2", "u8 isEnabled")
+                .replace("s32 iconId", "s32 iconType"),
+        ),
+    ];
+    for (name, contents) in defs {
+        std::fs::write(dir.join("Defs").join(name), contents).unwrap();
+    }
+    let mut new = Paramdex::new(&dir);
+    new.load_defs().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let candidate = |param_type: &str, old_name: &str, new_name: &str| RenameCandidate {
+        param_type: param_type.to_owned(),
+        old_name: old_name.to_owned(),
+        new_name: new_name.to_owned(),
+    };
+    assert_eq!(
+        detect_renames(&Paramdex::fixture(), &new),
+        [
+            candidate("BITFIELD_TEST_PARAM_ST", "byteVal", "byteValue"),
+            candidate("ENUM_TEST_PARAM_ST", "iconId", "iconType"),
+        ]
+    );
+}
@@ -0,0 +1,73 @@
+//! Writing defs back to XML with [`Paramdex::save_defs`].
+
+use paramdex::{
+    paramdef::{DefBaseType, DefTypeModifier, Paramdef},
+    unofficial::UnofficialFields,
+    Paramdex,
+};
+
+fn reload(def: &Paramdef) -> Paramdef {
+    quick_xml::de::from_str(&def.to_xml().unwrap()).unwrap()
+}
+
+#[test]
+fn defs_roundtrip() {
+    let paramdex = Paramdex::fixture();
+    for name in ["ArrayTestParam", "BitfieldTestParam", "EnumTestParam"] {
+        let def = &paramdex.def_with_meta(name).unwrap().def;
+        assert_eq!(&reload(def), def, "{name}");
+    }
+
+    let def = &paramdex.def_with_meta("BitfieldTestParam").unwrap().def;
+    let xml = def.to_xml().unwrap();
+    let header = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<PARAMDEF XmlVersion=\"3\">";
+    assert!(xml.starts_with(header));
+    assert!(xml.contains("<BigEndian>False</BigEndian>"));
+    assert!(xml.contains("<Field Def=\"u8 flagC:4\" FirstVersion=\"10300\"/>"));
+    assert!(xml.contains("<Field Def=\"u16 shortVal\" RemovedVersion=\"10500\"/>"));
+    assert!(xml.contains("<Field Def=\"dummy8 endPad[3]\"/>"));
+}
+
+#[test]
+fn default_values() {
+    let paramdex = Paramdex::fixture();
+    let def = &paramdex.def_with_meta("EnumTestParam").unwrap().def;
+    let field_def = &def.field_by_name("isEnabled").unwrap().field_def;
+    assert_eq!(field_def.default_value.as_deref(), Some("1"));
+    assert_eq!(field_def.to_string(), "u8 isEnabled = 1");
+
+    let reloaded = reload(def);
+    let field_def = &reloaded.field_by_name("isEnabled").unwrap().field_def;
+    assert_eq!(field_def.base_type, DefBaseType::U8);
+    assert_eq!(field_def.modifier, DefTypeModifier::None);
+    assert_eq!(field_def.default_value.as_deref(), Some("1"));
+}
+
+#[test]
+fn save_and_load() {
+    const OVERLAY: &str = r#"
+        [[BITFIELD_TEST_PARAM_ST]]
+        host = "endPad"
+        bit_offset = 0
+        def = "u8 unkFlag:1"
+    "#;
+    let mut paramdex = Paramdex::fixture();
+    paramdex
+        .merge_unofficial_fields(&UnofficialFields::from_toml(OVERLAY).unwrap())
+        .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("paramdex-save-defs-{}", std::process::id()));
+    paramdex.save_defs(&dir).unwrap();
+    let mut saved = Paramdex::new(&dir);
+    saved.load_defs().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let fixture = Paramdex::fixture();
+    assert_eq!(saved.defs().count(), 3);
+    for def in fixture.defs() {
+        let name = def.param_type.as_str();
+        let saved_def = saved.defs().find(|d| d.param_type == name).unwrap();
+        // Unofficial fields are left out
+        assert_eq!(saved_def, def, "{name}");
+    }
+}
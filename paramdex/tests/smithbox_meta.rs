@@ -0,0 +1,125 @@
+//! Parsing of the attributes found in Smithbox meta files, e.g. `@Refs` and `@FmgRef`, against a
+//! fixture laid out like a Smithbox `SpEffectParam` meta.
+
+use paramdex::meta::{ParamMeta, ParamRef, RefCondition};
+
+const SP_EFFECT_META: &str = include_str!("../testdata/smithbox/SpEffectParam.xml");
+
+fn meta() -> ParamMeta {
+    quick_xml::de::from_str(SP_EFFECT_META).unwrap()
+}
+
+fn conditional(name: &str, field: &str, value: i64) -> ParamRef {
+    ParamRef {
+        name: name.to_owned(),
+        condition: Some(RefCondition {
+            field: field.to_owned(),
+            value,
+        }),
+    }
+}
+
+#[test]
+fn self_attributes() {
+    let meta = meta();
+    assert_eq!(
+        meta.self_desc(),
+        Some("Defines the special effects applied to characters.")
+    );
+    assert!(meta.self_info.consecutive_ids);
+    assert!(!meta.self_info.row0_dummy);
+    assert_eq!(meta.self_info.offset_size, None);
+
+    let offsets: ParamMeta = quick_xml::de::from_str(
+        r#"<PARAMMETA XmlVersion="0"><Self Row0Dummy="" OffsetSize="100" FixedOffset="-1" />
+        <Field /></PARAMMETA>"#,
+    )
+    .unwrap();
+    assert!(offsets.self_desc().is_none() && offsets.self_info.row0_dummy);
+    assert_eq!(
+        (
+            offsets.self_info.offset_size,
+            offsets.self_info.fixed_offset
+        ),
+        (Some(100), Some(-1))
+    );
+}
+
+#[test]
+fn field_refs() {
+    let meta = meta();
+    let field = |name: &str| &meta.fields[name];
+
+    assert_eq!(
+        field("replaceSpEffectId").refs,
+        [ParamRef::unconditional("SpEffectParam")]
+    );
+    assert_eq!(
+        field("atkParamId").refs,
+        [
+            conditional("AtkParam_Pc", "atkParamType", 0),
+            conditional("AtkParam_Npc", "atkParamType", 1),
+            conditional("Bullet", "atkParamType", 2),
+        ]
+    );
+    assert_eq!(
+        field("textId").fmg_refs,
+        [
+            ParamRef::unconditional("Title_Goods"),
+            conditional("EventTextForTalk", "textType", 1),
+        ]
+    );
+    assert_eq!(
+        field("attackElementCorrectId").vref.as_deref(),
+        Some("AttackElementCorrect")
+    );
+    assert_eq!(
+        field("spCategory").param_ref2.as_deref(),
+        Some("SpEffectSetParam")
+    );
+    assert!(field("effectEndurance").refs.is_empty());
+}
+
+#[test]
+fn field_tags() {
+    let meta = meta();
+    let field = |name: &str| &meta.fields[name];
+
+    assert!(field("conditionHp").separator);
+    assert!(field("particleId").particle_alias);
+    assert!(field("soundId").sound_alias);
+    assert!(field("eventFlagId").flag_alias);
+    assert!(field("effectTargetSelf").is_bool);
+    assert!(!field("iconId").separator && !field("iconId").particle_alias);
+    // Unknown attributes such as `@IconConfig` and `@Padding` are ignored
+    assert_eq!(field("iconId").alt_name, "Icon ID");
+    assert_eq!(field("pad1").alt_name, "");
+    assert_eq!(
+        field("saveCategory").r#enum.as_deref(),
+        Some("SP_EFFECT_SAVE_CATEGORY")
+    );
+    assert_eq!(
+        field("stateInfo").project_enum.as_deref(),
+        Some("SP_EFFECT_TYPE")
+    );
+}
+
+#[test]
+fn ref_syntax() {
+    assert_eq!(
+        ParamRef::parse_list(" Magic , NpcParam( npcType = -2 ),"),
+        Ok(vec![
+            ParamRef::unconditional("Magic"),
+            conditional("NpcParam", "npcType", -2)
+        ])
+    );
+    assert_eq!(ParamRef::parse_list(""), Ok(vec![]));
+    for invalid in ["(a=1)", "A(a=1", "A(a)", "A(=1)", "A(a=x)"] {
+        assert!(invalid.parse::<ParamRef>().is_err(), "{invalid}");
+    }
+
+    let invalid: Result<ParamMeta, _> = quick_xml::de::from_str(
+        r#"<PARAMMETA XmlVersion="0"><Field><a Refs="A(a=x)" /></Field></PARAMMETA>"#,
+    );
+    assert!(invalid.is_err());
+}
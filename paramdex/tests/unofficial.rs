@@ -0,0 +1,161 @@
+use paramdex::{
+    paramdef::Paramdef,
+    unofficial::{UnofficialFieldError, UnofficialFields, UnofficialPlacement},
+    Paramdex,
+};
+
+const OVERLAY: &str = r#"
+[[BITFIELD_TEST_PARAM_ST]]
+host = "endPad"
+bit_offset = 8
+def = "s16 discoveredId"
+display_name = "Discovered ID"
+
+[[BITFIELD_TEST_PARAM_ST]]
+host = "endPad"
+bit_offset = 0
+def = "u8 discoveredFlag:1"
+
+[[UNKNOWN_PARAM_ST]]
+host = "pad"
+bit_offset = 0
+def = "u8 ignored"
+"#;
+
+fn bitfield_def(paramdex: &Paramdex) -> &Paramdef {
+    &paramdex.def_with_meta("BitfieldTestParam").unwrap().def
+}
+
+fn merge(toml: &str) -> Result<Paramdef, UnofficialFieldError> {
+    let paramdex = Paramdex::fixture();
+    let mut def = bitfield_def(&paramdex).clone();
+    let overlay = UnofficialFields::from_toml(toml).unwrap();
+    def.merge_unofficial_fields(overlay.get("BITFIELD_TEST_PARAM_ST"))?;
+    Ok(def)
+}
+
+fn overlay_with(host: &str, bit_offset: usize, def: &str) -> String {
+    format!(
+        "[[BITFIELD_TEST_PARAM_ST]]\nhost = {host:?}\nbit_offset = {bit_offset}\ndef = {def:?}\n"
+    )
+}
+
+#[test]
+fn merged_fields_are_laid_out() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex
+        .merge_unofficial_fields(&UnofficialFields::from_toml(OVERLAY).unwrap())
+        .unwrap()
+        .compute_def_layouts(u64::MAX);
+    let def = bitfield_def(&paramdex);
+
+    // Unofficial fields don't take space of their own
+    assert_eq!(def.size_bytes, Some(16));
+    let names: Vec<_> = def.fields.iter().map(|f| f.field_def.name.as_str()).collect();
+    assert_eq!(
+        names[names.len() - 3..],
+        ["endPad", "discoveredFlag", "discoveredId"]
+    );
+
+    let end_pad = def.field_by_name("endPad").unwrap().bit_offset.unwrap();
+    let id = def.field_by_name("discoveredId").unwrap();
+    assert_eq!(id.bit_offset, Some(end_pad + 8));
+    assert_eq!(id.display_name.as_deref(), Some("Discovered ID"));
+    assert_eq!(
+        id.unofficial,
+        Some(UnofficialPlacement {
+            host: "endPad".to_owned(),
+            bit_offset: 8
+        })
+    );
+    assert_eq!(
+        def.field_by_name("discoveredFlag").unwrap().bit_offset,
+        Some(end_pad)
+    );
+    assert!(def.field_by_name("endPad").unwrap().unofficial.is_none());
+
+    let carved: Vec<_> = def.unofficial_fields_in("endPad").map(|f| &f.field_def.name).collect();
+    assert_eq!(carved, ["discoveredFlag", "discoveredId"]);
+}
+
+#[test]
+fn offsets_follow_layout_version() {
+    let paramdex = Paramdex::fixture();
+    let mut def = bitfield_def(&paramdex).clone();
+    def.merge_unofficial_fields(
+        UnofficialFields::from_toml(OVERLAY).unwrap().get("BITFIELD_TEST_PARAM_ST"),
+    )
+    .unwrap();
+
+    for version in [10400, u64::MAX] {
+        def.compute_field_offsets(version);
+        let end_pad = def.field_by_name("endPad").unwrap().bit_offset.unwrap();
+        let id = def.field_by_name("discoveredId").unwrap().bit_offset.unwrap();
+        assert_eq!(id, end_pad + 8, "version {version}");
+    }
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    assert!(matches!(
+        merge(&overlay_with("noSuchField", 0, "u8 x")),
+        Err(UnofficialFieldError::UnknownHost { .. })
+    ));
+    assert!(matches!(
+        merge(&overlay_with("byteVal", 0, "u8 x:1")),
+        Err(UnofficialFieldError::OverlapsOfficialField { .. })
+    ));
+    assert!(matches!(
+        merge(&overlay_with("endPad", 16, "s16 x")),
+        Err(UnofficialFieldError::OutOfBounds {
+            start: 16,
+            end: 32,
+            ..
+        })
+    ));
+    assert!(matches!(
+        merge(&overlay_with("pad", 2, "u8 x:3")),
+        Err(UnofficialFieldError::OutOfBounds { .. })
+    ));
+    assert!(matches!(
+        merge(&overlay_with("endPad", 0, "u8 byteVal")),
+        Err(UnofficialFieldError::NameTaken { .. })
+    ));
+
+    let overlapping = overlay_with("endPad", 0, "u16 a") + &overlay_with("endPad", 15, "u8 b:1");
+    assert_eq!(
+        merge(&overlapping).unwrap_err(),
+        UnofficialFieldError::Overlap {
+            param_type: "BITFIELD_TEST_PARAM_ST".to_owned(),
+            field: "b".to_owned(),
+            other: "a".to_owned(),
+        }
+    );
+    // Unofficial fields can't host other ones
+    let nested = overlay_with("endPad", 0, "u16 a") + &overlay_with("a", 0, "u8 b");
+    assert!(matches!(
+        merge(&nested),
+        Err(UnofficialFieldError::UnknownHost { .. })
+    ));
+}
+
+#[test]
+fn failed_merge_leaves_def_unchanged() {
+    let paramdex = Paramdex::fixture();
+    let mut def = bitfield_def(&paramdex).clone();
+    let n_fields = def.fields.len();
+
+    let overlay = overlay_with("endPad", 0, "u8 a") + &overlay_with("byteVal", 0, "u8 b");
+    let overlay = UnofficialFields::from_toml(&overlay).unwrap();
+    assert!(def.merge_unofficial_fields(overlay.get("BITFIELD_TEST_PARAM_ST")).is_err());
+    assert_eq!(def.fields.len(), n_fields);
+
+    // Merging separately is the same as merging at once, and also checks previous merges
+    let first = UnofficialFields::from_toml(&overlay_with("endPad", 8, "u8 a")).unwrap();
+    let second = UnofficialFields::from_toml(&overlay_with("endPad", 12, "u8 b:4")).unwrap();
+    def.merge_unofficial_fields(first.get("BITFIELD_TEST_PARAM_ST")).unwrap();
+    assert!(matches!(
+        def.merge_unofficial_fields(second.get("BITFIELD_TEST_PARAM_ST")),
+        Err(UnofficialFieldError::Overlap { .. })
+    ));
+}
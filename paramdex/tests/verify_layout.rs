@@ -0,0 +1,223 @@
+use paramdex::{
+    paramdef::{LayoutMismatch, Paramdef, PlacedField},
+    Paramdex, VerifyLayoutError,
+};
+
+fn def(fields: &str) -> Paramdef {
+    let xml = format!(
+        "<PARAMDEF XmlVersion=\"3\"><ParamType>TEST_PARAM_ST</ParamType>\
+        <DataVersion>1</DataVersion><BigEndian>False</BigEndian><Unicode>True</Unicode>\
+        <FormatVersion>203</FormatVersion><Fields>{fields}</Fields></PARAMDEF>"
+    );
+    quick_xml::de::from_str(&xml).unwrap()
+}
+
+fn placed(name: &str, bit_offset: usize, size_bits: usize) -> PlacedField {
+    PlacedField {
+        name: name.to_owned(),
+        bit_offset,
+        size_bits,
+    }
+}
+
+#[test]
+fn bitfield_spill() {
+    // flagC doesn't fit in the byte of flagA and flagB, so it starts a new one
+    let mut def = def(r#"
+        <Field Def="u8 flagA:3" />
+        <Field Def="u8 flagB:3" />
+        <Field Def="u8 flagC:4" />
+        <Field Def="u8 value" />
+    "#);
+    def.compute_field_offsets(u64::MAX);
+    assert_eq!(def.verify_layout(3), Ok(()));
+
+    let err = def.verify_layout(2).unwrap_err();
+    assert_eq!(
+        err,
+        LayoutMismatch {
+            param_type: "TEST_PARAM_ST".to_owned(),
+            computed: Some(3),
+            expected: 2,
+            last_fields: vec![
+                placed("flagA", 0, 3),
+                placed("flagB", 3, 3),
+                placed("flagC", 8, 4),
+                placed("value", 16, 8),
+            ],
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "TEST_PARAM_ST is 3 bytes but rows are 2 bytes; last fields: flagA at 0x0 (3 bits), \
+        flagB at 0x0.3 (3 bits), flagC at 0x1 (4 bits), value at 0x2 (8 bits)"
+    );
+}
+
+#[test]
+fn trailing_dummy8_array() {
+    let mut def = def(r#"
+        <Field Def="s32 id" />
+        <Field Def="u16 a" />
+        <Field Def="u8 b" />
+        <Field Def="u8 c" />
+        <Field Def="u8 d" />
+        <Field Def="dummy8 endPad[7]" />
+    "#);
+    def.compute_field_offsets(u64::MAX);
+    assert_eq!(def.verify_layout(16), Ok(()));
+
+    // Only the last few fields are listed
+    let err = def.verify_layout(12).unwrap_err();
+    assert_eq!(err.computed, Some(16));
+    assert_eq!(err.last_fields.len(), LayoutMismatch::LAST_FIELDS);
+    assert_eq!(err.last_fields[0], placed("b", 48, 8));
+    assert_eq!(err.last_fields[3], placed("endPad", 72, 56));
+}
+
+#[test]
+fn version_gated_fields() {
+    let mut def = def(r#"
+        <Field Def="s32 id" />
+        <Field Def="f32 added" FirstVersion="10300" />
+        <Field Def="u16 removed" RemovedVersion="10500" />
+        <Field Def="u16 pad" />
+    "#);
+    def.compute_field_offsets(10200);
+    assert_eq!(def.verify_layout(8), Ok(()));
+    def.compute_field_offsets(10400);
+    assert_eq!(def.verify_layout(12), Ok(()));
+
+    def.compute_field_offsets(10500);
+    let err = def.verify_layout(12).unwrap_err();
+    assert_eq!(err.computed, Some(10));
+    // Fields absent at the layout version are not listed
+    assert_eq!(
+        err.last_fields,
+        [
+            placed("id", 0, 32),
+            placed("added", 32, 32),
+            placed("pad", 64, 16)
+        ]
+    );
+}
+
+/// Returns the bit offset of each field of `def`.
+fn bit_offsets(def: &Paramdef) -> Vec<(&str, Option<usize>)> {
+    let fields = def.fields.iter();
+    fields.map(|f| (&*f.field_def.name, f.bit_offset)).collect()
+}
+
+#[test]
+fn disabled_first_field() {
+    let mut def = def(r#"
+        <Field Def="s32 legacy" RemovedVersion="200" />
+        <Field Def="u8 a" />
+        <Field Def="u16 b" />
+    "#);
+    def.compute_field_offsets(100);
+    assert_eq!(
+        bit_offsets(&def),
+        [("legacy", Some(0)), ("a", Some(32)), ("b", Some(48))]
+    );
+    assert_eq!(def.size_bytes, Some(8));
+
+    def.compute_field_offsets(200);
+    assert_eq!(
+        bit_offsets(&def),
+        [("legacy", None), ("a", Some(0)), ("b", Some(16))]
+    );
+    assert_eq!(def.size_bytes, Some(4));
+}
+
+#[test]
+fn disabled_field_between_bitfields() {
+    let mut def = def(r#"
+        <Field Def="u8 flagA:3" />
+        <Field Def="u8 extra" FirstVersion="200" />
+        <Field Def="u8 flagB:4" />
+        <Field Def="u8 value" />
+    "#);
+    def.compute_field_offsets(200);
+    assert_eq!(
+        bit_offsets(&def),
+        [
+            ("flagA", Some(0)),
+            ("extra", Some(8)),
+            ("flagB", Some(16)),
+            ("value", Some(24))
+        ]
+    );
+    assert_eq!(def.size_bytes, Some(4));
+
+    // Without the field between them, the bitfields share a byte
+    def.compute_field_offsets(100);
+    assert_eq!(
+        bit_offsets(&def),
+        [
+            ("flagA", Some(0)),
+            ("extra", None),
+            ("flagB", Some(3)),
+            ("value", Some(8))
+        ]
+    );
+    assert_eq!(def.size_bytes, Some(2));
+}
+
+#[test]
+fn disabled_last_field() {
+    let mut def = def(r#"
+        <Field Def="s32 id" />
+        <Field Def="u16 a" />
+        <Field Def="u16 b" />
+        <Field Def="s32 added" FirstVersion="200" />
+    "#);
+    def.compute_field_offsets(200);
+    assert_eq!(def.field_by_name("added").unwrap().bit_offset, Some(64));
+    assert_eq!(def.size_bytes, Some(12));
+
+    def.compute_field_offsets(100);
+    assert_eq!(
+        bit_offsets(&def),
+        [
+            ("id", Some(0)),
+            ("a", Some(32)),
+            ("b", Some(48)),
+            ("added", None)
+        ]
+    );
+    assert_eq!(def.size_bytes, Some(8));
+}
+
+#[test]
+fn offsets_not_computed() {
+    let def = def(r#"<Field Def="s32 id" />"#);
+    let err = def.verify_layout(4).unwrap_err();
+    assert_eq!(err.computed, None);
+    assert!(err.last_fields.is_empty());
+    assert_eq!(
+        err.to_string(),
+        "TEST_PARAM_ST is not laid out but rows are 4 bytes; last fields: none"
+    );
+}
+
+#[test]
+fn paramdex_lookup() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(u64::MAX);
+    assert_eq!(paramdex.verify_layout("BITFIELD_TEST_PARAM_ST", 16), Ok(()));
+    assert!(matches!(
+        paramdex.verify_layout("BITFIELD_TEST_PARAM_ST", 20),
+        Err(VerifyLayoutError::Mismatch(LayoutMismatch {
+            computed: Some(16),
+            expected: 20,
+            ..
+        }))
+    ));
+    assert_eq!(
+        paramdex.verify_layout("UNKNOWN_PARAM_ST", 16),
+        Err(VerifyLayoutError::UnknownParamType(
+            "UNKNOWN_PARAM_ST".to_owned()
+        ))
+    );
+}
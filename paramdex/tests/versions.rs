@@ -0,0 +1,141 @@
+//! Parsing [`RegulationVersion`]s, computing defs at several versions with
+//! [`Paramdex::defs_for_version`], and finding the defs whose layout depends on the version.
+
+use std::sync::Arc;
+
+use paramdex::{
+    version::{ParseVersionError, RegulationVersion},
+    Paramdex, VersionSensitiveDef,
+};
+
+fn version(s: &str) -> RegulationVersion {
+    s.parse().unwrap()
+}
+
+#[test]
+fn version_formats() {
+    let v = version("1.07.1");
+    assert_eq!(
+        (v.major(), v.minor(), v.patch(), v.revision()),
+        (1, 7, 1, 0)
+    );
+    assert_eq!(v.packed(), 10701000);
+    assert_eq!(v, RegulationVersion::new(1, 7, 1));
+    assert_eq!(v, RegulationVersion::from_packed(10701000));
+    assert_eq!(v, RegulationVersion::from_paramdef_data_version(10701));
+    assert_eq!(v.to_string(), "1.07.1");
+
+    assert_eq!(version("1.12"), RegulationVersion::from_packed(11200000));
+    assert_eq!(version("1.12").to_string(), "1.12.0");
+    assert_eq!(version("1.7.1"), v);
+    assert_eq!(version("2.00.3.5").packed(), 20003005);
+    assert_eq!(version("2.00.3.5").to_string(), "2.00.3.5");
+
+    for invalid in [
+        "",
+        "1",
+        "1.",
+        "1.100",
+        "1.07.100",
+        "1.07.1.1000",
+        "1.07.1.0.0",
+        "+1.07",
+        "a.b",
+    ] {
+        assert_eq!(
+            invalid.parse::<RegulationVersion>(),
+            Err(ParseVersionError(invalid.to_owned())),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn versions_are_ordered() {
+    let mut versions = ["1.10", "1.09.1", "1.02.3", "1.09", "2.00", "1.09.1.1"].map(version);
+    versions.sort();
+    let sorted = versions.map(|v| v.to_string());
+    assert_eq!(
+        sorted,
+        ["1.02.3", "1.09.0", "1.09.1", "1.09.1.1", "1.10.0", "2.00.0"]
+    );
+    assert!(version("99.99.99") < RegulationVersion::MAX);
+}
+
+const DEF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<PARAMDEF XmlVersion="3">
+  <ParamType>VERSION_TEST_PARAM_ST</ParamType>
+  <DataVersion>1</DataVersion>
+  <BigEndian>False</BigEndian>
+  <Unicode>True</Unicode>
+  <FormatVersion>203</FormatVersion>
+  <Fields>
+    <Field Def="s32 id" />
+    <Field Def="f32 added" FirstVersion="10701000" />
+    <Field Def="u16 removed" RemovedVersion="11200000" />
+    <Field Def="u16 pad" />
+  </Fields>
+</PARAMDEF>"#;
+
+fn paramdex() -> Paramdex {
+    let dir = std::env::temp_dir().join(format!("paramdex-versions-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("Defs")).unwrap();
+    std::fs::write(dir.join("Defs").join("VersionTestParam.xml"), DEF).unwrap();
+    let mut paramdex = Paramdex::new(&dir);
+    paramdex.load_defs().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    paramdex
+}
+
+#[test]
+fn defs_at_several_versions() {
+    let paramdex = paramdex();
+    let size = |v: &str| {
+        let defs = paramdex.defs_for_version(version(v));
+        assert_eq!(defs.version(), version(v));
+        defs.get("VERSION_TEST_PARAM_ST").unwrap().size_bytes
+    };
+
+    let before = paramdex.defs_for_version(version("1.07.0"));
+    let after = paramdex.defs_for_version(version("1.07.1"));
+    let before_def = before.get("VERSION_TEST_PARAM_ST").unwrap();
+    let after_def = after.get("VERSION_TEST_PARAM_ST").unwrap();
+    // The field added in 1.07.1 takes 4 more bytes, and the one removed in 1.12 2 fewer
+    assert_eq!(before_def.size_bytes, Some(8));
+    assert_eq!(after_def.size_bytes, Some(12));
+    assert_eq!(size("1.12"), Some(10));
+    assert_eq!(size("1.11.9"), Some(12));
+
+    // Computed layouts are cached, and don't touch the defs of the paramdex
+    assert!(Arc::ptr_eq(
+        &before,
+        &paramdex.defs_for_version(version("1.07.0"))
+    ));
+    assert_eq!(before_def.size_bytes, Some(8));
+    let def = paramdex.defs().next().unwrap();
+    assert_eq!(def.size_bytes, None);
+}
+
+#[test]
+fn version_sensitive_defs() {
+    let paramdex = paramdex();
+    // All fields enabled excludes the field removed in 1.12
+    let sensitive = |size_at_version| {
+        [VersionSensitiveDef {
+            param_type: "VERSION_TEST_PARAM_ST",
+            size_at_version,
+            size_at_max: 10,
+        }]
+    };
+
+    // The field added in 1.07.1 is excluded before it
+    assert_eq!(
+        paramdex.version_sensitive_defs(version("1.07.0").packed()),
+        sensitive(8)
+    );
+    assert_eq!(
+        paramdex.version_sensitive_defs(version("1.11.9").packed()),
+        sensitive(12)
+    );
+    assert!(paramdex.version_sensitive_defs(version("1.12").packed()).is_empty());
+}
@@ -0,0 +1,94 @@
+//! Cost of creating a patch for a single changed field, with and without the changed span scan.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ppatch::{
+    patchers::{
+        base::{FieldBlock, RowPatchId, RowPatcher},
+        linked_list::LinkedListPatcher,
+        single_patch::SinglePatchPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+
+const ROW_SIZE: usize = 700;
+
+/// Layout resembling a large param like `EQUIP_PARAM_WEAPON_ST`: mostly 4 byte fields, with runs
+/// of smaller fields and bitfields every few blocks.
+fn field_blocks() -> Vec<FieldBlock<u32>> {
+    let mut blocks = Vec::new();
+    for offset in 0..(ROW_SIZE / 4) as u16 {
+        let masks: &[u32] = match offset % 8 {
+            5 => &[0xFFFF, 0xFFFF_0000],
+            6 => &[0xFF, 0xFF00, 0xFFFF_0000],
+            7 => &[1, 2, 4, 8, 0xF0, 0xFF00, 0xFF_0000, 0xFF00_0000],
+            _ => &[u32::MAX],
+        };
+        for &mask in masks {
+            blocks.push(FieldBlock {
+                field_start: blocks.len() as u16,
+                offset,
+                mask,
+            });
+        }
+    }
+    blocks
+}
+
+/// Original row, and the same row with a single `f32` field changed in the middle.
+fn rows() -> (Vec<Unaligned<u32>>, Vec<Unaligned<u32>>) {
+    let before: Vec<_> =
+        (0..ROW_SIZE as u32 / 4).map(|i| Unaligned((i as f32).to_bits())).collect();
+    let mut after = before.clone();
+    after[ROW_SIZE / 8] = Unaligned(1.5f32.to_bits());
+    (before, after)
+}
+
+type CreatePatch<P> = fn(&mut P, &[Unaligned<u32>], &[Unaligned<u32>]) -> Option<RowPatchId>;
+
+fn bench_patcher<'a, P: RowPatcher<'a, u32>>(
+    c: &mut Criterion,
+    name: &str,
+    field_blocks: &'a [FieldBlock<u32>],
+    create_patch_full_walk: CreatePatch<P>,
+) {
+    let (before, after) = rows();
+    let mut group = c.benchmark_group(format!("create_patch/{name}"));
+
+    let mut patcher = P::try_new(field_blocks, ROW_SIZE).unwrap();
+    let mut live = after.clone();
+    group.bench_function("changed_span", |b| {
+        b.iter(|| {
+            let id = patcher.create_patch(&before, &after).unwrap();
+            patcher.restore_patch(id, &mut live).unwrap();
+        })
+    });
+
+    let mut patcher = P::try_new(field_blocks, ROW_SIZE).unwrap();
+    let mut live = after.clone();
+    group.bench_function("full_walk", |b| {
+        b.iter(|| {
+            let id = create_patch_full_walk(&mut patcher, &before, &after).unwrap();
+            patcher.restore_patch(id, &mut live).unwrap();
+        })
+    });
+    group.finish();
+}
+
+pub fn create_single_field_patch(c: &mut Criterion) {
+    let field_blocks = field_blocks();
+    bench_patcher(
+        c,
+        "linked_list",
+        &field_blocks,
+        LinkedListPatcher::create_patch_full_walk,
+    );
+    bench_patcher(
+        c,
+        "single_patch",
+        &field_blocks,
+        SinglePatchPatcher::create_patch_full_walk,
+    );
+}
+
+criterion_group!(benches, create_single_field_patch);
+criterion_main!(benches);
@@ -1,28 +1,196 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use num_traits::PrimInt;
-use ppatch::patchers::{self, base::FieldBlock};
-use rand::distributions::WeightedIndex;
-use rand::prelude::*;
-
-pub fn gen_field_blocks<N: PrimInt>(
-    row_size: usize,
-    packed: bool,
-    field_size_weights: &[usize],
-) -> Vec<FieldBlock<N>> {
-    let mut rng = thread_rng();
-    let mut blocks = Vec::new();
-
-    let size_dist = WeightedIndex::new(field_size_weights).unwrap();
-    let bit_offset = 0;
-    loop {
-        let sz = 1 << size_dist.sample(&mut rng);
-        let mask = N::max_value() >> (8 * sz) << bit_offset;
+//! Comparison of [`LinkedListPatcher`], [`SparseArrayPatcher`] and [`ToggleMapPatcher`] on
+//! generated layouts, in the scenarios their designs trade off: sparse and dense patches,
+//! restoring the bottom of a deep stack of patches, interleaved creation and restoration, and
+//! toggling a single field on and off.
+//!
+//! [`SinglePatchPatcher`] only keeps one patch per row, so it only runs the sparse and dense
+//! patch scenarios, the common case it is designed for.
+
+use std::collections::VecDeque;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ppatch::{
+    fields::write_field_bytes,
+    patchers::{
+        base::{RowPatchId, RowPatcher},
+        linked_list::LinkedListPatcher,
+        single_patch::SinglePatchPatcher,
+        toggle_map::ToggleMapPatcher,
+        SparseArrayPatcher,
+    },
+    testing::{random_row, FieldLayout, LayoutConfig},
+    util::unaligned::Unaligned,
+};
+
+type Block = u32;
+type Row = Vec<Unaligned<Block>>;
+
+/// Number of outstanding patches below the restored one in the deep stack scenario.
+const STACK_DEPTH: usize = 64;
+/// Number of outstanding patches kept while interleaving creations and restorations, and of
+/// other outstanding patches while toggling a field.
+const INTERLEAVED_WINDOW: usize = 8;
+
+fn layout() -> FieldLayout<Block> {
+    FieldLayout::generate(&LayoutConfig {
+        row_size: 768,
+        ..Default::default()
+    })
+}
+
+/// Returns `row` with the field at index `field` (modulo the number of fields) set to `value`.
+fn with_field(layout: &FieldLayout<Block>, row: &Row, field: usize, value: u32) -> Row {
+    let mut row = row.clone();
+    let field_start = layout.field_starts[field % layout.field_starts.len()];
+    write_field_bytes(&mut row, &layout.blocks, field_start, &value.to_le_bytes());
+    row
+}
+
+/// Creates a patch changing a single field of `live`, chosen from `n`.
+fn patch_field<'a, P: RowPatcher<'a, Block>>(
+    patcher: &mut P,
+    layout: &FieldLayout<Block>,
+    live: &mut Row,
+    n: usize,
+) -> RowPatchId {
+    let after = with_field(layout, live, n.wrapping_mul(7919), n as u32 ^ 0xA5A5_A5A5);
+    let id = patcher.create_patch(live, &after).unwrap();
+    *live = after;
+    id
+}
+
+fn bench_patcher<'a, P: RowPatcher<'a, Block>>(
+    c: &mut Criterion,
+    name: &str,
+    layout: &'a FieldLayout<Block>,
+) {
+    let original = random_row::<Block>(layout.row_size, 0x5EED);
+    let new_patcher = || P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut group = c.benchmark_group(format!("row_patchers/{name}"));
+
+    // A single field in the middle of the row
+    let sparse = with_field(
+        layout,
+        &original,
+        layout.field_starts.len() / 2,
+        0x1234_5678,
+    );
+    let mut patcher = new_patcher();
+    let mut live = sparse.clone();
+    group.bench_function("sparse_single_field", |b| {
+        b.iter(|| {
+            let id = patcher.create_patch(&original, &sparse).unwrap();
+            patcher.restore_patch(id, &mut live).unwrap();
+            live.copy_from_slice(&sparse);
+        })
+    });
+
+    // Every field of the row
+    let dense = random_row::<Block>(layout.row_size, !0x5EED);
+    let mut patcher = new_patcher();
+    let mut live = dense.clone();
+    group.bench_function("dense_whole_row", |b| {
+        b.iter(|| {
+            let id = patcher.create_patch(&original, &dense).unwrap();
+            patcher.restore_patch(id, &mut live).unwrap();
+            live.copy_from_slice(&dense);
+        })
+    });
+
+    // Patchers which refuse a second outstanding patch skip the scenarios stacking patches
+    let mut patcher = new_patcher();
+    let first = patcher.create_patch(&original, &sparse);
+    if first.is_none() || patcher.create_patch(&sparse, &dense).is_none() {
+        group.finish();
+        return;
     }
 
-    blocks
+    // The first of a stack of patches, most of which change other fields
+    group.bench_function("deep_stack_bottom_restore", |b| {
+        b.iter_batched(
+            || {
+                let mut patcher = new_patcher();
+                let mut live = original.clone();
+                let bottom = patch_field(&mut patcher, layout, &mut live, 0);
+                for n in 1..=STACK_DEPTH {
+                    patch_field(&mut patcher, layout, &mut live, n);
+                }
+                (patcher, live, bottom)
+            },
+            |(mut patcher, mut live, bottom)| {
+                patcher.restore_patch(bottom, &mut live).unwrap();
+                (patcher, live)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // Creating a patch and restoring the oldest of a window of outstanding patches
+    let mut patcher = new_patcher();
+    let mut live = original.clone();
+    let mut outstanding: VecDeque<_> = (0..INTERLEAVED_WINDOW)
+        .map(|n| patch_field(&mut patcher, layout, &mut live, n))
+        .collect();
+    let mut n = INTERLEAVED_WINDOW;
+    group.bench_function("interleaved_create_restore", |b| {
+        b.iter(|| {
+            n += 1;
+            outstanding.push_back(patch_field(&mut patcher, layout, &mut live, n));
+            let oldest = outstanding.pop_front().unwrap();
+            patcher.restore_patch(oldest, &mut live).unwrap();
+        })
+    });
+
+    // Switching the first field on and off by creating and restoring a patch
+    let (mut patcher, off, on) = toggle_setup::<P>(layout, &original);
+    let mut live = off.clone();
+    group.bench_function("toggle_field", |b| {
+        b.iter(|| {
+            let id = patcher.create_patch(&off, &on).unwrap();
+            live.copy_from_slice(&on);
+            patcher.restore_patch(id, &mut live).unwrap();
+        })
+    });
+    group.finish();
 }
 
-pub fn test_patch_register(c: &mut Criterion) {}
+/// Returns a patcher with a few outstanding patches, the live row they give, and that row with
+/// the first field changed.
+fn toggle_setup<'a, P: RowPatcher<'a, Block>>(
+    layout: &'a FieldLayout<Block>,
+    original: &Row,
+) -> (P, Row, Row) {
+    let mut patcher = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live = original.clone();
+    for n in 1..=INTERLEAVED_WINDOW {
+        patch_field(&mut patcher, layout, &mut live, n);
+    }
+    let on = with_field(layout, &live, 0, 0x5A5A_5A5A);
+    (patcher, live, on)
+}
+
+pub fn compare_patchers(c: &mut Criterion) {
+    let layout = layout();
+    bench_patcher::<LinkedListPatcher<Block>>(c, "linked_list", &layout);
+    bench_patcher::<SinglePatchPatcher<Block>>(c, "single_patch", &layout);
+    bench_patcher::<SparseArrayPatcher<Block>>(c, "sparse_array", &layout);
+    bench_patcher::<ToggleMapPatcher<Block>>(c, "toggle_map", &layout);
+}
+
+/// Switching the first field on and off with [`ToggleMapPatcher::toggle`], to compare with the
+/// `toggle_field` scenario of [`compare_patchers`].
+pub fn toggle_in_place(c: &mut Criterion) {
+    let layout = layout();
+    let original = random_row::<Block>(layout.row_size, 0x5EED);
+    let (mut patcher, mut live, on) = toggle_setup::<ToggleMapPatcher<Block>>(&layout, &original);
+    patcher.create_patch(&live, &on).unwrap();
+    live = on;
+
+    let field_start = layout.field_starts[0];
+    c.bench_function("row_patchers/toggle_map/toggle_in_place", |b| {
+        b.iter(|| patcher.toggle(field_start, &mut live).unwrap())
+    });
+}
 
-criterion_group!(benches, test_patch_register);
+criterion_group!(benches, compare_patchers, toggle_in_place);
 criterion_main!(benches);
@@ -1,24 +1,266 @@
-#[cfg(any(
-    all(feature = "er", feature = "ds3"),
-    all(feature = "ds3", feature = "ac6"),
-    all(feature = "ac6", feature = "er")
+#[cfg(all(
+    not(feature = "multi-game"),
+    any(
+        all(feature = "er", feature = "ds3"),
+        all(feature = "ds3", feature = "ac6"),
+        all(feature = "ac6", feature = "er")
+    )
 ))]
-compile_error!("Only one of the target game features (ds3, er, ac6) may be enabled");
+compile_error!(
+    "Only one of the target game features (ds3, er, ac6) may be enabled, unless multi-game is"
+);
+#[cfg(not(any(
+    feature = "ds3",
+    feature = "er",
+    feature = "ac6",
+    feature = "multi-game"
+)))]
+compile_error!("One of the target game features (ds3, er, ac6) or multi-game must be enabled");
 
-use std::{error::Error, time::Instant};
+#[cfg(feature = "project-enums")]
+use std::io::Write;
+use std::{error::Error, fmt::Display, path::Path, time::Instant};
 
-use field_metadata::{serialize_fb_repo, Block, FieldBlock, FieldBlockRepo};
-use paramdex::{git_fetch::ParamdexGitFetch, Paramdex};
+use field_metadata::{
+    build_blocks, serialize_fb_repo_for_game, validate_field_blocks, Block, FieldBlockRepo,
+};
+use paramdex::{
+    git_fetch::ParamdexGitFetch, unofficial::UnofficialFields, Paramdex, VerifyLayoutError,
+};
 
-#[cfg(feature = "ds3")]
-const GAME: &'static str = "DS3";
-#[cfg(feature = "er")]
-const GAME: &'static str = "ER";
-#[cfg(feature = "ac6")]
-const GAME: &'static str = "AC6";
+/// Default game of the build, i.e. the one of the enabled game feature. ER is preferred when
+/// several are enabled along with `multi-game`, like `ppatch::Game::DEFAULT`.
+const GAME: &str = if cfg!(feature = "er") {
+    "ER"
+}
+else if cfg!(feature = "ds3") {
+    "DS3"
+}
+else if cfg!(feature = "ac6") {
+    "AC6"
+}
+else {
+    "ER"
+};
+
+/// Games whose field blocks are embedded, and the file they are written to.
+#[cfg(not(feature = "multi-game"))]
+const EMBEDDED_GAMES: &[(&str, &str)] = &[(GAME, "field_blocks.bin")];
+#[cfg(feature = "multi-game")]
+const EMBEDDED_GAMES: &[(&str, &str)] = &[
+    ("DS3", "field_blocks_ds3.bin"),
+    ("ER", "field_blocks_er.bin"),
+    ("AC6", "field_blocks_ac6.bin"),
+];
+
+/// Paramdef layout version used for each game unless overriden by the `PPATCH_LAYOUT_VERSION`
+/// environment variable. Fields whose `FirstVersion` is above it are left out of the layouts.
+///
+/// ER versions are packed as 8 digit numbers (`11200000` is regulation 1.12). DS3 and AC6 defs
+/// are not version gated in a way we can pin yet, so all of their fields are included.
+fn default_layout_version(game: &str) -> u64 {
+    match game {
+        "ER" => 11200000,
+        _ => u64::MAX,
+    }
+}
+
+/// Logs and wraps a paramdex error with some context and instructions on how to recover from it.
+fn paramdex_error(context: impl Display, err: impl Display) -> Box<dyn Error> {
+    let msg = format!(
+        "{context}: {err}\nIf the paramdex checkout is incomplete or corrupted, \
+        delete the .paramdex folder to force a fresh fetch."
+    );
+    log::error!("{msg}");
+    eprintln!("{msg}");
+    msg.into()
+}
+
+/// Layout version of the default game. Other games embedded by `multi-game` always use their
+/// default version, as versions are not comparable between games.
+fn layout_version() -> Result<u64, Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=PPATCH_LAYOUT_VERSION");
+    match std::env::var("PPATCH_LAYOUT_VERSION") {
+        Ok(v) => Ok(v.parse().map_err(|e| format!("Invalid PPATCH_LAYOUT_VERSION {v:?}: {e}"))?),
+        Err(_) => Ok(default_layout_version(GAME)),
+    }
+}
+
+/// Generates `$OUT_DIR/project_enums.rs` from the `Enums.json` of the target game.
+#[cfg(feature = "project-enums")]
+fn emit_project_enums(paramdex: &mut Paramdex, game_path: &Path) -> Result<(), Box<dyn Error>> {
+    let now = Instant::now();
+    paramdex.load_enums().map_err(|e| {
+        let enums_path = game_path.join("Enums.json");
+        paramdex_error(
+            format!("Failed to load {GAME} enums from {}", enums_path.display()),
+            e,
+        )
+    })?;
+
+    let out_path = Path::new(&std::env::var("OUT_DIR")?).join("project_enums.rs");
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&out_path)?);
+    codegen::emit_project_enums(paramdex, &mut out)?;
+    out.flush()?;
+
+    log::info!("{GAME} project enums generated in {:?}", now.elapsed());
+    Ok(())
+}
+
+/// Merges the unofficial field overlay at `PPATCH_UNOFFICIAL_FIELDS` into the defs, if set.
+fn merge_unofficial_fields(paramdex: &mut Paramdex) -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=PPATCH_UNOFFICIAL_FIELDS");
+    let Ok(path) = std::env::var("PPATCH_UNOFFICIAL_FIELDS")
+    else {
+        return Ok(());
+    };
+    println!("cargo:rerun-if-changed={path}");
+
+    let overlay = UnofficialFields::from_toml(&std::fs::read_to_string(&path)?)
+        .map_err(|e| format!("Invalid unofficial field overlay {path}: {e}"))?;
+    paramdex.merge_unofficial_fields(&overlay)?;
+    log::info!("Merged unofficial fields from {path}");
+    Ok(())
+}
+
+/// Reads the param type and row size of a little endian param file, i.e. the size of the
+/// smallest row like `ParamFile::row_size`.
+fn param_file_layout(file: &[u8]) -> Result<(String, usize), String> {
+    let read = |ofs: usize, len: usize| {
+        let bytes = file.get(ofs..ofs + len).ok_or("file is truncated")?;
+        Ok::<_, String>(bytes.iter().rev().fold(0, |acc, &b| acc << 8 | b as usize))
+    };
+    if file.len() < 0x30 || file[0x2C] != 0 {
+        return Err("not a little endian param file".to_owned());
+    }
+    let flags = file[0x2D];
+    let is_64_bit = flags & 4 != 0;
+    let header_size = if flags & 3 == 3 || is_64_bit { 0x40 } else { 0x30 };
+    let (param_type_ofs, data_end) = if flags & 0x80 != 0 {
+        (read(0x10, 4)?, read(0x10, 4)?)
+    }
+    else {
+        (0xC, read(0, 4)?)
+    };
+    let param_type = file.get(param_type_ofs..).ok_or("param type is out of bounds")?;
+    let len = param_type.iter().position(|&c| c == 0).unwrap_or(param_type.len());
+    let param_type = String::from_utf8_lossy(&param_type[..len.min(32)]).into_owned();
+
+    let (desc_size, ofs_size) = if is_64_bit { (24, 8) } else { (12, 4) };
+    let mut offsets = (0..read(0xA, 2)?)
+        .map(|i| {
+            read(
+                header_size + i * desc_size + desc_size - 2 * ofs_size,
+                ofs_size,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    offsets.sort_unstable();
+    let last_size = offsets.last().map_or(0, |&last| data_end.saturating_sub(last));
+    let row_size = offsets.windows(2).map(|p| p[1] - p[0]).chain([last_size]).min();
+    Ok((param_type, row_size.unwrap_or(0)))
+}
+
+/// Verifies the def layouts against the row sizes of the param files extracted to
+/// `PPATCH_VERIFY_PARAMS_DIR`, if set, and fails on the first mismatch.
+fn verify_param_files(paramdex: &Paramdex) -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=PPATCH_VERIFY_PARAMS_DIR");
+    let Ok(dir) = std::env::var("PPATCH_VERIFY_PARAMS_DIR")
+    else {
+        return Ok(());
+    };
+    println!("cargo:rerun-if-changed={dir}");
+
+    let mut verified = 0;
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "param") {
+            continue;
+        }
+        let (param_type, row_size) = param_file_layout(&std::fs::read(&path)?)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        match paramdex.verify_layout(&param_type, row_size) {
+            Ok(()) => verified += 1,
+            Err(VerifyLayoutError::UnknownParamType(_)) => {
+                log::warn!("No def for {param_type} ({})", path.display())
+            }
+            Err(e) => {
+                let msg = format!("Layout mismatch for {}: {e}", path.display());
+                log::error!("{msg}");
+                return Err(msg.into());
+            }
+        }
+    }
+    log::info!("Verified {verified} def layouts against the param files in {dir}");
+    Ok(())
+}
+
+/// Builds the field blocks of `game` and writes them to `path`.
+///
+/// Unofficial fields, param file verification and project enums only apply to the default game.
+fn build_game(paramdex_path: &Path, game: &str, path: &str) -> Result<(), Box<dyn Error>> {
+    let now = Instant::now();
+    let is_default = game == GAME;
+    let layout_version = if is_default { layout_version()? } else { default_layout_version(game) };
+    let game_path = paramdex_path.join(game);
+    let mut paramdex = Paramdex::new(&game_path);
+    paramdex.load_defs().map_err(|e| {
+        let defs_path = game_path.join("Defs");
+        paramdex_error(
+            format!(
+                "Failed to load {game} paramdefs from {}",
+                defs_path.display()
+            ),
+            e,
+        )
+    })?;
+    if is_default {
+        merge_unofficial_fields(&mut paramdex)?;
+    }
+    paramdex.compute_def_layouts(layout_version);
+
+    log::info!("{game} paramdefs loaded in {:?}", now.elapsed());
+    log::info!("Using layout version {layout_version}");
+    for s in paramdex.version_sensitive_defs(layout_version) {
+        log::warn!(
+            "{} is {} bytes at layout version {layout_version}, but {} bytes with all fields",
+            s.param_type,
+            s.size_at_version,
+            s.size_at_max
+        );
+    }
+    if is_default {
+        verify_param_files(&paramdex)?;
+    }
+    let now = Instant::now();
 
-const BLOCK_SIZE: usize = std::mem::size_of::<Block>();
-const BLOCK_SIZE_BITS: usize = 8 * BLOCK_SIZE;
+    let mut fb_repo = FieldBlockRepo::new();
+    for def in paramdex.defs() {
+        assert!(def.fields.len() < u16::MAX as usize);
+        let blocks = build_blocks::<Block>(def);
+
+        assert!(blocks.len() < u16::MAX as usize);
+        validate_field_blocks(&blocks).map_err(|e| {
+            format!(
+                "Generated invalid field blocks for {}: {e:?}",
+                def.param_type
+            )
+        })?;
+        fb_repo.insert(def.param_type.clone(), blocks);
+    }
+
+    let serialized = serialize_fb_repo_for_game(&fb_repo, game);
+    std::fs::write(path, &serialized)?;
+    log::info!("{game} field blocks built in {:?}", now.elapsed());
+
+    if is_default {
+        #[cfg(feature = "project-enums")]
+        emit_project_enums(&mut paramdex, &game_path)?;
+        println!("cargo:rustc-env=PPATCH_FB_LAYOUT_VERSION={layout_version}");
+    }
+    println!("cargo:rustc-env=PPATCH_FB_LAYOUT_VERSION_{game}={layout_version}");
+    Ok(())
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     let log_conf = simple_log::LogConfigBuilder::builder()
@@ -35,61 +277,18 @@ fn main() -> Result<(), Box<dyn Error>> {
         .branch("1.0.18.1")
         .paramdex_path("src/StudioCore/Assets/Paramdex")
         .games(["DS3", "ER", "AC6"])
-        .fetch_cached(".paramdex")?;
+        .fetch_cached(".paramdex")
+        .map_err(|e| paramdex_error("Failed to fetch the paramdex", e))?;
 
     log::info!(
         "Paramdex at {} fetched in {:?}",
         paramdex_path.to_string_lossy(),
         now.elapsed()
     );
-    let now = Instant::now();
-
-    let mut paramdex = Paramdex::new(paramdex_path.join(GAME));
-    paramdex.load_defs()?.compute_def_layouts(u64::MAX);
 
-    log::info!("{GAME} paramdefs loaded in {:?}", now.elapsed());
-    let now = Instant::now();
-
-    let mut fb_repo = FieldBlockRepo::new();
-    for def in paramdex.defs() {
-        assert!(def.fields.len() < u16::MAX as usize);
-        let mut blocks: Vec<FieldBlock<Block>> = Vec::new();
-
-        for f in def.fields.iter().filter(|f| f.bit_offset.is_some()) {
-            let bofs = f.bit_offset.unwrap();
-            let mut offset = (bofs / BLOCK_SIZE_BITS) as u16;
-
-            let mask = Block::MAX >> (BLOCK_SIZE_BITS.saturating_sub(f.size_bits()))
-                << (bofs - (bofs & BLOCK_SIZE_BITS - 1));
-
-            let field_start = blocks.len() as u16;
-            blocks.push(FieldBlock {
-                field_start,
-                offset,
-                mask,
-            });
-
-            let mut remaining_bits = f.size_bits() - mask.count_ones() as usize;
-            while remaining_bits != 0 {
-                let mask = Block::MAX >> (BLOCK_SIZE_BITS.saturating_sub(remaining_bits));
-                blocks.push(FieldBlock {
-                    field_start,
-                    offset,
-                    mask,
-                });
-                offset += 1;
-                remaining_bits -= mask.count_ones() as usize;
-            }
-        }
-
-        assert!(blocks.len() < u16::MAX as usize);
-        fb_repo.insert(def.param_type.clone(), blocks);
+    for &(game, path) in EMBEDDED_GAMES {
+        build_game(&paramdex_path, game, path)?;
     }
-
-    let serialized = serialize_fb_repo(&fb_repo);
-    std::fs::write("field_blocks.bin", &serialized)?;
-    log::info!("{GAME} field blocks built in {:?}", now.elapsed());
-
     println!("cargo:rerun-if-changed=.paramdex");
     println!("cargo:rerun-if-changed=../paramdex");
 
@@ -0,0 +1,102 @@
+#ifndef PPATCH_H
+#define PPATCH_H
+
+/*
+ * C API of ppatch, exported with the `capi` feature.
+ *
+ * Functions returning an integer report failures as the negated code of the ppatch error, e.g.:
+ * -110: the row length is not a multiple of 4 bytes;
+ * -131: the row is too short for the field blocks of its param type;
+ * -201, -202: the handle was not returned by the manager, or was already restored;
+ * -210: the patcher of the row rejected the patch;
+ * -240: the embedded field blocks have no entry for the param type;
+ * -241: the row length is not the size of the row's previous patches;
+ * -301 to -304: the field blocks of the param type are invalid;
+ * -801: a pointer argument is null;
+ * -802: the param name is not valid UTF-8;
+ * -803: the call panicked.
+ * See `ppatch::error::PpatchError` for the full list.
+ */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Value of [`Game::Ds3`] for [`ppatch_manager_new`].
+ */
+#define PPATCH_GAME_DS3 0
+
+/**
+ * Value of [`Game::Er`] for [`ppatch_manager_new`].
+ */
+#define PPATCH_GAME_ER 1
+
+/**
+ * Value of [`Game::Ac6`] for [`ppatch_manager_new`].
+ */
+#define PPATCH_GAME_AC6 2
+
+/**
+ * Patch manager created by [`ppatch_manager_new`], which hands out integer handles to the
+ * patches it creates.
+ */
+typedef struct PpatchManager PpatchManager;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a manager using the embedded field blocks of `game`, one of the `PPATCH_GAME_*`
+ * values.
+ *
+ * Returns null if the field blocks of the game are not embedded in this build.
+ */
+PpatchManager *ppatch_manager_new(uint32_t game);
+
+/**
+ * Frees a manager created by [`ppatch_manager_new`]. Does nothing if `manager` is null.
+ *
+ * # Safety
+ * `manager` must be null or a manager returned by [`ppatch_manager_new`] which was not freed.
+ */
+void ppatch_manager_free(PpatchManager *manager);
+
+/**
+ * Creates a patch from the changes between `before` and `after`, the `len` bytes of row
+ * `row_id` of the param of type `param_name`. See [`ParamPatchManager::create_patch`].
+ *
+ * Returns the positive handle of the patch, or a negated error code.
+ *
+ * # Safety
+ * - `manager` must be null or a live manager returned by [`ppatch_manager_new`].
+ * - `param_name` must be null or a NUL-terminated string.
+ * - `before` and `after` must be null or valid for reads of `len` bytes.
+ */
+int64_t ppatch_create_patch(PpatchManager *manager,
+                            const char *param_name,
+                            uint32_t row_id,
+                            const uint8_t *before,
+                            const uint8_t *after,
+                            uintptr_t len);
+
+/**
+ * Restores the patch of `handle` in `live`, the `len` bytes of its row. See
+ * [`ParamPatchManager::restore`].
+ *
+ * Returns 0, or a negated error code. Handles which were already restored or were never
+ * returned by this manager are [`RestorePatchError::UnknownId`]s.
+ *
+ * # Safety
+ * - `manager` must be null or a live manager returned by [`ppatch_manager_new`].
+ * - `live` must be null or valid for reads and writes of `len` bytes.
+ */
+int32_t ppatch_restore(PpatchManager *manager, int64_t handle, uint8_t *live, uintptr_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PPATCH_H */
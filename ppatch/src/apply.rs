@@ -0,0 +1,444 @@
+//! Param edits distributed as text, applied to live params through a [`ParamPatchManager`].
+//!
+//! An [`EditList`] is parsed from lines of `ParamName,RowId,FieldName,Value` with
+//! [`EditList::parse_csv`], or deserialized from the equivalent JSON with the `serde` feature:
+//!
+//! ```json
+//! [{ "param": "EquipParamWeapon", "row_id": 1000000, "field": "weight", "value": 2.5 }]
+//! ```
+//!
+//! Edits are resolved with the defs and metas of a [`Paramdex`], whose field offsets must have
+//! been computed for the version of the game (see [`row_fields`](crate::row_fields)). Like
+//! [`row_fields`](crate::row_fields), only scalar fields of little endian defs can be edited.
+
+use std::{collections::HashMap, fmt};
+
+use field_metadata::Block;
+use paramdex::{
+    paramdef::{DefBaseRustType, DefField},
+    DefWithMeta, Paramdex,
+};
+
+use crate::{
+    param_file::{ParamFile, UnalignedRowSize},
+    patchers::{
+        base::{RestorePatchError, RowPatcher},
+        manager::{CreatePatchError, ParamPatchManager, PatchHandle},
+        patch_set::has_param_type,
+    },
+    row_fields::ScalarField,
+};
+
+/// New value of a field of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct Edit {
+    /// Name of the def of the param in the paramdex, e.g. `EquipParamWeapon`.
+    pub param: String,
+    pub row_id: u32,
+    /// Name of the field in the def, or its `@AltName` in the param meta.
+    pub field: String,
+    /// Value of the field, parsed according to its type in the def. JSON numbers are accepted
+    /// as well as strings.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_value"))]
+    pub value: String,
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_value<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    #[derive(serde_derive::Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        String(String),
+        Int(i64),
+        Float(f64),
+    }
+
+    Ok(match serde::Deserialize::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Int(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+    })
+}
+
+/// List of edits, applied in order. See the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(transparent)
+)]
+pub struct EditList {
+    pub edits: Vec<Edit>,
+}
+
+/// Error returned by [`EditList::parse_csv`]. Lines are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEditListError {
+    /// The line has less than the 4 columns of an edit.
+    MissingColumns { line: usize },
+    /// The row ID of the line is not an unsigned 32-bit integer.
+    InvalidRowId { line: usize, row_id: String },
+}
+
+impl fmt::Display for ParseEditListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingColumns { line } => {
+                write!(f, "line {line} of the edit list has less than 4 columns")
+            }
+            Self::InvalidRowId { line, row_id } => {
+                write!(
+                    f,
+                    "line {line} of the edit list has an invalid row ID {row_id:?}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseEditListError {}
+
+/// Reason an edit was skipped or changed, see [`EditWarning`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditWarningKind {
+    /// The paramdex has no def named after the param.
+    UnknownParam,
+    /// None of the params has the param type of the def.
+    MissingParam { param_type: String },
+    /// The param has no row with the ID of the edit.
+    UnknownRow,
+    /// The def has no field with this name or `@AltName`.
+    UnknownField,
+    /// The field is an array, has no offset at the layout version of the def, lies outside of
+    /// the row or belongs to a big endian def.
+    UnsupportedField,
+    /// The value can't be parsed as a value of the type of the field.
+    InvalidValue,
+    /// The value was outside of the range of the field, so `written` was written instead. The
+    /// range is the one of the type and width of the field, narrowed by the `Minimum` and
+    /// `Maximum` of the def.
+    Clamped { written: f64 },
+}
+
+/// Edit of an [`EditList`] which was skipped, or applied with a clamped value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditWarning {
+    /// Index of the edit in [`EditList::edits`].
+    pub index: usize,
+    pub kind: EditWarningKind,
+}
+
+impl fmt::Display for EditWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "edit {}: ", self.index)?;
+        match &self.kind {
+            EditWarningKind::UnknownParam => f.write_str("no def for the param"),
+            EditWarningKind::MissingParam { param_type } => {
+                write!(f, "no param of type {param_type}")
+            }
+            EditWarningKind::UnknownRow => f.write_str("no row with this ID"),
+            EditWarningKind::UnknownField => f.write_str("no field with this name"),
+            EditWarningKind::UnsupportedField => f.write_str("field can't be edited"),
+            EditWarningKind::InvalidValue => f.write_str("invalid value for the field type"),
+            EditWarningKind::Clamped { written } => write!(f, "value clamped to {written}"),
+        }
+    }
+}
+
+/// Error returned by [`EditList::apply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyEditsError {
+    /// The size of an edited row is not a multiple of the block size.
+    UnalignedRowSize(UnalignedRowSize),
+    /// The manager could not create the patch of an edited row.
+    CreatePatch(CreatePatchError),
+}
+
+impl fmt::Display for ApplyEditsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnalignedRowSize(e) => e.fmt(f),
+            Self::CreatePatch(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ApplyEditsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::UnalignedRowSize(e) => Some(e),
+            Self::CreatePatch(e) => Some(e),
+        }
+    }
+}
+
+impl From<UnalignedRowSize> for ApplyEditsError {
+    fn from(value: UnalignedRowSize) -> Self {
+        Self::UnalignedRowSize(value)
+    }
+}
+
+impl From<CreatePatchError> for ApplyEditsError {
+    fn from(value: CreatePatchError) -> Self {
+        Self::CreatePatch(value)
+    }
+}
+
+/// Result of [`EditList::apply`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedEdits<'a> {
+    /// Patch of each edited row, in the order of the first edit of each row.
+    pub handles: Vec<PatchHandle<'a>>,
+    /// Edits which were skipped or clamped, in list order.
+    pub warnings: Vec<EditWarning>,
+}
+
+impl<'a> AppliedEdits<'a> {
+    /// Restores the patches of the edits in reverse order, given the params they were applied
+    /// to, reverting the whole list.
+    ///
+    /// # Errors
+    /// If a patch was already restored or its row is not in `params`, returns
+    /// [`RestorePatchError::UnknownId`]. The patches restored before it stay restored.
+    pub fn revert<P: RowPatcher<'a>>(
+        self,
+        manager: &mut ParamPatchManager<'a, P>,
+        params: &mut [ParamFile<'_>],
+    ) -> Result<(), RestorePatchError> {
+        for handle in self.handles.into_iter().rev() {
+            let param = params.iter_mut().find(|p| has_param_type(p, handle.param_type));
+            let mut row = (param.and_then(|p| p.by_id_mut(handle.row_id)))
+                .ok_or(RestorePatchError::UnknownId)?;
+            let live = row.as_blocks_mut().map_err(|_| RestorePatchError::UnknownId)?;
+            manager.restore(handle, live)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write of an edit, see [`EditList::resolve`].
+struct ResolvedEdit<'d> {
+    param_type: &'d str,
+    /// Index of the param in the edited params.
+    param: usize,
+    field: ScalarField,
+    /// Bits of the value of the field.
+    bits: u32,
+}
+
+/// Field writes of the edits of a row, in list order.
+struct RowEdits<'d> {
+    param_type: &'d str,
+    /// Index of the param in the edited params.
+    param: usize,
+    row_id: u32,
+    writes: Vec<(ScalarField, u32)>,
+}
+
+/// Finds the field `name` of `def`, falling back to the field whose `@AltName` is `name`.
+fn find_field<'d>(def: &'d DefWithMeta, name: &str) -> Option<&'d DefField> {
+    def.def.field_by_name(name).or_else(|| {
+        let meta = def.meta.as_ref()?;
+        let (field_name, _) = (meta.fields.iter()).find(|(_, f)| f.alt_name == name)?;
+        def.def.field_by_name(field_name)
+    })
+}
+
+/// Parses `value` as a value of `field`, clamped to its range (see
+/// [`EditWarningKind::Clamped`]). Returns the bits of the value, and the clamped value if it
+/// was clamped.
+fn parse_value(field: &DefField, value: &str) -> Option<(u32, Option<f64>)> {
+    let min = field.minimum.unwrap_or(f64::NEG_INFINITY);
+    let max = field.maximum.unwrap_or(f64::INFINITY);
+    let width = field.size_bits() as u32;
+    match field.field_def.base_type.rust_type() {
+        DefBaseRustType::F32 => {
+            let parsed: f32 = value.parse().ok().filter(|v: &f32| !v.is_nan())?;
+            let clamped = (parsed as f64).max(min).min(max) as f32;
+            Some((
+                clamped.to_bits(),
+                (clamped != parsed).then_some(clamped as f64),
+            ))
+        }
+        rust_type => {
+            let parsed: i64 = value.parse().ok()?;
+            let (type_min, type_max) = match rust_type {
+                DefBaseRustType::I8 | DefBaseRustType::I16 | DefBaseRustType::I32 => {
+                    (-(1 << (width - 1)), (1 << (width - 1)) - 1)
+                }
+                _ => (0, (1 << width) - 1),
+            };
+            let low = (min.ceil() as i64).max(type_min);
+            let high = (max.floor() as i64).min(type_max);
+            let clamped = parsed.max(low).min(high);
+            Some((
+                clamped as u32,
+                (clamped != parsed).then_some(clamped as f64),
+            ))
+        }
+    }
+}
+
+impl EditList {
+    /// Parses lines of `ParamName,RowId,FieldName,Value`.
+    ///
+    /// Columns are trimmed, and the value is the rest of the line after the third comma, so it
+    /// may contain commas. Empty lines and lines starting with `#` are skipped, as well as a
+    /// header line before the first edit, whose row ID column is `RowId` in any case.
+    ///
+    /// # Errors
+    /// If a line has less than 4 columns, returns [`ParseEditListError::MissingColumns`]. If its
+    /// row ID is not an unsigned 32-bit integer, returns [`ParseEditListError::InvalidRowId`].
+    pub fn parse_csv(text: &str) -> Result<Self, ParseEditListError> {
+        let mut edits = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<_> = line.splitn(4, ',').map(str::trim).collect();
+            let &[param, row_id, field, value] = columns.as_slice()
+            else {
+                return Err(ParseEditListError::MissingColumns { line: i + 1 });
+            };
+            if edits.is_empty() && row_id.eq_ignore_ascii_case("RowId") {
+                continue;
+            }
+            let row_id = row_id.parse().map_err(|_| ParseEditListError::InvalidRowId {
+                line: i + 1,
+                row_id: row_id.to_owned(),
+            })?;
+            edits.push(Edit {
+                param: param.to_owned(),
+                row_id,
+                field: field.to_owned(),
+                value: value.to_owned(),
+            });
+        }
+        Ok(Self { edits })
+    }
+
+    /// Resolves the write of `edit`, returning it along with the value written if it was
+    /// clamped.
+    fn resolve<'d>(
+        edit: &Edit,
+        paramdex: &'d Paramdex,
+        params: &[ParamFile<'_>],
+    ) -> Result<(ResolvedEdit<'d>, Option<f64>), EditWarningKind> {
+        let def = paramdex.def_with_meta(&edit.param).ok_or(EditWarningKind::UnknownParam)?;
+        let param_type = def.def.param_type.as_str();
+        let param =
+            (params.iter().position(|p| has_param_type(p, param_type))).ok_or_else(|| {
+                EditWarningKind::MissingParam {
+                    param_type: param_type.to_owned(),
+                }
+            })?;
+        let row = params[param].by_id(edit.row_id).ok_or(EditWarningKind::UnknownRow)?;
+        let field = find_field(def, &edit.field).ok_or(EditWarningKind::UnknownField)?;
+        let scalar = (!def.def.big_endian)
+            .then(|| ScalarField::locate(field, row.len()))
+            .flatten()
+            .ok_or(EditWarningKind::UnsupportedField)?;
+        let (bits, clamped) =
+            parse_value(field, &edit.value).ok_or(EditWarningKind::InvalidValue)?;
+        let resolved = ResolvedEdit {
+            param_type,
+            param,
+            field: scalar,
+            bits,
+        };
+        Ok((resolved, clamped))
+    }
+
+    /// Applies the edits to `params`, all the params of a regulation, creating a patch with
+    /// `manager` for each edited row. The whole list can then be reverted with
+    /// [`AppliedEdits::revert`].
+    ///
+    /// Edits of a row are applied in list order, so the last edit of a field wins, and are
+    /// recorded as a single patch. Edits which can't be resolved are skipped, and reported in
+    /// [`AppliedEdits::warnings`] along with the values which were clamped.
+    ///
+    /// # Errors
+    /// - If the size of an edited row is not a multiple of the block size, returns
+    ///   [`ApplyEditsError::UnalignedRowSize`].
+    /// - If the manager can't create the patch of a row, returns
+    ///   [`ApplyEditsError::CreatePatch`].
+    ///
+    /// On error, the rows edited so far are restored.
+    pub fn apply<'a, P: RowPatcher<'a>>(
+        &self,
+        paramdex: &Paramdex,
+        manager: &mut ParamPatchManager<'a, P>,
+        params: &mut [ParamFile<'_>],
+    ) -> Result<AppliedEdits<'a>, ApplyEditsError> {
+        let mut warnings = Vec::new();
+        let mut rows: Vec<RowEdits> = Vec::new();
+        let mut row_indices = HashMap::new();
+        for (index, edit) in self.edits.iter().enumerate() {
+            let (resolved, clamped) = match Self::resolve(edit, paramdex, params) {
+                Ok(resolved) => resolved,
+                Err(kind) => {
+                    warnings.push(EditWarning { index, kind });
+                    continue;
+                }
+            };
+            if let Some(written) = clamped {
+                let kind = EditWarningKind::Clamped { written };
+                warnings.push(EditWarning { index, kind });
+            }
+            let key = (resolved.param, edit.row_id);
+            let i = *row_indices.entry(key).or_insert_with(|| {
+                rows.push(RowEdits {
+                    param_type: resolved.param_type,
+                    param: resolved.param,
+                    row_id: edit.row_id,
+                    writes: Vec::new(),
+                });
+                rows.len() - 1
+            });
+            rows[i].writes.push((resolved.field, resolved.bits));
+        }
+
+        let mut applied = AppliedEdits {
+            handles: Vec::with_capacity(rows.len()),
+            warnings,
+        };
+        for row_edits in &rows {
+            match Self::apply_row(row_edits, manager, params) {
+                Ok(handle) => applied.handles.push(handle),
+                Err(e) => {
+                    applied.revert(manager, params).unwrap();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(applied)
+    }
+
+    fn apply_row<'a, P: RowPatcher<'a>>(
+        row_edits: &RowEdits<'_>,
+        manager: &mut ParamPatchManager<'a, P>,
+        params: &mut [ParamFile<'_>],
+    ) -> Result<PatchHandle<'a>, ApplyEditsError> {
+        let mut row = params[row_edits.param].by_id_mut(row_edits.row_id).unwrap();
+        let before = row.as_blocks::<Block>()?.to_vec();
+        for &(field, bits) in &row_edits.writes {
+            field.write(row.data_mut(), bits);
+        }
+
+        let after = row.as_blocks()?;
+        match manager.create_patch(row_edits.param_type, row_edits.row_id, &before, after) {
+            Ok(handle) => Ok(handle),
+            Err(e) => {
+                row.as_blocks_mut::<Block>()?.copy_from_slice(&before);
+                Err(e.into())
+            }
+        }
+    }
+}
@@ -1,6 +1,23 @@
-use std::ffi::{c_char, c_int};
+//! Calls into the Lua engine of Cheat Engine through the CELUA exports of CE.
+//!
+//! [`Session`] wraps the raw exports, which are only linked with the `celua` feature. Sessions
+//! are generic over a [`CeluaBackend`], so that code using them can be tested off-Windows with a
+//! mock backend.
 
-#[link(name = "CE", kind = "raw-dylib")]
+#[cfg(feature = "celua")]
+use std::ffi::c_char;
+use std::{
+    ffi::{c_int, CStr, CString},
+    fmt,
+};
+
+// CE exports undecorated names, which raw-dylib does not assume for 32-bit x86
+#[cfg(feature = "celua")]
+#[cfg_attr(not(target_arch = "x86"), link(name = "CE", kind = "raw-dylib"))]
+#[cfg_attr(
+    target_arch = "x86",
+    link(name = "CE", kind = "raw-dylib", import_name_type = "undecorated")
+)]
 extern "C" {
     /// Initializes the CELUA DLL.
     ///
@@ -52,3 +69,191 @@ extern "C" {
         is_async: c_int,
     ) -> usize;
 }
+
+/// Safe surface of the CELUA exports, implemented by [`CeBackend`] with the `celua` feature.
+///
+/// Implement it with a mock to test code using a [`Session`] without CE.
+pub trait CeluaBackend {
+    /// Connects to the lua server `name`, returning true on success.
+    fn initialize(&self, name: &CStr) -> bool;
+
+    /// Executes `code`, on the main CE UI thread if `is_async` is false.
+    fn execute_function(&self, code: &CStr, parameter: usize, is_async: bool) -> usize;
+
+    /// Returns the reference ID of the global function `name`, which is not positive if there is
+    /// no such function.
+    fn function_reference(&self, name: &CStr) -> c_int;
+
+    /// Calls the function with reference ID `ref_id`.
+    fn execute_function_by_reference(
+        &self,
+        ref_id: c_int,
+        parameters: &[usize],
+        is_async: bool,
+    ) -> usize;
+}
+
+/// Backend calling the CELUA exports of CE.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CeBackend;
+
+#[cfg(feature = "celua")]
+impl CeluaBackend for CeBackend {
+    fn initialize(&self, name: &CStr) -> bool {
+        unsafe { CELUA_Initialize(name.as_ptr()) != 0 }
+    }
+
+    fn execute_function(&self, code: &CStr, parameter: usize, is_async: bool) -> usize {
+        if is_async {
+            unsafe { CELUA_ExecuteFunctionAsync(code.as_ptr(), parameter) }
+        }
+        else {
+            unsafe { CELUA_ExecuteFunction(code.as_ptr(), parameter) }
+        }
+    }
+
+    fn function_reference(&self, name: &CStr) -> c_int {
+        unsafe { CELUA_GetFunctionReferenceFromName(name.as_ptr()) }
+    }
+
+    fn execute_function_by_reference(
+        &self,
+        ref_id: c_int,
+        parameters: &[usize],
+        is_async: bool,
+    ) -> usize {
+        unsafe {
+            CELUA_ExecuteFunctionByReference(
+                ref_id,
+                parameters.len(),
+                parameters.as_ptr(),
+                is_async as c_int,
+            )
+        }
+    }
+}
+
+/// Error returned by [`Session`] methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CeluaError {
+    /// A string passed to CE contains a NUL byte at `position`.
+    InteriorNul { position: usize },
+    /// CE could not connect to the lua server `server_name`.
+    InitializeFailed { server_name: String },
+    /// There is no global lua function `name`.
+    UnknownFunction { name: String },
+}
+
+impl fmt::Display for CeluaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InteriorNul { position } => {
+                write!(f, "string passed to CE has a NUL byte at {position}")
+            }
+            Self::InitializeFailed { server_name } => {
+                write!(f, "failed to connect to the lua server {server_name:?}")
+            }
+            Self::UnknownFunction { name } => write!(f, "no lua function {name:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CeluaError {}
+
+fn c_string(s: &str) -> Result<CString, CeluaError> {
+    CString::new(s).map_err(|e| CeluaError::InteriorNul {
+        position: e.nul_position(),
+    })
+}
+
+/// Connection to a lua server of CE.
+#[derive(Debug)]
+pub struct Session<B = CeBackend> {
+    backend: B,
+}
+
+#[cfg(feature = "celua")]
+impl Session {
+    /// Connects to the lua server `server_name` through the CELUA exports of CE.
+    ///
+    /// # Errors
+    /// - If `server_name` contains a NUL byte, returns [`CeluaError::InteriorNul`].
+    /// - If CE fails to connect, returns [`CeluaError::InitializeFailed`].
+    pub fn initialize(server_name: &str) -> Result<Self, CeluaError> {
+        Self::with_backend(CeBackend, server_name)
+    }
+}
+
+impl<B: CeluaBackend> Session<B> {
+    /// Same as [`Session::initialize`], through the given backend.
+    pub fn with_backend(backend: B, server_name: &str) -> Result<Self, CeluaError> {
+        if !backend.initialize(&c_string(server_name)?) {
+            return Err(CeluaError::InitializeFailed {
+                server_name: server_name.to_owned(),
+            });
+        }
+        Ok(Self { backend })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Executes `code` on the main CE UI thread, where `parameter` is the global `parameter`.
+    ///
+    /// Returns the value returned by the code if it is an integer, and an unspecified value
+    /// otherwise.
+    ///
+    /// # Errors
+    /// If `code` contains a NUL byte, returns [`CeluaError::InteriorNul`].
+    pub fn execute(&self, code: &str, parameter: usize) -> Result<usize, CeluaError> {
+        Ok(self.backend.execute_function(&c_string(code)?, parameter, false))
+    }
+
+    /// Same as [`Session::execute`], but runs the code in the lua server without waiting for the
+    /// UI thread.
+    pub fn execute_async(&self, code: &str, parameter: usize) -> Result<usize, CeluaError> {
+        Ok(self.backend.execute_function(&c_string(code)?, parameter, true))
+    }
+
+    /// Returns a reference to the global lua function `name`, to call it without compiling code.
+    ///
+    /// # Errors
+    /// - If `name` contains a NUL byte, returns [`CeluaError::InteriorNul`].
+    /// - If there is no such function, returns [`CeluaError::UnknownFunction`].
+    pub fn function_ref(&self, name: &str) -> Result<FunctionRef<'_, B>, CeluaError> {
+        let ref_id = self.backend.function_reference(&c_string(name)?);
+        if ref_id <= 0 {
+            return Err(CeluaError::UnknownFunction {
+                name: name.to_owned(),
+            });
+        }
+        Ok(FunctionRef {
+            session: self,
+            ref_id,
+        })
+    }
+}
+
+/// Reference to a lua function, obtained with [`Session::function_ref`].
+#[derive(Debug)]
+pub struct FunctionRef<'s, B = CeBackend> {
+    session: &'s Session<B>,
+    ref_id: c_int,
+}
+
+impl<B: CeluaBackend> FunctionRef<'_, B> {
+    /// Reference ID of the function in CE.
+    pub fn ref_id(&self) -> c_int {
+        self.ref_id
+    }
+
+    /// Calls the function with `params`, in a separate thread if `async_` is set and on the main
+    /// CE UI thread otherwise.
+    ///
+    /// Returns the value returned by the function if it is an integer, and an unspecified value
+    /// otherwise.
+    pub fn call(&self, params: &[usize], async_: bool) -> usize {
+        (self.session.backend).execute_function_by_reference(self.ref_id, params, async_)
+    }
+}
@@ -0,0 +1,227 @@
+//! Field-granular differences between two versions of a param, e.g. a vanilla regulation and a
+//! modded copy, with [`param_diff`].
+//!
+//! The resulting [`ParamDiff`] is serializable with the `serde` feature, so that it can be saved
+//! and later replayed on live params as patches with [`ChangedRow::create_patch`].
+
+use std::{cmp::Ordering, fmt};
+
+use field_metadata::Block;
+
+use crate::{
+    fields::FieldBlock,
+    param_file::{ParamFile, Row, UnalignedRowSize},
+    patchers::base::{RowPatchId, RowPatcher},
+    util::unaligned::Unaligned,
+};
+
+/// Change of a single field of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct FieldDiff {
+    /// Index of the first field block of the field.
+    pub field_start: u16,
+    /// Bits of the field in each of its blocks in the base row, masked by the field block.
+    pub before: Vec<Block>,
+    /// Bits of the field in each of its blocks in the modified row, masked by the field block.
+    pub after: Vec<Block>,
+}
+
+/// Row present in both params, whose fields differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct ChangedRow {
+    pub id: u32,
+    /// Changed fields, in field block order.
+    pub fields: Vec<FieldDiff>,
+}
+
+impl ChangedRow {
+    /// Writes the modified value of each changed field to `row`, leaving the bits of other
+    /// fields untouched.
+    ///
+    /// `field_blocks` must be the ones the diff was computed with.
+    pub fn apply(&self, field_blocks: &[FieldBlock<Block>], row: &mut [Unaligned<Block>]) {
+        for field in &self.fields {
+            let start = field.field_start as usize;
+            for (fb, &after) in field_blocks[start..].iter().zip(&field.after) {
+                let block = &mut row[fb.offset as usize];
+                block.write((block.read() & !fb.mask) | (after & fb.mask));
+            }
+        }
+    }
+
+    /// Applies the changes to `row` with [`ChangedRow::apply`], and records them as a patch of
+    /// `patcher`.
+    ///
+    /// Returns `None` if the patcher rejected the patch, in which case the row is left as it was.
+    pub fn create_patch<'a, P: RowPatcher<'a, Block>>(
+        &self,
+        patcher: &mut P,
+        field_blocks: &[FieldBlock<Block>],
+        row: &mut [Unaligned<Block>],
+    ) -> Option<RowPatchId> {
+        let before = row.to_vec();
+        self.apply(field_blocks, row);
+        let id = patcher.create_patch(&before, row);
+        if id.is_none() {
+            row.copy_from_slice(&before);
+        }
+        id
+    }
+}
+
+/// Result of [`param_diff`]. Rows are ordered by ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct ParamDiff {
+    /// IDs of the rows only present in the modified param.
+    pub added: Vec<u32>,
+    /// IDs of the rows only present in the base param.
+    pub removed: Vec<u32>,
+    pub changed: Vec<ChangedRow>,
+}
+
+impl ParamDiff {
+    /// Returns true if both params have the same rows and field values.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Error returned by [`param_diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamDiffError {
+    /// The row with ID `id` has a different size in both params.
+    RowSizeMismatch {
+        id: u32,
+        base: usize,
+        modified: usize,
+    },
+    /// The field blocks extend past the end of the row with ID `id`, which is `row_size` bytes
+    /// long.
+    FieldBlocksOutOfBounds {
+        id: u32,
+        row_size: usize,
+    },
+    UnalignedRowSize(UnalignedRowSize),
+}
+
+impl fmt::Display for ParamDiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::RowSizeMismatch { id, base, modified } => write!(
+                f,
+                "row {id} is {base} bytes long in the base param, but {modified} in the modified one"
+            ),
+            Self::FieldBlocksOutOfBounds { id, row_size } => write!(
+                f,
+                "field blocks extend past the end of row {id}, which is {row_size} bytes long"
+            ),
+            Self::UnalignedRowSize(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParamDiffError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::UnalignedRowSize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<UnalignedRowSize> for ParamDiffError {
+    fn from(e: UnalignedRowSize) -> Self {
+        Self::UnalignedRowSize(e)
+    }
+}
+
+/// Returns the changed fields of a row present in both params.
+fn diff_row(
+    field_blocks: &[FieldBlock<Block>],
+    base: Row<'_>,
+    modified: Row<'_>,
+) -> Result<Vec<FieldDiff>, ParamDiffError> {
+    if base.len() != modified.len() {
+        return Err(ParamDiffError::RowSizeMismatch {
+            id: base.id(),
+            base: base.len(),
+            modified: modified.len(),
+        });
+    }
+    let (before, after) = (base.as_blocks::<Block>()?, modified.as_blocks::<Block>()?);
+    if field_blocks.last().is_some_and(|fb| fb.offset as usize >= before.len()) {
+        return Err(ParamDiffError::FieldBlocksOutOfBounds {
+            id: base.id(),
+            row_size: base.len(),
+        });
+    }
+
+    let mut fields = Vec::new();
+    for field in field_blocks.chunk_by(|a, b| a.field_start == b.field_start) {
+        let masked = |row: &[Unaligned<Block>]| -> Vec<Block> {
+            field.iter().map(|fb| row[fb.offset as usize].read() & fb.mask).collect()
+        };
+        let (before, after) = (masked(before), masked(after));
+        if before != after {
+            fields.push(FieldDiff {
+                field_start: field[0].field_start,
+                before,
+                after,
+            });
+        }
+    }
+    Ok(fields)
+}
+
+/// Computes the rows added, removed and changed from `base` to `modified`, whose rows are
+/// described by `field_blocks`.
+///
+/// Rows are compared field by field, so changes to bytes covered by no field block (e.g.
+/// padding) are ignored.
+///
+/// # Errors
+/// - If a row present in both params has different sizes, returns
+///   [`ParamDiffError::RowSizeMismatch`].
+/// - If a row is smaller than the field blocks, returns [`ParamDiffError::FieldBlocksOutOfBounds`].
+/// - If a row size is not a multiple of the block size, returns
+///   [`ParamDiffError::UnalignedRowSize`].
+pub fn param_diff(
+    base: &ParamFile<'_>,
+    modified: &ParamFile<'_>,
+    field_blocks: &[FieldBlock<Block>],
+) -> Result<ParamDiff, ParamDiffError> {
+    let mut diff = ParamDiff::default();
+    let (mut base_rows, mut modified_rows) = (base.rows().peekable(), modified.rows().peekable());
+    loop {
+        let order = match (base_rows.peek(), modified_rows.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(b), Some(m)) => b.id().cmp(&m.id()),
+        };
+        match order {
+            Ordering::Less => diff.removed.push(base_rows.next().unwrap().id()),
+            Ordering::Greater => diff.added.push(modified_rows.next().unwrap().id()),
+            Ordering::Equal => {
+                let (b, m) = (base_rows.next().unwrap(), modified_rows.next().unwrap());
+                let fields = diff_row(field_blocks, b, m)?;
+                if !fields.is_empty() {
+                    diff.changed.push(ChangedRow { id: b.id(), fields });
+                }
+            }
+        }
+    }
+    Ok(diff)
+}
@@ -0,0 +1,356 @@
+//! Crate-wide error type, for consumers which present or bucket failures uniformly.
+//!
+//! Each subsystem keeps returning its own specific error type. [`PpatchError`] wraps any of them,
+//! and classifies them with a stable numeric [`code`](PpatchError::code) and an
+//! [`ErrorCategory`]. The specific error is available through [`std::error::Error::source`].
+
+use std::fmt;
+
+use field_metadata::{
+    FbRepoError, FieldBlockViolation, InvalidFieldBlockRepo, InvalidFieldBlocks, LoadFbRepoError,
+};
+
+#[cfg(feature = "capi")]
+use crate::ffi::FfiError;
+#[cfg(feature = "paramdex")]
+use crate::{
+    apply::{ApplyEditsError, ParseEditListError},
+    param_file::write::WriteParamError,
+};
+use crate::{
+    celua::CeluaError,
+    diff::ParamDiffError,
+    from::allocator::AllocError,
+    param_builder::InsertRowError,
+    param_file::{FromBytesError, IndexError, UnalignedRowSize},
+    patchers::{
+        base::{PatchRowError, RawAccessError, RestorePatchError},
+        linked_list::{ReplaceRowError, ThawError},
+        manager::CreatePatchError,
+        patch_set::PatchSetError,
+    },
+};
+
+/// Broad class of a [`PpatchError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Reading or writing files failed.
+    Io,
+    /// Some input is malformed, e.g. a truncated param file or invalid XML.
+    Parse,
+    /// Some input is well-formed, but breaks an invariant the crate relies on.
+    Validation,
+    /// The game is not in a state which allows the operation.
+    GameState,
+    /// The operation conflicts with other outstanding changes.
+    Conflict,
+    /// The input uses a format or feature the crate doesn't support.
+    Unsupported,
+}
+
+/// Error of any ppatch subsystem.
+///
+/// Codes identify the failure rather than the API which returned it, so e.g. an
+/// [`UnalignedRowSize`] has the same code whether it is returned directly or wrapped in a
+/// [`PatchRowError`]. Codes are grouped by subsystem:
+/// - `1`: IO;
+/// - `1xx`: param files;
+/// - `2xx`: patchers;
+/// - `3xx`: field blocks;
+/// - `4xx`: paramdex (with the `paramdex` feature);
+/// - `5xx`: CELUA;
+/// - `6xx`: edit lists (with the `paramdex` feature);
+/// - `7xx`: game structures;
+/// - `8xx`: C API (with the `capi` feature).
+///
+/// Codes of existing failures never change. New failures get new codes.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PpatchError {
+    Io(std::io::Error),
+    FromBytes(FromBytesError),
+    UnalignedRowSize(UnalignedRowSize),
+    Index(IndexError),
+    InsertRow(InsertRowError),
+    ParamDiff(ParamDiffError),
+    RestorePatch(RestorePatchError),
+    PatchRow(PatchRowError),
+    RawAccess(RawAccessError),
+    ReplaceRow(ReplaceRowError),
+    Thaw(ThawError),
+    CreatePatch(CreatePatchError),
+    PatchSet(PatchSetError),
+    InvalidFieldBlocks(InvalidFieldBlocks),
+    InvalidFieldBlockRepo(InvalidFieldBlockRepo),
+    LoadFbRepo(LoadFbRepoError),
+    FbRepo(FbRepoError),
+    Celua(CeluaError),
+    Alloc(AllocError),
+    #[cfg(feature = "paramdex")]
+    ParamdexLoad(paramdex::ParamdexLoadError),
+    #[cfg(feature = "paramdex")]
+    ParamdexFetch(paramdex::git_fetch::ParamdexFetchError),
+    #[cfg(feature = "paramdex")]
+    UnofficialField(paramdex::unofficial::UnofficialFieldError),
+    #[cfg(feature = "paramdex")]
+    ParseEditList(ParseEditListError),
+    #[cfg(feature = "paramdex")]
+    ApplyEdits(ApplyEditsError),
+    #[cfg(feature = "paramdex")]
+    WriteParam(WriteParamError),
+    #[cfg(feature = "capi")]
+    Ffi(FfiError),
+}
+
+fn violation_code(violation: FieldBlockViolation) -> u32 {
+    match violation {
+        FieldBlockViolation::FieldStartAfterEntry => 301,
+        FieldBlockViolation::NonContiguousField => 302,
+        FieldBlockViolation::DecreasingOffset => 303,
+        FieldBlockViolation::EmptyMask => 304,
+    }
+}
+
+impl PpatchError {
+    /// Returns the stable numeric code of the failure.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::Io(_) | Self::PatchSet(PatchSetError::Io(_)) => 1,
+            Self::FromBytes(e) => match e {
+                FromBytesError::BufferTooSmall => 101,
+                FromBytesError::UnsupportedFile { .. } => 102,
+                FromBytesError::OutOfBoundsOffset { .. } => 103,
+                FromBytesError::IntersectingData { .. } => 104,
+                FromBytesError::UnsortedRowDescs { .. } => 105,
+                FromBytesError::DuplicateIds { .. } => 106,
+                FromBytesError::InvalidNameOffset { .. } => 107,
+                FromBytesError::AmbiguousRowSize { .. } => 108,
+            },
+            Self::UnalignedRowSize(_)
+            | Self::PatchRow(PatchRowError::UnalignedRowSize(_))
+            | Self::RawAccess(RawAccessError::PatchRow(PatchRowError::UnalignedRowSize(_)))
+            | Self::ParamDiff(ParamDiffError::UnalignedRowSize(_))
+            | Self::PatchSet(PatchSetError::UnalignedRowSize(_)) => 110,
+            Self::Index(_) => 111,
+            Self::InsertRow(InsertRowError::DuplicateId(_)) => 120,
+            Self::InsertRow(InsertRowError::RowTooSmall { .. }) => 121,
+            Self::InsertRow(InsertRowError::TooManyRows) => 122,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(WriteParamError::DuplicateId(_)) => 120,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(WriteParamError::TooManyRows) => 122,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(WriteParamError::UnknownRowSize { .. }) => 140,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(WriteParamError::RowSizeMismatch { .. }) => 141,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(WriteParamError::ParamTypeTooLong { .. }) => 142,
+            Self::ParamDiff(ParamDiffError::RowSizeMismatch { .. }) => 130,
+            Self::ParamDiff(ParamDiffError::FieldBlocksOutOfBounds { .. })
+            | Self::PatchSet(PatchSetError::FieldBlocksOutOfBounds { .. })
+            | Self::CreatePatch(CreatePatchError::FieldBlocksOutOfBounds { .. })
+            | Self::PatchSet(PatchSetError::CreatePatch(
+                CreatePatchError::FieldBlocksOutOfBounds { .. },
+            )) => 131,
+            Self::RestorePatch(RestorePatchError::ForeignId) => 201,
+            Self::RestorePatch(RestorePatchError::UnknownId) => 202,
+            Self::RestorePatch(RestorePatchError::SizeMismatch { .. })
+            | Self::CreatePatch(CreatePatchError::SizeMismatch { .. })
+            | Self::PatchSet(PatchSetError::CreatePatch(CreatePatchError::SizeMismatch {
+                ..
+            })) => 241,
+            Self::PatchRow(PatchRowError::PatchRejected)
+            | Self::RawAccess(RawAccessError::PatchRow(PatchRowError::PatchRejected))
+            | Self::CreatePatch(CreatePatchError::PatchRejected)
+            | Self::PatchSet(PatchSetError::CreatePatch(CreatePatchError::PatchRejected)) => 210,
+            Self::ReplaceRow(ReplaceRowError::SizeMismatch { .. }) => 220,
+            Self::ReplaceRow(ReplaceRowError::TooManyPatches) => 221,
+            Self::Thaw(ThawError::FieldBlocksMismatch { .. }) => 230,
+            Self::Thaw(ThawError::InconsistentState) => 231,
+            Self::CreatePatch(CreatePatchError::UnknownParamType)
+            | Self::PatchSet(PatchSetError::UnknownParamType { .. })
+            | Self::PatchSet(PatchSetError::CreatePatch(CreatePatchError::UnknownParamType)) => 240,
+            Self::RawAccess(RawAccessError::OutOfBounds { .. }) => 250,
+            Self::RawAccess(RawAccessError::Unmapped { .. }) => 251,
+            Self::PatchSet(PatchSetError::NotAPatchSet) => 260,
+            Self::PatchSet(PatchSetError::UnsupportedVersion { .. }) => 261,
+            Self::PatchSet(PatchSetError::Truncated) => 262,
+            Self::PatchSet(PatchSetError::RegulationMismatch { .. }) => 263,
+            Self::PatchSet(PatchSetError::InvalidField { .. }) => 264,
+            Self::PatchSet(PatchSetError::MissingParam { .. }) => 265,
+            Self::PatchSet(PatchSetError::MissingRow { .. }) => 266,
+            Self::CreatePatch(CreatePatchError::InvalidFieldBlocks(e))
+            | Self::PatchSet(PatchSetError::CreatePatch(CreatePatchError::InvalidFieldBlocks(e)))
+            | Self::InvalidFieldBlocks(e) => violation_code(e.violation),
+            Self::InvalidFieldBlockRepo(e) => violation_code(e.error.violation),
+            Self::LoadFbRepo(e) | Self::FbRepo(FbRepoError::Header(e)) => match e {
+                LoadFbRepoError::NotARepo => 310,
+                LoadFbRepoError::UnsupportedVersion { .. } => 311,
+                LoadFbRepoError::BlockSizeMismatch { .. } => 312,
+            },
+            Self::FbRepo(FbRepoError::GameMismatch { .. }) => 313,
+            Self::FbRepo(FbRepoError::ChecksumMismatch) => 314,
+            Self::FbRepo(FbRepoError::InvalidArchive(_)) => 315,
+            Self::Celua(CeluaError::InteriorNul { .. }) => 501,
+            Self::Celua(CeluaError::InitializeFailed { .. }) => 502,
+            Self::Celua(CeluaError::UnknownFunction { .. }) => 503,
+            Self::Alloc(_) => 701,
+            #[cfg(feature = "capi")]
+            Self::Ffi(FfiError::NullPointer) => 801,
+            #[cfg(feature = "capi")]
+            Self::Ffi(FfiError::InvalidParamName) => 802,
+            #[cfg(feature = "capi")]
+            Self::Ffi(FfiError::Panic) => 803,
+            #[cfg(feature = "paramdex")]
+            Self::ParamdexLoad(e) => {
+                use paramdex::ParamdexLoadError as E;
+                match e {
+                    E::IoError(_) => 401,
+                    E::XmlError(_) => 402,
+                    E::JsonError(_) => 403,
+                    E::UnknownEnumsSchema { .. } => 404,
+                }
+            }
+            #[cfg(feature = "paramdex")]
+            Self::ParamdexFetch(e) => {
+                use paramdex::git_fetch::ParamdexFetchError as E;
+                match e {
+                    E::IoError(_) => 411,
+                    E::CommandFailed { .. } => 412,
+                    E::JsonError(_) => 413,
+                    E::IncompleteCheckout { .. } => 414,
+                }
+            }
+            #[cfg(feature = "paramdex")]
+            Self::UnofficialField(e) => {
+                use paramdex::unofficial::UnofficialFieldError as E;
+                match e {
+                    E::UnknownHost { .. } => 421,
+                    E::OverlapsOfficialField { .. } => 422,
+                    E::OutOfBounds { .. } => 423,
+                    E::Overlap { .. } => 424,
+                    E::NameTaken { .. } => 425,
+                }
+            }
+            #[cfg(feature = "paramdex")]
+            Self::ParseEditList(ParseEditListError::MissingColumns { .. }) => 601,
+            #[cfg(feature = "paramdex")]
+            Self::ParseEditList(ParseEditListError::InvalidRowId { .. }) => 602,
+            #[cfg(feature = "paramdex")]
+            Self::ApplyEdits(e) => match *e {
+                ApplyEditsError::UnalignedRowSize(e) => Self::UnalignedRowSize(e).code(),
+                ApplyEditsError::CreatePatch(e) => Self::CreatePatch(e).code(),
+            },
+        }
+    }
+
+    /// Returns the broad class of the failure.
+    pub fn category(&self) -> ErrorCategory {
+        use ErrorCategory::*;
+        match self.code() {
+            1 | 401 | 411 | 412 => Io,
+            102 | 108 | 110 | 142 | 261 | 311 | 312 | 313 => Unsupported,
+            101 | 103 | 104 | 107 | 231 | 260 | 262 | 310 | 314 | 315 | 402 | 403 | 404 | 413
+            | 601 | 602 | 802 => Parse,
+            202 | 210 | 221 | 424 => Conflict,
+            502 | 701 => GameState,
+            _ => Validation,
+        }
+    }
+}
+
+impl fmt::Display for PpatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = std::error::Error::source(self).unwrap();
+        write!(f, "E{:03}: {source}", self.code())
+    }
+}
+
+impl std::error::Error for PpatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(match self {
+            Self::Io(e) => e,
+            Self::FromBytes(e) => e,
+            Self::UnalignedRowSize(e) => e,
+            Self::Index(e) => e,
+            Self::InsertRow(e) => e,
+            Self::ParamDiff(e) => e,
+            Self::RestorePatch(e) => e,
+            Self::PatchRow(e) => e,
+            Self::RawAccess(e) => e,
+            Self::ReplaceRow(e) => e,
+            Self::Thaw(e) => e,
+            Self::CreatePatch(e) => e,
+            Self::PatchSet(e) => e,
+            Self::InvalidFieldBlocks(e) => e,
+            Self::InvalidFieldBlockRepo(e) => e,
+            Self::LoadFbRepo(e) => e,
+            Self::FbRepo(e) => e,
+            Self::Celua(e) => e,
+            Self::Alloc(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::ParamdexLoad(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::ParamdexFetch(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::UnofficialField(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::ParseEditList(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::ApplyEdits(e) => e,
+            #[cfg(feature = "paramdex")]
+            Self::WriteParam(e) => e,
+            #[cfg(feature = "capi")]
+            Self::Ffi(e) => e,
+        })
+    }
+}
+
+macro_rules! impl_from {
+    ($($(#[$attr:meta])* $variant:ident($error:ty),)*) => {
+        $(
+            $(#[$attr])*
+            impl From<$error> for PpatchError {
+                fn from(value: $error) -> Self {
+                    Self::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    Io(std::io::Error),
+    FromBytes(FromBytesError),
+    UnalignedRowSize(UnalignedRowSize),
+    Index(IndexError),
+    InsertRow(InsertRowError),
+    ParamDiff(ParamDiffError),
+    RestorePatch(RestorePatchError),
+    PatchRow(PatchRowError),
+    RawAccess(RawAccessError),
+    ReplaceRow(ReplaceRowError),
+    Thaw(ThawError),
+    CreatePatch(CreatePatchError),
+    PatchSet(PatchSetError),
+    InvalidFieldBlocks(InvalidFieldBlocks),
+    InvalidFieldBlockRepo(InvalidFieldBlockRepo),
+    LoadFbRepo(LoadFbRepoError),
+    FbRepo(FbRepoError),
+    Celua(CeluaError),
+    Alloc(AllocError),
+    #[cfg(feature = "paramdex")]
+    ParamdexLoad(paramdex::ParamdexLoadError),
+    #[cfg(feature = "paramdex")]
+    ParamdexFetch(paramdex::git_fetch::ParamdexFetchError),
+    #[cfg(feature = "paramdex")]
+    UnofficialField(paramdex::unofficial::UnofficialFieldError),
+    #[cfg(feature = "paramdex")]
+    ParseEditList(ParseEditListError),
+    #[cfg(feature = "paramdex")]
+    ApplyEdits(ApplyEditsError),
+    #[cfg(feature = "paramdex")]
+    WriteParam(WriteParamError),
+    #[cfg(feature = "capi")]
+    Ffi(FfiError),
+}
@@ -0,0 +1,197 @@
+//! C ABI over [`ParamPatchManager`], for mod frameworks written in other languages.
+//!
+//! Available with the `capi` feature. The functions are declared in `include/ppatch.h`, which is
+//! generated with `cbindgen --config cbindgen.toml --output include/ppatch.h` from the crate
+//! directory.
+//!
+//! Failures are reported as the negated [code](PpatchError::code) of the [`PpatchError`], e.g.
+//! `-240` when the embedded field blocks have no entry for a param type, or `-241` when the length
+//! of a row is not the one it had when its first patch was created. The failures specific to
+//! the C ABI are [`FfiError`]s. Panics are caught at the boundary and reported as
+//! [`FfiError::Panic`], after which the manager may be missing the patch being created or
+//! restored.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CStr},
+    fmt,
+    mem::size_of,
+    panic::{self, AssertUnwindSafe},
+    slice,
+};
+
+use field_metadata::Block;
+
+use crate::{
+    error::PpatchError,
+    game::Game,
+    param_file::UnalignedRowSize,
+    patchers::{
+        base::RestorePatchError,
+        linked_list::LinkedListPatcher,
+        manager::{ParamPatchManager, PatchHandle},
+    },
+};
+
+/// Value of [`Game::Ds3`] for [`ppatch_manager_new`].
+pub const PPATCH_GAME_DS3: u32 = 0;
+/// Value of [`Game::Er`] for [`ppatch_manager_new`].
+pub const PPATCH_GAME_ER: u32 = 1;
+/// Value of [`Game::Ac6`] for [`ppatch_manager_new`].
+pub const PPATCH_GAME_AC6: u32 = 2;
+
+/// Failure specific to the C ABI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiError {
+    /// A pointer argument is null.
+    NullPointer,
+    /// The param name is not valid UTF-8.
+    InvalidParamName,
+    /// The call panicked.
+    Panic,
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NullPointer => f.write_str("null pointer passed to the C API"),
+            Self::InvalidParamName => f.write_str("param name is not valid UTF-8"),
+            Self::Panic => f.write_str("panic caught at the C API boundary"),
+        }
+    }
+}
+
+impl std::error::Error for FfiError {}
+
+/// Patch manager created by [`ppatch_manager_new`], which hands out integer handles to the
+/// patches it creates.
+pub struct Manager {
+    manager: ParamPatchManager<'static, LinkedListPatcher<'static>>,
+    handles: HashMap<i64, PatchHandle<'static>>,
+    next_handle: i64,
+}
+
+/// Runs `f`, reporting a panic as [`FfiError::Panic`].
+fn guard<T>(f: impl FnOnce() -> Result<T, PpatchError>) -> Result<T, PpatchError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(Err(FfiError::Panic.into()))
+}
+
+fn check_non_null<T>(ptr: *const T) -> Result<(), PpatchError> {
+    if ptr.is_null() {
+        return Err(FfiError::NullPointer.into());
+    }
+    Ok(())
+}
+
+/// Checks that a row of `len` bytes at `ptr` is not null and holds whole blocks, returning
+/// their number.
+fn row_blocks(ptr: *const u8, len: usize) -> Result<usize, PpatchError> {
+    check_non_null(ptr)?;
+    let block_size = size_of::<Block>();
+    if !len.is_multiple_of(block_size) {
+        return Err(UnalignedRowSize {
+            row_size: len,
+            block_size,
+        }
+        .into());
+    }
+    Ok(len / block_size)
+}
+
+/// Creates a manager using the embedded field blocks of `game`, one of the `PPATCH_GAME_*`
+/// values.
+///
+/// Returns null if the field blocks of the game are not embedded in this build.
+#[no_mangle]
+pub extern "C" fn ppatch_manager_new(game: u32) -> *mut Manager {
+    let manager = guard(|| {
+        let game = Game::ALL.into_iter().find(|&g| g as u32 == game);
+        let repo = game.and_then(Game::field_block_repo);
+        Ok(repo.map(|repo| Manager {
+            manager: ParamPatchManager::new(repo),
+            handles: HashMap::new(),
+            next_handle: 1,
+        }))
+    });
+    match manager {
+        Ok(Some(manager)) => Box::into_raw(Box::new(manager)),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a manager created by [`ppatch_manager_new`]. Does nothing if `manager` is null.
+///
+/// # Safety
+/// `manager` must be null or a manager returned by [`ppatch_manager_new`] which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn ppatch_manager_free(manager: *mut Manager) {
+    if !manager.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(manager))));
+    }
+}
+
+/// Creates a patch from the changes between `before` and `after`, the `len` bytes of row
+/// `row_id` of the param of type `param_name`. See [`ParamPatchManager::create_patch`].
+///
+/// Returns the positive handle of the patch, or a negated error code. Once a row has patches,
+/// `len` must be the length it had then, or `-241` is returned.
+///
+/// # Safety
+/// - `manager` must be null or a live manager returned by [`ppatch_manager_new`].
+/// - `param_name` must be null or a NUL-terminated string.
+/// - `before` and `after` must be null or valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppatch_create_patch(
+    manager: *mut Manager,
+    param_name: *const c_char,
+    row_id: u32,
+    before: *const u8,
+    after: *const u8,
+    len: usize,
+) -> i64 {
+    let handle = guard(|| {
+        check_non_null(manager)?;
+        let manager = &mut *manager;
+        check_non_null(param_name)?;
+        let param_name = CStr::from_ptr(param_name).to_str();
+        let param_name = param_name.map_err(|_| FfiError::InvalidParamName)?;
+        let before = slice::from_raw_parts(before.cast(), row_blocks(before, len)?);
+        let after = slice::from_raw_parts(after.cast(), row_blocks(after, len)?);
+
+        let patch = manager.manager.create_patch(param_name, row_id, before, after)?;
+        let handle = manager.next_handle;
+        manager.next_handle += 1;
+        manager.handles.insert(handle, patch);
+        Ok(handle)
+    });
+    handle.unwrap_or_else(|e| -i64::from(e.code()))
+}
+
+/// Restores the patch of `handle` in `live`, the `len` bytes of its row. See
+/// [`ParamPatchManager::restore`].
+///
+/// Returns 0, or a negated error code. Handles which were already restored or were never
+/// returned by this manager are [`RestorePatchError::UnknownId`]s. If `len` is not the length of
+/// the row when the patch was created, `-241` is returned and the patch can be restored again.
+///
+/// # Safety
+/// - `manager` must be null or a live manager returned by [`ppatch_manager_new`].
+/// - `live` must be null or valid for reads and writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppatch_restore(
+    manager: *mut Manager,
+    handle: i64,
+    live: *mut u8,
+    len: usize,
+) -> i32 {
+    let restored = guard(|| {
+        check_non_null(manager)?;
+        let manager = &mut *manager;
+        let live = slice::from_raw_parts_mut(live.cast(), row_blocks(live, len)?);
+        let patch = *manager.handles.get(&handle).ok_or(RestorePatchError::UnknownId)?;
+        manager.manager.restore(patch, live)?;
+        manager.handles.remove(&handle);
+        Ok(())
+    });
+    restored.map_or_else(|e| -(e.code() as i32), |()| 0)
+}
@@ -0,0 +1,145 @@
+//! Helpers for accessing the value of a single paramdef field through its [`FieldBlock`]s.
+//!
+//! Field values are represented as little-endian byte arrays, with the first bit of the field
+//! stored in the least significant bit of the first byte. This makes them independent of how
+//! the field is split across blocks, e.g. if it starts in the middle of a block, spans multiple
+//! blocks or is a bitfield packed with its neighbors.
+
+pub use field_metadata::FieldBlock;
+use num_traits::PrimInt;
+
+use crate::util::unaligned::Unaligned;
+
+/// Returns the field blocks belonging to the field starting at index `field_start`.
+fn field_blocks<N: PrimInt>(blocks: &[FieldBlock<N>], field_start: u16) -> &[FieldBlock<N>] {
+    let start = field_start as usize;
+    let len = blocks[start..].iter().take_while(|fb| fb.field_start == field_start).count();
+    &blocks[start..start + len]
+}
+
+/// Returns the size of the field starting at index `field_start` in bits.
+pub fn field_size_bits<N: PrimInt>(blocks: &[FieldBlock<N>], field_start: u16) -> usize {
+    field_blocks(blocks, field_start)
+        .iter()
+        .map(|fb| fb.mask.count_ones() as usize)
+        .sum()
+}
+
+/// Reads the value of the field starting at index `field_start` in `blocks` from `row` into `out`.
+///
+/// Returns the size of the field in bytes. If `out` is smaller than that, only the first
+/// `out.len()` bytes of the value are written. Unused bits of the last byte are set to 0.
+///
+/// `N` must be an unsigned integer type of at most 64 bits.
+pub fn read_field_bytes<N: PrimInt>(
+    row: &[Unaligned<N>],
+    blocks: &[FieldBlock<N>],
+    field_start: u16,
+    out: &mut [u8],
+) -> usize {
+    let mut acc = 0u128;
+    let mut acc_bits = 0;
+    let mut written = 0;
+
+    for fb in field_blocks(blocks, field_start) {
+        let n_bits = fb.mask.count_ones();
+        if n_bits == 0 {
+            continue;
+        }
+        let bits =
+            (row[fb.offset as usize].read() & fb.mask).unsigned_shr(fb.mask.trailing_zeros());
+        acc |= (bits.to_u64().unwrap() as u128) << acc_bits;
+        acc_bits += n_bits;
+
+        while acc_bits >= 8 {
+            if let Some(b) = out.get_mut(written) {
+                *b = acc as u8;
+            }
+            written += 1;
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+    if acc_bits != 0 {
+        if let Some(b) = out.get_mut(written) {
+            *b = acc as u8;
+        }
+        written += 1;
+    }
+    written
+}
+
+/// Writes the value of the field starting at index `field_start` in `blocks` from `value` into
+/// `row`, leaving the bits of other fields untouched.
+///
+/// Returns the size of the field in bytes. If `value` is smaller than that, the remaining bits of
+/// the field are set to 0. Bits of `value` past the end of the field are ignored.
+///
+/// `N` must be an unsigned integer type of at most 64 bits.
+pub fn write_field_bytes<N: PrimInt>(
+    row: &mut [Unaligned<N>],
+    blocks: &[FieldBlock<N>],
+    field_start: u16,
+    value: &[u8],
+) -> usize {
+    let mut acc = 0u128;
+    let mut acc_bits = 0;
+    let mut read = 0;
+
+    for fb in field_blocks(blocks, field_start) {
+        let n_bits = fb.mask.count_ones();
+        if n_bits == 0 {
+            continue;
+        }
+        while acc_bits < n_bits {
+            acc |= (value.get(read).copied().unwrap_or(0) as u128) << acc_bits;
+            read += 1;
+            acc_bits += 8;
+        }
+
+        let bits = N::from(acc & (u128::MAX >> (128 - n_bits))).unwrap();
+        let block = &mut row[fb.offset as usize];
+        let bits = bits.unsigned_shl(fb.mask.trailing_zeros()) & fb.mask;
+        block.write((block.read() & !fb.mask) | bits);
+        acc >>= n_bits;
+        acc_bits -= n_bits;
+    }
+    read
+}
+
+/// Returns the `field_start` of each field with bits in the `len` bytes at `offset` of a row,
+/// in field block order.
+///
+/// Bytes covered by no field block (e.g. padding) are ignored, so the result is empty for ranges
+/// which only cover such bytes.
+pub fn fields_in_byte_range<N: PrimInt>(
+    blocks: &[FieldBlock<N>],
+    offset: usize,
+    len: usize,
+) -> Vec<u16> {
+    let block_size = std::mem::size_of::<N>();
+    let end = offset.saturating_add(len);
+    // Bits of the block at `block_offset` lying in the range, given that rows are native-endian
+    let range_mask = |block_offset: usize| {
+        let block_start = block_offset * block_size;
+        let first = offset.max(block_start) - block_start;
+        let last = end.min(block_start + block_size) - block_start;
+        (first..last).fold(N::zero(), |mask, byte| {
+            let byte = if cfg!(target_endian = "big") { block_size - 1 - byte } else { byte };
+            mask | N::from(0xFFu8).unwrap().unsigned_shl(8 * byte as u32)
+        })
+    };
+
+    let mut fields: Vec<u16> = blocks
+        .iter()
+        .filter(|fb| {
+            let block_start = fb.offset as usize * block_size;
+            block_start < end
+                && block_start + block_size > offset
+                && !(fb.mask & range_mask(fb.offset as usize)).is_zero()
+        })
+        .map(|fb| fb.field_start)
+        .collect();
+    fields.dedup();
+    fields
+}
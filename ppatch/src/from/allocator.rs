@@ -1,3 +1,10 @@
+use std::{
+    fmt,
+    mem::{align_of, size_of, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
 use crate::vtable::{vtable, vtable_entries, VTable};
 
 pub unsafe trait DLAllocator {
@@ -46,3 +53,275 @@ unsafe impl DLAllocator for DLAllocatorProxy {
         return *self.instance_ptr;
     }
 }
+
+/// Error returned when a [`DLAllocator`] fails to allocate a block, i.e. returns null.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    pub size: usize,
+    pub align: usize,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to allocate {} bytes aligned to {}",
+            self.size, self.align
+        )
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+/// Allocates a block of `size` bytes aligned to `align` with `allocator`.
+fn allocate_in<A: DLAllocator>(
+    allocator: &mut A,
+    size: usize,
+    align: usize,
+) -> Result<NonNull<u8>, AllocError> {
+    NonNull::new(allocator.allocate_aligned(size.max(1), align).cast())
+        .ok_or(AllocError { size, align })
+}
+
+/// Value owned by a block of a [`DLAllocator`], which is deallocated when the box is dropped.
+///
+/// The box stores a pointer to its allocator. It is `#[repr(C)]` with the pointer to the value
+/// first, and [`DLBox::into_raw`] hands the value over to the game, which frees it with the
+/// same allocator.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLBox<T, A: DLAllocator = DLAllocatorProxy> {
+    ptr: NonNull<T>,
+    allocator: NonNull<A>,
+}
+
+impl<T, A: DLAllocator> DLBox<T, A> {
+    /// Moves `value` to a block allocated by `allocator`.
+    ///
+    /// # Safety
+    /// The allocator must outlive the box and not move, since the box deallocates the value
+    /// through a pointer to it.
+    ///
+    /// # Errors
+    /// If the allocator fails, returns [`AllocError`] and drops `value`.
+    pub unsafe fn new_in(value: T, allocator: &mut A) -> Result<Self, AllocError> {
+        let ptr = allocate_in(allocator, size_of::<T>(), align_of::<T>())?.cast::<T>();
+        ptr.as_ptr().write(value);
+        Ok(Self::from_raw_in(ptr, allocator))
+    }
+
+    /// Takes ownership of the value at `ptr`, e.g. one taken from the game or returned by
+    /// [`DLBox::into_raw`].
+    ///
+    /// # Safety
+    /// `ptr` must point to an initialized `T` at the start of a block allocated by `allocator`,
+    /// which nothing else owns. The allocator must outlive the box and not move.
+    pub unsafe fn from_raw_in(ptr: NonNull<T>, allocator: &mut A) -> Self {
+        Self {
+            ptr,
+            allocator: NonNull::from(allocator),
+        }
+    }
+
+    /// Releases the value without deallocating it, e.g. to store it in a game structure.
+    pub fn into_raw(self) -> NonNull<T> {
+        ManuallyDrop::new(self).ptr
+    }
+
+    pub fn allocator(&self) -> &A {
+        // SAFETY: The allocator outlives the box
+        unsafe { self.allocator.as_ref() }
+    }
+}
+
+impl<T, A: DLAllocator> Deref for DLBox<T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The box owns an initialized value
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, A: DLAllocator> DerefMut for DLBox<T, A> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The box owns an initialized value
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T, A: DLAllocator> Drop for DLBox<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: The box owns the value and its block, and the allocator outlives it
+        unsafe {
+            std::ptr::drop_in_place(self.ptr.as_ptr());
+            self.allocator.as_mut().deallocate(self.ptr.as_ptr().cast());
+        }
+    }
+}
+
+/// Bytes owned by a block of a [`DLAllocator`], e.g. a row or string handed over to the game.
+///
+/// Same as [`DLBox`], for buffers whose size and alignment are only known at runtime. It is
+/// `#[repr(C)]` with the pointer to the bytes first, followed by their length.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLBuf<A: DLAllocator = DLAllocatorProxy> {
+    ptr: NonNull<u8>,
+    len: usize,
+    allocator: NonNull<A>,
+}
+
+impl<A: DLAllocator> DLBuf<A> {
+    /// Allocates `len` zeroed bytes aligned to `align` with `allocator`.
+    ///
+    /// # Safety
+    /// Same as [`DLBox::new_in`].
+    ///
+    /// # Errors
+    /// If the allocator fails, e.g. because `align` is not a power of two, returns
+    /// [`AllocError`].
+    pub unsafe fn new_in(len: usize, align: usize, allocator: &mut A) -> Result<Self, AllocError> {
+        let ptr = allocate_in(allocator, len, align)?;
+        ptr.as_ptr().write_bytes(0, len);
+        Ok(Self {
+            ptr,
+            len,
+            allocator: NonNull::from(allocator),
+        })
+    }
+
+    /// Same as [`DLBuf::new_in`], with a copy of `bytes`.
+    ///
+    /// # Safety
+    /// Same as [`DLBox::new_in`].
+    ///
+    /// # Errors
+    /// Same as [`DLBuf::new_in`].
+    pub unsafe fn from_slice_in(
+        bytes: &[u8],
+        align: usize,
+        allocator: &mut A,
+    ) -> Result<Self, AllocError> {
+        let mut buf = Self::new_in(bytes.len(), align, allocator)?;
+        buf.copy_from_slice(bytes);
+        Ok(buf)
+    }
+
+    /// Releases the bytes without deallocating them, e.g. to store them in a game structure.
+    pub fn into_raw(self) -> NonNull<u8> {
+        ManuallyDrop::new(self).ptr
+    }
+
+    pub fn allocator(&self) -> &A {
+        // SAFETY: The allocator outlives the buffer
+        unsafe { self.allocator.as_ref() }
+    }
+}
+
+impl<A: DLAllocator> Deref for DLBuf<A> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: The buffer owns `len` initialized bytes
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<A: DLAllocator> DerefMut for DLBuf<A> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: The buffer owns `len` initialized bytes
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<A: DLAllocator> Drop for DLBuf<A> {
+    fn drop(&mut self) {
+        // SAFETY: The buffer owns its block, and the allocator outlives it
+        unsafe { self.allocator.as_mut().deallocate(self.ptr.as_ptr().cast()) }
+    }
+}
+
+/// Allocator backed by [`std::alloc`], to test code allocating through a [`DLAllocator`] without
+/// the game.
+///
+/// It implements the allocation entries of the vtable, and panics if any other is called. Blocks
+/// still allocated are freed when it is dropped. Only available with the `test-fixtures` feature.
+#[cfg(feature = "test-fixtures")]
+#[derive(Debug, Default)]
+pub struct MockAllocator {
+    allocations: std::collections::HashMap<usize, std::alloc::Layout>,
+    fail: bool,
+}
+
+#[cfg(feature = "test-fixtures")]
+impl MockAllocator {
+    /// Alignment of the blocks returned by [`DLAllocator::allocate`], like the game's heaps.
+    pub const DEFAULT_ALIGN: usize = 16;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes allocations return null while `fail` is set, like an exhausted heap.
+    pub fn fail_allocations(&mut self, fail: bool) {
+        self.fail = fail;
+    }
+}
+
+#[cfg(feature = "test-fixtures")]
+impl Drop for MockAllocator {
+    fn drop(&mut self) {
+        for (&ptr, &layout) in &self.allocations {
+            // SAFETY: The block was allocated with this layout, and was not deallocated
+            unsafe { std::alloc::dealloc(ptr as *mut u8, layout) }
+        }
+    }
+}
+
+#[cfg(feature = "test-fixtures")]
+unsafe impl DLAllocator for MockAllocator {
+    fn vmt(&self) -> VTable {
+        panic!("MockAllocator has no vtable")
+    }
+
+    fn heap_allocation_count(&self) -> usize {
+        self.allocations.len()
+    }
+
+    fn block_size(&self, memory: *const ()) -> usize {
+        self.allocations
+            .get(&(memory as usize))
+            .expect("block not allocated by this allocator")
+            .size()
+    }
+
+    fn allocate(&mut self, cb: usize) -> *mut () {
+        self.allocate_aligned(cb, Self::DEFAULT_ALIGN)
+    }
+
+    fn allocate_aligned(&mut self, cb: usize, align: usize) -> *mut () {
+        let Ok(layout) = std::alloc::Layout::from_size_align(cb.max(1), align)
+        else {
+            return std::ptr::null_mut();
+        };
+        if self.fail {
+            return std::ptr::null_mut();
+        }
+        // SAFETY: The size of the layout is not zero
+        let ptr = unsafe { std::alloc::alloc(layout) };
+        if !ptr.is_null() {
+            self.allocations.insert(ptr as usize, layout);
+        }
+        ptr.cast()
+    }
+
+    fn deallocate(&mut self, ptr: *mut ()) {
+        let layout = self
+            .allocations
+            .remove(&(ptr as usize))
+            .expect("block not allocated by this allocator");
+        // SAFETY: The block was allocated with this layout, and is only deallocated once
+        unsafe { std::alloc::dealloc(ptr.cast(), layout) }
+    }
+}
@@ -0,0 +1,114 @@
+//! Layout differences of the game structures between games.
+//!
+//! Structures whose fields differ between games are generic over a [`GameLayout`], which
+//! defaults to the layout of the [default game](crate::Game::DEFAULT). Tools supporting several
+//! games with the `multi-game` feature can name the layout of the attached game explicitly, e.g.
+//! `DLString<u8, DLAllocatorProxy, Ds3Layout>`.
+
+use std::fmt::Debug;
+
+use crate::game::Game;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Types of the fields whose presence or position differ between games. Each is either the type
+/// of the field, or `()` where the game doesn't have it, which takes no space in a `#[repr(C)]`
+/// structure.
+pub trait GameLayout: private::Sealed + Debug + 'static {
+    const GAME: Game;
+
+    /// Allocator stored before the data of `DLString` and `DLVector`, or `()`.
+    type LeadingAllocator<A>;
+    /// Allocator stored after the data of `DLString` and `DLVector`, or `()`.
+    type TrailingAllocator<A>;
+    /// Debug menu fields at the end of `FD4ResCap`, or `()`.
+    type ResCapDebug: Debug;
+
+    /// Returns whichever of the allocator fields is the allocator.
+    fn allocator<'a, A>(
+        leading: &'a Self::LeadingAllocator<A>,
+        trailing: &'a Self::TrailingAllocator<A>,
+    ) -> &'a A;
+
+    /// Same as [`GameLayout::allocator`], for a mutable allocator.
+    fn allocator_mut<'a, A>(
+        leading: &'a mut Self::LeadingAllocator<A>,
+        trailing: &'a mut Self::TrailingAllocator<A>,
+    ) -> &'a mut A;
+}
+
+/// Debug menu fields added to `FD4ResCap` after DS3.
+#[derive(Debug)]
+#[repr(C)]
+pub struct FD4ResCapDebug {
+    pub is_debug: bool,
+    pub unk_61: bool,
+    pub debug_menu_item: *mut (),
+    pub unk_70: bool,
+}
+
+#[derive(Debug)]
+pub struct Ds3Layout;
+impl private::Sealed for Ds3Layout {}
+impl GameLayout for Ds3Layout {
+    const GAME: Game = Game::Ds3;
+    type LeadingAllocator<A> = ();
+    type TrailingAllocator<A> = A;
+    type ResCapDebug = ();
+
+    fn allocator<'a, A>(_leading: &'a (), trailing: &'a A) -> &'a A {
+        trailing
+    }
+
+    fn allocator_mut<'a, A>(_leading: &'a mut (), trailing: &'a mut A) -> &'a mut A {
+        trailing
+    }
+}
+
+#[derive(Debug)]
+pub struct ErLayout;
+impl private::Sealed for ErLayout {}
+impl GameLayout for ErLayout {
+    const GAME: Game = Game::Er;
+    type LeadingAllocator<A> = A;
+    type TrailingAllocator<A> = ();
+    type ResCapDebug = FD4ResCapDebug;
+
+    fn allocator<'a, A>(leading: &'a A, _trailing: &'a ()) -> &'a A {
+        leading
+    }
+
+    fn allocator_mut<'a, A>(leading: &'a mut A, _trailing: &'a mut ()) -> &'a mut A {
+        leading
+    }
+}
+
+#[derive(Debug)]
+pub struct Ac6Layout;
+impl private::Sealed for Ac6Layout {}
+impl GameLayout for Ac6Layout {
+    const GAME: Game = Game::Ac6;
+    type LeadingAllocator<A> = A;
+    type TrailingAllocator<A> = ();
+    type ResCapDebug = FD4ResCapDebug;
+
+    fn allocator<'a, A>(leading: &'a A, _trailing: &'a ()) -> &'a A {
+        leading
+    }
+
+    fn allocator_mut<'a, A>(leading: &'a mut A, _trailing: &'a mut ()) -> &'a mut A {
+        leading
+    }
+}
+
+/// Layout of the [default game](crate::Game::DEFAULT).
+#[cfg(all(feature = "ds3", not(feature = "er")))]
+pub type DefaultLayout = Ds3Layout;
+/// Layout of the [default game](crate::Game::DEFAULT).
+#[cfg(any(feature = "er", not(any(feature = "ds3", feature = "ac6"))))]
+pub type DefaultLayout = ErLayout;
+/// Layout of the [default game](crate::Game::DEFAULT).
+#[cfg(all(feature = "ac6", not(any(feature = "er", feature = "ds3"))))]
+pub type DefaultLayout = Ac6Layout;
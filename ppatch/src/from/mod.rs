@@ -1,5 +1,6 @@
 pub mod allocator;
 pub mod component;
+pub mod layout;
 pub mod regulation_man;
 pub mod resource;
 pub mod string;
@@ -1,23 +1,54 @@
-use super::{resource::ParamResCap, vector::DLVector};
+use super::{
+    allocator::DLAllocatorProxy,
+    layout::{DefaultLayout, GameLayout},
+    resource::ParamResCap,
+    vector::DLVector,
+};
+#[cfg(feature = "standalone")]
+use crate::locate;
 use crate::vtable::VTable;
 
-#[derive(Debug)]
+#[derive(fmt_derive::Debug)]
 #[repr(C)]
-pub struct CSRegulationManager {
+pub struct CSRegulationManager<L: GameLayout = DefaultLayout> {
     vtable: VTable,
     regulation_step_task: *mut (),
-    param_res_caps: DLVector<ParamResCap>,
+    param_res_caps: DLVector<ParamResCap<L>, DLAllocatorProxy, L>,
 }
 
+#[cfg(not(feature = "standalone"))]
 mod ce_ffi {
-    #[link(name = "CE", kind = "raw-dylib")]
+    // CE exports undecorated names, which raw-dylib does not assume for 32-bit x86
+    #[cfg_attr(not(target_arch = "x86"), link(name = "CE", kind = "raw-dylib"))]
+    #[cfg_attr(
+        target_arch = "x86",
+        link(name = "CE", kind = "raw-dylib", import_name_type = "undecorated")
+    )]
     extern "C" {
         pub static CSRegulationManager: *mut super::CSRegulationManager;
     }
 }
 
-impl CSRegulationManager {
+impl<L: GameLayout> CSRegulationManager<L> {
+    /// Returns the regulation manager exported by CE.
+    ///
+    /// # Safety
+    /// The regulation manager must be initialized, and have the layout `L` of the attached game.
+    #[cfg(not(feature = "standalone"))]
     pub unsafe fn instance() -> &'static mut Self {
-        &mut *ce_ffi::CSRegulationManager
+        &mut *ce_ffi::CSRegulationManager.cast()
+    }
+
+    /// Returns the regulation manager found by [`locate::regulation_manager`], so that CE is not
+    /// needed.
+    ///
+    /// # Safety
+    /// Same as [`locate::regulation_manager`].
+    ///
+    /// # Panics
+    /// If the regulation manager is not found.
+    #[cfg(feature = "standalone")]
+    pub unsafe fn instance() -> &'static mut Self {
+        locate::regulation_manager().expect("CSRegulationManager not found in the game executable")
     }
 }
@@ -1,21 +1,26 @@
 use std::ops::{Deref, DerefMut};
 
-use super::{component::FD4ComponentBase, string::FD4BasicHashString};
+use super::{
+    allocator::DLAllocatorProxy,
+    component::FD4ComponentBase,
+    layout::{DefaultLayout, GameLayout},
+    string::FD4BasicHashString,
+};
 use crate::vtable::VTable;
 
-pub type FD4ResNameHashString = FD4BasicHashString<u16>;
+pub type FD4ResNameHashString<L = DefaultLayout> = FD4BasicHashString<u16, DLAllocatorProxy, L>;
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct FD4ResCapHolderItem {
+pub struct FD4ResCapHolderItem<L: GameLayout = DefaultLayout> {
     vtable: VTable,
-    pub res_name: FD4ResNameHashString,
+    pub res_name: FD4ResNameHashString<L>,
     pub repository: *const (),
-    pub next_item: *mut FD4ResCapHolderItem,
+    pub next_item: *mut FD4ResCapHolderItem<L>,
     pub ref_count: usize,
 }
 
-unsafe impl FD4ComponentBase for FD4ResCapHolderItem {
+unsafe impl<L: GameLayout> FD4ComponentBase for FD4ResCapHolderItem<L> {
     fn vmt(&self) -> VTable {
         return self.vtable;
     }
@@ -23,25 +28,18 @@ unsafe impl FD4ComponentBase for FD4ResCapHolderItem {
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct FD4ResCap {
-    pub res_cap_holder_item: FD4ResCapHolderItem,
-    #[cfg(not(feature = "ds3"))]
-    pub is_debug: bool,
-    #[cfg(not(feature = "ds3"))]
-    pub unk_61: bool,
-    #[cfg(not(feature = "ds3"))]
-    pub debug_menu_item: *mut (),
-    #[cfg(not(feature = "ds3"))]
-    pub unk_70: bool,
+pub struct FD4ResCap<L: GameLayout = DefaultLayout> {
+    pub res_cap_holder_item: FD4ResCapHolderItem<L>,
+    pub debug: L::ResCapDebug,
 }
 
-impl Deref for FD4ResCap {
-    type Target = FD4ResCapHolderItem;
+impl<L: GameLayout> Deref for FD4ResCap<L> {
+    type Target = FD4ResCapHolderItem<L>;
     fn deref(&self) -> &Self::Target {
         &self.res_cap_holder_item
     }
 }
-impl DerefMut for FD4ResCap {
+impl<L: GameLayout> DerefMut for FD4ResCap<L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.res_cap_holder_item
     }
@@ -49,19 +47,19 @@ impl DerefMut for FD4ResCap {
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct FD4ParamResCap {
-    rescap: FD4ResCap,
+pub struct FD4ParamResCap<L: GameLayout = DefaultLayout> {
+    rescap: FD4ResCap<L>,
     file_size: usize,
     file: *mut u8,
 }
 
-impl Deref for FD4ParamResCap {
-    type Target = FD4ResCap;
+impl<L: GameLayout> Deref for FD4ParamResCap<L> {
+    type Target = FD4ResCap<L>;
     fn deref(&self) -> &Self::Target {
         &self.rescap
     }
 }
-impl DerefMut for FD4ParamResCap {
+impl<L: GameLayout> DerefMut for FD4ParamResCap<L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.rescap
     }
@@ -69,19 +67,19 @@ impl DerefMut for FD4ParamResCap {
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct ParamResCap {
-    rescap: FD4ResCap,
+pub struct ParamResCap<L: GameLayout = DefaultLayout> {
+    rescap: FD4ResCap<L>,
     unk_u32: u32,
-    fd4_res_cap: *mut FD4ParamResCap,
+    fd4_res_cap: *mut FD4ParamResCap<L>,
 }
 
-impl Deref for ParamResCap {
-    type Target = FD4ResCap;
+impl<L: GameLayout> Deref for ParamResCap<L> {
+    type Target = FD4ResCap<L>;
     fn deref(&self) -> &Self::Target {
         &self.rescap
     }
 }
-impl DerefMut for ParamResCap {
+impl<L: GameLayout> DerefMut for ParamResCap<L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.rescap
     }
@@ -1,6 +1,13 @@
-use std::ops::{Deref, DerefMut};
-
-use super::allocator::{DLAllocator, DLAllocatorProxy};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    ops::{Deref, DerefMut},
+};
+
+use super::{
+    allocator::{DLAllocator, DLAllocatorProxy},
+    layout::{DefaultLayout, GameLayout},
+};
 use crate::vtable::VTable;
 
 mod private {
@@ -40,31 +47,62 @@ impl<C: Copy, const N: usize> IStringStorage<C> for StringStorage<C, N> {
     }
 }
 
-pub trait Char: private::Sealed + Copy {
+pub trait Char: private::Sealed + Copy + Into<u32> {
     type Storage: IStringStorage<Self>;
+
+    /// Decodes `chars`, replacing invalid sequences with `U+FFFD`.
+    fn decode_lossy(chars: &[Self]) -> Cow<'_, str>;
+
+    /// Returns true if `chars` is the encoding of `s`.
+    fn eq_str(chars: &[Self], s: &str) -> bool;
 }
 impl private::Sealed for u8 {}
 impl Char for u8 {
     type Storage = StringStorage<Self, 16>;
+
+    fn decode_lossy(chars: &[Self]) -> Cow<'_, str> {
+        String::from_utf8_lossy(chars)
+    }
+
+    fn eq_str(chars: &[Self], s: &str) -> bool {
+        chars == s.as_bytes()
+    }
 }
 impl private::Sealed for u16 {}
 impl Char for u16 {
     type Storage = StringStorage<Self, 8>;
+
+    fn decode_lossy(chars: &[Self]) -> Cow<'_, str> {
+        Cow::Owned(String::from_utf16_lossy(chars))
+    }
+
+    fn eq_str(chars: &[Self], s: &str) -> bool {
+        s.encode_utf16().eq(chars.iter().copied())
+    }
+}
+
+/// Hash of FD4 resource names: an FNV-style hash with a multiplier of 137, over the characters
+/// with ASCII letters folded to lowercase.
+pub fn fd4_hash<C: Char>(chars: &[C]) -> u32 {
+    chars.iter().fold(0u32, |hash, &c| {
+        let c: u32 = c.into();
+        let folded = if (b'A' as u32..=b'Z' as u32).contains(&c) { c + 0x20 } else { c };
+        hash.wrapping_mul(137).wrapping_add(folded)
+    })
 }
 
 #[derive(fmt_derive::Debug)]
 #[repr(C)]
-pub struct DLString<C: Char = u8, A: DLAllocator = DLAllocatorProxy> {
-    #[cfg(not(feature = "ds3"))]
-    allocator: A,
+pub struct DLString<C: Char = u8, A: DLAllocator = DLAllocatorProxy, L: GameLayout = DefaultLayout>
+{
+    leading_allocator: L::LeadingAllocator<A>,
     storage: C::Storage,
     len: usize,
     capacity: usize,
-    #[cfg(feature = "ds3")]
-    allocator: A,
+    trailing_allocator: L::TrailingAllocator<A>,
 }
 
-impl<C: Char, A: DLAllocator> DLString<C, A> {
+impl<C: Char, A: DLAllocator, L: GameLayout> DLString<C, A, L> {
     pub fn len(&self) -> usize {
         self.len
     }
@@ -72,52 +110,88 @@ impl<C: Char, A: DLAllocator> DLString<C, A> {
     pub fn capacity(&self) -> usize {
         self.capacity
     }
+
+    /// Decodes the string as UTF-8 or UTF-16 depending on the character type, replacing invalid
+    /// sequences with `U+FFFD`.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        C::decode_lossy(self)
+    }
+
+    /// Compares the string to `s` without allocating.
+    pub fn eq_str(&self, s: &str) -> bool {
+        C::eq_str(self, s)
+    }
+}
+
+impl<C: Char, A: DLAllocator, L: GameLayout> PartialEq<str> for DLString<C, A, L> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl<C: Char, A: DLAllocator, L: GameLayout> PartialEq<&str> for DLString<C, A, L> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
 }
 
-impl<C: Char, A: DLAllocator> Deref for DLString<C, A> {
+impl<C: Char, A: DLAllocator, L: GameLayout> Deref for DLString<C, A, L> {
     type Target = [C];
     fn deref(&self) -> &Self::Target {
         unsafe { self.storage.get(self.len) }
     }
 }
 
-impl<C: Char, A: DLAllocator> DerefMut for DLString<C, A> {
+impl<C: Char, A: DLAllocator, L: GameLayout> DerefMut for DLString<C, A, L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { self.storage.get_mut(self.len) }
     }
 }
 
-pub type DLWString<A = DLAllocatorProxy> = DLString<u16, A>;
+pub type DLWString<A = DLAllocatorProxy, L = DefaultLayout> = DLString<u16, A, L>;
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct FD4BasicHashString<C: Char, A: DLAllocator = DLAllocatorProxy> {
+pub struct FD4BasicHashString<
+    C: Char,
+    A: DLAllocator = DLAllocatorProxy,
+    L: GameLayout = DefaultLayout,
+> {
     vtable: VTable,
-    string: DLString<C, A>,
+    string: DLString<C, A, L>,
     unk_08: usize,
-    hash: u32,
-    requires_rehash: bool,
+    hash: Cell<u32>,
+    requires_rehash: Cell<bool>,
 }
 
-impl<C: Char, A: DLAllocator> FD4BasicHashString<C, A> {
+impl<C: Char, A: DLAllocator, L: GameLayout> FD4BasicHashString<C, A, L> {
+    /// Returns the [`fd4_hash`] of the string, recomputing and caching it first if the string
+    /// changed since it was last computed, like the game does.
     pub fn hash(&self) -> u32 {
-        self.hash // Here, we would recompute the hash if requires_rehash was true
+        if self.requires_rehash.get() {
+            self.hash.set(fd4_hash(&self.string));
+            self.requires_rehash.set(false);
+        }
+        self.hash.get()
     }
 
     pub fn requires_rehash(&self) -> bool {
-        self.requires_rehash
+        self.requires_rehash.get()
     }
 }
 
-impl<C: Char, A: DLAllocator> Deref for FD4BasicHashString<C, A> {
-    type Target = DLString<C, A>;
+impl<C: Char, A: DLAllocator, L: GameLayout> Deref for FD4BasicHashString<C, A, L> {
+    type Target = DLString<C, A, L>;
     fn deref(&self) -> &Self::Target {
         &self.string
     }
 }
 
-impl<C: Char, A: DLAllocator> DerefMut for FD4BasicHashString<C, A> {
+/// The string may be modified, so the hash is recomputed on the next call to
+/// [`FD4BasicHashString::hash`].
+impl<C: Char, A: DLAllocator, L: GameLayout> DerefMut for FD4BasicHashString<C, A, L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.requires_rehash.set(true);
         &mut self.string
     }
 }
@@ -1,21 +1,27 @@
-use std::{marker::PhantomData, ops::Deref, ops::DerefMut};
+use std::{
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ops::Deref,
+    ops::DerefMut,
+};
 
-use super::allocator::{DLAllocator, DLAllocatorProxy};
+use super::{
+    allocator::{DLAllocator, DLAllocatorProxy},
+    layout::{DefaultLayout, GameLayout},
+};
 
 #[repr(C)]
 #[derive(Debug)]
-pub struct DLVector<T, A: DLAllocator = DLAllocatorProxy> {
-    #[cfg(not(feature = "ds3"))]
-    allocator: A,
+pub struct DLVector<T, A: DLAllocator = DLAllocatorProxy, L: GameLayout = DefaultLayout> {
+    leading_allocator: L::LeadingAllocator<A>,
     begin: *mut T,
     end: *mut T,
     buffer_end: *mut T,
-    #[cfg(feature = "ds3")]
-    allocator: A,
+    trailing_allocator: L::TrailingAllocator<A>,
     phantom: PhantomData<[T]>,
 }
 
-impl<T, A: DLAllocator> DLVector<T, A> {
+impl<T, A: DLAllocator, L: GameLayout> DLVector<T, A, L> {
     pub fn len(&self) -> usize {
         unsafe { self.end.offset_from(self.begin) as usize }
     }
@@ -23,18 +29,114 @@ impl<T, A: DLAllocator> DLVector<T, A> {
     pub fn capacity(&self) -> usize {
         unsafe { self.buffer_end.offset_from(self.begin) as usize }
     }
+
+    pub fn allocator(&self) -> &A {
+        L::allocator(&self.leading_allocator, &self.trailing_allocator)
+    }
+
+    /// Makes room for at least `additional` more elements, moving them to a new buffer allocated
+    /// with the allocator of the vector if needed.
+    ///
+    /// # Safety
+    /// The game must not access the vector during the call, and the buffer of the vector must
+    /// have been allocated by its allocator. Pointers to the elements are invalidated if the
+    /// buffer moves.
+    ///
+    /// # Panics
+    /// If the allocator fails to allocate the new buffer.
+    pub unsafe fn reserve(&mut self, additional: usize) {
+        let len = self.len();
+        if self.capacity() - len >= additional {
+            return;
+        }
+        let capacity =
+            len.checked_add(additional).expect("capacity overflow").max(2 * self.capacity());
+        let size = capacity.checked_mul(size_of::<T>()).expect("capacity overflow");
+
+        let allocator = L::allocator_mut(&mut self.leading_allocator, &mut self.trailing_allocator);
+        let begin: *mut T = allocator.allocate_aligned(size, align_of::<T>()).cast();
+        assert!(!begin.is_null(), "failed to allocate {size} bytes");
+        if !self.begin.is_null() {
+            std::ptr::copy_nonoverlapping(self.begin, begin, len);
+            allocator.deallocate(self.begin.cast());
+        }
+        self.begin = begin;
+        self.end = begin.add(len);
+        self.buffer_end = begin.add(capacity);
+    }
+
+    /// Appends `value`, growing the buffer with the allocator of the vector if it is full.
+    ///
+    /// # Safety
+    /// Same as [`DLVector::reserve`].
+    pub unsafe fn push_with(&mut self, value: T) {
+        if self.end == self.buffer_end {
+            self.reserve(1);
+        }
+        self.end.write(value);
+        self.end = self.end.add(1);
+    }
+
+    /// Removes the last element and returns it, or `None` if the vector is empty. The buffer is
+    /// kept.
+    ///
+    /// # Safety
+    /// The game must not access the vector during the call.
+    pub unsafe fn pop(&mut self) -> Option<T> {
+        if self.end == self.begin {
+            return None;
+        }
+        self.end = self.end.sub(1);
+        Some(self.end.read())
+    }
+
+    /// Drops all elements, keeping the buffer.
+    ///
+    /// # Safety
+    /// The game must not access the vector during the call.
+    pub unsafe fn clear_in_place(&mut self) {
+        let elements: *mut [T] = &mut **self;
+        self.end = self.begin;
+        std::ptr::drop_in_place(elements);
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the last element.
+    ///
+    /// # Safety
+    /// The game must not access the vector during the call, nor rely on the order of the
+    /// elements.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub unsafe fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(
+            index < len,
+            "swap_remove index {index} out of bounds for length {len}"
+        );
+        let removed = self.begin.add(index).read();
+        self.end = self.end.sub(1);
+        std::ptr::copy(self.end, self.begin.add(index), 1);
+        removed
+    }
 }
 
-impl<T, A: DLAllocator> Deref for DLVector<T, A> {
+impl<T, A: DLAllocator, L: GameLayout> Deref for DLVector<T, A, L> {
     type Target = [T];
 
     fn deref(&self) -> &Self::Target {
+        if self.begin.is_null() {
+            return &[];
+        }
         unsafe { std::slice::from_raw_parts(self.begin, self.len()) }
     }
 }
 
-impl<T, A: DLAllocator> DerefMut for DLVector<T, A> {
+impl<T, A: DLAllocator, L: GameLayout> DerefMut for DLVector<T, A, L> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.begin.is_null() {
+            return &mut [];
+        }
         unsafe { std::slice::from_raw_parts_mut(self.begin, self.len()) }
     }
 }
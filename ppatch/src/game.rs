@@ -0,0 +1,99 @@
+//! Games supported by ppatch, and selection of the one being patched at runtime.
+//!
+//! A build embeds the field blocks of the game selected by its game feature (`er`, `ds3` or
+//! `ac6`), or of all games with the `multi-game` feature. Entry points using the embedded field
+//! blocks, such as the `for_embedded_repo` constructors, use the ones of the [active
+//! game](active_game), which is the default game of the build until [`set_active_game`] is
+//! called.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use field_metadata::ArchivedFieldBlockRepo;
+
+use crate::r#static;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Game {
+    Ds3,
+    Er,
+    Ac6,
+}
+
+impl Game {
+    pub const ALL: [Game; 3] = [Game::Ds3, Game::Er, Game::Ac6];
+
+    /// Game of the enabled game feature. ER is preferred when several are enabled along with
+    /// `multi-game`, or when none is.
+    pub const DEFAULT: Game = if cfg!(feature = "er") {
+        Game::Er
+    }
+    else if cfg!(feature = "ds3") {
+        Game::Ds3
+    }
+    else if cfg!(feature = "ac6") {
+        Game::Ac6
+    }
+    else {
+        Game::Er
+    };
+
+    /// Name of the game in the paramdex, e.g. `"ER"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Game::Ds3 => "DS3",
+            Game::Er => "ER",
+            Game::Ac6 => "AC6",
+        }
+    }
+
+    /// Returns true if the field blocks of the game are embedded in this build.
+    pub const fn is_embedded(self) -> bool {
+        cfg!(feature = "multi-game") || self as u8 == Self::DEFAULT as u8
+    }
+
+    /// Returns the field blocks of the game embedded in this build, if any.
+    pub fn field_block_repo(self) -> Option<&'static ArchivedFieldBlockRepo> {
+        r#static::field_block_repo(self)
+    }
+
+    /// Returns the paramdef layout version the embedded field blocks of the game were generated
+    /// for, if they are embedded.
+    pub fn layout_version(self) -> Option<u64> {
+        r#static::layout_version(self)
+    }
+}
+
+static ACTIVE_GAME: AtomicU8 = AtomicU8::new(Game::DEFAULT as u8);
+
+/// Returns the game whose embedded field blocks are used by the entry points of ppatch.
+pub fn active_game() -> Game {
+    let game = ACTIVE_GAME.load(Ordering::Relaxed);
+    Game::ALL.into_iter().find(|&g| g as u8 == game).unwrap()
+}
+
+/// Sets the game whose embedded field blocks are used by the entry points of ppatch, e.g. once
+/// the game process was identified.
+///
+/// Patchers and managers created before keep the field blocks they were created with.
+///
+/// # Panics
+/// If the field blocks of `game` are not embedded in this build, see [`Game::is_embedded`].
+pub fn set_active_game(game: Game) {
+    assert!(
+        game.is_embedded(),
+        "{} field blocks are not embedded in this build, enable the multi-game feature",
+        game.name()
+    );
+    ACTIVE_GAME.store(game as u8, Ordering::Relaxed);
+}
+
+/// Field blocks and layout version of the active game.
+pub(crate) fn active_field_blocks() -> (&'static ArchivedFieldBlockRepo, u64) {
+    let game = active_game();
+    // The active game is always embedded
+    (
+        game.field_block_repo().unwrap(),
+        game.layout_version().unwrap(),
+    )
+}
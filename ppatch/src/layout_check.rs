@@ -0,0 +1,180 @@
+//! Detection of field metadata generated for another version of the game than the running one.
+//!
+//! When the field blocks don't match the game, patches silently corrupt values. Row sizes are a
+//! cheap way to catch this before patching: a [`LayoutReport`] compares the row size of each
+//! live param with the one implied by its field blocks and, with the `paramdex` feature, by its
+//! paramdef. A few mismatches may come from incomplete defs, but many of them mean that the
+//! metadata is for another game version, which [`LayoutReport::diagnostic`] reports as a whole.
+
+use field_metadata::{ArchivedFieldBlockRepo, Block};
+
+/// Row sizes of a param type according to each source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamRowSizes {
+    pub param_type: String,
+    /// Row size of the live param, in bytes.
+    pub live: usize,
+    /// Row size implied by the field blocks, in bytes (i.e. a multiple of the block size), or
+    /// `None` if there are no field blocks for the param type.
+    pub field_blocks: Option<usize>,
+    /// Size of the paramdef at the layout version, or `None` if there is no def for the param
+    /// type or defs were not checked.
+    pub def: Option<usize>,
+}
+
+impl ParamRowSizes {
+    /// Returns true if any source disagrees with the live row size.
+    pub fn is_mismatched(&self) -> bool {
+        let block_size = std::mem::size_of::<Block>();
+        self.field_blocks.is_some_and(|s| s != self.live.next_multiple_of(block_size))
+            || self.def.is_some_and(|s| s != self.live)
+    }
+
+    fn is_checked(&self) -> bool {
+        self.field_blocks.is_some() || self.def.is_some()
+    }
+}
+
+/// Row sizes of the live params compared to the field metadata, see the [module
+/// docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutReport {
+    /// Paramdef layout version the field metadata was generated for.
+    pub layout_version: u64,
+    /// Row sizes of each live param, in the order they were given.
+    pub params: Vec<ParamRowSizes>,
+    /// Layout version the live row sizes match best, if it could be inferred from the defs and
+    /// is not `layout_version`.
+    pub detected_version: Option<u64>,
+}
+
+impl LayoutReport {
+    /// Compares the row sizes of `live` params, given as param type and row size pairs, with the
+    /// size implied by their field blocks in `repo`, generated for `layout_version`.
+    pub fn new<'p>(
+        repo: &ArchivedFieldBlockRepo,
+        layout_version: u64,
+        live: impl IntoIterator<Item = (&'p str, usize)>,
+    ) -> Self {
+        let block_size = std::mem::size_of::<Block>();
+        let params = live
+            .into_iter()
+            .map(|(param_type, live)| ParamRowSizes {
+                param_type: param_type.to_owned(),
+                live,
+                field_blocks: repo.get(param_type).map(|blocks| {
+                    let n_blocks = blocks.iter().map(|fb| fb.offset as usize + 1).max();
+                    n_blocks.unwrap_or(0) * block_size
+                }),
+                def: None,
+            })
+            .collect();
+        Self {
+            layout_version,
+            params,
+            detected_version: None,
+        }
+    }
+
+    /// Same as [`LayoutReport::new`], with the embedded field blocks of the [active
+    /// game](crate::active_game).
+    pub fn for_embedded_repo<'p>(live: impl IntoIterator<Item = (&'p str, usize)>) -> Self {
+        let (repo, layout_version) = crate::game::active_field_blocks();
+        Self::new(repo, layout_version, live)
+    }
+
+    /// Also compares the row sizes with the size of the defs loaded in `paramdex`, and tries to
+    /// infer the layout version of the live params.
+    ///
+    /// The inferred version is the first version of a field or the version a field was removed
+    /// in (i.e. a version at which def sizes may change) for which the most def sizes match the
+    /// live row sizes. It is only reported if it matches more of them than `layout_version`.
+    #[cfg(feature = "paramdex")]
+    pub fn with_defs(mut self, paramdex: &paramdex::Paramdex) -> Self {
+        use std::collections::HashMap;
+
+        let defs: HashMap<_, _> = paramdex.defs().map(|d| (d.param_type.as_str(), d)).collect();
+        let def_size = |param_type: &str, version: u64| {
+            let mut def = (*defs.get(param_type)?).clone();
+            def.compute_field_offsets(version).size_bytes
+        };
+        let matching = |version: u64| {
+            (self.params.iter())
+                .filter(|p| def_size(&p.param_type, version) == Some(p.live))
+                .count()
+        };
+
+        let mut candidates: Vec<u64> = (paramdex.defs())
+            .flat_map(|d| d.fields.iter())
+            .flat_map(|f| [f.first_version, f.removed_version])
+            .flatten()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        let baseline = matching(self.layout_version);
+        self.detected_version = candidates
+            .into_iter()
+            .map(|v| (matching(v), v))
+            .filter(|&(n, _)| n > baseline)
+            .max()
+            .map(|(_, v)| v);
+
+        for p in &mut self.params {
+            p.def = def_size(&p.param_type, self.layout_version);
+        }
+        self
+    }
+
+    /// Iterates over the params whose row size disagrees with any source.
+    pub fn mismatches(&self) -> impl Iterator<Item = &ParamRowSizes> {
+        self.params.iter().filter(|p| p.is_mismatched())
+    }
+
+    /// Number of params with field blocks or a def to compare against.
+    pub fn checked(&self) -> usize {
+        self.params.iter().filter(|p| p.is_checked()).count()
+    }
+
+    /// Returns a single message describing the mismatch if more than `threshold` (a fraction
+    /// between 0 and 1) of the checked params have unexpected row sizes.
+    pub fn diagnostic(&self, threshold: f64) -> Option<String> {
+        let (mismatched, checked) = (self.mismatches().count(), self.checked());
+        if mismatched == 0 || mismatched as f64 <= threshold * checked as f64 {
+            return None;
+        }
+        let detected = match self.detected_version {
+            Some(v) => format!("detected {v}"),
+            None => "the running game differs".to_owned(),
+        };
+        Some(format!(
+            "field metadata appears to be for game version {}, {detected}: {mismatched}/{checked} \
+            params have unexpected row sizes",
+            self.layout_version
+        ))
+    }
+}
+
+/// Checks of paramdef layouts against param files, see [`Paramdef::verify_layout`].
+///
+/// [`Paramdef::verify_layout`]: paramdex::paramdef::Paramdef::verify_layout
+#[cfg(feature = "paramdex")]
+pub trait VerifyParamFile {
+    /// Checks that the computed layout of the def of `param_type` is as long as the rows of
+    /// `file`.
+    fn verify_against_param_file(
+        &self,
+        param_type: &str,
+        file: &crate::param_file::ParamFile,
+    ) -> Result<(), paramdex::VerifyLayoutError>;
+}
+
+#[cfg(feature = "paramdex")]
+impl VerifyParamFile for paramdex::Paramdex {
+    fn verify_against_param_file(
+        &self,
+        param_type: &str,
+        file: &crate::param_file::ParamFile,
+    ) -> Result<(), paramdex::VerifyLayoutError> {
+        self.verify_layout(param_type, file.row_size())
+    }
+}
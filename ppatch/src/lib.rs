@@ -1,14 +1,65 @@
-#[cfg(any(
-    all(feature = "er", feature = "ds3"),
-    all(feature = "ds3", feature = "ac6"),
-    all(feature = "ac6", feature = "er")
+#[cfg(all(
+    not(feature = "multi-game"),
+    any(
+        all(feature = "er", feature = "ds3"),
+        all(feature = "ds3", feature = "ac6"),
+        all(feature = "ac6", feature = "er")
+    )
 ))]
-compile_error!("Only one of the target game features (ds3, er, ac6) may be enabled");
+compile_error!(
+    "Only one of the target game features (ds3, er, ac6) may be enabled, unless multi-game is"
+);
+#[cfg(not(any(
+    feature = "ds3",
+    feature = "er",
+    feature = "ac6",
+    feature = "multi-game"
+)))]
+compile_error!("One of the target game features (ds3, er, ac6) or multi-game must be enabled");
 
+#[cfg(all(target_pointer_width = "32", any(feature = "er", feature = "ac6")))]
+compile_error!(
+    "The er and ac6 features require a 64-bit target. Only ds3 supports 32-bit targets (e.g. i686-pc-windows-msvc)"
+);
+
+/// Field block layout types, shared with the build script generating the embedded field blocks.
+///
+/// Dependents should use this re-export rather than depending on `field_metadata` directly, so
+/// that the [`FieldBlock`](field_metadata::FieldBlock) type always matches the one the patchers
+/// were built with.
+pub use field_metadata;
+/// Paramdex loader, available with the `paramdex` feature.
+#[cfg(feature = "paramdex")]
+pub use paramdex;
+
+#[cfg(feature = "paramdex")]
+pub mod apply;
 pub mod celua;
+pub mod diff;
+pub mod error;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod fields;
 pub mod from;
+pub mod game;
+pub use game::{active_game, set_active_game, Game};
+pub mod layout_check;
+#[cfg(feature = "standalone")]
+pub mod locate;
+pub mod name_search;
+pub mod param_builder;
 pub mod param_file;
 pub mod patchers;
+#[cfg(feature = "project-enums")]
+pub mod project_enums;
+#[cfg(feature = "paramdex")]
+pub mod row_fields;
+#[cfg(feature = "paramdex")]
+pub mod row_view;
 mod r#static;
+pub use r#static::LAYOUT_VERSION;
+pub mod stacking;
+#[cfg(feature = "test-fixtures")]
+pub mod testing;
 pub mod util;
 pub mod vtable;
@@ -0,0 +1,10 @@
+//! Signatures of `DarkSoulsIII.exe`.
+
+use super::Signature;
+
+/// `mov rax, [CSRegulationManager]`, followed by a null check of the manager.
+pub const REGULATION_MANAGER: &[Signature] = &[Signature::new(
+    "48 8B 05 ?? ?? ?? ?? 48 85 C0 74 ?? 48 8B 40 ?? C3",
+    3,
+    7,
+)];
@@ -0,0 +1,10 @@
+//! Signatures of `eldenring.exe`.
+
+use super::Signature;
+
+/// `mov rcx, [CSRegulationManager]`, followed by a null check of the manager.
+pub const REGULATION_MANAGER: &[Signature] = &[Signature::new(
+    "48 8B 0D ?? ?? ?? ?? 48 85 C9 74 0B 4C 8B C0 48 8B D7",
+    3,
+    7,
+)];
@@ -0,0 +1,167 @@
+//! Resolution of game statics by scanning the code of the game executable for known signatures,
+//! for tools injected in the game without Cheat Engine. Only available with the `standalone`
+//! feature.
+//!
+//! The signatures of each game live in its own module, so that supporting a new version of a game
+//! only requires adding its signatures.
+
+use std::sync::OnceLock;
+
+use crate::{
+    from::{layout::GameLayout, regulation_man::CSRegulationManager},
+    Game,
+};
+
+pub mod ac6;
+pub mod ds3;
+pub mod er;
+mod pattern;
+
+pub use pattern::{ParsePatternError, Pattern};
+
+/// Signature of an instruction referencing a static through a RIP-relative address, e.g.
+/// `mov rcx, [rip + disp32]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    /// [`Pattern`] matching the instruction and some of the surrounding code.
+    pub pattern: &'static str,
+    /// Offset of the 32-bit displacement of the instruction from the start of the pattern.
+    pub disp_offset: usize,
+    /// Offset of the end of the instruction from the start of the pattern, which the
+    /// displacement is relative to.
+    pub instruction_end: usize,
+}
+
+impl Signature {
+    pub const fn new(pattern: &'static str, disp_offset: usize, instruction_end: usize) -> Self {
+        Self {
+            pattern,
+            disp_offset,
+            instruction_end,
+        }
+    }
+
+    /// Finds the signature in `code`, which is loaded at the address `base`, and returns the
+    /// address referenced by the instruction.
+    ///
+    /// Returns `None` if the signature does not match or its pattern is invalid.
+    pub fn resolve(&self, code: &[u8], base: usize) -> Option<usize> {
+        let pattern: Pattern = self.pattern.parse().ok()?;
+        let start = pattern.find(code)?;
+        let disp = code.get(start + self.disp_offset..start + self.disp_offset + 4)?;
+        let disp = i32::from_le_bytes(disp.try_into().unwrap());
+        let instruction_end = base.checked_add(start + self.instruction_end)?;
+        instruction_end.checked_add_signed(disp as isize)
+    }
+}
+
+/// Returns the signatures of the `CSRegulationManager` static of `game`, by decreasing priority.
+pub fn regulation_manager_signatures(game: Game) -> &'static [Signature] {
+    match game {
+        Game::Ds3 => ds3::REGULATION_MANAGER,
+        Game::Er => er::REGULATION_MANAGER,
+        Game::Ac6 => ac6::REGULATION_MANAGER,
+    }
+}
+
+/// Section of a loaded PE image.
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    name: [u8; 8],
+    start: usize,
+    len: usize,
+}
+
+impl Section {
+    fn contains(&self, address: usize) -> bool {
+        (self.start..self.start + self.len).contains(&address)
+    }
+}
+
+/// Reads the section headers of the PE image loaded at `base`.
+///
+/// # Safety
+/// `base` must be the address of a loaded PE image.
+#[cfg(windows)]
+unsafe fn sections(base: *const u8) -> Option<Vec<Section>> {
+    let read_u16 = |ofs: usize| base.add(ofs).cast::<u16>().read_unaligned() as usize;
+    let read_u32 = |ofs: usize| base.add(ofs).cast::<u32>().read_unaligned() as usize;
+
+    if read_u16(0) != 0x5A4D {
+        return None;
+    }
+    let nt_headers = read_u32(0x3C);
+    if read_u32(nt_headers) != 0x4550 {
+        return None;
+    }
+    let section_count = read_u16(nt_headers + 6);
+    let optional_header_size = read_u16(nt_headers + 20);
+    let section_headers = nt_headers + 24 + optional_header_size;
+    let sections = (0..section_count)
+        .map(|i| {
+            let header = section_headers + 40 * i;
+            Section {
+                name: base.add(header).cast::<[u8; 8]>().read_unaligned(),
+                start: base as usize + read_u32(header + 12),
+                len: read_u32(header + 8),
+            }
+        })
+        .collect();
+    Some(sections)
+}
+
+/// Returns the sections of the executable of the current process.
+#[cfg(windows)]
+fn main_module_sections() -> Option<Vec<Section>> {
+    extern "system" {
+        fn GetModuleHandleW(module_name: *const u16) -> *const u8;
+    }
+    // SAFETY: A null name returns the executable of the process, which is a loaded PE image
+    unsafe {
+        let base = GetModuleHandleW(std::ptr::null());
+        if base.is_null() {
+            return None;
+        }
+        sections(base)
+    }
+}
+
+/// Returns the sections of the executable of the current process.
+#[cfg(not(windows))]
+fn main_module_sections() -> Option<Vec<Section>> {
+    None
+}
+
+/// Finds the address of the `CSRegulationManager` static of `game` in the executable of the
+/// current process, checking that it is in its `.data` section.
+fn find_regulation_manager_static(game: Game) -> Option<usize> {
+    let sections = main_module_sections()?;
+    let section = |name: &[u8]| sections.iter().find(|s| s.name.starts_with(name));
+    let (text, data) = (section(b".text\0")?, section(b".data\0")?);
+    // SAFETY: The section is mapped, and code is not modified while scanning for signatures
+    let code = unsafe { std::slice::from_raw_parts(text.start as *const u8, text.len) };
+
+    regulation_manager_signatures(game)
+        .iter()
+        .filter_map(|signature| signature.resolve(code, text.start))
+        .find(|&address| data.contains(address) && address % std::mem::align_of::<usize>() == 0)
+}
+
+/// Returns the regulation manager of the game, found by scanning the game executable for the
+/// signatures of the game of the layout `L`.
+///
+/// The address of the static is searched once per game and cached. Returns `None` if none of the
+/// signatures match, e.g. for an unsupported version of the game, or if the regulation manager is
+/// not created yet. It always returns `None` outside Windows.
+///
+/// # Safety
+/// The game executable must be the game of the layout `L`, and the regulation manager must not be
+/// accessed through another reference while the returned one is in use.
+pub unsafe fn regulation_manager<L: GameLayout>() -> Option<&'static mut CSRegulationManager<L>> {
+    static STATICS: [OnceLock<Option<usize>>; Game::ALL.len()] =
+        [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+
+    let address = STATICS[L::GAME as usize].get_or_init(|| find_regulation_manager_static(L::GAME));
+    let instance = (*address)? as *const *mut CSRegulationManager<L>;
+    instance.read_volatile().as_mut()
+}
@@ -0,0 +1,65 @@
+use std::{fmt, str::FromStr};
+
+/// Byte pattern with wildcards, written as space separated hex bytes where `??` (or `?`)
+/// matches any byte, e.g. `"48 8B 0D ?? ?? ?? ??"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    bytes: Vec<Option<u8>>,
+}
+
+/// Error returned when parsing a [`Pattern`] with an invalid token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePatternError {
+    pub token: String,
+}
+
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern byte {:?}", self.token)
+    }
+}
+
+impl std::error::Error for ParsePatternError {}
+
+impl FromStr for Pattern {
+    type Err = ParsePatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_byte = |token: &str| match token {
+            "?" | "??" => Ok(None),
+            _ if token.len() == 2 && token.bytes().all(|c| c.is_ascii_hexdigit()) => {
+                Ok(Some(u8::from_str_radix(token, 16).unwrap()))
+            }
+            _ => Err(ParsePatternError {
+                token: token.to_owned(),
+            }),
+        };
+        let bytes = s.split_ascii_whitespace().map(parse_byte).collect::<Result<_, _>>()?;
+        Ok(Self { bytes })
+    }
+}
+
+impl Pattern {
+    /// Length of the pattern, in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns true if `bytes` starts with bytes matching the pattern.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= self.len()
+            && self.bytes.iter().zip(bytes).all(|(p, b)| p.is_none_or(|p| p == *b))
+    }
+
+    /// Returns the offset of the first match of the pattern in `haystack`.
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        if self.is_empty() {
+            return Some(0);
+        }
+        haystack.windows(self.len()).position(|window| self.matches(window))
+    }
+}
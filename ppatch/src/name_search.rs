@@ -0,0 +1,146 @@
+//! Lookup of rows by their name, e.g. "Moonveil" rather than its row ID.
+//!
+//! [`ParamFile::find_by_name`] decodes the names of all rows on each search. For repeated
+//! searches over the same param, [`ParamFile::name_index`] decodes and lowercases them once.
+//!
+//! Rows without a name (or with an empty one) never match.
+
+use std::borrow::Cow;
+
+use crate::param_file::ParamFile;
+
+/// How a row name is compared to the searched one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchMode {
+    /// Names equal to the needle.
+    Exact,
+    /// Names equal to the needle, ignoring case.
+    CaseInsensitive,
+    /// Names containing the needle, ignoring case.
+    Substring,
+    /// Names containing the characters of the needle in order (e.g. "rvblood" for "Rivers of
+    /// Blood"), or whose edit distance to the needle, divided by the length of the longest of
+    /// both, is at most `max_distance`. Case is ignored.
+    Fuzzy { max_distance: f64 },
+}
+
+/// Needle of a search, lowercased once.
+struct Matcher<'n> {
+    needle: &'n str,
+    lower: String,
+    mode: MatchMode,
+}
+
+impl<'n> Matcher<'n> {
+    fn new(needle: &'n str, mode: MatchMode) -> Self {
+        Self {
+            needle,
+            lower: needle.to_lowercase(),
+            mode,
+        }
+    }
+
+    /// Returns true if `name`, whose lowercase version is `lower`, matches the needle.
+    fn matches(&self, name: &str, lower: &str) -> bool {
+        match self.mode {
+            MatchMode::Exact => name == self.needle,
+            MatchMode::CaseInsensitive => lower == self.lower,
+            MatchMode::Substring => lower.contains(&self.lower),
+            MatchMode::Fuzzy { max_distance } => {
+                is_subsequence(&self.lower, lower)
+                    || normalized_levenshtein(&self.lower, lower) <= max_distance
+            }
+        }
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Levenshtein distance between `a` and `b`, in characters, divided by the length of the
+/// longest one.
+fn normalized_levenshtein(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 0.0;
+    }
+    // Distances from a prefix of `a` to each prefix of `b`, one row at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()] as f64 / max_len as f64
+}
+
+/// Names of the rows of a param, decoded and lowercased once for repeated searches.
+///
+/// The index is a copy: it is not updated if the param changes.
+#[derive(Debug, Clone, Default)]
+pub struct NameIndex {
+    /// ID, name and lowercase name of each named row, in row order.
+    names: Vec<(u32, String, String)>,
+}
+
+impl NameIndex {
+    /// Returns the ID and name of each row whose name matches `needle`, in row order.
+    pub fn find(&self, needle: &str, mode: MatchMode) -> Vec<(u32, String)> {
+        let matcher = Matcher::new(needle, mode);
+        (self.names.iter())
+            .filter(|(_, name, lower)| matcher.matches(name, lower))
+            .map(|(id, name, _)| (*id, name.clone()))
+            .collect()
+    }
+
+    /// Number of named rows in the index.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl<'a> ParamFile<'a> {
+    /// Iterates over the ID and name of each row with a non-empty name.
+    fn named_rows(&self) -> impl Iterator<Item = (u32, Cow<'_, str>)> {
+        (0..self.row_descriptors().len())
+            .filter_map(|i| Some((self.row_descriptors()[i].id, self.row_name(i)?)))
+            .filter(|(_, name)| !name.is_empty())
+    }
+
+    /// Returns the ID and name of each row whose name matches `needle`, in row order.
+    ///
+    /// Names are decoded according to [`ParamFileHeader::is_unicode`]. See the
+    /// [module docs](crate::name_search).
+    ///
+    /// [`ParamFileHeader::is_unicode`]: crate::param_file::ParamFileHeader::is_unicode
+    pub fn find_by_name(&self, needle: &str, mode: MatchMode) -> Vec<(u32, String)> {
+        let matcher = Matcher::new(needle, mode);
+        self.named_rows()
+            .filter(|(_, name)| matcher.matches(name, &name.to_lowercase()))
+            .map(|(id, name)| (id, name.into_owned()))
+            .collect()
+    }
+
+    /// Decodes the names of all rows into a [`NameIndex`], for repeated searches.
+    pub fn name_index(&self) -> NameIndex {
+        let names = self
+            .named_rows()
+            .map(|(id, name)| {
+                let lower = name.to_lowercase();
+                (id, name.into_owned(), lower)
+            })
+            .collect();
+        NameIndex { names }
+    }
+}
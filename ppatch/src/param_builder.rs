@@ -0,0 +1,303 @@
+//! Adding and removing rows of a param file with [`ParamFileBuilder`].
+//!
+//! [`ParamFile`] is a view over an existing buffer, so rows can be edited in place but never
+//! added or removed. A builder owns a copy of the rows instead, and re-emits the whole file with
+//! [`ParamFileBuilder::to_bytes`].
+//!
+//! Row names are written to the strings region following the row data, where identical names
+//! are stored once like in the game files.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::param_file::{decode_name, ParamFile, ParamRowDescriptor};
+
+const FLAG_64_BIT: u8 = 4;
+const FLAG_PARAM_TYPE_OFFSET: u8 = 0x80;
+const OFFSET_SIZE: usize = std::mem::size_of::<usize>();
+
+/// Writes a pointer sized file offset at `ofs`.
+fn put_offset(out: &mut [u8], ofs: usize, value: usize) {
+    out[ofs..ofs + OFFSET_SIZE].copy_from_slice(&value.to_ne_bytes());
+}
+
+/// Error returned by [`ParamFileBuilder::insert_row`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertRowError {
+    /// A row with this ID already exists.
+    DuplicateId(u32),
+    /// The row data is smaller than the row size of the param.
+    RowTooSmall { row_size: usize, len: usize },
+    /// The param already has the maximum number of rows a file can hold.
+    TooManyRows,
+}
+
+impl fmt::Display for InsertRowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::DuplicateId(id) => write!(f, "param already has a row with ID {id}"),
+            Self::RowTooSmall { row_size, len } => {
+                write!(
+                    f,
+                    "row of {len} bytes is smaller than the row size {row_size}"
+                )
+            }
+            Self::TooManyRows => write!(f, "param files can't hold more than {} rows", u16::MAX),
+        }
+    }
+}
+
+impl std::error::Error for InsertRowError {}
+
+#[derive(Debug, Clone)]
+struct BuilderRow {
+    data: Box<[u8]>,
+    /// Encoded name, without its terminator.
+    name: Option<Box<[u8]>>,
+}
+
+/// Owned param file whose rows can be inserted, removed and renamed.
+///
+/// Rows are kept sorted by ID, and [`ParamFileBuilder::to_bytes`] emits a file for the target
+/// platform which [`ParamFile::from_bytes`] accepts: the header, the row descriptors, the row
+/// data in ID order, then the param type (if stored at an offset) and the distinct row names.
+#[derive(Debug, Clone)]
+pub struct ParamFileBuilder {
+    /// Header of the emitted file, whose offsets and row count are fixed up by `to_bytes`.
+    header: Vec<u8>,
+    param_type: Vec<u8>,
+    row_size: usize,
+    rows: BTreeMap<u32, BuilderRow>,
+}
+
+impl ParamFileBuilder {
+    /// Creates an empty param of type `param_type`, whose rows are at least `row_size` bytes.
+    ///
+    /// The file has a 0x40 byte header storing the param type at an offset, like the params of
+    /// recent games, and UTF-16 row names.
+    pub fn new(param_type: &str, row_size: usize) -> Self {
+        let mut header = vec![0u8; 0x40];
+        header[0x2C] = cfg!(target_endian = "big") as u8;
+        header[0x2D] = FLAG_PARAM_TYPE_OFFSET
+            | if cfg!(target_pointer_width = "64") { FLAG_64_BIT } else { 3 };
+        header[0x2E] = 1;
+        Self {
+            header,
+            param_type: param_type.as_bytes().to_vec(),
+            row_size,
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Copies the header, rows and row names of `file`.
+    ///
+    /// Bytes of the file which are not part of a row, the param type or a row name are dropped.
+    pub fn from_file(file: &ParamFile<'_>) -> Self {
+        let rows = (file.rows().enumerate())
+            .map(|(i, row)| {
+                let row = BuilderRow {
+                    data: row.data().into(),
+                    name: file.row_name_bytes(i).map(Into::into),
+                };
+                (file.row_descriptors()[i].id, row)
+            })
+            .collect();
+        Self {
+            header: file.raw_header_bytes().to_vec(),
+            param_type: file.param_type_bytes().to_vec(),
+            row_size: file.row_size(),
+            rows,
+        }
+    }
+
+    /// Minimum size of the rows of the param.
+    pub fn row_size(&self) -> usize {
+        self.row_size
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the data of the row with the given ID.
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        self.rows.get(&id).map(|r| &*r.data)
+    }
+
+    /// Returns the data of the row with the given ID mutably.
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut [u8]> {
+        self.rows.get_mut(&id).map(|r| &mut *r.data)
+    }
+
+    /// Returns the IDs of the rows, in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.rows.keys().copied()
+    }
+
+    /// Inserts a row without a name, which can be set with [`ParamFileBuilder::set_row_name`].
+    ///
+    /// Rows may be larger than [`ParamFileBuilder::row_size`], e.g. for params with a
+    /// variable-length tail.
+    ///
+    /// # Errors
+    /// - If a row with ID `id` already exists, returns [`InsertRowError::DuplicateId`].
+    /// - If `data` is smaller than the row size, returns [`InsertRowError::RowTooSmall`].
+    /// - If the param already has `u16::MAX` rows, returns [`InsertRowError::TooManyRows`].
+    pub fn insert_row(&mut self, id: u32, data: &[u8]) -> Result<(), InsertRowError> {
+        if self.rows.contains_key(&id) {
+            return Err(InsertRowError::DuplicateId(id));
+        }
+        if data.len() < self.row_size {
+            return Err(InsertRowError::RowTooSmall {
+                row_size: self.row_size,
+                len: data.len(),
+            });
+        }
+        if self.rows.len() >= u16::MAX as usize {
+            return Err(InsertRowError::TooManyRows);
+        }
+        let row = BuilderRow {
+            data: data.into(),
+            name: None,
+        };
+        self.rows.insert(id, row);
+        Ok(())
+    }
+
+    /// Removes the row with the given ID, returning its data.
+    pub fn remove_row(&mut self, id: u32) -> Option<Box<[u8]>> {
+        self.rows.remove(&id).map(|r| r.data)
+    }
+
+    /// Returns the name of the row with the given ID, or `None` if the row does not exist or has
+    /// no name. See [`ParamFile::row_name`].
+    pub fn row_name(&self, id: u32) -> Option<Cow<'_, str>> {
+        let name = self.rows.get(&id)?.name.as_deref()?;
+        Some(decode_name(name, self.is_unicode()))
+    }
+
+    /// Sets the name of the row with the given ID, or removes it if `name` is `None`.
+    ///
+    /// Names are encoded as UTF-16 in unicode files, and as UTF-8 otherwise. Names of files using
+    /// another encoding, like the Shift-JIS names of older games, can be set with
+    /// [`ParamFileBuilder::set_row_name_bytes`]. Names are cut at their first NUL character when
+    /// read back.
+    ///
+    /// Returns false if there is no row with this ID.
+    pub fn set_row_name(&mut self, id: u32, name: Option<&str>) -> bool {
+        let encoded = name.map(|name| -> Box<[u8]> {
+            if self.is_unicode() {
+                name.encode_utf16().flat_map(u16::to_ne_bytes).collect()
+            }
+            else {
+                name.as_bytes().into()
+            }
+        });
+        self.set_encoded_name(id, encoded)
+    }
+
+    /// Sets the already encoded name of the row with the given ID, without its terminator, or
+    /// removes it if `name` is `None`.
+    ///
+    /// Returns false if there is no row with this ID.
+    ///
+    /// # Panics
+    /// If the param is unicode and `name` has an odd length.
+    pub fn set_row_name_bytes(&mut self, id: u32, name: Option<&[u8]>) -> bool {
+        if let Some(name) = name {
+            assert!(
+                !self.is_unicode() || name.len().is_multiple_of(2),
+                "UTF-16 row name has an odd length of {} bytes",
+                name.len()
+            );
+        }
+        self.set_encoded_name(id, name.map(Into::into))
+    }
+
+    fn set_encoded_name(&mut self, id: u32, name: Option<Box<[u8]>>) -> bool {
+        let Some(row) = self.rows.get_mut(&id)
+        else {
+            return false;
+        };
+        row.name = name;
+        true
+    }
+
+    fn has_param_type_offset(&self) -> bool {
+        (self.header[0x2D] & FLAG_PARAM_TYPE_OFFSET) != 0
+    }
+
+    fn is_unicode(&self) -> bool {
+        (self.header[0x2E] & 1) != 0
+    }
+
+    /// Emits the param file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header_size = self.header.len();
+        let data_start = header_size + self.rows.len() * std::mem::size_of::<ParamRowDescriptor>();
+        let mut out = self.header.clone();
+        out.resize(data_start, 0);
+
+        let mut descs = Vec::with_capacity(self.rows.len());
+        for (&id, row) in &self.rows {
+            descs.push(ParamRowDescriptor {
+                id,
+                data_offset: out.len(),
+                name_offset: 0,
+            });
+            out.extend_from_slice(&row.data);
+        }
+
+        if self.has_param_type_offset() {
+            let ofs = out.len();
+            put_offset(&mut out, 0x10, ofs);
+            out.extend_from_slice(&self.param_type);
+            out.push(0);
+        }
+
+        // Names start at the strings offset, which is past the param type when it is stored at
+        // an offset
+        let strings_offset = out.len();
+        let terminator: &[u8] = if self.is_unicode() { &[0, 0] } else { &[0] };
+        let mut name_offsets = HashMap::new();
+        for (desc, row) in descs.iter_mut().zip(self.rows.values()) {
+            let Some(name) = &row.name
+            else {
+                continue;
+            };
+            desc.name_offset = *name_offsets.entry(&**name).or_insert_with(|| {
+                if self.is_unicode() && !out.len().is_multiple_of(2) {
+                    out.push(0);
+                }
+                let ofs = out.len();
+                out.extend_from_slice(name);
+                out.extend_from_slice(terminator);
+                ofs
+            });
+        }
+
+        // Header
+        out[0..4].copy_from_slice(&(strings_offset as u32).to_ne_bytes());
+        let short_data_offset = u16::from_ne_bytes([out[4], out[5]]);
+        if header_size == 0x30 || short_data_offset != 0 {
+            // Files whose data starts past 64KiB only store the low bits
+            out[4..6].copy_from_slice(&(data_start as u16).to_ne_bytes());
+        }
+        out[0xA..0xC].copy_from_slice(&(self.rows.len() as u16).to_ne_bytes());
+        if header_size == 0x40 {
+            put_offset(&mut out, 0x30, data_start);
+        }
+
+        // Row descriptors
+        for (i, desc) in descs.iter().enumerate() {
+            let ofs = header_size + i * std::mem::size_of::<ParamRowDescriptor>();
+            out[ofs..ofs + 4].copy_from_slice(&desc.id.to_ne_bytes());
+            put_offset(&mut out, ofs + OFFSET_SIZE, desc.data_offset);
+            put_offset(&mut out, ofs + 2 * OFFSET_SIZE, desc.name_offset);
+        }
+        out
+    }
+}
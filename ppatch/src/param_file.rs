@@ -1,3 +1,15 @@
+use std::{borrow::Cow, fmt, hash::Hasher, mem::ManuallyDrop, ptr::NonNull};
+
+use num_traits::{FromBytes, PrimInt};
+
+use crate::{
+    patchers::base::{PatchRowError, RowPatchId, RowPatcher},
+    util::unaligned::Unaligned,
+};
+
+#[cfg(feature = "paramdex")]
+pub mod write;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 struct ParamTypeOffset {
@@ -43,6 +55,12 @@ impl ParamFileHeader {
         return self.row_count;
     }
 
+    /// Data version of the paramdef the file was written with, see
+    /// `paramdex::version::RegulationVersion::from_paramdef_data_version`.
+    pub fn paramdef_data_version(&self) -> u16 {
+        self.paramdef_data_version
+    }
+
     pub fn is_big_endian(&self) -> bool {
         return self.is_big_endian != 0;
     }
@@ -55,8 +73,49 @@ impl ParamFileHeader {
         (self.format_flags_2d & 4) != 0
     }
 
+    /// Returns true if the param type is stored at an offset in the file rather than in the
+    /// header.
+    fn has_param_type_offset(&self) -> bool {
+        (self.format_flags_2d & 0x80) != 0
+    }
+
+    /// Returns the offset of the param type in the file, if it is not stored in the header.
+    pub fn param_type_offset(&self) -> Option<usize> {
+        let ofs = unsafe { self.param_type_block.offset }.param_type_offset as usize;
+        self.has_param_type_offset().then_some(ofs)
+    }
+
+    /// Raw value of the unknown `u16` at offset 6, which is zero in all known files.
+    pub fn raw_unk006(&self) -> u16 {
+        self.unk006
+    }
+
+    /// Raw value of the unknown `u32` preceding the param type offset, if the param type is
+    /// stored at an offset. It is zero in all known files.
+    pub fn raw_unk04(&self) -> Option<u32> {
+        self.has_param_type_offset()
+            .then_some(unsafe { self.param_type_block.offset }.unk04)
+    }
+
+    /// Raw bytes following the param type offset, if the param type is stored at an offset.
+    /// They are zero in all known files.
+    pub fn raw_param_type_padding(&self) -> Option<[u8; 24]> {
+        self.has_param_type_offset()
+            .then_some(unsafe { self.param_type_block.offset }.unk_pad)
+    }
+
+    /// Returns the range of the header bytes following the data offset, which are zero in all
+    /// known files. Empty for 0x30 byte headers, which have no data offset.
+    fn padding_range(&self) -> std::ops::Range<usize> {
+        match self.header_size() {
+            0x40 if self.is_64_bit() => 0x38..0x40,
+            0x40 => 0x34..0x40,
+            size => size..size,
+        }
+    }
+
     pub fn data_end_ofs(&self) -> usize {
-        if (self.format_flags_2d & 0x80) != 0 {
+        if self.has_param_type_offset() {
             unsafe { self.param_type_block.offset }.param_type_offset as usize
         }
         else {
@@ -65,17 +124,213 @@ impl ParamFileHeader {
     }
 }
 
+/// A region of a param file, reported by [`FromBytesError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// The header and row descriptors.
+    Header,
+    /// The data of the row at this index in the row descriptors.
+    Row(usize),
+    /// Everything after the row data: param type, row names and other strings.
+    Strings,
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => f.write_str("header"),
+            Self::Row(index) => write!(f, "row {index}"),
+            Self::Strings => f.write_str("strings"),
+        }
+    }
+}
+
+/// A region of a param file spanning `len` bytes from `offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataBlock {
+    pub kind: BlockKind,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for DataBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {:#x} ({} bytes)",
+            self.kind, self.offset, self.len
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FromBytesError {
-    InsufficientAlignment,
     BufferTooSmall,
-    UnsupportedFile { is_big_endian: bool, is_64bit: bool },
-    OutOfBoundsOffset,
-    IntersectingData,
-    UnsortedRowDescs,
-    DuplicateIds,
+    UnsupportedFile {
+        is_big_endian: bool,
+        is_64bit: bool,
+    },
+    /// `block` ends past the end of the file, which is `file_size` bytes long.
+    OutOfBoundsOffset {
+        block: DataBlock,
+        file_size: usize,
+    },
+    /// `first` overlaps `second`, which starts at a higher or equal offset.
+    IntersectingData {
+        first: DataBlock,
+        second: DataBlock,
+    },
+    /// The descriptor at `index` has a lower ID than the one before it.
+    UnsortedRowDescs {
+        index: usize,
+        previous_id: u32,
+        id: u32,
+    },
+    /// The descriptor at `index` has the same ID as the one before it.
+    DuplicateIds {
+        index: usize,
+        id: u32,
+    },
+    /// The descriptor at `index` has a name offset outside of the strings region.
+    InvalidNameOffset {
+        index: usize,
+        offset: usize,
+    },
+    /// The only row of the param, at `offset`, does not start before the end of row data at
+    /// `data_end`, so its size cannot be inferred. This happens when the param type string is
+    /// written before the row data. See [`ParamFile::from_bytes_with_row_size`].
+    AmbiguousRowSize {
+        offset: usize,
+        data_end: usize,
+    },
+}
+
+impl fmt::Display for FromBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::BufferTooSmall => f.write_str("buffer is too small to hold a param file"),
+            Self::UnsupportedFile {
+                is_big_endian,
+                is_64bit,
+            } => write!(
+                f,
+                "unsupported param file ({}-endian, {}-bit)",
+                if is_big_endian { "big" } else { "little" },
+                if is_64bit { 64 } else { 32 }
+            ),
+            Self::OutOfBoundsOffset { block, file_size } => write!(
+                f,
+                "param file offset is out of bounds: {block} ends past the file size of {file_size:#x}"
+            ),
+            Self::IntersectingData { first, second } => {
+                write!(f, "param file sections intersect: {first} and {second}")
+            }
+            Self::UnsortedRowDescs {
+                index,
+                previous_id,
+                id,
+            } => write!(
+                f,
+                "param rows are not sorted by ID: row {index} has ID {id} after ID {previous_id}"
+            ),
+            Self::DuplicateIds { index, id } => {
+                write!(f, "param file has duplicate row IDs: row {index} repeats ID {id}")
+            }
+            Self::InvalidNameOffset { index, offset } => write!(
+                f,
+                "param row name is outside of the strings region: row {index} has name offset {offset:#x}"
+            ),
+            Self::AmbiguousRowSize { offset, data_end } => write!(
+                f,
+                "param row size cannot be inferred: the only row at {offset:#x} does not start before the end of row data at {data_end:#x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FromBytesError {}
+
+/// Non-zero value in a region of a param file which the crate assumes unused.
+///
+/// Newer revisions of the format may repurpose these regions, in which case files using them may
+/// be misparsed. See [`ParamFile::from_bytes_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatWarning {
+    /// See [`ParamFileHeader::raw_unk006`].
+    NonZeroUnk006(u16),
+    /// See [`ParamFileHeader::raw_unk04`].
+    NonZeroParamTypeUnk04(u32),
+    /// A byte at `offset` of [`ParamFileHeader::raw_param_type_padding`] is not zero.
+    NonZeroParamTypePadding { offset: usize },
+    /// A byte at `offset` in the file, between the data offset and the end of the header, is not
+    /// zero.
+    NonZeroHeaderPadding { offset: usize },
+}
+
+/// How much of a param file [`ParamFile::from_bytes_with`] checks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// Everything checked by [`ParamFile::from_bytes`].
+    #[default]
+    Full,
+    /// Only the header and the bounds of the row descriptors, in constant time. Row IDs and data
+    /// offsets are trusted.
+    HeaderOnly(TrustedLayout),
+    /// Nothing, like [`ParamFile::from_bytes_unchecked`].
+    None(TrustedLayout),
+}
+
+/// Opt-in to the reduced [`ValidationLevel`]s, for files whose layout was already validated,
+/// e.g. by the game which loaded them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedLayout(());
+
+impl TrustedLayout {
+    /// # Safety
+    /// The param files parsed with the [`ValidationLevel`] holding this value must pass the
+    /// checks it skips, see [`ParamFile::from_bytes`] and [`ParamFile::from_bytes_unchecked`].
+    pub unsafe fn new() -> Self {
+        Self(())
+    }
+}
+
+/// Options of [`ParamFile::from_bytes_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FromBytesOptions {
+    /// Report non-zero values in regions the crate assumes unused as [`FormatWarning`]s.
+    pub strict: bool,
+    pub validation: ValidationLevel,
 }
 
+impl From<ValidationLevel> for FromBytesOptions {
+    fn from(validation: ValidationLevel) -> Self {
+        Self {
+            validation,
+            ..Default::default()
+        }
+    }
+}
+
+/// Byte order of the values in a param file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Self = Self::Little;
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Self = Self::Big;
+}
+
+/// Row descriptor of a param file.
+///
+/// Offsets are pointer sized, so this matches the layout of 64-bit param files on 64-bit targets
+/// and of 32-bit param files on 32-bit targets. [`ParamFile::from_bytes`] rejects other files.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct ParamRowDescriptor {
@@ -84,19 +339,33 @@ pub struct ParamRowDescriptor {
     pub name_offset: usize,
 }
 
+/// View over the rows of a param file.
+///
+/// When the underlying buffer is not aligned to a usize multiple, the header and row descriptors
+/// are copied out of it with unaligned reads. Row data is always accessed in place.
+///
+/// The size of a row is the distance from its data to the data of the next row in the file, or to
+/// the end of the row data for the last one. Most params have rows of a uniform size, but some
+/// have a variable-length tail after the part described by the paramdef. See
+/// [`ParamFile::has_uniform_rows`].
 #[derive(Debug)]
 pub struct ParamFile<'a> {
     data: *mut u8,
     file_size: usize,
     row_size: usize,
-    header: &'a ParamFileHeader,
-    row_descriptors: &'a [ParamRowDescriptor],
+    /// Size of each row, if they are not all `row_size` bytes long.
+    row_sizes: Option<Box<[usize]>>,
+    header: Cow<'a, ParamFileHeader>,
+    row_descriptors: Cow<'a, [ParamRowDescriptor]>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Row<'a> {
     id: u32,
     data: &'a [u8],
+    /// Encoded name, without its terminator.
+    name: Option<&'a [u8]>,
+    is_unicode: bool,
 }
 
 #[derive(Debug)]
@@ -105,28 +374,438 @@ pub struct RowMut<'a> {
     data: &'a mut [u8],
 }
 
+/// Owned copy of the contents of a row, as blocks of type `N`.
+///
+/// If the row size is not a multiple of the size of `N`, the last block is padded with zeroes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowSnapshot<N: PrimInt = u32> {
+    id: u32,
+    blocks: Vec<Unaligned<N>>,
+    /// Size of the snapshotted row in bytes, without the padding.
+    len: usize,
+}
+
+/// Error returned when viewing a row as blocks whose size does not divide the row size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnalignedRowSize {
+    pub row_size: usize,
+    pub block_size: usize,
+}
+
+impl fmt::Display for UnalignedRowSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row size {} is not a multiple of the block size {}",
+            self.row_size, self.block_size
+        )
+    }
+}
+
+impl std::error::Error for UnalignedRowSize {}
+
+fn check_block_size<N: PrimInt>(data: &[u8]) -> Result<usize, UnalignedRowSize> {
+    let block_size = std::mem::size_of::<N>();
+    if !data.len().is_multiple_of(block_size) {
+        return Err(UnalignedRowSize {
+            row_size: data.len(),
+            block_size,
+        });
+    }
+    Ok(data.len() / block_size)
+}
+
+/// Decodes a row name, replacing invalid sequences.
+pub(crate) fn decode_name(bytes: &[u8], is_unicode: bool) -> Cow<'_, str> {
+    if is_unicode {
+        let units: Vec<u16> =
+            bytes.chunks_exact(2).map(|c| u16::from_ne_bytes([c[0], c[1]])).collect();
+        Cow::Owned(String::from_utf16_lossy(&units))
+    }
+    else {
+        String::from_utf8_lossy(bytes)
+    }
+}
+
+impl<'a> Row<'a> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the name of the row, or `None` if it has none. See [`ParamFile::row_name`].
+    pub fn name(&self) -> Option<Cow<'a, str>> {
+        Some(decode_name(self.name?, self.is_unicode))
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Size of this row in bytes, including any variable-length tail.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Reads a `T` stored at `offset` bytes into the row with the given byte order, e.g. the
+    /// [`ParamFileOwned::source_endianness`] of a converted file.
+    ///
+    /// Returns `None` if the value doesn't fit in the row.
+    pub fn read_at<T>(&self, offset: usize, endianness: Endianness) -> Option<T>
+    where
+        T: FromBytes,
+        for<'b> &'b T::Bytes: TryFrom<&'b [u8]>,
+    {
+        let end = offset.checked_add(std::mem::size_of::<T>())?;
+        let bytes: &T::Bytes = self.data.get(offset..end)?.try_into().ok()?;
+        Some(match endianness {
+            Endianness::Little => T::from_le_bytes(bytes),
+            Endianness::Big => T::from_be_bytes(bytes),
+        })
+    }
+
+    /// Splits the row into its first `prefix_len` bytes (e.g. the part described by the
+    /// paramdef) and the remaining variable-length tail.
+    ///
+    /// Returns `None` if the row is smaller than `prefix_len`.
+    pub fn split_tail(&self, prefix_len: usize) -> Option<(Row<'a>, &'a [u8])> {
+        let (data, tail) = self.data.split_at_checked(prefix_len)?;
+        Some((Row { data, ..*self }, tail))
+    }
+
+    /// Views the row as a slice of (unaligned) blocks of type `N`.
+    ///
+    /// # Errors
+    /// If the row size is not a multiple of the size of `N`, returns [`UnalignedRowSize`].
+    pub fn as_blocks<N: PrimInt>(&self) -> Result<&'a [Unaligned<N>], UnalignedRowSize> {
+        let len = check_block_size::<N>(self.data)?;
+        // SAFETY: Unaligned<N> has an alignment of 1 and integers are valid for any bit pattern
+        Ok(unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const Unaligned<N>, len) })
+    }
+
+    /// Copies the row into an owned [`RowSnapshot`] of blocks of type `N`, e.g. to keep the
+    /// `before` side of a [`RowPatcher::create_patch`] call while the row is edited.
+    ///
+    /// If the row size is not a multiple of the size of `N`, the last block is padded with
+    /// zeroes. Bytes past the end of the row are never read.
+    pub fn snapshot<N: PrimInt>(&self) -> RowSnapshot<N> {
+        RowSnapshot::new(self.id, self.data)
+    }
+}
+
+impl<'a> RowMut<'a> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    /// Size of this row in bytes, including any variable-length tail.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Splits the row into its first `prefix_len` bytes (e.g. the part described by the
+    /// paramdef) and the remaining variable-length tail.
+    ///
+    /// Returns `None` if the row is smaller than `prefix_len`.
+    pub fn split_tail_mut(&mut self, prefix_len: usize) -> Option<(RowMut<'_>, &mut [u8])> {
+        let (data, tail) = self.data.split_at_mut_checked(prefix_len)?;
+        Some((RowMut { id: self.id, data }, tail))
+    }
+
+    /// Views the row as a slice of (unaligned) blocks of type `N`.
+    ///
+    /// # Errors
+    /// If the row size is not a multiple of the size of `N`, returns [`UnalignedRowSize`].
+    pub fn as_blocks<N: PrimInt>(&self) -> Result<&[Unaligned<N>], UnalignedRowSize> {
+        let len = check_block_size::<N>(self.data)?;
+        // SAFETY: Unaligned<N> has an alignment of 1 and integers are valid for any bit pattern
+        Ok(unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const Unaligned<N>, len) })
+    }
+
+    /// Views the row as a mutable slice of (unaligned) blocks of type `N`.
+    ///
+    /// # Errors
+    /// If the row size is not a multiple of the size of `N`, returns [`UnalignedRowSize`].
+    pub fn as_blocks_mut<N: PrimInt>(&mut self) -> Result<&mut [Unaligned<N>], UnalignedRowSize> {
+        let len = check_block_size::<N>(self.data)?;
+        // SAFETY: Unaligned<N> has an alignment of 1 and integers are valid for any bit pattern
+        Ok(unsafe {
+            std::slice::from_raw_parts_mut(self.data.as_mut_ptr() as *mut Unaligned<N>, len)
+        })
+    }
+
+    /// Copies the row into a [`RowSnapshot`]. See [`Row::snapshot`].
+    pub fn snapshot<N: PrimInt>(&self) -> RowSnapshot<N> {
+        RowSnapshot::new(self.id, self.data)
+    }
+
+    /// Snapshots the row, runs `edit` on its bytes and creates a patch from the changes it made
+    /// with `patcher`.
+    ///
+    /// Unlike [`RowPatcherExt::patch_row`](crate::patchers::base::RowPatcherExt::patch_row), rows
+    /// whose size is not a multiple of the block size are supported, by diffing snapshots
+    /// padded with zeroes. The patcher must then be created with the row size rounded up to a
+    /// multiple of the block size.
+    ///
+    /// # Errors
+    /// If the patcher refuses to create the patch, the row is restored to its previous contents
+    /// and [`PatchRowError::PatchRejected`] is returned.
+    pub fn edit_with_patch<'p, N: PrimInt, P: RowPatcher<'p, N>>(
+        &mut self,
+        patcher: &mut P,
+        edit: impl FnOnce(&mut [u8]),
+    ) -> Result<RowPatchId, PatchRowError> {
+        let before = self.snapshot::<N>();
+        edit(self.data);
+        let after = self.snapshot::<N>();
+
+        match patcher.create_patch(before.blocks(), after.blocks()) {
+            Some(id) => Ok(id),
+            None => {
+                self.data.copy_from_slice(before.bytes());
+                Err(PatchRowError::PatchRejected)
+            }
+        }
+    }
+}
+
+impl<N: PrimInt> RowSnapshot<N> {
+    fn new(id: u32, data: &[u8]) -> Self {
+        let block_size = std::mem::size_of::<N>();
+        let mut blocks = vec![Unaligned(N::zero()); data.len().div_ceil(block_size)];
+        // SAFETY: Unaligned<N> has an alignment of 1 and integers are valid for any bit pattern.
+        // Only the bytes of the row are copied, so the padding of the last block stays zeroed.
+        unsafe {
+            let bytes = blocks.as_mut_ptr() as *mut u8;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), bytes, data.len());
+        }
+        Self {
+            id,
+            blocks,
+            len: data.len(),
+        }
+    }
+
+    /// ID of the snapshotted row.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Contents of the row as blocks, the last of which is padded with zeroes if the row size
+    /// is not a multiple of the size of `N`.
+    pub fn blocks(&self) -> &[Unaligned<N>] {
+        &self.blocks
+    }
+
+    /// Contents of the row, without the padding.
+    pub fn bytes(&self) -> &[u8] {
+        // SAFETY: The blocks are at least `len` bytes long, and Unaligned<N> has no padding
+        unsafe { std::slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.len) }
+    }
+
+    /// Size of the snapshotted row in bytes, without the padding.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Error returned when indexing a [`ParamFile`] with an out of range row index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexError {
+    pub index: usize,
+    pub row_count: usize,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row index {} is out of range for {} rows",
+            self.index, self.row_count
+        )
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// Checksum of the parts of a param file which [`ParamFile::from_bytes`] validates.
+///
+/// See [`ParamFile::revalidate_cheap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationChecksum {
+    file_size: usize,
+    region_size: usize,
+    hash: u64,
+}
+
+impl ValidationChecksum {
+    fn compute(data: &[u8], region_size: usize) -> Self {
+        let mut hasher = fnv::FnvHasher::default();
+        hasher.write(&data[..region_size]);
+        Self {
+            file_size: data.len(),
+            region_size,
+            hash: hasher.finish(),
+        }
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        data.len() == self.file_size && *self == Self::compute(data, self.region_size)
+    }
+}
+
+/// Computes the size of each row from the data offset of the row following it in the file, or
+/// the end of the row data for the last one.
+///
+/// Returns the size of the smallest row, and the size of each row if they are not uniform. The
+/// last row is considered uniform if there is enough space after it for a row of the same size
+/// as the others. Only allocates if rows are not sorted by offset or not uniform.
+///
+/// Sizes are not validated, but the last row never extends past `data_end` or the end of the
+/// file. It has a size of 0 if it doesn't start before them, as does the row size of a param
+/// without rows.
+fn compute_row_sizes(
+    row_descriptors: &[ParamRowDescriptor],
+    data_end: usize,
+    file_size: usize,
+) -> (usize, Option<Box<[usize]>>) {
+    let is_sorted = row_descriptors.windows(2).all(|p| p[0].data_offset <= p[1].data_offset);
+    let sorted: Cow<'_, [ParamRowDescriptor]> = if is_sorted {
+        Cow::Borrowed(row_descriptors)
+    }
+    else {
+        let mut sorted = row_descriptors.to_vec();
+        sorted.sort_unstable_by_key(|r| r.data_offset);
+        Cow::Owned(sorted)
+    };
+    let Some(last) = sorted.last()
+    else {
+        return (0, None);
+    };
+    let last_size = data_end.min(file_size).saturating_sub(last.data_offset);
+
+    let mut deltas = sorted.windows(2).map(|p| p[1].data_offset - p[0].data_offset);
+    let row_size = deltas.next().unwrap_or(last_size);
+    if deltas.all(|s| s == row_size) && last.data_offset <= data_end && last_size >= row_size {
+        return (row_size, None);
+    }
+
+    let row_len = |r: &ParamRowDescriptor| match sorted
+        .binary_search_by_key(&r.data_offset, |s| s.data_offset)
+    {
+        Ok(i) if i + 1 < sorted.len() => sorted[i + 1].data_offset - r.data_offset,
+        _ => last_size,
+    };
+    let sizes: Box<[usize]> = row_descriptors.iter().map(row_len).collect();
+    (sizes.iter().copied().min().unwrap(), Some(sizes))
+}
+
+/// Checks that rows sorted by data offset lie between the row descriptors and the end of row
+/// data without intersecting each other.
+///
+/// Rows are given as `(offset, size, index)`.
+fn check_sorted_rows(
+    sorted_rows: impl Iterator<Item = (usize, usize, usize)>,
+    data_start: usize,
+    data_end: usize,
+    file_size: usize,
+) -> Result<(), FromBytesError> {
+    let strings = DataBlock {
+        kind: BlockKind::Strings,
+        offset: data_end,
+        len: file_size - data_end,
+    };
+    let mut last = DataBlock {
+        kind: BlockKind::Header,
+        offset: 0,
+        len: data_start,
+    };
+    let mut last_end = data_start;
+    for (ofs, row_size, index) in sorted_rows {
+        let block = DataBlock {
+            kind: BlockKind::Row(index),
+            offset: ofs,
+            len: row_size,
+        };
+        // Rows sharing data would have a size of 0 when sizes are computed from offsets
+        if ofs < last_end || (ofs == last.offset && last.kind != BlockKind::Header) {
+            return Err(FromBytesError::IntersectingData {
+                first: last,
+                second: block,
+            });
+        }
+        last_end = match ofs.checked_add(row_size) {
+            Some(end) if end <= file_size => end,
+            _ => return Err(FromBytesError::OutOfBoundsOffset { block, file_size }),
+        };
+        if last_end > data_end {
+            return Err(FromBytesError::IntersectingData {
+                first: block,
+                second: strings,
+            });
+        }
+        last = block;
+    }
+    Ok(())
+}
+
 impl<'a> ParamFile<'a> {
     /// Creates param file from a mutable byte slice, without any sanity checks.
     ///
     /// # Safety:
-    /// - The byte slice must be aligned to a usize multiple.
     /// - The byte slice must represent a valid param file with endianness and bitness corresponding
     ///   to the target platform.
     pub unsafe fn from_bytes_unchecked(data: &'a mut [u8]) -> Self {
-        let header = &*(data.as_ptr() as usize as *const ParamFileHeader);
-        let row_descriptors = std::slice::from_raw_parts(
-            (data.as_ptr() as usize + header.header_size()) as *const ParamRowDescriptor,
-            header.row_count as usize,
-        );
-        let row_size = match row_descriptors.len() {
-            0 => 0, // Obviously not true, but there are no rows so doesn't matter :)
-            1 => header.data_end_ofs() - row_descriptors[0].data_offset as usize,
-            _ => (row_descriptors[1].data_offset - row_descriptors[0].data_offset) as usize,
+        let addr = data.as_ptr() as usize;
+        // Header sizes are multiples of 8, so row descriptors are aligned iff the header is
+        let is_aligned = addr.is_multiple_of(std::mem::align_of::<usize>());
+
+        let header = if is_aligned {
+            Cow::Borrowed(&*(addr as *const ParamFileHeader))
+        }
+        else {
+            Cow::Owned((addr as *const ParamFileHeader).read_unaligned())
+        };
+        let row_descs_ptr = (addr + header.header_size()) as *const ParamRowDescriptor;
+        let row_descriptors = if is_aligned {
+            Cow::Borrowed(std::slice::from_raw_parts(
+                row_descs_ptr,
+                header.row_count as usize,
+            ))
+        }
+        else {
+            Cow::Owned(
+                (0..header.row_count as usize)
+                    .map(|i| row_descs_ptr.add(i).read_unaligned())
+                    .collect(),
+            )
         };
+        let (row_size, row_sizes) =
+            compute_row_sizes(&row_descriptors, header.data_end_ofs(), data.len());
         Self {
             data: data.as_mut_ptr(),
             file_size: data.len(),
             row_size,
+            row_sizes,
             header,
             row_descriptors,
         }
@@ -134,26 +813,92 @@ impl<'a> ParamFile<'a> {
 
     /// Creates a param file from a mutable byte slice, checking if it contains safe data **for the purposes of this API**.
     ///
+    /// The slice does not need to be aligned, but the header and row descriptors of unaligned
+    /// slices will be copied into the returned [`ParamFile`].
+    ///
+    /// Validation runs in linear time without allocating when rows are stored in ID order and have
+    /// a uniform size, which is the case for most files shipped with the games.
+    ///
     /// # Errors
-    /// - If the slice is not aligned to a usize multiple, returns [`FromBytesError::InsufficientAlignment`].
     /// - If the slice is too small, returns [`FromBytesError::BufferTooSmall`].
     /// - If the param file is designed for a system with a different endianness
     ///   or bitness, returns [`FromBytesError::UnsupportedFile`].
-    /// - If row descriptors are not sorted by unique IDs, returns [`FromBytesError::UnsortedRowDescs`].
+    /// - If row descriptors are not sorted by ID, returns [`FromBytesError::UnsortedRowDescs`].
+    /// - If two row descriptors have the same ID, returns [`FromBytesError::DuplicateIds`].
     /// - If one of the offsets in the file goes out of bounds, returns [`FromBytesError::OutOfBoundsOffset`].
     /// - If two data regions intersect, returns [`FromBytesError::IntersectingData`].
+    /// - If a row name does not start in the strings region following the row data, returns
+    ///   [`FromBytesError::InvalidNameOffset`].
+    /// - If the param has a single row which doesn't start before the end of row data, returns
+    ///   [`FromBytesError::AmbiguousRowSize`].
     pub fn from_bytes(data: &'a mut [u8]) -> Result<Self, FromBytesError> {
-        let addr = data.as_ptr() as usize;
+        Self::check_header(data)?;
+        // SAFETY: The header and row descriptors are in bounds, and we validate the rest below
+        let file = unsafe { Self::from_bytes_unchecked(data) };
+        file.check_row_size_inferred()?;
+        file.validate_data(false)?;
+        Ok(file)
+    }
 
-        // Check alignment
-        if (addr & std::mem::align_of::<usize>()) != 0 {
-            return Err(FromBytesError::InsufficientAlignment);
+    /// Checks that the size of the rows could be inferred from their offsets, which is only not
+    /// the case for a single row with nothing to bound it in the file.
+    ///
+    /// With several rows, the size is given by the difference of their offsets. Without rows, it
+    /// doesn't matter and is 0.
+    fn check_row_size_inferred(&self) -> Result<(), FromBytesError> {
+        let data_end = self.header.data_end_ofs();
+        match self.row_descriptors.as_ref() {
+            [row] if self.row_size == 0 && data_end <= row.data_offset => {
+                // Offsets out of bounds are reported as such by the validation
+                if row.data_offset < self.file_size {
+                    return Err(FromBytesError::AmbiguousRowSize {
+                        offset: row.data_offset,
+                        data_end,
+                    });
+                }
+                Ok(())
+            }
+            _ => Ok(()),
         }
+    }
+
+    /// Same as [`ParamFile::from_bytes`], but with rows of `row_size` bytes, e.g. the size given
+    /// by the paramdef of the param.
+    ///
+    /// [`ParamFile::from_bytes`] makes each row extend to the next row in the file, so padding
+    /// between rows (as left by some tools and DLC params) is part of the rows before it. Here,
+    /// rows have a uniform size instead, and bytes between them are not part of any row.
+    ///
+    /// This is also how to open params whose row size cannot be inferred from the file, for
+    /// which [`ParamFile::from_bytes`] returns [`FromBytesError::AmbiguousRowSize`]: params with
+    /// a single row stored after the param type string. The rows of such files must lie between
+    /// the param type string and the first row name following it, or the end of the file.
+    ///
+    /// # Errors
+    /// Same as [`ParamFile::from_bytes`], except for [`FromBytesError::AmbiguousRowSize`]. If a
+    /// row does not fit before the next row or the end of the row data, returns
+    /// [`FromBytesError::IntersectingData`].
+    pub fn from_bytes_with_row_size(
+        data: &'a mut [u8],
+        row_size: usize,
+    ) -> Result<Self, FromBytesError> {
+        Self::check_header(data)?;
+        // SAFETY: The header and row descriptors are in bounds, and we validate the rest below
+        let mut file = unsafe { Self::from_bytes_unchecked(data) };
+        file.row_size = row_size;
+        file.row_sizes = None;
+        file.validate_data(true)?;
+        Ok(file)
+    }
+
+    /// Checks that `data` holds the header and row descriptors of a param file for the target
+    /// platform.
+    fn check_header(data: &[u8]) -> Result<(), FromBytesError> {
         // Ensure large enough for the header
         if data.len() < std::mem::size_of::<ParamFileHeader>() {
             return Err(FromBytesError::BufferTooSmall);
         }
-        let header = unsafe { &*(addr as *const ParamFileHeader) };
+        let header = unsafe { (data.as_ptr() as *const ParamFileHeader).read_unaligned() };
 
         const EXPECTED_OFFSET_SZ: usize = std::mem::size_of::<usize>();
         let offset_sz = if header.is_64_bit() { 8 } else { 4 };
@@ -176,100 +921,414 @@ impl<'a> ParamFile<'a> {
         if data.len() < header.header_size() + row_desc_sz {
             return Err(FromBytesError::BufferTooSmall);
         }
-        let row_descriptors = unsafe {
-            std::slice::from_raw_parts(
-                (addr as usize + header.header_size()) as *const ParamRowDescriptor,
-                header.row_count as usize,
-            )
-        };
-        let row_size = match row_descriptors.len() {
-            0 => 0, // Obviously not true, but there are no rows so doesn't matter :)
-            1 => header.data_end_ofs() - row_descriptors[0].data_offset as usize,
-            _ => (row_descriptors[1].data_offset - row_descriptors[0].data_offset) as usize,
+        Ok(())
+    }
+
+    /// Same as [`ParamFile::from_bytes`], but with the checks selected by `options`, which may
+    /// be a [`ValidationLevel`].
+    ///
+    /// Extra checks never fail, and instead return warnings along with the file. If
+    /// [`FromBytesOptions::strict`] is set, these are the [`ParamFile::format_warnings`].
+    ///
+    /// [`ValidationLevel::HeaderOnly`] and [`ValidationLevel::None`] skip the checks of row data,
+    /// which sort rows by offset when they are not stored in order.
+    ///
+    /// # Errors
+    /// Same as [`ParamFile::from_bytes`], for the checks of the validation level.
+    pub fn from_bytes_with(
+        data: &'a mut [u8],
+        options: impl Into<FromBytesOptions>,
+    ) -> Result<(Self, Vec<FormatWarning>), FromBytesError> {
+        let options = options.into();
+        let file = match options.validation {
+            ValidationLevel::Full => Self::from_bytes(data)?,
+            ValidationLevel::HeaderOnly(_) => {
+                Self::check_header(data)?;
+                // SAFETY: The header and row descriptors are in bounds, and the caller vouched
+                // for the rest when creating the `TrustedLayout`
+                unsafe { Self::from_bytes_unchecked(data) }
+            }
+            // SAFETY: The caller vouched for the file when creating the `TrustedLayout`
+            ValidationLevel::None(_) => unsafe { Self::from_bytes_unchecked(data) },
         };
+        let warnings = if options.strict { file.format_warnings() } else { Vec::new() };
+        Ok((file, warnings))
+    }
 
-        // Check if row descriptors are strictly sorted by ID
-        if !row_descriptors.windows(2).all(|p| p[0].id < p[1].id) {
-            return Err(FromBytesError::UnsortedRowDescs);
+    /// Returns the regions of the file which the crate assumes unused, but hold non-zero values.
+    ///
+    /// Such values likely mean that the file uses a newer revision of the format, which may be
+    /// misparsed.
+    pub fn format_warnings(&self) -> Vec<FormatWarning> {
+        let header = self.header();
+        let mut warnings = Vec::new();
+        if header.raw_unk006() != 0 {
+            warnings.push(FormatWarning::NonZeroUnk006(header.raw_unk006()));
         }
+        if let Some(unk04) = header.raw_unk04().filter(|&v| v != 0) {
+            warnings.push(FormatWarning::NonZeroParamTypeUnk04(unk04));
+        }
+        let padding = header.raw_param_type_padding().unwrap_or_default();
+        warnings.extend(
+            (padding.iter().enumerate())
+                .filter(|(_, &b)| b != 0)
+                .map(|(offset, _)| FormatWarning::NonZeroParamTypePadding { offset }),
+        );
+        let raw_header = self.raw_header_bytes();
+        warnings.extend(
+            header
+                .padding_range()
+                .filter(|&i| raw_header[i] != 0)
+                .map(|offset| FormatWarning::NonZeroHeaderPadding { offset }),
+        );
+        warnings
+    }
 
-        // Collect all data blocks we might access in the file, and
-        // make sure they (1) aren't out of bounds and (2) don't intersect other blocks
-        let mut used_blocks: Vec<_> =
-            row_descriptors.iter().map(|r| (r.data_offset, row_size)).collect();
+    /// Returns the bytes of the header, including the ones which are not parsed.
+    pub fn raw_header_bytes(&self) -> &[u8] {
+        // SAFETY: The header is in bounds of the file, as checked by `from_bytes`
+        unsafe { std::slice::from_raw_parts(self.data, self.header.header_size()) }
+    }
 
-        used_blocks.push((0usize, header.header_size() + row_desc_sz));
-        used_blocks.push((header.data_end_ofs(), data.len() - header.data_end_ofs()));
-        used_blocks.sort_by_key(|b| b.0);
+    /// Returns the region of the file holding row data, which is between the row descriptors and
+    /// the end of row data.
+    ///
+    /// If `param_type_first` is set and the rows are stored after the param type string, the
+    /// region is between the end of the param type string and the first row name following it,
+    /// or the end of the file. `data_end` must be in bounds.
+    fn row_region(&self, param_type_first: bool) -> std::ops::Range<usize> {
+        let rows = self.row_descriptors.as_ref();
+        let descs_end = self.header.header_size() + std::mem::size_of_val(rows);
+        let data_end = self.header.data_end_ofs();
+        let first_row = rows.iter().map(|r| r.data_offset).min();
+        match first_row {
+            Some(first) if param_type_first && self.header.has_param_type_offset() => {
+                if first < data_end {
+                    return descs_end..data_end;
+                }
+                let param_type_end = data_end + self.param_type_bytes().len() + 1;
+                let names = rows.iter().map(|r| r.name_offset).filter(|&ofs| ofs >= first);
+                param_type_end..names.min().unwrap_or(self.file_size)
+            }
+            _ => descs_end..data_end,
+        }
+    }
 
-        let mut last_block_end = 0;
-        for (ofs, size) in used_blocks {
-            if ofs < last_block_end {
-                return Err(FromBytesError::IntersectingData);
+    /// Checks that row descriptors are sorted by ID, and that all data blocks we might access in
+    /// the file (1) aren't out of bounds and (2) don't intersect other blocks.
+    ///
+    /// If `param_type_first` is set, rows may be stored after the param type string, see
+    /// [`ParamFile::row_region`].
+    fn validate_data(&self, param_type_first: bool) -> Result<(), FromBytesError> {
+        let row_descriptors = self.row_descriptors.as_ref();
+
+        // Check if row descriptors are strictly sorted by ID
+        for (i, pair) in row_descriptors.windows(2).enumerate() {
+            let (previous_id, id) = (pair[0].id, pair[1].id);
+            if previous_id > id {
+                return Err(FromBytesError::UnsortedRowDescs {
+                    index: i + 1,
+                    previous_id,
+                    id,
+                });
+            }
+            if previous_id == id {
+                return Err(FromBytesError::DuplicateIds { index: i + 1, id });
             }
-            last_block_end = ofs + size;
         }
-        if last_block_end > data.len() {
-            return Err(FromBytesError::OutOfBoundsOffset);
+
+        // Row data must lie between the row descriptors and the end of the data section
+        let descs_end = self.header.header_size() + std::mem::size_of_val(row_descriptors);
+        let data_end = self.header.data_end_ofs();
+        if data_end > self.file_size {
+            return Err(FromBytesError::OutOfBoundsOffset {
+                block: DataBlock {
+                    kind: BlockKind::Strings,
+                    offset: data_end,
+                    len: 0,
+                },
+                file_size: self.file_size,
+            });
+        }
+        if data_end < descs_end {
+            return Err(FromBytesError::IntersectingData {
+                first: DataBlock {
+                    kind: BlockKind::Header,
+                    offset: 0,
+                    len: descs_end,
+                },
+                second: DataBlock {
+                    kind: BlockKind::Strings,
+                    offset: data_end,
+                    len: self.file_size - data_end,
+                },
+            });
         }
 
-        Ok(Self {
-            data: data.as_mut_ptr(),
-            file_size: data.len(),
-            row_size,
-            header,
-            row_descriptors,
-        })
+        // Names must start in the strings region, or have an offset of 0 if the row has none
+        let invalid_name = (row_descriptors.iter().map(|r| r.name_offset))
+            .position(|ofs| ofs != 0 && (ofs < data_end || ofs >= self.file_size));
+        if let Some(index) = invalid_name {
+            return Err(FromBytesError::InvalidNameOffset {
+                index,
+                offset: row_descriptors[index].name_offset,
+            });
+        }
+
+        // Fast path: rows are already sorted by offset, so no need to allocate and sort
+        let region = self.row_region(param_type_first);
+        let rows =
+            (row_descriptors.iter().enumerate()).map(|(i, r)| (r.data_offset, self.row_len(i), i));
+        if row_descriptors.windows(2).all(|p| p[0].data_offset <= p[1].data_offset) {
+            return check_sorted_rows(rows, region.start, region.end, self.file_size);
+        }
+
+        let mut sorted_rows: Vec<_> = rows.collect();
+        sorted_rows.sort_unstable();
+        check_sorted_rows(
+            sorted_rows.into_iter(),
+            region.start,
+            region.end,
+            self.file_size,
+        )
+    }
+
+    /// Returns a checksum of the parts of the file which are validated by
+    /// [`ParamFile::from_bytes`], to be passed to [`ParamFile::revalidate_cheap`].
+    ///
+    /// The checksum is computed whatever the validation of the file, so it only vouches for
+    /// files created with [`ParamFile::from_bytes`].
+    pub fn validation_checksum(&self) -> ValidationChecksum {
+        let region_size =
+            self.header.header_size() + std::mem::size_of_val(self.row_descriptors.as_ref());
+        let data = unsafe { std::slice::from_raw_parts(self.data, self.file_size) };
+        ValidationChecksum::compute(data, region_size)
     }
 
+    /// Creates a param file from a buffer that was previously validated by
+    /// [`ParamFile::from_bytes`], skipping validation if the file size, header and row
+    /// descriptors still match the given checksum.
+    ///
+    /// This only costs a hash of the header and row descriptors, and falls back to
+    /// [`ParamFile::from_bytes`] otherwise.
+    ///
+    /// # Safety
+    /// - `checksum` must have been returned by [`ParamFile::validation_checksum`] for a file
+    ///   created with [`ParamFile::from_bytes`], and not with a lower [`ValidationLevel`] or
+    ///   [`ParamFile::from_bytes_with_row_size`], which skip some of its checks.
+    /// - If `data` matches the checksum, its header and row descriptors must be the ones of that
+    ///   file. The checksum is not collision resistant: a file crafted to match it, e.g. by a mod,
+    ///   is not validated.
+    pub unsafe fn revalidate_cheap(
+        data: &'a mut [u8],
+        checksum: &ValidationChecksum,
+    ) -> Result<Self, FromBytesError> {
+        if checksum.matches(data) {
+            // SAFETY: Validation only depends on the file size, header and row descriptors, which
+            // the caller guarantees are the ones of a file validated by `from_bytes`
+            Ok(unsafe { Self::from_bytes_unchecked(data) })
+        }
+        else {
+            Self::from_bytes(data)
+        }
+    }
+
+    /// Size of the rows of the param.
+    ///
+    /// If the rows do not have a uniform size, this is the size of the smallest row.
     pub fn row_size(&self) -> usize {
         self.row_size
     }
 
+    /// Returns true if all rows have a size of [`ParamFile::row_size`].
+    ///
+    /// Otherwise, the size of each row must be obtained with [`Row::len`].
+    pub fn has_uniform_rows(&self) -> bool {
+        self.row_sizes.is_none()
+    }
+
+    /// Returns the size of the row at `index`.
+    fn row_len(&self, index: usize) -> usize {
+        self.row_sizes.as_ref().map_or(self.row_size, |s| s[index])
+    }
+
     pub fn header(&self) -> &ParamFileHeader {
-        return unsafe { &*(self.data as usize as *const ParamFileHeader) };
+        &self.header
     }
 
     pub fn row_descriptors(&self) -> &[ParamRowDescriptor] {
         &self.row_descriptors
     }
 
-    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
-        self.row_descriptors.iter().map(|r| Row {
-            id: r.id,
-            data: unsafe {
-                std::slice::from_raw_parts(self.data.add(r.data_offset), self.row_size)
-            },
+    /// Returns the bytes of the param type stored in the file, without its terminator.
+    ///
+    /// They are read from the header, or at [`ParamFileHeader::param_type_offset`] in the file.
+    /// The bytes may differ from the param type of the paramdef, e.g. in case or with trailing
+    /// whitespace, see [`FbRepoExt::get_normalized`](field_metadata::FbRepoExt::get_normalized)
+    /// to look up its field blocks.
+    pub fn param_type_bytes(&self) -> &[u8] {
+        let bytes = if let Some(ofs) = self.header.param_type_offset() {
+            let data = unsafe { std::slice::from_raw_parts(self.data, self.file_size) };
+            data.get(ofs..).unwrap_or_default()
+        }
+        else {
+            unsafe { &self.header.param_type_block.param_type_buf }
+        };
+        let len = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+        &bytes[..len]
+    }
+
+    /// Returns the param type stored in the file, replacing invalid UTF-8 sequences.
+    pub fn param_type(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.param_type_bytes())
+    }
+
+    /// Returns the encoded name of the row at `index` without its terminator, or `None` if the
+    /// row has no name offset or it is outside of the strings region.
+    pub(crate) fn row_name_bytes(&self, index: usize) -> Option<&[u8]> {
+        let ofs = self.row_descriptors.get(index)?.name_offset;
+        if ofs == 0 || ofs < self.header.data_end_ofs() {
+            return None;
+        }
+        let data = unsafe { std::slice::from_raw_parts(self.data, self.file_size) };
+        let bytes = data.get(ofs..).filter(|b| !b.is_empty())?;
+        let len = if self.header.is_unicode() {
+            let units = bytes.chunks_exact(2);
+            let full_len = bytes.len() - units.remainder().len();
+            units.into_iter().position(|c| c == [0, 0]).map_or(full_len, |i| 2 * i)
+        }
+        else {
+            bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len())
+        };
+        Some(&bytes[..len])
+    }
+
+    /// Returns the name of the row at `index`, replacing invalid sequences, or `None` if the row
+    /// has no name offset or it is outside of the strings region.
+    ///
+    /// Names are read up to their NUL terminator, or the end of the file. They are UTF-16 in
+    /// unicode files, and are otherwise decoded as UTF-8.
+    ///
+    /// Name offsets are checked by [`ParamFile::from_bytes`], but are also bounds checked here
+    /// for files parsed with a reduced [`ValidationLevel`].
+    pub fn row_name(&self, index: usize) -> Option<Cow<'_, str>> {
+        let bytes = self.row_name_bytes(index)?;
+        Some(decode_name(bytes, self.header.is_unicode()))
+    }
+
+    /// Iterates over the offset and name of each distinct name offset of the rows, in ascending
+    /// offset order.
+    ///
+    /// Game files store identical names once, in which case each name is yielded once. Rows
+    /// without a name, or whose name is outside of the strings region, are skipped. See
+    /// [`ParamFile::row_name`].
+    pub fn strings(&self) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+        let mut indices: Vec<usize> = (0..self.row_descriptors.len())
+            .filter(|&i| self.row_name_bytes(i).is_some())
+            .collect();
+        indices.sort_unstable_by_key(|&i| self.row_descriptors[i].name_offset);
+        indices.dedup_by_key(|i| self.row_descriptors[*i].name_offset);
+        indices.into_iter().map(|i| {
+            let name = self.row_name(i).unwrap_or_default();
+            (self.row_descriptors[i].name_offset, name)
         })
     }
 
+    /// Returns a pointer to the data of the row at `index`.
+    ///
+    /// In debug builds, re-checks that the row lies in the data section as a tripwire for
+    /// descriptors corrupted after validation.
+    fn row_ptr(&self, index: usize) -> *mut u8 {
+        let r = &self.row_descriptors[index];
+        #[cfg(debug_assertions)]
+        {
+            let descs_end =
+                self.header.header_size() + std::mem::size_of_val(&*self.row_descriptors);
+            let row_len = self.row_len(index);
+            let in_bounds = r.data_offset >= descs_end
+                && r.data_offset.checked_add(row_len).is_some_and(|end| end <= self.file_size);
+            assert!(
+                in_bounds,
+                "row {} of param {} has corrupted data offset {:#x} (row size {:#x}, file size {:#x})",
+                r.id,
+                self.param_type(),
+                r.data_offset,
+                row_len,
+                self.file_size
+            );
+        }
+        unsafe { self.data.add(r.data_offset) }
+    }
+
+    fn row(&self, index: usize) -> Row<'_> {
+        Row {
+            id: self.row_descriptors[index].id,
+            data: unsafe { std::slice::from_raw_parts(self.row_ptr(index), self.row_len(index)) },
+            name: self.row_name_bytes(index),
+            is_unicode: self.header.is_unicode(),
+        }
+    }
+
+    /// # Safety
+    /// No other reference to the data of the row at `index` may be live.
+    unsafe fn row_mut(&self, index: usize) -> RowMut<'_> {
+        RowMut {
+            id: self.row_descriptors[index].id,
+            data: std::slice::from_raw_parts_mut(self.row_ptr(index), self.row_len(index)),
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        (0..self.row_descriptors.len()).map(|i| self.row(i))
+    }
+
     pub fn rows_mut(&mut self) -> impl Iterator<Item = RowMut<'_>> {
-        self.row_descriptors.iter().map(|r| RowMut {
-            id: r.id,
-            data: unsafe {
-                std::slice::from_raw_parts_mut(self.data.add(r.data_offset), self.row_size)
-            },
-        })
+        let this = &*self;
+        // SAFETY: Rows don't intersect, and each one is only yielded once
+        (0..this.row_descriptors.len()).map(|i| unsafe { this.row_mut(i) })
     }
 
     pub fn get(&self, index: usize) -> Option<Row<'_>> {
-        let r = self.row_descriptors.get(index)?;
-        Some(Row {
-            id: r.id,
-            data: unsafe {
-                std::slice::from_raw_parts(self.data.add(r.data_offset), self.row_size)
-            },
-        })
+        (index < self.row_descriptors.len()).then(|| self.row(index))
     }
 
     pub fn get_mut(&mut self, index: usize) -> Option<RowMut<'_>> {
-        let r = self.row_descriptors.get(index)?;
-        Some(RowMut {
-            id: r.id,
-            data: unsafe {
-                std::slice::from_raw_parts_mut(self.data.add(r.data_offset), self.row_size)
-            },
-        })
+        // SAFETY: We have exclusive access to the file
+        (index < self.row_descriptors.len()).then(|| unsafe { self.row_mut(index) })
+    }
+
+    fn index_error(&self, index: usize) -> IndexError {
+        IndexError {
+            index,
+            row_count: self.row_descriptors.len(),
+        }
+    }
+
+    /// Returns the row at `index`.
+    ///
+    /// # Errors
+    /// If `index` is not less than the number of rows, returns [`IndexError`].
+    pub fn try_index(&self, index: usize) -> Result<Row<'_>, IndexError> {
+        self.get(index).ok_or(self.index_error(index))
+    }
+
+    /// Returns the row at `index` mutably.
+    ///
+    /// # Errors
+    /// If `index` is not less than the number of rows, returns [`IndexError`].
+    pub fn try_index_mut(&mut self, index: usize) -> Result<RowMut<'_>, IndexError> {
+        let err = self.index_error(index);
+        self.get_mut(index).ok_or(err)
+    }
+
+    #[cold]
+    #[track_caller]
+    fn index_panic(&self, index: usize) -> ! {
+        panic!(
+            "row index {index} out of range for param {} with {} rows",
+            self.param_type(),
+            self.row_descriptors.len()
+        )
     }
 
     pub fn index_of(&self, row_id: u32) -> Option<usize> {
@@ -287,15 +1346,296 @@ impl<'a> ParamFile<'a> {
 
 impl<'a> std::ops::Index<usize> for ParamFile<'a> {
     type Output = [u8];
+    #[track_caller]
     fn index(&self, index: usize) -> &Self::Output {
-        let r = &self.row_descriptors[index];
-        unsafe { std::slice::from_raw_parts(self.data.add(r.data_offset), self.row_size) }
+        match self.get(index) {
+            Some(row) => row.data,
+            None => self.index_panic(index),
+        }
     }
 }
 
 impl<'a> std::ops::IndexMut<usize> for ParamFile<'a> {
+    #[track_caller]
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        let r = &self.row_descriptors[index];
-        unsafe { std::slice::from_raw_parts_mut(self.data.add(r.data_offset), self.row_size) }
+        if index >= self.row_descriptors.len() {
+            self.index_panic(index)
+        }
+        self.get_mut(index).unwrap().data
+    }
+}
+
+/// Layout of the header and row descriptors of a param file, as given by its header flags.
+#[derive(Debug, Clone, Copy)]
+struct ForeignLayout {
+    endianness: Endianness,
+    format_flags_2d: u8,
+    offset_size: usize,
+    header_size: usize,
+    row_count: usize,
+}
+
+impl ForeignLayout {
+    const FLAG_64_BIT: u8 = 4;
+    const FLAG_PARAM_TYPE_OFFSET: u8 = 0x80;
+
+    fn new(endianness: Endianness, format_flags_2d: u8, row_count: usize) -> Self {
+        let is_64_bit = (format_flags_2d & Self::FLAG_64_BIT) != 0;
+        let has_data_offset = (format_flags_2d & 3) == 3 || is_64_bit;
+        Self {
+            endianness,
+            format_flags_2d,
+            offset_size: if is_64_bit { 8 } else { 4 },
+            header_size: if has_data_offset { 0x40 } else { 0x30 },
+            row_count,
+        }
+    }
+
+    /// Layout of the same file converted to the endianness and bitness of the target platform.
+    fn native(&self) -> Self {
+        let flags = if cfg!(target_pointer_width = "64") {
+            self.format_flags_2d | Self::FLAG_64_BIT
+        }
+        else {
+            self.format_flags_2d & !Self::FLAG_64_BIT
+        };
+        Self::new(Endianness::NATIVE, flags, self.row_count)
+    }
+
+    /// Size of a row descriptor: an ID, padded to the offset size, and two offsets.
+    fn desc_size(&self) -> usize {
+        3 * self.offset_size
+    }
+
+    fn descs_end(&self) -> usize {
+        self.header_size + self.row_count * self.desc_size()
+    }
+
+    fn read(&self, data: &[u8], ofs: usize, size: usize) -> Result<u64, FromBytesError> {
+        let bytes = data.get(ofs..ofs + size).ok_or(FromBytesError::BufferTooSmall)?;
+        let mut buf = [0; 8];
+        Ok(match self.endianness {
+            Endianness::Little => {
+                buf[..size].copy_from_slice(bytes);
+                u64::from_le_bytes(buf)
+            }
+            Endianness::Big => {
+                buf[8 - size..].copy_from_slice(bytes);
+                u64::from_be_bytes(buf)
+            }
+        })
+    }
+
+    fn read_offset(&self, data: &[u8], ofs: usize) -> Result<u64, FromBytesError> {
+        self.read(data, ofs, self.offset_size)
+    }
+
+    fn write(&self, data: &mut [u8], ofs: usize, size: usize, value: u64) {
+        let bytes = &mut data[ofs..ofs + size];
+        match self.endianness {
+            Endianness::Little => bytes.copy_from_slice(&value.to_le_bytes()[..size]),
+            Endianness::Big => bytes.copy_from_slice(&value.to_be_bytes()[8 - size..]),
+        }
+    }
+
+    fn write_offset(&self, data: &mut [u8], ofs: usize, value: u64) {
+        self.write(data, ofs, self.offset_size, value)
+    }
+}
+
+/// Param file converted from a file of any endianness and bitness, see
+/// [`ParamFileOwned::from_foreign_bytes`].
+///
+/// Derefs to a [`ParamFile`] over the converted buffer, which can only be read.
+#[derive(Debug)]
+pub struct ParamFileOwned {
+    /// Points into `buffer`, so it is dropped before `buffer` is freed.
+    file: ManuallyDrop<ParamFile<'static>>,
+    /// Converted file, as `usize`s so that it is aligned for row descriptors.
+    ///
+    /// Allocated by [`Box::into_raw`] and freed on drop. It is only accessed through this
+    /// pointer, so moving `self` moves no owner of the allocation which would invalidate the
+    /// pointers of `file` into it, and creating shared borrows of it doesn't either.
+    buffer: NonNull<[usize]>,
+    len: usize,
+    source_endianness: Endianness,
+    source_is_64_bit: bool,
+}
+
+impl ParamFileOwned {
+    /// Converts a param file of any endianness and bitness to a host-order copy.
+    ///
+    /// The header, row descriptors and offsets are converted to the endianness and bitness of the
+    /// target platform, which may change the size of the header and row descriptors and thus
+    /// move the rest of the file. UTF-16 row names are converted to the target endianness too.
+    ///
+    /// Row data is copied untouched: fields of files with another endianness than the target
+    /// must be read with [`Row::read_at`] and [`ParamFileOwned::source_endianness`].
+    ///
+    /// # Errors
+    /// Same as [`ParamFile::from_bytes`] on the converted file, except that files are never
+    /// [`FromBytesError::UnsupportedFile`].
+    pub fn from_foreign_bytes(data: &[u8]) -> Result<Self, FromBytesError> {
+        if data.len() < std::mem::size_of::<ParamFileHeader>() {
+            return Err(FromBytesError::BufferTooSmall);
+        }
+        let endianness = if data[0x2C] != 0 { Endianness::Big } else { Endianness::Little };
+        let mut src = ForeignLayout::new(endianness, data[0x2D], 0);
+        src.row_count = src.read(data, 0xA, 2)? as usize;
+        let row_count = src.row_count;
+        let dst = src.native();
+        if data.len() < src.descs_end() {
+            return Err(FromBytesError::BufferTooSmall);
+        }
+
+        let len = data.len() - src.descs_end() + dst.descs_end();
+        let mut buffer = vec![0usize; len.div_ceil(std::mem::size_of::<usize>())];
+        // SAFETY: The buffer holds at least `len` bytes, and integers are valid for any bit pattern
+        let out = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, len) };
+        // Offsets are reported in the header or the row descriptor holding them
+        let shift = |ofs: u64, kind: BlockKind| {
+            let shifted = (ofs as usize).checked_add(dst.descs_end());
+            shifted.and_then(|o| o.checked_sub(src.descs_end())).ok_or(
+                FromBytesError::OutOfBoundsOffset {
+                    block: DataBlock {
+                        kind,
+                        offset: ofs as usize,
+                        len: 0,
+                    },
+                    file_size: data.len(),
+                },
+            )
+        };
+
+        // Header
+        out[..0x30].copy_from_slice(&data[..0x30]);
+        let strings_offset = shift(src.read(data, 0, 4)?, BlockKind::Header)?;
+        dst.write(out, 0, 4, strings_offset as u64);
+        let short_data_offset = src.read(data, 4, 2)?;
+        if short_data_offset != 0 {
+            // Files whose data starts past 64KiB only store the low bits
+            let short_data_offset = shift(short_data_offset, BlockKind::Header)?;
+            dst.write(out, 4, 2, short_data_offset as u64 & 0xFFFF);
+        }
+        for ofs in [6, 8, 0xA] {
+            dst.write(out, ofs, 2, src.read(data, ofs, 2)?);
+        }
+        if (src.format_flags_2d & ForeignLayout::FLAG_PARAM_TYPE_OFFSET) != 0 {
+            dst.write(out, 0xC, 4, src.read(data, 0xC, 4)?);
+            let param_type_offset = shift(src.read_offset(data, 0x10)?, BlockKind::Header)?;
+            out[0x10..0x10 + src.offset_size.max(dst.offset_size)].fill(0);
+            dst.write_offset(out, 0x10, param_type_offset as u64);
+        }
+        out[0x2C] = (dst.endianness == Endianness::Big) as u8;
+        out[0x2D] = dst.format_flags_2d;
+        if dst.header_size == 0x40 {
+            let data_offset = match src.header_size {
+                0x40 => shift(src.read_offset(data, 0x30)?, BlockKind::Header)?,
+                _ => dst.descs_end(),
+            };
+            dst.write_offset(out, 0x30, data_offset as u64);
+        }
+
+        // Row descriptors
+        let mut name_offsets = Vec::with_capacity(row_count);
+        for i in 0..row_count {
+            let (src_desc, dst_desc) = (
+                src.header_size + i * src.desc_size(),
+                dst.header_size + i * dst.desc_size(),
+            );
+            // Rows without a name have a null name offset
+            let name_offset = match src.read_offset(data, src_desc + 2 * src.offset_size)? {
+                0 => 0,
+                ofs => shift(ofs, BlockKind::Row(i))?,
+            };
+            let data_offset = shift(
+                src.read_offset(data, src_desc + src.offset_size)?,
+                BlockKind::Row(i),
+            )?;
+            // Written field by field, as writing the whole descriptor would leave its padding
+            // uninitialized
+            dst.write(out, dst_desc, 4, src.read(data, src_desc, 4)?);
+            let (data_ofs, name_ofs) = (
+                dst_desc + std::mem::offset_of!(ParamRowDescriptor, data_offset),
+                dst_desc + std::mem::offset_of!(ParamRowDescriptor, name_offset),
+            );
+            dst.write_offset(out, data_ofs, data_offset as u64);
+            dst.write_offset(out, name_ofs, name_offset as u64);
+            name_offsets.push(name_offset);
+        }
+
+        // Row data and strings
+        out[dst.descs_end()..].copy_from_slice(&data[src.descs_end()..]);
+        let is_unicode = (data[0x2E] & 1) != 0;
+        if is_unicode && src.endianness != dst.endianness {
+            name_offsets.sort_unstable();
+            name_offsets.dedup();
+            for ofs in name_offsets.into_iter().filter(|&o| o >= dst.descs_end()) {
+                let name = out.get_mut(ofs..).unwrap_or_default();
+                for c in name.chunks_exact_mut(2) {
+                    c.swap(0, 1);
+                    if c == [0, 0] {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let buffer = NonNull::from(Box::leak(buffer.into_boxed_slice()));
+        // SAFETY: The buffer holds at least `len` bytes. It is only freed on drop, after the file
+        // which borrows it, and is only accessed through `file` and shared borrows until then.
+        let out = unsafe { std::slice::from_raw_parts_mut(buffer.as_ptr() as *mut u8, len) };
+        let file = match ParamFile::from_bytes(out) {
+            Ok(file) => ManuallyDrop::new(file),
+            Err(e) => {
+                // SAFETY: The buffer was allocated by `Box::leak` and nothing borrows it anymore
+                drop(unsafe { Box::from_raw(buffer.as_ptr()) });
+                return Err(e);
+            }
+        };
+        Ok(Self {
+            file,
+            buffer,
+            len,
+            source_endianness: src.endianness,
+            source_is_64_bit: src.offset_size == 8,
+        })
+    }
+
+    /// Byte order of the original file, which row data is still stored in.
+    pub fn source_endianness(&self) -> Endianness {
+        self.source_endianness
+    }
+
+    /// Returns true if the original file had 64-bit offsets.
+    pub fn source_is_64_bit(&self) -> bool {
+        self.source_is_64_bit
+    }
+
+    /// Returns the bytes of the converted file, which can be parsed with
+    /// [`ParamFile::from_bytes`].
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The buffer holds at least `len` initialized bytes, which can only be mutated
+        // through `&mut self`
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.len) }
+    }
+}
+
+impl Drop for ParamFileOwned {
+    fn drop(&mut self) {
+        // SAFETY: The file is not used after this, and is dropped before the buffer it borrows,
+        // which was allocated by `Box::leak`
+        unsafe {
+            ManuallyDrop::drop(&mut self.file);
+            drop(Box::from_raw(self.buffer.as_ptr()));
+        }
+    }
+}
+
+impl std::ops::Deref for ParamFileOwned {
+    type Target = ParamFile<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.file
     }
 }
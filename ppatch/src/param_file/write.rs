@@ -0,0 +1,255 @@
+//! Writing param files from scratch with [`ParamFileWriter`].
+//!
+//! Where [`ParamFileBuilder`](crate::param_builder::ParamFileBuilder) starts from an existing
+//! file, the writer only needs the [`Paramdef`] of the param, which gives its param type and row
+//! size, and a [`ParamFormat`]. Files are laid out like the game files: the header, the row
+//! descriptors, the row data in ID order, the param type (if stored at an offset), then the
+//! distinct row names.
+
+use std::{collections::HashMap, fmt};
+
+use paramdex::paramdef::Paramdef;
+
+use super::{Endianness, ForeignLayout};
+
+/// Size of the param type buffer of headers storing it inline.
+const INLINE_PARAM_TYPE_SIZE: usize = 0x20;
+
+/// Layout of a file written by [`ParamFileWriter`].
+///
+/// Files are written with the endianness of the target platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamFormat {
+    /// Offsets are 64-bit rather than 32-bit.
+    ///
+    /// [`ParamFile::from_bytes`](super::ParamFile::from_bytes) only accepts files with the
+    /// bitness of the target platform. Others can be converted with
+    /// [`ParamFileOwned::from_foreign_bytes`](super::ParamFileOwned::from_foreign_bytes).
+    pub is_64_bit: bool,
+    /// Row names are UTF-16 rather than UTF-8.
+    pub is_unicode: bool,
+    /// The header is 0x40 bytes and holds the offset of the row data, rather than 0x30 bytes.
+    /// The headers of 64-bit files are always 0x40 bytes.
+    pub long_header: bool,
+    /// The param type is stored after the row data rather than in the header, which can only
+    /// hold 32 bytes.
+    pub param_type_offset: bool,
+}
+
+impl Default for ParamFormat {
+    /// Format of the params of recent games for the target platform, which
+    /// [`ParamFile::from_bytes`](super::ParamFile::from_bytes) accepts.
+    fn default() -> Self {
+        Self {
+            is_64_bit: cfg!(target_pointer_width = "64"),
+            is_unicode: true,
+            long_header: true,
+            param_type_offset: true,
+        }
+    }
+}
+
+impl ParamFormat {
+    const FLAG_64_BIT: u8 = 4;
+    const FLAG_DATA_OFFSET: u8 = 3;
+    const FLAG_PARAM_TYPE_OFFSET: u8 = 0x80;
+
+    fn format_flags_2d(&self) -> u8 {
+        let mut flags = 0;
+        if self.is_64_bit {
+            flags |= Self::FLAG_64_BIT;
+        }
+        if self.long_header {
+            flags |= Self::FLAG_DATA_OFFSET;
+        }
+        if self.param_type_offset {
+            flags |= Self::FLAG_PARAM_TYPE_OFFSET;
+        }
+        flags
+    }
+}
+
+/// Error returned by [`ParamFileWriter::write`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteParamError {
+    /// The size of the def is unknown, as its field offsets were not computed with
+    /// [`Paramdef::compute_field_offsets`].
+    UnknownRowSize { param_type: String },
+    /// The data of the row with ID `id` is not the size of the rows of the def.
+    RowSizeMismatch {
+        id: u32,
+        row_size: usize,
+        len: usize,
+    },
+    /// Two rows have the same ID.
+    DuplicateId(u32),
+    /// There are more rows than a file can hold.
+    TooManyRows,
+    /// The param type is stored in the header, which can't hold its `len` bytes. See
+    /// [`ParamFormat::param_type_offset`].
+    ParamTypeTooLong { len: usize },
+}
+
+impl fmt::Display for WriteParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownRowSize { param_type } => {
+                write!(f, "field offsets of the {param_type} def were not computed")
+            }
+            Self::RowSizeMismatch { id, row_size, len } => write!(
+                f,
+                "row {id} is {len} bytes long instead of the row size {row_size}"
+            ),
+            Self::DuplicateId(id) => write!(f, "param already has a row with ID {id}"),
+            Self::TooManyRows => write!(f, "param files can't hold more than {} rows", u16::MAX),
+            Self::ParamTypeTooLong { len } => write!(
+                f,
+                "param type of {len} bytes doesn't fit the {INLINE_PARAM_TYPE_SIZE} bytes of the header"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WriteParamError {}
+
+/// Writes param files of the type of a [`Paramdef`].
+///
+/// The field offsets of the def must have been computed with
+/// [`Paramdef::compute_field_offsets`], which gives the row size.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamFileWriter<'a> {
+    def: &'a Paramdef,
+    format: ParamFormat,
+}
+
+impl<'a> ParamFileWriter<'a> {
+    pub fn new(def: &'a Paramdef, format: ParamFormat) -> Self {
+        Self { def, format }
+    }
+
+    pub fn format(&self) -> &ParamFormat {
+        &self.format
+    }
+
+    /// Emits a file holding `rows`, given as their ID, name and data.
+    ///
+    /// Rows are sorted by ID, so they may be given in any order. Names are encoded as UTF-16 in
+    /// unicode files, and as UTF-8 otherwise. Identical names are stored once.
+    ///
+    /// The paramdef data version in the header is the data version of the def.
+    ///
+    /// # Errors
+    /// - If the field offsets of the def were not computed, returns
+    ///   [`WriteParamError::UnknownRowSize`].
+    /// - If the data of a row is not the size of the def, returns
+    ///   [`WriteParamError::RowSizeMismatch`].
+    /// - If two rows have the same ID, returns [`WriteParamError::DuplicateId`].
+    /// - If there are more than `u16::MAX` rows, returns [`WriteParamError::TooManyRows`].
+    /// - If the param type is stored in the header but is longer than 32 bytes, returns
+    ///   [`WriteParamError::ParamTypeTooLong`].
+    pub fn write<'r>(
+        &self,
+        rows: impl IntoIterator<Item = (u32, Option<&'r str>, &'r [u8])>,
+    ) -> Result<Vec<u8>, WriteParamError> {
+        let row_size = self.def.size_bytes.ok_or_else(|| WriteParamError::UnknownRowSize {
+            param_type: self.def.param_type.clone(),
+        })?;
+        let param_type = self.def.param_type.as_bytes();
+        if !self.format.param_type_offset && param_type.len() > INLINE_PARAM_TYPE_SIZE {
+            return Err(WriteParamError::ParamTypeTooLong {
+                len: param_type.len(),
+            });
+        }
+
+        let mut rows: Vec<_> = rows.into_iter().collect();
+        if let Some(&(id, _, data)) = rows.iter().find(|(_, _, data)| data.len() != row_size) {
+            return Err(WriteParamError::RowSizeMismatch {
+                id,
+                row_size,
+                len: data.len(),
+            });
+        }
+        rows.sort_by_key(|&(id, _, _)| id);
+        if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(WriteParamError::DuplicateId(pair[0].0));
+        }
+        if rows.len() > u16::MAX as usize {
+            return Err(WriteParamError::TooManyRows);
+        }
+
+        let layout = ForeignLayout::new(
+            Endianness::NATIVE,
+            self.format.format_flags_2d(),
+            rows.len(),
+        );
+        let data_start = layout.descs_end();
+        let mut out = vec![0u8; data_start];
+        let mut data_offsets = Vec::with_capacity(rows.len());
+        for &(_, _, data) in &rows {
+            data_offsets.push(out.len());
+            out.extend_from_slice(data);
+        }
+
+        if self.format.param_type_offset {
+            let ofs = out.len();
+            layout.write_offset(&mut out, 0x10, ofs as u64);
+            out.extend_from_slice(param_type);
+            out.push(0);
+        }
+        else {
+            out[0xC..0xC + param_type.len()].copy_from_slice(param_type);
+        }
+
+        // Names start at the strings offset, which is past the param type when it is stored at
+        // an offset
+        let strings_offset = out.len();
+        let is_unicode = self.format.is_unicode;
+        let terminator: &[u8] = if is_unicode { &[0, 0] } else { &[0] };
+        let mut name_offsets = HashMap::new();
+        let names = rows.iter().map(|&(_, name, _)| {
+            let name = name?;
+            let ofs = *name_offsets.entry(name).or_insert_with(|| {
+                if is_unicode && !out.len().is_multiple_of(2) {
+                    out.push(0);
+                }
+                let ofs = out.len();
+                if is_unicode {
+                    out.extend(name.encode_utf16().flat_map(u16::to_ne_bytes));
+                }
+                else {
+                    out.extend_from_slice(name.as_bytes());
+                }
+                out.extend_from_slice(terminator);
+                ofs
+            });
+            Some(ofs)
+        });
+        let name_offsets: Vec<_> = names.collect();
+
+        // Header
+        layout.write(&mut out, 0, 4, strings_offset as u64);
+        if layout.header_size == 0x30 {
+            // Files whose data starts past 64KiB only store the low bits
+            layout.write(&mut out, 4, 2, data_start as u64 & 0xFFFF);
+        }
+        layout.write(&mut out, 8, 2, self.def.data_version as u64 & 0xFFFF);
+        layout.write(&mut out, 0xA, 2, rows.len() as u64);
+        out[0x2C] = (Endianness::NATIVE == Endianness::Big) as u8;
+        out[0x2D] = self.format.format_flags_2d();
+        out[0x2E] = is_unicode as u8;
+        if layout.header_size == 0x40 {
+            layout.write_offset(&mut out, 0x30, data_start as u64);
+        }
+
+        // Row descriptors
+        let descs = rows.iter().zip(data_offsets).zip(name_offsets);
+        for (i, ((&(id, _, _), data_offset), name_offset)) in descs.enumerate() {
+            let ofs = layout.header_size + i * layout.desc_size();
+            layout.write(&mut out, ofs, 4, id as u64);
+            layout.write_offset(&mut out, ofs + layout.offset_size, data_offset as u64);
+            let name_offset = name_offset.unwrap_or(0) as u64;
+            layout.write_offset(&mut out, ofs + 2 * layout.offset_size, name_offset);
+        }
+        Ok(out)
+    }
+}
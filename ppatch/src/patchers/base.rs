@@ -1,22 +1,358 @@
-use crate::util::unaligned::Unaligned;
-pub use field_metadata::FieldBlock;
+use std::{
+    hash::Hasher,
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use field_metadata::validate_field_blocks;
+pub use field_metadata::{FieldBlock, FieldBlockViolation, InvalidFieldBlocks};
 use num_traits::PrimInt;
 
+use crate::{
+    fields::fields_in_byte_range,
+    param_file::{Row, RowMut, UnalignedRowSize},
+    util::{diff_span::changed_block_span, unaligned::Unaligned},
+};
+
 /// Type representing an ID for a given row patch.
 ///
-/// A[`RowPatchId`] is only guaranteed to be unique for a specific instance of [`RowPatcher`].
-pub type RowPatchId = usize;
+/// The high 32 bits hold the tag of the [`RowPatcher`] instance which issued the ID (see
+/// [`next_instance_tag`]), and the low 32 bits an ID which is only unique for that instance.
+/// This lets patchers reject IDs issued by another instance instead of corrupting memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RowPatchId(u64);
+
+impl RowPatchId {
+    pub const fn new(instance_tag: u32, local_id: u32) -> Self {
+        Self((instance_tag as u64) << 32 | local_id as u64)
+    }
+
+    /// Tag of the patcher instance which issued this ID.
+    pub const fn instance_tag(self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    /// ID of the patch local to the patcher instance which issued it.
+    pub const fn local_id(self) -> u32 {
+        self.0 as u32
+    }
+
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+}
+
+impl std::fmt::Display for RowPatchId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:08x}:{:08x}", self.instance_tag(), self.local_id())
+    }
+}
+
+/// Returns a new process-wide unique tag to be used by a [`RowPatcher`] instance for the
+/// [`RowPatchId`]s it issues.
+///
+/// Tags only wrap around after 2^32 patcher instances have been created.
+pub fn next_instance_tag() -> u32 {
+    INSTANCE_TAG_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+static INSTANCE_TAG_COUNTER: AtomicU32 = AtomicU32::new(1);
+
+/// Makes sure [`next_instance_tag`] doesn't hand out `tag` (or any tag before it) again, e.g.
+/// when a patcher using it is restored from a previous process.
+pub(crate) fn reserve_instance_tag(tag: u32) {
+    INSTANCE_TAG_COUNTER.fetch_max(tag.saturating_add(1), Ordering::Relaxed);
+}
+
+/// Hash identifying a field block layout, to check that persisted patcher state is restored on
+/// the layout it was created with.
+///
+/// The hash is stable across processes, builds and targets, but depends on the block type.
+pub fn field_blocks_hash<N: PrimInt>(field_blocks: &[FieldBlock<N>]) -> u64 {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write_u8(std::mem::size_of::<N>() as u8);
+    for fb in field_blocks {
+        hasher.write(&fb.field_start.to_le_bytes());
+        hasher.write(&fb.offset.to_le_bytes());
+        hasher.write(&fb.mask.to_u64().unwrap().to_le_bytes());
+    }
+    hasher.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestorePatchError {
+    /// The [`RowPatchId`] was issued by another patcher instance.
+    ForeignId,
+    /// The [`RowPatchId`] does not refer to an outstanding patch (e.g. it was already restored).
+    UnknownId,
+    /// The live memory is not the size of the patched row, in blocks. Only returned by
+    /// [`ParamPatchManager::restore`](super::manager::ParamPatchManager::restore), which knows
+    /// the size of the rows it patches.
+    SizeMismatch { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for RestorePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ForeignId => f.write_str("row patch ID was issued by another patcher"),
+            Self::UnknownId => f.write_str("row patch ID does not refer to an outstanding patch"),
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "live memory is {actual} blocks long, but the patched row is {expected} blocks"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RestorePatchError {}
+
+/// Returns the range of `field_blocks` lying in the span of blocks which differ between `before`
+/// and `after`. Field blocks outside of it cannot be part of a change.
+///
+/// This lets patchers skip checking the blocks of the whole row for changes when only a few
+/// fields changed. The field blocks are not necessarily sorted by offset (the blocks of an
+/// unofficial field come after the blocks of the field it was carved from), so the range goes
+/// from the first to the last field block in the span and may include some outside of it.
+pub(crate) fn changed_field_blocks<N: PrimInt>(
+    field_blocks: &[FieldBlock<N>],
+    before: &[Unaligned<N>],
+    after: &[Unaligned<N>],
+) -> Range<usize> {
+    let Some(span) = changed_block_span(before, after)
+    else {
+        return 0..0;
+    };
+    let in_span = |fb: &FieldBlock<N>| span.contains(&(fb.offset as usize));
+    match field_blocks.iter().position(in_span) {
+        Some(start) => start..field_blocks.iter().rposition(in_span).unwrap() + 1,
+        None => 0..0,
+    }
+}
 
 /// Trait representing a data structure for creating and restoring
 /// patches to a single param row, working in blocks of `N`.
 pub trait RowPatcher<'a, N: PrimInt = u32> {
     fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self;
 
+    /// Creates a patcher after checking that `field_blocks` upholds the invariants patchers
+    /// rely on (see [`field_metadata::validate_field_blocks`]).
+    ///
+    /// # Errors
+    /// If the field blocks are invalid, returns [`InvalidFieldBlocks`].
+    fn try_new(
+        field_blocks: &'a [FieldBlock<N>],
+        row_size: usize,
+    ) -> Result<Self, InvalidFieldBlocks>
+    where
+        Self: Sized,
+    {
+        validate_field_blocks(field_blocks)?;
+        Ok(Self::new(field_blocks, row_size))
+    }
+
+    /// Creates a patch from the changes between `before` and `after`, returning its ID.
+    ///
+    /// Returns `None` if the patcher can't take another patch, for example once it has run out
+    /// of patch IDs. IDs are never reused, so an outstanding patch can't be shadowed.
     fn create_patch(
         &mut self,
         before: &[Unaligned<N>],
         after: &[Unaligned<N>],
     ) -> Option<RowPatchId>;
 
-    fn restore_patch(&mut self, id: RowPatchId, live_memory: &mut [Unaligned<N>]);
+    /// Restores the patch with the given ID, leaving changes made by other patches intact.
+    ///
+    /// # Errors
+    /// - If `id` was issued by another patcher instance, returns [`RestorePatchError::ForeignId`].
+    /// - If `id` does not refer to an outstanding patch, returns [`RestorePatchError::UnknownId`].
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError>;
+
+    /// Returns a bitmask the size of the row (in blocks) where all the bits of the fields changed
+    /// by at least one outstanding patch are set, e.g. to render a heatmap of patched data.
+    fn patched_mask_for_row(&self) -> Vec<N>;
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchRowError {
+    /// The row size is not a multiple of the block size.
+    UnalignedRowSize(UnalignedRowSize),
+    /// The patcher could not create the patch. The edit has been rolled back.
+    PatchRejected,
+}
+
+impl std::fmt::Display for PatchRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnalignedRowSize(e) => e.fmt(f),
+            Self::PatchRejected => f.write_str("patcher rejected the patch"),
+        }
+    }
+}
+
+impl std::error::Error for PatchRowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::UnalignedRowSize(e) => Some(e),
+            Self::PatchRejected => None,
+        }
+    }
+}
+
+impl From<UnalignedRowSize> for PatchRowError {
+    fn from(value: UnalignedRowSize) -> Self {
+        Self::UnalignedRowSize(value)
+    }
+}
+
+/// Error returned by [`raw_read`] and [`RowPatcherExt::raw_write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawAccessError {
+    /// The `len` bytes at `offset` do not lie within the row, which is `row_size` bytes long.
+    OutOfBounds {
+        offset: usize,
+        len: usize,
+        row_size: usize,
+    },
+    /// The range covers no field of the row, and unmapped writes were not allowed.
+    Unmapped { offset: usize, len: usize },
+    /// The write could not be patched, see [`RowPatcherExt::patch_row`]. The write has been
+    /// rolled back.
+    PatchRow(PatchRowError),
+}
+
+impl std::fmt::Display for RawAccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::OutOfBounds {
+                offset,
+                len,
+                row_size,
+            } => write!(
+                f,
+                "{len} bytes at offset {offset:#x} are out of bounds of the row size {row_size:#x}"
+            ),
+            Self::Unmapped { offset, len } => {
+                write!(f, "{len} bytes at offset {offset:#x} cover no field")
+            }
+            Self::PatchRow(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RawAccessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PatchRow(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PatchRowError> for RawAccessError {
+    fn from(value: PatchRowError) -> Self {
+        Self::PatchRow(value)
+    }
+}
+
+/// Patch created by [`RowPatcherExt::raw_write`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawWrite {
+    pub id: RowPatchId,
+    /// The `field_start` of each field the write covers, see [`fields_in_byte_range`].
+    pub fields: Vec<u16>,
+}
+
+fn check_raw_range(row_size: usize, offset: usize, len: usize) -> Result<(), RawAccessError> {
+    if offset.checked_add(len).is_none_or(|end| end > row_size) {
+        return Err(RawAccessError::OutOfBounds {
+            offset,
+            len,
+            row_size,
+        });
+    }
+    Ok(())
+}
+
+/// Reads the `len` bytes at `offset` of `row`.
+///
+/// Reads may cover bytes which are not part of any field, e.g. padding.
+///
+/// # Errors
+/// If the range does not lie within the row, returns [`RawAccessError::OutOfBounds`].
+pub fn raw_read<'a>(row: &Row<'a>, offset: usize, len: usize) -> Result<&'a [u8], RawAccessError> {
+    check_raw_range(row.len(), offset, len)?;
+    Ok(&row.data()[offset..offset + len])
+}
+
+/// Convenience methods for patching rows obtained from a [`crate::param_file::ParamFile`].
+pub trait RowPatcherExt<'a, N: PrimInt = u32>: RowPatcher<'a, N> {
+    /// Snapshots the row, runs `edit` on its bytes and creates a patch from the changes it made.
+    ///
+    /// # Errors
+    /// - If the row size is not a multiple of the block size, returns
+    ///   [`PatchRowError::UnalignedRowSize`] without running `edit`.
+    /// - If the patcher refuses to create the patch, the row is restored to its previous
+    ///   contents and [`PatchRowError::PatchRejected`] is returned.
+    fn patch_row(
+        &mut self,
+        row: &mut RowMut<'_>,
+        edit: impl FnOnce(&mut [u8]),
+    ) -> Result<RowPatchId, PatchRowError> {
+        let before = row.as_blocks::<N>()?.to_vec();
+        edit(row.data_mut());
+
+        match self.create_patch(&before, row.as_blocks::<N>()?) {
+            Some(id) => Ok(id),
+            None => {
+                row.as_blocks_mut::<N>()?.copy_from_slice(&before);
+                Err(PatchRowError::PatchRejected)
+            }
+        }
+    }
+
+    /// Writes `bytes` at `offset` of `row` and creates a patch for the write.
+    ///
+    /// This is the narrow entry point for scripting integrations, which validates the range and
+    /// reports the fields it covers. `field_blocks` must be the field blocks the patcher was
+    /// created with. Ranges covering no field are rejected unless `allow_unmapped` is set.
+    /// Patchers only track the bits of fields, so restoring the patch leaves unmapped bytes as
+    /// written.
+    ///
+    /// # Errors
+    /// - If the range does not lie within the row, returns [`RawAccessError::OutOfBounds`].
+    /// - If the range covers no field and `allow_unmapped` is not set, returns
+    ///   [`RawAccessError::Unmapped`].
+    /// - If the row can't be patched, returns [`RawAccessError::PatchRow`] with the error of
+    ///   [`Self::patch_row`], which leaves the row as it was.
+    fn raw_write(
+        &mut self,
+        field_blocks: &[FieldBlock<N>],
+        row: &mut RowMut<'_>,
+        offset: usize,
+        bytes: &[u8],
+        allow_unmapped: bool,
+    ) -> Result<RawWrite, RawAccessError> {
+        check_raw_range(row.len(), offset, bytes.len())?;
+        let fields = fields_in_byte_range(field_blocks, offset, bytes.len());
+        if fields.is_empty() && !allow_unmapped {
+            return Err(RawAccessError::Unmapped {
+                offset,
+                len: bytes.len(),
+            });
+        }
+        let id = self.patch_row(row, |data| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        })?;
+        Ok(RawWrite { id, fields })
+    }
+}
+
+impl<'a, N: PrimInt, P: RowPatcher<'a, N>> RowPatcherExt<'a, N> for P {}
@@ -0,0 +1,122 @@
+use num_traits::PrimInt;
+
+use super::base::{next_instance_tag, FieldBlock, RestorePatchError, RowPatchId, RowPatcher};
+use crate::util::unaligned::Unaligned;
+
+#[derive(Debug, Clone)]
+struct FullCopyPatch<N: PrimInt> {
+    id: RowPatchId,
+    /// Copy of the row before the patch, with the bits of fields changed by patches restored
+    /// below this one replaced by the values they had before them.
+    before: Box<[Unaligned<N>]>,
+    /// Bits of all the fields changed by the patch.
+    mask: Box<[N]>,
+}
+
+/// Row patcher which stores a full copy of the row for every patch.
+///
+/// This is the simplest possible implementation of [`RowPatcher`], meant as a baseline to test
+/// the other patchers against and for debugging. Field blocks are only used to find the fields
+/// changed by a patch.
+///
+/// Restoring a patch writes back the original value of the fields it changed, unless a more
+/// recent outstanding patch changed them too. In that case, that patch inherits the original
+/// values instead, to write them back once it is restored.
+///
+/// ### Memory consumed per patch
+/// `48 + 2*row_size`
+///
+/// ### Complexity of [`RowPatcher::create_patch`]
+/// `O(row_size + n_fields)`
+///
+/// ### Complexity of [`RowPatcher::restore_patch`]
+/// `O(row_size * n_patches)`
+///
+#[derive(Debug, Clone)]
+pub struct FullCopyPatcher<'a, N: PrimInt = u32> {
+    field_blocks: &'a [FieldBlock<N>],
+    row_blocks: usize,
+    /// Outstanding patches, in creation order.
+    patches: Vec<FullCopyPatch<N>>,
+    id_counter: u32,
+    instance_tag: u32,
+}
+
+impl<'a, N: PrimInt> RowPatcher<'a, N> for FullCopyPatcher<'a, N> {
+    fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
+        Self {
+            field_blocks,
+            row_blocks: row_size / std::mem::size_of::<N>(),
+            patches: Vec::new(),
+            id_counter: 0,
+            instance_tag: next_instance_tag(),
+        }
+    }
+
+    fn create_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        let mut field_changed = vec![false; self.field_blocks.len()];
+        for fb in self.field_blocks {
+            let offset = fb.offset as usize;
+            if !((before[offset].read() ^ after[offset].read()) & fb.mask).is_zero() {
+                field_changed[fb.field_start as usize] = true;
+            }
+        }
+        let mut mask = vec![N::zero(); self.row_blocks].into_boxed_slice();
+        for fb in self.field_blocks.iter().filter(|fb| field_changed[fb.field_start as usize]) {
+            let m = &mut mask[fb.offset as usize];
+            *m = *m | fb.mask;
+        }
+
+        self.id_counter = self.id_counter.checked_add(1)?;
+        let id = RowPatchId::new(self.instance_tag, self.id_counter);
+        self.patches.push(FullCopyPatch {
+            id,
+            before: before[..self.row_blocks].into(),
+            mask,
+        });
+        Some(id)
+    }
+
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        if id.instance_tag() != self.instance_tag {
+            return Err(RestorePatchError::ForeignId);
+        }
+        let index =
+            (self.patches.iter().position(|p| p.id == id)).ok_or(RestorePatchError::UnknownId)?;
+        let patch = self.patches.remove(index);
+
+        for (offset, &patch_mask) in patch.mask.iter().enumerate() {
+            let original = patch.before[offset].read();
+            let mut remaining = patch_mask;
+            // Hand the original values down to the next patches which changed the same bits
+            for later in &mut self.patches[index..] {
+                let bits = remaining & later.mask[offset];
+                let later_before = later.before[offset].read();
+                later.before[offset] = Unaligned((later_before & !bits) | (original & bits));
+                remaining = remaining & !bits;
+            }
+            // The others are still visible, so write them back
+            let live = live_memory[offset].read();
+            live_memory[offset] = Unaligned((live & !remaining) | (original & remaining));
+        }
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        let mut mask = vec![N::zero(); self.row_blocks];
+        for patch in &self.patches {
+            for (m, &pm) in mask.iter_mut().zip(patch.mask.iter()) {
+                *m = *m | pm;
+            }
+        }
+        mask
+    }
+}
@@ -0,0 +1,174 @@
+use num_traits::PrimInt;
+
+use super::base::{FieldBlock, RestorePatchError, RowPatchId, RowPatcher};
+use crate::util::unaligned::Unaligned;
+
+/// A patch recorded by a [`PatchJournal`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Position of the patch in creation order. Sequence numbers are never reused, so they keep
+    /// increasing after patches are restored.
+    pub seq: u64,
+    pub id: RowPatchId,
+    /// Tag given to [`PatchJournal::create_patch_tagged`], e.g. the name of the mod which made
+    /// the change.
+    pub tag: Option<String>,
+}
+
+/// Row patcher wrapper which records the order in which patches were created, to undo them in
+/// reverse order.
+///
+/// Patches restored through the wrapper are removed from the journal, whether they are restored
+/// by [`RowPatcher::restore_patch`] or one of the undo methods. These restore patches from the
+/// most recent one, which is also the cheapest order for patchers keeping a stack of patches
+/// like the sparse array patcher.
+#[derive(Debug, Clone)]
+pub struct PatchJournal<P> {
+    patcher: P,
+    /// Outstanding patches, in creation order.
+    entries: Vec<JournalEntry>,
+    next_seq: u64,
+}
+
+impl<P> PatchJournal<P> {
+    /// Wraps a patcher which has no outstanding patches.
+    pub fn wrap(patcher: P) -> Self {
+        Self {
+            patcher,
+            entries: Vec::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn patcher(&self) -> &P {
+        &self.patcher
+    }
+
+    pub fn into_inner(self) -> P {
+        self.patcher
+    }
+
+    /// Returns the outstanding patches, oldest first.
+    pub fn journal(&self) -> impl DoubleEndedIterator<Item = &JournalEntry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Returns the outstanding patch with the given ID.
+    pub fn entry(&self, id: RowPatchId) -> Option<&JournalEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Same as [`RowPatcher::create_patch`], tagging the patch in the journal.
+    pub fn create_patch_tagged<'a, N: PrimInt>(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+        tag: Option<&str>,
+    ) -> Option<&JournalEntry>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        let id = self.patcher.create_patch(before, after)?;
+        self.entries.push(JournalEntry {
+            seq: self.next_seq,
+            id,
+            tag: tag.map(str::to_owned),
+        });
+        self.next_seq += 1;
+        self.entries.last()
+    }
+
+    /// Restores the most recent outstanding patch, returning its entry or `None` if there is no
+    /// outstanding patch.
+    ///
+    /// # Errors
+    /// If the patcher fails to restore the patch, returns its error and keeps the entry.
+    pub fn undo_last<'a, N: PrimInt>(
+        &mut self,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<Option<JournalEntry>, RestorePatchError>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        let Some(last) = self.entries.last()
+        else {
+            return Ok(None);
+        };
+        self.patcher.restore_patch(last.id, live_memory)?;
+        Ok(self.entries.pop())
+    }
+
+    /// Restores the patches created after the one with sequence number `seq`, most recent first,
+    /// and returns their entries in the order they were restored.
+    ///
+    /// # Errors
+    /// If the patcher fails to restore a patch, returns its error. The patches restored before
+    /// it are removed from the journal.
+    pub fn undo_until<'a, N: PrimInt>(
+        &mut self,
+        seq: u64,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<Vec<JournalEntry>, RestorePatchError>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        let mut undone = Vec::new();
+        while self.entries.last().is_some_and(|e| e.seq > seq) {
+            undone.extend(self.undo_last(live_memory)?);
+        }
+        Ok(undone)
+    }
+
+    /// Restores the patches tagged with `tag`, most recent first, and returns their entries in
+    /// the order they were restored.
+    ///
+    /// Changes made by patches with other tags are left intact, including the ones interleaved
+    /// with the restored patches.
+    ///
+    /// # Errors
+    /// If the patcher fails to restore a patch, returns its error. The patches restored before
+    /// it are removed from the journal.
+    pub fn revert_tag<'a, N: PrimInt>(
+        &mut self,
+        tag: &str,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<Vec<JournalEntry>, RestorePatchError>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        let mut reverted = Vec::new();
+        while let Some(i) = self.entries.iter().rposition(|e| e.tag.as_deref() == Some(tag)) {
+            self.patcher.restore_patch(self.entries[i].id, live_memory)?;
+            reverted.push(self.entries.remove(i));
+        }
+        Ok(reverted)
+    }
+}
+
+impl<'a, N: PrimInt, P: RowPatcher<'a, N>> RowPatcher<'a, N> for PatchJournal<P> {
+    fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
+        Self::wrap(P::new(field_blocks, row_size))
+    }
+
+    fn create_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        self.create_patch_tagged(before, after, None).map(|e| e.id)
+    }
+
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        self.patcher.restore_patch(id, live_memory)?;
+        self.entries.retain(|e| e.id != id);
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        self.patcher.patched_mask_for_row()
+    }
+}
@@ -1,9 +1,21 @@
+use std::ops::Range;
+
 use num_traits::PrimInt;
 
-use super::base::{FieldBlock, RowPatchId, RowPatcher};
-use crate::util::unaligned::{ToUnalignedSlice, Unaligned};
+use super::base::{
+    changed_field_blocks, field_blocks_hash, next_instance_tag, reserve_instance_tag, FieldBlock,
+    RestorePatchError, RowPatchId, RowPatcher,
+};
+use crate::util::{
+    atomic_write::{xor_blocks, WriteStats, WriteStrategy},
+    unaligned::Unaligned,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct RowDiffId(u16);
 
 impl RowDiffId {
@@ -24,6 +36,10 @@ impl Default for RowDiffId {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 struct PatchedFieldRef {
     /// Row diff the PatchedField belongs to.
     diff: RowDiffId,
@@ -72,11 +88,17 @@ impl PatchedFieldRef {
 }
 
 /// Stores information about the patch to an individual field.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 struct PatchedField {
     /// Start index of the patched field in the field block array.
     field_start: u16,
     /// Start index of the diff for this field in the `block_diffs` vector.
+    ///
+    /// Diffs are stored for every block of the field (changed or not), starting at its first block.
     diff_start: u16,
     /// Patched field that is "on top" of this one in the patch stack (i.e. closer to head of the LL).
     prev: PatchedFieldRef,
@@ -84,7 +106,11 @@ struct PatchedField {
     next: PatchedFieldRef,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 struct RowDiff<N: PrimInt> {
     /// Stores XOR binary diff from the previous field values.
     /// Contiguous blocks that have changes will be contiguous here.
@@ -105,25 +131,38 @@ struct RowDiff<N: PrimInt> {
 /// `56 + n_bytes_patched + 12*n_fields_patched`
 ///
 /// ### Complexity of [`RowPatcher::create_patch`]
-/// `O(n_fields + row_size)`
+/// `O(row_size + n_fields_in_changed_span)`
 ///
 /// ### Complexity of [`RowPatcher::restore_patch`]
 /// `O(n_fields_patched + n_bytes_patched)`
 ///
 pub struct LinkedListPatcher<'a, N: PrimInt + Default = u32> {
     diffs: Vec<RowDiff<N>>,
+    /// Generation of each slot in `diffs`, bumped when the slot is reclaimed.
+    /// Stored in the [`RowPatchId`]s to detect stale IDs.
+    generations: Vec<u16>,
     field_blocks: &'a [FieldBlock<N>],
+    row_blocks: usize,
     patched_field_heads: Vec<PatchedFieldRef>,
     free_list_head: RowDiffId,
+    instance_tag: u32,
+    write_stats: WriteStats,
 }
 
 impl<'a, N: PrimInt + Default> LinkedListPatcher<'a, N> {
+    /// Returns the number of writes made to live memory by [`RowPatcher::restore_patch`], by
+    /// [`WriteStrategy`].
+    pub fn write_stats(&self) -> WriteStats {
+        self.write_stats
+    }
+
     fn allocate_slot(&mut self) -> RowDiffId {
         if let Some(i) = self.free_list_head.as_index() {
             self.free_list_head = self.diffs[i].next_free_slot;
             RowDiffId(i as u16)
         } else if self.diffs.len() < u16::MAX as usize {
             self.diffs.push(Default::default());
+            self.generations.push(0);
             RowDiffId((self.diffs.len() - 1) as u16)
         } else {
             RowDiffId::none()
@@ -132,6 +171,7 @@ impl<'a, N: PrimInt + Default> LinkedListPatcher<'a, N> {
 
     fn reclaim_slot(&mut self, id: RowDiffId) {
         if let Some(diff_index) = id.as_index() {
+            self.generations[diff_index] = self.generations[diff_index].wrapping_add(1);
             if self.free_list_head != RowDiffId::none() {
                 self.diffs[diff_index].next_free_slot = self.free_list_head
             }
@@ -139,6 +179,24 @@ impl<'a, N: PrimInt + Default> LinkedListPatcher<'a, N> {
         }
     }
 
+    fn patch_id(&self, slot: RowDiffId) -> RowPatchId {
+        let generation = self.generations[slot.0 as usize] as u32;
+        RowPatchId::new(self.instance_tag, generation << 16 | slot.0 as u32)
+    }
+
+    /// Returns the slot of an outstanding patch from its ID.
+    fn patch_slot(&self, id: RowPatchId) -> Result<RowDiffId, RestorePatchError> {
+        if id.instance_tag() != self.instance_tag {
+            return Err(RestorePatchError::ForeignId);
+        }
+        let slot = RowDiffId(id.local_id() as u16);
+        let generation = (id.local_id() >> 16) as u16;
+        match slot.as_index() {
+            Some(i) if self.generations.get(i) == Some(&generation) => Ok(slot),
+            _ => Err(RestorePatchError::UnknownId),
+        }
+    }
+
     fn pf_ll_insert(
         &mut self,
         fb: FieldBlock<N>,
@@ -154,22 +212,25 @@ impl<'a, N: PrimInt + Default> LinkedListPatcher<'a, N> {
         }
         *head = field_ref;
     }
-}
 
-impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for LinkedListPatcher<'a, N> {
-    fn new(field_blocks: &'a [FieldBlock<N>], _row_size: usize) -> Self {
-        Self {
-            diffs: Vec::new(),
-            field_blocks,
-            patched_field_heads: vec![Default::default(); field_blocks.len()],
-            free_list_head: Default::default(),
-        }
+    /// Same as [`RowPatcher::create_patch`], but walks the field blocks of the whole row instead
+    /// of only the ones in the span of changed blocks.
+    ///
+    /// Both create the same patch. This is a baseline for tests and benchmarks.
+    pub fn create_patch_full_walk(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        self.create_patch_in(before, after, 0..self.field_blocks.len())
     }
 
-    fn create_patch(
+    /// Creates a patch from the changes to the given range of field blocks.
+    fn create_patch_in(
         &mut self,
         before: &[Unaligned<N>],
         after: &[Unaligned<N>],
+        field_blocks: Range<usize>,
     ) -> Option<RowPatchId> {
         let mut diff = RowDiff::default();
         let slot = self.allocate_slot();
@@ -177,21 +238,24 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for LinkedListPatcher<'a, N> {
             return None;
         }
 
-        let mut i = 0;
+        let mut i = field_blocks.start;
         let mut last_offset = None;
-        while i < self.field_blocks.len() {
+        while i < field_blocks.end {
             let fb = self.field_blocks[i];
             let fb_offset = fb.offset as usize;
-            if ((before[fb_offset].0 ^ after[fb_offset].0) & fb.mask) == N::zero() {
+            if ((before[fb_offset].read() ^ after[fb_offset].read()) & fb.mask) == N::zero() {
                 i += 1;
                 continue;
             }
 
-            let diff_start = if last_offset.map(|x| x < fb_offset).unwrap_or(true) {
-                diff.block_diffs.len()
-            } else {
-                diff.block_diffs.len() - 1
-            } as u16;
+            // The changed block may not be the first block of the field, but diffs are stored
+            // densely starting from the field's first block, which may already have been pushed
+            // if it is shared with the previous field. Fields starting before that block (i.e.
+            // unofficial fields carved out of an earlier field) get their own copy of the diffs.
+            let field_offset = self.field_blocks[fb.field_start as usize].offset as usize;
+            let shares_last = last_offset == Some(field_offset);
+            let diff_start = (diff.block_diffs.len() - shares_last as usize) as u16;
+            let mut next_offset = field_offset + shares_last as usize;
 
             let mut pf = PatchedField {
                 field_start: fb.field_start,
@@ -209,54 +273,360 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for LinkedListPatcher<'a, N> {
             while i < self.field_blocks.len() && self.field_blocks[i].field_start == fb.field_start
             {
                 let offset = self.field_blocks[i].offset as usize;
-                if last_offset.map(|x| x < offset).unwrap_or(true) {
-                    diff.block_diffs.push(before[offset].0 ^ after[offset].0);
-                    last_offset = Some(offset);
+                for o in next_offset..=offset {
+                    diff.block_diffs.push(before[o].read() ^ after[o].read());
+                    last_offset = Some(o);
                 }
+                next_offset = next_offset.max(offset + 1);
                 i += 1;
             }
         }
 
         self.diffs[slot.0 as usize] = diff;
-        Some(slot.0 as usize)
+        Some(self.patch_id(slot))
+    }
+
+    /// Records the change from `before` to `after` as a single patch covering every field of
+    /// the row, whether it changed or not.
+    ///
+    /// Unlike [`RowPatcher::create_patch`], this doesn't scan for changed fields: block diffs
+    /// are stored for the whole row, so each field's diff starts at its own block offset.
+    pub fn create_dense_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        let slot = self.allocate_slot();
+        if slot == RowDiffId::none() {
+            return None;
+        }
+
+        let mut diff = RowDiff {
+            block_diffs: before.iter().zip(after).map(|(b, a)| b.read() ^ a.read()).collect(),
+            ..Default::default()
+        };
+        for (i, fb) in self.field_blocks.iter().enumerate() {
+            if fb.field_start as usize != i {
+                continue;
+            }
+            let mut pf = PatchedField {
+                field_start: fb.field_start,
+                diff_start: fb.offset,
+                ..Default::default()
+            };
+            self.pf_ll_insert(
+                *fb,
+                &mut pf,
+                PatchedFieldRef::new(slot, diff.patched_fields.len() as u16),
+            );
+            diff.patched_fields.push(pf);
+        }
+
+        self.diffs[slot.0 as usize] = diff;
+        Some(self.patch_id(slot))
+    }
+
+    /// Replaces the contents of all fields of the row in `live_memory` with the ones in
+    /// `new_row`, recording the change as a single dense patch (see
+    /// [`LinkedListPatcher::create_dense_patch`]).
+    ///
+    /// Bits not belonging to any field (e.g. alignment padding) are left untouched, so that
+    /// restoring the patch gives back the exact original row.
+    ///
+    /// # Errors
+    /// - If `live_memory` or `new_row` are not the size of a row, returns
+    ///   [`ReplaceRowError::SizeMismatch`].
+    /// - If the maximum number of outstanding patches is reached, returns
+    ///   [`ReplaceRowError::TooManyPatches`]. The row is then left unchanged.
+    pub fn replace_row(
+        &mut self,
+        live_memory: &mut [Unaligned<N>],
+        new_row: &[Unaligned<N>],
+    ) -> Result<RowPatchId, ReplaceRowError> {
+        for len in [live_memory.len(), new_row.len()] {
+            if len != self.row_blocks {
+                return Err(ReplaceRowError::SizeMismatch {
+                    expected: self.row_blocks,
+                    actual: len,
+                });
+            }
+        }
+
+        let mut field_mask = vec![N::zero(); self.row_blocks];
+        for fb in self.field_blocks {
+            let m = &mut field_mask[fb.offset as usize];
+            *m = *m | fb.mask;
+        }
+        let before = live_memory.to_vec();
+        for ((live, new), m) in live_memory.iter_mut().zip(new_row).zip(field_mask) {
+            live.write((live.read() & !m) | (new.read() & m));
+        }
+
+        self.create_dense_patch(&before, live_memory).ok_or_else(|| {
+            live_memory.copy_from_slice(&before);
+            ReplaceRowError::TooManyPatches
+        })
+    }
+}
+
+/// Error returned by [`LinkedListPatcher::replace_row`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceRowError {
+    /// A row was not of the expected size, in blocks.
+    SizeMismatch { expected: usize, actual: usize },
+    /// The patcher can't hold any more outstanding patches.
+    TooManyPatches,
+}
+
+impl std::fmt::Display for ReplaceRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SizeMismatch { expected, actual } => {
+                write!(f, "row is {actual} blocks long, expected {expected}")
+            }
+            Self::TooManyPatches => f.write_str("patcher can't hold any more patches"),
+        }
+    }
+}
+
+impl std::error::Error for ReplaceRowError {}
+
+/// State of a [`LinkedListPatcher`] detached from its field blocks, e.g. to persist it across
+/// process restarts. Serializable with the `serde` feature.
+///
+/// Created by [`LinkedListPatcher::freeze`] and turned back into a patcher by
+/// [`FrozenLinkedListPatcher::thaw`], which keeps the [`RowPatchId`]s issued before freezing valid.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct FrozenLinkedListPatcher<N: PrimInt + Default = u32> {
+    diffs: Vec<RowDiff<N>>,
+    generations: Vec<u16>,
+    row_blocks: usize,
+    patched_field_heads: Vec<PatchedFieldRef>,
+    free_list_head: RowDiffId,
+    instance_tag: u32,
+    field_blocks_hash: u64,
+}
+
+impl<'a, N: PrimInt + Default> LinkedListPatcher<'a, N> {
+    /// Snapshots the state of the patcher, including its outstanding patches.
+    pub fn freeze(&self) -> FrozenLinkedListPatcher<N> {
+        FrozenLinkedListPatcher {
+            diffs: self.diffs.clone(),
+            generations: self.generations.clone(),
+            row_blocks: self.row_blocks,
+            patched_field_heads: self.patched_field_heads.clone(),
+            free_list_head: self.free_list_head,
+            instance_tag: self.instance_tag,
+            field_blocks_hash: field_blocks_hash(self.field_blocks),
+        }
+    }
+}
+
+impl<N: PrimInt + Default> FrozenLinkedListPatcher<N> {
+    /// Hash of the field blocks of the frozen patcher (see [`field_blocks_hash`]).
+    pub fn field_blocks_hash(&self) -> u64 {
+        self.field_blocks_hash
     }
 
-    fn restore_patch(&mut self, diff_id: RowPatchId, live_memory: &mut [Unaligned<N>]) {
-        let slot = RowDiffId(diff_id as u16);
+    /// Recreates the patcher on the same field blocks it was frozen with.
+    ///
+    /// The patcher keeps its instance tag, so that it accepts the [`RowPatchId`]s issued before
+    /// freezing. Tags handed out by [`next_instance_tag`] afterwards won't collide with it, but
+    /// thawing the same state twice creates two patchers accepting the same IDs.
+    ///
+    /// # Errors
+    /// - If `field_blocks` are not the ones the patcher was frozen with, returns
+    ///   [`ThawError::FieldBlocksMismatch`].
+    /// - If the state is not one a patcher could have been frozen in (e.g. it was edited after
+    ///   being serialized), returns [`ThawError::InconsistentState`].
+    pub fn thaw(
+        self,
+        field_blocks: &[FieldBlock<N>],
+    ) -> Result<LinkedListPatcher<'_, N>, ThawError> {
+        let actual = field_blocks_hash(field_blocks);
+        if actual != self.field_blocks_hash {
+            return Err(ThawError::FieldBlocksMismatch {
+                expected: self.field_blocks_hash,
+                actual,
+            });
+        }
+        if !self.is_consistent(field_blocks) {
+            return Err(ThawError::InconsistentState);
+        }
+
+        reserve_instance_tag(self.instance_tag);
+        Ok(LinkedListPatcher {
+            diffs: self.diffs,
+            generations: self.generations,
+            field_blocks,
+            row_blocks: self.row_blocks,
+            patched_field_heads: self.patched_field_heads,
+            free_list_head: self.free_list_head,
+            instance_tag: self.instance_tag,
+            write_stats: WriteStats::default(),
+        })
+    }
+
+    /// Checks that all the indices stored in the state are in bounds, so that the thawed patcher
+    /// can't panic on them.
+    fn is_consistent(&self, field_blocks: &[FieldBlock<N>]) -> bool {
+        let n_diffs = self.diffs.len();
+        let slot_ok = |id: RowDiffId| id.as_index().is_none_or(|i| i < n_diffs);
+        let ref_ok = |r: PatchedFieldRef| match r.row_diff(&self.diffs) {
+            Some(rd) => (r.index as usize) < rd.patched_fields.len(),
+            None => r.is_null(),
+        };
+        let row_fits = field_blocks.iter().all(|fb| (fb.offset as usize) < self.row_blocks);
+
+        row_fits
+            && self.generations.len() == n_diffs
+            && self.patched_field_heads.len() == field_blocks.len()
+            && self.patched_field_heads.iter().all(|&r| ref_ok(r))
+            && slot_ok(self.free_list_head)
+            && self.diffs.iter().all(|rd| {
+                slot_ok(rd.next_free_slot)
+                    && rd.patched_fields.iter().all(|pf| {
+                        let field_start = pf.field_start as usize;
+                        let field_end = field_blocks[field_start.min(field_blocks.len())..]
+                            .iter()
+                            .take_while(|fb| fb.field_start == pf.field_start)
+                            .last();
+                        let span = field_end.map(|fb| {
+                            fb.offset as usize - field_blocks[field_start].offset as usize + 1
+                        });
+                        span.is_some_and(|n| pf.diff_start as usize + n <= rd.block_diffs.len())
+                            && ref_ok(pf.prev)
+                            && ref_ok(pf.next)
+                    })
+            })
+    }
+}
+
+/// Error returned by [`FrozenLinkedListPatcher::thaw`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThawError {
+    /// The field blocks don't have the hash of the ones the patcher was frozen with.
+    FieldBlocksMismatch { expected: u64, actual: u64 },
+    /// The frozen state is corrupted.
+    InconsistentState,
+}
+
+impl std::fmt::Display for ThawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FieldBlocksMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "field blocks hash to {actual:016x}, expected {expected:016x}"
+                )
+            }
+            Self::InconsistentState => f.write_str("frozen patcher state is inconsistent"),
+        }
+    }
+}
+
+impl std::error::Error for ThawError {}
+
+impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for LinkedListPatcher<'a, N> {
+    fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
+        Self {
+            diffs: Vec::new(),
+            generations: Vec::new(),
+            field_blocks,
+            row_blocks: row_size / std::mem::size_of::<N>(),
+            patched_field_heads: vec![Default::default(); field_blocks.len()],
+            free_list_head: Default::default(),
+            instance_tag: next_instance_tag(),
+            write_stats: WriteStats::default(),
+        }
+    }
+
+    fn create_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        let field_blocks = changed_field_blocks(self.field_blocks, before, after);
+        self.create_patch_in(before, after, field_blocks)
+    }
+
+    fn restore_patch(
+        &mut self,
+        diff_id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        let slot = self.patch_slot(diff_id)?;
         let diff_index = slot.as_index().unwrap();
         let diff = std::mem::take(&mut self.diffs[diff_index]);
 
         for pf in &diff.patched_fields {
-            let mut i_fb = pf.field_start as usize;
-            let fb = self.field_blocks[i_fb];
-            let base_offset = fb.offset as usize;
-
-            let blocks =
-                if let Some((prev_pf, blocks)) = pf.prev.field_and_diff_mut(&mut self.diffs) {
-                    prev_pf.next = pf.next;
-                    blocks[prev_pf.diff_start as usize..].to_unaligned_slice_mut()
-                } else {
-                    &mut live_memory[base_offset..]
-                };
+            let field_blocks = &self.field_blocks[pf.field_start as usize..];
+            let base_offset = field_blocks[0].offset as usize;
+            let field_len =
+                field_blocks.iter().take_while(|fb| fb.field_start == pf.field_start).count();
 
+            // Diff of each block of the field, relative to the field's first block
             let orig_blocks = &diff.block_diffs[pf.diff_start as usize..];
-            while i_fb < self.field_blocks.len()
-                && self.field_blocks[i_fb].field_start == fb.field_start
-            {
-                let offset_diff = self.field_blocks[i_fb].offset as usize - base_offset;
-                blocks[offset_diff].0 =
-                    blocks[offset_diff].0 ^ orig_blocks[offset_diff] & self.field_blocks[i_fb].mask;
-                i_fb += 1;
+            let field_diffs = field_blocks[..field_len].iter().map(|fb| {
+                let offset_diff = fb.offset as usize - base_offset;
+                (offset_diff, orig_blocks[offset_diff] & fb.mask)
+            });
+
+            // If the field is obscured by a more recent patch, fold the changes into its diff.
+            // Otherwise, revert them in live memory.
+            if let Some((prev_pf, blocks)) = pf.prev.field_and_diff_mut(&mut self.diffs) {
+                prev_pf.next = pf.next;
+                let blocks = &mut blocks[prev_pf.diff_start as usize..];
+                for (offset_diff, d) in field_diffs {
+                    blocks[offset_diff] = blocks[offset_diff] ^ d;
+                }
+            }
+            else {
+                let field_span = field_blocks[field_len - 1].offset as usize - base_offset + 1;
+                if field_span * std::mem::size_of::<N>() <= 8 {
+                    // Small enough to possibly be reverted by a single atomic write
+                    let mut d_span = [N::zero(); 8];
+                    for (offset_diff, d) in field_diffs {
+                        d_span[offset_diff] = d_span[offset_diff] ^ d;
+                    }
+                    if let Some(s) = xor_blocks(live_memory, base_offset, &d_span[..field_span]) {
+                        self.write_stats.record(s);
+                    }
+                }
+                else {
+                    let blocks = &mut live_memory[base_offset..];
+                    for (offset_diff, d) in field_diffs {
+                        blocks[offset_diff].write(blocks[offset_diff].read() ^ d);
+                    }
+                    self.write_stats.record(WriteStrategy::Plain);
+                }
             }
 
             if let Some((next_pf, _)) = pf.next.field_and_diff_mut(&mut self.diffs) {
                 next_pf.prev = pf.prev;
             }
-            if self.patched_field_heads[i_fb].diff == slot {
-                self.patched_field_heads[i_fb] = pf.next;
+            let head = &mut self.patched_field_heads[pf.field_start as usize];
+            if head.diff == slot {
+                *head = pf.next;
             }
         }
 
         self.reclaim_slot(slot);
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        let mut mask = vec![N::zero(); self.row_blocks];
+        for fb in self.field_blocks {
+            if !self.patched_field_heads[fb.field_start as usize].is_null() {
+                let m = &mut mask[fb.offset as usize];
+                *m = *m | fb.mask;
+            }
+        }
+        mask
     }
 }
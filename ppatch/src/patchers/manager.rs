@@ -0,0 +1,309 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+use field_metadata::{ArchivedFieldBlockRepo, Block};
+
+use super::{
+    base::{FieldBlock, InvalidFieldBlocks, RestorePatchError, RowPatchId, RowPatcher},
+    patch_set::{RegulationFingerprint, SavedField, SavedPatch, SerializablePatchSet},
+};
+use crate::util::unaligned::Unaligned;
+
+/// Handle to a patch created by a [`ParamPatchManager`].
+///
+/// Handles stay valid until the patch is restored, whatever happens to the patches of other
+/// rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatchHandle<'a> {
+    pub param_type: &'a str,
+    pub row_id: u32,
+    /// ID of the patch in the [`RowPatcher`] of the row.
+    pub patch_id: RowPatchId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatePatchError {
+    /// The field block repo has no field blocks for the param type.
+    UnknownParamType,
+    /// The field blocks of the param type are invalid.
+    InvalidFieldBlocks(InvalidFieldBlocks),
+    /// The patcher of the row could not create the patch.
+    PatchRejected,
+    /// `before` or `after` is not the size of the row, in blocks. The size of a row is set by
+    /// its first patch.
+    SizeMismatch { expected: usize, actual: usize },
+    /// The first patch of a row is smaller than the field blocks of its param type, in blocks.
+    FieldBlocksOutOfBounds { row_blocks: usize },
+}
+
+impl std::fmt::Display for CreatePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownParamType => f.write_str("no field blocks for the param type"),
+            Self::InvalidFieldBlocks(e) => e.fmt(f),
+            Self::PatchRejected => f.write_str("patcher rejected the patch"),
+            Self::SizeMismatch { expected, actual } => {
+                write!(f, "row is {actual} blocks long, expected {expected} blocks")
+            }
+            Self::FieldBlocksOutOfBounds { row_blocks } => {
+                write!(f, "field blocks don't fit in a row of {row_blocks} blocks")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CreatePatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidFieldBlocks(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<InvalidFieldBlocks> for CreatePatchError {
+    fn from(value: InvalidFieldBlocks) -> Self {
+        Self::InvalidFieldBlocks(value)
+    }
+}
+
+/// Outstanding patch of a row, with the changes it makes.
+#[derive(Debug)]
+struct ManagedPatch {
+    id: RowPatchId,
+    /// XOR diff of each field the patch changes, in field block order, as the `field_start` of
+    /// the field and the diff of each of its blocks masked by the field block.
+    fields: Vec<(u16, Box<[Block]>)>,
+}
+
+#[derive(Debug)]
+struct ManagedRow<P> {
+    patcher: P,
+    /// Size of the row in blocks, set by its first patch.
+    row_blocks: usize,
+    /// Outstanding patches of the row, in creation order.
+    patches: Vec<ManagedPatch>,
+}
+
+/// Returns the XOR diff of each field changed from `before` to `after`, see
+/// [`ManagedPatch::fields`].
+fn field_diffs(
+    field_blocks: &[FieldBlock<Block>],
+    before: &[Unaligned<Block>],
+    after: &[Unaligned<Block>],
+) -> Vec<(u16, Box<[Block]>)> {
+    field_blocks
+        .chunk_by(|a, b| a.field_start == b.field_start)
+        .filter_map(|field| {
+            let diff: Box<[Block]> = (field.iter())
+                .map(|fb| {
+                    let offset = fb.offset as usize;
+                    (before[offset].read() ^ after[offset].read()) & fb.mask
+                })
+                .collect();
+            diff.iter().any(|&d| d != 0).then(|| (field[0].field_start, diff))
+        })
+        .collect()
+}
+
+/// Patches rows of any param, with a [`RowPatcher`] of type `P` per row.
+///
+/// Patchers are created on the first patch of a row, using the field blocks of its param type
+/// in a field block repo. Rows are identified by their param type and row ID, so the same
+/// manager can't patch two params sharing a param type.
+///
+/// The manager also keeps the changes made by each outstanding patch, so that they can be saved
+/// with [`ParamPatchManager::patch_set`] and replayed after a restart.
+#[derive(Debug)]
+pub struct ParamPatchManager<'a, P: RowPatcher<'a>> {
+    repo: &'a ArchivedFieldBlockRepo,
+    rows: HashMap<(&'a str, u32), ManagedRow<P>>,
+}
+
+impl<'a, P: RowPatcher<'a>> ParamPatchManager<'a, P> {
+    pub fn new(repo: &'a ArchivedFieldBlockRepo) -> Self {
+        Self {
+            repo,
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Creates a patch from the changes between `before` and `after`, the whole row `row_id` of
+    /// a param of type `param_type`.
+    ///
+    /// # Errors
+    /// - If the repo has no field blocks for `param_type`, returns
+    ///   [`CreatePatchError::UnknownParamType`].
+    /// - If this is the first patch of the row and the field blocks are invalid, returns
+    ///   [`CreatePatchError::InvalidFieldBlocks`]. If they don't fit in `before`, returns
+    ///   [`CreatePatchError::FieldBlocksOutOfBounds`].
+    /// - If `before` and `after` are not the same size, or not the size of the previous patches
+    ///   of the row, returns [`CreatePatchError::SizeMismatch`].
+    /// - If the patcher refuses to create the patch, returns [`CreatePatchError::PatchRejected`].
+    ///   The caller is responsible for rolling back the edit.
+    ///
+    /// The row is only managed once its first patch is created, so a failed first patch doesn't
+    /// set the size of the row.
+    pub fn create_patch(
+        &mut self,
+        param_type: &str,
+        row_id: u32,
+        before: &[Unaligned<Block>],
+        after: &[Unaligned<Block>],
+    ) -> Result<PatchHandle<'a>, CreatePatchError> {
+        let (param_type, blocks) = (self.repo)
+            .get_key_value(param_type)
+            .ok_or(CreatePatchError::UnknownParamType)?;
+        let param_type = param_type.as_str();
+        if after.len() != before.len() {
+            return Err(CreatePatchError::SizeMismatch {
+                expected: before.len(),
+                actual: after.len(),
+            });
+        }
+
+        let (row, patch_id) = match self.rows.entry((param_type, row_id)) {
+            Entry::Occupied(e) => {
+                let row = e.into_mut();
+                if before.len() != row.row_blocks {
+                    return Err(CreatePatchError::SizeMismatch {
+                        expected: row.row_blocks,
+                        actual: before.len(),
+                    });
+                }
+                let patch_id = (row.patcher)
+                    .create_patch(before, after)
+                    .ok_or(CreatePatchError::PatchRejected)?;
+                (row, patch_id)
+            }
+            Entry::Vacant(e) => {
+                // Field blocks are not sorted by offset, as unofficial fields follow their host
+                let max_offset = blocks.iter().map(|fb| fb.offset as usize).max();
+                if max_offset.is_some_and(|o| o >= before.len()) {
+                    return Err(CreatePatchError::FieldBlocksOutOfBounds {
+                        row_blocks: before.len(),
+                    });
+                }
+                let mut patcher = P::try_new(blocks.as_slice(), std::mem::size_of_val(before))?;
+                let patch_id =
+                    patcher.create_patch(before, after).ok_or(CreatePatchError::PatchRejected)?;
+                let row = e.insert(ManagedRow {
+                    patcher,
+                    row_blocks: before.len(),
+                    patches: Vec::new(),
+                });
+                (row, patch_id)
+            }
+        };
+        row.patches.push(ManagedPatch {
+            id: patch_id,
+            fields: field_diffs(blocks.as_slice(), before, after),
+        });
+        Ok(PatchHandle {
+            param_type,
+            row_id,
+            patch_id,
+        })
+    }
+
+    /// Restores the patch of `handle`, given the live memory of its row.
+    ///
+    /// # Errors
+    /// - If the patch was created by another manager, returns [`RestorePatchError::ForeignId`]
+    ///   or [`RestorePatchError::UnknownId`].
+    /// - If the patch was already restored, returns [`RestorePatchError::UnknownId`].
+    /// - If `live_memory` is not the size of the row, returns [`RestorePatchError::SizeMismatch`].
+    ///   The patch stays outstanding.
+    pub fn restore(
+        &mut self,
+        handle: PatchHandle<'a>,
+        live_memory: &mut [Unaligned<Block>],
+    ) -> Result<(), RestorePatchError> {
+        let row = (self.rows.get_mut(&(handle.param_type, handle.row_id)))
+            .ok_or(RestorePatchError::UnknownId)?;
+        if live_memory.len() != row.row_blocks {
+            return Err(RestorePatchError::SizeMismatch {
+                expected: row.row_blocks,
+                actual: live_memory.len(),
+            });
+        }
+        row.patcher.restore_patch(handle.patch_id, live_memory)?;
+
+        // Like the patchers, fold the changes hidden by a more recent patch of the same field
+        // into its diff, so that it restores the field to its value before both patches
+        let i = row.patches.iter().position(|p| p.id == handle.patch_id).unwrap();
+        let restored = row.patches.remove(i);
+        for (field_start, diff) in restored.fields {
+            let more_recent = (row.patches[i..].iter_mut())
+                .flat_map(|p| p.fields.iter_mut())
+                .find(|(fs, _)| *fs == field_start);
+            if let Some((_, next)) = more_recent {
+                next.iter_mut().zip(diff.iter()).for_each(|(n, d)| *n ^= d);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the outstanding patches of all rows of `param_type`, sorted by row ID and in
+    /// creation order for each row, e.g. to restore all of them when unloading a mod.
+    pub fn outstanding(&self, param_type: &str) -> Vec<PatchHandle<'a>> {
+        let mut handles: Vec<_> = (self.rows.iter())
+            .filter(|((pt, _), _)| *pt == param_type)
+            .flat_map(|(&(param_type, row_id), row)| {
+                row.patches.iter().map(move |p| PatchHandle {
+                    param_type,
+                    row_id,
+                    patch_id: p.id,
+                })
+            })
+            .collect();
+        handles.sort_by_key(|h| h.row_id);
+        handles
+    }
+
+    /// Returns the outstanding patches of all rows, e.g. to save them and replay them with
+    /// [`SerializablePatchSet::apply_all`] after a restart.
+    ///
+    /// `fingerprint` is the one of the regulation holding the patched params.
+    pub fn patch_set(&self, fingerprint: RegulationFingerprint) -> SerializablePatchSet {
+        let mut rows: Vec<_> = self.rows.iter().collect();
+        rows.sort_unstable_by_key(|(&key, _)| key);
+        let patches = rows
+            .into_iter()
+            .flat_map(|(&(param_type, row_id), row)| {
+                row.patches.iter().map(move |p| SavedPatch {
+                    param_type: param_type.to_owned(),
+                    row_id,
+                    fields: (p.fields.iter())
+                        .map(|(field_start, diff)| SavedField {
+                            field_start: *field_start,
+                            diff: diff.to_vec(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        SerializablePatchSet {
+            fingerprint,
+            patches,
+        }
+    }
+
+    /// Returns the field block repo the manager was created with.
+    pub fn repo(&self) -> &'a ArchivedFieldBlockRepo {
+        self.repo
+    }
+
+    /// Returns the patcher of a row, if it was ever patched.
+    pub fn row_patcher(&self, param_type: &str, row_id: u32) -> Option<&P> {
+        let (param_type, _) = self.repo.get_key_value(param_type)?;
+        Some(&self.rows.get(&(param_type.as_str(), row_id))?.patcher)
+    }
+}
+
+impl<P: RowPatcher<'static>> ParamPatchManager<'static, P> {
+    /// Same as [`ParamPatchManager::new`], with the embedded field blocks of the [active
+    /// game](crate::active_game).
+    pub fn for_embedded_repo() -> Self {
+        Self::new(crate::game::active_field_blocks().0)
+    }
+}
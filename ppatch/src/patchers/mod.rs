@@ -1,3 +1,12 @@
 pub mod base;
+pub mod full_copy;
+pub mod journal;
 pub mod linked_list;
+pub mod manager;
+pub mod patch_set;
+pub mod single_patch;
 pub mod sparse_array;
+pub mod sync;
+pub mod toggle_map;
+
+pub use sparse_array::SparseArrayPatcher;
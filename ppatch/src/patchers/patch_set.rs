@@ -0,0 +1,487 @@
+//! Outstanding patches of a [`ParamPatchManager`], saved to be replayed after a restart.
+//!
+//! Patches are XOR diffs of the fields they change, so they can be replayed on the same params
+//! in a later session, as long as the regulation did not change. A [`SerializablePatchSet`]
+//! records the [`RegulationFingerprint`] of the params it was taken from, and is only replayed on
+//! params with the same fingerprint.
+
+use std::{fmt, hash::Hasher, path::Path};
+
+use field_metadata::{ArchivedFieldBlockRepo, Block};
+
+use super::{
+    base::RowPatcher,
+    manager::{CreatePatchError, ParamPatchManager, PatchHandle},
+};
+use crate::param_file::{ParamFile, UnalignedRowSize};
+
+/// Version of the format written by [`SerializablePatchSet::to_bytes`].
+pub const PATCH_SET_FORMAT_VERSION: u32 = 1;
+
+const PATCH_SET_MAGIC: [u8; 4] = *b"PPST";
+
+/// Hash identifying the params of a regulation, from their headers and row counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct RegulationFingerprint(pub u64);
+
+impl RegulationFingerprint {
+    /// Computes the fingerprint of all the params of a regulation, in any order.
+    pub fn compute(params: &[ParamFile<'_>]) -> Self {
+        let mut param_hashes: Vec<u64> = params
+            .iter()
+            .map(|param| {
+                let mut hasher = fnv::FnvHasher::default();
+                hasher.write(param.raw_header_bytes());
+                hasher.write(param.param_type_bytes());
+                hasher.write(&param.header().row_count().to_le_bytes());
+                hasher.finish()
+            })
+            .collect();
+        param_hashes.sort_unstable();
+
+        let mut hasher = fnv::FnvHasher::default();
+        for hash in param_hashes {
+            hasher.write(&hash.to_le_bytes());
+        }
+        Self(hasher.finish())
+    }
+}
+
+impl fmt::Display for RegulationFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Change a patch makes to a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct SavedField {
+    /// Index of the first field block of the field.
+    pub field_start: u16,
+    /// XOR diff of each block of the field, masked by the field block.
+    pub diff: Vec<Block>,
+}
+
+/// Outstanding patch of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct SavedPatch {
+    /// Param type of the patched param, as in the field block repo.
+    pub param_type: String,
+    pub row_id: u32,
+    /// Changed fields, in field block order.
+    pub fields: Vec<SavedField>,
+}
+
+/// Outstanding patches of a [`ParamPatchManager`], created by [`ParamPatchManager::patch_set`].
+///
+/// Serializable with the `serde` feature, or to a compact binary format with
+/// [`SerializablePatchSet::to_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct SerializablePatchSet {
+    /// Fingerprint of the regulation the patches were made on.
+    pub fingerprint: RegulationFingerprint,
+    /// Patches sorted by param type and row ID, and in creation order for each row.
+    pub patches: Vec<SavedPatch>,
+}
+
+/// Error returned when saving, loading or replaying a [`SerializablePatchSet`].
+#[derive(Debug)]
+pub enum PatchSetError {
+    Io(std::io::Error),
+    /// The bytes do not start with the header written by [`SerializablePatchSet::to_bytes`].
+    NotAPatchSet,
+    /// The patch set was written with another [`PATCH_SET_FORMAT_VERSION`].
+    UnsupportedVersion {
+        version: u32,
+    },
+    /// The bytes end in the middle of the patch set.
+    Truncated,
+    /// The patch set was made on another regulation.
+    RegulationMismatch {
+        expected: RegulationFingerprint,
+        actual: RegulationFingerprint,
+    },
+    /// The field block repo has no field blocks for the param type.
+    UnknownParamType {
+        param_type: String,
+    },
+    /// A patch changes a field the field blocks of its param type don't have, or with another
+    /// number of blocks.
+    InvalidField {
+        param_type: String,
+        field_start: u16,
+    },
+    /// None of the params has the param type of a patch.
+    MissingParam {
+        param_type: String,
+    },
+    /// The param has no row with the ID of a patch.
+    MissingRow {
+        param_type: String,
+        row_id: u32,
+    },
+    /// The field blocks extend past the end of the patched row, which is `row_size` bytes long.
+    FieldBlocksOutOfBounds {
+        param_type: String,
+        row_id: u32,
+        row_size: usize,
+    },
+    UnalignedRowSize(UnalignedRowSize),
+    /// The manager could not create a replayed patch.
+    CreatePatch(CreatePatchError),
+}
+
+impl fmt::Display for PatchSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::NotAPatchSet => f.write_str("not a patch set"),
+            Self::UnsupportedVersion { version } => write!(
+                f,
+                "patch set format version {version} is not supported, expected {PATCH_SET_FORMAT_VERSION}"
+            ),
+            Self::Truncated => f.write_str("patch set is truncated"),
+            Self::RegulationMismatch { expected, actual } => write!(
+                f,
+                "patch set was made on regulation {expected}, but the params are of regulation {actual}"
+            ),
+            Self::UnknownParamType { param_type } => {
+                write!(f, "no field blocks for the param type {param_type}")
+            }
+            Self::InvalidField {
+                param_type,
+                field_start,
+            } => write!(f, "{param_type} has no field starting at field block {field_start}"),
+            Self::MissingParam { param_type } => write!(f, "no param of type {param_type}"),
+            Self::MissingRow { param_type, row_id } => {
+                write!(f, "param {param_type} has no row {row_id}")
+            }
+            Self::FieldBlocksOutOfBounds {
+                param_type,
+                row_id,
+                row_size,
+            } => write!(
+                f,
+                "field blocks of {param_type} extend past the end of row {row_id}, which is {row_size} bytes long"
+            ),
+            Self::UnalignedRowSize(e) => e.fmt(f),
+            Self::CreatePatch(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PatchSetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::UnalignedRowSize(e) => Some(e),
+            Self::CreatePatch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PatchSetError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<UnalignedRowSize> for PatchSetError {
+    fn from(value: UnalignedRowSize) -> Self {
+        Self::UnalignedRowSize(value)
+    }
+}
+
+impl From<CreatePatchError> for PatchSetError {
+    fn from(value: CreatePatchError) -> Self {
+        Self::CreatePatch(value)
+    }
+}
+
+/// Reads the little endian integers of a serialized patch set.
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], PatchSetError> {
+        let (head, tail) = self.bytes.split_first_chunk().ok_or(PatchSetError::Truncated)?;
+        self.bytes = tail;
+        Ok(*head)
+    }
+
+    fn u16(&mut self) -> Result<u16, PatchSetError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, PatchSetError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, PatchSetError> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'b [u8], PatchSetError> {
+        if len > self.bytes.len() {
+            return Err(PatchSetError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+}
+
+/// Returns true if `param` has the param type `param_type`, ignoring the padding and case of the
+/// one stored in the param file (see [`field_metadata::FbRepoExt`]).
+pub(crate) fn has_param_type(param: &ParamFile<'_>, param_type: &str) -> bool {
+    let raw = param.param_type_bytes();
+    let len = raw.iter().position(|&c| c == 0).unwrap_or(raw.len());
+    raw[..len].trim_ascii().eq_ignore_ascii_case(param_type.as_bytes())
+}
+
+impl SerializablePatchSet {
+    /// Serializes the patch set to a binary format, versioned by [`PATCH_SET_FORMAT_VERSION`].
+    ///
+    /// All integers are little endian. The header holds a magic, the format version (`u32`), the
+    /// fingerprint (`u64`) and the number of patches (`u32`). Each patch then holds its param
+    /// type (`u16` length and UTF-8 bytes), row ID (`u32`) and number of fields (`u16`), each
+    /// field holding its `field_start` (`u16`), number of blocks (`u16`) and diff blocks.
+    ///
+    /// # Panics
+    /// If a param type is longer than 65535 bytes, or a patch has more than 65535 fields or
+    /// field blocks per field, which can't be the case of patches made by a manager.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len = |n: usize| u16::try_from(n).expect("patch set is too large").to_le_bytes();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&PATCH_SET_MAGIC);
+        bytes.extend_from_slice(&PATCH_SET_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.0.to_le_bytes());
+        bytes.extend_from_slice(&(self.patches.len() as u32).to_le_bytes());
+        for patch in &self.patches {
+            bytes.extend_from_slice(&len(patch.param_type.len()));
+            bytes.extend_from_slice(patch.param_type.as_bytes());
+            bytes.extend_from_slice(&patch.row_id.to_le_bytes());
+            bytes.extend_from_slice(&len(patch.fields.len()));
+            for field in &patch.fields {
+                bytes.extend_from_slice(&field.field_start.to_le_bytes());
+                bytes.extend_from_slice(&len(field.diff.len()));
+                for block in &field.diff {
+                    bytes.extend_from_slice(&block.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes a patch set written by [`SerializablePatchSet::to_bytes`].
+    ///
+    /// The patches are not checked against field blocks, see [`SerializablePatchSet::validate`].
+    ///
+    /// # Errors
+    /// - If the bytes don't start with a patch set header, returns
+    ///   [`PatchSetError::NotAPatchSet`].
+    /// - If the patch set was written with another format version, returns
+    ///   [`PatchSetError::UnsupportedVersion`].
+    /// - If the bytes end before the last patch, or a param type is not UTF-8, returns
+    ///   [`PatchSetError::Truncated`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PatchSetError> {
+        let mut reader = Reader { bytes };
+        if reader.take::<4>().ok() != Some(PATCH_SET_MAGIC) {
+            return Err(PatchSetError::NotAPatchSet);
+        }
+        let version = reader.u32()?;
+        if version != PATCH_SET_FORMAT_VERSION {
+            return Err(PatchSetError::UnsupportedVersion { version });
+        }
+        let fingerprint = RegulationFingerprint(reader.u64()?);
+
+        let n_patches = reader.u32()?;
+        // Don't trust the count for the allocation, each patch takes at least 8 bytes
+        let mut patches = Vec::with_capacity((n_patches as usize).min(bytes.len() / 8));
+        for _ in 0..n_patches {
+            let len = reader.u16()? as usize;
+            let param_type = std::str::from_utf8(reader.bytes(len)?)
+                .map_err(|_| PatchSetError::Truncated)?
+                .to_owned();
+            let row_id = reader.u32()?;
+            let fields = (0..reader.u16()?)
+                .map(|_| {
+                    let field_start = reader.u16()?;
+                    let diff =
+                        (0..reader.u16()?).map(|_| reader.u32()).collect::<Result<_, _>>()?;
+                    Ok(SavedField { field_start, diff })
+                })
+                .collect::<Result<_, PatchSetError>>()?;
+            patches.push(SavedPatch {
+                param_type,
+                row_id,
+                fields,
+            });
+        }
+        Ok(Self {
+            fingerprint,
+            patches,
+        })
+    }
+
+    /// Writes the patch set to a file, in the format of [`SerializablePatchSet::to_bytes`].
+    ///
+    /// # Errors
+    /// If writing the file fails, returns [`PatchSetError::Io`].
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), PatchSetError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    /// Reads a patch set saved by [`SerializablePatchSet::save_to`], and checks that its patches
+    /// fit the field blocks of `repo`.
+    ///
+    /// # Errors
+    /// - If reading the file fails, returns [`PatchSetError::Io`].
+    /// - If the file is not a valid patch set, returns the errors of
+    ///   [`SerializablePatchSet::from_bytes`].
+    /// - If the patches don't fit `repo`, returns the errors of
+    ///   [`SerializablePatchSet::validate`].
+    pub fn load_from(
+        path: impl AsRef<Path>,
+        repo: &ArchivedFieldBlockRepo,
+    ) -> Result<Self, PatchSetError> {
+        let patch_set = Self::from_bytes(&std::fs::read(path)?)?;
+        patch_set.validate(repo)?;
+        Ok(patch_set)
+    }
+
+    /// Checks that each patch changes fields of the field blocks of its param type in `repo`.
+    ///
+    /// # Errors
+    /// - If `repo` has no field blocks for the param type of a patch, returns
+    ///   [`PatchSetError::UnknownParamType`].
+    /// - If a patch changes a field which does not start at its `field_start`, or does not have
+    ///   as many blocks as its diff, returns [`PatchSetError::InvalidField`].
+    pub fn validate(&self, repo: &ArchivedFieldBlockRepo) -> Result<(), PatchSetError> {
+        for patch in &self.patches {
+            let blocks = repo.get(patch.param_type.as_str()).ok_or_else(|| {
+                PatchSetError::UnknownParamType {
+                    param_type: patch.param_type.clone(),
+                }
+            })?;
+            for field in &patch.fields {
+                let field_len = (blocks.get(field.field_start as usize..).unwrap_or_default())
+                    .iter()
+                    .take_while(|fb| fb.field_start == field.field_start)
+                    .count();
+                if field_len == 0 || field_len != field.diff.len() {
+                    return Err(PatchSetError::InvalidField {
+                        param_type: patch.param_type.clone(),
+                        field_start: field.field_start,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays the patches on the rows of `params`, all the params of a regulation, creating a
+    /// patch with `manager` for each one. Returns the handles of the created patches, in the
+    /// order of [`SerializablePatchSet::patches`].
+    ///
+    /// # Errors
+    /// - If the params don't have the fingerprint of the patch set, returns
+    ///   [`PatchSetError::RegulationMismatch`].
+    /// - If the patches don't fit the field blocks of the manager, returns the errors of
+    ///   [`SerializablePatchSet::validate`].
+    /// - If a patched param or row is missing, returns [`PatchSetError::MissingParam`] or
+    ///   [`PatchSetError::MissingRow`].
+    /// - If a row does not fit the field blocks of its param type, returns
+    ///   [`PatchSetError::FieldBlocksOutOfBounds`] or [`PatchSetError::UnalignedRowSize`].
+    /// - If the manager can't create a patch, returns [`PatchSetError::CreatePatch`].
+    ///
+    /// On error, the patches replayed so far are restored.
+    pub fn apply_all<'a, P: RowPatcher<'a>>(
+        &self,
+        manager: &mut ParamPatchManager<'a, P>,
+        params: &mut [ParamFile<'_>],
+    ) -> Result<Vec<PatchHandle<'a>>, PatchSetError> {
+        let actual = RegulationFingerprint::compute(params);
+        if actual != self.fingerprint {
+            return Err(PatchSetError::RegulationMismatch {
+                expected: self.fingerprint,
+                actual,
+            });
+        }
+        self.validate(manager.repo())?;
+
+        let mut handles = Vec::with_capacity(self.patches.len());
+        for patch in &self.patches {
+            if let Err(e) = Self::apply_patch(patch, manager, params).map(|h| handles.push(h)) {
+                for &handle in handles.iter().rev() {
+                    let param = params.iter_mut().find(|p| has_param_type(p, handle.param_type));
+                    let mut row = param.and_then(|p| p.by_id_mut(handle.row_id)).unwrap();
+                    let live = row.as_blocks_mut().unwrap();
+                    manager.restore(handle, live).unwrap();
+                }
+                return Err(e);
+            }
+        }
+        Ok(handles)
+    }
+
+    fn apply_patch<'a, P: RowPatcher<'a>>(
+        patch: &SavedPatch,
+        manager: &mut ParamPatchManager<'a, P>,
+        params: &mut [ParamFile<'_>],
+    ) -> Result<PatchHandle<'a>, PatchSetError> {
+        let param_type = &patch.param_type;
+        let param =
+            (params.iter_mut().find(|p| has_param_type(p, param_type))).ok_or_else(|| {
+                PatchSetError::MissingParam {
+                    param_type: param_type.clone(),
+                }
+            })?;
+        let mut row = param.by_id_mut(patch.row_id).ok_or_else(|| PatchSetError::MissingRow {
+            param_type: param_type.clone(),
+            row_id: patch.row_id,
+        })?;
+        let row_size = row.len();
+        let live = row.as_blocks_mut::<Block>()?;
+
+        // Validated against the repo of the manager by `apply_all`
+        let blocks = manager.repo().get(param_type.as_str()).unwrap();
+        if blocks.last().is_some_and(|fb| fb.offset as usize >= live.len()) {
+            return Err(PatchSetError::FieldBlocksOutOfBounds {
+                param_type: param_type.clone(),
+                row_id: patch.row_id,
+                row_size,
+            });
+        }
+
+        let before = live.to_vec();
+        for field in &patch.fields {
+            for (fb, &d) in blocks[field.field_start as usize..].iter().zip(&field.diff) {
+                let block = &mut live[fb.offset as usize];
+                block.write(block.read() ^ (d & fb.mask));
+            }
+        }
+        manager.create_patch(param_type, patch.row_id, &before, live).map_err(|e| {
+            live.copy_from_slice(&before);
+            e.into()
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use num_traits::PrimInt;
+
+use super::base::{
+    changed_field_blocks, next_instance_tag, FieldBlock, RestorePatchError, RowPatchId, RowPatcher,
+};
+use crate::util::{
+    atomic_write::{xor_blocks, WriteStats},
+    unaligned::Unaligned,
+};
+
+#[derive(Debug, Clone)]
+struct PatchedBlock<N: PrimInt> {
+    /// XOR bitwise diff of the changes made to the block, restricted to field bits.
+    diff: N,
+    /// Offset of block from the start of the param row.
+    offset: u16,
+}
+
+/// Row patcher which supports a single outstanding patch at a time.
+///
+/// Meant for the common case of tools which apply at most one patch per row,
+/// for which conflict resolution between stacked patches is unnecessary.
+/// [`RowPatcher::create_patch`] returns `None` while a patch is already applied.
+///
+/// ### Memory consumed per patch
+/// `16 + 2*n_bytes_patched`
+///
+/// ### Complexity of [`RowPatcher::create_patch`]
+/// `O(row_size + n_fields_in_changed_span)`
+///
+/// ### Complexity of [`RowPatcher::restore_patch`]
+/// `O(n_bytes_patched)`
+///
+#[derive(Debug, Clone)]
+pub struct SinglePatchPatcher<'a, N: PrimInt = u32> {
+    field_blocks: &'a [FieldBlock<N>],
+    row_blocks: usize,
+    patch: Option<(RowPatchId, Box<[PatchedBlock<N>]>)>,
+    id_counter: u32,
+    instance_tag: u32,
+    write_stats: WriteStats,
+}
+
+impl<'a, N: PrimInt> SinglePatchPatcher<'a, N> {
+    /// Returns `true` if a patch is currently outstanding.
+    pub fn is_patched(&self) -> bool {
+        self.patch.is_some()
+    }
+
+    /// Returns the number of writes made to live memory by [`RowPatcher::restore_patch`], by
+    /// [`WriteStrategy`](crate::util::atomic_write::WriteStrategy).
+    pub fn write_stats(&self) -> WriteStats {
+        self.write_stats
+    }
+
+    /// Same as [`RowPatcher::create_patch`], but walks the field blocks of the whole row instead
+    /// of only the ones in the span of changed blocks.
+    ///
+    /// Both create the same patch. This is a baseline for tests and benchmarks.
+    pub fn create_patch_full_walk(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        self.create_patch_in(before, after, 0..self.field_blocks.len())
+    }
+
+    /// Creates a patch from the changes to the given range of field blocks.
+    fn create_patch_in(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+        field_blocks: Range<usize>,
+    ) -> Option<RowPatchId> {
+        if self.patch.is_some() {
+            return None;
+        }
+
+        let mut blocks: Vec<PatchedBlock<N>> = Vec::new();
+        for fb in &self.field_blocks[field_blocks] {
+            let offset = fb.offset as usize;
+            let diff = (before[offset].read() ^ after[offset].read()) & fb.mask;
+            if diff.is_zero() {
+                continue;
+            }
+            // Field blocks are mostly sorted by offset, except for the blocks of unofficial fields
+            match blocks.binary_search_by_key(&fb.offset, |b| b.offset) {
+                Ok(i) => blocks[i].diff = blocks[i].diff | diff,
+                Err(i) => blocks.insert(
+                    i,
+                    PatchedBlock {
+                        diff,
+                        offset: fb.offset,
+                    },
+                ),
+            }
+        }
+
+        self.id_counter = self.id_counter.checked_add(1)?;
+        let id = RowPatchId::new(self.instance_tag, self.id_counter);
+        self.patch = Some((id, blocks.into_boxed_slice()));
+        Some(id)
+    }
+}
+
+impl<'a, N: PrimInt> RowPatcher<'a, N> for SinglePatchPatcher<'a, N> {
+    fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
+        Self {
+            field_blocks,
+            row_blocks: row_size / std::mem::size_of::<N>(),
+            patch: None,
+            id_counter: 0,
+            instance_tag: next_instance_tag(),
+            write_stats: WriteStats::default(),
+        }
+    }
+
+    fn create_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        let field_blocks = changed_field_blocks(self.field_blocks, before, after);
+        self.create_patch_in(before, after, field_blocks)
+    }
+
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        if id.instance_tag() != self.instance_tag {
+            return Err(RestorePatchError::ForeignId);
+        }
+        let (_, blocks) = self
+            .patch
+            .take_if(|(patch_id, _)| *patch_id == id)
+            .ok_or(RestorePatchError::UnknownId)?;
+
+        for b in blocks.iter() {
+            let ofs = b.offset as usize;
+            if let Some(s) = xor_blocks(live_memory, ofs, &[b.diff]) {
+                self.write_stats.record(s);
+            }
+        }
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        let mut mask = vec![N::zero(); self.row_blocks];
+        let Some((_, blocks)) = &self.patch
+        else {
+            return mask;
+        };
+
+        // Blocks only store the changed bits, so find which fields they belong to first
+        let mut field_patched = vec![false; self.field_blocks.len()];
+        for fb in self.field_blocks {
+            if let Ok(i) = blocks.binary_search_by_key(&fb.offset, |b| b.offset) {
+                if !(blocks[i].diff & fb.mask).is_zero() {
+                    field_patched[fb.field_start as usize] = true;
+                }
+            }
+        }
+        for fb in self.field_blocks {
+            if field_patched[fb.field_start as usize] {
+                let m = &mut mask[fb.offset as usize];
+                *m = *m | fb.mask;
+            }
+        }
+        mask
+    }
+}
@@ -1,21 +1,22 @@
-use std::u32;
-
 use num_traits::PrimInt;
 
-use super::base::{FieldBlock, RowPatchId, RowPatcher};
-use crate::util::unaligned::Unaligned;
+use super::base::{next_instance_tag, FieldBlock, RestorePatchError, RowPatchId, RowPatcher};
+use crate::util::{
+    atomic_write::{xor_blocks, WriteStats},
+    unaligned::Unaligned,
+};
 
 #[derive(Debug, Clone, Default)]
 struct PatchedBlock<N: PrimInt> {
     /// XOR bitwise diff of the changes made to the block.
     diff: N,
-    /// Bitmask where all bits of affected fields are set to 1.
+    /// Bitmask where all bits of affected fields are set to 1. Padding bits are never set.
     mask: N,
     /// Offset of block from the start of the param row.
     offset: u32,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 struct RowDiff<N: PrimInt> {
     /// Array of 4-byte blocks that were patched, in ascending order.
     blocks: Box<[PatchedBlock<N>]>,
@@ -25,7 +26,10 @@ struct RowDiff<N: PrimInt> {
 
 impl<N: PrimInt> RowDiff<N> {
     /// Merge this row diff with another in linear time using a mergesort like strategy.
-    pub fn merge(&mut self, rd: &RowDiff<N>) {
+    ///
+    /// Blocks left without patched bits are dropped. Returns true if the merged diff has no
+    /// blocks left.
+    pub fn merge(&mut self, rd: &RowDiff<N>) -> bool {
         let mut new_blocks = Vec::with_capacity(self.blocks.len() + rd.blocks.len());
 
         let (mut i, mut j) = (0, 0);
@@ -35,10 +39,12 @@ impl<N: PrimInt> RowDiff<N> {
             let merged = if a.offset < b.offset {
                 i += 1;
                 a.clone()
-            } else if a.offset > b.offset {
+            }
+            else if a.offset > b.offset {
                 j += 1;
                 b.clone()
-            } else {
+            }
+            else {
                 i += 1;
                 j += 1;
                 PatchedBlock {
@@ -47,14 +53,51 @@ impl<N: PrimInt> RowDiff<N> {
                     offset: a.offset,
                 }
             };
-            if merged.mask.is_zero() {
+            if !merged.mask.is_zero() {
                 new_blocks.push(merged);
             }
         }
-        new_blocks.extend_from_slice(&self.blocks[i..]);
-        new_blocks.extend_from_slice(&rd.blocks[j..]);
+        let rest = self.blocks[i..].iter().chain(&rd.blocks[j..]);
+        new_blocks.extend(rest.filter(|b| !b.mask.is_zero()).cloned());
 
         self.blocks = new_blocks.into_boxed_slice();
+        self.blocks.is_empty()
+    }
+
+    /// Moves the patched bits of this row diff which are also patched by `by` into a new row
+    /// diff, in linear time.
+    fn take_covered(&mut self, by: &RowDiff<N>) -> RowDiff<N> {
+        let mut covered = Vec::new();
+
+        let mut j = 0;
+        for a in self.blocks.iter_mut() {
+            while j < by.blocks.len() && by.blocks[j].offset < a.offset {
+                j += 1;
+            }
+            let Some(b) = by.blocks.get(j).filter(|b| b.offset == a.offset)
+            else {
+                continue;
+            };
+            let bits = a.mask & b.mask;
+            if bits.is_zero() {
+                continue;
+            }
+            covered.push(PatchedBlock {
+                diff: a.diff & bits,
+                mask: bits,
+                offset: a.offset,
+            });
+            a.diff = a.diff & !bits;
+            a.mask = a.mask & !bits;
+        }
+        if !covered.is_empty() {
+            self.blocks = self.blocks.iter().filter(|b| !b.mask.is_zero()).cloned().collect();
+        }
+
+        RowDiff {
+            blocks: covered.into_boxed_slice(),
+            id: self.id,
+        }
     }
 }
 
@@ -86,41 +129,74 @@ struct MaskBlock<N: PrimInt + Default> {
 /// O(sum of number of bytes patched for all patches above and including the restored patch)
 ///
 #[derive(Debug, Clone)]
-struct SparseArrayPatcher<N: PrimInt + Default = u32> {
+pub struct SparseArrayPatcher<N: PrimInt + Default = u32> {
     diff_stack: Vec<RowDiff<N>>,
     combined_mask: Box<[MaskBlock<N>]>,
     field_blocks: Box<[N]>,
-    id_counter: usize,
+    /// Union of the field block masks at each offset, excluding padding bits.
+    field_masks: Box<[N]>,
+    id_counter: u32,
     step_counter: u32,
+    instance_tag: u32,
+    write_stats: WriteStats,
+}
+
+impl<N: PrimInt + Default> SparseArrayPatcher<N> {
+    /// Returns the number of writes made to live memory by [`RowPatcher::restore_patch`], by
+    /// [`WriteStrategy`](crate::util::atomic_write::WriteStrategy).
+    pub fn write_stats(&self) -> WriteStats {
+        self.write_stats
+    }
+
+    /// Appends the `bits` of the block at `offset` which belong to fields to a patch, merging them
+    /// with the last block of the patch if it is at the same offset.
+    fn push_block(
+        &self,
+        rd_blocks: &mut Vec<PatchedBlock<N>>,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+        offset: usize,
+        bits: N,
+    ) {
+        let mask = bits & self.field_masks[offset];
+        if mask.is_zero() {
+            return;
+        }
+        let diff = (before[offset].read() ^ after[offset].read()) & mask;
+        match rd_blocks.last_mut() {
+            Some(last) if last.offset as usize == offset => {
+                last.diff = last.diff | diff;
+                last.mask = last.mask | mask;
+            }
+            _ => rd_blocks.push(PatchedBlock {
+                diff,
+                mask,
+                offset: offset as u32,
+            }),
+        }
+    }
 }
 
 impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for SparseArrayPatcher<N> {
     fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
-        // Convert "standard" field block format into optimized bit format
-        let mut bin_fb: Vec<N> = Vec::new();
+        // Convert "standard" field block format into optimized bit format, where the block at
+        // each offset has the last bit of the fields ending in it set. Offsets without field
+        // blocks (e.g. a whole block of padding) are left zero, making them part of the next
+        // field like padding bits within a block.
+        let mut bin_fb = vec![N::zero(); row_size / std::mem::size_of::<N>()];
+        let mut field_masks = bin_fb.clone();
         let max_bit = !(N::max_value() >> 1);
 
         for (i, fb) in field_blocks.iter().enumerate() {
-            let mut b = if fb.mask.is_zero() {
-                N::zero()
-            } else {
-                N::from(max_bit >> fb.mask.leading_zeros() as usize).unwrap()
-            };
-            if let Some(next) = field_blocks.get(i + 1) {
-                if next.field_start == fb.field_start {
-                    b = N::zero();
-                }
-            }
-            if let Some(prev) = field_blocks.get(i - 1) {
-                if prev.offset == fb.offset {
-                    let last = bin_fb.last_mut().unwrap();
-                    *last = *last | b;
-                    continue;
-                }
-                // Field skipping is not supported
-                assert!(fb.offset == prev.offset + 1);
+            let m = &mut field_masks[fb.offset as usize];
+            *m = *m | fb.mask;
+            let continues =
+                field_blocks.get(i + 1).is_some_and(|next| next.field_start == fb.field_start);
+            if fb.mask.is_zero() || continues {
+                continue;
             }
-            bin_fb.push(b);
+            let b = &mut bin_fb[fb.offset as usize];
+            *b = *b | max_bit >> fb.mask.leading_zeros() as usize;
         }
 
         Self {
@@ -128,8 +204,11 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for SparseArrayPatcher<N> {
             combined_mask: vec![MaskBlock::default(); row_size / std::mem::size_of::<N>()]
                 .into_boxed_slice(),
             field_blocks: bin_fb.into_boxed_slice(),
+            field_masks: field_masks.into_boxed_slice(),
             id_counter: 0,
             step_counter: 0,
+            instance_tag: next_instance_tag(),
+            write_stats: WriteStats::default(),
         }
     }
 
@@ -143,82 +222,61 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for SparseArrayPatcher<N> {
 
         let mut rd_blocks: Vec<PatchedBlock<N>> = Vec::new();
 
-        let mut i = 0;
-        let mut last_block = 0;
-        let mut last_acc = N::max_value();
-        let mut last_field_done = true;
+        // The field being walked starts at `span_start`, where it owns the bits `span_bits`, and
+        // owns all the blocks after it up to the one where it ends
+        let mut span_start = 0;
+        let mut span_bits = N::max_value();
+        let mut span_changed = false;
 
-        while i < self.field_blocks.len() {
-            let diff = before[i].0 ^ after[i].0;
+        for i in 0..self.field_blocks.len() {
+            let diff = (before[i].read() ^ after[i].read()) & self.field_masks[i];
             let mut fields = self.field_blocks[i];
+            let mut free = N::max_value();
 
-            if diff.is_zero() && last_field_done {
-                match fields.leading_zeros() {
-                    32 => (),
-                    n => {
-                        last_acc = N::max_value() >> n as usize;
-                        last_block = i as u32;
-                    }
-                }
-                i += 1;
-                continue;
-            }
-            if last_acc != N::max_value() {
-                if let Some(blk) = rd_blocks.last_mut() {
-                    blk.mask = blk.mask | !last_acc;
-                } else {
-                    rd_blocks.push(PatchedBlock {
-                        offset: last_block,
-                        diff: N::zero(),
-                        mask: !last_acc,
-                    });
-                }
-            }
-            rd_blocks.extend((last_block + 1..i as u32).map(|o| PatchedBlock {
-                diff: N::zero(),
-                mask: N::max_value(),
-                offset: o as u32,
-            }));
-
-            let mut mask = N::zero();
-            let mut acc = N::zero();
             while !fields.is_zero() {
-                let fm = (fields ^ (fields - N::one())) & !acc;
-                if !(fm & diff).is_zero() {
-                    mask = mask | fm;
+                let end = fields ^ (fields - N::one());
+                if span_changed || !(diff & end & free).is_zero() {
+                    for o in span_start..=i {
+                        let mut bits = if o == span_start { span_bits } else { N::max_value() };
+                        if o == i {
+                            bits = bits & end & free;
+                        }
+                        self.push_block(&mut rd_blocks, before, after, o, bits);
+                    }
                 }
-                acc = acc | fm;
+                free = free & !end;
                 fields = fields & (fields - N::one());
+                span_start = i;
+                span_bits = free;
+                span_changed = false;
             }
-
-            rd_blocks.push(PatchedBlock {
-                offset: i as u32,
-                diff,
-                mask,
-            });
-            last_field_done = acc == N::max_value();
-            last_acc = acc;
-            last_block = i as u32;
-            i += 1;
+            span_changed |= !(diff & free).is_zero();
         }
 
-        self.id_counter += 1;
+        self.id_counter = self.id_counter.checked_add(1)?;
+        let id = RowPatchId::new(self.instance_tag, self.id_counter);
         self.diff_stack.push(RowDiff {
             blocks: rd_blocks.into_boxed_slice(),
-            id: self.id_counter,
+            id,
         });
-        Some(self.id_counter)
+        Some(id)
     }
 
-    fn restore_patch(&mut self, id: RowPatchId, live_memory: &mut [Unaligned<N>]) {
-        // Find and remove the row diff from the stack
-        let mut i = self.diff_stack.len();
-        loop {
-            i = i.checked_sub(1).unwrap();
-            if self.diff_stack[i].id == id {
-                break;
-            }
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        if id.instance_tag() != self.instance_tag {
+            return Err(RestorePatchError::ForeignId);
         }
+
+        // Find and remove the row diff from the stack
+        let i = self
+            .diff_stack
+            .iter()
+            .rposition(|rd| rd.id == id)
+            .ok_or(RestorePatchError::UnknownId)?;
         let mut rd = self.diff_stack.remove(i);
 
         // If the step overflows, we have to reset the combined mask!
@@ -234,8 +292,13 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for SparseArrayPatcher<N> {
         for rd in &self.diff_stack[i..] {
             for b in rd.blocks.iter() {
                 let m = &mut self.combined_mask[b.offset as usize];
+                if m.step != self.step_counter {
+                    *m = MaskBlock {
+                        value: N::zero(),
+                        step: self.step_counter,
+                    };
+                }
                 m.value = m.value | b.mask;
-                m.step = self.step_counter;
             }
         }
 
@@ -244,12 +307,39 @@ impl<'a, N: PrimInt + Default> RowPatcher<'a, N> for SparseArrayPatcher<N> {
             let ofs = b.offset as usize;
             let m = &self.combined_mask[ofs];
             let hidden = if m.step == self.step_counter { m.value } else { N::zero() };
-            live_memory[ofs].0 = live_memory[ofs].0 ^ (b.diff & !hidden);
+            if let Some(s) = xor_blocks(live_memory, ofs, &[b.diff & !hidden]) {
+                self.write_stats.record(s);
+            }
             b.mask = b.mask & hidden;
             b.diff = b.diff & hidden;
         }
+        rd.blocks = rd.blocks.iter().filter(|b| !b.mask.is_zero()).cloned().collect();
 
-        // Merge with next patch if necessary
-        self.diff_stack.get_mut(i).map(|next| next.merge(&rd));
+        // Fold each hidden change into the lowest patch above which covers it, as that patch's
+        // diff is now relative to the value from before the restored patch
+        for next in &mut self.diff_stack[i..] {
+            if rd.blocks.is_empty() {
+                break;
+            }
+            let covered = rd.take_covered(next);
+            if !covered.blocks.is_empty() {
+                let emptied = next.merge(&covered);
+                debug_assert!(!emptied, "patch covering the folded changes was emptied");
+            }
+        }
+        debug_assert!(
+            rd.blocks.is_empty(),
+            "hidden changes not covered by any patch"
+        );
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        let mut mask = vec![N::zero(); self.combined_mask.len()];
+        for b in self.diff_stack.iter().flat_map(|rd| rd.blocks.iter()) {
+            let m = &mut mask[b.offset as usize];
+            *m = *m | b.mask;
+        }
+        mask
     }
 }
@@ -0,0 +1,124 @@
+use std::sync::{Mutex, MutexGuard};
+
+use num_traits::PrimInt;
+
+use super::base::{
+    FieldBlock, InvalidFieldBlocks, PatchRowError, RestorePatchError, RowPatchId, RowPatcher,
+};
+use crate::{param_file::RowMut, util::unaligned::Unaligned};
+
+/// Row patcher wrapper which can be shared between threads, e.g. to create patches from a UI
+/// thread and restore them from a teardown hook.
+///
+/// The methods mirror [`RowPatcher`] but take `&self`, serializing access to the patcher of the
+/// row with a lock. Use one wrapper per row, so that patching a row never waits on patches of
+/// another.
+///
+/// ### Memory ordering
+/// The lock only guards the state of the patcher, not the live memory of the row:
+/// - The game reads rows without synchronization. Restores XOR field changes into live memory
+///   with one relaxed interlocked operation per aligned word where possible (see
+///   [`crate::util::atomic_write`]), so other threads never observe a torn write within a word,
+///   but may observe a restore spanning several words partially applied.
+/// - `before` and `after` passed to [`SyncRowPatcher::create_patch`] must not change until it
+///   returns, or the patch won't match the edit. [`SyncRowPatcher::patch_row`] holds the lock
+///   while editing the row, so edits made through it are serialized with patches and restores.
+///
+/// # Panics
+/// All methods panic if another thread panicked while using the patcher, as its state may be
+/// inconsistent with live memory.
+#[derive(Debug)]
+pub struct SyncRowPatcher<P> {
+    patcher: Mutex<P>,
+}
+
+impl<P> SyncRowPatcher<P> {
+    pub fn wrap(patcher: P) -> Self {
+        Self {
+            patcher: Mutex::new(patcher),
+        }
+    }
+
+    /// See [`RowPatcher::new`].
+    pub fn new<'a, N: PrimInt>(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self
+    where
+        P: RowPatcher<'a, N>,
+    {
+        Self::wrap(P::new(field_blocks, row_size))
+    }
+
+    /// See [`RowPatcher::try_new`].
+    pub fn try_new<'a, N: PrimInt>(
+        field_blocks: &'a [FieldBlock<N>],
+        row_size: usize,
+    ) -> Result<Self, InvalidFieldBlocks>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        P::try_new(field_blocks, row_size).map(Self::wrap)
+    }
+
+    /// Locks the patcher, e.g. to inspect its state.
+    pub fn lock(&self) -> MutexGuard<'_, P> {
+        self.patcher.lock().expect("row patcher poisoned by a panic")
+    }
+
+    pub fn into_inner(self) -> P {
+        self.patcher.into_inner().expect("row patcher poisoned by a panic")
+    }
+
+    /// See [`RowPatcher::create_patch`].
+    pub fn create_patch<'a, N: PrimInt>(
+        &self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        self.lock().create_patch(before, after)
+    }
+
+    /// See [`RowPatcher::restore_patch`].
+    pub fn restore_patch<'a, N: PrimInt>(
+        &self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        self.lock().restore_patch(id, live_memory)
+    }
+
+    /// See [`RowPatcher::patched_mask_for_row`].
+    pub fn patched_mask_for_row<'a, N: PrimInt>(&self) -> Vec<N>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        self.lock().patched_mask_for_row()
+    }
+
+    /// Same as [`RowPatcherExt::patch_row`](super::base::RowPatcherExt::patch_row), holding the
+    /// lock while `edit` runs.
+    pub fn patch_row<'a, N: PrimInt>(
+        &self,
+        row: &mut RowMut<'_>,
+        edit: impl FnOnce(&mut [u8]),
+    ) -> Result<RowPatchId, PatchRowError>
+    where
+        P: RowPatcher<'a, N>,
+    {
+        let mut patcher = self.lock();
+        let before = row.as_blocks::<N>()?.to_vec();
+        edit(row.data_mut());
+
+        match patcher.create_patch(&before, row.as_blocks::<N>()?) {
+            Some(id) => Ok(id),
+            None => {
+                row.as_blocks_mut::<N>()?.copy_from_slice(&before);
+                Err(PatchRowError::PatchRejected)
+            }
+        }
+    }
+}
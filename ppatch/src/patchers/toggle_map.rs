@@ -0,0 +1,185 @@
+use fnv::FnvHashMap;
+use num_traits::PrimInt;
+use smallvec::SmallVec;
+
+use super::base::{
+    changed_field_blocks, next_instance_tag, FieldBlock, RestorePatchError, RowPatchId, RowPatcher,
+};
+use crate::util::unaligned::Unaligned;
+
+/// Bits of a field in each of its field blocks, in field block order.
+type FieldValue<N> = SmallVec<[N; 2]>;
+
+#[derive(Debug, Clone)]
+struct FieldState<N: PrimInt> {
+    /// Value of the field before the first outstanding patch changed it.
+    original: FieldValue<N>,
+    /// Values written by the outstanding patches which changed the field, in creation order.
+    layers: SmallVec<[(RowPatchId, FieldValue<N>); 2]>,
+    /// The field was toggled back to its original value, see [`ToggleMapPatcher::toggle`].
+    toggled_off: bool,
+}
+
+/// Row patcher which stores the original and written values of each patched field in a hash
+/// map, optimized for a few fields being patched and restored over and over, e.g. a trainer
+/// flipping a flag.
+///
+/// Patched fields can also be switched between their original value and the value of their
+/// most recent patch with [`ToggleMapPatcher::toggle`], without creating new patches.
+///
+/// ### Memory consumed per patch
+/// `~40 + 32*n_changed_fields`, for fields spanning at most two blocks
+///
+/// ### Complexity of [`RowPatcher::create_patch`]
+/// `O(n_changed_blocks + n_changed_fields)`
+///
+/// ### Complexity of [`RowPatcher::restore_patch`]
+/// `O(n_changed_fields * field_depth)`, where `field_depth` is the number of outstanding patches
+/// which changed the same field. It doesn't depend on the total number of patches.
+#[derive(Debug, Clone)]
+pub struct ToggleMapPatcher<'a, N: PrimInt = u32> {
+    field_blocks: &'a [FieldBlock<N>],
+    row_blocks: usize,
+    /// State of the fields changed by at least one outstanding patch, by first field block.
+    fields: FnvHashMap<u16, FieldState<N>>,
+    /// Fields changed by each outstanding patch, by local ID.
+    patches: FnvHashMap<u32, SmallVec<[u16; 4]>>,
+    id_counter: u32,
+    instance_tag: u32,
+}
+
+impl<'a, N: PrimInt> ToggleMapPatcher<'a, N> {
+    /// Field blocks of the field whose first field block is `field_start`.
+    fn field(&self, field_start: u16) -> impl Iterator<Item = &'a FieldBlock<N>> {
+        let field_blocks = self.field_blocks;
+        (field_blocks.get(field_start as usize..).unwrap_or_default().iter())
+            .take_while(move |fb| fb.field_start == field_start)
+    }
+
+    fn read_field(&self, field_start: u16, row: &[Unaligned<N>]) -> FieldValue<N> {
+        self.field(field_start)
+            .map(|fb| row[fb.offset as usize].read() & fb.mask)
+            .collect()
+    }
+
+    fn write_field(&self, field_start: u16, value: &[N], live_memory: &mut [Unaligned<N>]) {
+        for (fb, &bits) in self.field(field_start).zip(value) {
+            let block = &mut live_memory[fb.offset as usize];
+            block.write((block.read() & !fb.mask) | bits);
+        }
+    }
+
+    /// Switches the field whose first field block is `field_start` between its original value
+    /// and the value written by the most recent outstanding patch which changed it.
+    ///
+    /// Returns whether the field now holds its patched value, or `None` if no outstanding patch
+    /// changed it. Creating a patch which changes the field switches it back to its patched
+    /// value, and restoring all the patches which changed it leaves it at its original value.
+    pub fn toggle(&mut self, field_start: u16, live_memory: &mut [Unaligned<N>]) -> Option<bool> {
+        let state = self.fields.get_mut(&field_start)?;
+        state.toggled_off = !state.toggled_off;
+        let state = &self.fields[&field_start];
+        let value = if state.toggled_off {
+            &state.original
+        }
+        else {
+            &state.layers.last().unwrap().1
+        };
+        self.write_field(field_start, value, live_memory);
+        Some(!state.toggled_off)
+    }
+
+    /// Returns whether the field whose first field block is `field_start` holds its patched
+    /// value, or `None` if no outstanding patch changed it. See [`ToggleMapPatcher::toggle`].
+    pub fn is_toggled_on(&self, field_start: u16) -> Option<bool> {
+        self.fields.get(&field_start).map(|state| !state.toggled_off)
+    }
+}
+
+impl<'a, N: PrimInt> RowPatcher<'a, N> for ToggleMapPatcher<'a, N> {
+    fn new(field_blocks: &'a [FieldBlock<N>], row_size: usize) -> Self {
+        Self {
+            field_blocks,
+            row_blocks: row_size / std::mem::size_of::<N>(),
+            fields: FnvHashMap::default(),
+            patches: FnvHashMap::default(),
+            id_counter: 0,
+            instance_tag: next_instance_tag(),
+        }
+    }
+
+    fn create_patch(
+        &mut self,
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Option<RowPatchId> {
+        let mut changed: SmallVec<[u16; 4]> = SmallVec::new();
+        let range = changed_field_blocks(self.field_blocks, before, after);
+        for fb in &self.field_blocks[range] {
+            let offset = fb.offset as usize;
+            let diff = (before[offset].read() ^ after[offset].read()) & fb.mask;
+            if !diff.is_zero() && !changed.contains(&fb.field_start) {
+                changed.push(fb.field_start);
+            }
+        }
+
+        self.id_counter = self.id_counter.checked_add(1)?;
+        let id = RowPatchId::new(self.instance_tag, self.id_counter);
+        for &field_start in &changed {
+            let (original, written) = (
+                self.read_field(field_start, before),
+                self.read_field(field_start, after),
+            );
+            let state = self.fields.entry(field_start).or_insert_with(|| FieldState {
+                original,
+                layers: SmallVec::new(),
+                toggled_off: false,
+            });
+            state.layers.push((id, written));
+            state.toggled_off = false;
+        }
+        self.patches.insert(id.local_id(), changed);
+        Some(id)
+    }
+
+    fn restore_patch(
+        &mut self,
+        id: RowPatchId,
+        live_memory: &mut [Unaligned<N>],
+    ) -> Result<(), RestorePatchError> {
+        if id.instance_tag() != self.instance_tag {
+            return Err(RestorePatchError::ForeignId);
+        }
+        let changed = self.patches.remove(&id.local_id()).ok_or(RestorePatchError::UnknownId)?;
+
+        for field_start in changed {
+            let state = self.fields.get_mut(&field_start).unwrap();
+            let index = state.layers.iter().position(|(layer_id, _)| *layer_id == id).unwrap();
+            state.layers.remove(index);
+            // Patches below the top one, and toggled off fields, don't show in the live row
+            let visible = index == state.layers.len() && !state.toggled_off;
+            let value = visible.then(|| match state.layers.last() {
+                Some((_, value)) => value.clone(),
+                None => state.original.clone(),
+            });
+            if state.layers.is_empty() {
+                self.fields.remove(&field_start);
+            }
+            if let Some(value) = value {
+                self.write_field(field_start, &value, live_memory);
+            }
+        }
+        Ok(())
+    }
+
+    fn patched_mask_for_row(&self) -> Vec<N> {
+        let mut mask = vec![N::zero(); self.row_blocks];
+        for &field_start in self.fields.keys() {
+            for fb in self.field(field_start) {
+                let m = &mut mask[fb.offset as usize];
+                *m = *m | fb.mask;
+            }
+        }
+        mask
+    }
+}
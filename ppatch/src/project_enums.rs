@@ -0,0 +1,6 @@
+//! Rust enums for the project enums (`Enums.json`) of the target game, generated by the build
+//! script with `codegen::emit_project_enums`.
+//!
+//! Available with the `project-enums` feature.
+
+include!(concat!(env!("OUT_DIR"), "/project_enums.rs"));
@@ -0,0 +1,194 @@
+//! Typed access to the fields of a [`Row`] by name, using the layout of a [`Paramdef`].
+//!
+//! Field offsets must have been computed with [`Paramdef::compute_field_offsets`] for the version
+//! of the game the rows come from. Fields removed in that version, or added after it, have no
+//! offset and can't be accessed.
+//!
+//! Only little endian defs are supported. Offsets are checked against the size of the row, so a
+//! def which doesn't match the rows can't cause out of bounds accesses, but can still return
+//! garbage.
+
+use paramdex::paramdef::{DefBaseRustType, DefField, Paramdef};
+
+use crate::param_file::{Row, RowMut};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Rust type of a scalar paramdef field value.
+///
+/// Implemented for the primitive types of [`DefBaseRustType`].
+pub trait FieldValue: Copy + private::Sealed {
+    /// Type of the fields holding values of this type.
+    const RUST_TYPE: DefBaseRustType;
+
+    /// Converts the value of a field `width` bits wide, sign extending it for signed types.
+    fn from_bits(bits: u32, width: usize) -> Self;
+
+    /// Returns the bits of the value.
+    fn to_bits(self) -> u32;
+}
+
+macro_rules! impl_field_value {
+    ($($ty:ty => $rust_type:ident, $unsigned:ty;)*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl FieldValue for $ty {
+                const RUST_TYPE: DefBaseRustType = DefBaseRustType::$rust_type;
+
+                fn from_bits(bits: u32, width: usize) -> Self {
+                    let unused = <$unsigned>::BITS as usize - width;
+                    ((bits as $unsigned as $ty) << unused) >> unused
+                }
+
+                fn to_bits(self) -> u32 {
+                    self as $unsigned as u32
+                }
+            }
+        )*
+    };
+}
+
+impl_field_value! {
+    u8 => U8, u8;
+    i8 => I8, u8;
+    u16 => U16, u16;
+    i16 => I16, u16;
+    u32 => U32, u32;
+    i32 => I32, u32;
+}
+
+impl private::Sealed for f32 {}
+
+impl FieldValue for f32 {
+    const RUST_TYPE: DefBaseRustType = DefBaseRustType::F32;
+
+    fn from_bits(bits: u32, _width: usize) -> Self {
+        f32::from_bits(bits)
+    }
+
+    fn to_bits(self) -> u32 {
+        f32::to_bits(self)
+    }
+}
+
+/// Location of a scalar field in a row, in bits.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScalarField {
+    bit_offset: usize,
+    width: usize,
+}
+
+impl ScalarField {
+    /// Locates the field `name` of `def` if it holds a `T` and lies within `row_len` bytes.
+    fn find<T: FieldValue>(def: &Paramdef, name: &str, row_len: usize) -> Option<Self> {
+        let field = def.field_by_name(name)?;
+        if def.big_endian || field.field_def.base_type.rust_type() != T::RUST_TYPE {
+            return None;
+        }
+        Self::locate(field, row_len)
+    }
+
+    /// Locates `field` if it is a scalar lying within `row_len` bytes, whatever its type.
+    pub(crate) fn locate(field: &DefField, row_len: usize) -> Option<Self> {
+        if field.field_def.modifier.is_array() || field.size_bits() == 0 {
+            return None;
+        }
+        let this = Self {
+            bit_offset: field.bit_offset?,
+            width: field.size_bits(),
+        };
+        (this.bytes().end <= row_len).then_some(this)
+    }
+
+    /// Range of the bytes holding at least one bit of the field.
+    fn bytes(self) -> std::ops::Range<usize> {
+        self.bit_offset / 8..(self.bit_offset + self.width).div_ceil(8)
+    }
+
+    /// Mask of the field bits in the 64-bit window starting at its first byte.
+    fn window_mask(self) -> u64 {
+        (u64::MAX >> (64 - self.width)) << (self.bit_offset % 8)
+    }
+
+    fn read_window(self, data: &[u8]) -> u64 {
+        let bytes = &data[self.bytes()];
+        let mut window = [0; 8];
+        window[..bytes.len()].copy_from_slice(bytes);
+        u64::from_le_bytes(window)
+    }
+
+    pub(crate) fn read<T: FieldValue>(self, data: &[u8]) -> T {
+        let bits = (self.read_window(data) & self.window_mask()) >> (self.bit_offset % 8);
+        T::from_bits(bits as u32, self.width)
+    }
+
+    pub(crate) fn write<T: FieldValue>(self, data: &mut [u8], value: T) {
+        let mask = self.window_mask();
+        let bits = ((value.to_bits() as u64) << (self.bit_offset % 8)) & mask;
+        let window = ((self.read_window(data) & !mask) | bits).to_le_bytes();
+        let bytes = &mut data[self.bytes()];
+        let len = bytes.len();
+        bytes.copy_from_slice(&window[..len]);
+    }
+}
+
+/// Returns the bytes of `field` if it starts and ends on a byte boundary within `row_len`.
+pub(crate) fn byte_range(field: &DefField, row_len: usize) -> Option<std::ops::Range<usize>> {
+    let bit_offset = field.bit_offset?;
+    if !bit_offset.is_multiple_of(8) || !field.size_bits().is_multiple_of(8) {
+        return None;
+    }
+    let range = bit_offset / 8..bit_offset / 8 + field.size_bytes();
+    (range.end <= row_len).then_some(range)
+}
+
+impl<'a> Row<'a> {
+    /// Returns the value of the scalar field `name` of `def`, bitfields included.
+    ///
+    /// Returns `None` if there is no such field, if it is an array, if it doesn't hold a `T` (e.g.
+    /// reading a `u8` field as a `u32`) or if it has no offset for the current version. Signed
+    /// bitfields are sign extended.
+    pub fn field<T: FieldValue>(&self, def: &Paramdef, name: &str) -> Option<T> {
+        let data = self.data();
+        ScalarField::find::<T>(def, name, data.len()).map(|f| f.read(data))
+    }
+
+    /// Returns the raw bytes of the field `name` of `def`, e.g. for arrays and `fixstr`s.
+    ///
+    /// Returns `None` if there is no such field, if it has no offset for the current version or
+    /// if it doesn't start and end on a byte boundary (i.e. is a bitfield).
+    pub fn field_raw(&self, def: &Paramdef, name: &str) -> Option<&'a [u8]> {
+        let data = self.data();
+        data.get(byte_range(def.field_by_name(name)?, data.len())?)
+    }
+}
+
+impl<'a> RowMut<'a> {
+    /// Same as [`Row::field`].
+    pub fn field<T: FieldValue>(&self, def: &Paramdef, name: &str) -> Option<T> {
+        let data = self.data();
+        ScalarField::find::<T>(def, name, data.len()).map(|f| f.read(data))
+    }
+
+    /// Writes `value` to the scalar field `name` of `def`, leaving all other bits of the row
+    /// untouched. Values too wide for a bitfield are truncated to its width.
+    ///
+    /// Returns the previous value of the field, or `None` without writing anything if the field
+    /// can't be accessed (see [`Row::field`]).
+    pub fn set_field<T: FieldValue>(&mut self, def: &Paramdef, name: &str, value: T) -> Option<T> {
+        let data = self.data_mut();
+        let field = ScalarField::find::<T>(def, name, data.len())?;
+        let previous = field.read(data);
+        field.write(data, value);
+        Some(previous)
+    }
+
+    /// Same as [`Row::field_raw`], but returns the bytes mutably.
+    pub fn field_raw_mut(&mut self, def: &Paramdef, name: &str) -> Option<&mut [u8]> {
+        let range = byte_range(def.field_by_name(name)?, self.len())?;
+        self.data_mut().get_mut(range)
+    }
+}
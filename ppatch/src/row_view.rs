@@ -0,0 +1,176 @@
+//! Decoded values of all the fields of a [`Row`], e.g. to dump a row while debugging patches.
+//!
+//! Like [`row_fields`](crate::row_fields), this uses the layout of a [`Paramdef`] whose field
+//! offsets were computed for the version of the game the row comes from, and only supports little
+//! endian defs.
+
+use std::fmt;
+
+use paramdex::{
+    meta::ParamMeta,
+    paramdef::{DefBaseRustType, DefBaseType, DefField, Paramdef},
+};
+
+use crate::{
+    param_file::Row,
+    row_fields::{byte_range, ScalarField},
+};
+
+/// Value of a field, decoded according to its type in the def.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedValue<'a> {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    /// Raw bytes of an array field other than a string.
+    Bytes(&'a [u8]),
+    /// `fixstr` field, up to the first NUL.
+    Str(String),
+    /// `fixstrW` field, up to the first NUL.
+    WStr(String),
+}
+
+impl fmt::Display for DecodedValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::U8(v) => v.fmt(f),
+            Self::I8(v) => v.fmt(f),
+            Self::U16(v) => v.fmt(f),
+            Self::I16(v) => v.fmt(f),
+            Self::U32(v) => v.fmt(f),
+            Self::I32(v) => v.fmt(f),
+            Self::F32(v) => v.fmt(f),
+            Self::Bytes(bytes) => {
+                let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+                write!(f, "[{}]", hex.join(" "))
+            }
+            Self::Str(s) | Self::WStr(s) => write!(f, "{s:?}"),
+        }
+    }
+}
+
+/// A field of a row and its decoded value, see [`RowView::fields`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldValue<'a> {
+    pub name: &'a str,
+    /// `@AltName` of the field in the param meta, if the view has one and it is not empty.
+    pub display_name: Option<&'a str>,
+    pub value: DecodedValue<'a>,
+    pub bit_offset: usize,
+}
+
+/// View over the fields of a row, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct RowView<'a> {
+    row: Row<'a>,
+    def: &'a Paramdef,
+    meta: Option<&'a ParamMeta>,
+}
+
+impl<'a> RowView<'a> {
+    pub fn new(row: Row<'a>, def: &'a Paramdef) -> Self {
+        Self {
+            row,
+            def,
+            meta: None,
+        }
+    }
+
+    /// Uses the display names of `meta` for the fields.
+    pub fn with_meta(mut self, meta: &'a ParamMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    pub fn row(&self) -> Row<'a> {
+        self.row
+    }
+
+    /// Iterates over the fields of the row in def order, including unofficial fields.
+    ///
+    /// Fields without an offset at the layout version are skipped, as well as fields out of
+    /// bounds of the row. Fields of big endian defs can't be decoded, so there are none.
+    pub fn fields(&self) -> impl Iterator<Item = FieldValue<'a>> + 'a {
+        let (row, meta) = (self.row, self.meta);
+        let fields = (!self.def.big_endian).then_some(self.def.fields.iter());
+        fields.into_iter().flatten().filter_map(move |field| {
+            let name = field.field_def.name.as_str();
+            let display_name = meta
+                .and_then(|m| m.fields.get(name))
+                .map(|f| f.alt_name.as_str())
+                .filter(|n| !n.is_empty());
+            Some(FieldValue {
+                name,
+                display_name,
+                value: decode(field, row.data())?,
+                bit_offset: field.bit_offset?,
+            })
+        })
+    }
+}
+
+/// Decodes `field` in the row `data`, if it has an offset and lies within the row.
+fn decode<'a>(field: &DefField, data: &'a [u8]) -> Option<DecodedValue<'a>> {
+    let field_def = &field.field_def;
+    if field_def.modifier.is_array() {
+        let bytes = data.get(byte_range(field, data.len())?)?;
+        return Some(match field_def.base_type {
+            DefBaseType::Fixstr => {
+                let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                DecodedValue::Str(String::from_utf8_lossy(&bytes[..len]).into_owned())
+            }
+            DefBaseType::FixstrW => {
+                let units = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+                let units: Vec<_> = units.take_while(|&c| c != 0).collect();
+                DecodedValue::WStr(String::from_utf16_lossy(&units))
+            }
+            _ => DecodedValue::Bytes(bytes),
+        });
+    }
+
+    let scalar = ScalarField::locate(field, data.len())?;
+    Some(match field_def.base_type.rust_type() {
+        DefBaseRustType::U8 => DecodedValue::U8(scalar.read(data)),
+        DefBaseRustType::I8 => DecodedValue::I8(scalar.read(data)),
+        DefBaseRustType::U16 => DecodedValue::U16(scalar.read(data)),
+        DefBaseRustType::I16 => DecodedValue::I16(scalar.read(data)),
+        DefBaseRustType::U32 => DecodedValue::U32(scalar.read(data)),
+        DefBaseRustType::I32 => DecodedValue::I32(scalar.read(data)),
+        DefBaseRustType::F32 => DecodedValue::F32(scalar.read(data)),
+    })
+}
+
+/// Renders the fields as a table of offsets, names and values. Bit offsets of fields which don't
+/// start on a byte boundary are written after a dot.
+impl fmt::Display for RowView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<_> = self
+            .fields()
+            .map(|fv| {
+                let (byte, bit) = (fv.bit_offset / 8, fv.bit_offset % 8);
+                let offset = match bit {
+                    0 => format!("0x{byte:03X}"),
+                    _ => format!("0x{byte:03X}.{bit}"),
+                };
+                let display_name = fv.display_name.unwrap_or_default();
+                (offset, fv.name, display_name, fv.value.to_string())
+            })
+            .collect();
+        let name_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+        let display_width = rows.iter().map(|r| r.2.len()).max().unwrap_or(0);
+
+        writeln!(f, "Row {} ({})", self.row.id(), self.def.param_type)?;
+        for (offset, name, display_name, value) in rows {
+            write!(f, "{offset:<9} {name:<name_width$}")?;
+            if display_width != 0 {
+                write!(f, "  {display_name:<display_width$}")?;
+            }
+            writeln!(f, "  {value}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! Offline computation of the row resulting from stacking several patches, without live memory.
+//!
+//! Patches are applied with the same semantics as the runtime [`RowPatcher`]s: changes are
+//! tracked per field, and each field holds the value written by the most recent patch which
+//! changed it. Fields sharing a block (e.g. bitfields) are attributed independently.
+//!
+//! [`RowPatcher`]: crate::patchers::base::RowPatcher
+
+use num_traits::PrimInt;
+
+use crate::{
+    fields::{field_size_bits, read_field_bytes, write_field_bytes, FieldBlock},
+    param_file::UnalignedRowSize,
+    util::unaligned::Unaligned,
+};
+
+/// New value of a single field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Index of the first field block of the field.
+    pub field_start: u16,
+    /// Value of the field, in the format of [`crate::fields::read_field_bytes`].
+    pub value: Box<[u8]>,
+}
+
+/// Set of field values written by a patch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldChangeSet {
+    pub changes: Vec<FieldChange>,
+}
+
+impl FieldChangeSet {
+    /// Records the fields whose value differs between `before` and `after`, e.g. from the row
+    /// contents around the creation of a live patch.
+    pub fn from_diff<N: PrimInt>(
+        field_blocks: &[FieldBlock<N>],
+        before: &[Unaligned<N>],
+        after: &[Unaligned<N>],
+    ) -> Self {
+        let changes = field_starts(field_blocks)
+            .filter_map(|field_start| {
+                let value = read_field(field_blocks, after, field_start);
+                (value != read_field(field_blocks, before, field_start))
+                    .then_some(FieldChange { field_start, value })
+            })
+            .collect();
+        Self { changes }
+    }
+}
+
+/// Which patches changed a field, in the order they were applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldAttribution {
+    /// Index of the first field block of the field.
+    pub field_start: u16,
+    /// Index of the patch whose value the field holds.
+    pub winner: usize,
+    /// Indices of the patches whose change to the field was overridden by a later one.
+    pub overridden: Vec<usize>,
+}
+
+/// Result of [`stack_patches`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackedRow {
+    /// Row contents after all patches are applied.
+    pub data: Box<[u8]>,
+    /// Attribution of every field changed by at least one patch, ordered by field.
+    pub fields: Vec<FieldAttribution>,
+}
+
+fn field_starts<N: PrimInt>(field_blocks: &[FieldBlock<N>]) -> impl Iterator<Item = u16> + '_ {
+    field_blocks
+        .iter()
+        .enumerate()
+        .filter(|(i, fb)| fb.field_start as usize == *i)
+        .map(|(_, fb)| fb.field_start)
+}
+
+fn read_field<N: PrimInt>(
+    field_blocks: &[FieldBlock<N>],
+    row: &[Unaligned<N>],
+    field_start: u16,
+) -> Box<[u8]> {
+    let mut value = vec![0; field_size_bits(field_blocks, field_start).div_ceil(8)];
+    read_field_bytes(row, field_blocks, field_start, &mut value);
+    value.into_boxed_slice()
+}
+
+/// Applies `patches` in order on top of a copy of `baseline_row`.
+///
+/// As with the runtime patchers, writing a field to the value it already holds is not a change,
+/// so such a write neither wins nor overrides the field.
+///
+/// # Errors
+/// If the row size is not a multiple of the block size, returns [`UnalignedRowSize`].
+pub fn stack_patches<N: PrimInt>(
+    field_blocks: &[FieldBlock<N>],
+    baseline_row: &[u8],
+    patches: &[FieldChangeSet],
+) -> Result<StackedRow, UnalignedRowSize> {
+    let block_size = std::mem::size_of::<N>();
+    if !baseline_row.len().is_multiple_of(block_size) {
+        return Err(UnalignedRowSize {
+            row_size: baseline_row.len(),
+            block_size,
+        });
+    }
+
+    let mut data: Box<[u8]> = baseline_row.into();
+    // SAFETY: Unaligned<N> has an alignment of 1 and integers are valid for any bit pattern
+    let row = unsafe {
+        std::slice::from_raw_parts_mut(
+            data.as_mut_ptr() as *mut Unaligned<N>,
+            data.len() / block_size,
+        )
+    };
+
+    // Patches which changed each field, indexed by field start
+    let mut writers: Vec<Vec<usize>> = vec![Vec::new(); field_blocks.len()];
+    for (i, patch) in patches.iter().enumerate() {
+        let old_values: Vec<_> = patch
+            .changes
+            .iter()
+            .map(|c| (c.field_start, read_field(field_blocks, row, c.field_start)))
+            .collect();
+        for change in &patch.changes {
+            write_field_bytes(row, field_blocks, change.field_start, &change.value);
+        }
+        for (field_start, old_value) in old_values {
+            let field_writers = &mut writers[field_start as usize];
+            if field_writers.last() != Some(&i)
+                && read_field(field_blocks, row, field_start) != old_value
+            {
+                field_writers.push(i);
+            }
+        }
+    }
+
+    let fields = writers
+        .into_iter()
+        .enumerate()
+        .filter_map(|(field_start, mut patches)| {
+            let winner = patches.pop()?;
+            Some(FieldAttribution {
+                field_start: field_start as u16,
+                winner,
+                overridden: patches,
+            })
+        })
+        .collect();
+
+    Ok(StackedRow { data, fields })
+}
@@ -1,7 +1,68 @@
-use field_metadata::{load_fb_repo, ArchivedFieldBlockRepo};
+use field_metadata::{load_fb_repo_checked, ArchivedFieldBlockRepo};
 use lazy_static::lazy_static;
 
-lazy_static! {
-    pub static ref FIELD_BLOCK_REPO: &'static ArchivedFieldBlockRepo =
-        unsafe { load_fb_repo(include_bytes!("../field_blocks.bin")) };
+use crate::game::Game;
+
+/// Bytes aligned like the field block archive.
+#[repr(C, align(16))]
+struct Aligned<T: ?Sized>(T);
+
+/// Loads the field blocks of `game` written by the build script to `$path` (relative to this
+/// file) on first use.
+macro_rules! embedded_repo {
+    ($game:expr, $path:literal) => {{
+        static FIELD_BLOCKS: &Aligned<[u8]> = &Aligned(*include_bytes!($path));
+        lazy_static! {
+            static ref FIELD_BLOCK_REPO: &'static ArchivedFieldBlockRepo =
+                load_fb_repo_checked(&FIELD_BLOCKS.0, $game.name()).unwrap_or_else(|e| {
+                    panic!(
+                        "{} does not match this build of ppatch ({}): {e}",
+                        &$path[3..],
+                        $game.name()
+                    )
+                });
+        }
+        *FIELD_BLOCK_REPO
+    }};
 }
+
+pub fn field_block_repo(game: Game) -> Option<&'static ArchivedFieldBlockRepo> {
+    match game {
+        #[cfg(not(feature = "multi-game"))]
+        _ if game == Game::DEFAULT => Some(embedded_repo!(Game::DEFAULT, "../field_blocks.bin")),
+        #[cfg(feature = "multi-game")]
+        Game::Ds3 => Some(embedded_repo!(Game::Ds3, "../field_blocks_ds3.bin")),
+        #[cfg(feature = "multi-game")]
+        Game::Er => Some(embedded_repo!(Game::Er, "../field_blocks_er.bin")),
+        #[cfg(feature = "multi-game")]
+        Game::Ac6 => Some(embedded_repo!(Game::Ac6, "../field_blocks_ac6.bin")),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+const fn parse_layout_version(v: &str) -> u64 {
+    match u64::from_str_radix(v, 10) {
+        Ok(v) => v,
+        Err(_) => panic!("invalid layout version emitted by the build script"),
+    }
+}
+
+pub fn layout_version(game: Game) -> Option<u64> {
+    match game {
+        #[cfg(not(feature = "multi-game"))]
+        _ if game == Game::DEFAULT => Some(LAYOUT_VERSION),
+        #[cfg(feature = "multi-game")]
+        Game::Ds3 => Some(parse_layout_version(env!("PPATCH_FB_LAYOUT_VERSION_DS3"))),
+        #[cfg(feature = "multi-game")]
+        Game::Er => Some(parse_layout_version(env!("PPATCH_FB_LAYOUT_VERSION_ER"))),
+        #[cfg(feature = "multi-game")]
+        Game::Ac6 => Some(parse_layout_version(env!("PPATCH_FB_LAYOUT_VERSION_AC6"))),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Paramdef layout version the embedded field blocks of the [default game](Game::DEFAULT) were
+/// generated for.
+pub const LAYOUT_VERSION: u64 = parse_layout_version(env!("PPATCH_FB_LAYOUT_VERSION"));
@@ -0,0 +1,209 @@
+//! Generators of field block layouts and row contents, shared by the property tests and the
+//! benchmarks of the patchers. Only available with the `test-fixtures` feature.
+//!
+//! Everything is generated from a seed with a small xorshift generator, so that layouts are
+//! reproducible and don't pull a random number generator into the crate.
+
+use num_traits::PrimInt;
+
+use crate::{
+    fields::{read_field_bytes, FieldBlock},
+    util::unaligned::Unaligned,
+};
+
+/// Field of a generated paramdef.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSpec {
+    /// Padding bytes not belonging to any field.
+    Gap(usize),
+    /// Bitfield which is not byte aligned, packed with its neighbors.
+    Bits(usize),
+    /// Naturally aligned field of the given size in bytes (up to 4 byte alignment).
+    Bytes(usize),
+}
+
+impl FieldSpec {
+    /// Bit range occupied by the field when placed at `bit`, or `None` for gaps, along with the
+    /// bit following it.
+    fn place(self, bit: usize) -> (Option<(usize, usize)>, usize) {
+        match self {
+            FieldSpec::Gap(n) => (None, bit.next_multiple_of(8) + 8 * n),
+            FieldSpec::Bits(n) => (Some((bit, bit + n)), bit + n),
+            FieldSpec::Bytes(n) => {
+                let start = bit.next_multiple_of(8 * n.min(4));
+                (Some((start, start + 8 * n)), start + 8 * n)
+            }
+        }
+    }
+}
+
+/// Field blocks of a paramdef made of [`FieldSpec`]s.
+#[derive(Debug, Clone)]
+pub struct FieldLayout<N: PrimInt = u32> {
+    pub blocks: Vec<FieldBlock<N>>,
+    /// Index of the first field block of each field.
+    pub field_starts: Vec<u16>,
+    /// Size of the row in bytes, a multiple of the block size.
+    pub row_size: usize,
+}
+
+impl<N: PrimInt> FieldLayout<N> {
+    /// Lays out `fields` in order, with a row just large enough to hold them.
+    pub fn new(fields: &[FieldSpec]) -> Self {
+        let block_bits = 8 * std::mem::size_of::<N>();
+        let mut layout = Self {
+            blocks: Vec::new(),
+            field_starts: Vec::new(),
+            row_size: 0,
+        };
+
+        let mut bit = 0;
+        for &field in fields {
+            let (range, next) = field.place(bit);
+            if let Some((start, end)) = range {
+                layout.push_field(start, end);
+            }
+            bit = next;
+        }
+        layout.row_size = bit.div_ceil(block_bits).max(1) * block_bits / 8;
+        layout
+    }
+
+    /// Generates a layout of `config.row_size` bytes, filled with fields drawn according to
+    /// the weights of `config`.
+    pub fn generate(config: &LayoutConfig) -> Self {
+        let block_size = std::mem::size_of::<N>();
+        let row_bits = 8 * config.row_size.next_multiple_of(block_size).max(block_size);
+        let mut rng = XorShift::new(config.seed);
+        let weights = [
+            config.gap_weight,
+            config.bitfield_weight,
+            config.byte_weights[0],
+            config.byte_weights[1],
+            config.byte_weights[2],
+        ];
+        let total: u64 = weights.iter().map(|&w| w as u64).sum();
+        assert!(total > 0, "layout config has no field weights");
+
+        let mut layout = Self {
+            blocks: Vec::new(),
+            field_starts: Vec::new(),
+            row_size: row_bits / 8,
+        };
+        let mut bit = 0;
+        loop {
+            let mut pick = rng.below(total);
+            let kind = (weights.iter())
+                .position(|&w| match pick.checked_sub(w as u64) {
+                    Some(rest) => {
+                        pick = rest;
+                        false
+                    }
+                    None => true,
+                })
+                .unwrap();
+            let field = match kind {
+                0 => FieldSpec::Gap(1 + rng.below(4) as usize),
+                1 => FieldSpec::Bits(1 + rng.below(7) as usize),
+                2 => FieldSpec::Bytes(1),
+                3 => FieldSpec::Bytes(2),
+                _ => FieldSpec::Bytes(4),
+            };
+            let (range, next) = field.place(bit);
+            if next > row_bits {
+                break;
+            }
+            if let Some((start, end)) = range {
+                layout.push_field(start, end);
+            }
+            bit = next;
+        }
+        layout
+    }
+
+    /// Adds the field blocks of a field spanning the bits `start..end` of the row.
+    fn push_field(&mut self, start: usize, end: usize) {
+        let block_bits = 8 * std::mem::size_of::<N>();
+        let field_start = self.blocks.len() as u16;
+        self.field_starts.push(field_start);
+
+        let mut bit = start;
+        while bit < end {
+            let block_end = (bit / block_bits + 1) * block_bits;
+            let n = block_end.min(end) - bit;
+            let mask = (N::max_value() >> (block_bits - n)) << (bit % block_bits);
+            self.blocks.push(FieldBlock {
+                field_start,
+                offset: (bit / block_bits) as u16,
+                mask,
+            });
+            bit += n;
+        }
+    }
+
+    /// Reads the bytes of the field whose first field block is `field_start`.
+    pub fn read_field(&self, row: &[Unaligned<N>], field_start: u16) -> Vec<u8> {
+        let mut value = vec![0; 16];
+        let len = read_field_bytes(row, &self.blocks, field_start, &mut value);
+        value.truncate(len);
+        value
+    }
+}
+
+/// Parameters of [`FieldLayout::generate`]. Weights are relative to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutConfig {
+    /// Size of the row in bytes, rounded up to a multiple of the block size.
+    pub row_size: usize,
+    /// Weights of 1, 2 and 4 byte fields.
+    pub byte_weights: [u32; 3],
+    /// Weight of bitfields of 1 to 7 bits.
+    pub bitfield_weight: u32,
+    /// Weight of gaps of 1 to 4 bytes of padding.
+    pub gap_weight: u32,
+    pub seed: u64,
+}
+
+impl Default for LayoutConfig {
+    /// Layout resembling a typical param: mostly 4 byte fields, some smaller fields and
+    /// bitfields, and a little padding.
+    fn default() -> Self {
+        Self {
+            row_size: 256,
+            byte_weights: [2, 2, 8],
+            bitfield_weight: 3,
+            gap_weight: 1,
+            seed: 0x5EED,
+        }
+    }
+}
+
+/// Returns `row_size` bytes of pseudo-random row contents, as blocks of type `N`.
+pub fn random_row<N: PrimInt>(row_size: usize, seed: u64) -> Vec<Unaligned<N>> {
+    let block_bits = 8 * std::mem::size_of::<N>() as u32;
+    let mut rng = XorShift::new(seed);
+    (0..row_size / std::mem::size_of::<N>())
+        .map(|_| Unaligned(N::from(rng.next() >> (64 - block_bits)).unwrap()))
+        .collect()
+}
+
+/// xorshift64* generator.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
@@ -0,0 +1,133 @@
+//! Writes to live memory which avoid torn reads from other threads where possible.
+//!
+//! Changes spanning at most 8 bytes which lie in a single naturally aligned 4 or 8 byte word are
+//! applied with one interlocked XOR on that word. Since bits outside the change are XORed with 0,
+//! concurrent writes to neighboring fields sharing the word are not lost.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use num_traits::PrimInt;
+
+use super::unaligned::Unaligned;
+
+/// How a write to live memory is performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStrategy {
+    /// A single interlocked operation on the aligned 4 byte word containing the write.
+    Atomic32,
+    /// A single interlocked operation on the aligned 8 byte word containing the write.
+    Atomic64,
+    /// Plain writes, which other threads may observe partially applied.
+    Plain,
+}
+
+/// Number of writes to live memory performed with each [`WriteStrategy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    pub atomic32: u64,
+    pub atomic64: u64,
+    pub plain: u64,
+}
+
+impl WriteStats {
+    /// Counts a write performed with `strategy`.
+    pub fn record(&mut self, strategy: WriteStrategy) {
+        match strategy {
+            WriteStrategy::Atomic32 => self.atomic32 += 1,
+            WriteStrategy::Atomic64 => self.atomic64 += 1,
+            WriteStrategy::Plain => self.plain += 1,
+        }
+    }
+}
+
+/// Classifies a write of `len` bytes at address `addr`.
+///
+/// Writes are atomic-capable if they are non-empty and lie within a single naturally aligned
+/// 4 or 8 byte word. For example, a 4 byte write straddling an 8 byte boundary is not.
+pub fn classify_write(addr: usize, len: usize) -> WriteStrategy {
+    let Some(last) = len.checked_sub(1).and_then(|l| addr.checked_add(l))
+    else {
+        return WriteStrategy::Plain;
+    };
+    if addr / 4 == last / 4 {
+        WriteStrategy::Atomic32
+    }
+    else if addr / 8 == last / 8 {
+        WriteStrategy::Atomic64
+    }
+    else {
+        WriteStrategy::Plain
+    }
+}
+
+/// XORs `diff` into `bytes[start..start + diff.len()]`, atomically if possible.
+///
+/// The write is only performed atomically if the aligned word containing it lies within `bytes`.
+///
+/// # Panics
+/// If the range is out of bounds of `bytes`.
+pub fn xor_bytes(bytes: &mut [u8], start: usize, diff: &[u8]) -> WriteStrategy {
+    let end = start + diff.len();
+    assert!(end <= bytes.len(), "write out of bounds");
+
+    let base = bytes.as_ptr() as usize;
+    let word_size = match classify_write(base + start, diff.len()) {
+        WriteStrategy::Atomic32 => 4,
+        WriteStrategy::Atomic64 => 8,
+        WriteStrategy::Plain => 0,
+    };
+    let word_start = (base + start) & !(word_size.max(1) - 1);
+    if word_size == 0 || word_start < base || word_start + word_size > base + bytes.len() {
+        for (b, d) in bytes[start..end].iter_mut().zip(diff) {
+            *b ^= d;
+        }
+        return WriteStrategy::Plain;
+    }
+
+    let shift = base + start - word_start;
+    let mut word = [0u8; 8];
+    word[shift..shift + diff.len()].copy_from_slice(diff);
+    let word_ptr = bytes[word_start - base..].as_mut_ptr();
+
+    // SAFETY: The word is naturally aligned, lies within `bytes` and we have exclusive access to it
+    if word_size == 4 {
+        let value = u32::from_ne_bytes(word[..4].try_into().unwrap());
+        unsafe { &*(word_ptr as *const AtomicU32) }.fetch_xor(value, Ordering::Relaxed);
+        WriteStrategy::Atomic32
+    }
+    else {
+        let value = u64::from_ne_bytes(word);
+        unsafe { &*(word_ptr as *const AtomicU64) }.fetch_xor(value, Ordering::Relaxed);
+        WriteStrategy::Atomic64
+    }
+}
+
+/// XORs `diffs` into the consecutive blocks of `live` starting at `first_block`, atomically if
+/// the changed bytes allow it (see [`classify_write`]).
+///
+/// Only the bytes actually changed by `diffs` are considered, so a change to a small field
+/// in a larger span of blocks can still be applied atomically. Returns `None` without writing
+/// anything if all diffs are zero.
+pub fn xor_blocks<N: PrimInt>(
+    live: &mut [Unaligned<N>],
+    first_block: usize,
+    diffs: &[N],
+) -> Option<WriteStrategy> {
+    let block_size = std::mem::size_of::<N>();
+    // SAFETY: Unaligned<N> has the same size as N and an alignment of 1. Both slices are
+    // viewed in native byte order, so bytes of `diffs` line up with the bytes of `live`.
+    let (bytes, diff_bytes) = unsafe {
+        (
+            std::slice::from_raw_parts_mut(live.as_mut_ptr() as *mut u8, size_of_val(live)),
+            std::slice::from_raw_parts(diffs.as_ptr() as *const u8, size_of_val(diffs)),
+        )
+    };
+
+    let first = diff_bytes.iter().position(|&b| b != 0)?;
+    let last = diff_bytes.iter().rposition(|&b| b != 0).unwrap();
+    Some(xor_bytes(
+        bytes,
+        first_block * block_size + first,
+        &diff_bytes[first..=last],
+    ))
+}
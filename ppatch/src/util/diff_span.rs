@@ -0,0 +1,73 @@
+//! Locates the changes between two versions of a row without looking at its field layout.
+//!
+//! Rows are compared 16 bytes at a time, so that the common case of a small change in a large
+//! row is found without walking the field blocks of the whole row.
+
+use std::ops::RangeInclusive;
+
+use num_traits::PrimInt;
+
+use super::unaligned::Unaligned;
+
+const CHUNK_SIZE: usize = std::mem::size_of::<u128>();
+
+fn chunks_differ(a: &[u8], b: &[u8]) -> bool {
+    u128::from_ne_bytes(a.try_into().unwrap()) != u128::from_ne_bytes(b.try_into().unwrap())
+}
+
+/// Returns the index of the first byte which differs between `a` and `b`.
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    let chunked_len = a.len() / CHUNK_SIZE * CHUNK_SIZE;
+    let chunk = a
+        .chunks_exact(CHUNK_SIZE)
+        .zip(b.chunks_exact(CHUNK_SIZE))
+        .position(|(a, b)| chunks_differ(a, b));
+    let start = chunk.map_or(chunked_len, |c| c * CHUNK_SIZE);
+    let end = chunk.map_or(a.len(), |_| start + CHUNK_SIZE);
+    a[start..end]
+        .iter()
+        .zip(&b[start..end])
+        .position(|(a, b)| a != b)
+        .map(|i| start + i)
+}
+
+/// Returns the index of the last byte which differs between `a` and `b`.
+fn last_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    let remainder = a.len() % CHUNK_SIZE;
+    let chunk = a
+        .rchunks_exact(CHUNK_SIZE)
+        .zip(b.rchunks_exact(CHUNK_SIZE))
+        .position(|(a, b)| chunks_differ(a, b));
+    let end = chunk.map_or(remainder, |c| a.len() - c * CHUNK_SIZE);
+    let start = chunk.map_or(0, |_| end - CHUNK_SIZE);
+    a[start..end]
+        .iter()
+        .zip(&b[start..end])
+        .rposition(|(a, b)| a != b)
+        .map(|i| start + i)
+}
+
+/// Returns the range of blocks spanning all the differences between `before` and `after`, or
+/// `None` if they are equal.
+///
+/// # Panics
+/// If the slices are not the same length.
+pub fn changed_block_span<N: PrimInt>(
+    before: &[Unaligned<N>],
+    after: &[Unaligned<N>],
+) -> Option<RangeInclusive<usize>> {
+    assert_eq!(before.len(), after.len(), "rows must be the same length");
+    let block_size = std::mem::size_of::<N>();
+    // SAFETY: Unaligned<N> has the same size as N and an alignment of 1, and primitive integers
+    // have no padding bytes
+    let (a, b) = unsafe {
+        (
+            std::slice::from_raw_parts(before.as_ptr() as *const u8, size_of_val(before)),
+            std::slice::from_raw_parts(after.as_ptr() as *const u8, size_of_val(after)),
+        )
+    };
+
+    let first = first_difference(a, b)?;
+    let last = last_difference(a, b).unwrap();
+    Some(first / block_size..=last / block_size)
+}
@@ -1 +1,3 @@
+pub mod atomic_write;
+pub mod diff_span;
 pub mod unaligned;
@@ -2,6 +2,22 @@
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Unaligned<N>(pub N);
 
+impl<N: Copy> Unaligned<N> {
+    /// Reads the value with an explicit unaligned read.
+    #[inline]
+    pub fn read(&self) -> N {
+        // SAFETY: The pointer is valid for reads, and read_unaligned has no alignment requirement
+        unsafe { std::ptr::addr_of!(self.0).read_unaligned() }
+    }
+
+    /// Writes the value with an explicit unaligned write.
+    #[inline]
+    pub fn write(&mut self, value: N) {
+        // SAFETY: The pointer is valid for writes, and write_unaligned has no alignment requirement
+        unsafe { std::ptr::addr_of_mut!(self.0).write_unaligned(value) }
+    }
+}
+
 pub trait ToUnaligned {
     fn to_unaligned(&self) -> &Unaligned<Self>
     where
@@ -65,6 +81,6 @@ impl<T: Copy, const N: usize> ToUnalignedArray<T, N> for [T; N] {
     }
 
     fn to_unaligned_fixed_slice_mut(&mut self) -> &mut [Unaligned<T>; N] {
-        unsafe { unsafe { std::mem::transmute(self) } }
+        unsafe { std::mem::transmute(self) }
     }
 }
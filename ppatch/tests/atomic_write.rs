@@ -0,0 +1,126 @@
+//! Classification and application of writes to live memory by [`ppatch::util::atomic_write`].
+
+use ppatch::{
+    fields::write_field_bytes,
+    patchers::{base::RowPatcher, linked_list::LinkedListPatcher},
+    testing::{FieldLayout, FieldSpec},
+    util::{
+        atomic_write::{classify_write, xor_blocks, xor_bytes, WriteStats, WriteStrategy},
+        unaligned::Unaligned,
+    },
+};
+
+/// Returns a 16 byte buffer which starts on an 8 byte boundary.
+fn aligned_buffer(words: &mut [u64; 2]) -> &mut [u8] {
+    // SAFETY: u8 has no alignment and the buffer is 16 bytes long
+    unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, 16) }
+}
+
+#[test]
+fn classify_aligned() {
+    assert_eq!(classify_write(0x1000, 4), WriteStrategy::Atomic32);
+    assert_eq!(classify_write(0x1001, 2), WriteStrategy::Atomic32);
+    assert_eq!(classify_write(0x1003, 1), WriteStrategy::Atomic32);
+    assert_eq!(classify_write(0x1000, 8), WriteStrategy::Atomic64);
+    assert_eq!(classify_write(0x1002, 4), WriteStrategy::Atomic64);
+    assert_eq!(classify_write(0x1004, 1), WriteStrategy::Atomic32);
+}
+
+#[test]
+fn classify_straddling() {
+    // A 4 byte field straddling an 8 byte boundary
+    assert_eq!(classify_write(0x1006, 4), WriteStrategy::Plain);
+    assert_eq!(classify_write(0x1007, 2), WriteStrategy::Plain);
+    // Aligned but too large for a single word
+    assert_eq!(classify_write(0x1000, 9), WriteStrategy::Plain);
+    assert_eq!(classify_write(0x1000, 16), WriteStrategy::Plain);
+}
+
+#[test]
+fn classify_empty_and_end() {
+    assert_eq!(classify_write(0x1000, 0), WriteStrategy::Plain);
+    assert_eq!(classify_write(usize::MAX, 0), WriteStrategy::Plain);
+    assert_eq!(classify_write(usize::MAX, 1), WriteStrategy::Atomic32);
+    assert_eq!(classify_write(usize::MAX - 7, 8), WriteStrategy::Atomic64);
+    assert_eq!(classify_write(usize::MAX, 2), WriteStrategy::Plain);
+}
+
+/// Bits and bytes around the change are left intact, whatever the strategy.
+#[test]
+fn xor_bytes_keeps_neighbors() {
+    let mut words = [0; 2];
+    let bytes = aligned_buffer(&mut words);
+    bytes.copy_from_slice(&[0xA5; 16]);
+
+    assert_eq!(xor_bytes(bytes, 1, &[0x0F]), WriteStrategy::Atomic32);
+    assert_eq!(xor_bytes(bytes, 5, &[0xFF, 0x01]), WriteStrategy::Atomic32);
+    assert_eq!(
+        xor_bytes(bytes, 10, &[0x10, 0, 0x80]),
+        WriteStrategy::Atomic64
+    );
+    assert_eq!(xor_bytes(bytes, 7, &[0x02, 0x04]), WriteStrategy::Plain);
+    assert_eq!(
+        bytes,
+        [
+            0xA5, 0xAA, 0xA5, 0xA5, 0xA5, 0x5A, 0xA4, 0xA7, //
+            0xA1, 0xA5, 0xB5, 0xA5, 0x25, 0xA5, 0xA5, 0xA5,
+        ]
+    );
+}
+
+/// Words which would extend past either end of the slice are written with plain writes.
+#[test]
+fn xor_bytes_at_slice_ends() {
+    let mut words = [0; 2];
+    let bytes = &mut aligned_buffer(&mut words)[2..14];
+
+    assert_eq!(xor_bytes(bytes, 0, &[1]), WriteStrategy::Plain);
+    assert_eq!(xor_bytes(bytes, 11, &[1]), WriteStrategy::Plain);
+    assert_eq!(xor_bytes(bytes, 2, &[1, 1, 1, 1]), WriteStrategy::Atomic32);
+    assert_eq!(bytes, [1, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn xor_blocks_changed_bytes_only() {
+    let mut words = [0; 2];
+    let bytes = aligned_buffer(&mut words);
+    // SAFETY: Unaligned<u32> has an alignment of 1 and the buffer is 16 bytes long
+    let live =
+        unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut Unaligned<u32>, 4) };
+
+    // Only the last byte of the first block and the first of the second one change
+    let diffs = [0xFF00_0000u32.to_le(), 0xFFu32.to_le()];
+    assert_eq!(xor_blocks(live, 0, &diffs), Some(WriteStrategy::Atomic64));
+    assert_eq!(xor_blocks(live, 1, &[0; 3]), None);
+    let values: Vec<_> = live.iter().map(|b| b.read()).collect();
+    assert_eq!(values, [diffs[0], diffs[1], 0, 0]);
+}
+
+/// Patchers count the strategy of each write made when restoring patches.
+#[test]
+fn restore_records_write_strategies() {
+    use FieldSpec::*;
+    let layout = FieldLayout::<u32>::new(&[Bytes(1), Bytes(1), Bytes(2), Bytes(12)]);
+    let mut patcher = LinkedListPatcher::<u32>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut words = [0; 2];
+    let bytes = aligned_buffer(&mut words);
+    // SAFETY: Unaligned<u32> has an alignment of 1 and the buffer is 16 bytes long
+    let live =
+        unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut Unaligned<u32>, 4) };
+
+    let before = live.to_vec();
+    for &field_start in &layout.field_starts {
+        write_field_bytes(live, &layout.blocks, field_start, &[0xFF; 12]);
+    }
+    let id = patcher.create_patch(&before, live).unwrap();
+    patcher.restore_patch(id, live).unwrap();
+    assert_eq!(live, before);
+    assert_eq!(
+        patcher.write_stats(),
+        WriteStats {
+            atomic32: 3,
+            atomic64: 0,
+            plain: 1,
+        }
+    );
+}
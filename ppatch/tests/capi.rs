@@ -0,0 +1,172 @@
+//! Driving a patch manager through the C API of [`ppatch::ffi`].
+
+use std::{
+    ffi::{c_char, CString},
+    ptr,
+};
+
+use ppatch::{
+    ffi::{
+        ppatch_create_patch, ppatch_manager_free, ppatch_manager_new, ppatch_restore,
+        PPATCH_GAME_AC6, PPATCH_GAME_DS3, PPATCH_GAME_ER,
+    },
+    Game,
+};
+
+const HEADER: &str = include_str!("../include/ppatch.h");
+
+/// Returns a param type of the embedded field blocks of the default game, the length of its rows
+/// and the offset and mask of the first block of a field.
+fn param() -> (CString, usize, usize, u32) {
+    let repo = Game::DEFAULT.field_block_repo().unwrap();
+    let (param_type, blocks) =
+        repo.iter().min_by_key(|(param_type, _)| param_type.as_str()).unwrap();
+    let last = blocks.iter().map(|b| b.offset as usize).max().unwrap();
+    let first = &blocks[0];
+    (
+        CString::new(param_type.as_str()).unwrap(),
+        4 * (last + 1),
+        first.offset as usize,
+        first.mask,
+    )
+}
+
+fn game_value(game: Game) -> u32 {
+    match game {
+        Game::Ds3 => PPATCH_GAME_DS3,
+        Game::Er => PPATCH_GAME_ER,
+        Game::Ac6 => PPATCH_GAME_AC6,
+    }
+}
+
+#[test]
+fn managers() {
+    for game in Game::ALL {
+        assert_eq!(game_value(game), game as u32);
+        let manager = ppatch_manager_new(game_value(game));
+        assert_eq!(manager.is_null(), !game.is_embedded());
+        unsafe { ppatch_manager_free(manager) };
+    }
+    assert!(ppatch_manager_new(3).is_null());
+    unsafe { ppatch_manager_free(ptr::null_mut()) };
+}
+
+#[test]
+fn create_and_restore() {
+    let (name, len, offset, mask) = param();
+    let manager = ppatch_manager_new(game_value(Game::DEFAULT));
+
+    let mut live = vec![0u8; len];
+    let before = live.clone();
+    live[4 * offset..4 * offset + 4].copy_from_slice(&mask.to_ne_bytes());
+    unsafe {
+        let create = |row_id, live: &[u8]| {
+            ppatch_create_patch(
+                manager,
+                name.as_ptr(),
+                row_id,
+                before.as_ptr(),
+                live.as_ptr(),
+                len,
+            )
+        };
+        let first = create(10, &live);
+        let second = create(20, &live);
+        assert!(first > 0 && second > first, "{first}, {second}");
+
+        assert_eq!(ppatch_restore(manager, first, live.as_mut_ptr(), len), 0);
+        assert_eq!(live, before);
+        // Restored and unknown handles
+        assert_eq!(ppatch_restore(manager, first, live.as_mut_ptr(), len), -202);
+        assert_eq!(ppatch_restore(manager, 1000, live.as_mut_ptr(), len), -202);
+        ppatch_manager_free(manager);
+    }
+}
+
+#[test]
+fn failures() {
+    let (name, len, _, _) = param();
+    let manager = ppatch_manager_new(game_value(Game::DEFAULT));
+    let row = vec![0u8; len];
+    let mut live = row.clone();
+    unsafe {
+        let create = |manager, name: *const c_char, row: *const u8, len| {
+            ppatch_create_patch(manager, name, 10, row, row, len)
+        };
+        let name = name.as_ptr();
+        assert_eq!(create(ptr::null_mut(), name, row.as_ptr(), len), -801);
+        assert_eq!(create(manager, ptr::null(), row.as_ptr(), len), -801);
+        assert_eq!(create(manager, name, ptr::null(), len), -801);
+        assert_eq!(create(manager, c"\xFF".as_ptr(), row.as_ptr(), len), -802);
+        assert_eq!(
+            create(manager, c"NOT_A_PARAM_ST".as_ptr(), row.as_ptr(), len),
+            -240
+        );
+        assert_eq!(create(manager, name, row.as_ptr(), len - 1), -110);
+
+        assert_eq!(
+            ppatch_restore(ptr::null_mut(), 1, live.as_mut_ptr(), len),
+            -801
+        );
+        assert_eq!(ppatch_restore(manager, 1, ptr::null_mut(), len), -801);
+        assert_eq!(ppatch_restore(manager, 1, live.as_mut_ptr(), 2), -110);
+        ppatch_manager_free(manager);
+    }
+}
+
+/// Rows whose length differs from the one of their first patch are rejected, and the patch is
+/// kept for a retry with the right length.
+#[test]
+fn row_size_mismatch() {
+    let (name, len, offset, mask) = param();
+    let manager = ppatch_manager_new(game_value(Game::DEFAULT));
+
+    let mut live = vec![0u8; len + 4];
+    let before = live.clone();
+    live[4 * offset..4 * offset + 4].copy_from_slice(&mask.to_ne_bytes());
+    unsafe {
+        let create = |live: &[u8], len| {
+            let (before, after) = (before.as_ptr(), live.as_ptr());
+            ppatch_create_patch(manager, name.as_ptr(), 10, before, after, len)
+        };
+        // A first patch too short for the field blocks doesn't set the length of the row
+        assert_eq!(create(&live, len - 4), -131);
+        let handle = create(&live, len);
+        assert!(handle > 0, "{handle}");
+        assert_eq!(create(&live, len + 4), -241);
+
+        let restore =
+            |live: &mut [u8], len| ppatch_restore(manager, handle, live.as_mut_ptr(), len);
+        assert_eq!(restore(&mut live, len + 4), -241);
+        assert_eq!(restore(&mut live, len - 4), -241);
+        assert_ne!(live, before);
+        assert_eq!(restore(&mut live, len), 0);
+        assert_eq!(live, before);
+        ppatch_manager_free(manager);
+    }
+}
+
+#[test]
+fn header_declares_api() {
+    for declaration in [
+        "PpatchManager *ppatch_manager_new(uint32_t game);",
+        "void ppatch_manager_free(PpatchManager *manager);",
+        "int64_t ppatch_create_patch(PpatchManager *manager,",
+        "int32_t ppatch_restore(PpatchManager *manager, int64_t handle, uint8_t *live, uintptr_t len);",
+    ] {
+        assert!(HEADER.contains(declaration), "{declaration}");
+    }
+    for code in ["-131", "-240", "-241", "-803"] {
+        assert!(HEADER.contains(&format!(" * {code}: ")), "{code}");
+    }
+    for (name, value) in [
+        ("PPATCH_GAME_DS3", PPATCH_GAME_DS3),
+        ("PPATCH_GAME_ER", PPATCH_GAME_ER),
+        ("PPATCH_GAME_AC6", PPATCH_GAME_AC6),
+    ] {
+        assert!(
+            HEADER.contains(&format!("#define {name} {value}\n")),
+            "{name}"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! [`Session`] marshalling and errors, through a mock [`CeluaBackend`].
+
+use std::{
+    cell::RefCell,
+    ffi::{c_int, CStr},
+};
+
+use ppatch::celua::{CeluaBackend, CeluaError, Session};
+
+/// Call received by the mock backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Call {
+    Execute {
+        code: String,
+        parameter: usize,
+        is_async: bool,
+    },
+    ByReference {
+        ref_id: c_int,
+        parameters: Vec<usize>,
+        is_async: bool,
+    },
+}
+
+/// Backend connecting to the server `"ppatch"` only, with the global functions `FUNCTIONS`.
+#[derive(Debug, Default)]
+struct MockBackend {
+    calls: RefCell<Vec<Call>>,
+}
+
+const FUNCTIONS: [&str; 2] = ["applyPatch", "restorePatch"];
+
+impl CeluaBackend for MockBackend {
+    fn initialize(&self, name: &CStr) -> bool {
+        name == c"ppatch"
+    }
+
+    fn execute_function(&self, code: &CStr, parameter: usize, is_async: bool) -> usize {
+        self.calls.borrow_mut().push(Call::Execute {
+            code: code.to_str().unwrap().to_owned(),
+            parameter,
+            is_async,
+        });
+        parameter + 1
+    }
+
+    fn function_reference(&self, name: &CStr) -> c_int {
+        // CE returns 0 or a negative reference for unknown functions
+        match FUNCTIONS.iter().position(|f| f.as_bytes() == name.to_bytes()) {
+            Some(i) => i as c_int + 1,
+            None if name.to_bytes().is_empty() => -1,
+            None => 0,
+        }
+    }
+
+    fn execute_function_by_reference(
+        &self,
+        ref_id: c_int,
+        parameters: &[usize],
+        is_async: bool,
+    ) -> usize {
+        self.calls.borrow_mut().push(Call::ByReference {
+            ref_id,
+            parameters: parameters.to_vec(),
+            is_async,
+        });
+        parameters.iter().sum()
+    }
+}
+
+fn session() -> Session<MockBackend> {
+    Session::with_backend(MockBackend::default(), "ppatch").unwrap()
+}
+
+#[test]
+fn initialize() {
+    assert!(Session::with_backend(MockBackend::default(), "ppatch").is_ok());
+    assert_eq!(
+        Session::with_backend(MockBackend::default(), "other").unwrap_err(),
+        CeluaError::InitializeFailed {
+            server_name: "other".to_owned()
+        }
+    );
+    assert_eq!(
+        Session::with_backend(MockBackend::default(), "ppatch\0").unwrap_err(),
+        CeluaError::InteriorNul { position: 6 }
+    );
+}
+
+#[test]
+fn execute() {
+    let session = session();
+    assert_eq!(session.execute("return parameter + 1", 41), Ok(42));
+    assert_eq!(session.execute_async("print(parameter)", 7), Ok(8));
+    assert_eq!(
+        session.execute("print('a')\0print('b')", 0),
+        Err(CeluaError::InteriorNul { position: 10 })
+    );
+    assert_eq!(
+        *session.backend().calls.borrow(),
+        [
+            Call::Execute {
+                code: "return parameter + 1".to_owned(),
+                parameter: 41,
+                is_async: false,
+            },
+            Call::Execute {
+                code: "print(parameter)".to_owned(),
+                parameter: 7,
+                is_async: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn function_refs() {
+    let session = session();
+    let restore = session.function_ref("restorePatch").unwrap();
+    assert_eq!(restore.ref_id(), 2);
+    assert_eq!(restore.call(&[1, 2, 3], true), 6);
+    assert_eq!(restore.call(&[], false), 0);
+    assert_eq!(
+        *session.backend().calls.borrow(),
+        [
+            Call::ByReference {
+                ref_id: 2,
+                parameters: vec![1, 2, 3],
+                is_async: true,
+            },
+            Call::ByReference {
+                ref_id: 2,
+                parameters: vec![],
+                is_async: false,
+            },
+        ]
+    );
+
+    for unknown in ["missing", ""] {
+        assert_eq!(
+            session.function_ref(unknown).unwrap_err(),
+            CeluaError::UnknownFunction {
+                name: unknown.to_owned()
+            }
+        );
+    }
+    assert_eq!(
+        session.function_ref("apply\0Patch").unwrap_err(),
+        CeluaError::InteriorNul { position: 5 }
+    );
+}
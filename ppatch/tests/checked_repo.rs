@@ -0,0 +1,135 @@
+//! Loading field block repos with [`load_fb_repo_checked`].
+
+use std::hash::Hasher;
+
+use ppatch::field_metadata::{
+    load_fb_repo_checked, serialize_fb_repo, serialize_fb_repo_for_game, Block, FbRepoError,
+    FieldBlock, FieldBlockRepo, LoadFbRepoError,
+};
+
+const HEADER_SIZE: usize = 32;
+
+fn repo_bytes(game: &str) -> Vec<u8> {
+    let mut repo = FieldBlockRepo::new();
+    for (i, param_type) in ["A_PARAM_ST", "B_PARAM_ST", "C_PARAM_ST"].into_iter().enumerate() {
+        let blocks = (0..=i as u16).map(|offset| FieldBlock {
+            field_start: offset,
+            offset,
+            mask: Block::MAX,
+        });
+        repo.insert(param_type.to_owned(), blocks.collect());
+    }
+    serialize_fb_repo_for_game(&repo, game).into_vec()
+}
+
+/// Runs `f` on a copy of `bytes` aligned like the archive.
+fn with_aligned<R>(bytes: &[u8], f: impl FnOnce(&[u8]) -> R) -> R {
+    let mut buf = vec![0u128; bytes.len().div_ceil(16)];
+    // SAFETY: The buffer is at least `bytes.len()` bytes long, and any bytes are valid u8s
+    let aligned =
+        unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, bytes.len()) };
+    aligned.copy_from_slice(bytes);
+    f(aligned)
+}
+
+fn load(bytes: &[u8], game: &str) -> Result<usize, FbRepoError> {
+    with_aligned(bytes, |bytes| {
+        load_fb_repo_checked(bytes, game).map(|repo| repo.len())
+    })
+}
+
+#[test]
+fn valid_repo() {
+    let bytes = repo_bytes("ER");
+    with_aligned(&bytes, |bytes| {
+        let repo = load_fb_repo_checked(bytes, "ER").unwrap();
+        assert_eq!(repo.len(), 3);
+        assert_eq!(repo.get("C_PARAM_ST").unwrap()[2].offset, 2);
+    });
+}
+
+#[test]
+fn wrong_game() {
+    assert_eq!(
+        load(&repo_bytes("DS3"), "ER"),
+        Err(FbRepoError::GameMismatch {
+            expected: "ER".to_owned(),
+            found: "DS3".to_owned(),
+        })
+    );
+    let untagged = serialize_fb_repo(&FieldBlockRepo::new());
+    assert_eq!(
+        load(&untagged, "AC6"),
+        Err(FbRepoError::GameMismatch {
+            expected: "AC6".to_owned(),
+            found: String::new(),
+        })
+    );
+    let message = load(&repo_bytes("DS3"), "ER").unwrap_err().to_string();
+    assert_eq!(
+        message,
+        "field block repo was generated for game \"DS3\", expected \"ER\""
+    );
+}
+
+#[test]
+fn truncated() {
+    let bytes = repo_bytes("ER");
+    for len in [0, 4, HEADER_SIZE - 1] {
+        assert_eq!(
+            load(&bytes[..len], "ER"),
+            Err(FbRepoError::Header(LoadFbRepoError::NotARepo))
+        );
+    }
+    for len in HEADER_SIZE..bytes.len() {
+        assert_eq!(
+            load(&bytes[..len], "ER"),
+            Err(FbRepoError::ChecksumMismatch),
+            "{len}"
+        );
+    }
+}
+
+#[test]
+fn flipped_bits() {
+    let bytes = repo_bytes("ER");
+    for i in HEADER_SIZE..bytes.len() {
+        for bit in [0, 5] {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 1 << bit;
+            assert_eq!(
+                load(&corrupted, "ER"),
+                Err(FbRepoError::ChecksumMismatch),
+                "{i}"
+            );
+        }
+    }
+
+    let mut corrupted = bytes.clone();
+    corrupted[4] ^= 1;
+    assert!(matches!(
+        load(&corrupted, "ER"),
+        Err(FbRepoError::Header(
+            LoadFbRepoError::UnsupportedVersion { .. }
+        ))
+    ));
+    let mut corrupted = bytes.clone();
+    corrupted[HEADER_SIZE - 1] ^= 1;
+    assert_eq!(load(&corrupted, "ER"), Err(FbRepoError::ChecksumMismatch));
+}
+
+#[test]
+fn invalid_archive_with_valid_checksum() {
+    let mut bytes = repo_bytes("ER");
+    // Point the root of the archive out of bounds, then fix up the hash
+    let len = bytes.len();
+    bytes[len - 8..].fill(0x7F);
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(&bytes[HEADER_SIZE..]);
+    bytes[24..32].copy_from_slice(&hasher.finish().to_le_bytes());
+
+    assert!(matches!(
+        load(&bytes, "ER"),
+        Err(FbRepoError::InvalidArchive(_))
+    ));
+}
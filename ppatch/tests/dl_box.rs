@@ -0,0 +1,150 @@
+//! Owned allocations of a [`DLAllocator`] with [`DLBox`] and [`DLBuf`], with a [`MockAllocator`]
+//! in place of the game's.
+
+use std::{cell::Cell, ptr::NonNull, rc::Rc};
+
+use ppatch::from::allocator::{AllocError, DLAllocator, DLBox, DLBuf, MockAllocator};
+
+/// Counts the drops of its values.
+#[derive(Debug)]
+struct Counted(Rc<Cell<usize>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn box_values() {
+    let mut allocator = MockAllocator::new();
+    let drops = Rc::new(Cell::new(0));
+    unsafe {
+        let mut boxed = DLBox::new_in([1u64, 2], &mut allocator).unwrap();
+        boxed[1] = 3;
+        assert_eq!(*boxed, [1, 3]);
+        assert_eq!(
+            &*boxed as *const _ as usize % std::mem::align_of::<u64>(),
+            0
+        );
+
+        let counted = DLBox::new_in(Counted(drops.clone()), &mut allocator).unwrap();
+        assert_eq!(counted.allocator().heap_allocation_count(), 2);
+        drop(counted);
+        drop(boxed);
+    }
+    assert_eq!(drops.get(), 1);
+    assert_eq!(allocator.heap_allocation_count(), 0);
+
+    // Zero-sized values still get a block
+    let unit = unsafe { DLBox::new_in((), &mut allocator) }.unwrap();
+    assert_eq!(unit.allocator().heap_allocation_count(), 1);
+    drop(unit);
+    assert_eq!(allocator.heap_allocation_count(), 0);
+}
+
+#[test]
+fn box_raw_ownership() {
+    let mut allocator = MockAllocator::new();
+    let drops = Rc::new(Cell::new(0));
+
+    // Handed over to the game, which owns the value until it is taken back
+    let boxed = unsafe { DLBox::new_in(Counted(drops.clone()), &mut allocator) }.unwrap();
+    let ptr: NonNull<Counted> = boxed.into_raw();
+    assert_eq!(drops.get(), 0);
+    assert_eq!(allocator.heap_allocation_count(), 1);
+    assert_eq!(
+        allocator.block_size(ptr.as_ptr().cast()),
+        std::mem::size_of::<Counted>()
+    );
+
+    drop(unsafe { DLBox::from_raw_in(ptr, &mut allocator) });
+    assert_eq!(drops.get(), 1);
+    assert_eq!(allocator.heap_allocation_count(), 0);
+}
+
+#[test]
+fn buffers() {
+    let mut allocator = MockAllocator::new();
+    unsafe {
+        let mut buf = DLBuf::new_in(24, 64, &mut allocator).unwrap();
+        assert_eq!(*buf, [0; 24]);
+        assert_eq!(buf.as_ptr() as usize % 64, 0);
+        buf[4..8].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(buf[..8], [0, 0, 0, 0, 1, 2, 3, 4]);
+
+        let row = DLBuf::from_slice_in(b"row data", 4, &mut allocator).unwrap();
+        assert_eq!(*row, *b"row data");
+        assert_eq!(row.allocator().heap_allocation_count(), 2);
+
+        let ptr = row.into_raw();
+        drop(buf);
+        assert_eq!(allocator.block_size(ptr.as_ptr().cast()), 8);
+        allocator.deallocate(ptr.as_ptr().cast());
+
+        let empty = DLBuf::new_in(0, 1, &mut allocator).unwrap();
+        assert!(empty.is_empty());
+    }
+    assert_eq!(allocator.heap_allocation_count(), 0);
+}
+
+#[test]
+fn allocation_failures() {
+    let mut allocator = MockAllocator::new();
+    let drops = Rc::new(Cell::new(0));
+    allocator.fail_allocations(true);
+    unsafe {
+        let error = DLBox::new_in(Counted(drops.clone()), &mut allocator).unwrap_err();
+        assert_eq!(
+            error,
+            AllocError {
+                size: std::mem::size_of::<Counted>(),
+                align: std::mem::align_of::<Counted>(),
+            }
+        );
+        // The value is dropped rather than leaked
+        assert_eq!(drops.get(), 1);
+        assert_eq!(
+            DLBuf::new_in(16, 8, &mut allocator).unwrap_err(),
+            AllocError { size: 16, align: 8 }
+        );
+
+        allocator.fail_allocations(false);
+        assert_eq!(
+            DLBuf::from_slice_in(&[1, 2, 3], 3, &mut allocator).unwrap_err(),
+            AllocError { size: 3, align: 3 }
+        );
+        assert!(DLBuf::new_in(16, 8, &mut allocator).is_ok());
+    }
+    assert_eq!(allocator.heap_allocation_count(), 0);
+}
+
+#[test]
+fn repr_c_layout() {
+    /// Game structure holding a value and a buffer allocated by the game's allocator.
+    #[repr(C)]
+    struct Holder {
+        value: DLBox<u32, MockAllocator>,
+        bytes: DLBuf<MockAllocator>,
+    }
+
+    let mut allocator = MockAllocator::new();
+    let holder = unsafe {
+        Holder {
+            value: DLBox::new_in(7, &mut allocator).unwrap(),
+            bytes: DLBuf::from_slice_in(&[1, 2], 1, &mut allocator).unwrap(),
+        }
+    };
+    let raw = &holder as *const Holder as *const usize;
+    unsafe {
+        // Pointers come first, followed by the length of the buffer
+        assert_eq!(*(raw.read() as *const u32), 7);
+        assert_eq!(
+            raw.add(2).read(),
+            &*holder.bytes as *const [u8] as *const u8 as usize
+        );
+        assert_eq!(raw.add(3).read(), 2);
+    }
+    drop(holder);
+    assert_eq!(allocator.heap_allocation_count(), 0);
+}
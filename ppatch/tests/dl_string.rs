@@ -0,0 +1,161 @@
+//! Decoding and hashing of in-place FD4 strings, built from a mirror of their layout since they
+//! are normally only created by the game.
+
+use ppatch::{
+    from::{
+        allocator::DLAllocatorProxy,
+        layout::{DefaultLayout, Ds3Layout, ErLayout, GameLayout},
+        string::{fd4_hash, Char, DLString, FD4BasicHashString},
+    },
+    Game,
+};
+
+/// Layout of a [`DLString`] with its characters stored in place, in games other than DS3.
+#[repr(C)]
+struct RawDLString {
+    allocator: &'static usize,
+    in_place: [u8; 16],
+    len: usize,
+    capacity: usize,
+}
+
+/// Layout of a [`DLString`] in DS3, whose allocator is last.
+#[repr(C)]
+struct RawDs3DLString {
+    in_place: [u8; 16],
+    len: usize,
+    capacity: usize,
+    allocator: &'static usize,
+}
+
+/// Layout of a [`FD4BasicHashString`].
+#[repr(C)]
+struct RawHashString {
+    vtable: usize,
+    string: RawDLString,
+    unk_08: usize,
+    hash: u32,
+    requires_rehash: bool,
+}
+
+static ALLOCATOR_VTABLE: usize = 0;
+
+fn raw_string<C: Char>(chars: &[C]) -> RawDLString {
+    let capacity = 16 / std::mem::size_of::<C>() - 1;
+    assert!(chars.len() <= capacity, "string is not stored in place");
+    let mut in_place = [0; 16];
+    // SAFETY: Characters are plain integers
+    let bytes = unsafe {
+        std::slice::from_raw_parts(chars.as_ptr() as *const u8, std::mem::size_of_val(chars))
+    };
+    in_place[..bytes.len()].copy_from_slice(bytes);
+    RawDLString {
+        allocator: &ALLOCATOR_VTABLE,
+        in_place,
+        len: chars.len(),
+        capacity,
+    }
+}
+
+fn dl_string<C: Char>(chars: &[C]) -> DLString<C, DLAllocatorProxy, ErLayout> {
+    let raw = raw_string(chars);
+    assert_eq!(
+        std::mem::size_of_val(&raw),
+        std::mem::size_of::<DLString<C, DLAllocatorProxy, ErLayout>>()
+    );
+    // SAFETY: Same layout, and the string is in place so it doesn't need an allocator
+    unsafe { std::mem::transmute_copy(&std::mem::ManuallyDrop::new(raw)) }
+}
+
+fn hash_string<C: Char>(
+    chars: &[C],
+    hash: u32,
+    rehash: bool,
+) -> FD4BasicHashString<C, DLAllocatorProxy, ErLayout> {
+    let raw = RawHashString {
+        vtable: 0,
+        string: raw_string(chars),
+        unk_08: 0,
+        hash,
+        requires_rehash: rehash,
+    };
+    assert_eq!(
+        std::mem::size_of_val(&raw),
+        std::mem::size_of::<FD4BasicHashString<C, DLAllocatorProxy, ErLayout>>()
+    );
+    // SAFETY: Same layout, and the vtable is never called
+    unsafe { std::mem::transmute_copy(&std::mem::ManuallyDrop::new(raw)) }
+}
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().collect()
+}
+
+#[test]
+fn decode_strings() {
+    let narrow = dl_string(b"EquipParamGoods");
+    assert_eq!(narrow.to_string_lossy(), "EquipParamGoods");
+    assert!(narrow.eq_str("EquipParamGoods"));
+    assert!(narrow != "EquipParamGood");
+    assert_eq!(dl_string(&[b'a', 0xFF]).to_string_lossy(), "a\u{FFFD}");
+
+    let wide = dl_string(&utf16("Épée"));
+    assert_eq!(wide.len(), 4);
+    assert_eq!(wide.to_string_lossy(), "Épée");
+    assert!(wide == "Épée");
+    assert!(!wide.eq_str("Epee"));
+    assert!(!wide.eq_str("Épée!"));
+    assert_eq!(dl_string(&[0xD800u16]).to_string_lossy(), "\u{FFFD}");
+}
+
+#[test]
+fn hash_is_case_folded() {
+    assert_eq!(fd4_hash::<u8>(b""), 0);
+    assert_eq!(fd4_hash(b"ab"), 97 * 137 + 98);
+    assert_eq!(fd4_hash(b"AB"), fd4_hash(b"ab"));
+    assert_eq!(fd4_hash(&utf16("Ab")), fd4_hash(b"aB"));
+    assert_ne!(fd4_hash(b"ab"), fd4_hash(b"ba"));
+}
+
+#[test]
+fn hash_is_recomputed() {
+    let name = utf16("Param");
+    let cached = hash_string(&name, 1234, false);
+    assert_eq!(cached.hash(), 1234);
+
+    let stale = hash_string(&name, 1234, true);
+    assert!(stale.requires_rehash());
+    assert_eq!(stale.hash(), fd4_hash(&name));
+    assert!(!stale.requires_rehash());
+
+    // Modifying the string invalidates the hash, which ignores case
+    let mut modified = hash_string(&name, fd4_hash(&name), false);
+    modified[0] = b'p' as u16;
+    assert!(modified.requires_rehash());
+    assert_eq!(modified.hash(), fd4_hash(&name));
+    modified[1] = b'e' as u16;
+    assert_ne!(modified.hash(), fd4_hash(&name));
+    assert_eq!(modified.to_string_lossy(), "peram");
+}
+
+#[test]
+fn layout_of_each_game() {
+    assert_eq!(DefaultLayout::GAME, Game::DEFAULT);
+
+    let raw = raw_string(b"Param");
+    let raw = RawDs3DLString {
+        in_place: raw.in_place,
+        len: raw.len,
+        capacity: raw.capacity,
+        allocator: raw.allocator,
+    };
+    assert_eq!(
+        std::mem::size_of_val(&raw),
+        std::mem::size_of::<DLString<u8, DLAllocatorProxy, Ds3Layout>>()
+    );
+    // SAFETY: Same layout, and the string is in place so it doesn't need an allocator
+    let ds3: DLString<u8, DLAllocatorProxy, Ds3Layout> =
+        unsafe { std::mem::transmute_copy(&std::mem::ManuallyDrop::new(raw)) };
+    assert_eq!(ds3.len(), 5);
+    assert_eq!(ds3.to_string_lossy(), "Param");
+}
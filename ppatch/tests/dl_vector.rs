@@ -0,0 +1,163 @@
+//! Growing and shrinking a [`DLVector`] through its allocator, with a [`MockAllocator`] in place
+//! of the game's.
+
+use std::{cell::Cell, rc::Rc};
+
+use ppatch::from::{
+    allocator::{DLAllocator, MockAllocator},
+    layout::{Ds3Layout, ErLayout},
+    vector::DLVector,
+};
+
+/// Layout of a [`DLVector`] in games other than DS3.
+#[repr(C)]
+struct RawDLVector<T> {
+    allocator: MockAllocator,
+    begin: *mut T,
+    end: *mut T,
+    buffer_end: *mut T,
+}
+
+/// Layout of a [`DLVector`] in DS3, whose allocator is last.
+#[repr(C)]
+struct RawDs3DLVector<T> {
+    begin: *mut T,
+    end: *mut T,
+    buffer_end: *mut T,
+    allocator: MockAllocator,
+}
+
+fn empty<T>() -> DLVector<T, MockAllocator, ErLayout> {
+    let raw = RawDLVector::<T> {
+        allocator: MockAllocator::new(),
+        begin: std::ptr::null_mut(),
+        end: std::ptr::null_mut(),
+        buffer_end: std::ptr::null_mut(),
+    };
+    assert_eq!(
+        std::mem::size_of_val(&raw),
+        std::mem::size_of::<DLVector<T, MockAllocator, ErLayout>>()
+    );
+    // SAFETY: Same layout, and the vector has no buffer yet
+    unsafe { std::mem::transmute_copy(&std::mem::ManuallyDrop::new(raw)) }
+}
+
+#[test]
+fn push_and_grow() {
+    let mut vector = empty::<u64>();
+    assert_eq!(vector.len(), 0);
+    assert!(vector.is_empty());
+
+    unsafe {
+        for i in 0..5 {
+            vector.push_with(i);
+        }
+    }
+    assert_eq!(*vector, [0, 1, 2, 3, 4]);
+    // The capacity doubles, and each reallocation frees the previous buffer
+    assert_eq!(vector.capacity(), 8);
+    assert_eq!(vector.allocator().heap_allocation_count(), 1);
+    assert_eq!(
+        vector.allocator().block_size(vector.as_ptr().cast()),
+        8 * std::mem::size_of::<u64>()
+    );
+    assert_eq!(vector.as_ptr() as usize % std::mem::align_of::<u64>(), 0);
+
+    unsafe { vector.reserve(3) };
+    assert_eq!(vector.capacity(), 8);
+    unsafe { vector.reserve(10) };
+    assert_eq!(vector.capacity(), 16);
+    assert_eq!(*vector, [0, 1, 2, 3, 4]);
+    assert_eq!(vector.allocator().heap_allocation_count(), 1);
+}
+
+#[test]
+fn shrink() {
+    let mut vector = empty::<u32>();
+    unsafe {
+        for i in 10..15 {
+            vector.push_with(i);
+        }
+        assert_eq!(vector.swap_remove(1), 11);
+        assert_eq!(*vector, [10, 14, 12, 13]);
+        assert_eq!(vector.swap_remove(3), 13);
+        assert_eq!(*vector, [10, 14, 12]);
+        assert_eq!(vector.pop(), Some(12));
+        assert_eq!(*vector, [10, 14]);
+
+        // The buffer is kept, so pushing again doesn't allocate
+        let capacity = vector.capacity();
+        vector.clear_in_place();
+        assert!(vector.is_empty());
+        assert_eq!(vector.pop(), None);
+        assert_eq!(vector.capacity(), capacity);
+        vector.push_with(1);
+        assert_eq!(*vector, [1]);
+    }
+    assert_eq!(vector.allocator().heap_allocation_count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "swap_remove index 2 out of bounds for length 2")]
+fn swap_remove_out_of_bounds() {
+    let mut vector = empty::<u8>();
+    unsafe {
+        vector.push_with(1);
+        vector.push_with(2);
+        vector.swap_remove(2);
+    }
+}
+
+/// Counts the drops of its values.
+#[derive(Debug)]
+struct Counted(Rc<Cell<usize>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn elements_are_moved_and_dropped_once() {
+    let drops = Rc::new(Cell::new(0));
+    let mut vector = empty::<Counted>();
+    unsafe {
+        for _ in 0..4 {
+            vector.push_with(Counted(drops.clone()));
+        }
+        // Growing moves the elements without dropping them
+        vector.reserve(100);
+        assert_eq!(drops.get(), 0);
+
+        drop(vector.swap_remove(0));
+        drop(vector.pop());
+        assert_eq!(drops.get(), 2);
+        vector.clear_in_place();
+    }
+    assert_eq!(drops.get(), 4);
+    assert_eq!(Rc::strong_count(&drops), 1);
+}
+
+#[test]
+fn ds3_layout() {
+    let raw = RawDs3DLVector::<u16> {
+        begin: std::ptr::null_mut(),
+        end: std::ptr::null_mut(),
+        buffer_end: std::ptr::null_mut(),
+        allocator: MockAllocator::new(),
+    };
+    assert_eq!(
+        std::mem::size_of_val(&raw),
+        std::mem::size_of::<DLVector<u16, MockAllocator, Ds3Layout>>()
+    );
+    // SAFETY: Same layout, and the vector has no buffer yet
+    let mut vector: DLVector<u16, MockAllocator, Ds3Layout> =
+        unsafe { std::mem::transmute_copy(&std::mem::ManuallyDrop::new(raw)) };
+    unsafe {
+        vector.push_with(7);
+        vector.push_with(8);
+    }
+    assert_eq!(*vector, [7, 8]);
+    assert_eq!(vector.allocator().heap_allocation_count(), 1);
+}
@@ -0,0 +1,237 @@
+//! Parsing [`EditList`]s and applying them to params with a [`ParamPatchManager`].
+
+use ppatch::{
+    apply::{ApplyEditsError, EditList, EditWarning, EditWarningKind, ParseEditListError},
+    field_metadata::{load_fb_repo, serialize_fb_repo, Block, FieldBlock, FieldBlockRepo},
+    param_builder::ParamFileBuilder,
+    param_file::ParamFile,
+    paramdex::Paramdex,
+    patchers::{
+        base::RowPatcher,
+        linked_list::LinkedListPatcher,
+        manager::{CreatePatchError, ParamPatchManager},
+        single_patch::SinglePatchPatcher,
+    },
+};
+
+const PARAM_TYPE: &str = "EDIT_TEST_PARAM_ST";
+const ROW_SIZE: usize = 16;
+
+const DEF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<PARAMDEF XmlVersion="3">
+  <ParamType>EDIT_TEST_PARAM_ST</ParamType>
+  <DataVersion>1</DataVersion>
+  <BigEndian>False</BigEndian>
+  <Unicode>True</Unicode>
+  <FormatVersion>203</FormatVersion>
+  <Fields>
+    <Field Def="s32 hp">
+      <Minimum>0</Minimum>
+      <Maximum>9999</Maximum>
+    </Field>
+    <Field Def="f32 weight">
+      <Minimum>0</Minimum>
+      <Maximum>100</Maximum>
+    </Field>
+    <Field Def="u8 flag:1" />
+    <Field Def="u8 level:7" />
+    <Field Def="s8 bonus" />
+    <Field Def="s16 offset" />
+    <Field Def="dummy8 tag[4]" />
+  </Fields>
+</PARAMDEF>"#;
+
+const META: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<PARAMMETA XmlVersion="0">
+  <Field>
+    <hp AltName="Hit Points" />
+    <weight AltName="Weight" />
+  </Field>
+</PARAMMETA>"#;
+
+/// The fixture paramdex, with the def of the edited param.
+fn paramdex() -> Paramdex {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.add_def_xml("EditTestParam", DEF).unwrap();
+    paramdex.add_meta_xml("EditTestParam", META).unwrap();
+    paramdex.compute_def_layouts(10400);
+    paramdex
+}
+
+fn repo() -> Box<[u8]> {
+    let blocks = [
+        (0, 0, 0xFFFF_FFFF),
+        (1, 1, 0xFFFF_FFFF),
+        (2, 2, 0x0000_0001),
+        (3, 2, 0x0000_00FE),
+        (4, 2, 0x0000_FF00),
+        (5, 2, 0xFFFF_0000),
+        (6, 3, 0xFFFF_FFFF),
+    ];
+    let blocks: Vec<FieldBlock<Block>> = blocks
+        .iter()
+        .map(|&(field_start, offset, mask)| FieldBlock {
+            field_start,
+            offset,
+            mask,
+        })
+        .collect();
+    let repo: FieldBlockRepo = [(PARAM_TYPE.to_owned(), blocks)].into_iter().collect();
+    serialize_fb_repo(&repo)
+}
+
+/// Param file with rows 10 and 20, filled with zeros.
+fn param_file() -> Vec<u8> {
+    let mut builder = ParamFileBuilder::new(PARAM_TYPE, ROW_SIZE);
+    builder.insert_row(10, &[0; ROW_SIZE]).unwrap();
+    builder.insert_row(20, &[0; ROW_SIZE]).unwrap();
+    builder.to_bytes()
+}
+
+const CSV: &str = "\
+ParamName,RowId,FieldName,Value
+# Buffs
+EditTestParam,10,Hit Points,500
+EditTestParam, 10 ,Weight, 2.5
+EditTestParam,20,level,200
+EditTestParam,20,flag,1
+EditTestParam,20,offset,-3
+
+EditTestParam,10,hp,12000
+EditTestParam,20,weight,-1
+UnknownParam,10,hp,1
+BitfieldTestParam,10,flagA,1
+EditTestParam,30,hp,1
+EditTestParam,10,mp,1
+EditTestParam,10,tag,1
+EditTestParam,10,bonus,lots
+";
+
+#[test]
+fn parse_csv() {
+    let list = EditList::parse_csv(CSV).unwrap();
+    assert_eq!(list.edits.len(), 13);
+    let edit = &list.edits[1];
+    assert_eq!(
+        (&*edit.param, edit.row_id, &*edit.field, &*edit.value),
+        ("EditTestParam", 10, "Weight", "2.5")
+    );
+    // The value is the rest of the line
+    let list = EditList::parse_csv("A,1,b,1,2").unwrap();
+    assert_eq!(list.edits[0].value, "1,2");
+
+    assert_eq!(
+        EditList::parse_csv("A,1,b,1\n\nA,2,b"),
+        Err(ParseEditListError::MissingColumns { line: 3 })
+    );
+    assert_eq!(
+        EditList::parse_csv("A,1,b,1\nA,RowId,b,1"),
+        Err(ParseEditListError::InvalidRowId {
+            line: 2,
+            row_id: "RowId".to_owned()
+        })
+    );
+    assert_eq!(
+        EditList::parse_csv("# empty\n").unwrap(),
+        EditList::default()
+    );
+}
+
+#[test]
+fn parse_json() {
+    let json = r#"[
+        { "param": "EditTestParam", "row_id": 10, "field": "Hit Points", "value": 500 },
+        { "param": "EditTestParam", "row_id": 10, "field": "Weight", "value": 2.5 },
+        { "param": "EditTestParam", "row_id": 20, "field": "level", "value": "200" }
+    ]"#;
+    let list: EditList = serde_json::from_str(json).unwrap();
+    let csv = EditList::parse_csv(CSV).unwrap();
+    assert_eq!(list.edits, csv.edits[..3]);
+}
+
+#[test]
+fn apply_and_revert() {
+    let paramdex = paramdex();
+    let def = &paramdex.def_with_meta("EditTestParam").unwrap().def;
+    let bytes = repo();
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<LinkedListPatcher>::new(repo);
+    let mut file = param_file();
+    let mut params = [ParamFile::from_bytes(&mut file).unwrap()];
+
+    let list = EditList::parse_csv(CSV).unwrap();
+    let applied = list.apply(&paramdex, &mut manager, &mut params).unwrap();
+
+    let warning = |index, kind| EditWarning { index, kind };
+    assert_eq!(
+        applied.warnings,
+        [
+            warning(2, EditWarningKind::Clamped { written: 127.0 }),
+            warning(5, EditWarningKind::Clamped { written: 9999.0 }),
+            warning(6, EditWarningKind::Clamped { written: 0.0 }),
+            warning(7, EditWarningKind::UnknownParam),
+            warning(
+                8,
+                EditWarningKind::MissingParam {
+                    param_type: "BITFIELD_TEST_PARAM_ST".to_owned()
+                }
+            ),
+            warning(9, EditWarningKind::UnknownRow),
+            warning(10, EditWarningKind::UnknownField),
+            warning(11, EditWarningKind::UnsupportedField),
+            warning(12, EditWarningKind::InvalidValue),
+        ]
+    );
+    assert_eq!(
+        applied.warnings[0].to_string(),
+        "edit 2: value clamped to 127"
+    );
+
+    // One patch per row, with the last edit of each field
+    let handles: Vec<_> = applied.handles.iter().map(|h| h.row_id).collect();
+    assert_eq!(handles, [10, 20]);
+    assert_eq!(manager.outstanding(PARAM_TYPE), applied.handles);
+    let row = params[0].by_id(10).unwrap();
+    assert_eq!(row.field::<i32>(def, "hp"), Some(9999));
+    assert_eq!(row.field::<f32>(def, "weight"), Some(2.5));
+    let row = params[0].by_id(20).unwrap();
+    assert_eq!(row.field::<u8>(def, "level"), Some(127));
+    assert_eq!(row.field::<u8>(def, "flag"), Some(1));
+    assert_eq!(row.field::<i16>(def, "offset"), Some(-3));
+    assert_eq!(row.field::<i8>(def, "bonus"), Some(0));
+
+    applied.revert(&mut manager, &mut params).unwrap();
+    assert!(params[0].rows().all(|row| row.data() == [0; ROW_SIZE]));
+    assert_eq!(manager.outstanding(PARAM_TYPE), []);
+}
+
+#[test]
+fn rejected_patch_rolls_back() {
+    let paramdex = paramdex();
+    let def = &paramdex.def_with_meta("EditTestParam").unwrap().def;
+    let bytes = repo();
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<SinglePatchPatcher>::new(repo);
+    let mut file = param_file();
+    let mut params = [ParamFile::from_bytes(&mut file).unwrap()];
+
+    let first = EditList::parse_csv("EditTestParam,20,hp,1").unwrap();
+    let first = first.apply(&paramdex, &mut manager, &mut params).unwrap();
+
+    // Row 20 already has a patch, which the patcher can't stack another one on
+    let second = EditList::parse_csv("EditTestParam,10,hp,2\nEditTestParam,20,hp,3").unwrap();
+    assert_eq!(
+        second.apply(&paramdex, &mut manager, &mut params),
+        Err(ApplyEditsError::CreatePatch(
+            CreatePatchError::PatchRejected
+        ))
+    );
+    assert_eq!(params[0].by_id(10).unwrap().data(), [0; ROW_SIZE]);
+    assert_eq!(
+        params[0].by_id(20).unwrap().field::<i32>(def, "hp"),
+        Some(1)
+    );
+    assert_eq!(manager.outstanding(PARAM_TYPE), first.handles);
+    let patcher = manager.row_patcher(PARAM_TYPE, 10).unwrap();
+    assert!(patcher.patched_mask_for_row().iter().all(|&m| m == 0));
+}
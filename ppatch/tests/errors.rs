@@ -0,0 +1,491 @@
+//! Codes, categories and messages of [`PpatchError`].
+
+use std::{collections::HashMap, error::Error};
+
+use ppatch::{
+    celua::CeluaError,
+    diff::ParamDiffError,
+    error::{ErrorCategory, PpatchError},
+    field_metadata::{
+        FbRepoError, FieldBlockViolation, InvalidFieldBlockRepo, InvalidFieldBlocks,
+        LoadFbRepoError,
+    },
+    from::allocator::AllocError,
+    param_builder::InsertRowError,
+    param_file::{BlockKind, DataBlock, FromBytesError, IndexError, UnalignedRowSize},
+    patchers::{
+        base::{PatchRowError, RawAccessError, RestorePatchError},
+        linked_list::{ReplaceRowError, ThawError},
+        manager::CreatePatchError,
+        patch_set::{PatchSetError, RegulationFingerprint},
+    },
+};
+
+/// Fails to compile if a subsystem error does not convert into [`PpatchError`].
+fn into_ppatch<E: Into<PpatchError>>(e: E) -> PpatchError {
+    e.into()
+}
+
+const UNALIGNED: UnalignedRowSize = UnalignedRowSize {
+    row_size: 6,
+    block_size: 4,
+};
+
+const ROW_1: DataBlock = DataBlock {
+    kind: BlockKind::Row(1),
+    offset: 0x58,
+    len: 8,
+};
+
+const OUT_OF_BOUNDS: FromBytesError = FromBytesError::OutOfBoundsOffset {
+    block: ROW_1,
+    file_size: 0x5C,
+};
+
+const INTERSECTING: FromBytesError = FromBytesError::IntersectingData {
+    first: DataBlock {
+        kind: BlockKind::Header,
+        offset: 0,
+        len: 0x5C,
+    },
+    second: ROW_1,
+};
+
+fn invalid_blocks(violation: FieldBlockViolation) -> InvalidFieldBlocks {
+    InvalidFieldBlocks {
+        index: 3,
+        violation,
+    }
+}
+
+/// One error of every distinct failure.
+fn all_failures() -> Vec<PpatchError> {
+    let mut errors = vec![
+        into_ppatch(std::io::Error::from(std::io::ErrorKind::NotFound)),
+        into_ppatch(FromBytesError::BufferTooSmall),
+        into_ppatch(FromBytesError::UnsupportedFile {
+            is_big_endian: true,
+            is_64bit: false,
+        }),
+        into_ppatch(OUT_OF_BOUNDS),
+        into_ppatch(INTERSECTING),
+        into_ppatch(FromBytesError::UnsortedRowDescs {
+            index: 1,
+            previous_id: 20,
+            id: 10,
+        }),
+        into_ppatch(FromBytesError::DuplicateIds { index: 1, id: 10 }),
+        into_ppatch(FromBytesError::InvalidNameOffset {
+            index: 1,
+            offset: 0x60,
+        }),
+        into_ppatch(FromBytesError::AmbiguousRowSize {
+            offset: 0x60,
+            data_end: 0x58,
+        }),
+        into_ppatch(UNALIGNED),
+        into_ppatch(IndexError {
+            index: 10,
+            row_count: 4,
+        }),
+        into_ppatch(InsertRowError::DuplicateId(10)),
+        into_ppatch(InsertRowError::RowTooSmall {
+            row_size: 8,
+            len: 4,
+        }),
+        into_ppatch(InsertRowError::TooManyRows),
+        into_ppatch(ParamDiffError::RowSizeMismatch {
+            id: 10,
+            base: 8,
+            modified: 12,
+        }),
+        into_ppatch(ParamDiffError::FieldBlocksOutOfBounds {
+            id: 10,
+            row_size: 4,
+        }),
+        into_ppatch(RestorePatchError::ForeignId),
+        into_ppatch(RestorePatchError::UnknownId),
+        into_ppatch(PatchRowError::PatchRejected),
+        into_ppatch(ReplaceRowError::SizeMismatch {
+            expected: 4,
+            actual: 5,
+        }),
+        into_ppatch(ReplaceRowError::TooManyPatches),
+        into_ppatch(ThawError::FieldBlocksMismatch {
+            expected: 1,
+            actual: 2,
+        }),
+        into_ppatch(ThawError::InconsistentState),
+        into_ppatch(CreatePatchError::UnknownParamType),
+        into_ppatch(CreatePatchError::SizeMismatch {
+            expected: 3,
+            actual: 2,
+        }),
+        into_ppatch(RawAccessError::OutOfBounds {
+            offset: 10,
+            len: 3,
+            row_size: 12,
+        }),
+        into_ppatch(RawAccessError::Unmapped { offset: 2, len: 2 }),
+        into_ppatch(PatchSetError::NotAPatchSet),
+        into_ppatch(PatchSetError::UnsupportedVersion { version: 2 }),
+        into_ppatch(PatchSetError::Truncated),
+        into_ppatch(PatchSetError::RegulationMismatch {
+            expected: RegulationFingerprint(1),
+            actual: RegulationFingerprint(2),
+        }),
+        into_ppatch(PatchSetError::InvalidField {
+            param_type: "TEST_PARAM_ST".to_owned(),
+            field_start: 3,
+        }),
+        into_ppatch(PatchSetError::MissingParam {
+            param_type: "TEST_PARAM_ST".to_owned(),
+        }),
+        into_ppatch(PatchSetError::MissingRow {
+            param_type: "TEST_PARAM_ST".to_owned(),
+            row_id: 10,
+        }),
+        into_ppatch(LoadFbRepoError::NotARepo),
+        into_ppatch(LoadFbRepoError::UnsupportedVersion { version: 7 }),
+        into_ppatch(LoadFbRepoError::BlockSizeMismatch {
+            expected: 4,
+            actual: 8,
+        }),
+        into_ppatch(FbRepoError::GameMismatch {
+            expected: "ER".to_owned(),
+            found: "DS3".to_owned(),
+        }),
+        into_ppatch(FbRepoError::ChecksumMismatch),
+        into_ppatch(FbRepoError::InvalidArchive("out of bounds".to_owned())),
+        into_ppatch(CeluaError::InteriorNul { position: 3 }),
+        into_ppatch(CeluaError::InitializeFailed {
+            server_name: "ppatch".to_owned(),
+        }),
+        into_ppatch(CeluaError::UnknownFunction {
+            name: "applyPatch".to_owned(),
+        }),
+        into_ppatch(AllocError {
+            size: 0x100,
+            align: 16,
+        }),
+    ];
+    errors.extend(
+        [
+            FieldBlockViolation::FieldStartAfterEntry,
+            FieldBlockViolation::NonContiguousField,
+            FieldBlockViolation::DecreasingOffset,
+            FieldBlockViolation::EmptyMask,
+        ]
+        .map(|v| into_ppatch(invalid_blocks(v))),
+    );
+    errors
+}
+
+#[test]
+fn codes_are_unique() {
+    let mut seen = HashMap::new();
+    for e in all_failures() {
+        if let Some(other) = seen.insert(e.code(), format!("{e:?}")) {
+            panic!("{e:?} and {other} share code {}", e.code());
+        }
+    }
+}
+
+#[test]
+fn codes_identify_failures() {
+    // The same failure has the same code, whatever the API which returned it
+    let wrapped = into_ppatch(PatchRowError::UnalignedRowSize(UNALIGNED));
+    assert_eq!(wrapped.code(), into_ppatch(UNALIGNED).code());
+    let raw = into_ppatch(RawAccessError::PatchRow(PatchRowError::UnalignedRowSize(
+        UNALIGNED,
+    )));
+    assert_eq!(raw.code(), into_ppatch(UNALIGNED).code());
+    let rejected = into_ppatch(RawAccessError::PatchRow(PatchRowError::PatchRejected));
+    assert_eq!(
+        rejected.code(),
+        into_ppatch(PatchRowError::PatchRejected).code()
+    );
+
+    let repo = into_ppatch(InvalidFieldBlockRepo {
+        param_type: "TEST_PARAM_ST".to_owned(),
+        error: invalid_blocks(FieldBlockViolation::EmptyMask),
+    });
+    assert_eq!(
+        repo.code(),
+        into_ppatch(invalid_blocks(FieldBlockViolation::EmptyMask)).code()
+    );
+    assert_eq!(
+        into_ppatch(FbRepoError::Header(LoadFbRepoError::NotARepo)).code(),
+        into_ppatch(LoadFbRepoError::NotARepo).code()
+    );
+    let rejected = into_ppatch(CreatePatchError::PatchRejected);
+    assert_eq!(
+        rejected.code(),
+        into_ppatch(PatchRowError::PatchRejected).code()
+    );
+    assert_eq!(
+        into_ppatch(ParamDiffError::UnalignedRowSize(UNALIGNED)).code(),
+        into_ppatch(UNALIGNED).code()
+    );
+    let unknown = PatchSetError::UnknownParamType {
+        param_type: "TEST_PARAM_ST".to_owned(),
+    };
+    assert_eq!(
+        into_ppatch(unknown).code(),
+        into_ppatch(CreatePatchError::UnknownParamType).code()
+    );
+    assert_eq!(
+        into_ppatch(PatchSetError::CreatePatch(CreatePatchError::PatchRejected)).code(),
+        rejected.code()
+    );
+    let restore_mismatch = RestorePatchError::SizeMismatch {
+        expected: 3,
+        actual: 2,
+    };
+    let create_mismatch = CreatePatchError::SizeMismatch {
+        expected: 3,
+        actual: 2,
+    };
+    assert_eq!(
+        into_ppatch(restore_mismatch).code(),
+        into_ppatch(create_mismatch).code()
+    );
+    let out_of_bounds = ParamDiffError::FieldBlocksOutOfBounds {
+        id: 10,
+        row_size: 4,
+    };
+    assert_eq!(
+        into_ppatch(CreatePatchError::FieldBlocksOutOfBounds { row_blocks: 1 }).code(),
+        into_ppatch(out_of_bounds).code()
+    );
+    let io = PatchSetError::Io(std::io::ErrorKind::NotFound.into());
+    assert_eq!(into_ppatch(io).category(), ErrorCategory::Io);
+}
+
+#[test]
+fn categories() {
+    let category = |e: PpatchError| e.category();
+    assert_eq!(
+        category(into_ppatch(std::io::Error::other("x"))),
+        ErrorCategory::Io
+    );
+    assert_eq!(
+        category(FromBytesError::BufferTooSmall.into()),
+        ErrorCategory::Parse
+    );
+    assert_eq!(
+        category(
+            FromBytesError::UnsupportedFile {
+                is_big_endian: false,
+                is_64bit: true
+            }
+            .into()
+        ),
+        ErrorCategory::Unsupported
+    );
+    assert_eq!(
+        category(FromBytesError::DuplicateIds { index: 1, id: 10 }.into()),
+        ErrorCategory::Validation
+    );
+    assert_eq!(
+        category(RestorePatchError::UnknownId.into()),
+        ErrorCategory::Conflict
+    );
+    assert_eq!(
+        category(ReplaceRowError::TooManyPatches.into()),
+        ErrorCategory::Conflict
+    );
+    assert_eq!(
+        category(LoadFbRepoError::UnsupportedVersion { version: 2 }.into()),
+        ErrorCategory::Unsupported
+    );
+    assert_eq!(
+        category(AllocError { size: 8, align: 8 }.into()),
+        ErrorCategory::GameState
+    );
+}
+
+#[test]
+fn display() {
+    let messages: Vec<_> = [
+        into_ppatch(FromBytesError::BufferTooSmall),
+        into_ppatch(FromBytesError::UnsupportedFile {
+            is_big_endian: true,
+            is_64bit: false,
+        }),
+        into_ppatch(OUT_OF_BOUNDS),
+        into_ppatch(INTERSECTING),
+        into_ppatch(FromBytesError::UnsortedRowDescs {
+            index: 1,
+            previous_id: 20,
+            id: 10,
+        }),
+        into_ppatch(FromBytesError::DuplicateIds { index: 1, id: 10 }),
+        into_ppatch(FromBytesError::InvalidNameOffset {
+            index: 1,
+            offset: 0x60,
+        }),
+        into_ppatch(FromBytesError::AmbiguousRowSize {
+            offset: 0x60,
+            data_end: 0x58,
+        }),
+        into_ppatch(PatchRowError::UnalignedRowSize(UNALIGNED)),
+        into_ppatch(RestorePatchError::UnknownId),
+        into_ppatch(ReplaceRowError::SizeMismatch {
+            expected: 4,
+            actual: 5,
+        }),
+        into_ppatch(invalid_blocks(FieldBlockViolation::DecreasingOffset)),
+        into_ppatch(LoadFbRepoError::UnsupportedVersion { version: 7 }),
+        into_ppatch(LoadFbRepoError::BlockSizeMismatch {
+            expected: 4,
+            actual: 8,
+        }),
+        into_ppatch(AllocError {
+            size: 0x100,
+            align: 16,
+        }),
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+
+    assert_eq!(
+        messages,
+        [
+            "E101: buffer is too small to hold a param file",
+            "E102: unsupported param file (big-endian, 32-bit)",
+            "E103: param file offset is out of bounds: row 1 at 0x58 (8 bytes) ends past the file \
+             size of 0x5c",
+            "E104: param file sections intersect: header at 0x0 (92 bytes) and row 1 at 0x58 (8 \
+             bytes)",
+            "E105: param rows are not sorted by ID: row 1 has ID 10 after ID 20",
+            "E106: param file has duplicate row IDs: row 1 repeats ID 10",
+            "E107: param row name is outside of the strings region: row 1 has name offset 0x60",
+            "E108: param row size cannot be inferred: the only row at 0x60 does not start before \
+             the end of row data at 0x58",
+            "E110: row size 6 is not a multiple of the block size 4",
+            "E202: row patch ID does not refer to an outstanding patch",
+            "E220: row is 5 blocks long, expected 4",
+            "E303: invalid field block 3: offset is smaller than the previous block of the field",
+            "E311: unsupported field block repo format version 7 (expected 3)",
+            "E312: field block repo has 8 byte blocks (expected 4)",
+            "E701: failed to allocate 256 bytes aligned to 16",
+        ]
+    );
+}
+
+#[test]
+fn source_chain() {
+    let e = into_ppatch(PatchRowError::UnalignedRowSize(UNALIGNED));
+    let source = e.source().unwrap();
+    assert!(source.downcast_ref::<PatchRowError>().is_some());
+    let root = source.source().unwrap();
+    assert_eq!(root.downcast_ref::<UnalignedRowSize>(), Some(&UNALIGNED));
+}
+
+#[cfg(feature = "paramdex")]
+#[test]
+fn paramdex_errors() {
+    use ppatch::paramdex::{unofficial::UnofficialFieldError, ParamdexLoadError};
+
+    let load = into_ppatch(ParamdexLoadError::IoError(
+        std::io::ErrorKind::NotFound.into(),
+    ));
+    assert_eq!(load.category(), ErrorCategory::Io);
+    let overlap = into_ppatch(UnofficialFieldError::NameTaken {
+        param_type: "TEST_PARAM_ST".to_owned(),
+        field: "a".to_owned(),
+    });
+    assert_eq!(overlap.category(), ErrorCategory::Validation);
+
+    let codes: Vec<_> = all_failures().iter().map(PpatchError::code).collect();
+    assert!(!codes.contains(&load.code()) && !codes.contains(&overlap.code()));
+}
+
+#[cfg(feature = "paramdex")]
+#[test]
+fn edit_list_errors() {
+    use ppatch::apply::{ApplyEditsError, ParseEditListError};
+
+    let missing = into_ppatch(ParseEditListError::MissingColumns { line: 3 });
+    let row_id = into_ppatch(ParseEditListError::InvalidRowId {
+        line: 3,
+        row_id: "x".to_owned(),
+    });
+    assert_eq!((missing.code(), row_id.code()), (601, 602));
+    assert_eq!(missing.category(), ErrorCategory::Parse);
+    assert_eq!(
+        row_id.to_string(),
+        "E602: line 3 of the edit list has an invalid row ID \"x\""
+    );
+
+    // Failures of the manager keep their codes
+    let rejected = ApplyEditsError::CreatePatch(CreatePatchError::PatchRejected);
+    assert_eq!(
+        into_ppatch(rejected).code(),
+        into_ppatch(CreatePatchError::PatchRejected).code()
+    );
+    assert_eq!(
+        into_ppatch(ApplyEditsError::UnalignedRowSize(UNALIGNED)).code(),
+        into_ppatch(UNALIGNED).code()
+    );
+}
+
+#[cfg(feature = "paramdex")]
+#[test]
+fn param_writer_errors() {
+    use ppatch::param_file::write::WriteParamError;
+
+    // Failures shared with the builder keep its codes
+    assert_eq!(
+        into_ppatch(WriteParamError::DuplicateId(10)).code(),
+        into_ppatch(InsertRowError::DuplicateId(10)).code()
+    );
+    assert_eq!(
+        into_ppatch(WriteParamError::TooManyRows).code(),
+        into_ppatch(InsertRowError::TooManyRows).code()
+    );
+
+    let unknown = into_ppatch(WriteParamError::UnknownRowSize {
+        param_type: "TEST_PARAM_ST".to_owned(),
+    });
+    let mismatch = into_ppatch(WriteParamError::RowSizeMismatch {
+        id: 10,
+        row_size: 8,
+        len: 4,
+    });
+    let too_long = into_ppatch(WriteParamError::ParamTypeTooLong { len: 33 });
+    assert_eq!(
+        (unknown.code(), mismatch.code(), too_long.code()),
+        (140, 141, 142)
+    );
+    assert_eq!(mismatch.category(), ErrorCategory::Validation);
+    assert_eq!(too_long.category(), ErrorCategory::Unsupported);
+    assert_eq!(
+        mismatch.to_string(),
+        "E141: row 10 is 4 bytes long instead of the row size 8"
+    );
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn capi_errors() {
+    use ppatch::ffi::FfiError;
+
+    let errors = [
+        FfiError::NullPointer,
+        FfiError::InvalidParamName,
+        FfiError::Panic,
+    ];
+    let codes: Vec<_> = errors.iter().map(|&e| into_ppatch(e).code()).collect();
+    assert_eq!(codes, [801, 802, 803]);
+    assert_eq!(
+        into_ppatch(FfiError::InvalidParamName).category(),
+        ErrorCategory::Parse
+    );
+    assert_eq!(
+        into_ppatch(FfiError::NullPointer).to_string(),
+        "E801: null pointer passed to the C API"
+    );
+}
@@ -0,0 +1,99 @@
+//! Reading and writing field values through their field blocks with [`read_field_bytes`] and
+//! [`write_field_bytes`].
+
+use ppatch::{
+    fields::{field_size_bits, read_field_bytes, write_field_bytes, FieldBlock},
+    util::unaligned::Unaligned,
+};
+
+type Block = u32;
+
+fn fb(field_start: u16, offset: u16, mask: Block) -> FieldBlock<Block> {
+    FieldBlock {
+        field_start,
+        offset,
+        mask,
+    }
+}
+
+fn row(blocks: &[Block]) -> Vec<Unaligned<Block>> {
+    blocks.iter().map(|&b| Unaligned(b)).collect()
+}
+
+fn read(row: &[Unaligned<Block>], blocks: &[FieldBlock<Block>], field_start: u16) -> Vec<u8> {
+    let mut out = [0; 16];
+    let len = read_field_bytes(row, blocks, field_start, &mut out);
+    out[..len].to_vec()
+}
+
+/// A u16 in the middle bytes of a block, between two u8s.
+#[test]
+fn field_starting_mid_block() {
+    let blocks = [fb(0, 0, 0xFF), fb(1, 0, 0x00FF_FF00), fb(2, 0, 0xFF00_0000)];
+    let mut live = row(&[0x4433_2211]);
+
+    assert_eq!(read(&live, &blocks, 1), [0x22, 0x33]);
+    assert_eq!(write_field_bytes(&mut live, &blocks, 1, &[0xBB, 0xAA]), 2);
+    assert_eq!(live, row(&[0x44AA_BB11]));
+}
+
+/// A u64 starting in the upper half of a block, spanning three blocks.
+#[test]
+fn field_spanning_three_blocks() {
+    let blocks = [
+        fb(0, 0, 0xFFFF),
+        fb(1, 0, 0xFFFF_0000),
+        fb(1, 1, Block::MAX),
+        fb(1, 2, 0xFFFF),
+        fb(4, 2, 0xFFFF_0000),
+    ];
+    let mut live = row(&[0x2211_AAAA, 0x6655_4433, 0xBBBB_8877]);
+    assert_eq!(field_size_bits(&blocks, 1), 64);
+
+    let value = read(&live, &blocks, 1);
+    assert_eq!(value, 0x8877_6655_4433_2211u64.to_le_bytes());
+
+    let new_value = 0x0123_4567_89AB_CDEFu64.to_le_bytes();
+    assert_eq!(write_field_bytes(&mut live, &blocks, 1, &new_value), 8);
+    assert_eq!(live, row(&[0xCDEF_AAAA, 0x4567_89AB, 0xBBBB_0123]));
+    assert_eq!(read(&live, &blocks, 1), new_value);
+
+    // Shorter buffers only receive the start of the value
+    let mut out = [0; 3];
+    assert_eq!(read_field_bytes(&live, &blocks, 1, &mut out), 8);
+    assert_eq!(out, [0xEF, 0xCD, 0xAB]);
+    // Shorter values leave the rest of the field zeroed
+    write_field_bytes(&mut live, &blocks, 1, &[0x11]);
+    assert_eq!(live, row(&[0x0011_AAAA, 0, 0xBBBB_0000]));
+}
+
+/// Bitfields packed with their neighbors, including one straddling two blocks.
+#[test]
+fn packed_bitfields() {
+    // 3 bits at 4..7, 5 bits at 7..12, 6 bits at 29..35 and the rest of each block
+    let blocks = [
+        fb(0, 0, 0xF),
+        fb(1, 0, 0x70),
+        fb(2, 0, 0xF80),
+        fb(3, 0, 0x1FFF_F000),
+        fb(4, 0, 0xE000_0000),
+        fb(4, 1, 0x7),
+        fb(6, 1, !0x7),
+    ];
+    let mut live = row(&[0, 0]);
+
+    write_field_bytes(&mut live, &blocks, 1, &[0b101]);
+    write_field_bytes(&mut live, &blocks, 2, &[0b11111]);
+    write_field_bytes(&mut live, &blocks, 4, &[0b110011]);
+    assert_eq!(live, row(&[0b011 << 29 | 0b11111 << 7 | 0b101 << 4, 0b110]));
+    assert_eq!(read(&live, &blocks, 1), [0b101]);
+    assert_eq!(read(&live, &blocks, 2), [0b11111]);
+    assert_eq!(read(&live, &blocks, 4), [0b110011]);
+
+    // Bits past the end of a bitfield are ignored, and neighbors are left untouched
+    let mut full = row(&[Block::MAX, Block::MAX]);
+    write_field_bytes(&mut full, &blocks, 2, &[0xE0]);
+    assert_eq!(full, row(&[!0xF80, Block::MAX]));
+    assert_eq!(read(&full, &blocks, 1), [0b111]);
+    assert_eq!(read(&full, &blocks, 4), [0b111111]);
+}
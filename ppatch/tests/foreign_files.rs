@@ -0,0 +1,173 @@
+//! Converting param files of another endianness or bitness with
+//! [`ParamFileOwned::from_foreign_bytes`].
+
+use ppatch::param_file::{
+    BlockKind, DataBlock, Endianness, FromBytesError, ParamFile, ParamFileOwned,
+};
+
+const HEADER_SIZE: usize = 0x40;
+const ROW_SIZE: usize = 8;
+const ROWS: [(u32, u32, u16, &str); 3] = [
+    (10, 0x1122_3344, 0x5566, "Moonveil"),
+    (20, 0xDEAD_BEEF, 0xCAFE, "Épée d'éclat"),
+    (30, 7, 8, "Rivers of Blood"),
+];
+
+fn put(file: &mut [u8], ofs: usize, size: usize, value: u64, big_endian: bool) {
+    let bytes = if big_endian {
+        value.to_be_bytes()[8 - size..].to_vec()
+    }
+    else {
+        value.to_le_bytes()[..size].to_vec()
+    };
+    file[ofs..ofs + size].copy_from_slice(&bytes);
+}
+
+/// Builds a param file with [`ROWS`] and UTF-16 row names. Each row holds a `u32` and a `u16`
+/// stored in the endianness of the file.
+fn build(big_endian: bool, is_64_bit: bool) -> Vec<u8> {
+    let offset_size = if is_64_bit { 8 } else { 4 };
+    let desc_size = 3 * offset_size;
+    let data_start = HEADER_SIZE + ROWS.len() * desc_size;
+    let data_end = data_start + ROWS.len() * ROW_SIZE;
+    let mut file = vec![0u8; data_end];
+    let put = |file: &mut Vec<u8>, ofs, size, value| put(file, ofs, size, value, big_endian);
+
+    put(&mut file, 0, 4, data_end as u64);
+    put(&mut file, 0xA, 2, ROWS.len() as u64);
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2C] = big_endian as u8;
+    file[0x2D] = if is_64_bit { 4 | 3 } else { 3 };
+    file[0x2E] = 1;
+    put(&mut file, 0x30, offset_size, data_start as u64);
+
+    for (i, &(id, a, b, name)) in ROWS.iter().enumerate() {
+        let desc = HEADER_SIZE + i * desc_size;
+        let data = data_start + i * ROW_SIZE;
+        put(&mut file, desc, 4, id as u64);
+        put(&mut file, desc + offset_size, offset_size, data as u64);
+        let name_offset = file.len();
+        put(
+            &mut file,
+            desc + 2 * offset_size,
+            offset_size,
+            name_offset as u64,
+        );
+        put(&mut file, data, 4, a as u64);
+        put(&mut file, data + 4, 2, b as u64);
+        for c in name.encode_utf16().chain([0]) {
+            let ofs = file.len();
+            file.extend([0, 0]);
+            put(&mut file, ofs, 2, c as u64);
+        }
+    }
+    file
+}
+
+/// Returns the UTF-16 name of each row of a converted file.
+fn names(param: &ParamFileOwned) -> Vec<String> {
+    let bytes = param.as_bytes();
+    (param.row_descriptors().iter())
+        .map(|r| {
+            let units: Vec<u16> = bytes[r.name_offset..]
+                .chunks_exact(2)
+                .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            String::from_utf16(&units).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn big_endian_matches_little_endian_twin() {
+    for is_64_bit in [false, true] {
+        let le = ParamFileOwned::from_foreign_bytes(&build(false, is_64_bit)).unwrap();
+        let be = ParamFileOwned::from_foreign_bytes(&build(true, is_64_bit)).unwrap();
+        assert_eq!(le.source_endianness(), Endianness::Little);
+        assert_eq!(be.source_endianness(), Endianness::Big);
+        assert_eq!(be.source_is_64_bit(), is_64_bit);
+
+        for param in [&le, &be] {
+            assert_eq!(param.header().row_count(), 3);
+            assert!(!param.header().is_big_endian());
+            assert_eq!(param.row_size(), ROW_SIZE);
+            assert_eq!(names(param), ROWS.map(|r| r.3));
+
+            let endianness = param.source_endianness();
+            for (row, &(id, a, b, _)) in param.rows().zip(&ROWS) {
+                assert_eq!(row.id(), id);
+                assert_eq!(row.read_at::<u32>(0, endianness), Some(a));
+                assert_eq!(row.read_at::<u16>(4, endianness), Some(b));
+                assert_eq!(row.read_at::<u16>(7, endianness), None);
+            }
+        }
+        // Only row data is left in the original byte order
+        let row = be.by_id(20).unwrap();
+        assert_eq!(row.read_at::<u32>(0, Endianness::Little), Some(0xEFBE_ADDE));
+    }
+}
+
+#[test]
+fn converted_files_are_native() {
+    let native = build(false, true);
+    assert!(ParamFile::from_bytes(&mut native.clone()).is_ok());
+    let param = ParamFileOwned::from_foreign_bytes(&native).unwrap();
+    assert_eq!(param.as_bytes(), native);
+
+    // 32-bit row descriptors are widened, moving the rest of the file
+    let param = ParamFileOwned::from_foreign_bytes(&build(true, false)).unwrap();
+    let mut converted = param.as_bytes().to_vec();
+    assert_eq!(converted.len(), build(true, false).len() + ROWS.len() * 12);
+    let reparsed = ParamFile::from_bytes(&mut converted).unwrap();
+    assert!(reparsed.header().is_64_bit());
+    assert_eq!(reparsed.index_of(30), Some(2));
+}
+
+#[test]
+fn foreign_files_need_conversion() {
+    for (big_endian, is_64_bit) in [(true, true), (false, false)] {
+        let mut file = build(big_endian, is_64_bit);
+        assert_eq!(
+            ParamFile::from_bytes(&mut file).err(),
+            Some(FromBytesError::UnsupportedFile {
+                is_big_endian: big_endian,
+                is_64bit: is_64_bit,
+            })
+        );
+    }
+
+    let mut file = build(true, true);
+    put(&mut file, HEADER_SIZE + 8, 8, 1 << 40, true);
+    assert_eq!(
+        ParamFileOwned::from_foreign_bytes(&file).err(),
+        // The last row in the file now extends up to the offset of the first one
+        Some(FromBytesError::OutOfBoundsOffset {
+            block: DataBlock {
+                kind: BlockKind::Row(2),
+                offset: 0x98,
+                len: (1 << 40) - 0x98,
+            },
+            file_size: 0xEC,
+        })
+    );
+}
+
+/// Converted files stay valid when moved, and their bytes can be read while rows are borrowed.
+/// Also run under Miri by `cargo miri-param-files`.
+#[test]
+fn moved_owned_files() {
+    let param = ParamFileOwned::from_foreign_bytes(&build(true, false)).unwrap();
+    let mut params = vec![param];
+    params.push(ParamFileOwned::from_foreign_bytes(&build(false, true)).unwrap());
+    let params: Box<[_]> = params.into_boxed_slice();
+
+    for param in params.iter() {
+        let row = param.get(2).unwrap();
+        let bytes = param.as_bytes();
+        assert_eq!(row.id(), 30);
+        assert_eq!(names(param), ROWS.map(|r| r.3));
+        assert!(bytes.len() > HEADER_SIZE);
+        assert_eq!(row.read_at::<u16>(4, param.source_endianness()), Some(8));
+    }
+}
@@ -0,0 +1,87 @@
+//! Non-zero values in the regions of param files which are assumed unused.
+
+use ppatch::param_file::{FormatWarning, FromBytesOptions, ParamFile, ValidationLevel};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const STRICT: FromBytesOptions = FromBytesOptions {
+    strict: true,
+    validation: ValidationLevel::Full,
+};
+
+/// Builds a 64-bit little endian param file with two 4 byte rows. If `type_offset` is set, the
+/// param type is stored after the row data rather than in the header.
+fn build(type_offset: bool) -> Vec<u8> {
+    let data_start = HEADER_SIZE + 2 * DESC_SIZE;
+    let data_end = data_start + 8;
+    let mut file = vec![0u8; data_end + 8];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA..0xC].copy_from_slice(&2u16.to_le_bytes());
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[0x30..0x38].copy_from_slice(&(data_start as u64).to_le_bytes());
+    file[data_end..].copy_from_slice(b"TEST_ST\0");
+    if type_offset {
+        file[0x2D] |= 0x80;
+        file[0x10..0x14].copy_from_slice(&(data_end as u32).to_le_bytes());
+    }
+    else {
+        file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    }
+
+    for i in 0..2 {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        file[desc..desc + 4].copy_from_slice(&(i as u32).to_le_bytes());
+        file[desc + 8..desc + 16].copy_from_slice(&((data_start + 4 * i) as u64).to_le_bytes());
+    }
+    file
+}
+
+#[test]
+fn clean_files_have_no_warnings() {
+    for type_offset in [false, true] {
+        let mut file = build(type_offset);
+        let (param, warnings) = ParamFile::from_bytes_with(&mut file, STRICT).unwrap();
+        assert_eq!(warnings, []);
+        assert_eq!(param.header().raw_unk006(), 0);
+        assert_eq!(param.header().raw_unk04(), type_offset.then_some(0));
+        assert_eq!(param.raw_header_bytes().len(), HEADER_SIZE);
+    }
+}
+
+#[test]
+fn non_zero_regions_warn_but_parse() {
+    let mut file = build(true);
+    file[6] = 0x12;
+    file[0xC..0x10].copy_from_slice(&7u32.to_le_bytes());
+    file[0x14 + 5] = 1;
+    file[0x3F] = 0xFF;
+
+    let (param, warnings) = ParamFile::from_bytes_with(&mut file, STRICT).unwrap();
+    assert_eq!(
+        warnings,
+        [
+            FormatWarning::NonZeroUnk006(0x12),
+            FormatWarning::NonZeroParamTypeUnk04(7),
+            FormatWarning::NonZeroParamTypePadding { offset: 5 },
+            FormatWarning::NonZeroHeaderPadding { offset: 0x3F },
+        ]
+    );
+    assert_eq!(param.rows().count(), 2);
+    assert_eq!(param.header().raw_unk04(), Some(7));
+    assert_eq!(param.header().raw_param_type_padding().unwrap()[5], 1);
+    assert_eq!(param.raw_header_bytes()[0x3F], 0xFF);
+}
+
+#[test]
+fn warnings_are_opt_in() {
+    let mut file = build(false);
+    file[6] = 1;
+    let (_, warnings) = ParamFile::from_bytes_with(&mut file, FromBytesOptions::default()).unwrap();
+    assert_eq!(warnings, []);
+    // The param type buffer of files without a param type offset is not padding
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).unwrap().format_warnings().len(),
+        1
+    );
+}
@@ -0,0 +1,154 @@
+//! Persisting [`LinkedListPatcher`] state with [`LinkedListPatcher::freeze`].
+
+use ppatch::{
+    fields::FieldBlock,
+    patchers::{
+        base::{RestorePatchError, RowPatchId, RowPatcher},
+        linked_list::{FrozenLinkedListPatcher, LinkedListPatcher, ThawError},
+    },
+    util::unaligned::Unaligned,
+};
+
+const ROW_SIZE: usize = 20;
+
+/// A u32, two u16s, a byte and two bitfields, and a u64.
+fn field_blocks() -> Vec<FieldBlock<u32>> {
+    let blocks = [
+        (0, 0xFFFF_FFFF),
+        (1, 0xFFFF),
+        (1, 0xFFFF_0000),
+        (2, 0xFF),
+        (2, 0x700),
+        (2, 0xF800),
+        (3, 0xFFFF_FFFF),
+        (4, 0xFFFF_FFFF),
+    ];
+    let mut field_blocks: Vec<_> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, &(offset, mask))| FieldBlock {
+            field_start: i as u16,
+            offset,
+            mask,
+        })
+        .collect();
+    field_blocks[7].field_start = 6;
+    field_blocks
+}
+
+/// Live row of a patcher and the one of the control patcher, which are edited identically.
+struct Rows {
+    live: Vec<Unaligned<u32>>,
+    control: Vec<Unaligned<u32>>,
+}
+
+impl Rows {
+    fn new() -> Self {
+        let row: Vec<_> = (0..ROW_SIZE as u32 / 4).map(|i| Unaligned(i * 0x1111_1111)).collect();
+        Self {
+            live: row.clone(),
+            control: row,
+        }
+    }
+
+    /// XORs `diff` into block `offset` of both rows, and creates a patch in both patchers.
+    fn patch(
+        &mut self,
+        patcher: &mut LinkedListPatcher<'_>,
+        control: &mut LinkedListPatcher<'_>,
+        offset: usize,
+        diff: u32,
+    ) -> (RowPatchId, RowPatchId) {
+        let before = self.live.clone();
+        for row in [&mut self.live, &mut self.control] {
+            row[offset] = Unaligned(row[offset].read() ^ diff);
+        }
+        let id = patcher.create_patch(&before, &self.live).unwrap();
+        let control_id = control.create_patch(&before, &self.control).unwrap();
+        (id, control_id)
+    }
+}
+
+fn roundtrip(frozen: &FrozenLinkedListPatcher) -> FrozenLinkedListPatcher {
+    serde_json::from_str(&serde_json::to_string(frozen).unwrap()).unwrap()
+}
+
+#[test]
+fn thawed_patcher_matches_control() {
+    let field_blocks = field_blocks();
+    let mut patcher = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    let mut control = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    let mut rows = Rows::new();
+    let original = rows.live.clone();
+
+    let a = rows.patch(&mut patcher, &mut control, 1, 0x0001_0001);
+    let b = rows.patch(&mut patcher, &mut control, 2, 0x0000_0F05);
+    let c = rows.patch(&mut patcher, &mut control, 1, 0x0100_0000);
+    let d = rows.patch(&mut patcher, &mut control, 4, 0x8000_0000);
+    // Leave a reclaimed slot behind, so that the free list and generations are frozen too
+    patcher.restore_patch(b.0, &mut rows.live).unwrap();
+    control.restore_patch(b.1, &mut rows.control).unwrap();
+
+    let frozen = roundtrip(&patcher.freeze());
+    drop(patcher);
+    let mut patcher = frozen.thaw(&field_blocks).unwrap();
+    assert_eq!(
+        patcher.patched_mask_for_row(),
+        control.patched_mask_for_row()
+    );
+
+    // The reclaimed slot is reused with the same generation as the control
+    let e = rows.patch(&mut patcher, &mut control, 2, 0x0000_0600);
+    assert_eq!(e.0.local_id(), e.1.local_id());
+    assert_eq!(
+        patcher.restore_patch(b.0, &mut rows.live),
+        Err(RestorePatchError::UnknownId)
+    );
+
+    for (id, control_id) in [a, e, d, c] {
+        patcher.restore_patch(id, &mut rows.live).unwrap();
+        control.restore_patch(control_id, &mut rows.control).unwrap();
+        assert_eq!(rows.live, rows.control);
+        assert_eq!(
+            patcher.patched_mask_for_row(),
+            control.patched_mask_for_row()
+        );
+    }
+    assert_eq!(rows.live, original);
+}
+
+#[test]
+fn thaw_checks_field_blocks() {
+    let field_blocks = field_blocks();
+    let mut patcher = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    let mut control = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    Rows::new().patch(&mut patcher, &mut control, 0, 1);
+    let frozen = patcher.freeze();
+
+    let mut other_blocks = field_blocks.clone();
+    other_blocks[3].mask = 0x7F;
+    assert_eq!(
+        frozen.clone().thaw(&other_blocks).err(),
+        Some(ThawError::FieldBlocksMismatch {
+            expected: frozen.field_blocks_hash(),
+            actual: ppatch::patchers::base::field_blocks_hash(&other_blocks),
+        })
+    );
+    assert!(frozen.thaw(&field_blocks).is_ok());
+}
+
+#[test]
+fn thaw_rejects_corrupted_state() {
+    let field_blocks = field_blocks();
+    let mut patcher = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    let mut control = LinkedListPatcher::new(&field_blocks, ROW_SIZE);
+    Rows::new().patch(&mut patcher, &mut control, 3, 1);
+
+    let mut state = serde_json::to_value(patcher.freeze()).unwrap();
+    state["diffs"][0]["block_diffs"].as_array_mut().unwrap().clear();
+    let frozen: FrozenLinkedListPatcher = serde_json::from_value(state).unwrap();
+    assert_eq!(
+        frozen.thaw(&field_blocks).err(),
+        Some(ThawError::InconsistentState)
+    );
+}
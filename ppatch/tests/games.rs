@@ -0,0 +1,44 @@
+//! Field blocks embedded for each [`Game`], and selection of the active game with
+//! [`set_active_game`].
+
+use ppatch::{set_active_game, Game, LAYOUT_VERSION};
+
+#[test]
+fn default_game_is_embedded() {
+    assert!(Game::DEFAULT.field_block_repo().is_some());
+    assert_eq!(Game::DEFAULT.layout_version(), Some(LAYOUT_VERSION));
+    for game in Game::ALL {
+        assert_eq!(game.field_block_repo().is_some(), game.is_embedded());
+        assert_eq!(game.layout_version().is_some(), game.is_embedded());
+    }
+}
+
+#[cfg(not(feature = "multi-game"))]
+#[test]
+#[should_panic = "not embedded"]
+fn other_games_cannot_be_selected() {
+    let other = Game::ALL.into_iter().find(|&g| g != Game::DEFAULT).unwrap();
+    assert!(!other.is_embedded());
+    set_active_game(other);
+}
+
+#[cfg(feature = "multi-game")]
+#[test]
+fn all_games_are_embedded() {
+    use ppatch::{active_game, layout_check::LayoutReport};
+
+    let er = Game::Er.field_block_repo().unwrap();
+    let ds3 = Game::Ds3.field_block_repo().unwrap();
+    assert!(!std::ptr::eq(er, ds3));
+    assert!(Game::Ac6.field_block_repo().is_some());
+
+    // Entry points use the field blocks of the active game
+    assert_eq!(active_game(), Game::DEFAULT);
+    for game in Game::ALL {
+        set_active_game(game);
+        assert_eq!(active_game(), game);
+        let report = LayoutReport::for_embedded_repo([]);
+        assert_eq!(Some(report.layout_version), game.layout_version());
+    }
+    set_active_game(Game::DEFAULT);
+}
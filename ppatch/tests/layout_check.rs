@@ -0,0 +1,124 @@
+//! Detection of field metadata generated for another game version.
+
+use paramdex::{Paramdex, VerifyLayoutError};
+use ppatch::{
+    field_metadata::{load_fb_repo, serialize_fb_repo, FieldBlock, FieldBlockRepo},
+    layout_check::{LayoutReport, ParamRowSizes, VerifyParamFile},
+    param_file::ParamFile,
+};
+
+/// Repo with a single field block per param type, at the last block of a row of `size` bytes.
+fn repo(params: &[(&str, usize)]) -> Box<[u8]> {
+    let repo: FieldBlockRepo = params
+        .iter()
+        .map(|&(param_type, size)| {
+            let block = FieldBlock {
+                field_start: 0,
+                offset: (size / 4 - 1) as u16,
+                mask: u32::MAX,
+            };
+            (param_type.to_owned(), vec![block])
+        })
+        .collect();
+    serialize_fb_repo(&repo)
+}
+
+#[test]
+fn field_block_sizes() {
+    let bytes = repo(&[("A_ST", 8), ("B_ST", 16), ("C_ST", 4)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let report = LayoutReport::new(
+        repo,
+        100,
+        [("A_ST", 8), ("B_ST", 12), ("D_ST", 4), ("C_ST", 3)],
+    );
+
+    let mismatches: Vec<_> = report.mismatches().collect();
+    assert_eq!(
+        mismatches,
+        [&ParamRowSizes {
+            param_type: "B_ST".to_owned(),
+            live: 12,
+            field_blocks: Some(16),
+            def: None,
+        }]
+    );
+    // D_ST has no field blocks, and C_ST rows are padded to whole blocks
+    assert_eq!(report.checked(), 3);
+    assert_eq!(report.diagnostic(0.5), None);
+    assert_eq!(
+        report.diagnostic(0.2).unwrap(),
+        "field metadata appears to be for game version 100, the running game differs: 1/3 \
+        params have unexpected row sizes"
+    );
+}
+
+#[test]
+fn detects_version_from_defs() {
+    // At 10400, BITFIELD_TEST_PARAM_ST rows are 20 bytes. The u16 removed in 10500 makes them 16
+    let bytes = repo(&[("BITFIELD_TEST_PARAM_ST", 20), ("OTHER_ST", 8)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(10400);
+
+    let live = [("BITFIELD_TEST_PARAM_ST", 16), ("OTHER_ST", 8)];
+    let report = LayoutReport::new(repo, 10400, live).with_defs(&paramdex);
+    assert_eq!(report.params[0].def, Some(20));
+    assert_eq!(report.params[1].def, None);
+    assert_eq!(report.detected_version, Some(10500));
+    assert_eq!(
+        report.diagnostic(0.25).unwrap(),
+        "field metadata appears to be for game version 10400, detected 10500: 1/2 params have \
+        unexpected row sizes"
+    );
+
+    let matching = LayoutReport::new(repo, 10400, [("BITFIELD_TEST_PARAM_ST", 20)]);
+    let matching = matching.with_defs(&paramdex);
+    assert_eq!(matching.detected_version, None);
+    assert_eq!(matching.diagnostic(0.0), None);
+}
+
+/// Builds a 64-bit little endian param file with two rows of `row_size` bytes.
+fn param_file(row_size: usize) -> Vec<u8> {
+    let data_start = 0x40 + 2 * 24;
+    let data_end = data_start + 2 * row_size;
+    let mut file = vec![0u8; data_end + 1];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA] = 2;
+    file[0xC..0x23].copy_from_slice(b"BITFIELD_TEST_PARAM_ST\0");
+    file[0x2D] = 4 | 3;
+    for i in 0..2 {
+        let desc = 0x40 + i * 24;
+        file[desc] = i as u8;
+        let offset = (data_start + i * row_size) as u64;
+        file[desc + 8..desc + 16].copy_from_slice(&offset.to_le_bytes());
+    }
+    file
+}
+
+#[test]
+fn verify_def_against_param_file() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(10400);
+
+    let mut bytes = param_file(20);
+    let file = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert_eq!(
+        paramdex.verify_against_param_file("BITFIELD_TEST_PARAM_ST", &file),
+        Ok(())
+    );
+    assert!(matches!(
+        paramdex.verify_against_param_file("OTHER_ST", &file),
+        Err(VerifyLayoutError::UnknownParamType(_))
+    ));
+
+    let mut bytes = param_file(16);
+    let file = ParamFile::from_bytes(&mut bytes).unwrap();
+    let Err(VerifyLayoutError::Mismatch(mismatch)) =
+        paramdex.verify_against_param_file("BITFIELD_TEST_PARAM_ST", &file)
+    else {
+        panic!("expected a layout mismatch");
+    };
+    assert_eq!((mismatch.computed, mismatch.expected), (Some(20), 16));
+    assert_eq!(mismatch.last_fields.last().unwrap().name, "endPad");
+}
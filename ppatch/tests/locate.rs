@@ -0,0 +1,93 @@
+//! Matching [`Pattern`]s and resolving [`Signature`]s in synthetic code.
+
+use ppatch::{
+    locate::{regulation_manager_signatures, ParsePatternError, Pattern, Signature},
+    Game,
+};
+
+fn pattern(s: &str) -> Pattern {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parse_patterns() {
+    assert_eq!(pattern("48 8b ?? ? C3").len(), 5);
+    assert!(pattern("").is_empty());
+    for invalid in ["4", "488B", "0x48", "G0", "???"] {
+        assert_eq!(
+            format!("48 {invalid}").parse::<Pattern>(),
+            Err(ParsePatternError {
+                token: invalid.to_owned()
+            })
+        );
+    }
+}
+
+#[test]
+fn find_patterns() {
+    let haystack = [0x90, 0x48, 0x8B, 0x05, 0x48, 0x8B, 0x0D, 0x11, 0x22, 0xC3];
+
+    assert_eq!(pattern("48 8B 0D").find(&haystack), Some(4));
+    assert_eq!(pattern("48 8B ??").find(&haystack), Some(1));
+    assert_eq!(pattern("48 8B ?? 11 ?? C3").find(&haystack), Some(4));
+    assert_eq!(pattern("?? ?? C3").find(&haystack), Some(7));
+    assert_eq!(pattern("").find(&haystack), Some(0));
+    // Matches must fit in the haystack
+    assert_eq!(pattern("22 C3 ??").find(&haystack), None);
+    assert_eq!(pattern("48 8B 0E").find(&haystack), None);
+    assert_eq!(pattern("48").find(&[]), None);
+
+    assert!(pattern("48 ?? 05").matches(&haystack[1..]));
+    assert!(!pattern("48 ?? 05").matches(&haystack[1..2]));
+}
+
+#[test]
+fn resolve_signatures() {
+    let signature = Signature::new("48 8B 0D ?? ?? ?? ?? 48 85 C9", 3, 7);
+    let mut code = vec![0xCC; 0x20];
+    code[0x10..0x1A].copy_from_slice(&[0x48, 0x8B, 0x0D, 0, 0, 0, 0, 0x48, 0x85, 0xC9]);
+
+    let base = 0x1_4000_1000;
+    for disp in [0x1234_5678, -0x100, 0] {
+        code[0x13..0x17].copy_from_slice(&i32::to_le_bytes(disp));
+        assert_eq!(
+            signature.resolve(&code, base),
+            Some((base + 0x17).wrapping_add_signed(disp as isize))
+        );
+    }
+
+    // Truncated or unmatched code, and invalid patterns, resolve to nothing
+    assert_eq!(signature.resolve(&code[..0x18], base), None);
+    assert_eq!(signature.resolve(&code[0x11..], base), None);
+    assert_eq!(
+        Signature::new("48 8B 0D X", 3, 7).resolve(&code, base),
+        None
+    );
+}
+
+#[test]
+fn known_signatures() {
+    for game in Game::ALL {
+        let signatures = regulation_manager_signatures(game);
+        assert!(!signatures.is_empty(), "{game:?}");
+        for signature in signatures {
+            // The displacement is a wildcard in the instruction
+            let pattern = pattern(signature.pattern);
+            let mut code = vec![0; pattern.len()];
+            for (i, byte) in signature.pattern.split_ascii_whitespace().enumerate() {
+                code[i] = u8::from_str_radix(byte, 16).unwrap_or(0xAA);
+            }
+            let disp = &code[signature.disp_offset..signature.disp_offset + 4];
+            assert_eq!(disp, [0xAA; 4], "{game:?}");
+            assert!(signature.instruction_end <= pattern.len(), "{game:?}");
+            assert!(pattern.matches(&code), "{game:?}");
+        }
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn no_regulation_manager_outside_windows() {
+    use ppatch::from::layout::ErLayout;
+    assert!(unsafe { ppatch::locate::regulation_manager::<ErLayout>() }.is_none());
+}
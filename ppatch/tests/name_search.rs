@@ -0,0 +1,124 @@
+//! Searching rows by name with [`ParamFile::find_by_name`] and [`NameIndex`].
+
+use ppatch::{
+    name_search::MatchMode,
+    param_file::{ParamFile, ParamFileOwned},
+};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+
+/// Rows of the test param. `None` is a row without a name offset.
+const NAMES: [(u32, Option<&str>); 6] = [
+    (10, Some("Moonveil")),
+    (20, Some("Rivers of Blood")),
+    (30, Some("Épée d'éclat")),
+    (40, Some("")),
+    (50, None),
+    (60, Some("MOONVEIL")),
+];
+
+/// Builds a 64-bit unicode param file with 4 byte rows named after [`NAMES`].
+fn build(big_endian: bool) -> Vec<u8> {
+    let to_bytes = |v: u64| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+    let put = |file: &mut [u8], ofs: usize, size: usize, value: u64| {
+        let bytes = to_bytes(value);
+        let bytes = if big_endian { &bytes[8 - size..] } else { &bytes[..size] };
+        file[ofs..ofs + size].copy_from_slice(bytes);
+    };
+
+    let data_start = HEADER_SIZE + NAMES.len() * DESC_SIZE;
+    let data_end = data_start + 4 * NAMES.len();
+    let mut file = vec![0u8; data_end];
+    put(&mut file, 0, 4, data_end as u64);
+    put(&mut file, 0xA, 2, NAMES.len() as u64);
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2C] = big_endian as u8;
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    put(&mut file, 0x30, 8, data_start as u64);
+
+    for (i, (id, name)) in NAMES.into_iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        put(&mut file, desc, 4, id as u64);
+        put(&mut file, desc + 8, 8, (data_start + 4 * i) as u64);
+        let Some(name) = name
+        else {
+            continue;
+        };
+        let name_offset = file.len();
+        put(&mut file, desc + 16, 8, name_offset as u64);
+        for c in name.encode_utf16().chain([0]) {
+            let ofs = file.len();
+            file.extend([0, 0]);
+            put(&mut file, ofs, 2, c as u64);
+        }
+    }
+    file
+}
+
+fn ids(matches: Vec<(u32, String)>) -> Vec<u32> {
+    matches.into_iter().map(|(id, _)| id).collect()
+}
+
+#[test]
+fn match_modes() {
+    let mut file = build(false);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let find = |needle, mode| ids(param.find_by_name(needle, mode));
+
+    assert_eq!(
+        param.find_by_name("Moonveil", MatchMode::Exact),
+        [(10, "Moonveil".to_owned())]
+    );
+    assert_eq!(find("moonveil", MatchMode::Exact), [0u32; 0]);
+    assert_eq!(find("moonveil", MatchMode::CaseInsensitive), [10, 60]);
+    assert_eq!(find("OF BLO", MatchMode::Substring), [20]);
+    // Empty and missing names never match, even an empty needle
+    assert_eq!(find("", MatchMode::Substring), [10, 20, 30, 60]);
+
+    let fuzzy = MatchMode::Fuzzy { max_distance: 0.25 };
+    assert_eq!(find("rvblood", fuzzy), [20]);
+    assert_eq!(find("Mooonvail", fuzzy), [10, 60]);
+    assert_eq!(find("Moonlight", fuzzy), [0u32; 0]);
+}
+
+#[test]
+fn unicode_needles() {
+    let mut file = build(false);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let find = |needle, mode| ids(param.find_by_name(needle, mode));
+
+    assert_eq!(
+        param.find_by_name("ÉPÉE", MatchMode::Substring),
+        [(30, "Épée d'éclat".to_owned())]
+    );
+    assert_eq!(find("épée d'éclat", MatchMode::CaseInsensitive), [30]);
+    assert_eq!(find("epee", MatchMode::Substring), [0u32; 0]);
+    let exact_fuzzy = MatchMode::Fuzzy { max_distance: 0.0 };
+    assert_eq!(find("épé déclat", exact_fuzzy), [30]);
+
+    // Names of big endian files are converted along with the file
+    let param = ParamFileOwned::from_foreign_bytes(&build(true)).unwrap();
+    assert_eq!(ids(param.find_by_name("épée", MatchMode::Substring)), [30]);
+}
+
+#[test]
+fn name_index_matches_direct_search() {
+    let mut file = build(false);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let index = param.name_index();
+    assert_eq!(index.len(), 4);
+
+    let modes = [
+        MatchMode::Exact,
+        MatchMode::CaseInsensitive,
+        MatchMode::Substring,
+        MatchMode::Fuzzy { max_distance: 0.3 },
+    ];
+    for mode in modes {
+        for needle in ["Moonveil", "moon", "blood", "ÉCLAT", "x", ""] {
+            assert_eq!(index.find(needle, mode), param.find_by_name(needle, mode));
+        }
+    }
+}
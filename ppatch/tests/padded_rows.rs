@@ -0,0 +1,104 @@
+//! Params with padding between rows, parsed with [`ParamFile::from_bytes_with_row_size`].
+
+use ppatch::param_file::{BlockKind, DataBlock, FromBytesError, ParamFile};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const ROW_SIZE: usize = 8;
+
+/// Builds a 64-bit little endian param file with rows 0, 1, ... of [`ROW_SIZE`] bytes, each
+/// followed by the given number of padding bytes. Rows are filled with their ID, and padding
+/// with `0xEE`.
+fn build(padding: &[usize]) -> Vec<u8> {
+    let data_start = HEADER_SIZE + padding.len() * DESC_SIZE;
+    let mut file = vec![0u8; data_start];
+    file[0xA..0xC].copy_from_slice(&(padding.len() as u16).to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[0x30..0x38].copy_from_slice(&(data_start as u64).to_le_bytes());
+
+    for (i, &pad) in padding.iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let offset = file.len();
+        file[desc..desc + 4].copy_from_slice(&(i as u32).to_le_bytes());
+        file[desc + 8..desc + 16].copy_from_slice(&(offset as u64).to_le_bytes());
+        file.extend([i as u8; ROW_SIZE]);
+        file.extend(vec![0xEE; pad]);
+    }
+    let data_end = file.len();
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file.extend(b"strings\0");
+    file
+}
+
+fn row_block(index: usize, offset: usize, len: usize) -> DataBlock {
+    DataBlock {
+        kind: BlockKind::Row(index),
+        offset,
+        len,
+    }
+}
+
+#[test]
+fn uniform_padding() {
+    let mut file = build(&[8, 8, 8]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    // Without the row size, padding is part of the rows
+    assert!(param.has_uniform_rows());
+    assert_eq!(param.row_size(), 2 * ROW_SIZE);
+
+    let param = ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE).unwrap();
+    assert!(param.has_uniform_rows());
+    assert_eq!(param.row_size(), ROW_SIZE);
+    for (i, row) in param.rows().enumerate() {
+        assert_eq!(row.data(), [i as u8; ROW_SIZE]);
+    }
+}
+
+#[test]
+fn irregular_padding() {
+    let mut file = build(&[0, 4, 12, 0]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(!param.has_uniform_rows());
+    let sizes: Vec<_> = param.rows().map(|r| r.len()).collect();
+    assert_eq!(sizes, [8, 12, 20, 8]);
+    assert_eq!(param.row_size(), ROW_SIZE);
+
+    let param = ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE).unwrap();
+    assert!(param.has_uniform_rows());
+    assert!(param.rows().all(|r| r.len() == ROW_SIZE && !r.data().contains(&0xEE)));
+    assert_eq!(param.by_id(3).unwrap().data(), [3; ROW_SIZE]);
+
+    // Smaller rows are fine too, e.g. for a def older than the file
+    let param = ParamFile::from_bytes_with_row_size(&mut file, 4).unwrap();
+    assert_eq!(param[2], [2; 4]);
+}
+
+#[test]
+fn rows_must_fit_before_their_successor() {
+    let mut file = build(&[0, 4, 4]);
+    let data_start = HEADER_SIZE + 3 * DESC_SIZE;
+    assert_eq!(
+        ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE + 4).err(),
+        Some(FromBytesError::IntersectingData {
+            first: row_block(0, data_start, 12),
+            second: row_block(1, data_start + 8, 12),
+        })
+    );
+
+    // The last row must end before the strings
+    let mut file = build(&[4, 4, 0]);
+    let data_end = HEADER_SIZE + 3 * DESC_SIZE + 3 * ROW_SIZE + 8;
+    assert_eq!(
+        ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE + 4).err(),
+        Some(FromBytesError::IntersectingData {
+            first: row_block(2, data_end - ROW_SIZE, 12),
+            second: DataBlock {
+                kind: BlockKind::Strings,
+                offset: data_end,
+                len: 8,
+            },
+        })
+    );
+}
@@ -0,0 +1,175 @@
+//! Inserting and removing rows with [`ParamFileBuilder`], checked by parsing the emitted files
+//! with [`ParamFile::from_bytes`].
+
+use ppatch::{
+    name_search::MatchMode,
+    param_builder::{InsertRowError, ParamFileBuilder},
+    param_file::ParamFile,
+};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const ROW_SIZE: usize = 4;
+
+/// Rows of the test param. `None` is a row without a name offset.
+const ROWS: [(u32, Option<&str>); 4] = [
+    (10, Some("Moonveil")),
+    (20, None),
+    (30, Some("Épée d'éclat")),
+    (40, Some("")),
+];
+
+/// Builds a 64-bit unicode param file with [`ROWS`], whose data is their ID.
+fn build() -> Vec<u8> {
+    let put = |file: &mut [u8], ofs: usize, size: usize, value: u64| {
+        file[ofs..ofs + size].copy_from_slice(&value.to_le_bytes()[..size]);
+    };
+
+    let data_start = HEADER_SIZE + ROWS.len() * DESC_SIZE;
+    let data_end = data_start + ROW_SIZE * ROWS.len();
+    let mut file = vec![0u8; data_end];
+    put(&mut file, 0, 4, data_end as u64);
+    put(&mut file, 0xA, 2, ROWS.len() as u64);
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    put(&mut file, 0x30, 8, data_start as u64);
+
+    for (i, (id, name)) in ROWS.into_iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let data = data_start + ROW_SIZE * i;
+        put(&mut file, desc, 4, id as u64);
+        put(&mut file, desc + 8, 8, data as u64);
+        put(&mut file, data, 4, id as u64);
+        let Some(name) = name
+        else {
+            continue;
+        };
+        let name_offset = file.len();
+        put(&mut file, desc + 16, 8, name_offset as u64);
+        for c in name.encode_utf16().chain([0]) {
+            file.extend(c.to_le_bytes());
+        }
+    }
+    file
+}
+
+/// Returns the ID and first `u32` of each row.
+fn rows(param: &ParamFile) -> Vec<(u32, u32)> {
+    param
+        .rows()
+        .map(|r| {
+            (
+                r.id(),
+                u32::from_le_bytes(r.data()[..4].try_into().unwrap()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn build_from_scratch() {
+    let mut builder = ParamFileBuilder::new("TEST_ST", 8);
+    for id in [30, 10, 20] {
+        builder.insert_row(id, &[id as u8; 8]).unwrap();
+    }
+    assert_eq!(
+        builder.insert_row(20, &[0; 8]),
+        Err(InsertRowError::DuplicateId(20))
+    );
+    assert_eq!(
+        builder.insert_row(40, &[0; 4]),
+        Err(InsertRowError::RowTooSmall {
+            row_size: 8,
+            len: 4
+        })
+    );
+    // Rows of a variable size param may have a tail
+    builder.insert_row(0, &[0xFF; 12]).unwrap();
+    assert_eq!(builder.remove_row(10).as_deref(), Some(&[10; 8][..]));
+    assert_eq!(builder.remove_row(10), None);
+    assert_eq!(builder.ids().collect::<Vec<_>>(), [0, 20, 30]);
+
+    let mut bytes = builder.to_bytes();
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert_eq!(param.header().row_count(), 3);
+    assert_eq!(param.row_size(), 8);
+    assert!(!param.has_uniform_rows());
+    let ids: Vec<_> = param.rows().map(|r| r.id()).collect();
+    assert_eq!(ids, [0, 20, 30]);
+    assert_eq!(param.by_id(0).unwrap().data(), [0xFF; 12]);
+    assert_eq!(param.by_id(30).unwrap().data(), [30; 8]);
+    assert_eq!(param.index_of(20), Some(1));
+
+    // The emitted file can be edited again
+    let mut builder = ParamFileBuilder::from_file(&param);
+    builder.remove_row(0);
+    builder.get_mut(20).unwrap().fill(2);
+    let mut bytes = builder.to_bytes();
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert!(param.has_uniform_rows());
+    assert_eq!(param.by_id(20).unwrap().data(), [2; 8]);
+}
+
+#[test]
+fn round_trip_keeps_rows_and_names() {
+    let mut file = build();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let builder = ParamFileBuilder::from_file(&param);
+    assert_eq!(builder.row_count(), ROWS.len());
+    assert_eq!(builder.row_size(), ROW_SIZE);
+
+    let mut bytes = builder.to_bytes();
+    let copy = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert_eq!(rows(&copy), rows(&param));
+    assert_eq!(
+        copy.raw_header_bytes()[0xC..0x30],
+        param.raw_header_bytes()[0xC..0x30]
+    );
+    assert_eq!(
+        copy.name_index().find("", MatchMode::Substring),
+        param.name_index().find("", MatchMode::Substring)
+    );
+    assert_eq!(
+        copy.find_by_name("épée", MatchMode::Substring),
+        [(30, "Épée d'éclat".to_owned())]
+    );
+}
+
+#[test]
+fn insert_and_remove_rows_of_file() {
+    let mut file = build();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut builder = ParamFileBuilder::from_file(&param);
+    builder.insert_row(25, &25u32.to_le_bytes()).unwrap();
+    builder.insert_row(5, &5u32.to_le_bytes()).unwrap();
+    builder.remove_row(30);
+    assert_eq!(
+        builder.insert_row(40, &[0; 4]),
+        Err(InsertRowError::DuplicateId(40))
+    );
+
+    let mut bytes = builder.to_bytes();
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert_eq!(
+        rows(&param),
+        [(5, 5), (10, 10), (20, 20), (25, 25), (40, 40)]
+    );
+    // Names follow their rows, and inserted rows have none
+    assert_eq!(
+        param.find_by_name("Moonveil", MatchMode::Exact),
+        [(10, "Moonveil".to_owned())]
+    );
+    assert!(param.find_by_name("Épée", MatchMode::Substring).is_empty());
+    let named: Vec<_> = param.row_descriptors().iter().map(|r| r.name_offset != 0).collect();
+    assert_eq!(named, [false, true, false, false, true]);
+
+    // Removing every row leaves a valid empty file
+    for id in [5, 10, 20, 25, 40] {
+        assert!(builder.remove_row(id).is_some());
+    }
+    let mut bytes = builder.to_bytes();
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    assert_eq!(param.header().row_count(), 0);
+    assert_eq!(param.rows().count(), 0);
+}
@@ -0,0 +1,153 @@
+//! Field-granular diffs between params with [`param_diff`], and replaying them as patches.
+
+use ppatch::{
+    diff::{param_diff, ChangedRow, FieldDiff, ParamDiff, ParamDiffError},
+    field_metadata::Block,
+    fields::FieldBlock,
+    param_builder::ParamFileBuilder,
+    param_file::ParamFile,
+    patchers::{base::RowPatcher, linked_list::LinkedListPatcher},
+};
+
+const ROW_SIZE: usize = 12;
+
+/// A u16 at 0, two bitfields sharing the block at 4 and a u32 at 8. Bytes 2..4 are padding.
+fn field_blocks() -> Vec<FieldBlock<Block>> {
+    let blocks = [
+        (0, 0, 0x0000_FFFF),
+        (1, 1, 0x0000_000F),
+        (2, 1, 0xFFFF_FFF0),
+        (3, 2, 0xFFFF_FFFF),
+    ];
+    blocks
+        .iter()
+        .map(|&(field_start, offset, mask)| FieldBlock {
+            field_start,
+            offset,
+            mask,
+        })
+        .collect()
+}
+
+fn row(values: [u32; 3]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+}
+
+fn build(rows: &[(u32, [u32; 3])]) -> Vec<u8> {
+    let mut builder = ParamFileBuilder::new("TEST_ST", ROW_SIZE);
+    for (id, values) in rows {
+        builder.insert_row(*id, &row(*values)).unwrap();
+    }
+    builder.to_bytes()
+}
+
+const BASE: [(u32, [u32; 3]); 4] = [
+    (10, [1, 0x21, 100]),
+    (20, [2, 0x22, 200]),
+    (30, [3, 0x23, 300]),
+    (40, [4, 0x24, 400]),
+];
+
+const MODIFIED: [(u32, [u32; 3]); 4] = [
+    // Padding only
+    (10, [0x0005_0001, 0x21, 100]),
+    // Low bitfield and the u32
+    (20, [2, 0x2A, 201]),
+    (25, [0, 0, 0]),
+    (40, [4, 0x24, 400]),
+];
+
+fn diff() -> ParamDiff {
+    let (mut base, mut modified) = (build(&BASE), build(&MODIFIED));
+    let base = ParamFile::from_bytes(&mut base).unwrap();
+    let modified = ParamFile::from_bytes(&mut modified).unwrap();
+    param_diff(&base, &modified, &field_blocks()).unwrap()
+}
+
+#[test]
+fn rows_and_fields() {
+    let diff = diff();
+    assert_eq!(diff.added, [25]);
+    assert_eq!(diff.removed, [30]);
+    assert_eq!(
+        diff.changed,
+        [ChangedRow {
+            id: 20,
+            fields: vec![
+                FieldDiff {
+                    field_start: 1,
+                    before: vec![0x2],
+                    after: vec![0xA],
+                },
+                FieldDiff {
+                    field_start: 3,
+                    before: vec![200],
+                    after: vec![201],
+                },
+            ],
+        }]
+    );
+
+    let mut file = build(&BASE);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(param_diff(&param, &param, &field_blocks()).unwrap().is_empty());
+}
+
+#[test]
+fn serialized_diffs_replay_as_patches() {
+    let json = serde_json::to_string(&diff()).unwrap();
+    let diff: ParamDiff = serde_json::from_str(&json).unwrap();
+    assert_eq!(diff, self::diff());
+
+    let blocks = field_blocks();
+    let mut file = build(&BASE);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let changed = &diff.changed[0];
+    let mut row = param.by_id_mut(changed.id).unwrap();
+    let row = row.as_blocks_mut::<Block>().unwrap();
+    let mut patcher = LinkedListPatcher::new(&blocks, ROW_SIZE);
+
+    let id = changed.create_patch(&mut patcher, &blocks, row).unwrap();
+    let values: Vec<u32> = row.iter().map(|b| b.read()).collect();
+    assert_eq!(values, [2, 0x2A, 201]);
+
+    patcher.restore_patch(id, row).unwrap();
+    let values: Vec<u32> = row.iter().map(|b| b.read()).collect();
+    assert_eq!(values, BASE[1].1);
+}
+
+#[test]
+fn rows_of_different_sizes() {
+    let mut base = build(&BASE);
+    let mut builder = ParamFileBuilder::new("TEST_ST", ROW_SIZE);
+    builder.insert_row(10, &row(BASE[0].1)).unwrap();
+    builder.insert_row(20, &[row(BASE[1].1), vec![0; 4]].concat()).unwrap();
+    builder.insert_row(30, &row(BASE[2].1)).unwrap();
+    let mut modified = builder.to_bytes();
+
+    let base = ParamFile::from_bytes(&mut base).unwrap();
+    let modified = ParamFile::from_bytes(&mut modified).unwrap();
+    assert_eq!(
+        param_diff(&base, &modified, &field_blocks()),
+        Err(ParamDiffError::RowSizeMismatch {
+            id: 20,
+            base: ROW_SIZE,
+            modified: ROW_SIZE + 4,
+        })
+    );
+
+    // Field blocks of a larger param
+    let mut blocks = field_blocks();
+    blocks.push(FieldBlock {
+        field_start: 4,
+        offset: 3,
+        mask: u32::MAX,
+    });
+    assert_eq!(
+        param_diff(&base, &base, &blocks),
+        Err(ParamDiffError::FieldBlocksOutOfBounds {
+            id: 10,
+            row_size: ROW_SIZE,
+        })
+    );
+}
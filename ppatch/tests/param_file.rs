@@ -0,0 +1,168 @@
+//! Parsing and indexing of [`ParamFile`]s.
+
+use std::mem::{offset_of, size_of};
+
+use ppatch::param_file::{FromBytesError, IndexError, ParamFile, ParamRowDescriptor};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = size_of::<ParamRowDescriptor>();
+const ROW_SIZE: usize = 8;
+/// Format flags of a param file with a 0x40 byte header, with 64-bit offsets on 64-bit targets.
+const FORMAT_FLAGS: u8 = if cfg!(target_pointer_width = "64") { 4 | 3 } else { 3 };
+
+/// Builds a little endian param file of type `TEST_ST` for the target, with the rows 0, 1, ... of
+/// [`ROW_SIZE`] bytes, each filled with its ID. Offsets are 64-bit on 64-bit targets, and 32-bit
+/// otherwise.
+fn build(n_rows: usize) -> Vec<u8> {
+    let data_start = HEADER_SIZE + n_rows * DESC_SIZE;
+    let mut file = vec![0u8; data_start];
+    file[0xA..0xC].copy_from_slice(&(n_rows as u16).to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = FORMAT_FLAGS;
+    file[0x2E] = 1;
+    file[0x30..0x30 + size_of::<usize>()].copy_from_slice(&data_start.to_le_bytes());
+
+    for i in 0..n_rows {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let offset = file.len();
+        let offset_desc = desc + offset_of!(ParamRowDescriptor, data_offset);
+        file[desc..desc + 4].copy_from_slice(&(i as u32).to_le_bytes());
+        file[offset_desc..offset_desc + size_of::<usize>()].copy_from_slice(&offset.to_le_bytes());
+        file.extend([i as u8; ROW_SIZE]);
+    }
+    let data_end = file.len();
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file.extend(b"strings\0");
+    file
+}
+
+/// Files whose offsets are not pointer sized are rejected.
+#[test]
+fn other_bitness_is_rejected() {
+    let mut file = build(2);
+    file[0x2D] ^= 4;
+    let is_64bit = cfg!(target_pointer_width = "32");
+    assert!(matches!(
+        ParamFile::from_bytes(&mut file),
+        Err(FromBytesError::UnsupportedFile {
+            is_big_endian: false,
+            is_64bit: b,
+        }) if b == is_64bit
+    ));
+}
+
+/// 32-bit param files have 12 byte row descriptors with 32-bit offsets, which 32-bit targets
+/// view in place.
+#[cfg(target_pointer_width = "32")]
+#[test]
+fn files_32_bit() {
+    assert_eq!(DESC_SIZE, 12);
+    let mut file = build(2);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(!param.header().is_64_bit());
+    assert_eq!(
+        param.row_descriptors()[1].data_offset,
+        HEADER_SIZE + 2 * 12 + ROW_SIZE
+    );
+    assert_eq!(param.get(1).unwrap().data(), [1; ROW_SIZE]);
+}
+
+/// 64-bit param files have 24 byte row descriptors with 64-bit offsets.
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn files_64_bit() {
+    assert_eq!(DESC_SIZE, 24);
+    let mut file = build(2);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(param.header().is_64_bit());
+    assert_eq!(
+        param.row_descriptors()[1].data_offset,
+        HEADER_SIZE + 2 * 24 + ROW_SIZE
+    );
+    assert_eq!(param.get(1).unwrap().data(), [1; ROW_SIZE]);
+}
+
+/// A file starting 4 bytes into an 8-byte aligned buffer, as the game sometimes hands them out,
+/// is parsed and edited in place.
+#[test]
+fn misaligned_buffer() {
+    let file = build(3);
+    let mut buffer = vec![0u64; (file.len() + 4).div_ceil(8)];
+    // SAFETY: The buffer is at least `file.len() + 4` bytes long, and u8 has no alignment
+    let bytes =
+        unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 8 * buffer.len()) };
+    let misaligned = &mut bytes[4..4 + file.len()];
+    misaligned.copy_from_slice(&file);
+    assert_eq!(misaligned.as_ptr() as usize % 8, 4);
+
+    let mut param = ParamFile::from_bytes(misaligned).unwrap();
+    assert_eq!(param.param_type(), "TEST_ST");
+    assert_eq!(param.header().row_count(), 3);
+    assert_eq!(param.row_size(), ROW_SIZE);
+    let ids: Vec<_> = param.row_descriptors().iter().map(|r| r.id).collect();
+    assert_eq!(ids, [0, 1, 2]);
+    for (i, row) in param.rows().enumerate() {
+        assert_eq!(row.id(), i as u32);
+        assert_eq!(row.data(), [i as u8; ROW_SIZE]);
+    }
+
+    param.by_id_mut(2).unwrap().data_mut()[0] = 0xAA;
+    let row_offset = param.row_descriptors()[2].data_offset;
+    assert_eq!(bytes[4 + row_offset], 0xAA);
+}
+
+/// Out of range indices are returned as errors by the `try_` variants.
+#[test]
+fn try_index() {
+    let mut file = build(2);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+
+    assert_eq!(param.try_index(1).unwrap().data(), [1; ROW_SIZE]);
+    let err = IndexError {
+        index: 2,
+        row_count: 2,
+    };
+    assert_eq!(param.try_index(2).err(), Some(err));
+    assert_eq!(err.to_string(), "row index 2 is out of range for 2 rows");
+
+    param.try_index_mut(0).unwrap().data_mut()[0] = 0xAA;
+    assert_eq!(param[0][0], 0xAA);
+    assert_eq!(param.try_index_mut(5).err().map(|e| e.index), Some(5));
+}
+
+/// Indexing panics with the param type and row count in the message.
+#[test]
+#[should_panic(expected = "row index 3 out of range for param TEST_ST with 2 rows")]
+fn index_panic() {
+    let mut file = build(2);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let _ = &param[3];
+}
+
+#[test]
+#[should_panic(expected = "row index 2 out of range for param TEST_ST with 2 rows")]
+fn index_mut_panic() {
+    let mut file = build(2);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    param[2][0] = 0;
+}
+
+/// Files matching the checksum of a validated file skip validation, others are validated.
+#[test]
+fn revalidate_cheap() {
+    let mut file = build(2);
+    let checksum = ParamFile::from_bytes(&mut file).unwrap().validation_checksum();
+
+    // SAFETY: The checksum is the one of this file, validated by `from_bytes`
+    let param = unsafe { ParamFile::revalidate_cheap(&mut file, &checksum) }.unwrap();
+    assert_eq!(param.get(1).unwrap().data(), [1; ROW_SIZE]);
+
+    // An out of bounds row offset no longer matches, and fails validation
+    let offset_desc = HEADER_SIZE + DESC_SIZE + offset_of!(ParamRowDescriptor, data_offset);
+    file[offset_desc..offset_desc + size_of::<usize>()].copy_from_slice(&usize::MAX.to_le_bytes());
+    // SAFETY: As above, and the edited descriptors don't match the checksum
+    assert!(matches!(
+        unsafe { ParamFile::revalidate_cheap(&mut file, &checksum) },
+        Err(FromBytesError::OutOfBoundsOffset { .. })
+    ));
+}
@@ -0,0 +1,187 @@
+//! Patching rows of several params with a [`ParamPatchManager`].
+
+use std::collections::HashMap;
+
+use ppatch::{
+    field_metadata::{load_fb_repo, serialize_fb_repo, Block, FieldBlock, FieldBlockRepo},
+    patchers::{
+        base::RestorePatchError,
+        linked_list::LinkedListPatcher,
+        manager::{CreatePatchError, ParamPatchManager, PatchHandle},
+        single_patch::SinglePatchPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+
+/// Repo where each block of a row of `blocks` blocks is a field.
+fn repo(params: &[(&str, u16)]) -> Box<[u8]> {
+    let repo: FieldBlockRepo = params
+        .iter()
+        .map(|&(param_type, blocks)| {
+            let fields = (0..blocks).map(|i| FieldBlock {
+                field_start: i,
+                offset: i,
+                mask: Block::MAX,
+            });
+            (param_type.to_owned(), fields.collect())
+        })
+        .collect();
+    serialize_fb_repo(&repo)
+}
+
+/// Live rows, keyed by param type and row ID.
+type Rows = HashMap<(&'static str, u32), Vec<Unaligned<Block>>>;
+
+fn row<'r>(
+    rows: &'r mut Rows,
+    param_type: &'static str,
+    row_id: u32,
+) -> &'r mut [Unaligned<Block>] {
+    let len = if param_type == "A_ST" { 2 } else { 3 };
+    rows.entry((param_type, row_id)).or_insert_with(|| vec![Unaligned(0); len])
+}
+
+/// Writes `value` to a block of a row and creates a patch for it.
+fn patch<'a>(
+    manager: &mut ParamPatchManager<'a, LinkedListPatcher<'a>>,
+    rows: &mut Rows,
+    (param_type, row_id): (&'static str, u32),
+    block: usize,
+    value: Block,
+) -> PatchHandle<'a> {
+    let live = row(rows, param_type, row_id);
+    let before = live.to_vec();
+    live[block] = Unaligned(value);
+    manager.create_patch(param_type, row_id, &before, live).unwrap()
+}
+
+#[test]
+fn interleaved_patches() {
+    let bytes = repo(&[("A_ST", 2), ("B_ST", 3)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<LinkedListPatcher>::new(repo);
+    let mut rows = Rows::new();
+
+    let a10 = patch(&mut manager, &mut rows, ("A_ST", 10), 0, 1);
+    let b10 = patch(&mut manager, &mut rows, ("B_ST", 10), 2, 2);
+    let a20 = patch(&mut manager, &mut rows, ("A_ST", 20), 1, 3);
+    let a10_2 = patch(&mut manager, &mut rows, ("A_ST", 10), 0, 4);
+    let b30 = patch(&mut manager, &mut rows, ("B_ST", 30), 0, 5);
+    assert_eq!(a10.param_type, "A_ST");
+    assert_eq!((b10.param_type, b10.row_id), ("B_ST", 10));
+
+    assert_eq!(manager.outstanding("A_ST"), [a10, a10_2, a20]);
+    assert_eq!(manager.outstanding("B_ST"), [b10, b30]);
+    assert_eq!(manager.outstanding("C_ST"), []);
+
+    // Restoring patches of other rows leaves the handles of this one valid
+    manager.restore(b10, row(&mut rows, "B_ST", 10)).unwrap();
+    manager.restore(a20, row(&mut rows, "A_ST", 20)).unwrap();
+    manager.restore(a10, row(&mut rows, "A_ST", 10)).unwrap();
+    assert_eq!(rows[&("A_ST", 10)], [Unaligned(4), Unaligned(0)]);
+    assert_eq!(rows[&("A_ST", 20)], [Unaligned(0); 2]);
+    assert_eq!(rows[&("B_ST", 10)], [Unaligned(0); 3]);
+    assert_eq!(
+        manager.restore(a10, row(&mut rows, "A_ST", 10)),
+        Err(RestorePatchError::UnknownId)
+    );
+    assert_eq!(manager.outstanding("A_ST"), [a10_2]);
+
+    // Bulk revert of a param
+    for handle in manager.outstanding("B_ST") {
+        manager.restore(handle, row(&mut rows, "B_ST", handle.row_id)).unwrap();
+    }
+    assert_eq!(rows[&("B_ST", 30)], [Unaligned(0); 3]);
+    assert_eq!(manager.outstanding("B_ST"), []);
+    assert_eq!(manager.outstanding("A_ST"), [a10_2]);
+    assert!(manager.row_patcher("B_ST", 30).is_some());
+    assert!(manager.row_patcher("B_ST", 40).is_none());
+}
+
+#[test]
+fn patch_errors() {
+    let bytes = repo(&[("A_ST", 2)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<SinglePatchPatcher>::new(repo);
+    let before = [Unaligned(0); 2];
+    let after = [Unaligned(1), Unaligned(0)];
+
+    assert_eq!(
+        manager.create_patch("C_ST", 10, &before, &after),
+        Err(CreatePatchError::UnknownParamType)
+    );
+    let handle = manager.create_patch("A_ST", 10, &before, &after).unwrap();
+    // The single patch patcher refuses a second outstanding patch for the same row only
+    assert_eq!(
+        manager.create_patch("A_ST", 10, &after, &before),
+        Err(CreatePatchError::PatchRejected)
+    );
+    assert!(manager.create_patch("A_ST", 20, &before, &after).is_ok());
+
+    // Handles of another manager are rejected
+    let mut other = ParamPatchManager::<SinglePatchPatcher>::new(repo);
+    let other_handle = other.create_patch("A_ST", 10, &before, &after).unwrap();
+    let mut live = after;
+    assert_eq!(
+        manager.restore(other_handle, &mut live),
+        Err(RestorePatchError::ForeignId)
+    );
+    manager.restore(handle, &mut live).unwrap();
+    assert_eq!(live, before);
+}
+
+/// Rows are checked against the size set by their first patch before reaching the patcher, which
+/// would panic on shorter rows.
+/// A first patch too short for the field blocks is rejected without setting the size of the row.
+#[test]
+fn short_first_patch() {
+    let bytes = repo(&[("B_ST", 3)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<LinkedListPatcher>::new(repo);
+    let before = [Unaligned(0); 3];
+    let after = [Unaligned(1), Unaligned(0), Unaligned(2)];
+
+    assert_eq!(
+        manager.create_patch("B_ST", 10, &before[..2], &after[..2]),
+        Err(CreatePatchError::FieldBlocksOutOfBounds { row_blocks: 2 })
+    );
+    assert!(manager.outstanding("B_ST").is_empty());
+    let handle = manager.create_patch("B_ST", 10, &before, &after).unwrap();
+    let mut live = after;
+    manager.restore(handle, &mut live).unwrap();
+    assert_eq!(live, before);
+}
+
+#[test]
+fn row_size_mismatch() {
+    let bytes = repo(&[("B_ST", 3)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+    let mut manager = ParamPatchManager::<LinkedListPatcher>::new(repo);
+    let before = [Unaligned(0); 3];
+    let after = [Unaligned(1), Unaligned(0), Unaligned(2)];
+    let mismatch = |expected, actual| CreatePatchError::SizeMismatch { expected, actual };
+
+    assert_eq!(
+        manager.create_patch("B_ST", 10, &before, &after[..2]),
+        Err(mismatch(3, 2))
+    );
+    let handle = manager.create_patch("B_ST", 10, &before, &after).unwrap();
+    assert_eq!(
+        manager.create_patch("B_ST", 10, &after[..1], &before[..1]),
+        Err(mismatch(3, 1))
+    );
+
+    let mut live = after;
+    assert_eq!(
+        manager.restore(handle, &mut live[..2]),
+        Err(RestorePatchError::SizeMismatch {
+            expected: 3,
+            actual: 2
+        })
+    );
+    assert_eq!(live, after);
+    // The patch is still outstanding
+    assert_eq!(manager.outstanding("B_ST"), [handle]);
+    manager.restore(handle, &mut live).unwrap();
+    assert_eq!(live, before);
+}
@@ -0,0 +1,88 @@
+//! Looking up the field blocks of a param file by the param type it stores, with
+//! [`FbRepoExt::get_normalized`].
+
+use ppatch::{
+    field_metadata::{load_fb_repo, serialize_fb_repo, FbRepoExt, FieldBlock, FieldBlockRepo},
+    param_builder::ParamFileBuilder,
+    param_file::ParamFile,
+};
+
+/// Repo whose param types each have a single block, with the mask identifying the param type.
+fn repo(params: &[(&str, u32)]) -> Box<[u8]> {
+    let repo: FieldBlockRepo = params
+        .iter()
+        .map(|&(param_type, mask)| {
+            let block = FieldBlock {
+                field_start: 0,
+                offset: 0,
+                mask,
+            };
+            (param_type.to_owned(), vec![block])
+        })
+        .collect();
+    serialize_fb_repo(&repo)
+}
+
+/// Builds a 64-bit param file without rows, whose param type is stored in the header.
+fn inline_param_type(param_type: &[u8; 32]) -> Vec<u8> {
+    let mut file = vec![0u8; 0x40];
+    file[0..4].copy_from_slice(&0x40u32.to_le_bytes());
+    file[0xC..0x2C].copy_from_slice(param_type);
+    file[0x2D] = 4 | 3;
+    file[0x30..0x38].copy_from_slice(&0x40u64.to_le_bytes());
+    file
+}
+
+fn mask_of(repo: &impl FbRepoExt<u32>, raw: &[u8]) -> Option<u32> {
+    repo.get_normalized(raw).map(|blocks| blocks[0].mask)
+}
+
+#[test]
+fn normalized_param_types() {
+    let bytes = repo(&[
+        ("EQUIP_PARAM_WEAPON_ST", 1),
+        ("NpcParam", 2),
+        ("npcparam", 3),
+    ]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+
+    assert_eq!(mask_of(repo, b"EQUIP_PARAM_WEAPON_ST"), Some(1));
+    assert_eq!(mask_of(repo, b"equip_param_weapon_st"), Some(1));
+    assert_eq!(mask_of(repo, b"EQUIP_PARAM_WEAPON_ST  \0\0\0\0"), Some(1));
+    assert_eq!(mask_of(repo, b" Equip_Param_Weapon_ST\0garbage"), Some(1));
+    // Exact matches win over case-insensitive ones
+    assert_eq!(mask_of(repo, b"NpcParam"), Some(2));
+    assert_eq!(mask_of(repo, b"npcparam\0"), Some(3));
+    assert!(matches!(mask_of(repo, b"NPCPARAM"), Some(2 | 3)));
+
+    assert_eq!(mask_of(repo, b"EQUIP_PARAM_WEAPON"), None);
+    assert_eq!(mask_of(repo, b"EQUIP_PARAM_WEAPON_ST_X"), None);
+    assert_eq!(mask_of(repo, b""), None);
+    assert_eq!(mask_of(repo, b"\xFF\xFE"), None);
+}
+
+#[test]
+fn param_types_of_both_forms() {
+    let bytes = repo(&[("EQUIP_PARAM_GOODS_ST", 1)]);
+    let repo = unsafe { load_fb_repo(&bytes) }.unwrap();
+
+    // Stored in the 32-byte header buffer, padded with spaces
+    let mut param_type = [0; 32];
+    param_type[..24].copy_from_slice(b"equip_param_goods_st    ");
+    let mut file = inline_param_type(&param_type);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.header().param_type_offset(), None);
+    assert_eq!(param.param_type(), "equip_param_goods_st    ");
+    assert_eq!(mask_of(repo, param.param_type_bytes()), Some(1));
+
+    // Stored after the row data
+    let mut builder = ParamFileBuilder::new("Equip_Param_Goods_ST ", 4);
+    builder.insert_row(10, &[0; 4]).unwrap();
+    builder.set_row_name(10, Some("Estus Flask"));
+    let mut file = builder.to_bytes();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let offset = param.header().param_type_offset().unwrap();
+    assert_eq!(offset, param.header().data_end_ofs());
+    assert_eq!(param.param_type(), "Equip_Param_Goods_ST ");
+    assert_eq!(mask_of(repo, param.param_type_bytes()), Some(1));
+}
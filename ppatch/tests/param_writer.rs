@@ -0,0 +1,194 @@
+//! Writing param files from a paramdef with [`ParamFileWriter`], checked by parsing the written
+//! files.
+
+use ppatch::{
+    param_file::{
+        write::{ParamFileWriter, ParamFormat, WriteParamError},
+        ParamFile, ParamFileOwned,
+    },
+    paramdex::{paramdef::Paramdef, Paramdex},
+};
+
+const ROW_SIZE: usize = 8;
+
+const DEF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<PARAMDEF XmlVersion="3">
+  <ParamType>WRITER_TEST_PARAM_ST</ParamType>
+  <DataVersion>5</DataVersion>
+  <BigEndian>False</BigEndian>
+  <Unicode>True</Unicode>
+  <FormatVersion>203</FormatVersion>
+  <Fields>
+    <Field Def="s32 hp" />
+    <Field Def="f32 weight" />
+  </Fields>
+</PARAMDEF>"#;
+
+fn def() -> Paramdef {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.add_def_xml("WriterTestParam", DEF).unwrap();
+    paramdex.compute_def_layouts(10400);
+    paramdex.def_with_meta("WriterTestParam").unwrap().def.clone()
+}
+
+/// Rows of the test param, out of ID order, whose data is their ID followed by zeros.
+const ROWS: [(u32, Option<&str>); 4] = [
+    (30, Some("Moonveil")),
+    (10, Some("Épée d'éclat")),
+    (20, None),
+    (40, Some("Moonveil")),
+];
+
+fn row_data(id: u32) -> [u8; ROW_SIZE] {
+    let mut data = [0; ROW_SIZE];
+    data[..4].copy_from_slice(&id.to_ne_bytes());
+    data
+}
+
+fn write(def: &Paramdef, format: ParamFormat) -> Result<Vec<u8>, WriteParamError> {
+    let data: Vec<_> = ROWS.iter().map(|&(id, _)| row_data(id)).collect();
+    let rows = ROWS.iter().zip(&data).map(|(&(id, name), data)| (id, name, &data[..]));
+    ParamFileWriter::new(def, format).write(rows)
+}
+
+/// Checks the rows of a file written with [`ROWS`].
+fn check_rows(param: &ParamFile) {
+    let ids: Vec<_> = param.rows().map(|row| row.id()).collect();
+    assert_eq!(ids, [10, 20, 30, 40]);
+    for row in param.rows() {
+        assert_eq!(row.data(), row_data(row.id()));
+    }
+    let names: Vec<_> = (0..4).map(|i| param.row_name(i)).collect();
+    assert_eq!(
+        names,
+        [
+            Some("Épée d'éclat".into()),
+            None,
+            Some("Moonveil".into()),
+            Some("Moonveil".into())
+        ]
+    );
+    assert_eq!(param.param_type(), "WRITER_TEST_PARAM_ST");
+    assert_eq!(param.row_size(), ROW_SIZE);
+}
+
+#[test]
+fn round_trip() {
+    let def = def();
+    let mut file = write(&def, ParamFormat::default()).unwrap();
+    let len = file.len();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    check_rows(&param);
+    assert!(param.format_warnings().is_empty());
+
+    let header = param.header();
+    assert_eq!(header.header_size(), 0x40);
+    assert!(header.is_unicode());
+    assert_eq!(header.paramdef_data_version(), 5);
+    // Row data, then the param type, then the names
+    let data_start = 0x40 + 4 * 24;
+    assert_eq!(param.row_descriptors()[0].data_offset, data_start);
+    assert_eq!(header.data_end_ofs(), data_start + 4 * ROW_SIZE);
+    assert_eq!(header.param_type_offset(), Some(header.data_end_ofs()));
+    let strings: Vec<_> = param.strings().map(|(_, name)| name).collect();
+    assert_eq!(strings, ["Épée d'éclat", "Moonveil"]);
+    let last_name = param.row_descriptors()[3].name_offset;
+    assert_eq!(last_name, param.row_descriptors()[2].name_offset);
+    assert_eq!(len, last_name + 2 * ("Moonveil".len() + 1));
+}
+
+#[test]
+fn formats() {
+    let def = def();
+    for bits in 0..16u8 {
+        let format = ParamFormat {
+            is_64_bit: (bits & 1) != 0,
+            is_unicode: (bits & 2) != 0,
+            long_header: (bits & 4) != 0,
+            param_type_offset: (bits & 8) != 0,
+        };
+        let mut file = write(&def, format).unwrap();
+        let owned = ParamFileOwned::from_foreign_bytes(&file).unwrap();
+        check_rows(&owned);
+        assert_eq!(owned.source_is_64_bit(), format.is_64_bit);
+        assert_eq!(owned.header().is_unicode(), format.is_unicode);
+        let param_type_offset = owned.header().param_type_offset();
+        assert_eq!(param_type_offset.is_some(), format.param_type_offset);
+
+        let header_size = if format.is_64_bit || format.long_header { 0x40 } else { 0x30 };
+        if format.is_64_bit == cfg!(target_pointer_width = "64") {
+            let param = ParamFile::from_bytes(&mut file).unwrap();
+            check_rows(&param);
+            assert_eq!(param.header().header_size(), header_size);
+        }
+    }
+}
+
+#[test]
+fn small_params() {
+    let def = def();
+    for param_type_offset in [false, true] {
+        let format = ParamFormat {
+            param_type_offset,
+            ..Default::default()
+        };
+        let writer = ParamFileWriter::new(&def, format);
+        let mut file = writer.write([]).unwrap();
+        let param = ParamFile::from_bytes(&mut file).unwrap();
+        assert_eq!(param.rows().count(), 0);
+        assert_eq!(param.param_type(), "WRITER_TEST_PARAM_ST");
+
+        // The row size of a single row is inferred, as it is written before the param type
+        let mut file = writer.write([(7, None, &row_data(7)[..])]).unwrap();
+        let param = ParamFile::from_bytes(&mut file).unwrap();
+        assert_eq!(param.row_size(), ROW_SIZE);
+        assert_eq!(param.by_id(7).unwrap().data(), row_data(7));
+    }
+}
+
+#[test]
+fn errors() {
+    let def = def();
+    let writer = ParamFileWriter::new(&def, ParamFormat::default());
+    assert_eq!(
+        writer.write([(1, None, &[0; ROW_SIZE][..]), (2, None, &[0; 4][..])]),
+        Err(WriteParamError::RowSizeMismatch {
+            id: 2,
+            row_size: ROW_SIZE,
+            len: 4
+        })
+    );
+    assert_eq!(
+        writer.write([
+            (2, None, &[0; ROW_SIZE][..]),
+            (2, Some("two"), &[1; ROW_SIZE][..])
+        ]),
+        Err(WriteParamError::DuplicateId(2))
+    );
+
+    let mut no_layout = def.clone();
+    no_layout.size_bytes = None;
+    assert_eq!(
+        write(&no_layout, ParamFormat::default()),
+        Err(WriteParamError::UnknownRowSize {
+            param_type: "WRITER_TEST_PARAM_ST".to_owned()
+        })
+    );
+
+    // Param types stored in the header are limited to 32 bytes
+    let mut long_type = def.clone();
+    long_type.param_type = "A".repeat(33);
+    let inline = ParamFormat {
+        param_type_offset: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        write(&long_type, inline),
+        Err(WriteParamError::ParamTypeTooLong { len: 33 })
+    );
+    assert!(write(&long_type, ParamFormat::default()).is_ok());
+    long_type.param_type.pop();
+    let mut file = write(&long_type, inline).unwrap();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.param_type(), "A".repeat(32));
+}
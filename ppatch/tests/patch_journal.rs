@@ -0,0 +1,120 @@
+//! Undoing patches in reverse creation order with [`PatchJournal`].
+
+use ppatch::{
+    fields::FieldBlock,
+    patchers::{
+        base::RowPatcher, full_copy::FullCopyPatcher, journal::PatchJournal,
+        linked_list::LinkedListPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+
+const ROW_BLOCKS: usize = 4;
+
+/// One field per block.
+const FIELD_BLOCKS: [FieldBlock<u32>; ROW_BLOCKS] = [
+    FieldBlock {
+        field_start: 0,
+        offset: 0,
+        mask: u32::MAX,
+    },
+    FieldBlock {
+        field_start: 1,
+        offset: 1,
+        mask: u32::MAX,
+    },
+    FieldBlock {
+        field_start: 2,
+        offset: 2,
+        mask: u32::MAX,
+    },
+    FieldBlock {
+        field_start: 3,
+        offset: 3,
+        mask: u32::MAX,
+    },
+];
+
+const ORIGINAL: [u32; ROW_BLOCKS] = [1, 2, 3, 4];
+
+fn read(live: &[Unaligned<u32>]) -> [u32; ROW_BLOCKS] {
+    std::array::from_fn(|i| live[i].read())
+}
+
+/// Writes `value` to the block at `offset` and records the change with `tag`.
+fn edit<'a, P: RowPatcher<'a, u32>>(
+    journal: &mut PatchJournal<P>,
+    live: &mut [Unaligned<u32>],
+    offset: usize,
+    value: u32,
+    tag: &str,
+) -> u64 {
+    let before = live.to_vec();
+    live[offset] = Unaligned(value);
+    journal.create_patch_tagged(&before, live, Some(tag)).unwrap().seq
+}
+
+fn interleaved_tags<'a, P: RowPatcher<'a, u32>>() {
+    let mut journal = PatchJournal::<P>::new(&FIELD_BLOCKS, 4 * ROW_BLOCKS);
+    let mut live = ORIGINAL.map(Unaligned);
+
+    edit(&mut journal, &mut live, 0, 10, "a");
+    edit(&mut journal, &mut live, 1, 20, "b");
+    edit(&mut journal, &mut live, 0, 11, "a");
+    edit(&mut journal, &mut live, 2, 30, "a");
+    edit(&mut journal, &mut live, 3, 40, "b");
+    // Overwrites a field last changed by "a"
+    edit(&mut journal, &mut live, 2, 31, "b");
+    let expected_b = [1, 20, 31, 40];
+
+    let reverted = journal.revert_tag("a", &mut live).unwrap();
+    assert_eq!(
+        reverted.iter().map(|e| e.seq).collect::<Vec<_>>(),
+        [3, 2, 0]
+    );
+    assert_eq!(read(&live), expected_b);
+    assert!(journal.journal().all(|e| e.tag.as_deref() == Some("b")));
+    assert!(journal.revert_tag("a", &mut live).unwrap().is_empty());
+
+    journal.revert_tag("b", &mut live).unwrap();
+    assert_eq!(read(&live), ORIGINAL);
+    assert_eq!(journal.journal().count(), 0);
+}
+
+#[test]
+fn revert_interleaved_tags() {
+    interleaved_tags::<LinkedListPatcher<u32>>();
+    interleaved_tags::<FullCopyPatcher<u32>>();
+}
+
+#[test]
+fn undo_in_reverse_order() {
+    let mut journal = PatchJournal::<LinkedListPatcher<u32>>::new(&FIELD_BLOCKS, 4 * ROW_BLOCKS);
+    let mut live = ORIGINAL.map(Unaligned);
+
+    let first = edit(&mut journal, &mut live, 0, 10, "a");
+    edit(&mut journal, &mut live, 0, 11, "a");
+    edit(&mut journal, &mut live, 1, 20, "b");
+    let last = edit(&mut journal, &mut live, 2, 30, "b");
+
+    assert_eq!(journal.undo_last(&mut live).unwrap().unwrap().seq, last);
+    assert_eq!(read(&live), [11, 20, 3, 4]);
+
+    let undone = journal.undo_until(first, &mut live).unwrap();
+    assert_eq!(undone.iter().map(|e| e.seq).collect::<Vec<_>>(), [2, 1]);
+    assert_eq!(read(&live), [10, 2, 3, 4]);
+
+    // Sequence numbers keep increasing after undoing
+    assert_eq!(edit(&mut journal, &mut live, 3, 40, "b"), last + 1);
+    let seqs: Vec<_> = journal.journal().map(|e| e.seq).collect();
+    assert_eq!(seqs, [first, last + 1]);
+
+    // Restoring through the patcher API also removes the entry
+    let id = journal.journal().next().unwrap().id;
+    journal.restore_patch(id, &mut live).unwrap();
+    assert!(journal.entry(id).is_none());
+
+    journal.undo_last(&mut live).unwrap();
+    assert_eq!(read(&live), ORIGINAL);
+    assert_eq!(journal.undo_last(&mut live), Ok(None));
+}
@@ -0,0 +1,97 @@
+//! Block views of rows and [`RowPatcherExt::patch_row`].
+
+use ppatch::{
+    param_file::{ParamFile, UnalignedRowSize},
+    patchers::{
+        base::{FieldBlock, PatchRowError, RowPatcher, RowPatcherExt},
+        single_patch::SinglePatchPatcher,
+    },
+};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+
+/// Builds a 64-bit little endian param file with `n_rows` rows of `row_size` bytes, each filled
+/// with its ID.
+fn build(n_rows: usize, row_size: usize) -> Vec<u8> {
+    let data_start = HEADER_SIZE + n_rows * DESC_SIZE;
+    let mut file = vec![0u8; data_start];
+    file[0xA..0xC].copy_from_slice(&(n_rows as u16).to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[0x30..0x38].copy_from_slice(&(data_start as u64).to_le_bytes());
+
+    for i in 0..n_rows {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let offset = file.len();
+        file[desc..desc + 4].copy_from_slice(&(i as u32).to_le_bytes());
+        file[desc + 8..desc + 16].copy_from_slice(&(offset as u64).to_le_bytes());
+        file.extend(vec![i as u8; row_size]);
+    }
+    let data_end = file.len();
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file.extend(b"strings\0");
+    file
+}
+
+/// One 4-byte field per block of a row of `row_size` bytes.
+fn field_blocks(row_size: usize) -> Vec<FieldBlock<u32>> {
+    (0..row_size / 4)
+        .map(|i| FieldBlock {
+            field_start: i as u16,
+            offset: i as u16,
+            mask: u32::MAX,
+        })
+        .collect()
+}
+
+#[test]
+fn unaligned_row_size() {
+    let mut file = build(2, 6);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.row_size(), 6);
+    let unaligned = UnalignedRowSize {
+        row_size: 6,
+        block_size: 4,
+    };
+
+    let row = param.get(0).unwrap();
+    assert_eq!(row.as_blocks::<u32>().err(), Some(unaligned));
+    assert_eq!(row.as_blocks::<u16>().unwrap().len(), 3);
+
+    let fbs = field_blocks(8);
+    let mut patcher = SinglePatchPatcher::<u32>::try_new(&fbs, 8).unwrap();
+    let mut row = param.get_mut(1).unwrap();
+    assert_eq!(row.as_blocks_mut::<u32>().err(), Some(unaligned));
+
+    let mut edited = false;
+    let result = patcher.patch_row(&mut row, |data| {
+        edited = true;
+        data.fill(0xFF);
+    });
+    assert_eq!(result, Err(PatchRowError::UnalignedRowSize(unaligned)));
+    assert!(!edited, "the edit must not run on unaligned rows");
+    assert_eq!(row.data(), [1; 6]);
+    assert!(!patcher.is_patched());
+}
+
+#[test]
+fn patch_row_rolls_back_rejected_edits() {
+    let mut file = build(1, 8);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let fbs = field_blocks(8);
+    let mut patcher = SinglePatchPatcher::<u32>::try_new(&fbs, 8).unwrap();
+
+    let mut row = param.get_mut(0).unwrap();
+    let id = patcher.patch_row(&mut row, |data| data[0] = 0xAA).unwrap();
+    assert_eq!(row.data(), [0xAA, 0, 0, 0, 0, 0, 0, 0]);
+
+    // The single patch patcher refuses a second patch
+    let result = patcher.patch_row(&mut row, |data| data[4] = 0xBB);
+    assert_eq!(result, Err(PatchRowError::PatchRejected));
+    assert_eq!(row.data(), [0xAA, 0, 0, 0, 0, 0, 0, 0]);
+
+    patcher.restore_patch(id, row.as_blocks_mut().unwrap()).unwrap();
+    assert_eq!(row.data(), [0; 8]);
+}
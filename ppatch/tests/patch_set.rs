@@ -0,0 +1,274 @@
+//! Saving the outstanding patches of a [`ParamPatchManager`] as a [`SerializablePatchSet`], and
+//! replaying them on the params of a later session.
+
+use ppatch::{
+    field_metadata::{load_fb_repo, serialize_fb_repo, Block, FieldBlock, FieldBlockRepo},
+    fields::write_field_bytes,
+    param_builder::ParamFileBuilder,
+    param_file::ParamFile,
+    patchers::{
+        linked_list::LinkedListPatcher,
+        manager::{ParamPatchManager, PatchHandle},
+        patch_set::{
+            PatchSetError, RegulationFingerprint, SavedField, SavedPatch, SerializablePatchSet,
+            PATCH_SET_FORMAT_VERSION,
+        },
+    },
+};
+
+const ROW_SIZE: usize = 12;
+const PARAM_TYPES: [&str; 2] = ["A_PARAM_ST", "B_PARAM_ST"];
+
+/// A u16 at 0, two bitfields sharing the block at 4 and a u32 at 8. Bytes 2..4 are padding.
+fn field_blocks() -> Vec<FieldBlock<Block>> {
+    let blocks = [
+        (0, 0, 0x0000_FFFF),
+        (1, 1, 0x0000_000F),
+        (2, 1, 0xFFFF_FFF0),
+        (3, 2, 0xFFFF_FFFF),
+    ];
+    blocks
+        .iter()
+        .map(|&(field_start, offset, mask)| FieldBlock {
+            field_start,
+            offset,
+            mask,
+        })
+        .collect()
+}
+
+fn repo() -> Box<[u8]> {
+    let repo: FieldBlockRepo =
+        PARAM_TYPES.iter().map(|pt| (pt.to_string(), field_blocks())).collect();
+    serialize_fb_repo(&repo)
+}
+
+/// Param files of a regulation, the second param having `extra_rows` more rows.
+fn regulation(extra_rows: u32) -> Vec<Vec<u8>> {
+    PARAM_TYPES
+        .iter()
+        .enumerate()
+        .map(|(i, param_type)| {
+            let mut builder = ParamFileBuilder::new(param_type, ROW_SIZE);
+            for id in (10..40 + 10 * i as u32 * extra_rows).step_by(10) {
+                builder.insert_row(id, &[id as u8; ROW_SIZE]).unwrap();
+            }
+            builder.to_bytes()
+        })
+        .collect()
+}
+
+fn parse(files: &mut [Vec<u8>]) -> Vec<ParamFile<'_>> {
+    files.iter_mut().map(|f| ParamFile::from_bytes(f).unwrap()).collect()
+}
+
+fn rows(params: &[ParamFile<'_>]) -> Vec<Vec<u8>> {
+    params.iter().flat_map(|p| p.rows().map(|r| r.data().to_vec())).collect()
+}
+
+type Manager<'a> = ParamPatchManager<'a, LinkedListPatcher<'a>>;
+
+/// Writes `value` to a field of a row and creates a patch of the change.
+fn patch<'a>(
+    manager: &mut Manager<'a>,
+    params: &mut [ParamFile<'_>],
+    (param, row_id): (usize, u32),
+    field_start: u16,
+    value: u32,
+) -> PatchHandle<'a> {
+    let mut row = params[param].by_id_mut(row_id).unwrap();
+    let live = row.as_blocks_mut::<Block>().unwrap();
+    let before = live.to_vec();
+    write_field_bytes(live, &field_blocks(), field_start, &value.to_le_bytes());
+    manager.create_patch(PARAM_TYPES[param], row_id, &before, live).unwrap()
+}
+
+fn restore<'a>(manager: &mut Manager<'a>, params: &mut [ParamFile<'_>], handle: PatchHandle<'a>) {
+    let param = PARAM_TYPES.iter().position(|&pt| pt == handle.param_type).unwrap();
+    let mut row = params[param].by_id_mut(handle.row_id).unwrap();
+    manager.restore(handle, row.as_blocks_mut().unwrap()).unwrap();
+}
+
+#[test]
+fn save_and_replay() {
+    let repo_bytes = repo();
+    let repo = unsafe { load_fb_repo(&repo_bytes) }.unwrap();
+    let mut files = regulation(0);
+    let mut params = parse(&mut files);
+    let mut manager = Manager::new(repo);
+
+    let bottom = patch(&mut manager, &mut params, (0, 10), 0, 5);
+    patch(&mut manager, &mut params, (0, 10), 0, 6);
+    patch(&mut manager, &mut params, (0, 10), 2, 0xABC);
+    patch(&mut manager, &mut params, (1, 30), 3, 7);
+    patch(&mut manager, &mut params, (0, 20), 1, 3);
+    // The change of the restored patch is hidden by the next one, whose diff now holds it
+    restore(&mut manager, &mut params, bottom);
+
+    let fingerprint = RegulationFingerprint::compute(&params);
+    let set = manager.patch_set(fingerprint);
+    assert_eq!(set.fingerprint, fingerprint);
+    let patched: Vec<_> = set.patches.iter().map(|p| (p.param_type.as_str(), p.row_id)).collect();
+    assert_eq!(
+        patched,
+        [
+            ("A_PARAM_ST", 10),
+            ("A_PARAM_ST", 10),
+            ("A_PARAM_ST", 20),
+            ("B_PARAM_ST", 30)
+        ]
+    );
+    assert_eq!(
+        set.patches[0].fields,
+        [SavedField {
+            field_start: 0,
+            diff: vec![0x0A0A ^ 6],
+        }]
+    );
+
+    // Round trip through bytes, a file and JSON
+    assert_eq!(
+        SerializablePatchSet::from_bytes(&set.to_bytes()).unwrap(),
+        set
+    );
+    let path = std::env::temp_dir().join(format!("ppatch-patch-set-{}.bin", std::process::id()));
+    set.save_to(&path).unwrap();
+    let loaded = SerializablePatchSet::load_from(&path, repo).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, set);
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SerializablePatchSet>(&json).unwrap(),
+        set
+    );
+
+    // Replaying the patches on the params of a new session gives the same rows
+    let mut new_files = regulation(0);
+    let mut new_params = parse(&mut new_files);
+    let mut new_manager = Manager::new(repo);
+    let handles = loaded.apply_all(&mut new_manager, &mut new_params).unwrap();
+    assert_eq!(handles.len(), 4);
+    assert_eq!(rows(&new_params), rows(&params));
+    assert_eq!(new_manager.patch_set(fingerprint), set);
+
+    // and the replayed patches can be restored individually
+    for handle in handles.into_iter().rev() {
+        restore(&mut new_manager, &mut new_params, handle);
+    }
+    assert_eq!(rows(&new_params), rows(&parse(&mut regulation(0))));
+}
+
+#[test]
+fn changed_regulation_is_rejected() {
+    let repo_bytes = repo();
+    let repo = unsafe { load_fb_repo(&repo_bytes) }.unwrap();
+    let mut files = regulation(0);
+    let mut params = parse(&mut files);
+    let mut manager = Manager::new(repo);
+    patch(&mut manager, &mut params, (0, 10), 3, 1);
+    let set = manager.patch_set(RegulationFingerprint::compute(&params));
+
+    // Params are fingerprinted in any order
+    params.reverse();
+    assert_eq!(RegulationFingerprint::compute(&params), set.fingerprint);
+
+    let mut new_files = regulation(1);
+    let mut new_params = parse(&mut new_files);
+    let mut new_manager = Manager::new(repo);
+    let err = set.apply_all(&mut new_manager, &mut new_params).unwrap_err();
+    assert!(
+        matches!(err, PatchSetError::RegulationMismatch { expected, actual }
+            if expected == set.fingerprint && actual == RegulationFingerprint::compute(&new_params)),
+        "{err:?}"
+    );
+    assert_eq!(rows(&new_params), rows(&parse(&mut regulation(1))));
+}
+
+#[test]
+fn failed_replay_is_rolled_back() {
+    let repo_bytes = repo();
+    let repo = unsafe { load_fb_repo(&repo_bytes) }.unwrap();
+    let mut files = regulation(0);
+    let mut params = parse(&mut files);
+    let saved_patch = |row_id| SavedPatch {
+        param_type: "B_PARAM_ST".to_owned(),
+        row_id,
+        fields: vec![SavedField {
+            field_start: 3,
+            diff: vec![0xFF],
+        }],
+    };
+    let set = SerializablePatchSet {
+        fingerprint: RegulationFingerprint::compute(&params),
+        patches: vec![saved_patch(10), saved_patch(20), saved_patch(99)],
+    };
+
+    let mut manager = Manager::new(repo);
+    let err = set.apply_all(&mut manager, &mut params).unwrap_err();
+    assert!(
+        matches!(&err, PatchSetError::MissingRow { param_type, row_id: 99 }
+            if param_type == "B_PARAM_ST"),
+        "{err:?}"
+    );
+    assert_eq!(rows(&params), rows(&parse(&mut regulation(0))));
+    assert_eq!(manager.outstanding("B_PARAM_ST"), []);
+}
+
+#[test]
+fn invalid_patch_sets() {
+    let repo_bytes = repo();
+    let repo = unsafe { load_fb_repo(&repo_bytes) }.unwrap();
+    let field = |field_start, diff: &[Block]| SavedField {
+        field_start,
+        diff: diff.to_vec(),
+    };
+    let set = |param_type: &str, field: SavedField| SerializablePatchSet {
+        fingerprint: RegulationFingerprint(0x1234),
+        patches: vec![SavedPatch {
+            param_type: param_type.to_owned(),
+            row_id: 10,
+            fields: vec![field],
+        }],
+    };
+
+    let valid = set("A_PARAM_ST", field(1, &[0xF]));
+    assert!(valid.validate(repo).is_ok());
+    let bytes = valid.to_bytes();
+    assert!(matches!(
+        SerializablePatchSet::from_bytes(b"PPS"),
+        Err(PatchSetError::NotAPatchSet)
+    ));
+    assert!(matches!(
+        SerializablePatchSet::from_bytes(&bytes[1..]),
+        Err(PatchSetError::NotAPatchSet)
+    ));
+    let mut newer = bytes.clone();
+    newer[4..8].copy_from_slice(&(PATCH_SET_FORMAT_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        SerializablePatchSet::from_bytes(&newer),
+        Err(PatchSetError::UnsupportedVersion { version }) if version == PATCH_SET_FORMAT_VERSION + 1
+    ));
+    for len in 4..bytes.len() {
+        assert!(
+            matches!(
+                SerializablePatchSet::from_bytes(&bytes[..len]),
+                Err(PatchSetError::Truncated)
+            ),
+            "{len}"
+        );
+    }
+
+    assert!(matches!(
+        set("C_PARAM_ST", field(0, &[1])).validate(repo),
+        Err(PatchSetError::UnknownParamType { param_type }) if param_type == "C_PARAM_ST"
+    ));
+    for invalid in [field(4, &[1]), field(2, &[1, 1]), field(2, &[])] {
+        assert!(matches!(
+            set("A_PARAM_ST", invalid).validate(repo),
+            Err(PatchSetError::InvalidField {
+                field_start: 2 | 4,
+                ..
+            })
+        ));
+    }
+}
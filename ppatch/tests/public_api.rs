@@ -0,0 +1,87 @@
+//! Snapshot of the public re-export graph and the serialized field block repo format.
+//!
+//! Removing one of the stable paths below fails to compile, and re-exporting a different type
+//! than the one of the defining crate fails the tests. Update this file only along with a
+//! semver-breaking release.
+
+use std::any::TypeId;
+
+#[allow(unused_imports)]
+use ppatch::{
+    field_metadata::{
+        load_fb_repo, serialize_fb_repo, validate_fb_repo, validate_field_blocks,
+        ArchivedFieldBlockRepo, Block, FbRepoExt, FieldBlock, FieldBlockRepo, FieldBlockViolation,
+        InvalidFieldBlockRepo, InvalidFieldBlocks, LoadFbRepoError, FB_REPO_FORMAT_VERSION,
+    },
+    fields::{field_size_bits, read_field_bytes, write_field_bytes},
+    param_file::{FromBytesError, ParamFile, ParamFileHeader, ParamRowDescriptor, Row, RowMut},
+    patchers::{
+        base::{RestorePatchError, RowPatchId, RowPatcher},
+        linked_list::LinkedListPatcher,
+        single_patch::SinglePatchPatcher,
+        sparse_array::SparseArrayPatcher,
+    },
+    stacking::{stack_patches, FieldChangeSet, StackedRow},
+    util::unaligned::Unaligned,
+    LAYOUT_VERSION,
+};
+
+fn same_type<A: 'static, B: 'static>() -> bool {
+    TypeId::of::<A>() == TypeId::of::<B>()
+}
+
+#[test]
+fn shared_types_are_reexported() {
+    use ppatch::{field_metadata as fm, fields, patchers, patchers::base};
+
+    assert!(same_type::<fields::FieldBlock<u32>, fm::FieldBlock<u32>>());
+    assert!(same_type::<base::FieldBlock<u32>, fm::FieldBlock<u32>>());
+    assert!(same_type::<
+        base::FieldBlockViolation,
+        fm::FieldBlockViolation,
+    >());
+    assert!(same_type::<base::InvalidFieldBlocks, fm::InvalidFieldBlocks>());
+    assert!(same_type::<fm::Block, u32>());
+    assert!(same_type::<
+        patchers::SparseArrayPatcher,
+        patchers::sparse_array::SparseArrayPatcher,
+    >());
+}
+
+#[cfg(feature = "paramdex")]
+#[test]
+fn paramdex_is_reexported() {
+    use ppatch::paramdex::{paramdef::Paramdef, Paramdex, ParamdexLoadError};
+
+    let _ = TypeId::of::<(Paramdef, Paramdex, ParamdexLoadError)>();
+}
+
+#[test]
+fn fb_repo_format() {
+    // Changing the archived layout requires bumping the format version in a breaking release
+    assert_eq!(FB_REPO_FORMAT_VERSION, 3);
+
+    let mut repo = FieldBlockRepo::new();
+    let blocks = vec![FieldBlock {
+        field_start: 0,
+        offset: 0,
+        mask: Block::MAX,
+    }];
+    repo.insert("TEST_PARAM_ST".to_owned(), blocks);
+    let bytes = serialize_fb_repo(&repo);
+
+    let loaded = unsafe { load_fb_repo(&bytes) }.unwrap();
+    assert_eq!(loaded.get("TEST_PARAM_ST").unwrap().len(), 1);
+    assert_eq!(validate_fb_repo(loaded), Ok(()));
+
+    let mut old = bytes.to_vec();
+    old[4..8].copy_from_slice(&0u32.to_le_bytes());
+    assert_eq!(
+        unsafe { load_fb_repo(&old) }.err(),
+        Some(LoadFbRepoError::UnsupportedVersion { version: 0 })
+    );
+    assert_eq!(
+        unsafe { load_fb_repo(&bytes[8..]) }.err(),
+        Some(LoadFbRepoError::NotARepo)
+    );
+}
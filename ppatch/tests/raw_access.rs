@@ -0,0 +1,139 @@
+//! Validated byte range reads and writes with [`raw_read`] and [`RowPatcherExt::raw_write`].
+
+use ppatch::{
+    field_metadata::{Block, FieldBlock},
+    fields::fields_in_byte_range,
+    param_builder::ParamFileBuilder,
+    param_file::ParamFile,
+    patchers::{
+        base::{raw_read, PatchRowError, RawAccessError, RowPatcher, RowPatcherExt},
+        linked_list::LinkedListPatcher,
+        single_patch::SinglePatchPatcher,
+    },
+};
+
+const ROW_SIZE: usize = 12;
+
+/// A u16 at 0, a u32 at 6 straddling two blocks and a byte at 10. Bytes 2..6 and 11 are padding.
+fn field_blocks() -> Vec<FieldBlock<Block>> {
+    let blocks = [
+        (0, 0, 0x0000_FFFF),
+        (1, 1, 0xFFFF_0000),
+        (1, 2, 0x0000_FFFF),
+        (3, 2, 0x00FF_0000),
+    ];
+    blocks
+        .iter()
+        .map(|&(field_start, offset, mask)| FieldBlock {
+            field_start,
+            offset,
+            mask,
+        })
+        .collect()
+}
+
+/// Param file with rows 10 and 20, filled with zeros.
+fn param_file() -> Vec<u8> {
+    let mut builder = ParamFileBuilder::new("A_ST", ROW_SIZE);
+    builder.insert_row(10, &[0; ROW_SIZE]).unwrap();
+    builder.insert_row(20, &[0; ROW_SIZE]).unwrap();
+    builder.to_bytes()
+}
+
+#[test]
+fn fields_of_ranges() {
+    let blocks = field_blocks();
+    let fields = |offset, len| fields_in_byte_range(&blocks, offset, len);
+
+    assert_eq!(fields(0, ROW_SIZE), [0, 1, 3]);
+    assert_eq!(fields(1, 2), [0]);
+    // Both halves of the straddling field
+    assert_eq!(fields(7, 1), [1]);
+    assert_eq!(fields(8, 1), [1]);
+    assert_eq!(fields(9, 2), [1, 3]);
+    // Padding only
+    assert_eq!(fields(2, 4), [0u16; 0]);
+    assert_eq!(fields(11, 1), [0u16; 0]);
+    assert_eq!(fields(4, 0), [0u16; 0]);
+}
+
+#[test]
+fn raw_reads() {
+    let mut file = param_file();
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    param.by_id_mut(10).unwrap().data_mut()[4..8].copy_from_slice(&[1, 2, 3, 4]);
+    let row = param.by_id(10).unwrap();
+
+    assert_eq!(raw_read(&row, 3, 4), Ok(&[0, 1, 2, 3][..]));
+    assert_eq!(raw_read(&row, ROW_SIZE, 0), Ok(&[][..]));
+    assert_eq!(
+        raw_read(&row, 10, 3),
+        Err(RawAccessError::OutOfBounds {
+            offset: 10,
+            len: 3,
+            row_size: ROW_SIZE
+        })
+    );
+    assert!(matches!(
+        raw_read(&row, usize::MAX, 2),
+        Err(RawAccessError::OutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn raw_writes() {
+    let blocks = field_blocks();
+    let mut patcher = LinkedListPatcher::new(&blocks, ROW_SIZE);
+    let mut file = param_file();
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.by_id_mut(10).unwrap();
+
+    let straddling = patcher.raw_write(&blocks, &mut row, 7, &[1, 2, 3, 4], false).unwrap();
+    assert_eq!(straddling.fields, [1, 3]);
+    assert_eq!(row.data()[7..11], [1, 2, 3, 4]);
+
+    // Padding only ranges need to be allowed explicitly
+    assert_eq!(
+        patcher.raw_write(&blocks, &mut row, 2, &[5, 5], false),
+        Err(RawAccessError::Unmapped { offset: 2, len: 2 })
+    );
+    assert_eq!(row.data()[2..4], [0, 0]);
+    let padding = patcher.raw_write(&blocks, &mut row, 2, &[5, 5], true).unwrap();
+    assert!(padding.fields.is_empty());
+
+    // Straddling a field and padding
+    let partial = patcher.raw_write(&blocks, &mut row, 1, &[6, 6], false).unwrap();
+    assert_eq!(partial.fields, [0]);
+
+    assert_eq!(
+        patcher.raw_write(&blocks, &mut row, 11, &[0, 0], true),
+        Err(RawAccessError::OutOfBounds {
+            offset: 11,
+            len: 2,
+            row_size: ROW_SIZE
+        })
+    );
+
+    // Restoring reverts the bits of fields only
+    for write in [partial, padding, straddling] {
+        let live = row.as_blocks_mut().unwrap();
+        patcher.restore_patch(write.id, live).unwrap();
+    }
+    assert_eq!(row.data(), [0, 0, 6, 5, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn rejected_writes_are_rolled_back() {
+    let blocks = field_blocks();
+    let mut patcher = SinglePatchPatcher::new(&blocks, ROW_SIZE);
+    let mut file = param_file();
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.by_id_mut(20).unwrap();
+
+    patcher.raw_write(&blocks, &mut row, 0, &[1, 1], false).unwrap();
+    assert_eq!(
+        patcher.raw_write(&blocks, &mut row, 6, &[2; 4], false),
+        Err(RawAccessError::PatchRow(PatchRowError::PatchRejected))
+    );
+    assert_eq!(row.data(), [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
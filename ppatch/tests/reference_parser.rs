@@ -0,0 +1,303 @@
+//! Differential tests of [`ParamFile::from_bytes`] against a reference parser.
+//!
+//! The reference parser reads the same format byte by byte into owned structures, with explicit
+//! bounds checks and without any unsafe code. Files accepted by `from_bytes` must be accepted by
+//! the reference with the same header fields, row descriptors and row bytes, and rejected files
+//! must be rejected for the same category of reason.
+//!
+//! Inputs are valid files with random rows, gaps and row orders, then mutated by overwriting a
+//! few random bytes (mostly in the header and row descriptors) and truncating them.
+
+use ppatch::param_file::{FromBytesError, ParamFile};
+use proptest::prelude::*;
+
+const DESC_SIZE: usize = 24;
+
+/// Reason a file is rejected, regardless of which check of a category failed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    TooSmall,
+    Unsupported,
+    /// Row descriptors are not sorted by unique IDs.
+    Order,
+    /// Data lies outside of the file or the row data section, or intersects other data. Row names
+    /// don't start in the strings region.
+    Layout,
+}
+
+impl From<FromBytesError> for Rejection {
+    fn from(e: FromBytesError) -> Self {
+        match e {
+            FromBytesError::BufferTooSmall => Self::TooSmall,
+            FromBytesError::UnsupportedFile { .. } => Self::Unsupported,
+            FromBytesError::UnsortedRowDescs { .. } | FromBytesError::DuplicateIds { .. } => {
+                Self::Order
+            }
+            FromBytesError::OutOfBoundsOffset { .. }
+            | FromBytesError::IntersectingData { .. }
+            | FromBytesError::InvalidNameOffset { .. }
+            | FromBytesError::AmbiguousRowSize { .. } => Self::Layout,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct RefParam {
+    row_count: u16,
+    is_unicode: bool,
+    data_end: usize,
+    /// ID, data offset and name offset of each row descriptor.
+    descriptors: Vec<(u32, usize, usize)>,
+    rows: Vec<Vec<u8>>,
+    names: Vec<Option<String>>,
+}
+
+fn read<const N: usize>(data: &[u8], ofs: usize) -> Result<[u8; N], Rejection> {
+    let end = ofs.checked_add(N).ok_or(Rejection::TooSmall)?;
+    let bytes = data.get(ofs..end).ok_or(Rejection::TooSmall)?;
+    Ok(bytes.try_into().unwrap())
+}
+
+/// Reads a name up to its NUL terminator or the end of the file.
+fn read_name(data: &[u8], ofs: usize, is_unicode: bool) -> String {
+    let mut units = Vec::new();
+    let mut i = ofs;
+    if is_unicode {
+        while let Ok(unit) = read(data, i).map(u16::from_le_bytes) {
+            if unit == 0 {
+                break;
+            }
+            units.push(unit);
+            i += 2;
+        }
+        String::from_utf16_lossy(&units)
+    }
+    else {
+        let bytes = data[ofs..].split(|&b| b == 0).next().unwrap();
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// Parses a 64-bit little endian param file.
+fn reference_parse(data: &[u8]) -> Result<RefParam, Rejection> {
+    if data.len() < 0x30 {
+        return Err(Rejection::TooSmall);
+    }
+    let (is_big_endian, flags) = (data[0x2C] != 0, data[0x2D]);
+    let is_64_bit = flags & 4 != 0;
+    if is_big_endian || !is_64_bit {
+        return Err(Rejection::Unsupported);
+    }
+    let header_size = 0x40;
+    let row_count = u16::from_le_bytes(read(data, 0xA)?);
+    let descs_end = header_size + DESC_SIZE * row_count as usize;
+    if data.len() < descs_end {
+        return Err(Rejection::TooSmall);
+    }
+
+    let mut descriptors = Vec::new();
+    for i in 0..row_count as usize {
+        let desc = header_size + i * DESC_SIZE;
+        let id = u32::from_le_bytes(read(data, desc)?);
+        let data_offset = u64::from_le_bytes(read(data, desc + 8)?) as usize;
+        let name_offset = u64::from_le_bytes(read(data, desc + 16)?) as usize;
+        descriptors.push((id, data_offset, name_offset));
+    }
+    for i in 1..descriptors.len() {
+        if descriptors[i - 1].0 >= descriptors[i].0 {
+            return Err(Rejection::Order);
+        }
+    }
+
+    let data_end_field = if flags & 0x80 != 0 { 0x10 } else { 0 };
+    let data_end = u32::from_le_bytes(read(data, data_end_field)?) as usize;
+    if data_end > data.len() || data_end < descs_end {
+        return Err(Rejection::Layout);
+    }
+    for d in &descriptors {
+        if d.2 != 0 && (d.2 < data_end || d.2 >= data.len()) {
+            return Err(Rejection::Layout);
+        }
+    }
+
+    // A row extends to the next row in the file, or to the end of the row data for the last one
+    let mut offsets: Vec<usize> = descriptors.iter().map(|d| d.1).collect();
+    offsets.sort();
+    let mut sizes_by_offset = Vec::new();
+    for i in 0..offsets.len() {
+        let end = offsets.get(i + 1).copied().unwrap_or(data_end);
+        // Rows sharing data have a size of 0, and are thus rejected like rows of size 0 which
+        // aren't the last one
+        if offsets[i] < descs_end || end < offsets[i] || end == offsets[i] && i + 1 < offsets.len()
+        {
+            return Err(Rejection::Layout);
+        }
+        sizes_by_offset.push(end - offsets[i]);
+    }
+    // Except that when all rows but the last have the same size and the last one has room for
+    // it, they all have that size
+    if let Some(&first) = sizes_by_offset.first() {
+        let n = sizes_by_offset.len();
+        let uniform = sizes_by_offset[..n - 1].iter().all(|&s| s == first);
+        if uniform && sizes_by_offset[n - 1] >= first {
+            sizes_by_offset[n - 1] = first;
+        }
+    }
+
+    let mut rows = Vec::new();
+    for d in &descriptors {
+        let i = offsets.iter().position(|&o| o == d.1).unwrap();
+        rows.push(data[d.1..d.1 + sizes_by_offset[i]].to_vec());
+    }
+    let is_unicode = data[0x2E] & 1 != 0;
+    let names = descriptors.iter().map(|d| (d.2 != 0).then(|| read_name(data, d.2, is_unicode)));
+    Ok(RefParam {
+        row_count,
+        is_unicode,
+        data_end,
+        names: names.collect(),
+        descriptors,
+        rows,
+    })
+}
+
+fn zero_copy_parse(data: &[u8]) -> Result<RefParam, Rejection> {
+    let mut data = data.to_vec();
+    let param = ParamFile::from_bytes(&mut data)?;
+    Ok(RefParam {
+        row_count: param.header().row_count(),
+        is_unicode: param.header().is_unicode(),
+        data_end: param.header().data_end_ofs(),
+        descriptors: (param.row_descriptors().iter())
+            .map(|d| (d.id, d.data_offset, d.name_offset))
+            .collect(),
+        rows: param.rows().map(|r| r.data().to_vec()).collect(),
+        names: param.rows().map(|r| r.name().map(|n| n.into_owned())).collect(),
+    })
+}
+
+/// Row of a generated valid file.
+#[derive(Debug, Clone, Copy)]
+struct RowSpec {
+    id_delta: u32,
+    size: usize,
+    /// Padding after the row data.
+    gap: usize,
+    named: bool,
+}
+
+/// Builds a valid 64-bit little endian param file. Row data is stored in reverse ID order if
+/// `reversed` is set.
+fn build(rows: &[RowSpec], reversed: bool, type_offset: bool) -> Vec<u8> {
+    const HEADER_SIZE: usize = 0x40;
+    let data_start = HEADER_SIZE + rows.len() * DESC_SIZE;
+    let mut file = vec![0u8; data_start];
+    file[0xA..0xC].copy_from_slice(&(rows.len() as u16).to_le_bytes());
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[0x30..0x38].copy_from_slice(&(data_start as u64).to_le_bytes());
+
+    let mut id = 0u32;
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    if reversed {
+        order.reverse();
+    }
+    for (i, row) in rows.iter().enumerate() {
+        id += row.id_delta;
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        file[desc..desc + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    for i in order {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let len = file.len();
+        file[desc + 8..desc + 16].copy_from_slice(&(len as u64).to_le_bytes());
+        file.extend((0..rows[i].size).map(|b| (len + b) as u8));
+        file.resize(file.len() + rows[i].gap, 0);
+    }
+
+    let data_end = file.len() as u32;
+    file.extend(b"TEST_ST\0");
+    if type_offset {
+        file[0x2D] |= 0x80;
+        file[0x10..0x14].copy_from_slice(&data_end.to_le_bytes());
+        file[0..4].copy_from_slice(&(data_end + 8).to_le_bytes());
+    }
+    else {
+        file[0..4].copy_from_slice(&data_end.to_le_bytes());
+    }
+
+    for (i, row) in rows.iter().enumerate().filter(|(_, r)| r.named) {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let len = file.len();
+        file[desc + 16..desc + 24].copy_from_slice(&(len as u64).to_le_bytes());
+        let name = format!("Row {i} ({}b)", row.size);
+        file.extend(name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes));
+    }
+    file
+}
+
+fn row_spec() -> impl Strategy<Value = RowSpec> {
+    let gap = prop_oneof![3 => Just(0usize), 1 => 1usize..4];
+    (1u32..4, 1usize..12, gap, any::<bool>()).prop_map(|(id_delta, size, gap, named)| RowSpec {
+        id_delta,
+        size,
+        gap,
+        named,
+    })
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    (prop::bool::weighted(0.8), any::<usize>(), any::<u8>())
+}
+
+/// Write of a byte at a position, wrapped to the header and row descriptors if the flag is set,
+/// or to the whole file otherwise.
+type Mutation = (bool, usize, u8);
+
+fn mutate(file: &mut Vec<u8>, mutations: &[Mutation], truncate: Option<usize>) {
+    let descs_end = 0x40 + DESC_SIZE * u16::from_le_bytes([file[0xA], file[0xB]]) as usize;
+    for &(in_descs, pos, value) in mutations {
+        let len = if in_descs { descs_end.min(file.len()) } else { file.len() };
+        if len != 0 {
+            file[pos % len] = value;
+        }
+    }
+    if let Some(len) = truncate {
+        file.truncate(len % (file.len() + 1));
+    }
+}
+
+fn check(file: &[u8]) -> Result<(), TestCaseError> {
+    let expected = reference_parse(file);
+    let actual = zero_copy_parse(file);
+    prop_assert_eq!(actual, expected, "file: {:02x?}", file);
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn valid_files_match_reference(
+        rows in prop::collection::vec(row_spec(), 0..8),
+        reversed in any::<bool>(),
+        type_offset in any::<bool>(),
+    ) {
+        let file = build(&rows, reversed, type_offset);
+        prop_assert!(reference_parse(&file).is_ok());
+        check(&file)?;
+    }
+
+    #[test]
+    fn mutated_files_match_reference(
+        rows in prop::collection::vec(row_spec(), 0..8),
+        reversed in any::<bool>(),
+        type_offset in any::<bool>(),
+        mutations in prop::collection::vec(mutation(), 0..4),
+        truncate in prop::option::weighted(0.1, any::<usize>()),
+    ) {
+        let mut file = build(&rows, reversed, type_offset);
+        mutate(&mut file, &mutations, truncate);
+        check(&file)?;
+    }
+}
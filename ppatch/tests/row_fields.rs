@@ -0,0 +1,143 @@
+//! Typed access to row fields by name, with the fixture defs.
+
+use paramdex::{paramdef::Paramdef, unofficial::UnofficialFields, Paramdex};
+use ppatch::param_file::ParamFile;
+
+const OVERLAY: &str = r#"
+[[BITFIELD_TEST_PARAM_ST]]
+host = "endPad"
+bit_offset = 10
+def = "s8 signedBits:3"
+"#;
+
+/// Builds a 64-bit little endian param file with a single row.
+fn build(row: &[u8]) -> Vec<u8> {
+    const HEADER_SIZE: usize = 0x40;
+    const DESC_SIZE: usize = 24;
+
+    let data_start = HEADER_SIZE + DESC_SIZE;
+    let data_end = data_start + row.len();
+    let mut file = vec![0u8; data_end + 8];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA..0xC].copy_from_slice(&1u16.to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[HEADER_SIZE + 8..HEADER_SIZE + 16].copy_from_slice(&(data_start as u64).to_le_bytes());
+    file[data_start..data_end].copy_from_slice(row);
+    file[data_end..].copy_from_slice(b"strings\0");
+    file
+}
+
+fn bitfield_def(version: u64) -> Paramdef {
+    let mut paramdex = Paramdex::fixture();
+    paramdex
+        .merge_unofficial_fields(&UnofficialFields::from_toml(OVERLAY).unwrap())
+        .unwrap()
+        .compute_def_layouts(version);
+    paramdex.def_with_meta("BitfieldTestParam").unwrap().def.clone()
+}
+
+/// Row of `BitfieldTestParam` at version 10400.
+#[rustfmt::skip]
+const BITFIELD_ROW: [u8; 20] = [
+    0xFB, 0xFF, 0xFF, 0xFF, // id = -5
+    0xCB, 0, // flagA = 1, flagB = 5, flagC = 0xC
+    0x34, 0x12, // shortVal
+    0x7F, 0, 0, 0, // byteVal
+    0xBC, 0x5A, 0x34, 0x12, // wideBits = 0xABC, narrowBits = 0x12345
+    0x90, // pad, lastBits = 9
+    0x01, 0x18, 0x03, // endPad, signedBits = -2 at bits 10..13
+];
+
+#[test]
+fn read_scalars_and_bitfields() {
+    let def = bitfield_def(10400);
+    let mut file = build(&BITFIELD_ROW);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let row = param.rows().next().unwrap();
+
+    assert_eq!(row.field::<i32>(&def, "id"), Some(-5));
+    assert_eq!(row.field::<u8>(&def, "flagA"), Some(1));
+    assert_eq!(row.field::<u8>(&def, "flagB"), Some(5));
+    assert_eq!(row.field::<u8>(&def, "flagC"), Some(0xC));
+    assert_eq!(row.field::<u16>(&def, "shortVal"), Some(0x1234));
+    assert_eq!(row.field::<u8>(&def, "byteVal"), Some(0x7F));
+    assert_eq!(row.field::<u32>(&def, "wideBits"), Some(0xABC));
+    assert_eq!(row.field::<u32>(&def, "narrowBits"), Some(0x12345));
+    assert_eq!(row.field::<u8>(&def, "lastBits"), Some(9));
+    assert_eq!(row.field::<i8>(&def, "signedBits"), Some(-2));
+
+    assert_eq!(row.field_raw(&def, "endPad"), Some(&BITFIELD_ROW[17..20]));
+    assert_eq!(row.field_raw(&def, "shortVal"), Some(&BITFIELD_ROW[6..8]));
+    assert_eq!(row.field_raw(&def, "flagB"), None);
+}
+
+#[test]
+fn inaccessible_fields() {
+    let mut file = build(&BITFIELD_ROW);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let row = param.rows().next().unwrap();
+    let def = bitfield_def(10400);
+
+    assert_eq!(row.field::<u8>(&def, "missing"), None);
+    assert_eq!(row.field::<u32>(&def, "byteVal"), None, "wrong type");
+    assert_eq!(row.field::<u8>(&def, "endPad"), None, "array");
+
+    // flagC is only in the def from version 10300
+    let old_def = bitfield_def(10200);
+    assert_eq!(row.field::<u8>(&old_def, "flagC"), None);
+    assert_eq!(row.field::<u8>(&old_def, "flagB"), Some(5));
+
+    // Fields past the end of a row too small for the def
+    let mut file = build(&BITFIELD_ROW[..12]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let row = param.rows().next().unwrap();
+    assert_eq!(row.field::<u8>(&def, "byteVal"), Some(0x7F));
+    assert_eq!(row.field::<u32>(&def, "wideBits"), None);
+    assert_eq!(row.field_raw(&def, "endPad"), None);
+}
+
+#[test]
+fn set_field_keeps_other_bits() {
+    let def = bitfield_def(10400);
+    let mut file = build(&BITFIELD_ROW);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.rows_mut().next().unwrap();
+
+    assert_eq!(
+        row.set_field(&def, "narrowBits", 0xFFF_FFFFu32),
+        Some(0x12345)
+    );
+    assert_eq!(row.field::<u32>(&def, "narrowBits"), Some(0xF_FFFF));
+    assert_eq!(row.set_field(&def, "flagB", 2u8), Some(5));
+    assert_eq!(row.set_field(&def, "signedBits", 3i8), Some(-2));
+    assert_eq!(row.set_field(&def, "byteVal", 1u32), None);
+
+    let mut expected = BITFIELD_ROW;
+    expected[4] = 0xC5;
+    expected[13..16].copy_from_slice(&[0xFA, 0xFF, 0xFF]);
+    expected[18] = 0x0C;
+    assert_eq!(row.data(), expected);
+}
+
+#[test]
+fn arrays_and_floats() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(0);
+    let def = &paramdex.def_with_meta("ArrayTestParam").unwrap().def;
+    let mut data = vec![0u8; def.size_bytes.unwrap()];
+    data[..4].copy_from_slice(&1.5f32.to_le_bytes());
+    let mut file = build(&data);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.rows_mut().next().unwrap();
+
+    assert_eq!(row.field::<f32>(def, "weight"), Some(1.5));
+    assert_eq!(row.set_field(def, "weight", -2.0f32), Some(1.5));
+    row.field_raw_mut(def, "name").unwrap()[..3].copy_from_slice(b"abc");
+
+    let row = param.rows().next().unwrap();
+    assert_eq!(row.field::<f32>(def, "weight"), Some(-2.0));
+    assert_eq!(row.field_raw(def, "values").map(<[u8]>::len), Some(16));
+    assert_eq!(&row.field_raw(def, "name").unwrap()[..4], b"abc\0");
+}
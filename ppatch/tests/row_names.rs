@@ -0,0 +1,129 @@
+//! Reading row names with [`ParamFile::row_name`] and [`Row::name`], and validation of name
+//! offsets by [`ParamFile::from_bytes`].
+
+use ppatch::param_file::{FromBytesError, ParamFile, Row, TrustedLayout, ValidationLevel};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const ROW_SIZE: usize = 4;
+
+/// Builds a 64-bit param file whose rows have IDs 0, 1, ... and the given names, stored after the
+/// param type in order. `None` is a row without a name offset.
+fn build(names: &[Option<&str>], is_unicode: bool) -> Vec<u8> {
+    let put = |file: &mut [u8], ofs: usize, size: usize, value: usize| {
+        file[ofs..ofs + size].copy_from_slice(&(value as u64).to_le_bytes()[..size]);
+    };
+
+    let data_start = HEADER_SIZE + names.len() * DESC_SIZE;
+    let data_end = data_start + ROW_SIZE * names.len();
+    let mut file = vec![0u8; data_end];
+    put(&mut file, 0, 4, data_end + 8);
+    put(&mut file, 0xA, 2, names.len());
+    put(&mut file, 0x10, 4, data_end);
+    file[0x2D] = 0x80 | 4 | 3;
+    file[0x2E] = is_unicode as u8;
+    put(&mut file, 0x30, 8, data_start);
+    file.extend(b"TEST_ST\0");
+
+    for (i, name) in names.iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        put(&mut file, desc, 4, i);
+        put(&mut file, desc + 8, 8, data_start + ROW_SIZE * i);
+        let Some(name) = name
+        else {
+            continue;
+        };
+        let name_offset = file.len();
+        put(&mut file, desc + 16, 8, name_offset);
+        if is_unicode {
+            file.extend(name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes));
+        }
+        else {
+            file.extend(name.bytes().chain([0]));
+        }
+    }
+    file
+}
+
+fn set_name_offset(file: &mut [u8], index: usize, offset: usize) {
+    let ofs = HEADER_SIZE + index * DESC_SIZE + 16;
+    file[ofs..ofs + 8].copy_from_slice(&(offset as u64).to_le_bytes());
+}
+
+fn names(param: &ParamFile) -> Vec<Option<String>> {
+    (0..param.row_descriptors().len())
+        .map(|i| param.row_name(i).map(|n| n.into_owned()))
+        .collect()
+}
+
+#[test]
+fn unicode_names() {
+    let rows = [Some("Dagger"), None, Some("Épée"), Some("")];
+    let mut file = build(&rows, true);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+
+    let expected: Vec<_> = rows.iter().map(|n| n.map(str::to_owned)).collect();
+    assert_eq!(names(&param), expected);
+    assert_eq!(param.row_name(rows.len()), None);
+    let row_names: Vec<_> = param.rows().map(|r| r.name()).collect();
+    assert_eq!(row_names, rows.map(|n| n.map(Into::into)));
+    assert_eq!(param.by_id(2).unwrap().name().as_deref(), Some("Épée"));
+}
+
+#[test]
+fn byte_names() {
+    let rows = [Some("Longsword"), Some("Dagger")];
+    let mut file = build(&rows, false);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(
+        names(&param),
+        [Some("Longsword".into()), Some("Dagger".into())]
+    );
+
+    // Splitting the row keeps its name
+    let row: Row = param.get(1).unwrap();
+    let (prefix, _) = row.split_tail(2).unwrap();
+    assert_eq!(prefix.name().as_deref(), Some("Dagger"));
+}
+
+#[test]
+fn unterminated_names_end_with_the_file() {
+    let mut file = build(&[Some("Club"), Some("Mace")], true);
+    file.truncate(file.len() - 3);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(names(&param), [Some("Club".into()), Some("Mac".into())]);
+
+    let mut file = build(&[Some("Club"), Some("Mace")], false);
+    file.pop();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.row_name(1).as_deref(), Some("Mace"));
+}
+
+#[test]
+fn names_outside_of_strings_are_rejected() {
+    let valid = build(&[Some("Dagger"), Some("Club")], true);
+    let data_start = HEADER_SIZE + 2 * DESC_SIZE;
+    let data_end = data_start + 2 * ROW_SIZE;
+
+    // The strings region starts at the param type, so it may be used as a name
+    let mut file = valid.clone();
+    set_name_offset(&mut file, 0, data_end);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.row_name(0).as_deref(), Some("䕔呓卟TDagger"));
+
+    for offset in [data_start, data_end - 1, valid.len(), usize::MAX] {
+        let mut file = valid.clone();
+        set_name_offset(&mut file, 1, offset);
+        assert_eq!(
+            ParamFile::from_bytes(&mut file).err(),
+            Some(FromBytesError::InvalidNameOffset { index: 1, offset })
+        );
+
+        // Files parsed without validation never read names out of the strings region
+        // SAFETY: Only the name offset is invalid, which is checked again when reading names
+        let level = ValidationLevel::HeaderOnly(unsafe { TrustedLayout::new() });
+        let (param, _) = ParamFile::from_bytes_with(&mut file, level).unwrap();
+        assert_eq!(names(&param), [Some("Dagger".into()), None]);
+        assert_eq!(param.get(1).unwrap().name(), None);
+    }
+}
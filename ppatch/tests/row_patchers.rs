@@ -0,0 +1,941 @@
+//! Differential tests of the [`RowPatcher`] implementations against a reference model.
+//!
+//! Random field block layouts are patched by random sequences of field edits and restores, and
+//! the live row of every patcher is compared to the reference after each operation.
+//!
+//! The reference model keeps the original row and, for every outstanding patch, the values of the
+//! fields it changed. The expected row is obtained by replaying the outstanding patches in order,
+//! i.e. each field holds the value written by the most recent outstanding patch that changed it.
+
+use ppatch::{
+    fields::{write_field_bytes, FieldBlock},
+    patchers::{
+        base::{RestorePatchError, RowPatchId, RowPatcher},
+        full_copy::FullCopyPatcher,
+        linked_list::{LinkedListPatcher, ReplaceRowError},
+        single_patch::SinglePatchPatcher,
+        toggle_map::ToggleMapPatcher,
+        SparseArrayPatcher,
+    },
+    stacking::{stack_patches, FieldChange, FieldChangeSet},
+    testing::{random_row, FieldLayout, FieldSpec, LayoutConfig},
+    util::{diff_span::changed_block_span, unaligned::Unaligned},
+};
+use proptest::{prelude::*, test_runner::FileFailurePersistence};
+
+type Block = u32;
+type Layout = FieldLayout<Block>;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    /// Writes random values to fields, then creates a patch.
+    /// Fields are selected modulo the number of fields.
+    Patch(&'static [(usize, u128)]),
+    /// Restores an outstanding patch, selected modulo the number of outstanding patches.
+    Restore(usize),
+}
+
+/// Values written to fields by a patch, keyed by the index of their first field block.
+type FieldWrites = Vec<(u16, Vec<u8>)>;
+
+/// Reference model of the patched row.
+struct Model {
+    original: Vec<Unaligned<Block>>,
+    /// Outstanding patches and the field values they wrote, in creation order.
+    patches: Vec<(RowPatchId, FieldWrites)>,
+}
+
+impl Model {
+    fn expected(&self, layout: &Layout) -> Vec<Unaligned<Block>> {
+        let mut row = self.original.clone();
+        for (field_start, value) in self.patches.iter().flat_map(|(_, w)| w) {
+            write_field_bytes(&mut row, &layout.blocks, *field_start, value);
+        }
+        row
+    }
+
+    /// Row computed offline by [`stack_patches`] from the outstanding patches.
+    fn stacked(&self, layout: &Layout) -> Vec<Unaligned<Block>> {
+        let change_sets: Vec<_> = self
+            .patches
+            .iter()
+            .map(|(_, writes)| FieldChangeSet {
+                changes: writes
+                    .iter()
+                    .map(|(field_start, value)| FieldChange {
+                        field_start: *field_start,
+                        value: value.as_slice().into(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        let original: Vec<u8> = self.original.iter().flat_map(|b| b.read().to_le_bytes()).collect();
+        let stacked = stack_patches(&layout.blocks, &original, &change_sets).unwrap();
+        stacked
+            .data
+            .chunks_exact(4)
+            .map(|c| Unaligned(Block::from_le_bytes(c.try_into().unwrap())))
+            .collect()
+    }
+
+    /// Mask of all bits of the fields changed by outstanding patches.
+    fn patched_mask(&self, layout: &Layout) -> Vec<Block> {
+        let mut mask = vec![0; layout.row_size / 4];
+        for (field_start, _) in self.patches.iter().flat_map(|(_, w)| w) {
+            for fb in layout.blocks.iter().filter(|fb| fb.field_start == *field_start) {
+                mask[fb.offset as usize] |= fb.mask;
+            }
+        }
+        mask
+    }
+}
+
+fn original_row(layout: &Layout, seed: u64) -> Vec<Unaligned<Block>> {
+    // Generated from the seed, so that the row contents don't need to be shrunk by proptest
+    random_row(layout.row_size, seed)
+}
+
+/// Runs `ops` on a patcher of type `P`, checking the live row against the model after each one.
+fn run<'a, P: RowPatcher<'a, Block>>(
+    layout: &'a Layout,
+    seed: u64,
+    ops: &[Op],
+) -> Result<(), TestCaseError> {
+    let mut patcher = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live = original_row(layout, seed);
+    let mut model = Model {
+        original: live.clone(),
+        patches: Vec::new(),
+    };
+
+    for (i, op) in ops.iter().enumerate() {
+        match *op {
+            Op::Patch(writes) => {
+                let before = live.clone();
+                for &(field, value) in writes {
+                    let n_fields = layout.field_starts.len().max(1);
+                    let Some(&field_start) = layout.field_starts.get(field % n_fields)
+                    else {
+                        continue;
+                    };
+                    write_field_bytes(&mut live, &layout.blocks, field_start, &value.to_le_bytes());
+                }
+                let changed: Vec<_> = layout
+                    .field_starts
+                    .iter()
+                    .map(|&fs| (fs, layout.read_field(&live, fs)))
+                    .filter(|(fs, value)| *value != layout.read_field(&before, *fs))
+                    .collect();
+
+                match patcher.create_patch(&before, &live) {
+                    Some(id) => model.patches.push((id, changed)),
+                    // Patchers may refuse to create patches. The caller then rolls back the edit.
+                    None => live = before,
+                }
+            }
+            Op::Restore(_) if model.patches.is_empty() => continue,
+            Op::Restore(sel) => {
+                let (id, _) = model.patches.remove(sel % model.patches.len());
+                prop_assert_eq!(patcher.restore_patch(id, &mut live), Ok(()), "op {}", i);
+                prop_assert_eq!(
+                    patcher.restore_patch(id, &mut live),
+                    Err(RestorePatchError::UnknownId),
+                    "op {}: restored twice",
+                    i
+                );
+            }
+        }
+        prop_assert_eq!(&live, &model.expected(layout), "op {}: {:?}", i, op);
+        prop_assert_eq!(&live, &model.stacked(layout), "op {}: offline stacking", i);
+        prop_assert_eq!(
+            patcher.patched_mask_for_row(),
+            model.patched_mask(layout),
+            "op {}: patched mask",
+            i
+        );
+    }
+
+    // Restoring all remaining patches must give back the original row
+    while let Some((id, _)) = model.patches.pop() {
+        prop_assert_eq!(patcher.restore_patch(id, &mut live), Ok(()));
+    }
+    prop_assert_eq!(&live, &model.original);
+    Ok(())
+}
+
+fn run_all(fields: &[FieldSpec], seed: u64, ops: &[Op]) -> Result<(), TestCaseError> {
+    let layout = Layout::new(fields);
+    run::<LinkedListPatcher<Block>>(&layout, seed, ops)?;
+    run::<SinglePatchPatcher<Block>>(&layout, seed, ops)?;
+    run::<FullCopyPatcher<Block>>(&layout, seed, ops)?;
+    run::<ToggleMapPatcher<Block>>(&layout, seed, ops)?;
+    run::<SparseArrayPatcher<Block>>(&layout, seed, ops)?;
+    Ok(())
+}
+
+/// Runs `ops` on every patcher in lockstep, checking that they all end up with the same row as
+/// [`FullCopyPatcher`], and report the same patched mask after each op. Edits refused by any
+/// patcher are rolled back for all of them.
+fn run_against_full_copy(layout: &Layout, seed: u64, ops: &[Op]) -> Result<(), TestCaseError> {
+    let mut reference = FullCopyPatcher::<Block>::new(&layout.blocks, layout.row_size);
+    let mut linked_list =
+        LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut single = SinglePatchPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut sparse = SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut rows = [(); 4].map(|_| original_row(layout, seed));
+    let mut patches = Vec::new();
+
+    for (i, op) in ops.iter().enumerate() {
+        match *op {
+            Op::Patch(writes) => {
+                let before = rows[0].clone();
+                let mut after = before.clone();
+                for &(field, value) in writes {
+                    let n_fields = layout.field_starts.len().max(1);
+                    let Some(&field_start) = layout.field_starts.get(field % n_fields)
+                    else {
+                        continue;
+                    };
+                    let value = value.to_le_bytes();
+                    write_field_bytes(&mut after, &layout.blocks, field_start, &value);
+                }
+                // Only the single patch patcher may refuse, and only if it has a patch already
+                let Some(single_id) = single.create_patch(&before, &after)
+                else {
+                    continue;
+                };
+                let ids = (
+                    reference.create_patch(&before, &after).unwrap(),
+                    linked_list.create_patch(&before, &after).unwrap(),
+                    single_id,
+                    sparse.create_patch(&before, &after).unwrap(),
+                );
+                patches.push(ids);
+                rows = [(); 4].map(|_| after.clone());
+            }
+            Op::Restore(_) if patches.is_empty() => continue,
+            Op::Restore(sel) => {
+                let (ref_id, ll_id, single_id, sparse_id) = patches.remove(sel % patches.len());
+                let [ref_row, ll_row, single_row, sparse_row] = &mut rows;
+                prop_assert_eq!(reference.restore_patch(ref_id, ref_row), Ok(()));
+                prop_assert_eq!(linked_list.restore_patch(ll_id, ll_row), Ok(()));
+                prop_assert_eq!(single.restore_patch(single_id, single_row), Ok(()));
+                prop_assert_eq!(sparse.restore_patch(sparse_id, sparse_row), Ok(()));
+            }
+        }
+        let masks = [
+            ("linked list", linked_list.patched_mask_for_row()),
+            ("single patch", single.patched_mask_for_row()),
+            ("sparse array", sparse.patched_mask_for_row()),
+        ];
+        for (name, mask) in masks {
+            prop_assert_eq!(mask, reference.patched_mask_for_row(), "op {}: {}", i, name);
+        }
+    }
+    prop_assert_eq!(&rows[1], &rows[0], "linked list");
+    prop_assert_eq!(&rows[2], &rows[0], "single patch");
+    prop_assert_eq!(&rows[3], &rows[0], "sparse array");
+    Ok(())
+}
+
+/// Creates one patch per element of `patches` on every patcher, checking that they all report the
+/// same patched mask as [`LinkedListPatcher`] after each one.
+fn run_patched_masks(
+    layout: &Layout,
+    seed: u64,
+    patches: &[Vec<(usize, u128)>],
+) -> Result<(), TestCaseError> {
+    let mut reference =
+        LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut full_copy = FullCopyPatcher::<Block>::new(&layout.blocks, layout.row_size);
+    let mut toggle_map = ToggleMapPatcher::<Block>::new(&layout.blocks, layout.row_size);
+    let mut sparse = SparseArrayPatcher::<Block>::new(&layout.blocks, layout.row_size);
+    let mut live = original_row(layout, seed);
+
+    for (i, writes) in patches.iter().enumerate() {
+        let before = live.clone();
+        for &(field, value) in writes {
+            let n_fields = layout.field_starts.len().max(1);
+            let Some(&field_start) = layout.field_starts.get(field % n_fields)
+            else {
+                continue;
+            };
+            write_field_bytes(&mut live, &layout.blocks, field_start, &value.to_le_bytes());
+        }
+        reference.create_patch(&before, &live).unwrap();
+        full_copy.create_patch(&before, &live).unwrap();
+        toggle_map.create_patch(&before, &live).unwrap();
+        sparse.create_patch(&before, &live).unwrap();
+
+        let mask = reference.patched_mask_for_row();
+        prop_assert_eq!(
+            &full_copy.patched_mask_for_row(),
+            &mask,
+            "patch {}: full copy",
+            i
+        );
+        prop_assert_eq!(
+            &toggle_map.patched_mask_for_row(),
+            &mask,
+            "patch {}: toggle map",
+            i
+        );
+        prop_assert_eq!(
+            &sparse.patched_mask_for_row(),
+            &mask,
+            "patch {}: sparse array",
+            i
+        );
+    }
+    Ok(())
+}
+
+/// Replaces the whole row, patches `writes` on top, then restores both patches in the given order.
+fn run_replace_row(
+    fields: &[FieldSpec],
+    seed: u64,
+    writes: &[(usize, u128)],
+    replaced_first: bool,
+) -> Result<(), TestCaseError> {
+    let layout = Layout::new(fields);
+    let mut patcher = LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live = original_row(&layout, seed);
+    let mut model = Model {
+        original: live.clone(),
+        patches: Vec::new(),
+    };
+
+    let new_row = original_row(&layout, !seed);
+    let replace_id = patcher.replace_row(&mut live, &new_row).unwrap();
+    let replaced: Vec<_> = layout
+        .field_starts
+        .iter()
+        .map(|&fs| (fs, layout.read_field(&new_row, fs)))
+        .collect();
+    model.patches.push((replace_id, replaced));
+    prop_assert_eq!(&live, &model.expected(&layout), "replace");
+    prop_assert_eq!(patcher.patched_mask_for_row(), model.patched_mask(&layout));
+
+    let before = live.clone();
+    for &(field, value) in writes {
+        let Some(&field_start) = layout.field_starts.get(field % layout.field_starts.len().max(1))
+        else {
+            continue;
+        };
+        write_field_bytes(&mut live, &layout.blocks, field_start, &value.to_le_bytes());
+    }
+    // Writing a field to the value it already holds is not a change
+    let changed: Vec<_> = layout
+        .field_starts
+        .iter()
+        .map(|&fs| (fs, layout.read_field(&live, fs)))
+        .filter(|(fs, value)| *value != layout.read_field(&before, *fs))
+        .collect();
+    let patch_id = patcher.create_patch(&before, &live).unwrap();
+    model.patches.push((patch_id, changed));
+
+    let order = if replaced_first { [0, 0] } else { [1, 0] };
+    for i in order {
+        let (id, _) = model.patches.remove(i);
+        prop_assert_eq!(patcher.restore_patch(id, &mut live), Ok(()));
+        prop_assert_eq!(&live, &model.expected(&layout), "restore {}", id);
+    }
+    prop_assert_eq!(&live, &model.original);
+    Ok(())
+}
+
+/// Patchers which can create patches without first scanning for the span of changed blocks.
+trait FullWalk<'a>: RowPatcher<'a, Block> {
+    fn create_patch_full_walk(
+        &mut self,
+        before: &[Unaligned<Block>],
+        after: &[Unaligned<Block>],
+    ) -> Option<RowPatchId>;
+}
+
+impl<'a> FullWalk<'a> for LinkedListPatcher<'a, Block> {
+    fn create_patch_full_walk(
+        &mut self,
+        before: &[Unaligned<Block>],
+        after: &[Unaligned<Block>],
+    ) -> Option<RowPatchId> {
+        LinkedListPatcher::create_patch_full_walk(self, before, after)
+    }
+}
+
+impl<'a> FullWalk<'a> for SinglePatchPatcher<'a, Block> {
+    fn create_patch_full_walk(
+        &mut self,
+        before: &[Unaligned<Block>],
+        after: &[Unaligned<Block>],
+    ) -> Option<RowPatchId> {
+        SinglePatchPatcher::create_patch_full_walk(self, before, after)
+    }
+}
+
+/// Runs `ops` on two patchers of type `P`, one creating patches with
+/// [`RowPatcher::create_patch`] and the other with [`FullWalk::create_patch_full_walk`], and
+/// checks that they behave identically.
+fn run_full_walk<'a, P: FullWalk<'a>>(
+    layout: &'a Layout,
+    seed: u64,
+    ops: &[Op],
+) -> Result<(), TestCaseError> {
+    let mut fast = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut full = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live_fast = original_row(layout, seed);
+    let mut live_full = live_fast.clone();
+    let mut patches = Vec::new();
+
+    for (i, op) in ops.iter().enumerate() {
+        match *op {
+            Op::Patch(writes) => {
+                let before = live_fast.clone();
+                for &(field, value) in writes {
+                    let n_fields = layout.field_starts.len().max(1);
+                    let Some(&field_start) = layout.field_starts.get(field % n_fields)
+                    else {
+                        continue;
+                    };
+                    let value = value.to_le_bytes();
+                    write_field_bytes(&mut live_fast, &layout.blocks, field_start, &value);
+                }
+                let fast_id = fast.create_patch(&before, &live_fast);
+                let full_id = full.create_patch_full_walk(&before, &live_fast);
+                prop_assert_eq!(fast_id.is_some(), full_id.is_some(), "op {}", i);
+                match fast_id.zip(full_id) {
+                    Some(ids) => {
+                        live_full.clone_from(&live_fast);
+                        patches.push(ids);
+                    }
+                    None => live_fast = before,
+                }
+            }
+            Op::Restore(_) if patches.is_empty() => continue,
+            Op::Restore(sel) => {
+                let (fast_id, full_id) = patches.remove(sel % patches.len());
+                prop_assert_eq!(
+                    fast.restore_patch(fast_id, &mut live_fast),
+                    Ok(()),
+                    "op {}",
+                    i
+                );
+                prop_assert_eq!(
+                    full.restore_patch(full_id, &mut live_full),
+                    Ok(()),
+                    "op {}",
+                    i
+                );
+            }
+        }
+        prop_assert_eq!(&live_fast, &live_full, "op {}: {:?}", i, op);
+        prop_assert_eq!(
+            fast.patched_mask_for_row(),
+            full.patched_mask_for_row(),
+            "op {}",
+            i
+        );
+    }
+    Ok(())
+}
+
+/// Field blocks of a 12 byte padding field with an unofficial u8 carved out of its second block,
+/// as built for unofficial fields. The blocks of the unofficial field come after those of its
+/// host, so the field blocks are not sorted by offset.
+fn carved_layout() -> Layout {
+    let block = |field_start, offset, mask| FieldBlock {
+        field_start,
+        offset,
+        mask,
+    };
+    Layout {
+        blocks: vec![
+            block(0, 0, Block::MAX),
+            block(0, 1, 0xFF),
+            block(0, 1, 0xFFFF_0000),
+            block(0, 2, Block::MAX),
+            block(4, 1, 0xFF00),
+        ],
+        field_starts: vec![0, 4],
+        row_size: 12,
+    }
+}
+
+fn field_spec() -> impl Strategy<Value = FieldSpec> {
+    prop_oneof![
+        1 => (1usize..=4).prop_map(FieldSpec::Gap),
+        2 => (1usize..=7).prop_map(FieldSpec::Bits),
+        4 => prop::sample::select(&[1usize, 2, 4, 8, 12][..]).prop_map(FieldSpec::Bytes),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    // Leak the writes so that `Op` can be `Copy` and shared between patcher runs
+    let writes = prop::collection::vec((0usize..32, any::<u128>()), 1..4)
+        .prop_map(|w| &*Box::leak(w.into_boxed_slice()));
+    prop_oneof![
+        3 => writes.prop_map(Op::Patch),
+        2 => (0usize..8).prop_map(Op::Restore),
+    ]
+}
+
+proptest! {
+    // Integration tests have no lib.rs for proptest's default regressions path to sit next to
+    #![proptest_config(ProptestConfig {
+        failure_persistence: Some(Box::new(FileFailurePersistence::Direct(
+            "tests/proptest-regressions/row_patchers.txt",
+        ))),
+        ..ProptestConfig::with_cases(512)
+    })]
+
+    #[test]
+    fn patchers_match_reference(
+        fields in prop::collection::vec(field_spec(), 1..24),
+        seed in any::<u64>(),
+        ops in prop::collection::vec(op(), 1..48),
+    ) {
+        run_all(&fields, seed, &ops)?;
+    }
+
+    #[test]
+    fn fast_path_matches_full_walk(
+        fields in prop::collection::vec(field_spec(), 1..24),
+        seed in any::<u64>(),
+        ops in prop::collection::vec(op(), 1..48),
+    ) {
+        let layout = Layout::new(&fields);
+        run_full_walk::<LinkedListPatcher<Block>>(&layout, seed, &ops)?;
+        run_full_walk::<SinglePatchPatcher<Block>>(&layout, seed, &ops)?;
+    }
+
+    #[test]
+    fn carved_fields(seed in any::<u64>(), ops in prop::collection::vec(op(), 1..48)) {
+        let layout = carved_layout();
+        run_full_walk::<LinkedListPatcher<Block>>(&layout, seed, &ops)?;
+        run_full_walk::<SinglePatchPatcher<Block>>(&layout, seed, &ops)?;
+        run::<LinkedListPatcher<Block>>(&layout, seed, &ops)?;
+        run::<SinglePatchPatcher<Block>>(&layout, seed, &ops)?;
+        run::<FullCopyPatcher<Block>>(&layout, seed, &ops)?;
+        run::<ToggleMapPatcher<Block>>(&layout, seed, &ops)?;
+    }
+
+    #[test]
+    fn patchers_match_full_copy(
+        fields in prop::collection::vec(field_spec(), 1..24),
+        seed in any::<u64>(),
+        ops in prop::collection::vec(op(), 1..48),
+    ) {
+        run_against_full_copy(&Layout::new(&fields), seed, &ops)?;
+    }
+
+    #[test]
+    fn patched_masks_match(
+        fields in prop::collection::vec(field_spec(), 1..24),
+        seed in any::<u64>(),
+        patches in prop::collection::vec(
+            prop::collection::vec((0usize..32, any::<u128>()), 1..4),
+            1..8,
+        ),
+    ) {
+        run_patched_masks(&Layout::new(&fields), seed, &patches)?;
+    }
+
+    #[test]
+    fn generated_layouts_match_full_copy(
+        row_size in 4usize..512,
+        layout_seed in any::<u64>(),
+        seed in any::<u64>(),
+        ops in prop::collection::vec(op(), 1..48),
+    ) {
+        let config = LayoutConfig { row_size, seed: layout_seed, ..Default::default() };
+        let layout = Layout::generate(&config);
+        prop_assert_eq!(layout.row_size, row_size.next_multiple_of(4));
+        run_against_full_copy(&layout, seed, &ops)?;
+    }
+
+    #[test]
+    fn changed_block_span_matches_naive_scan(
+        len in 0usize..80,
+        changes in prop::collection::vec((any::<usize>(), 1u8..), 0..3),
+    ) {
+        let before = vec![0u8; len];
+        let mut after = before.clone();
+        for &(i, x) in changes.iter().filter(|_| len != 0) {
+            after[i % len] ^= x;
+        }
+        let blocks = |bytes: &[u8]| -> Vec<Unaligned<u16>> {
+            bytes.chunks_exact(2).map(|c| Unaligned(u16::from_ne_bytes([c[0], c[1]]))).collect()
+        };
+        let (before, after) = (blocks(&before), blocks(&after));
+        let first = before.iter().zip(&after).position(|(b, a)| b != a);
+        let last = before.iter().zip(&after).rposition(|(b, a)| b != a);
+        prop_assert_eq!(changed_block_span(&before, &after), first.zip(last).map(|(f, l)| f..=l));
+    }
+
+    #[test]
+    fn replace_row_then_patch(
+        fields in prop::collection::vec(field_spec(), 1..24),
+        seed in any::<u64>(),
+        writes in prop::collection::vec((0usize..32, any::<u128>()), 1..4),
+        replaced_first in any::<bool>(),
+    ) {
+        run_replace_row(&fields, seed, &writes, replaced_first)?;
+    }
+}
+
+// Regression corpus of failing cases previously found by the test above.
+
+fn check(fields: &[FieldSpec], ops: &[Op]) {
+    if let Err(e) = run_all(fields, 0x5EED, ops) {
+        panic!("{e}");
+    }
+}
+
+/// Patch the last field of the def and restore it.
+#[test]
+fn restore_last_field() {
+    use FieldSpec::*;
+    check(
+        &[Bytes(4), Bytes(4)],
+        &[Op::Patch(&[(1, 7)]), Op::Restore(0)],
+    );
+}
+
+/// Re-patch a field after restoring its only patch.
+#[test]
+fn repatch_after_restore() {
+    use FieldSpec::*;
+    check(
+        &[Bytes(4), Bytes(2), Bytes(2)],
+        &[
+            Op::Patch(&[(0, 1)]),
+            Op::Restore(0),
+            Op::Patch(&[(0, 2)]),
+            Op::Restore(0),
+        ],
+    );
+}
+
+/// Two patches on the same field restored out of order, then a third patch on top.
+#[test]
+fn restore_out_of_order() {
+    use FieldSpec::*;
+    check(
+        &[Bytes(4), Bits(3), Bits(5)],
+        &[
+            Op::Patch(&[(1, 1)]),
+            Op::Patch(&[(1, 2), (2, 3)]),
+            Op::Restore(0),
+            Op::Patch(&[(1, 4)]),
+            Op::Restore(1),
+            Op::Restore(0),
+        ],
+    );
+}
+
+/// A multi-block field whose change is only in a later block.
+#[test]
+fn change_in_later_block() {
+    use FieldSpec::*;
+    check(
+        &[Bits(4), Bytes(12)],
+        &[
+            Op::Patch(&[(1, 1 << 80)]),
+            Op::Patch(&[(0, 1)]),
+            Op::Restore(0),
+        ],
+    );
+}
+
+/// A patch restored while its change is hidden by a patch two above it, with a patch of another
+/// field of the same block in between.
+#[test]
+fn restore_hidden_by_several_patches() {
+    use FieldSpec::*;
+    check(
+        &[Bits(1), Bytes(1), Bits(1)],
+        &[
+            Op::Patch(&[(0, 0)]),
+            Op::Patch(&[(22, 0xAB)]),
+            Op::Patch(&[(14, 0)]),
+            Op::Patch(&[(22, 0)]),
+            Op::Restore(1),
+            Op::Restore(2),
+        ],
+    );
+}
+
+/// Field blocks of a 4-bit field followed by a 64-bit field packed against it, which shares its
+/// first block with the 4-bit field and spans three blocks.
+fn packed_three_block_layout() -> Layout {
+    let block = |field_start, offset, mask| FieldBlock {
+        field_start,
+        offset,
+        mask,
+    };
+    Layout {
+        blocks: vec![
+            block(0, 0, 0xF),
+            block(1, 0, !0xF),
+            block(1, 1, Block::MAX),
+            block(1, 2, 0xF),
+        ],
+        field_starts: vec![0, 1],
+        row_size: 12,
+    }
+}
+
+/// Flips `bits` of the given blocks of the row and creates a patch of the change.
+fn flip_bits<'a, P: RowPatcher<'a, Block>>(
+    patcher: &mut P,
+    live: &mut [Unaligned<Block>],
+    bits: &[(usize, Block)],
+) -> RowPatchId {
+    let before = live.to_vec();
+    for &(offset, bits) in bits {
+        live[offset].write(live[offset].read() ^ bits);
+    }
+    patcher.create_patch(&before, live).unwrap()
+}
+
+/// The packed field is changed in its last block but not in its interior one, along with the
+/// field sharing its first block, and restored while it is the top of the stack.
+#[test]
+fn unchanged_interior_block_top() {
+    let layout = packed_three_block_layout();
+    let mut patcher = LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let id = flip_bits(&mut patcher, &mut live, &[(0, 1), (2, 1)]);
+    patcher.restore_patch(id, &mut live).unwrap();
+    assert_eq!(live, original);
+}
+
+/// Same as above, but the field is obscured by a more recent patch when restored.
+#[test]
+fn unchanged_interior_block_obscured() {
+    let layout = packed_three_block_layout();
+    let mut patcher = LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let bottom = flip_bits(&mut patcher, &mut live, &[(0, 1), (2, 1)]);
+    let top = flip_bits(&mut patcher, &mut live, &[(2, 2)]);
+    patcher.restore_patch(bottom, &mut live).unwrap();
+    assert_eq!(
+        live,
+        [original[0], original[1], Unaligned(original[2].read() ^ 3)]
+    );
+
+    patcher.restore_patch(top, &mut live).unwrap();
+    assert_eq!(live, original);
+}
+
+/// Only the unofficial field carved out of another one is changed, then restored.
+#[test]
+fn change_carved_field_only() {
+    let layout = carved_layout();
+    let ops = [Op::Patch(&[(1, 0xAB)]), Op::Restore(0)];
+    run::<LinkedListPatcher<Block>>(&layout, 0, &ops).unwrap();
+    run::<SinglePatchPatcher<Block>>(&layout, 0, &ops).unwrap();
+    run_full_walk::<LinkedListPatcher<Block>>(&layout, 0, &ops).unwrap();
+}
+
+/// Patches a row with one patcher and restores it with another instance of the same type.
+fn check_foreign_id<'a, P: RowPatcher<'a, Block>>(layout: &'a Layout) {
+    let mut issuer = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut other = P::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(layout, 0x5EED);
+    let mut live = original.clone();
+
+    let id = flip_bits(&mut issuer, &mut live, &[(0, 1)]);
+    let patched = live.clone();
+    assert_eq!(
+        other.restore_patch(id, &mut live),
+        Err(RestorePatchError::ForeignId)
+    );
+    assert_eq!(live, patched);
+
+    issuer.restore_patch(id, &mut live).unwrap();
+    assert_eq!(live, original);
+}
+
+/// IDs issued by one patcher instance are rejected by the others.
+#[test]
+fn foreign_ids_are_rejected() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Bytes(4)]);
+    check_foreign_id::<LinkedListPatcher<Block>>(&layout);
+    check_foreign_id::<SinglePatchPatcher<Block>>(&layout);
+    check_foreign_id::<SparseArrayPatcher<Block>>(&layout);
+    check_foreign_id::<FullCopyPatcher<Block>>(&layout);
+    check_foreign_id::<ToggleMapPatcher<Block>>(&layout);
+}
+
+/// Padding between fields is not touched by row replacement.
+#[test]
+fn replace_row_keeps_padding() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(1), Gap(2), Bytes(4)]);
+    let mut patcher = LinkedListPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live = vec![Unaligned(0); 2];
+    let new_row = vec![Unaligned(Block::MAX); 2];
+
+    let id = patcher.replace_row(&mut live, &new_row).unwrap();
+    assert_eq!(live, [Unaligned(0xFF), Unaligned(Block::MAX)]);
+    patcher.restore_patch(id, &mut live).unwrap();
+    assert_eq!(live, [Unaligned(0); 2]);
+
+    assert_eq!(
+        patcher.replace_row(&mut live, &new_row[..1]),
+        Err(ReplaceRowError::SizeMismatch {
+            expected: 2,
+            actual: 1
+        })
+    );
+}
+
+/// Field blocks skipping a whole block of padding, which the sparse array patcher used to reject.
+#[test]
+fn sparse_array_field_gaps() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Gap(4), Bytes(4), Gap(4)]);
+    let offsets: Vec<_> = layout.blocks.iter().map(|fb| fb.offset).collect();
+    assert_eq!(offsets, [0, 2]);
+    assert_eq!(layout.row_size, 16);
+
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+    let mut patches = Vec::new();
+    for (field, value) in [(0, 1u32), (1, 2), (0, 3), (1, 4)] {
+        let before = live.clone();
+        let field_start = layout.field_starts[field];
+        write_field_bytes(&mut live, &layout.blocks, field_start, &value.to_le_bytes());
+        patches.push((patcher.create_patch(&before, &live).unwrap(), before));
+    }
+    assert_eq!(patcher.patched_mask_for_row()[0], Block::MAX);
+    assert_eq!(patcher.patched_mask_for_row()[2], Block::MAX);
+
+    while let Some((id, before)) = patches.pop() {
+        patcher.restore_patch(id, &mut live).unwrap();
+        assert_eq!(live, before);
+    }
+    assert_eq!(live, original);
+    assert_eq!(patcher.patched_mask_for_row(), [0; 4]);
+}
+
+/// The padding bits between a bitfield and the next field are not part of the patched mask.
+#[test]
+fn sparse_array_mask_excludes_padding() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bits(1), Bytes(1)]);
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let mut live = original_row(&layout, 0x5EED);
+
+    let value = !live[0].read() >> 8;
+    sparse_patch(&mut patcher, &layout, &mut live, &[(1, value)]);
+    assert_eq!(patcher.patched_mask_for_row(), [0xFF00]);
+}
+
+/// Writes `writes` to the fields of the row and creates a patch of the change.
+fn sparse_patch(
+    patcher: &mut SparseArrayPatcher<Block>,
+    layout: &Layout,
+    live: &mut [Unaligned<Block>],
+    writes: &[(usize, u32)],
+) -> RowPatchId {
+    let before = live.to_vec();
+    for &(field, value) in writes {
+        let field_start = layout.field_starts[field];
+        write_field_bytes(live, &layout.blocks, field_start, &value.to_le_bytes());
+    }
+    patcher.create_patch(&before, live).unwrap()
+}
+
+/// Restoring a patch below another one changing the same field merges what it hides into it.
+#[test]
+fn sparse_array_merge_overlapping() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Bytes(4)]);
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let bottom = sparse_patch(&mut patcher, &layout, &mut live, &[(0, 1)]);
+    let top = sparse_patch(&mut patcher, &layout, &mut live, &[(0, 2), (1, 3)]);
+    let patched = live.clone();
+    patcher.restore_patch(bottom, &mut live).unwrap();
+    assert_eq!(live, patched);
+    assert_eq!(patcher.patched_mask_for_row(), [Block::MAX; 2]);
+
+    patcher.restore_patch(top, &mut live).unwrap();
+    assert_eq!(live, original);
+    assert_eq!(patcher.patched_mask_for_row(), [0; 2]);
+}
+
+/// Restoring a patch below another one changing other fields leaves nothing to merge.
+#[test]
+fn sparse_array_merge_disjoint() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Bytes(4)]);
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let bottom = sparse_patch(&mut patcher, &layout, &mut live, &[(0, 1)]);
+    let top = sparse_patch(&mut patcher, &layout, &mut live, &[(1, 2)]);
+    patcher.restore_patch(bottom, &mut live).unwrap();
+    assert_eq!(live, [original[0], Unaligned(2)]);
+    assert_eq!(patcher.patched_mask_for_row(), [0, Block::MAX]);
+
+    patcher.restore_patch(top, &mut live).unwrap();
+    assert_eq!(live, original);
+}
+
+/// A patch reverting the change of the one below it merges into an empty diff which still
+/// patches the field, so that restoring it gives back the original value.
+#[test]
+fn sparse_array_merge_cancelling() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Bytes(4)]);
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let bottom = sparse_patch(&mut patcher, &layout, &mut live, &[(1, 1)]);
+    let top = sparse_patch(&mut patcher, &layout, &mut live, &[(1, original[1].read())]);
+    assert_eq!(live, original);
+    patcher.restore_patch(bottom, &mut live).unwrap();
+    assert_eq!(live, original);
+    assert_eq!(patcher.patched_mask_for_row(), [0, Block::MAX]);
+
+    patcher.restore_patch(top, &mut live).unwrap();
+    assert_eq!(live, original);
+    assert_eq!(patcher.patched_mask_for_row(), [0; 2]);
+}
+
+/// Three patches on the same field, of which the middle then the bottom one are restored.
+#[test]
+fn sparse_array_restore_below_top() {
+    use FieldSpec::*;
+    let layout = Layout::new(&[Bytes(4), Bytes(4)]);
+    let mut patcher =
+        SparseArrayPatcher::<Block>::try_new(&layout.blocks, layout.row_size).unwrap();
+    let original = original_row(&layout, 0x5EED);
+    let mut live = original.clone();
+
+    let ids = [1, 2, 3].map(|v| sparse_patch(&mut patcher, &layout, &mut live, &[(0, v)]));
+    patcher.restore_patch(ids[1], &mut live).unwrap();
+    patcher.restore_patch(ids[0], &mut live).unwrap();
+    assert_eq!(live, [Unaligned(3), original[1]]);
+
+    patcher.restore_patch(ids[2], &mut live).unwrap();
+    assert_eq!(live, original);
+}
@@ -0,0 +1,105 @@
+//! Owned [`RowSnapshot`]s of rows and [`RowMut::edit_with_patch`], including rows whose size is
+//! not a multiple of the block size.
+
+use ppatch::{
+    field_metadata::{Block, FieldBlock},
+    param_builder::ParamFileBuilder,
+    param_file::{ParamFile, RowSnapshot},
+    patchers::{
+        base::{PatchRowError, RowPatcher},
+        single_patch::SinglePatchPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+
+/// A u32 at 0, a u32 at 4 and a u16 at 8, without any padding.
+const ROW_SIZE: usize = 10;
+
+fn field_blocks() -> Vec<FieldBlock<Block>> {
+    [
+        (0, 0, 0xFFFF_FFFF),
+        (1, 1, 0xFFFF_FFFF),
+        (2, 2, 0x0000_FFFF),
+    ]
+    .iter()
+    .map(|&(field_start, offset, mask)| FieldBlock {
+        field_start,
+        offset,
+        mask,
+    })
+    .collect()
+}
+
+/// Param file with a row 10, followed by the 0xFF bytes of row 20.
+fn param_file() -> Vec<u8> {
+    let mut builder = ParamFileBuilder::new("A_ST", ROW_SIZE);
+    builder.insert_row(10, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]).unwrap();
+    builder.insert_row(20, &[0xFF; ROW_SIZE]).unwrap();
+    builder.to_bytes()
+}
+
+#[test]
+fn snapshots_are_padded() {
+    let mut file = param_file();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let row = param.by_id(10).unwrap();
+    assert!(row.as_blocks::<Block>().is_err());
+
+    let snapshot: RowSnapshot = row.snapshot();
+    assert_eq!((snapshot.id(), snapshot.len()), (10, ROW_SIZE));
+    assert_eq!(snapshot.bytes(), row.data());
+    // The tail is padded with zeroes rather than the bytes of the next row
+    assert_eq!(
+        snapshot.blocks(),
+        [
+            Unaligned(0x0403_0201),
+            Unaligned(0x0807_0605),
+            Unaligned(0x0000_0A09)
+        ]
+    );
+
+    let wide = row.snapshot::<u64>();
+    assert_eq!(
+        wide.blocks(),
+        [Unaligned(0x0807_0605_0403_0201), Unaligned(0x0A09)]
+    );
+
+    let (head, _) = row.split_tail(0).unwrap();
+    let empty: RowSnapshot = head.snapshot();
+    assert!(empty.is_empty() && empty.blocks().is_empty());
+}
+
+#[test]
+fn edits_create_patches() {
+    let blocks = field_blocks();
+    let mut patcher = SinglePatchPatcher::new(&blocks, ROW_SIZE.next_multiple_of(4));
+    let mut file = param_file();
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.by_id_mut(10).unwrap();
+
+    let id = row
+        .edit_with_patch(&mut patcher, |data| {
+            data[8..10].copy_from_slice(&[0xAA, 0xBB])
+        })
+        .unwrap();
+    assert_eq!(row.data()[8..], [0xAA, 0xBB]);
+
+    // The patcher only holds a single patch, so the next edit is rolled back
+    assert_eq!(
+        row.edit_with_patch(&mut patcher, |data| data.fill(0)),
+        Err(PatchRowError::PatchRejected)
+    );
+    assert_eq!(row.data(), [1, 2, 3, 4, 5, 6, 7, 8, 0xAA, 0xBB]);
+
+    // Restoring the patch on a padded snapshot and writing it back reverts the edit
+    let mut live: Vec<Unaligned<Block>> = row.snapshot().blocks().to_vec();
+    patcher.restore_patch(id, &mut live).unwrap();
+    row.data_mut().copy_from_slice(&block_bytes(&live)[..ROW_SIZE]);
+    assert_eq!(row.data(), [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+    assert_eq!(param.by_id(20).unwrap().data(), [0xFF; ROW_SIZE]);
+}
+
+fn block_bytes(blocks: &[Unaligned<Block>]) -> Vec<u8> {
+    blocks.iter().flat_map(|b| b.read().to_le_bytes()).collect()
+}
@@ -0,0 +1,178 @@
+//! Dumping all the fields of a row, with the fixture defs.
+
+use paramdex::Paramdex;
+use ppatch::{
+    param_file::ParamFile,
+    row_view::{DecodedValue, FieldValue, RowView},
+};
+
+/// Builds a 64-bit little endian param file with a single row of ID 10300.
+fn build(row: &[u8]) -> Vec<u8> {
+    const HEADER_SIZE: usize = 0x40;
+    const DESC_SIZE: usize = 24;
+
+    let data_start = HEADER_SIZE + DESC_SIZE;
+    let data_end = data_start + row.len();
+    let mut file = vec![0u8; data_end + 8];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA..0xC].copy_from_slice(&1u16.to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&10300u32.to_le_bytes());
+    file[HEADER_SIZE + 8..HEADER_SIZE + 16].copy_from_slice(&(data_start as u64).to_le_bytes());
+    file[data_start..data_end].copy_from_slice(row);
+    file[data_end..].copy_from_slice(b"strings\0");
+    file
+}
+
+/// Row of `BitfieldTestParam` at version 10400.
+#[rustfmt::skip]
+const BITFIELD_ROW: [u8; 20] = [
+    0xFB, 0xFF, 0xFF, 0xFF, // id = -5
+    0xCB, 0, // flagA = 1, flagB = 5, flagC = 0xC
+    0x34, 0x12, // shortVal
+    0x7F, 0, 0, 0, // byteVal
+    0xBC, 0x5A, 0x34, 0x12, // wideBits = 0xABC, narrowBits = 0x12345
+    0x90, // pad, lastBits = 9
+    0x01, 0x02, 0x03, // endPad
+];
+
+fn values<'a>(view: &RowView<'a>) -> Vec<(&'a str, DecodedValue<'a>)> {
+    view.fields().map(|f| (f.name, f.value)).collect()
+}
+
+#[test]
+fn bitfields_are_masked() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(10400);
+    let def = &paramdex.def_with_meta("BitfieldTestParam").unwrap().def;
+    let mut file = build(&BITFIELD_ROW);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let view = RowView::new(param.rows().next().unwrap(), def);
+
+    assert_eq!(
+        values(&view),
+        [
+            ("id", DecodedValue::I32(-5)),
+            ("flagA", DecodedValue::U8(1)),
+            ("flagB", DecodedValue::U8(5)),
+            ("flagC", DecodedValue::U8(0xC)),
+            ("shortVal", DecodedValue::U16(0x1234)),
+            ("byteVal", DecodedValue::U8(0x7F)),
+            ("wideBits", DecodedValue::U32(0xABC)),
+            ("narrowBits", DecodedValue::U32(0x12345)),
+            ("pad", DecodedValue::U8(0)),
+            ("lastBits", DecodedValue::U8(9)),
+            ("endPad", DecodedValue::Bytes(&[1, 2, 3])),
+        ]
+    );
+    let narrow_bits = view.fields().find(|f| f.name == "narrowBits").unwrap();
+    assert_eq!(narrow_bits.bit_offset, 12 * 8 + 12);
+    assert_eq!(narrow_bits.display_name, None);
+}
+
+#[test]
+fn disabled_fields_are_skipped() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(10500);
+    let def = &paramdex.def_with_meta("BitfieldTestParam").unwrap().def;
+    let mut file = build(&BITFIELD_ROW[..16]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let view = RowView::new(param.rows().next().unwrap(), def);
+
+    // shortVal is removed in 10500
+    let names: Vec<_> = view.fields().map(|f| f.name).collect();
+    assert_eq!(
+        names,
+        [
+            "id",
+            "flagA",
+            "flagB",
+            "flagC",
+            "byteVal",
+            "wideBits",
+            "narrowBits",
+            "pad",
+            "lastBits",
+            "endPad"
+        ]
+    );
+    let byte_val = view.fields().find(|f| f.name == "byteVal").unwrap();
+    assert_eq!(byte_val.bit_offset, 5 * 8);
+
+    // Fields past the end of the row are skipped too
+    let mut file = build(&BITFIELD_ROW[..8]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let view = RowView::new(param.rows().next().unwrap(), def);
+    assert_eq!(view.fields().last().unwrap().name, "byteVal");
+}
+
+#[test]
+fn strings_and_arrays() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(u64::MAX);
+    let def = &paramdex.def_with_meta("ArrayTestParam").unwrap().def;
+
+    let mut row = [0u8; 56];
+    row[0..4].copy_from_slice(&1.5f32.to_le_bytes());
+    row[4..8].copy_from_slice(&7i32.to_le_bytes());
+    row[20..23].copy_from_slice(&[1, 2, 3]);
+    row[24..30].copy_from_slice(b"Dagger");
+    for (i, c) in "Ker".encode_utf16().enumerate() {
+        row[40 + 2 * i..42 + 2 * i].copy_from_slice(&c.to_le_bytes());
+    }
+    let mut file = build(&row);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let view = RowView::new(param.rows().next().unwrap(), def);
+
+    let mut expected_values = [0u8; 16];
+    expected_values[0] = 7;
+    assert_eq!(
+        values(&view),
+        [
+            ("weight", DecodedValue::F32(1.5)),
+            ("values", DecodedValue::Bytes(&expected_values)),
+            ("bytes", DecodedValue::Bytes(&[1, 2, 3])),
+            ("pad", DecodedValue::Bytes(&[0])),
+            ("name", DecodedValue::Str("Dagger".to_owned())),
+            ("wideName", DecodedValue::WStr("Ker".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn display_names_and_table() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex.compute_def_layouts(u64::MAX);
+    let def = paramdex.def_with_meta("EnumTestParam").unwrap();
+    let mut file = build(&[1, 0, 0, 0, 2, 1, 0xFF, 0xFF]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    let view =
+        RowView::new(param.rows().next().unwrap(), &def.def).with_meta(def.meta.as_ref().unwrap());
+
+    assert_eq!(
+        view.fields().next().unwrap(),
+        FieldValue {
+            name: "iconId",
+            display_name: Some("Icon"),
+            value: DecodedValue::I32(1),
+            bit_offset: 0,
+        }
+    );
+    assert_eq!(
+        view.to_string(),
+        "Row 10300 (ENUM_TEST_PARAM_ST)\n\
+        0x000     iconId     Icon        1\n\
+        0x004     spellType  Spell Type  2\n\
+        0x005     isEnabled  Enabled     1\n\
+        0x006     mixed      Mixed       -1\n"
+    );
+
+    // Without a meta, there is no display name column
+    let view = RowView::new(view.row(), &def.def);
+    assert_eq!(
+        view.to_string().lines().nth(1),
+        Some("0x000     iconId     1")
+    );
+}
@@ -0,0 +1,140 @@
+//! Params with zero or one row, whose row size cannot always be inferred from the offsets of
+//! the rows.
+
+use ppatch::param_file::{BlockKind, DataBlock, FromBytesError, ParamFile};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const ROW_SIZE: usize = 8;
+const PARAM_TYPE: &[u8] = b"TEST_ST\0";
+
+/// Layout of the row data and param type string of a built file.
+#[derive(Clone, Copy)]
+enum Layout {
+    /// No rows, followed by the param type string.
+    Empty,
+    /// One row followed by `padding` bytes, then the param type string.
+    RowFirst { padding: usize },
+    /// The param type string, then one row, optionally followed by a row name.
+    ParamTypeFirst { named: bool },
+}
+
+/// Builds a 64-bit little endian param file storing its param type at an offset, with row 10
+/// filled with `0xAB` if it has one.
+fn build(layout: Layout) -> Vec<u8> {
+    let row_count = !matches!(layout, Layout::Empty) as usize;
+    let data_start = HEADER_SIZE + row_count * DESC_SIZE;
+    let mut file = vec![0u8; data_start];
+    file[0xA..0xC].copy_from_slice(&(row_count as u16).to_le_bytes());
+    file[0x2D] = 0x80 | 4 | 3;
+    file[0x30..0x38].copy_from_slice(&(data_start as u64).to_le_bytes());
+
+    let put_row = |file: &mut Vec<u8>| {
+        file[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&10u32.to_le_bytes());
+        let offset = (file.len() as u64).to_le_bytes();
+        file[HEADER_SIZE + 8..HEADER_SIZE + 16].copy_from_slice(&offset);
+        file.extend([0xAB; ROW_SIZE]);
+    };
+    let put_param_type = |file: &mut Vec<u8>| {
+        let offset = file.len() as u32;
+        file[0x10..0x14].copy_from_slice(&offset.to_le_bytes());
+        file.extend(PARAM_TYPE);
+    };
+    match layout {
+        Layout::Empty => put_param_type(&mut file),
+        Layout::RowFirst { padding } => {
+            put_row(&mut file);
+            file.extend(vec![0xEE; padding]);
+            put_param_type(&mut file);
+        }
+        Layout::ParamTypeFirst { named } => {
+            put_param_type(&mut file);
+            put_row(&mut file);
+            if named {
+                let offset = (file.len() as u64).to_le_bytes();
+                file[HEADER_SIZE + 16..HEADER_SIZE + 24].copy_from_slice(&offset);
+                file.extend(b"name\0");
+            }
+        }
+    }
+    let strings_offset = file.len() as u32;
+    file[0..4].copy_from_slice(&strings_offset.to_le_bytes());
+    file
+}
+
+#[test]
+fn zero_rows() {
+    let mut file = build(Layout::Empty);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.header().row_count(), 0);
+    assert_eq!(param.row_size(), 0);
+    assert!(param.has_uniform_rows());
+    assert_eq!(param.rows().count(), 0);
+    assert!(param.by_id(10).is_none());
+    assert_eq!(param.param_type(), "TEST_ST");
+
+    let param = ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE).unwrap();
+    assert_eq!(param.header().row_count(), 0);
+}
+
+#[test]
+fn one_row_before_param_type() {
+    let mut file = build(Layout::RowFirst { padding: 0 });
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.row_size(), ROW_SIZE);
+    assert_eq!(param.by_id(10).unwrap().data(), [0xAB; ROW_SIZE]);
+
+    // Padding after the only row is part of it, unless the row size is given
+    let mut file = build(Layout::RowFirst { padding: 4 });
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert_eq!(param.row_size(), ROW_SIZE + 4);
+    let param = ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE).unwrap();
+    assert_eq!(param.by_id(10).unwrap().data(), [0xAB; ROW_SIZE]);
+}
+
+#[test]
+fn one_row_after_param_type() {
+    let data_end = HEADER_SIZE + DESC_SIZE;
+    let offset = data_end + PARAM_TYPE.len();
+
+    for named in [false, true] {
+        let mut file = build(Layout::ParamTypeFirst { named });
+        assert_eq!(
+            ParamFile::from_bytes(&mut file).err(),
+            Some(FromBytesError::AmbiguousRowSize { offset, data_end })
+        );
+        // Without validation, the row is empty rather than extending past the end of the file
+        let param = unsafe { ParamFile::from_bytes_unchecked(&mut file) };
+        assert_eq!(param.row_size(), 0);
+
+        let param = ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE).unwrap();
+        assert_eq!(param.by_id(10).unwrap().data(), [0xAB; ROW_SIZE]);
+        assert_eq!(param.param_type(), "TEST_ST");
+        let name = param.row_name(0);
+        assert_eq!(name.as_deref(), named.then_some("name"));
+    }
+
+    // The row must end before the name following it, or the end of the file
+    let mut file = build(Layout::ParamTypeFirst { named: true });
+    let strings = DataBlock {
+        kind: BlockKind::Strings,
+        offset: offset + ROW_SIZE,
+        len: 5,
+    };
+    assert_eq!(
+        ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE + 1).err(),
+        Some(FromBytesError::IntersectingData {
+            first: DataBlock {
+                kind: BlockKind::Row(0),
+                offset,
+                len: ROW_SIZE + 1,
+            },
+            second: strings,
+        })
+    );
+    let mut file = build(Layout::ParamTypeFirst { named: false });
+    assert!(matches!(
+        ParamFile::from_bytes_with_row_size(&mut file, ROW_SIZE + 1),
+        Err(FromBytesError::OutOfBoundsOffset { .. })
+    ));
+}
@@ -0,0 +1,115 @@
+//! Writing row names with [`ParamFileBuilder::set_row_name`] and listing them with
+//! [`ParamFile::strings`].
+
+use ppatch::{param_builder::ParamFileBuilder, param_file::ParamFile};
+
+const ROW_SIZE: usize = 4;
+
+/// Builder with rows 10 to 50 and no names.
+fn builder() -> ParamFileBuilder {
+    let mut builder = ParamFileBuilder::new("TEST_ST", ROW_SIZE);
+    for id in [10, 20, 30, 40, 50] {
+        builder.insert_row(id, &id.to_le_bytes()).unwrap();
+    }
+    builder
+}
+
+/// Same as [`builder`], for a file whose names are not unicode.
+fn byte_builder() -> ParamFileBuilder {
+    let mut bytes = builder().to_bytes();
+    bytes[0x2E] = 0;
+    ParamFileBuilder::from_file(&ParamFile::from_bytes(&mut bytes).unwrap())
+}
+
+fn strings_offset(bytes: &[u8]) -> usize {
+    u32::from_ne_bytes(bytes[..4].try_into().unwrap()) as usize
+}
+
+fn strings(param: &ParamFile) -> Vec<(usize, String)> {
+    param.strings().map(|(ofs, s)| (ofs, s.into_owned())).collect()
+}
+
+#[test]
+fn unicode_names_are_deduplicated() {
+    let mut builder = builder();
+    for (id, name) in [(10, "Dagger"), (20, "Club"), (30, "Dagger"), (50, "")] {
+        assert!(builder.set_row_name(id, Some(name)));
+    }
+    assert!(!builder.set_row_name(60, Some("Mace")));
+    assert_eq!(builder.row_name(30).as_deref(), Some("Dagger"));
+    assert_eq!(builder.row_name(40), None);
+
+    let mut bytes = builder.to_bytes();
+    let strings_offset = strings_offset(&bytes);
+    let len = bytes.len();
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    let descs = param.row_descriptors();
+    assert_eq!(descs[0].name_offset, descs[2].name_offset);
+    assert_eq!(descs[3].name_offset, 0);
+
+    // Names start at the strings offset, past the param type
+    let type_end = param.header().data_end_ofs() + b"TEST_ST\0".len();
+    assert_eq!(strings_offset, type_end);
+    let dagger = strings_offset + strings_offset % 2;
+    let club = dagger + 2 * "Dagger\0".len();
+    let empty = club + 2 * "Club\0".len();
+    assert_eq!(
+        strings(&param),
+        [
+            (dagger, "Dagger".to_owned()),
+            (club, "Club".to_owned()),
+            (empty, String::new()),
+        ]
+    );
+    // The empty name is the last thing in the file
+    assert_eq!(len, empty + 2);
+}
+
+#[test]
+fn renaming_and_removing_names() {
+    let mut builder = builder();
+    builder.set_row_name(10, Some("Longsword"));
+    builder.set_row_name(20, Some("Longsword"));
+    let before = builder.to_bytes().len();
+
+    builder.set_row_name(10, Some("Broadsword"));
+    builder.set_row_name(20, None);
+    assert_eq!(builder.row_name(20), None);
+    let mut bytes = builder.to_bytes();
+    // The old name isn't kept around, so the file only grows by the extra UTF-16 unit
+    assert_eq!(bytes.len(), before + 2);
+    let param = ParamFile::from_bytes(&mut bytes).unwrap();
+    let names: Vec<_> = param.rows().map(|r| r.name()).collect();
+    assert_eq!(names, [Some("Broadsword".into()), None, None, None, None]);
+    assert_eq!(param.strings().count(), 1);
+}
+
+#[test]
+fn byte_names() {
+    let mut builder = byte_builder();
+    builder.set_row_name(10, Some("Dagger"));
+    builder.set_row_name(20, Some("Dagger"));
+    // Shift-JIS for "ダガー"
+    let sjis = [0x83, 0x5F, 0x83, 0x4B, 0x81, 0x5B];
+    builder.set_row_name_bytes(40, Some(&sjis));
+
+    let bytes = builder.to_bytes();
+    let strings_offset = strings_offset(&bytes);
+    let mut file = bytes.clone();
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(!param.header().is_unicode());
+    let sjis_offset = strings_offset + "Dagger\0".len();
+    assert_eq!(strings(&param)[0], (strings_offset, "Dagger".to_owned()));
+    assert_eq!(param.strings().nth(1).unwrap().0, sjis_offset);
+    assert_eq!(bytes[sjis_offset..], [&sjis[..], &[0]].concat());
+
+    // Names round trip without being decoded
+    let copy = ParamFileBuilder::from_file(&param).to_bytes();
+    assert_eq!(copy, bytes);
+}
+
+#[test]
+#[should_panic = "odd length"]
+fn odd_utf16_names_panic() {
+    builder().set_row_name_bytes(10, Some(&[0x41]));
+}
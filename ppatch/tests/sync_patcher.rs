@@ -0,0 +1,159 @@
+//! Sharing row patchers between threads with [`SyncRowPatcher`].
+//!
+//! Threads create and restore patches on disjoint rows of a shared table of patchers, while the
+//! same edits are replayed on an unsynchronized [`FullCopyPatcher`] per row. Remaining patches
+//! are then restored from other threads than the ones which created them.
+
+use std::thread;
+
+use ppatch::{
+    fields::FieldBlock,
+    patchers::{
+        base::{RowPatchId, RowPatcher},
+        full_copy::FullCopyPatcher,
+        linked_list::LinkedListPatcher,
+        sync::SyncRowPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const THREADS: usize = 8;
+const ROWS_PER_THREAD: usize = 4;
+const ROW_BLOCKS: usize = 8;
+const OPS_PER_ROW: usize = 500;
+
+type Synced<'a> = SyncRowPatcher<LinkedListPatcher<'a, u32>>;
+
+/// One field per block.
+fn field_blocks() -> Vec<FieldBlock<u32>> {
+    (0..ROW_BLOCKS)
+        .map(|i| FieldBlock {
+            field_start: i as u16,
+            offset: i as u16,
+            mask: u32::MAX,
+        })
+        .collect()
+}
+
+fn original(row: usize) -> Vec<Unaligned<u32>> {
+    (0..ROW_BLOCKS).map(|i| Unaligned((row * ROW_BLOCKS + i) as u32)).collect()
+}
+
+/// Live memory and outstanding patches of a row, along with the control patcher.
+struct Row<'a> {
+    live: Vec<Unaligned<u32>>,
+    control: FullCopyPatcher<'a, u32>,
+    control_live: Vec<Unaligned<u32>>,
+    /// IDs issued by the shared patcher and the control one for the same patch.
+    patches: Vec<(RowPatchId, RowPatchId)>,
+}
+
+impl<'a> Row<'a> {
+    fn new(field_blocks: &'a [FieldBlock<u32>], index: usize) -> Self {
+        Self {
+            live: original(index),
+            control: FullCopyPatcher::new(field_blocks, 4 * ROW_BLOCKS),
+            control_live: original(index),
+            patches: Vec::new(),
+        }
+    }
+
+    fn create(&mut self, patcher: &Synced<'a>, rng: &mut impl Rng) {
+        let before = self.live.clone();
+        for _ in 0..rng.gen_range(1..=3) {
+            let block = rng.gen_range(0..ROW_BLOCKS);
+            let value = rng.gen();
+            self.live[block] = Unaligned(value);
+            self.control_live[block] = Unaligned(value);
+        }
+        let id = patcher.create_patch(&before, &self.live);
+        let control_id = self.control.create_patch(&before, &self.control_live);
+        assert_eq!(id.is_some(), control_id.is_some());
+        if let (Some(id), Some(control_id)) = (id, control_id) {
+            self.patches.push((id, control_id));
+        }
+    }
+
+    fn restore(&mut self, patcher: &Synced<'a>, index: usize) {
+        let (id, control_id) = self.patches.swap_remove(index);
+        patcher.restore_patch(id, &mut self.live).unwrap();
+        self.control.restore_patch(control_id, &mut self.control_live).unwrap();
+    }
+
+    fn assert_matches_control(&self) {
+        let live: Vec<u32> = self.live.iter().map(|b| b.read()).collect();
+        let control: Vec<u32> = self.control_live.iter().map(|b| b.read()).collect();
+        assert_eq!(live, control);
+    }
+}
+
+#[test]
+fn shared_patchers_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Synced<'static>>();
+    assert_send_sync::<SyncRowPatcher<FullCopyPatcher<'static, u32>>>();
+}
+
+#[test]
+fn threads_patch_disjoint_rows() {
+    let field_blocks = field_blocks();
+    let patchers: Vec<Synced> = (0..THREADS * ROWS_PER_THREAD)
+        .map(|_| SyncRowPatcher::new(&field_blocks, 4 * ROW_BLOCKS))
+        .collect();
+    let mut rows: Vec<Row> = (0..patchers.len()).map(|i| Row::new(&field_blocks, i)).collect();
+
+    thread::scope(|s| {
+        for (t, (rows, patchers)) in rows
+            .chunks_mut(ROWS_PER_THREAD)
+            .zip(patchers.chunks(ROWS_PER_THREAD))
+            .enumerate()
+        {
+            s.spawn(move || {
+                let mut rng = StdRng::seed_from_u64(t as u64);
+                for _ in 0..OPS_PER_ROW * ROWS_PER_THREAD {
+                    let r = rng.gen_range(0..ROWS_PER_THREAD);
+                    let row = &mut rows[r];
+                    if row.patches.is_empty() || rng.gen_bool(0.6) {
+                        row.create(&patchers[r], &mut rng);
+                    }
+                    else {
+                        let index = rng.gen_range(0..row.patches.len());
+                        row.restore(&patchers[r], index);
+                    }
+                }
+            });
+        }
+    });
+
+    for (row, patcher) in rows.iter().zip(&patchers) {
+        row.assert_matches_control();
+        let mask = patcher.patched_mask_for_row();
+        assert_eq!(mask, row.control.patched_mask_for_row());
+    }
+
+    // Restore the remaining patches from threads which did not create them, interleaving rows
+    // so that every thread touches rows of several creating threads.
+    thread::scope(|s| {
+        let mut assigned: Vec<Vec<(&mut Row, &Synced)>> =
+            (0..THREADS).map(|_| Vec::new()).collect();
+        for (i, pair) in rows.iter_mut().zip(&patchers).enumerate() {
+            assigned[i % THREADS].push(pair);
+        }
+        for rows in assigned {
+            s.spawn(move || {
+                for (row, patcher) in rows {
+                    while !row.patches.is_empty() {
+                        row.restore(patcher, row.patches.len() - 1);
+                    }
+                }
+            });
+        }
+    });
+
+    for (i, (row, patcher)) in rows.iter().zip(&patchers).enumerate() {
+        row.assert_matches_control();
+        assert_eq!(row.live, original(i));
+        assert!(patcher.patched_mask_for_row().iter().all(|&m| m == 0));
+    }
+}
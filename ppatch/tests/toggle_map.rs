@@ -0,0 +1,133 @@
+//! [`ToggleMapPatcher`] with conflicting patches on the same field, and toggling fields between
+//! their original and patched values.
+
+use ppatch::{
+    fields::{read_field_bytes, write_field_bytes},
+    patchers::{
+        base::{RestorePatchError, RowPatcher},
+        toggle_map::ToggleMapPatcher,
+    },
+    testing::{random_row, FieldLayout, FieldSpec},
+    util::unaligned::Unaligned,
+};
+
+type Block = u32;
+type Row = Vec<Unaligned<Block>>;
+
+/// A flag bit, a u16, a byte, a 32 bit bitfield straddling two blocks and a u8.
+fn layout() -> FieldLayout<Block> {
+    use FieldSpec::*;
+    FieldLayout::new(&[Bits(1), Bytes(2), Gap(1), Bits(8), Bits(32), Bytes(1)])
+}
+
+struct Fixture {
+    layout: FieldLayout<Block>,
+    original: Row,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let layout = layout();
+        let original = random_row(layout.row_size, 0x5EED);
+        Self { layout, original }
+    }
+
+    fn field(&self, index: usize) -> u16 {
+        self.layout.field_starts[index]
+    }
+
+    fn with(&self, row: &Row, index: usize, value: u32) -> Row {
+        let mut row = row.clone();
+        write_field_bytes(
+            &mut row,
+            &self.layout.blocks,
+            self.field(index),
+            &value.to_le_bytes(),
+        );
+        row
+    }
+
+    fn read(&self, row: &Row, index: usize) -> u32 {
+        let mut value = [0; 4];
+        read_field_bytes(row, &self.layout.blocks, self.field(index), &mut value);
+        u32::from_le_bytes(value)
+    }
+}
+
+#[test]
+fn conflicting_patches() {
+    let fx = Fixture::new();
+    for restore_top_first in [true, false] {
+        let mut patcher = ToggleMapPatcher::try_new(&fx.layout.blocks, fx.layout.row_size).unwrap();
+        let mut live = fx.original.clone();
+
+        // Two patches of the straddling field, the second one also changing the flag
+        let after = fx.with(&live, 3, 0x1111_1111);
+        let first = patcher.create_patch(&live, &after).unwrap();
+        live = after;
+        let after = fx.with(&fx.with(&live, 3, 0x2222_2222), 0, !fx.read(&live, 0) & 1);
+        let second = patcher.create_patch(&live, &after).unwrap();
+        live = after;
+
+        if restore_top_first {
+            patcher.restore_patch(second, &mut live).unwrap();
+            assert_eq!(fx.read(&live, 3), 0x1111_1111);
+            assert_eq!(fx.read(&live, 0), fx.read(&fx.original, 0));
+            patcher.restore_patch(first, &mut live).unwrap();
+        }
+        else {
+            // The second patch is still visible
+            patcher.restore_patch(first, &mut live).unwrap();
+            assert_eq!(fx.read(&live, 3), 0x2222_2222);
+            patcher.restore_patch(second, &mut live).unwrap();
+        }
+        assert_eq!(live, fx.original);
+        assert!(patcher.patched_mask_for_row().iter().all(|&m| m == 0));
+        assert_eq!(
+            patcher.restore_patch(first, &mut live),
+            Err(RestorePatchError::UnknownId)
+        );
+    }
+}
+
+#[test]
+fn toggles() {
+    let fx = Fixture::new();
+    let mut patcher = ToggleMapPatcher::try_new(&fx.layout.blocks, fx.layout.row_size).unwrap();
+    let mut live = fx.original.clone();
+    let (flag, u16_field) = (fx.field(0), fx.field(1));
+    let flag_on = !fx.read(&live, 0) & 1;
+
+    assert_eq!(patcher.toggle(flag, &mut live), None);
+    let after = fx.with(&live, 0, flag_on);
+    let id = patcher.create_patch(&live, &after).unwrap();
+    live = after;
+
+    for _ in 0..3 {
+        assert_eq!(patcher.toggle(flag, &mut live), Some(false));
+        assert_eq!(live, fx.original);
+        assert_eq!(patcher.is_toggled_on(flag), Some(false));
+        assert_eq!(patcher.toggle(flag, &mut live), Some(true));
+        assert_eq!(fx.read(&live, 0), flag_on);
+    }
+    // Toggling doesn't affect other fields, nor create patches
+    assert_eq!(patcher.toggle(u16_field, &mut live), None);
+
+    // A patch changing a toggled off field switches it back on
+    patcher.toggle(flag, &mut live);
+    let after = fx.with(&fx.with(&live, 0, flag_on), 1, 0xBEEF);
+    let second = patcher.create_patch(&live, &after).unwrap();
+    live = after;
+    assert_eq!(patcher.is_toggled_on(flag), Some(true));
+
+    // Restoring the top patch of a toggled off field leaves it at its original value
+    patcher.toggle(flag, &mut live);
+    patcher.restore_patch(second, &mut live).unwrap();
+    assert_eq!(fx.read(&live, 0), fx.read(&fx.original, 0));
+    assert_eq!(patcher.is_toggled_on(flag), Some(false));
+    assert_eq!(fx.read(&live, 1), fx.read(&fx.original, 1));
+
+    patcher.restore_patch(id, &mut live).unwrap();
+    assert_eq!(patcher.is_toggled_on(flag), None);
+    assert_eq!(live, fx.original);
+}
@@ -0,0 +1,99 @@
+//! End-to-end patching of unofficial fields merged into a fixture def.
+
+use paramdex::{paramdef::Paramdef, unofficial::UnofficialFields, Paramdex};
+use ppatch::{
+    fields::{read_field_bytes, write_field_bytes, FieldBlock},
+    patchers::{base::RowPatcher, linked_list::LinkedListPatcher},
+    util::unaligned::Unaligned,
+};
+
+type Block = u32;
+const BLOCK_BITS: usize = Block::BITS as usize;
+
+const OVERLAY: &str = r#"
+[[BITFIELD_TEST_PARAM_ST]]
+host = "endPad"
+bit_offset = 4
+def = "u8 discoveredBits:6"
+"#;
+
+/// Builds the field blocks of `def` like the build script, with the bits of unofficial fields
+/// carved out of their host. Masks are kept contiguous, as expected by the field accessors.
+///
+/// Returns the blocks and the first block index of each field by name.
+fn field_blocks(def: &Paramdef) -> (Vec<FieldBlock<Block>>, Vec<(String, u16)>) {
+    let mut blocks = Vec::new();
+    let mut starts = Vec::new();
+    for f in def.fields.iter().filter(|f| f.bit_offset.is_some()) {
+        let start = f.bit_offset.unwrap();
+        let claimed: Vec<_> = def
+            .unofficial_fields_in(&f.field_def.name)
+            .map(|u| u.bit_offset.unwrap()..u.bit_offset.unwrap() + u.size_bits())
+            .collect();
+
+        let field_start = blocks.len() as u16;
+        let mut prev_bit = None;
+        for bit in (start..start + f.size_bits()).filter(|b| !claimed.iter().any(|c| c.contains(b)))
+        {
+            let offset = (bit / BLOCK_BITS) as u16;
+            let contiguous = prev_bit.replace(bit) == Some(bit.wrapping_sub(1));
+            match blocks.last_mut() {
+                Some(FieldBlock {
+                    field_start: fs,
+                    offset: o,
+                    mask,
+                }) if *fs == field_start && *o == offset && contiguous => {
+                    *mask |= 1 << (bit % BLOCK_BITS)
+                }
+                _ => blocks.push(FieldBlock {
+                    field_start,
+                    offset,
+                    mask: 1 << (bit % BLOCK_BITS),
+                }),
+            }
+        }
+        starts.push((f.field_def.name.clone(), field_start));
+    }
+    (blocks, starts)
+}
+
+#[test]
+fn patch_unofficial_field() {
+    let mut paramdex = Paramdex::fixture();
+    paramdex
+        .merge_unofficial_fields(&UnofficialFields::from_toml(OVERLAY).unwrap())
+        .unwrap()
+        .compute_def_layouts(u64::MAX);
+    let def = &paramdex.def_with_meta("BitfieldTestParam").unwrap().def;
+    let row_size = def.size_bytes.unwrap();
+
+    let (blocks, starts) = field_blocks(def);
+    let start_of = |name: &str| starts.iter().find(|(n, _)| n == name).unwrap().1;
+    let (host, discovered) = (start_of("endPad"), start_of("discoveredBits"));
+
+    let mut patcher = LinkedListPatcher::<Block>::try_new(&blocks, row_size).unwrap();
+    let original = vec![Unaligned(0); row_size / 4];
+    let mut live = original.clone();
+
+    // Patch the unofficial field, then the rest of the padding on top
+    write_field_bytes(&mut live, &blocks, discovered, &[0x2A]);
+    let discovered_patch = patcher.create_patch(&original, &live).unwrap();
+    let before = live.clone();
+    write_field_bytes(&mut live, &blocks, host, &[0xFF; 3]);
+    let host_patch = patcher.create_patch(&before, &live).unwrap();
+
+    let mut value = [0];
+    read_field_bytes(&live, &blocks, discovered, &mut value);
+    assert_eq!(value, [0x2A]);
+
+    // Restoring the unofficial field leaves the padding patch intact, and vice versa
+    patcher.restore_patch(discovered_patch, &mut live).unwrap();
+    read_field_bytes(&live, &blocks, discovered, &mut value);
+    assert_eq!(value, [0]);
+    let mut padding = [0; 3];
+    assert_eq!(read_field_bytes(&live, &blocks, host, &mut padding), 3);
+    assert_eq!(padding, [0xFF, 0xFF, 0x03]);
+
+    patcher.restore_patch(host_patch, &mut live).unwrap();
+    assert_eq!(live, original);
+}
@@ -0,0 +1,136 @@
+//! Errors of [`ParamFile::from_bytes`] and the checks skipped by [`ValidationLevel`]s.
+
+use ppatch::param_file::{
+    BlockKind, DataBlock, FromBytesError, ParamFile, TrustedLayout, ValidationLevel,
+};
+
+const HEADER_SIZE: usize = 0x40;
+const DESC_SIZE: usize = 24;
+const ROW_SIZE: usize = 4;
+
+/// Builds a 64-bit little endian param file with a row of [`ROW_SIZE`] bytes per ID.
+fn build(ids: &[u32]) -> Vec<u8> {
+    let data_start = HEADER_SIZE + ids.len() * DESC_SIZE;
+    let data_end = data_start + ids.len() * ROW_SIZE;
+    let mut file = vec![0u8; data_end + 8];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA..0xC].copy_from_slice(&(ids.len() as u16).to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[data_end..].copy_from_slice(b"strings\0");
+    for (i, id) in ids.iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        let offset = data_start + i * ROW_SIZE;
+        file[desc..desc + 4].copy_from_slice(&id.to_le_bytes());
+        file[desc + 8..desc + 16].copy_from_slice(&(offset as u64).to_le_bytes());
+        file[offset] = i as u8;
+    }
+    file
+}
+
+fn set_data_offset(file: &mut [u8], index: usize, offset: usize) {
+    let desc = HEADER_SIZE + index * DESC_SIZE;
+    file[desc + 8..desc + 16].copy_from_slice(&(offset as u64).to_le_bytes());
+}
+
+fn trusted() -> TrustedLayout {
+    // SAFETY: Only used on files whose row data is in bounds
+    unsafe { TrustedLayout::new() }
+}
+
+#[test]
+fn order_errors() {
+    let mut file = build(&[10, 30, 20]);
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).err(),
+        Some(FromBytesError::UnsortedRowDescs {
+            index: 2,
+            previous_id: 30,
+            id: 20,
+        })
+    );
+
+    let mut file = build(&[10, 20, 20]);
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).err(),
+        Some(FromBytesError::DuplicateIds { index: 2, id: 20 })
+    );
+}
+
+#[test]
+fn layout_errors() {
+    // Row 1 overlaps the row descriptors
+    let mut file = build(&[10, 20]);
+    set_data_offset(&mut file, 1, HEADER_SIZE + DESC_SIZE);
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).err(),
+        Some(FromBytesError::IntersectingData {
+            first: DataBlock {
+                kind: BlockKind::Header,
+                offset: 0,
+                len: HEADER_SIZE + 2 * DESC_SIZE,
+            },
+            second: DataBlock {
+                kind: BlockKind::Row(1),
+                offset: HEADER_SIZE + DESC_SIZE,
+                // Up to the data of row 0, which directly follows the row descriptors
+                len: DESC_SIZE,
+            },
+        })
+    );
+
+    // Strings start past the end of the file
+    let mut file = build(&[10, 20]);
+    file[0..4].copy_from_slice(&0x1000u32.to_le_bytes());
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).err(),
+        Some(FromBytesError::OutOfBoundsOffset {
+            block: DataBlock {
+                kind: BlockKind::Strings,
+                offset: 0x1000,
+                len: 0,
+            },
+            file_size: 0x80,
+        })
+    );
+}
+
+#[test]
+fn header_only() {
+    let mut file = build(&[10, 30, 20]);
+    let (param, _) =
+        ParamFile::from_bytes_with(&mut file, ValidationLevel::HeaderOnly(trusted())).unwrap();
+    assert_eq!(param.row_descriptors()[2].id, 20);
+
+    let mut file = build(&[10, 30, 20]);
+    assert!(ParamFile::from_bytes_with(&mut file, ValidationLevel::Full).is_err());
+
+    let mut file = build(&[10, 20]);
+    file[0x2C] = 1;
+    assert_eq!(
+        ParamFile::from_bytes_with(&mut file, ValidationLevel::HeaderOnly(trusted())).err(),
+        Some(FromBytesError::UnsupportedFile {
+            is_big_endian: true,
+            is_64bit: true,
+        })
+    );
+    let mut file = build(&[10, 20]);
+    assert_eq!(
+        ParamFile::from_bytes_with(
+            &mut file[..HEADER_SIZE + DESC_SIZE],
+            ValidationLevel::HeaderOnly(trusted())
+        )
+        .err(),
+        Some(FromBytesError::BufferTooSmall)
+    );
+}
+
+#[test]
+fn no_validation() {
+    let mut file = build(&[10, 20, 30]);
+    let (param, warnings) =
+        ParamFile::from_bytes_with(&mut file, ValidationLevel::None(trusted())).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(param.by_id(30).unwrap().data(), &[2, 0, 0, 0]);
+}
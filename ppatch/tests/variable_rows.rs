@@ -0,0 +1,166 @@
+//! Params whose rows have a variable-length tail after the part described by the paramdef.
+
+use ppatch::{
+    fields::FieldBlock,
+    param_file::{BlockKind, DataBlock, FromBytesError, ParamFile},
+    patchers::{base::RowPatcher, linked_list::LinkedListPatcher},
+};
+
+/// Builds a 64-bit little endian param file with the given rows, stored in order.
+fn build(rows: &[(u32, &[u8])]) -> Vec<u8> {
+    const HEADER_SIZE: usize = 0x40;
+    const DESC_SIZE: usize = 24;
+
+    let data_start = HEADER_SIZE + rows.len() * DESC_SIZE;
+    let data_end = data_start + rows.iter().map(|(_, d)| d.len()).sum::<usize>();
+    let mut file = vec![0u8; data_end + 8];
+    file[0..4].copy_from_slice(&(data_end as u32).to_le_bytes());
+    file[0xA..0xC].copy_from_slice(&(rows.len() as u16).to_le_bytes());
+    file[0xC..0x14].copy_from_slice(b"TEST_ST\0");
+    file[0x2D] = 4 | 3;
+    file[0x2E] = 1;
+    file[data_end..].copy_from_slice(b"strings\0");
+
+    let mut offset = data_start;
+    for (i, (id, data)) in rows.iter().enumerate() {
+        let desc = HEADER_SIZE + i * DESC_SIZE;
+        file[desc..desc + 4].copy_from_slice(&id.to_le_bytes());
+        file[desc + 8..desc + 16].copy_from_slice(&(offset as u64).to_le_bytes());
+        file[offset..offset + data.len()].copy_from_slice(data);
+        offset += data.len();
+    }
+    file
+}
+
+/// Swaps the data offsets of the row descriptors at `a` and `b`.
+fn swap_offsets(file: &mut [u8], a: usize, b: usize) {
+    let (a, b) = (0x40 + a * 24 + 8, 0x40 + b * 24 + 8);
+    let tmp: [u8; 8] = file[a..a + 8].try_into().unwrap();
+    file.copy_within(b..b + 8, a);
+    file[b..b + 8].copy_from_slice(&tmp);
+}
+
+const PREFIX: usize = 8;
+const ROWS: [(u32, &[u8]); 3] = [
+    (1, &[1, 0, 0, 0, 2, 0, 0, 0, b'a', b'b']),
+    (5, &[3, 0, 0, 0, 4, 0, 0, 0]),
+    (
+        9,
+        &[5, 0, 0, 0, 6, 0, 0, 0, b'c', b'd', b'e', b'f', b'g', b'h'],
+    ),
+];
+
+#[test]
+fn uniform_rows() {
+    let mut file = build(&[(1, &[1, 2, 3, 4]), (2, &[5, 6, 7, 8])]);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(param.has_uniform_rows());
+    assert_eq!(param.row_size(), 4);
+    assert!(param.rows().all(|r| r.len() == 4));
+}
+
+#[test]
+fn row_sizes() {
+    let mut file = build(&ROWS);
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(!param.has_uniform_rows());
+    assert_eq!(param.row_size(), PREFIX);
+    for (row, (id, data)) in param.rows().zip(ROWS) {
+        assert_eq!(row.id(), id);
+        assert_eq!(row.len(), data.len());
+        assert_eq!(row.data(), data);
+    }
+}
+
+#[test]
+fn row_sizes_unsorted_offsets() {
+    // Store the data of row 5 after the data of row 9, as the file is otherwise unchanged
+    let mut file = build(&[ROWS[0], ROWS[2], ROWS[1]]);
+    let ids = [1u32, 5, 9];
+    for (i, id) in ids.iter().enumerate() {
+        file[0x40 + i * 24..0x40 + i * 24 + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    swap_offsets(&mut file, 1, 2);
+
+    let param = ParamFile::from_bytes(&mut file).unwrap();
+    assert!(!param.has_uniform_rows());
+    for (row, (id, data)) in param.rows().zip(ROWS) {
+        assert_eq!(row.id(), id);
+        assert_eq!(row.data(), data);
+    }
+}
+
+#[test]
+fn shared_row_data() {
+    let mut file = build(&ROWS);
+    file.copy_within(0x40 + 8..0x40 + 16, 0x40 + 24 + 8);
+    assert_eq!(
+        ParamFile::from_bytes(&mut file).err(),
+        Some(FromBytesError::IntersectingData {
+            first: DataBlock {
+                kind: BlockKind::Row(0),
+                offset: 0x88,
+                len: 18,
+            },
+            second: DataBlock {
+                kind: BlockKind::Row(1),
+                offset: 0x88,
+                len: 18,
+            },
+        })
+    );
+}
+
+#[test]
+fn tail_access() {
+    let mut file = build(&ROWS);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+
+    let row = param.by_id(9).unwrap();
+    let (prefix, tail) = row.split_tail(PREFIX).unwrap();
+    assert_eq!(prefix.id(), 9);
+    assert_eq!(prefix.data(), &ROWS[2].1[..PREFIX]);
+    assert_eq!(tail, b"cdefgh");
+    assert!(row.split_tail(row.len() + 1).is_none());
+
+    let mut row = param.by_id_mut(1).unwrap();
+    let (_, tail) = row.split_tail_mut(PREFIX).unwrap();
+    tail.copy_from_slice(b"xy");
+    assert_eq!(
+        param.by_id(1).unwrap().data(),
+        &[1, 0, 0, 0, 2, 0, 0, 0, b'x', b'y']
+    );
+}
+
+#[test]
+fn prefix_patching() {
+    let blocks = [
+        FieldBlock {
+            field_start: 0,
+            offset: 0,
+            mask: u32::MAX,
+        },
+        FieldBlock {
+            field_start: 1,
+            offset: 1,
+            mask: u32::MAX,
+        },
+    ];
+    let mut patcher = LinkedListPatcher::<u32>::try_new(&blocks, PREFIX).unwrap();
+
+    let mut file = build(&ROWS);
+    let mut param = ParamFile::from_bytes(&mut file).unwrap();
+    let mut row = param.by_id_mut(9).unwrap();
+    let (mut prefix, tail) = row.split_tail_mut(PREFIX).unwrap();
+    let live = prefix.as_blocks_mut::<u32>().unwrap();
+
+    let before = live.to_vec();
+    live[1].write(0x1234);
+    tail.fill(0);
+    let id = patcher.create_patch(&before, live).unwrap();
+    patcher.restore_patch(id, live).unwrap();
+
+    // The patch only covers the prefix, so the tail edit is kept
+    let expected = [5, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(param.by_id(9).unwrap().data(), &expected);
+}
@@ -0,0 +1,162 @@
+//! Patching rows in blocks of 32 and 64 bits.
+
+use std::fmt::Debug;
+
+use num_traits::PrimInt;
+use paramdex::Paramdex;
+use ppatch::{
+    field_metadata::{
+        build_blocks, load_fb_repo, load_fb_repo64, serialize_fb_repo, FieldBlock, FieldBlockRepo,
+        FieldBlockRepo64, LoadFbRepoError,
+    },
+    fields::{read_field_bytes, write_field_bytes},
+    patchers::{
+        base::RowPatcher, full_copy::FullCopyPatcher, linked_list::LinkedListPatcher,
+        single_patch::SinglePatchPatcher,
+    },
+    util::unaligned::Unaligned,
+};
+
+const ROW_SIZE: usize = 16;
+/// Number of fields in [`DEF`].
+const N_FIELDS: usize = 8;
+/// Def with mixed 1, 2 and 4 byte fields, naturally aligned so that none of them spans two blocks.
+const DEF: &str = r#"<PARAMDEF XmlVersion="3">
+  <ParamType>WIDE_BLOCKS_TEST_PARAM_ST</ParamType>
+  <DataVersion>1</DataVersion>
+  <BigEndian>False</BigEndian>
+  <Unicode>True</Unicode>
+  <FormatVersion>203</FormatVersion>
+  <Fields>
+    <Field Def="u8 a" />
+    <Field Def="u8 b" />
+    <Field Def="u16 c" />
+    <Field Def="u32 d" />
+    <Field Def="u16 e" />
+    <Field Def="u8 f" />
+    <Field Def="u8 g" />
+    <Field Def="u32 h" />
+  </Fields>
+</PARAMDEF>"#;
+
+/// Builds the field blocks of [`DEF`] in blocks of type `N`, as the build script does.
+fn field_blocks<N: PrimInt>() -> Vec<FieldBlock<N>> {
+    let mut paramdex = Paramdex::from_sources([("WideBlocksTestParam", DEF)]).unwrap();
+    paramdex.compute_def_layouts(u64::MAX);
+    let def = &paramdex.def_with_meta("WideBlocksTestParam").unwrap().def;
+    let blocks = build_blocks(def);
+
+    // One block per field, so field indices are also their `field_start`
+    assert_eq!(blocks.len(), N_FIELDS);
+    assert!((blocks.iter().enumerate()).all(|(i, fb)| fb.field_start as usize == i));
+    blocks
+}
+
+fn original_row<N: PrimInt>() -> Vec<Unaligned<N>> {
+    vec![Unaligned(N::zero()); ROW_SIZE / std::mem::size_of::<N>()]
+}
+
+/// Writes `value` to each field of `fields` in `row`.
+fn write_fields<N: PrimInt>(blocks: &[FieldBlock<N>], row: &mut [Unaligned<N>], fields: &[usize]) {
+    for &field in fields {
+        let value = [0xA0 | field as u8; 4];
+        write_field_bytes(row, blocks, field as u16, &value);
+    }
+}
+
+/// Values of all fields of `row`.
+fn read_fields<N: PrimInt>(blocks: &[FieldBlock<N>], row: &[Unaligned<N>]) -> Vec<Vec<u8>> {
+    (0..N_FIELDS)
+        .map(|field| {
+            let mut value = vec![0; 4];
+            let len = read_field_bytes(row, blocks, field as u16, &mut value);
+            value.truncate(len);
+            value
+        })
+        .collect()
+}
+
+/// Creates three overlapping patches, then restores them out of order.
+fn roundtrip<'a, N: PrimInt + Debug, P: RowPatcher<'a, N>>(blocks: &'a [FieldBlock<N>]) {
+    let mut patcher = P::try_new(blocks, ROW_SIZE).unwrap();
+    let mut live = original_row::<N>();
+    let mut ids = Vec::new();
+    for fields in [&[0, 3][..], &[3, 7], &[2, 5]] {
+        let before = live.clone();
+        write_fields(blocks, &mut live, fields);
+        ids.push(patcher.create_patch(&before, &live).unwrap());
+    }
+
+    let mut expected = original_row::<N>();
+    write_fields(blocks, &mut expected, &[0, 3, 7, 2, 5]);
+    assert_eq!(read_fields(blocks, &live), read_fields(blocks, &expected));
+
+    patcher.restore_patch(ids[1], &mut live).unwrap();
+    let mut expected = original_row::<N>();
+    write_fields(blocks, &mut expected, &[0, 3, 2, 5]);
+    assert_eq!(read_fields(blocks, &live), read_fields(blocks, &expected));
+
+    patcher.restore_patch(ids[0], &mut live).unwrap();
+    patcher.restore_patch(ids[2], &mut live).unwrap();
+    assert_eq!(live, original_row::<N>());
+}
+
+/// Creates and restores a single patch of all fields.
+fn single_roundtrip<'a, N: PrimInt + Debug, P: RowPatcher<'a, N>>(blocks: &'a [FieldBlock<N>]) {
+    let mut patcher = P::try_new(blocks, ROW_SIZE).unwrap();
+    let mut live = original_row::<N>();
+    write_fields(blocks, &mut live, &[0, 1, 2, 3, 4, 5, 6, 7]);
+    let id = patcher.create_patch(&original_row::<N>(), &live).unwrap();
+    assert_eq!(live.iter().filter(|b| b.read() == N::zero()).count(), 0);
+    patcher.restore_patch(id, &mut live).unwrap();
+    assert_eq!(live, original_row::<N>());
+}
+
+#[test]
+fn patchers_at_both_widths() {
+    let blocks32 = field_blocks::<u32>();
+    let blocks64 = field_blocks::<u64>();
+    let offsets32: Vec<_> = blocks32.iter().map(|fb| fb.offset).collect();
+    let offsets64: Vec<_> = blocks64.iter().map(|fb| fb.offset).collect();
+    assert_eq!(offsets32, [0, 0, 0, 1, 2, 2, 2, 3]);
+    assert_eq!(offsets64, [0, 0, 0, 0, 1, 1, 1, 1]);
+    assert_eq!(blocks32[2].mask, 0xFFFF_0000);
+    assert_eq!(blocks64[5].mask, 0x0000_0000_00FF_0000);
+
+    roundtrip::<u32, LinkedListPatcher<u32>>(&blocks32);
+    roundtrip::<u64, LinkedListPatcher<u64>>(&blocks64);
+    roundtrip::<u32, FullCopyPatcher<u32>>(&blocks32);
+    roundtrip::<u64, FullCopyPatcher<u64>>(&blocks64);
+    single_roundtrip::<u32, SinglePatchPatcher<u32>>(&blocks32);
+    single_roundtrip::<u64, SinglePatchPatcher<u64>>(&blocks64);
+}
+
+#[test]
+fn repos_record_block_size() {
+    let mut repo64 = FieldBlockRepo64::new();
+    repo64.insert("TEST_PARAM_ST".to_owned(), field_blocks());
+    let bytes = serialize_fb_repo(&repo64);
+    let loaded = unsafe { load_fb_repo64(&bytes) }.unwrap();
+    let blocks = loaded.get("TEST_PARAM_ST").unwrap();
+    assert_eq!(blocks.len(), N_FIELDS);
+    assert_eq!(blocks[7].mask, 0xFFFF_FFFF_0000_0000);
+    assert_eq!(
+        unsafe { load_fb_repo(&bytes) }.err(),
+        Some(LoadFbRepoError::BlockSizeMismatch {
+            expected: 4,
+            actual: 8
+        })
+    );
+
+    let mut repo32 = FieldBlockRepo::new();
+    repo32.insert("TEST_PARAM_ST".to_owned(), field_blocks());
+    let bytes = serialize_fb_repo(&repo32);
+    assert!(unsafe { load_fb_repo(&bytes) }.is_ok());
+    assert_eq!(
+        unsafe { load_fb_repo64(&bytes) }.err(),
+        Some(LoadFbRepoError::BlockSizeMismatch {
+            expected: 8,
+            actual: 4
+        })
+    );
+}